@@ -22,6 +22,9 @@ pub struct ActiveTerminal {
     /// A Tokio broadcast sender to send protocol messages that control the shadow terminal and
     /// PTY. For example; resizing and shutting down.
     pub control_tx: tokio::sync::broadcast::Sender<crate::Protocol>,
+    /// A Tokio broadcast sender of semantic events, like the terminal's title changing. Use
+    /// [`Self::subscribe()`] to receive them.
+    pub events_tx: tokio::sync::broadcast::Sender<crate::events::Event>,
 }
 
 impl ActiveTerminal {
@@ -36,6 +39,7 @@ impl ActiveTerminal {
         let mut shadow_terminal =
             crate::shadow_terminal::ShadowTerminal::new(config, surface_output_tx);
         let control_tx = shadow_terminal.channels.control_tx.clone();
+        let events_tx = shadow_terminal.channels.events_tx.clone();
 
         let current_span = tracing::Span::current();
         let task_handle = tokio::spawn(async move {
@@ -51,9 +55,19 @@ impl ActiveTerminal {
             surface_output_rx,
             pty_input_tx,
             control_tx,
+            events_tx,
         }
     }
 
+    /// Subscribe to the terminal's semantic events, such as resizes, the bell, title changes,
+    /// switching to and from the alternate screen and the PTY's process exiting. Unlike the surface
+    /// output, any number of subscribers can listen to these.
+    #[inline]
+    #[must_use]
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<crate::events::Event> {
+        self.events_tx.subscribe()
+    }
+
     /// Send input directly into the underlying PTY process. This doesn't go through the shadow
     /// terminal's "frontend".
     ///
@@ -126,6 +140,45 @@ impl ActiveTerminal {
         self.control_tx
             .send(crate::Protocol::Scroll(crate::Scroll::Cancel))
     }
+
+    /// Scroll so that the given number of lines of the scrollback are below the bottom of the
+    /// terminal.
+    ///
+    /// # Errors
+    /// If sending message over channel fails.
+    #[inline]
+    pub fn scroll_to(
+        &self,
+        position: usize,
+    ) -> Result<usize, tokio::sync::broadcast::error::SendError<crate::Protocol>> {
+        self.control_tx
+            .send(crate::Protocol::Scroll(crate::Scroll::To(position)))
+    }
+
+    /// Kill the PTY's current process and replace it with another command, eg to switch to a
+    /// rescue shell. The shadow terminal and its scrollback are kept.
+    ///
+    /// # Errors
+    /// If sending message over channel fails.
+    #[inline]
+    pub fn respawn(
+        &self,
+        command: Vec<std::ffi::OsString>,
+    ) -> Result<usize, tokio::sync::broadcast::error::SendError<crate::Protocol>> {
+        self.control_tx.send(crate::Protocol::Respawn(command))
+    }
+
+    /// Add, remove or jump to bookmarks in the scrollback.
+    ///
+    /// # Errors
+    /// If sending message over channel fails.
+    #[inline]
+    pub fn bookmark(
+        &self,
+        bookmark: crate::Bookmark,
+    ) -> Result<usize, tokio::sync::broadcast::error::SendError<crate::Protocol>> {
+        self.control_tx.send(crate::Protocol::Bookmark(bookmark))
+    }
 }
 
 impl Drop for ActiveTerminal {
@@ -0,0 +1,25 @@
+//! Semantic events from the shadow terminal. Whereas [`crate::output::Output`] is mostly about the
+//! terminal's contents, these describe what happened to the terminal, so that embedders can react
+//! to them without having to parse surfaces.
+
+/// Something that happened to the shadow terminal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The terminal was resized.
+    Resized {
+        /// The new width of the terminal
+        width: u16,
+        /// The new height of the terminal
+        height: u16,
+    },
+    /// The terminal rang its bell, eg the PTY output the `BEL` character.
+    Bell,
+    /// An application set the terminal's title, eg with the OSC 2 code.
+    Title(String),
+    /// The terminal switched between the primary and alternate screens. `true` when entering the
+    /// alternate screen.
+    AlternateScreen(bool),
+    /// The PTY's process exited, with its exit code.
+    Exited(u32),
+}
@@ -22,8 +22,10 @@ pub use wezterm_term;
 
 pub mod active_terminal;
 pub mod errors;
+pub mod events;
 pub mod output;
 mod pty;
+pub mod screen_diff;
 pub mod shadow_terminal;
 pub mod steppable_terminal;
 
@@ -47,6 +49,14 @@ pub enum Protocol {
     },
     /// Scrolling of the terminal scrollback
     Scroll(Scroll),
+    /// Bookmarking lines of the terminal scrollback
+    Bookmark(Bookmark),
+    /// The PTY's process exited with the given exit code. It's always sent just before the PTY
+    /// sends `End`.
+    Exited(u32),
+    /// Kill the PTY's current process and replace it with the given command. The shadow terminal,
+    /// and so its scrollback, is kept.
+    Respawn(Vec<std::ffi::OsString>),
 }
 
 /// The various states of scrolling
@@ -59,4 +69,24 @@ pub enum Scroll {
     Down,
     /// Exit the scroll, returning the terminal to how it was before scrolling started.
     Cancel,
+    /// Scroll so that the given number of lines of the scrollback are below the bottom of the
+    /// terminal. `0` is the same as exiting the scroll.
+    To(usize),
+}
+
+/// The actions for bookmarks of lines in the scrollback. Bookmarks follow their lines as the
+/// scrollback grows and as the terminal is resized.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Bookmark {
+    /// Bookmark the line at the top of the current scroll, or the cursor's line when not
+    /// scrolling.
+    Add,
+    /// Scroll to the nearest bookmark above the current scroll.
+    Previous,
+    /// Scroll to the nearest bookmark below the current scroll, or exit scrolling if there isn't
+    /// one.
+    Next,
+    /// Remove all the bookmarks.
+    Clear,
 }
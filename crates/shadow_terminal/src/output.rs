@@ -172,6 +172,45 @@ pub enum Output {
     /// In certain cases, it's likely more efficient to just send all the cell data for the
     /// terminal. Or perhaps it's useful in moments of recovery or reset.
     Complete(CompleteSurface),
+    /// The terminal rang its bell, eg the PTY output the `BEL` character.
+    Bell,
+    /// The shell reported its current working directory, using the OSC 7 code.
+    WorkingDirectory(std::path::PathBuf),
+    /// The shell reported that a command started running, using the OSC 133 code.
+    CommandStarted,
+    /// The shell reported that a command finished running, using the OSC 133 code. With the
+    /// command's exit code, if the shell reported it.
+    CommandFinished(Option<i32>),
+    /// The scrollback's bookmarks changed, or one of them was jumped to. They're ordered from the
+    /// top of the scrollback.
+    Bookmarks(Vec<Bookmark>),
+    /// The PTY's process exited, with its exit code.
+    Exited(u32),
+    /// The terminal raised a notification, using the OSC 9 or OSC 777 codes.
+    Notification(Notification),
+    /// An application changed the cursor's colour, using the OSC 12 code. `None` when it was
+    /// reset to the terminal's default, eg with the OSC 112 code.
+    CursorColour(Option<termwiz::color::SrgbaTuple>),
+}
+
+/// A notification raised by a program running in the terminal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Notification {
+    /// The title of the notification.
+    pub title: String,
+    /// The optional body of the notification.
+    pub body: Option<String>,
+}
+
+/// A bookmarked line of the scrollback.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Bookmark {
+    /// The text of the bookmarked line.
+    pub text: String,
+    /// How many lines the bookmark is above the bottom of the scrollback.
+    pub lines_up: usize,
 }
 
 /// The kinds of surfaces that can be output.
@@ -356,6 +395,8 @@ impl crate::shadow_terminal::ShadowTerminal {
         }
 
         self.cursor_state(&mut changes)?;
+        // So that consumers of the surface can know what the running program called itself.
+        changes.push(TermwizChange::Title(self.terminal.get_title().to_owned()));
 
         Ok(changes)
     }
@@ -14,6 +14,40 @@ pub type BytesFromPTY = [u8; 4096];
 /// A single payload from the user's input stream (or sometimes internal input).
 pub type BytesFromSTDIN = [u8; 128];
 
+/// The longest time to wait for a replaced command to exit before spawning its replacement.
+const REPLACED_COMMAND_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+
+/// A command running in the PTY.
+struct Child {
+    /// Sends the command OS kill signals.
+    killer: Box<dyn portable_pty::ChildKiller + Send + Sync>,
+    /// Set when the command is replaced by another one, so that it exiting doesn't end the PTY.
+    is_replaced: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Resolves once the command has exited.
+    exited: tokio::sync::oneshot::Receiver<()>,
+}
+
+impl Child {
+    /// Kill the command, if it's still running.
+    fn kill(&mut self) {
+        let result = self.killer.kill();
+        if let Err(error) = result {
+            // This is the error when the PTY naturally ends. Is there a better way to match?
+            let pty_exit = "No such process";
+            if error.to_string().contains(pty_exit) {
+                tracing::debug!("Tried killing PTY command that was already gone.");
+                return;
+            }
+
+            tracing::error!("Couldn't kill PTY command: {error:?}");
+            // TODO: maybe we want to force exit here?
+            return;
+        }
+
+        tracing::debug!("`kill()` (which includes OS kill signals) sent to PTY spawn process");
+    }
+}
+
 /// This is the PTY process that replaces the user's current TTY
 #[non_exhaustive]
 pub struct PTY {
@@ -38,22 +72,41 @@ impl PTY {
             .openpty(Self::pty_size(self.width, self.height))
             .with_whatever_context(|_| "Error opening PTY")?;
 
-        tracing::debug!("Launching `{:?}` on PTY", self.command);
-        let mut cmd = portable_pty::CommandBuilder::from_argv(self.command.clone());
+        tracing::trace!("Returning PTY pair");
+        Ok(pair)
+    }
+
+    /// Spawn a command on the PTY. The returned flag is for marking the command as replaced by
+    /// another one, so that it exiting doesn't end the PTY.
+    fn spawn_command(
+        control_tx: &tokio::sync::broadcast::Sender<crate::Protocol>,
+        slave: &(dyn portable_pty::SlavePty + Send),
+        command: Vec<OsString>,
+    ) -> Result<Child, crate::errors::PTYError> {
+        tracing::debug!("Launching `{command:?}` on PTY");
+        let mut cmd = portable_pty::CommandBuilder::from_argv(command);
         cmd.cwd(
             std::env::current_dir()
                 .with_whatever_context(|_| "Couldn't get user's current directory")?,
         );
-        let spawn = pair
-            .slave
+        let spawn = slave
             .spawn_command(cmd)
             .with_whatever_context(|_| "Error spawning PTY command")?;
         let killer = spawn.clone_killer();
-        Self::wait_for_pty_end(self.control_tx.clone(), spawn);
-        Self::kill_on_protocol_end(self.control_tx.subscribe(), killer);
+        let is_replaced = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (exited_tx, exited) = tokio::sync::oneshot::channel();
+        Self::wait_for_pty_end(
+            control_tx.clone(),
+            spawn,
+            std::sync::Arc::clone(&is_replaced),
+            exited_tx,
+        );
 
-        tracing::trace!("Returning PTY pair");
-        Ok(pair)
+        Ok(Child {
+            killer,
+            is_replaced,
+            exited,
+        })
     }
 
     /// The PTY crate is not async, so here we're basically just listening to the PTY to be able to
@@ -99,12 +152,29 @@ impl PTY {
     fn wait_for_pty_end(
         protocol_out: tokio::sync::broadcast::Sender<crate::Protocol>,
         mut spawn: Box<dyn portable_pty::Child + Send + Sync>,
+        is_replaced: std::sync::Arc<std::sync::atomic::AtomicBool>,
+        exited: tokio::sync::oneshot::Sender<()>,
     ) {
         tokio::task::spawn_blocking(move || {
             tracing::debug!("Starting to wait for PTY end");
-            let waiter_result = spawn.wait();
-            if let Err(error) = waiter_result {
-                tracing::error!("Waiting for PTY: {error:?}");
+            let result = spawn.wait();
+            if exited.send(()).is_err() {
+                tracing::trace!("Nothing was waiting for the PTY command to exit");
+            }
+            if is_replaced.load(std::sync::atomic::Ordering::Relaxed) {
+                tracing::debug!("Replaced PTY command exited: {result:?}");
+                return;
+            }
+
+            match result {
+                Ok(status) => {
+                    let exit_result =
+                        protocol_out.send(crate::Protocol::Exited(status.exit_code()));
+                    if let Err(error) = exit_result {
+                        tracing::error!("Sending `Protocol::Exited`: {error:?}");
+                    }
+                }
+                Err(error) => tracing::error!("Waiting for PTY: {error:?}"),
             }
 
             // A crude hack to make sure that early-exiting commands still have a chance to
@@ -119,51 +189,83 @@ impl PTY {
         });
     }
 
-    /// Listen for the `End` message from the Tattoy protocol channel and then kill the PTY.
-    fn kill_on_protocol_end(
+    /// Listen to the protocol channel to kill the PTY's command when everything ends, or to replace
+    /// it with another command. The PTY's slave side is kept open, for spawning replacement
+    /// commands, until the end.
+    fn manage_command(
+        control_tx: tokio::sync::broadcast::Sender<crate::Protocol>,
         mut protocol_in: tokio::sync::broadcast::Receiver<crate::Protocol>,
-        mut spawn: Box<dyn portable_pty::ChildKiller + Send + Sync>,
+        slave: Box<dyn portable_pty::SlavePty + Send>,
+        mut child: Child,
     ) {
         let current_span = tracing::Span::current();
         tokio::spawn(
             async move {
                 tracing::debug!("Starting loop for PTY spawn to receive protocol messages");
                 loop {
+                    #[expect(
+                        clippy::wildcard_enum_match_arm,
+                        reason = "It's our internal protocol"
+                    )]
                     match protocol_in.recv().await {
-                        Ok(message) => {
-                            if matches!(message, crate::Protocol::End)  {
-                                tracing::debug!("PTY received Tattoy message {message:?}");
-                                let result = spawn.kill();
-                                if let Err(error) = result {
-                                    // This is the error when the PTY naturally ends. Is there a better way to
-                                    // match?
-                                    let pty_exit = "No such process";
-                                    if error.to_string().contains(pty_exit) {
-                                        tracing::debug!("Tried killing PTY that was already gone.");
-                                        break;
+                        Ok(crate::Protocol::End) => {
+                            tracing::debug!("PTY received protocol `End` message");
+                            child.kill();
+                            break;
+                        }
+                        Ok(crate::Protocol::Respawn(command)) => {
+                            match Self::replace_command(&control_tx, slave.as_ref(), child, command)
+                                .await
+                            {
+                                Ok(replacement) => child = replacement,
+                                Err(error) => {
+                                    tracing::error!("Couldn't replace PTY command: {error:?}");
+                                    // There's nothing left running in the PTY.
+                                    if let Err(send_error) = control_tx.send(crate::Protocol::End) {
+                                        tracing::error!("Sending `Protocol::End`: {send_error:?}");
                                     }
-
-                                    tracing::error!("Couldn't kill PTY: {error:?}");
-                                    // TODO: maybe we want to force exit here?
+                                    break;
                                 }
-
-                                tracing::debug!(
-                                    "`kill()` (which includes OS kill signals) sent to PTY spawn process"
-                                );
-                                break;
                             }
                         }
+                        Ok(_) => (),
                         Err(error) => {
                             tracing::error!("Reading protocol from PTY loop: {error:?}");
                         }
                     }
                 }
+
+                // We have to drop the slave so that we don't hang on it when we exit.
+                drop(slave);
                 tracing::debug!("Leaving spawn shutdown listener loop.");
             }
             .instrument(current_span),
         );
     }
 
+    /// Kill the PTY's current command and spawn another one in its place. The current command has
+    /// to have exited first, because a PTY can only be the controlling terminal of one session.
+    async fn replace_command(
+        control_tx: &tokio::sync::broadcast::Sender<crate::Protocol>,
+        slave: &(dyn portable_pty::SlavePty + Send),
+        mut child: Child,
+        command: Vec<OsString>,
+    ) -> Result<Child, crate::errors::PTYError> {
+        tracing::debug!("Replacing PTY command with: {command:?}");
+        child
+            .is_replaced
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        child.kill();
+        if tokio::time::timeout(REPLACED_COMMAND_TIMEOUT, child.exited)
+            .await
+            .is_err()
+        {
+            tracing::warn!("Replaced PTY command didn't exit in time");
+        }
+
+        Self::spawn_command(control_tx, slave, command)
+    }
+
     /// Start the PTY
     pub async fn run(
         self,
@@ -177,7 +279,13 @@ impl PTY {
         // miss any of those messages later when we finally start the listening loop.
         let mut protocol_for_main_loop = self.control_tx.subscribe();
 
+        let protocol_for_command = self.control_tx.subscribe();
         let pty_pair = self.setup_pty()?;
+        let child = Self::spawn_command(
+            &self.control_tx,
+            pty_pair.slave.as_ref(),
+            self.command.clone(),
+        )?;
         let pty_writer = pty_pair
             .master
             .take_writer()
@@ -188,9 +296,12 @@ impl PTY {
             .with_whatever_context(|err| format!("Getting PTY reader: {err:?}"))?;
 
         Self::pty_reader_loop(pty_reader, pty_reader_tx);
-
-        // We have to drop the slave so that we don't hang on it when we exit.
-        drop(pty_pair.slave);
+        Self::manage_command(
+            self.control_tx.clone(),
+            protocol_for_command,
+            pty_pair.slave,
+            child,
+        );
 
         // TODO: should we be handling any errors in here?
         let protocol_for_input_loop = self.control_tx.subscribe();
@@ -0,0 +1,193 @@
+//! Structured differences between two captures of a terminal's screen. Useful for asserting that
+//! something only changed the part of the screen that it was meant to, eg that a compositing
+//! effect didn't disturb any of the terminal's content outside of its own region.
+
+/// A capture of every cell on the screen at a certain moment.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Screen {
+    /// The screen's cells, indexed by row and then by column.
+    pub cells: Vec<Vec<wezterm_term::Cell>>,
+}
+
+/// A rectangular region of the screen, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Rect {
+    /// The column of the left edge.
+    pub x: usize,
+    /// The row of the top edge.
+    pub y: usize,
+    /// The number of columns.
+    pub width: usize,
+    /// The number of rows.
+    pub height: usize,
+}
+
+/// A single cell that's different between two captures of the screen.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Change {
+    /// The column of the cell.
+    pub x: usize,
+    /// The row of the cell.
+    pub y: usize,
+    /// The cell in the earlier capture, if the screen was big enough to have it.
+    pub before: Option<wezterm_term::Cell>,
+    /// The cell in the later capture, if the screen was big enough to have it.
+    pub after: Option<wezterm_term::Cell>,
+}
+
+impl Rect {
+    /// Instantiate
+    #[inline]
+    #[must_use]
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the rectangle covers the given cell.
+    #[inline]
+    #[must_use]
+    pub const fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+impl Change {
+    /// A short, readable description of the change, for assertion messages.
+    #[inline]
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let text = |cell: Option<&wezterm_term::Cell>| {
+            cell.map_or_else(|| "nothing".to_owned(), |cell| format!("'{}'", cell.str()))
+        };
+        let before = text(self.before.as_ref());
+        let after = text(self.after.as_ref());
+        if before == after {
+            format!("{}x{}: {before}'s attributes changed", self.x, self.y)
+        } else {
+            format!("{}x{}: {before} became {after}", self.x, self.y)
+        }
+    }
+}
+
+impl Screen {
+    /// Instantiate from rows of cells.
+    #[inline]
+    #[must_use]
+    pub const fn new(cells: Vec<Vec<wezterm_term::Cell>>) -> Self {
+        Self { cells }
+    }
+
+    /// Every cell that's different in the later capture of the screen. Differences in both the
+    /// text and the attributes, such as colours, of a cell count.
+    #[inline]
+    #[must_use]
+    pub fn diff(&self, later: &Self) -> Vec<Change> {
+        let rows = self.cells.len().max(later.cells.len());
+        let mut changes = Vec::new();
+        for y in 0..rows {
+            let before_row = self.cells.get(y);
+            let after_row = later.cells.get(y);
+            let columns = before_row
+                .map_or(0, Vec::len)
+                .max(after_row.map_or(0, Vec::len));
+            for x in 0..columns {
+                let before = before_row.and_then(|row| row.get(x));
+                let after = after_row.and_then(|row| row.get(x));
+                if before != after {
+                    changes.push(Change {
+                        x,
+                        y,
+                        before: before.cloned(),
+                        after: after.cloned(),
+                    });
+                }
+            }
+        }
+        changes
+    }
+
+    /// The changes in the later capture of the screen that are outside of the given region.
+    #[inline]
+    #[must_use]
+    pub fn changes_outside(&self, later: &Self, region: Rect) -> Vec<Change> {
+        self.diff(later)
+            .into_iter()
+            .filter(|change| !region.contains(change.x, change.y))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen(rows: &[&str]) -> Screen {
+        Screen::new(
+            rows.iter()
+                .map(|row| {
+                    row.chars()
+                        .map(|character| {
+                            wezterm_term::Cell::new(
+                                character,
+                                termwiz::cell::CellAttributes::default(),
+                            )
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn diffing_text() {
+        let before = screen(&["abc", "def"]);
+        let after = screen(&["abc", "dXf"]);
+        let changes = before.diff(&after);
+        assert_eq!(changes.len(), 1);
+        let change = changes.first().unwrap();
+        assert_eq!((change.x, change.y), (1, 1));
+        assert_eq!(change.describe(), "1x1: 'e' became 'X'");
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[test]
+    fn diffing_attributes() {
+        let before = screen(&["abc"]);
+        let mut after = before.clone();
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_background(termwiz::color::ColorAttribute::PaletteIndex(1));
+        *after.cells.first_mut().unwrap().last_mut().unwrap() =
+            wezterm_term::Cell::new('c', attributes);
+
+        let changes = before.diff(&after);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes.first().unwrap().describe(),
+            "2x0: 'c''s attributes changed"
+        );
+    }
+
+    #[test]
+    fn changes_outside_of_a_region() {
+        let before = screen(&["abc", "def", "ghi"]);
+        let after = screen(&["aXc", "dYf", "ghZ"]);
+        let region = Rect::new(1, 0, 1, 2);
+        assert!(region.contains(1, 1));
+        assert!(!region.contains(2, 2));
+
+        let outside = before.changes_outside(&after, region);
+        assert_eq!(outside.len(), 1);
+        assert_eq!(outside.first().unwrap().describe(), "2x2: 'i' became 'Z'");
+        assert!(before
+            .changes_outside(&after, Rect::new(0, 0, 3, 3))
+            .is_empty());
+    }
+}
@@ -21,6 +21,67 @@ impl wezterm_term::TerminalConfiguration for WeztermConfig {
     }
 }
 
+/// Listens for alerts from the Wezterm terminal, like the bell.
+struct AlertListener {
+    /// Set whenever the terminal rings its bell, until the bell is broadcast.
+    is_bell_rung: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Set whenever the shell reports a new working directory, until it is broadcast.
+    is_working_directory_changed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Notifications raised by the terminal, until they are broadcast.
+    notifications: std::sync::Arc<std::sync::Mutex<Vec<crate::output::Notification>>>,
+    /// The title most recently set by an application, until it is broadcast.
+    title: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    /// Set whenever an application changes the palette, eg the cursor colour, until it is
+    /// broadcast.
+    is_palette_changed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl wezterm_term::AlertHandler for AlertListener {
+    fn alert(&mut self, alert: wezterm_term::Alert) {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few alerts"
+        )]
+        match alert {
+            wezterm_term::Alert::Bell => {
+                self.is_bell_rung
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            wezterm_term::Alert::CurrentWorkingDirectoryChanged => {
+                self.is_working_directory_changed
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            // Raised by the OSC 4, 10, 11 and 12 codes, and their resets.
+            wezterm_term::Alert::PaletteChanged => {
+                self.is_palette_changed
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            // Raised by the OSC 9 and OSC 777 codes.
+            wezterm_term::Alert::ToastNotification { title, body, .. } => {
+                let notification = match title {
+                    Some(title) => crate::output::Notification {
+                        title,
+                        body: Some(body),
+                    },
+                    None => crate::output::Notification {
+                        title: body,
+                        body: None,
+                    },
+                };
+                match self.notifications.lock() {
+                    Ok(mut notifications) => notifications.push(notification),
+                    Err(error) => tracing::error!("Couldn't store notification: {error:?}"),
+                }
+            }
+            wezterm_term::Alert::WindowTitleChanged(title) => match self.title.lock() {
+                Ok(mut latest) => *latest = Some(title),
+                Err(error) => tracing::error!("Couldn't store title: {error:?}"),
+            },
+            _ => (),
+        }
+    }
+}
+
 /// Config for creating a shadow terminal.
 #[expect(
     clippy::exhaustive_structs,
@@ -71,6 +132,8 @@ pub struct Channels {
     pub internal_input_tx: Option<tokio::sync::mpsc::Sender<crate::pty::BytesFromSTDIN>>,
     /// Sends complete snapshots of the current screen state.
     shadow_output: tokio::sync::mpsc::Sender<crate::output::Output>,
+    /// Broadcasts semantic events, like the terminal being resized, to any subscribers.
+    pub events_tx: tokio::sync::broadcast::Sender<crate::events::Event>,
 }
 
 /// Keep track of the metadata for the last sent output.
@@ -91,9 +154,85 @@ const APPLICATION_MODE_START: &str = "\x1b[?1h";
 /// Disable the user's terminal's 'application mode'.
 const APPLICATION_MODE_END: &str = "\x1b[?1l";
 
+/// The OSC 133 "semantic prompt" code that shells with integration enabled send when a command
+/// starts running.
+const COMMAND_STARTED: &str = "\x1b]133;C";
+
+/// The OSC 133 "semantic prompt" code that shells with integration enabled send when a command has
+/// finished running.
+const COMMAND_FINISHED: &str = "\x1b]133;D";
+
 /// The time to wait for more output from the PTY. In microseconds (1000s of a millisecond).
 const TIME_TO_WAIT_FOR_MORE_PTY_OUTPUT: u64 = 1000;
 
+/// The "Begin Synchronized Update" code, DEC private mode 2026. Applications send it before a big
+/// update to ask the terminal not to render anything until the update is finished.
+const SYNCHRONIZED_UPDATE_START: &str = "\x1b[?2026h";
+
+/// The "End Synchronized Update" code, after which the terminal can render the whole update.
+const SYNCHRONIZED_UPDATE_END: &str = "\x1b[?2026l";
+
+/// The longest time to hold back rendering whilst waiting for the end of a synchronized update, in
+/// case the application never sends it.
+const SYNCHRONIZED_UPDATE_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_millis(150);
+
+/// The ANSI codes in a chunk of PTY output that the shadow terminal needs to react to, beyond just
+/// passing them on to the Wezterm terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "Each code is found independently of the others"
+)]
+pub struct SpecialCodes {
+    /// The user's terminal should enter 'application mode'.
+    pub is_application_mode_start: bool,
+    /// The user's terminal should leave 'application mode'.
+    pub is_application_mode_end: bool,
+    /// An application wants to know where the cursor is.
+    pub is_cursor_position_request: bool,
+    /// The shell has started running a command.
+    pub is_command_started: bool,
+    /// The shell has finished running a command.
+    pub is_command_finished: bool,
+    /// The exit code of the most recently finished command, if the shell reported it.
+    pub command_exit_code: Option<i32>,
+}
+
+impl SpecialCodes {
+    /// Scan raw PTY output for the special codes. The output can be any bytes at all, so this must
+    /// never panic.
+    #[inline]
+    #[must_use]
+    pub fn scan(bytes: &[u8]) -> Self {
+        let contains =
+            |code: &str| ShadowTerminal::find_subsequence(bytes, code.as_bytes()).is_some();
+        Self {
+            is_application_mode_start: contains(APPLICATION_MODE_START),
+            is_application_mode_end: contains(APPLICATION_MODE_END),
+            is_cursor_position_request: contains(CURSOR_POSITION_REQUEST),
+            is_command_started: contains(COMMAND_STARTED),
+            is_command_finished: contains(COMMAND_FINISHED),
+            command_exit_code: Self::command_exit_code(bytes),
+        }
+    }
+
+    /// The exit code from the last OSC 133 command finished code, eg `\x1b]133;D;1\x07`.
+    fn command_exit_code(bytes: &[u8]) -> Option<i32> {
+        let start = ShadowTerminal::rfind_subsequence(bytes, COMMAND_FINISHED.as_bytes())?
+            + COMMAND_FINISHED.len();
+        let parameters = bytes.get(start..)?.strip_prefix(b";")?;
+        let length = parameters
+            .iter()
+            .position(|byte| !byte.is_ascii_digit() && *byte != b'-')
+            .unwrap_or(parameters.len());
+        core::str::from_utf8(parameters.get(..length)?)
+            .ok()?
+            .parse()
+            .ok()
+    }
+}
+
 // TODO: Would it be useful to keep the PTY's task handle on here, and `await` it in the main loop,
 // so that the PTY module always has time to do its shutdown?
 //
@@ -114,10 +253,35 @@ pub struct ShadowTerminal {
     pub accumulated_pty_output: Vec<u8>,
     /// The timestamp for when to broadcast accumulated PTY output.
     pub wait_for_output_until: Option<tokio::time::Instant>,
+    /// Whether the accumulated PTY output has started a synchronized update that it hasn't yet
+    /// ended. It's kept up to date as each chunk of output arrives, so that the whole accumulated
+    /// output doesn't have to be searched again for every chunk.
+    pub is_synchronized_update_open: bool,
+    /// When the application in the PTY started its current synchronized update, if it's in the
+    /// middle of one.
+    pub synchronized_update_started: Option<tokio::time::Instant>,
     /// The current position of the scollback buffer.
     pub scroll_position: usize,
+    /// Bookmarked lines of the scrollback, in order from the top. Wezterm's stable row indexes
+    /// don't change as the scrollback grows, or when the terminal is resized.
+    pub bookmarks: Vec<wezterm_term::StableRowIndex>,
     /// Metadata about the most recent sent output.
     pub last_sent: LastSent,
+    /// Whether the terminal has rung its bell since the last output was sent.
+    pub is_bell_rung: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Whether the shell has reported a new working directory since the last output was sent.
+    pub is_working_directory_changed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Notifications that the terminal has raised since the last output was sent.
+    pub notifications: std::sync::Arc<std::sync::Mutex<Vec<crate::output::Notification>>>,
+    /// The title that an application has set since the last events were sent.
+    pub title: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    /// Whether the alternate screen was active when the last events were sent.
+    pub is_alternate_screen: bool,
+    /// Whether an application has changed the palette since the last output was sent.
+    pub is_palette_changed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// The cursor colour that an application has set with the OSC 12 code. `None` when the
+    /// terminal's default cursor colour is being used.
+    pub cursor_colour: Option<termwiz::color::SrgbaTuple>,
 }
 
 impl ShadowTerminal {
@@ -129,9 +293,10 @@ impl ShadowTerminal {
     ) -> Self {
         let (control_tx, _) = tokio::sync::broadcast::channel(64);
         let (output_tx, output_rx) = tokio::sync::mpsc::channel(1);
+        let (events_tx, _) = tokio::sync::broadcast::channel(64);
 
         tracing::debug!("Creating the in-memory Wezterm terminal");
-        let terminal = wezterm_term::Terminal::new(
+        let mut terminal = wezterm_term::Terminal::new(
             Self::wezterm_size(config.width.into(), config.height.into()),
             std::sync::Arc::new(WeztermConfig {
                 scrollback: config.scrollback_size,
@@ -140,6 +305,19 @@ impl ShadowTerminal {
             "O_o",
             Box::<Vec<u8>>::default(),
         );
+        let is_bell_rung = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let is_working_directory_changed =
+            std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let notifications = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let title = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let is_palette_changed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        terminal.set_notification_handler(Box::new(AlertListener {
+            is_bell_rung: std::sync::Arc::clone(&is_bell_rung),
+            is_working_directory_changed: std::sync::Arc::clone(&is_working_directory_changed),
+            notifications: std::sync::Arc::clone(&notifications),
+            title: std::sync::Arc::clone(&title),
+            is_palette_changed: std::sync::Arc::clone(&is_palette_changed),
+        }));
 
         let pty_size = (config.width.into(), config.height.into());
         Self {
@@ -151,17 +329,35 @@ impl ShadowTerminal {
                 output_rx,
                 internal_input_tx: None,
                 shadow_output,
+                events_tx,
             },
             accumulated_pty_output: Vec::new(),
             wait_for_output_until: None,
+            is_synchronized_update_open: false,
+            synchronized_update_started: None,
             scroll_position: 0,
+            bookmarks: Vec::new(),
             last_sent: LastSent {
                 pty_sequence: 0,
                 pty_size,
             },
+            is_bell_rung,
+            is_working_directory_changed,
+            notifications,
+            title,
+            is_alternate_screen: false,
+            is_palette_changed,
+            cursor_colour: None,
         }
     }
 
+    /// Subscribe to the terminal's semantic events, see [`crate::events::Event`].
+    #[inline]
+    #[must_use]
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<crate::events::Event> {
+        self.channels.events_tx.subscribe()
+    }
+
     /// Start the background PTY process.
     #[inline]
     pub fn start(
@@ -243,6 +439,7 @@ impl ShadowTerminal {
 
     /// Accumulate PTY outputs.
     fn accumulate_pty_output(&mut self, bytes: &crate::pty::BytesFromPTY) {
+        let previous_length = self.accumulated_pty_output.len();
         // TODO: I feel like this loop is either inefficient, naive, or both.
         for byte in bytes {
             if byte == &0 {
@@ -251,26 +448,80 @@ impl ShadowTerminal {
             self.accumulated_pty_output.push(*byte);
         }
 
-        let next_output_broadcast = tokio::time::Instant::now()
-            + tokio::time::Duration::from_micros(TIME_TO_WAIT_FOR_MORE_PTY_OUTPUT);
+        self.is_synchronized_update_open = Self::follow_synchronized_update(
+            self.is_synchronized_update_open,
+            &self.accumulated_pty_output,
+            previous_length,
+        );
+
+        let now = tokio::time::Instant::now();
+        let next_output_broadcast = if self.is_synchronized_update_open {
+            // Don't render half of an update, but don't wait forever for it to finish either.
+            let started = *self.synchronized_update_started.get_or_insert(now);
+            started + SYNCHRONIZED_UPDATE_TIMEOUT
+        } else {
+            now + tokio::time::Duration::from_micros(TIME_TO_WAIT_FOR_MORE_PTY_OUTPUT)
+        };
         self.wait_for_output_until = Some(next_output_broadcast);
     }
 
-    /// Find bytes in bytes.
+    /// Whether a synchronized update is open after more output has been accumulated. Only the new
+    /// output is searched, along with the end of the previous output, in case a code was split
+    /// between chunks.
+    fn follow_synchronized_update(
+        was_open: bool,
+        accumulated: &[u8],
+        previous_length: usize,
+    ) -> bool {
+        let overlap = SYNCHRONIZED_UPDATE_START.len().saturating_sub(1);
+        let new_output = accumulated
+            .get(previous_length.saturating_sub(overlap)..)
+            .unwrap_or_default();
+        Self::synchronized_update_state(new_output).unwrap_or(was_open)
+    }
+
+    /// Whether PTY output leaves a synchronized update open, or `None` if it neither starts nor
+    /// ends one.
+    fn synchronized_update_state(bytes: &[u8]) -> Option<bool> {
+        let maybe_start = Self::rfind_subsequence(bytes, SYNCHRONIZED_UPDATE_START.as_bytes());
+        let maybe_end = Self::rfind_subsequence(bytes, SYNCHRONIZED_UPDATE_END.as_bytes());
+        match (maybe_start, maybe_end) {
+            (None, None) => None,
+            (Some(_), None) => Some(true),
+            (None, Some(_)) => Some(false),
+            (Some(start), Some(end)) => Some(start > end),
+        }
+    }
+
+    /// Find bytes in bytes. An empty needle is never found.
     fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return None;
+        }
         haystack
             .windows(needle.len())
             .position(|window| window == needle)
     }
 
+    /// Find the last occurrence of bytes in bytes. An empty needle is never found.
+    fn rfind_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return None;
+        }
+        haystack
+            .windows(needle.len())
+            .rposition(|window| window == needle)
+    }
+
     /// Handle bytes from the PTY
     pub(crate) async fn handle_pty_output(
         &mut self,
     ) -> Result<(), crate::errors::ShadowTerminalError> {
         let bytes_copy = self.accumulated_pty_output.clone();
         let bytes = bytes_copy.as_slice();
+        let codes = SpecialCodes::scan(bytes);
 
-        if Self::find_subsequence(bytes, APPLICATION_MODE_START.as_bytes()).is_some() {
+        if codes.is_application_mode_start {
             tracing::trace!("Starting terminal 'application mode'");
             crate::output::raw_string_direct_to_terminal(APPLICATION_MODE_START)
                 .with_whatever_context(|err| {
@@ -278,7 +529,7 @@ impl ShadowTerminal {
                 })?;
         }
 
-        if Self::find_subsequence(bytes, APPLICATION_MODE_END.as_bytes()).is_some() {
+        if codes.is_application_mode_end {
             tracing::trace!("APPLICATION_MODE_END");
             crate::output::raw_string_direct_to_terminal(APPLICATION_MODE_END)
                 .with_whatever_context(|err| {
@@ -286,15 +537,146 @@ impl ShadowTerminal {
                 })?;
         }
 
-        self.handle_cursor_position_request(bytes).await?;
+        if codes.is_cursor_position_request {
+            self.handle_cursor_position_request().await?;
+        }
         self.terminal.advance_bytes(bytes);
         tracing::trace!("Wezterm shadow terminal advanced {} bytes", bytes.len());
+        self.send_screen_events();
         let result = self.send_outputs().await;
         if let Err(error) = result {
             tracing::error!("{error:?}");
         }
+        if self
+            .is_bell_rung
+            .swap(false, std::sync::atomic::Ordering::Relaxed)
+        {
+            tracing::trace!("Shadow terminal bell rung");
+            self.send_event(crate::events::Event::Bell);
+            self.send_output(crate::output::Output::Bell).await?;
+        }
+        self.send_notification_outputs().await?;
+        self.send_cursor_colour_output().await?;
+        self.send_shell_integration_outputs(codes).await?;
         self.accumulated_pty_output.clear();
         self.wait_for_output_until = None;
+        self.is_synchronized_update_open = false;
+        self.synchronized_update_started = None;
+        Ok(())
+    }
+
+    /// Broadcast the events that can only be noticed by looking at the terminal after it has
+    /// handled some PTY output, namely title changes and switching screens.
+    fn send_screen_events(&mut self) {
+        let maybe_title = match self.title.lock() {
+            Ok(mut title) => title.take(),
+            Err(error) => {
+                tracing::error!("Couldn't read title: {error:?}");
+                None
+            }
+        };
+        if let Some(title) = maybe_title {
+            self.send_event(crate::events::Event::Title(title));
+        }
+
+        let is_alternate_screen = self.terminal.is_alt_screen_active();
+        if is_alternate_screen != self.is_alternate_screen {
+            self.is_alternate_screen = is_alternate_screen;
+            self.send_event(crate::events::Event::AlternateScreen(is_alternate_screen));
+        }
+    }
+
+    /// Broadcast a semantic event to any subscribers.
+    fn send_event(&self, event: crate::events::Event) {
+        tracing::trace!("Shadow terminal event: {event:?}");
+        // It's normal for nobody to be subscribed.
+        if self.channels.events_tx.send(event).is_err() {
+            tracing::trace!("No subscribers for shadow terminal events");
+        }
+    }
+
+    /// Broadcast any notifications that the terminal has raised, eg with `printf '\e]9;Hi\a'`.
+    async fn send_notification_outputs(
+        &mut self,
+    ) -> Result<(), crate::errors::ShadowTerminalError> {
+        let notifications = match self.notifications.lock() {
+            Ok(mut notifications) => core::mem::take(&mut *notifications),
+            Err(error) => {
+                tracing::error!("Couldn't read notifications: {error:?}");
+                return Ok(());
+            }
+        };
+        for notification in notifications {
+            tracing::trace!("Shadow terminal notification: {notification:?}");
+            self.send_output(crate::output::Output::Notification(notification))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Broadcast the cursor colour if an application has changed it, eg with
+    /// `printf '\e]12;#ff0000\a'`, or reset it with the OSC 112 code.
+    async fn send_cursor_colour_output(
+        &mut self,
+    ) -> Result<(), crate::errors::ShadowTerminalError> {
+        if !self
+            .is_palette_changed
+            .swap(false, std::sync::atomic::Ordering::Relaxed)
+        {
+            return Ok(());
+        }
+
+        let cursor_colour = self.terminal.palette().cursor_bg;
+        let default_cursor_colour = wezterm_term::color::ColorPalette::default().cursor_bg;
+        let maybe_cursor_colour = (cursor_colour != default_cursor_colour).then_some(cursor_colour);
+        if maybe_cursor_colour == self.cursor_colour {
+            return Ok(());
+        }
+
+        tracing::trace!("Shadow terminal cursor colour changed: {maybe_cursor_colour:?}");
+        self.cursor_colour = maybe_cursor_colour;
+        self.send_output(crate::output::Output::CursorColour(maybe_cursor_colour))
+            .await
+    }
+
+    /// Broadcast the events that shells send when they have shell integration enabled, namely
+    /// changes to the working directory (OSC 7) and commands finishing (OSC 133).
+    async fn send_shell_integration_outputs(
+        &mut self,
+        codes: SpecialCodes,
+    ) -> Result<(), crate::errors::ShadowTerminalError> {
+        if self
+            .is_working_directory_changed
+            .swap(false, std::sync::atomic::Ordering::Relaxed)
+        {
+            let maybe_path = self
+                .terminal
+                .get_current_dir()
+                .and_then(|url| url.to_file_path().ok());
+            if let Some(path) = maybe_path {
+                tracing::trace!("Shadow terminal working directory changed: {path:?}");
+                self.send_output(crate::output::Output::WorkingDirectory(path))
+                    .await?;
+            }
+        }
+
+        // A quick command may start and finish in the same chunk of output, so the start is always
+        // sent first.
+        if codes.is_command_started {
+            tracing::trace!("Shadow terminal command started");
+            self.send_output(crate::output::Output::CommandStarted)
+                .await?;
+        }
+
+        if codes.is_command_finished {
+            tracing::trace!("Shadow terminal command finished");
+            self.send_output(crate::output::Output::CommandFinished(
+                codes.command_exit_code,
+            ))
+            .await?;
+        }
+
         Ok(())
     }
 
@@ -311,12 +693,7 @@ impl ShadowTerminal {
     )]
     async fn handle_cursor_position_request(
         &mut self,
-        bytes: &[u8],
     ) -> Result<(), crate::errors::ShadowTerminalError> {
-        if Self::find_subsequence(bytes, CURSOR_POSITION_REQUEST.as_bytes()).is_none() {
-            return Ok(());
-        }
-
         let mut payload: crate::pty::BytesFromSTDIN = [0; 128];
         let cursor_position = self.terminal.cursor_pos();
         let response_string = format!("\x1b[{};{}R", cursor_position.y, cursor_position.x);
@@ -416,6 +793,10 @@ impl ShadowTerminal {
                     usize::from(*height),
                 ));
                 tracing::trace!("Wezterm terminal resized to: {width}x{height}");
+                self.send_event(crate::events::Event::Resized {
+                    width: *width,
+                    height: *height,
+                });
             }
             crate::Protocol::Scroll(scroll) => {
                 match scroll {
@@ -436,6 +817,15 @@ impl ShadowTerminal {
                     crate::Scroll::Cancel => {
                         self.scroll_position = 0;
                     }
+                    crate::Scroll::To(position) => {
+                        let size = self.terminal.get_size();
+                        let total_lines = self
+                            .terminal
+                            .screen()
+                            .scrollback_rows()
+                            .saturating_sub(size.rows);
+                        self.scroll_position = (*position).min(total_lines);
+                    }
                 }
 
                 let result = self.send_outputs().await;
@@ -443,11 +833,122 @@ impl ShadowTerminal {
                     tracing::error!("Couldn't send PTY output from shadow terminal: {error:?}");
                 }
             }
+            crate::Protocol::Bookmark(bookmark) => {
+                let result = self.handle_bookmark(bookmark).await;
+                if let Err(error) = result {
+                    tracing::error!("Couldn't handle scrollback bookmark: {error:?}");
+                }
+            }
+            crate::Protocol::Exited(code) => {
+                tracing::debug!("Shadow terminal's PTY exited with code: {code}");
+                self.send_event(crate::events::Event::Exited(*code));
+                let result = self.send_output(crate::output::Output::Exited(*code)).await;
+                if let Err(error) = result {
+                    tracing::error!("Couldn't send PTY exit code: {error:?}");
+                }
+            }
 
             _ => (),
         }
     }
 
+    /// The physical row of the scrollback that's at the top of the current scroll.
+    fn scroll_top(&self) -> usize {
+        let total_lines = self.terminal.screen().scrollback_rows();
+        let rows = self.terminal.get_size().rows;
+        total_lines.saturating_sub(rows + self.scroll_position)
+    }
+
+    /// Scroll so that the bookmarked line is at the top of the terminal. Lines that are already
+    /// on the bottom screen just exit scrolling.
+    fn scroll_to_bookmark(&mut self, bookmark: wezterm_term::StableRowIndex) {
+        let screen = self.terminal.screen();
+        let Some(row) = screen.stable_row_to_phys(bookmark) else {
+            return;
+        };
+        let bottom_screen_top = screen
+            .scrollback_rows()
+            .saturating_sub(self.terminal.get_size().rows);
+        self.scroll_position = bottom_screen_top.saturating_sub(row);
+    }
+
+    /// Handle the actions for scrollback bookmarks.
+    async fn handle_bookmark(
+        &mut self,
+        bookmark: &crate::Bookmark,
+    ) -> Result<(), crate::errors::ShadowTerminalError> {
+        // The alternate screen doesn't have any scrollback.
+        if self.terminal.is_alt_screen_active() {
+            return Ok(());
+        }
+
+        let screen = self.terminal.screen();
+        // Forget about bookmarks whose lines have fallen off the top of the scrollback.
+        self.bookmarks
+            .retain(|stable_row| screen.stable_row_to_phys(*stable_row).is_some());
+        let scroll_top = screen.phys_to_stable_row_index(self.scroll_top());
+
+        match bookmark {
+            crate::Bookmark::Add => {
+                let stable_row = if self.scroll_position == 0 {
+                    screen.visible_row_to_stable_row(self.terminal.cursor_pos().y)
+                } else {
+                    scroll_top
+                };
+                if !self.bookmarks.contains(&stable_row) {
+                    self.bookmarks.push(stable_row);
+                    self.bookmarks.sort_unstable();
+                }
+            }
+            crate::Bookmark::Previous => {
+                let maybe_previous = self
+                    .bookmarks
+                    .iter()
+                    .rev()
+                    .find(|stable_row| **stable_row < scroll_top)
+                    .copied();
+                if let Some(previous) = maybe_previous {
+                    self.scroll_to_bookmark(previous);
+                }
+            }
+            crate::Bookmark::Next => {
+                let maybe_next = self
+                    .bookmarks
+                    .iter()
+                    .find(|stable_row| **stable_row > scroll_top)
+                    .copied();
+                match maybe_next {
+                    Some(next) => self.scroll_to_bookmark(next),
+                    None => self.scroll_position = 0,
+                }
+            }
+            crate::Bookmark::Clear => self.bookmarks.clear(),
+        }
+
+        self.send_outputs().await?;
+        let bookmarks = self.describe_bookmarks();
+        self.send_output(crate::output::Output::Bookmarks(bookmarks))
+            .await
+    }
+
+    /// The text and position of all the bookmarks.
+    fn describe_bookmarks(&self) -> Vec<crate::output::Bookmark> {
+        let screen = self.terminal.screen();
+        let total_lines = screen.scrollback_rows();
+        self.bookmarks
+            .iter()
+            .filter_map(|stable_row| screen.stable_row_to_phys(*stable_row))
+            .map(|row| crate::output::Bookmark {
+                text: screen
+                    .lines_in_phys_range(row..row + 1)
+                    .first()
+                    .map(|line| line.as_str().trim_end().to_owned())
+                    .unwrap_or_default(),
+                lines_up: total_lines.saturating_sub(row + 1),
+            })
+            .collect()
+    }
+
     /// Just a convenience wrapper around the native Wezterm type
     const fn wezterm_size(width: usize, height: usize) -> wezterm_term::TerminalSize {
         wezterm_term::TerminalSize {
@@ -488,3 +989,89 @@ impl Drop for ShadowTerminal {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detecting_open_synchronized_updates() {
+        let state = |bytes: &str| ShadowTerminal::synchronized_update_state(bytes.as_bytes());
+        assert_eq!(state("hello"), None);
+        assert_eq!(state("\x1b[?2026hhello"), Some(true));
+        assert_eq!(state("\x1b[?2026hhello\x1b[?2026l"), Some(false));
+        assert_eq!(
+            state("\x1b[?2026hhello\x1b[?2026l\x1b[?2026hworld"),
+            Some(true)
+        );
+        assert_eq!(state("hello\x1b[?2026l"), Some(false));
+    }
+
+    #[test]
+    fn following_synchronized_updates_across_chunks() {
+        let mut accumulated = Vec::new();
+        let mut is_open = false;
+        let mut follow = |chunk: &str| {
+            let previous_length = accumulated.len();
+            accumulated.extend_from_slice(chunk.as_bytes());
+            is_open =
+                ShadowTerminal::follow_synchronized_update(is_open, &accumulated, previous_length);
+            is_open
+        };
+        assert!(!follow("hello \x1b[?20"));
+        assert!(follow("26h world"));
+        assert!(follow("still updating"));
+        assert!(follow("nearly \x1b[?2026"));
+        assert!(!follow("l done"));
+        assert!(!follow("after"));
+    }
+
+    #[test]
+    fn finding_command_exit_codes() {
+        let exit_code = |bytes: &str| SpecialCodes::scan(bytes.as_bytes()).command_exit_code;
+        assert_eq!(exit_code("hello"), None);
+        assert_eq!(exit_code("\x1b]133;D\x07"), None);
+        assert_eq!(exit_code("\x1b]133;D;0\x07"), Some(0));
+        assert_eq!(exit_code("\x1b]133;D;1\x07\x1b]133;D;127\x1b\\"), Some(127));
+        assert_eq!(exit_code("\x1b]133;D;oops\x07"), None);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn broadcasting_events() {
+        let mut stepper = Box::pin(crate::tests::helpers::run(None, None)).await;
+        let mut events = stepper.shadow_terminal.subscribe();
+
+        stepper
+            .send_command("printf '\\e]2;hello\\a\\e[?1049hdo''ne'")
+            .unwrap();
+        stepper.wait_for_string("done", None).await.unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert!(received.contains(&crate::events::Event::Title("hello".to_owned())));
+        assert!(received.contains(&crate::events::Event::AlternateScreen(true)));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tracking_the_cursor_colour() {
+        let mut stepper = Box::pin(crate::tests::helpers::run(None, None)).await;
+        assert_eq!(stepper.shadow_terminal.cursor_colour, None);
+
+        stepper
+            .send_command("printf '\\e]12;#ff0000\\are''d'")
+            .unwrap();
+        stepper.wait_for_string("red", None).await.unwrap();
+        assert_eq!(
+            stepper.shadow_terminal.cursor_colour,
+            Some(termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0))
+        );
+
+        stepper.send_command("printf '\\e]112\\arese''t'").unwrap();
+        stepper.wait_for_string("reset", None).await.unwrap();
+        assert_eq!(stepper.shadow_terminal.cursor_colour, None);
+    }
+}
@@ -9,6 +9,9 @@ use tracing::Instrument as _;
 /// The default time to wait looking for terminal screen content.
 const DEFAULT_TIMEOUT: u32 = 500;
 
+/// The `dwControlKeyState` flag for the Shift key being held, see `win32_key_records()`.
+const WIN32_SHIFT_PRESSED: u32 = 0x0010;
+
 /// Handle various kinds of input.
 ///
 /// Simulating STDIN has actually been quite hard. For one, it seems like terminal input parsers
@@ -25,6 +28,65 @@ pub enum Input {
     Event(String),
 }
 
+/// How typed input is encoded before it's sent to the PTY.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InputMode {
+    /// Raw characters and ANSI sequences, as Unix PTYs expect.
+    Ansi,
+    /// Windows' ConPTY converts raw input into console input records itself, but it can't always
+    /// tell what was meant, eg whether a newline is the Enter key. And PowerShell doesn't support
+    /// bracketed paste. So characters are typed one key at a time, as the "win32-input-mode"
+    /// sequences that ConPTY turns directly into input records.
+    Win32,
+}
+
+impl Default for InputMode {
+    #[inline]
+    fn default() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::Win32
+        } else {
+            Self::Ansi
+        }
+    }
+}
+
+/// Encode a character as the "win32-input-mode" sequences for pressing and then releasing its key.
+/// Each sequence is `ESC [ Vk ; Sc ; Uc ; Kd ; Cs ; Rc _`, namely the virtual key code, the scan
+/// code, the UTF-16 character, whether the key is down, the control key state and the repeat
+/// count. See Microsoft's "Improved keyboard handling in Conpty" spec.
+#[inline]
+#[must_use]
+pub fn win32_key_records(character: char) -> String {
+    let virtual_key = match character {
+        '\n' | '\r' => 0x0D,
+        '\x7f' | '\x08' => 0x08,
+        '\t' => 0x09,
+        '\x1b' => 0x1B,
+        _ if character.is_ascii_alphanumeric() => u32::from(character.to_ascii_uppercase()),
+        _ => 0,
+    };
+    let control_keys = if character.is_ascii_uppercase() {
+        WIN32_SHIFT_PRESSED
+    } else {
+        0
+    };
+    let character = match character {
+        '\n' => '\r',
+        '\x7f' => '\x08',
+        _ => character,
+    };
+
+    character
+        .encode_utf16(&mut [0; 2])
+        .iter()
+        .flat_map(|unit| {
+            [1, 0].map(|is_down| format!("\x1b[{virtual_key};0;{unit};{is_down};{control_keys};1_"))
+        })
+        .collect()
+}
+
 /// This Steppable Terminal is likely more useful for running end to end tests.
 ///
 /// It doesn't run [`ShadowTerminal`] in a loop and so requires calling certain methods manually to advance the
@@ -40,6 +102,8 @@ pub struct SteppableTerminal {
     >,
     /// A Tokio channel that forwards bytes to the underlying PTY's STDIN.
     pub pty_input_tx: tokio::sync::mpsc::Sender<crate::pty::BytesFromSTDIN>,
+    /// How typed input is encoded. Defaults to what the current OS's PTYs expect.
+    pub input_mode: InputMode,
 }
 
 impl SteppableTerminal {
@@ -62,6 +126,7 @@ impl SteppableTerminal {
             shadow_terminal,
             pty_task_handle: std::sync::Arc::new(tokio::sync::Mutex::new(pty_task_handle)),
             pty_input_tx,
+            input_mode: InputMode::default(),
         };
 
         for i in 0i8..=100 {
@@ -142,7 +207,15 @@ impl SteppableTerminal {
             Input::Characters(characters) => {
                 for char in characters.chars() {
                     let mut buffer: crate::pty::BytesFromSTDIN = [0; 128];
-                    char.encode_utf8(&mut buffer);
+                    match self.input_mode {
+                        InputMode::Ansi => {
+                            char.encode_utf8(&mut buffer);
+                        }
+                        InputMode::Win32 => {
+                            let records = win32_key_records(char);
+                            crate::pty::PTY::add_bytes_to_buffer(&mut buffer, records.as_bytes())?;
+                        }
+                    }
 
                     self.pty_input_tx
                         .try_send(buffer)
@@ -186,12 +259,18 @@ impl SteppableTerminal {
         Ok(())
     }
 
-    /// Use OSC Paste codes to send a large amount of text at once to the terminal.
+    /// Use OSC Paste codes to send a large amount of text at once to the terminal. In
+    /// [`InputMode::Win32`] the text is typed instead, because PowerShell doesn't support
+    /// bracketed paste.
     ///
     /// # Errors
     /// If sending the string fails
     #[inline]
     pub fn paste_string(&self, string: &str) -> Result<(), crate::errors::PTYError> {
+        if self.input_mode == InputMode::Win32 {
+            return self.send_input(Input::Characters(string.to_owned()));
+        }
+
         let paste_start = "\x1b[200~";
         let paste_end = "\x1b[201~";
         let pastable_string = format!("{paste_start}{string}{paste_end}");
@@ -381,6 +460,70 @@ impl SteppableTerminal {
         Ok(())
     }
 
+    /// Capture every cell of the current screen, so that later changes can be compared against
+    /// it, see [`Self::assert_screen_changed_only_within()`].
+    ///
+    /// # Errors
+    /// If a cell's row index can't be converted.
+    #[inline]
+    pub fn snapshot(
+        &mut self,
+    ) -> Result<crate::screen_diff::Screen, crate::errors::SteppableTerminalError> {
+        let size = self.shadow_terminal.terminal.get_size();
+        let mut screen = self.shadow_terminal.terminal.screen().clone();
+        let mut cells = Vec::new();
+
+        for y in 0..size.rows {
+            let mut row = Vec::new();
+            for x in 0..size.cols {
+                let maybe_cell = screen.get_cell(
+                    x,
+                    y.try_into().with_whatever_context(|err| {
+                        format!("Couldn't convert cell index to i64: {err}")
+                    })?,
+                );
+                row.push(maybe_cell.cloned().unwrap_or_default());
+            }
+            cells.push(row);
+        }
+
+        Ok(crate::screen_diff::Screen::new(cells))
+    }
+
+    /// Assert that, since the given snapshot, nothing on the screen has changed outside of the
+    /// given region. Useful for checking that an effect didn't disturb any of the terminal's
+    /// content that it wasn't meant to.
+    ///
+    /// # Errors
+    /// * If it can't get the screen contents.
+    /// * If anything changed outside of the region. Every such change is listed.
+    #[inline]
+    pub async fn assert_screen_changed_only_within(
+        &mut self,
+        before: &crate::screen_diff::Screen,
+        region: crate::screen_diff::Rect,
+    ) -> Result<(), crate::errors::SteppableTerminalError> {
+        self.render_all_output()
+            .await
+            .with_whatever_context(|err| format!("Couldn't render output: {err:?}"))?;
+        let after = self.snapshot()?;
+        let outside = before.changes_outside(&after, region);
+        if !outside.is_empty() {
+            self.dump_screen()?;
+            let changes = outside
+                .iter()
+                .map(crate::screen_diff::Change::describe)
+                .collect::<Vec<String>>()
+                .join("\n");
+            snafu::whatever!(
+                "{} cells changed outside of {region:?}:\n{changes}",
+                outside.len()
+            );
+        }
+
+        Ok(())
+    }
+
     /// Get the prompt as a string. Useful for reproducibility as prompts can change between
     /// machines.
     ///
@@ -646,6 +789,7 @@ impl Drop for SteppableTerminal {
 
 #[cfg(test)]
 mod test {
+    use super::*;
 
     /// Setup logging
     fn setup_logging() {
@@ -699,6 +843,82 @@ mod test {
         assert_eq!(resized_menu_item_paste, "Paste");
     }
 
+    #[test]
+    fn encoding_win32_key_records() {
+        assert_eq!(
+            win32_key_records('a'),
+            "\x1b[65;0;97;1;0;1_\x1b[65;0;97;0;0;1_"
+        );
+        assert_eq!(
+            win32_key_records('A'),
+            "\x1b[65;0;65;1;16;1_\x1b[65;0;65;0;16;1_"
+        );
+        assert_eq!(
+            win32_key_records('\n'),
+            "\x1b[13;0;13;1;0;1_\x1b[13;0;13;0;0;1_"
+        );
+        assert_eq!(
+            win32_key_records('$'),
+            "\x1b[0;0;36;1;0;1_\x1b[0;0;36;0;0;1_"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn canonical_prompt() {
+        let mut stepper =
+            Box::pin(crate::tests::helpers::run_with_canonical_prompt(None, None)).await;
+        stepper.send_command("echo $((1+1))").unwrap();
+        stepper.wait_for_string_at("2", 0, 1, None).await.unwrap();
+        let prompt = crate::tests::helpers::CANONICAL_PROMPT;
+        stepper
+            .wait_for_string_at(prompt, 0, 2, None)
+            .await
+            .unwrap();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn screen_changed_only_within() {
+        let mut stepper = Box::pin(crate::tests::helpers::run(None, None)).await;
+        let width = stepper.shadow_terminal.terminal.get_size().cols;
+        let before = stepper.snapshot().unwrap();
+
+        stepper.send_command("echo changed").unwrap();
+        stepper
+            .wait_for_string_at("changed", 0, 1, None)
+            .await
+            .unwrap();
+
+        let command_and_output = crate::screen_diff::Rect::new(0, 0, width, 3);
+        stepper
+            .assert_screen_changed_only_within(&before, command_and_output)
+            .await
+            .unwrap();
+        let prompt_only = crate::screen_diff::Rect::new(0, 0, width, 1);
+        assert!(stepper
+            .assert_screen_changed_only_within(&before, prompt_only)
+            .await
+            .is_err());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn respawning_the_command() {
+        let mut stepper = Box::pin(crate::tests::helpers::run(None, None)).await;
+        stepper.send_command("echo before").unwrap();
+        stepper.wait_for_string("before", None).await.unwrap();
+
+        let command = vec!["sh".into(), "-c".into(), "echo after; sleep 10".into()];
+        stepper
+            .shadow_terminal
+            .channels
+            .control_tx
+            .send(crate::Protocol::Respawn(command))
+            .unwrap();
+        stepper.wait_for_string("after", None).await.unwrap();
+        assert!(stepper.screen_as_string().unwrap().contains("before"));
+    }
+
     #[cfg(not(target_os = "windows"))]
     #[tokio::test(flavor = "multi_thread")]
     async fn cursor_position_response() {
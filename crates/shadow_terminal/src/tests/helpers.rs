@@ -42,6 +42,31 @@ pub fn get_canonical_shell() -> Vec<std::ffi::OsString> {
         .collect()
 }
 
+/// The prompt that [`run_with_canonical_prompt()`] sets, so that tests don't depend on the user's
+/// own prompt.
+pub const CANONICAL_PROMPT: &str = "shadow $ ";
+
+/// Whether the shell is PowerShell, which needs its own syntax.
+fn is_powershell(shell: &[std::ffi::OsString]) -> bool {
+    shell.first().is_some_and(|program| {
+        let program = program.to_string_lossy().to_lowercase();
+        program.contains("powershell") || program.contains("pwsh")
+    })
+}
+
+/// The command that sets the shell's prompt to [`CANONICAL_PROMPT`] and then clears the screen.
+/// The prompt is split into two strings, so that the command itself doesn't contain it.
+#[inline]
+#[must_use]
+pub fn set_canonical_prompt_command(shell: &[std::ffi::OsString]) -> String {
+    let (start, end) = CANONICAL_PROMPT.split_at(CANONICAL_PROMPT.len() / 2);
+    if is_powershell(shell) {
+        format!("function prompt {{ '{start}' + '{end}' }}; Clear-Host")
+    } else {
+        format!("PS1='{start}''{end}'; clear")
+    }
+}
+
 /// Run a steppable terminal.
 ///
 /// # Panics
@@ -60,3 +85,22 @@ pub async fn run(
         .await
         .unwrap()
 }
+
+/// Run a steppable terminal with the canonical shell, and wait for it to show the canonical
+/// prompt at the top of the screen.
+///
+/// # Panics
+#[inline]
+pub async fn run_with_canonical_prompt(
+    width: Option<u16>,
+    height: Option<u16>,
+) -> crate::steppable_terminal::SteppableTerminal {
+    let mut stepper = Box::pin(run(width, height)).await;
+    let command = set_canonical_prompt_command(&get_canonical_shell());
+    stepper.send_command(&command).unwrap();
+    stepper
+        .wait_for_string_at(CANONICAL_PROMPT, 0, 0, Some(5000))
+        .await
+        .unwrap();
+    stepper
+}
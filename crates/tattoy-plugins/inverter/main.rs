@@ -42,7 +42,8 @@ fn main() {
                 let output = tattoy_protocol::PluginOutputMessages::OutputCells(outgoing_cells);
                 print!("{}", serde_json::to_string(&output).unwrap());
             }
-            _ => todo!(),
+            // Tattoy can always send new kinds of messages, so anything else is ignored.
+            _ => (),
         }
     }
 }
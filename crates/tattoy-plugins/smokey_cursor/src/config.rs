@@ -1,11 +1,23 @@
-//! All the variables that can be configured for the simulation
+//! All the variables that can be configured for the simulation. They can be set by the user in
+//! the `config` table of the plugin's entry in Tattoy's config.
 
 /// All the config for the simulation
-#[derive(Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
 #[non_exhaustive]
 pub struct Config {
-    /// The gravitational exceleration of the system in metres per second
-    pub gravity: (f32, f32),
+    /// How strongly the smoke rises
+    pub buoyancy: f32,
+    /// A horizontal force on the smoke. Positive values blow it to the right.
+    pub wind: f32,
+    /// The resting density of the smoke. Lower values make the smoke spread out more.
+    pub density: f32,
+    /// The colours that a particle fades through as it ages
+    pub colour_ramp: Vec<tattoy_protocol::Colour>,
+    /// How many seconds it takes a particle to fade through all the colours of the ramp
+    pub colour_ramp_duration: f32,
+    /// Whether the smoke collides with, and pools against, text in the terminal
+    pub collide_with_text: bool,
     /// The velocity of a particle when it is first added
     pub initial_velocity: (f32, f32),
     /// How much bigger a partical is compared to a rendered pixel
@@ -17,10 +29,85 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
-            gravity: (0.0, -9.81),
-            initial_velocity: (0.0, 0.0),
+            buoyancy: 9.81,
+            wind: 0.0,
+            density: 300.0,
+            colour_ramp: vec![(0.15, 0.15, 0.15, 1.0)],
+            colour_ramp_duration: 5.0,
+            collide_with_text: true,
+            initial_velocity: (0.01, -0.1),
             scale: 0.75,
             max_particles: 3000,
         }
     }
 }
+
+#[expect(
+    clippy::arithmetic_side_effects,
+    clippy::float_arithmetic,
+    reason = "This is a prototype"
+)]
+impl Config {
+    /// The gravitational exceleration of the system in metres per second. Smoke rises, so it's
+    /// upwards.
+    #[must_use]
+    pub fn gravity(&self) -> (f32, f32) {
+        (self.wind, -self.buoyancy)
+    }
+
+    /// The colour of a particle of the given age, in seconds.
+    #[must_use]
+    pub fn colour_at(&self, age: f32) -> tattoy_protocol::Colour {
+        let Some(last) = self.colour_ramp.len().checked_sub(1) else {
+            return tattoy_protocol::Colour::default();
+        };
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "Colour ramps are never long enough to lose precision"
+        )]
+        let (from, to, mix) = {
+            let progress = (age / self.colour_ramp_duration.max(f32::EPSILON)).clamp(0.0, 1.0);
+            let position = progress * last as f32;
+            let from = position.floor() as usize;
+            (from, (from + 1).min(last), position.fract())
+        };
+
+        let (Some(from), Some(to)) = (self.colour_ramp.get(from), self.colour_ramp.get(to)) else {
+            return tattoy_protocol::Colour::default();
+        };
+        (
+            from.0 + (to.0 - from.0) * mix,
+            from.1 + (to.1 - from.1) * mix,
+            from.2 + (to.2 - from.2) * mix,
+            from.3 + (to.3 - from.3) * mix,
+        )
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::float_cmp, reason = "Tests aren't so strict")]
+mod test {
+    use super::*;
+
+    #[test]
+    fn colour_ramp() {
+        let config = Config {
+            colour_ramp: vec![(0.0, 0.0, 0.0, 1.0), (1.0, 0.5, 0.0, 1.0)],
+            colour_ramp_duration: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(config.colour_at(0.0), (0.0, 0.0, 0.0, 1.0));
+        assert_eq!(config.colour_at(1.0), (0.5, 0.25, 0.0, 1.0));
+        assert_eq!(config.colour_at(10.0), (1.0, 0.5, 0.0, 1.0));
+
+        let empty = Config {
+            colour_ramp: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(empty.colour_at(1.0), tattoy_protocol::Colour::default());
+    }
+}
@@ -13,8 +13,6 @@ pub const PARTICLE_SIZE_SQUARED: f32 = PARTICLE_SIZE * PARTICLE_SIZE;
 const MASS: f32 = 2.5;
 /// ?
 const GAS_CONST: f32 = 2000.0;
-/// ?
-const REST_DENSITY: f32 = 300.0;
 /// Viscosity of the gas/liquid
 const VISCOSITY: f32 = 200.0;
 
@@ -101,8 +99,8 @@ impl Particle {
     }
 
     /// Given the acummulated density of a particle and its neighbours, calculate its presssure
-    pub fn update_pressure(&mut self) {
-        self.pressure = GAS_CONST * (self.density - REST_DENSITY);
+    pub fn update_pressure(&mut self, rest_density: f32) {
+        self.pressure = GAS_CONST * (self.density - rest_density);
     }
 
     /// The force from gravity
@@ -145,6 +143,14 @@ impl Particle {
         }
     }
 
+    /// Stop the particle from moving into something solid, like text. It keeps some of its
+    /// horizontal velocity so that it can slide along, and pool against, the obstacle.
+    pub fn collide(&mut self, previous_position: Vec2) {
+        self.position = previous_position;
+        self.velocity.x *= -BOUND_DAMPING;
+        self.velocity.y = 0.0;
+    }
+
     /// The position of the particle in the same scale as they were added
     #[must_use]
     pub fn position_unscaled(&self) -> Vec2 {
@@ -26,10 +26,15 @@ pub struct SmokeyCursor {
     tty: TTY,
     /// All the particles of the gas.
     simulation: Simulation,
+    /// The user's settings for the simulation.
+    config: crate::config::Config,
     /// Timestamps of recent render ticks.
     durations: VecDeque<f64>,
     /// The time at which the previous frame was rendererd.
     last_frame_tick: tokio::time::Instant,
+    /// Whether the user has asked for animations to be avoided. The smoke isn't rendered at all
+    /// whilst it's set.
+    is_motion_reduced: bool,
 }
 
 impl SmokeyCursor {
@@ -43,13 +48,16 @@ impl SmokeyCursor {
             },
             last_frame_tick: tokio::time::Instant::now(),
             simulation: Simulation::new(0, 0),
+            config: crate::config::Config::default(),
             durations: VecDeque::default(),
+            is_motion_reduced: false,
         }
     }
 
     /// Initialise the simulation.
     fn initialise(&mut self) {
-        self.simulation = Simulation::new(self.tty.size.0, self.tty.size.1 * 2);
+        self.simulation =
+            Simulation::with_config(self.tty.size.0, self.tty.size.1 * 2, self.config.clone());
 
         tracing::debug!("Simulation initialised.");
     }
@@ -105,6 +113,29 @@ impl SmokeyCursor {
                 self.tty.size = (width, height);
                 self.simulation.resize(width, height * 2);
             }
+            tattoy_protocol::PluginInputMessages::Config(value) => {
+                match serde_json::from_value(value) {
+                    Ok(config) => {
+                        tracing::debug!("Received config: {config:?}");
+                        self.config = config;
+                        // Restart the simulation with the new settings.
+                        self.simulation = Simulation::new(0, 0);
+                    }
+                    Err(error) => tracing::error!("Couldn't parse config: {error:?}"),
+                }
+            }
+            tattoy_protocol::PluginInputMessages::Accessibility { reduce_motion } => {
+                tracing::debug!("Reduce motion: {reduce_motion}");
+                self.is_motion_reduced = reduce_motion;
+                if reduce_motion {
+                    // Clear any smoke that's already been rendered.
+                    if let Err(error) = Self::send_output(Vec::new()) {
+                        tracing::error!("Couldn't clear smoke: {error:?}");
+                    }
+                }
+            }
+            // The smoke is blown by the cursor, which already moves along with scrolled text.
+            tattoy_protocol::PluginInputMessages::Scroll { .. } => (),
 
             #[expect(
                 clippy::unreachable,
@@ -119,7 +150,7 @@ impl SmokeyCursor {
 
     /// Send a frame to Tattoy.
     fn render(&mut self) -> Result<()> {
-        if self.tty.size.0 == 0 || self.tty.size.1 == 0 {
+        if self.is_motion_reduced || self.tty.size.0 == 0 || self.tty.size.1 == 0 {
             return Ok(());
         }
 
@@ -2,7 +2,7 @@
 //! Heavily inspired by [mueller-sph-rs](https://github.com/lucas-schuermann/mueller-sph-rs)
 
 use rayon::iter::{IntoParallelRefMutIterator as _, ParallelIterator as _};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 
 use glam::Vec2;
 
@@ -28,6 +28,8 @@ pub struct Simulation {
     pub neighbours: rstar::RTree<Particle>,
     /// The configurable settings for the simulation
     pub config: Config,
+    /// The pixels that are covered by text in the user's terminal
+    pub text: HashSet<(u32, u32)>,
 }
 
 #[expect(
@@ -39,16 +41,19 @@ impl Simulation {
     /// Initialise a new simulation
     #[must_use]
     pub fn new(width: u16, height: u16) -> Self {
-        let config = Config {
-            initial_velocity: Vec2::new(0.01, -0.1).into(),
-            ..Default::default()
-        };
+        Self::with_config(width, height, Config::default())
+    }
+
+    /// Initialise a new simulation with the given settings
+    #[must_use]
+    pub fn with_config(width: u16, height: u16, config: Config) -> Self {
         let mut simulation = Self {
             width: 0.0,
             height: 0.0,
             particles: VecDeque::default(),
             neighbours: rstar::RTree::new(),
             config,
+            text: HashSet::new(),
         };
         simulation.resize(width, height);
         simulation
@@ -72,6 +77,7 @@ impl Simulation {
             self.add_particle(f32::from(cursor.0), f32::from(cursor.1 * 2));
         }
 
+        self.update_text(cells);
         let pty_pixel_count = self.add_pty_particles(cursor, cells);
 
         for _ in 0..NUMBER_OF_SIMULATION_STEPS_PER_TICK {
@@ -83,6 +89,47 @@ impl Simulation {
         }
 
         self.remove_old_particles();
+        self.update_colours();
+    }
+
+    /// Keep track of which pixels are covered by text, so that the smoke can collide with them.
+    fn update_text(&mut self, cells: &[tattoy_protocol::Cell]) {
+        self.text.clear();
+        if !self.config.collide_with_text {
+            return;
+        }
+
+        for cell in cells {
+            if cell.character.is_whitespace() {
+                continue;
+            }
+            let (x, row) = cell.coordinates;
+            self.text.insert((x, row * 2));
+            self.text.insert((x, row * 2 + 1));
+        }
+    }
+
+    /// Whether the given unscaled position is covered by text.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Negative positions are outside the terminal anyway"
+    )]
+    fn is_in_text(text: &HashSet<(u32, u32)>, position: Vec2) -> bool {
+        !text.is_empty() && text.contains(&(position.x as u32, position.y as u32))
+    }
+
+    /// Fade the smoke through the colour ramp as it ages.
+    fn update_colours(&mut self) {
+        for particle in &mut self.particles {
+            if particle.is_immovable {
+                continue;
+            }
+            particle.colour = self
+                .config
+                .colour_at(particle.created_at.elapsed().as_secs_f32());
+        }
     }
 
     /// Step through the simulation
@@ -105,8 +152,13 @@ impl Simulation {
     /// Calculate the next position of the particles
     fn integrate(&mut self) {
         for particle in &mut self.particles {
+            let previous_position = particle.position;
             particle.integrate();
             particle.boundaries(self.width, self.height);
+            if !particle.is_immovable && Self::is_in_text(&self.text, particle.position_unscaled())
+            {
+                particle.collide(previous_position);
+            }
         }
     }
 
@@ -125,7 +177,7 @@ impl Simulation {
                 particle.accumulate_density(neighbour);
             });
 
-            particle.update_pressure();
+            particle.update_pressure(self.config.density);
         });
     }
 
@@ -150,7 +202,7 @@ impl Simulation {
                 }
             });
 
-            let gravity = particle.force_from_gravity(self.config.gravity.into());
+            let gravity = particle.force_from_gravity(self.config.gravity().into());
             particle.force += gravity;
         });
     }
@@ -167,7 +219,7 @@ mod test {
 
     fn make_sim() -> Simulation {
         let mut sim = Simulation::new(100, 100);
-        sim.config.gravity = Vec2::ZERO.into();
+        sim.config.buoyancy = 0.0;
         sim.config.initial_velocity = Vec2::ZERO.into();
         sim.config.scale = 1.0 * crate::particle::PARTICLE_SIZE; // So we don't have to scale/unscale
         sim
@@ -229,7 +281,7 @@ mod test {
     #[test]
     fn gravity_moves_particle() {
         let mut sim = make_sim();
-        sim.config.gravity = Vec2::new(0.0, -1.0).into();
+        sim.config.buoyancy = 1.0;
         add_particle(&mut sim, Vec2::new(50.0, 50.0));
 
         for _ in 0usize..10 {
@@ -242,4 +294,22 @@ mod test {
         assert!(y > 40.0, "y: {y}");
         assert_eq!(x, 50.0);
     }
+
+    #[test]
+    fn smoke_collides_with_text() {
+        let mut sim = make_sim();
+        sim.config.buoyancy = 1.0;
+        for y in 40..=45 {
+            sim.text.insert((50, y));
+        }
+        add_particle(&mut sim, Vec2::new(50.0, 50.0));
+
+        for _ in 0usize..100 {
+            sim.evolve();
+        }
+
+        let y = sim.particles[0].position.y;
+        assert!(y >= 46.0, "y: {y}");
+        assert!(y < 50.0, "y: {y}");
+    }
 }
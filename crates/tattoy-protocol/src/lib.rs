@@ -61,6 +61,23 @@ pub enum PluginInputMessages {
         /// The number of rows in the new terminal size.
         height: u16,
     },
+    /// The plugin's own settings, from the `config` table of its entry in Tattoy's config. It's
+    /// sent when the plugin starts, but only if there are any settings.
+    Config(serde_json::Value),
+    /// The user's accessibility preferences. It's sent when the plugin starts, but only if any
+    /// preferences are set, and then whenever they change.
+    Accessibility {
+        /// Whether the user wants to avoid animations and moving effects. Plugins should freeze
+        /// their animations, or stop rendering anything that moves, whilst it's `true`.
+        reduce_motion: bool,
+    },
+    /// Sent whenever the user scrolls, so that plugins can move their output along with the text.
+    /// It's only sent to plugins that have `scroll_updates = true` in their config.
+    Scroll {
+        /// The number of lines that the terminal is scrolled up by. It's `0` when the terminal
+        /// isn't scrolling.
+        position: usize,
+    },
 }
 
 /// All the message kinds that the plugin can send to Tattoy.
@@ -222,4 +239,60 @@ mod test {
             serde_json::to_string(&output).unwrap()
         );
     }
+
+    #[test]
+    fn input_config() {
+        let expected = serde_json::json!(
+            {
+                "config": {
+                    "wind": 1.5,
+                }
+            }
+        );
+
+        let output = PluginInputMessages::Config(serde_json::json!({ "wind": 1.5 }));
+
+        assert_eq!(
+            expected.to_string(),
+            serde_json::to_string(&output).unwrap()
+        );
+    }
+
+    #[test]
+    fn input_accessibility() {
+        let expected = serde_json::json!(
+            {
+                "accessibility": {
+                    "reduce_motion": true,
+                }
+            }
+        );
+
+        let output = PluginInputMessages::Accessibility {
+            reduce_motion: true,
+        };
+
+        assert_eq!(
+            expected.to_string(),
+            serde_json::to_string(&output).unwrap()
+        );
+    }
+
+    #[test]
+    fn input_scroll() {
+        let expected = serde_json::json!(
+            {
+                "scroll": {
+                    "position": 3,
+                }
+            }
+        );
+
+        let output = PluginInputMessages::Scroll { position: 3 };
+
+        assert_eq!(
+            expected.to_string(),
+            serde_json::to_string(&output).unwrap()
+        );
+    }
 }
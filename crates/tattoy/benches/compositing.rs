@@ -0,0 +1,100 @@
+//! Benchmarks for the hot paths of rendering: compositing and blending single cells, and
+//! compositing and colour grading whole frames at various terminal sizes.
+//!
+//! Run with `cargo bench --package tattoy`. Criterion saves the results, so running the benchmarks
+//! again after a change reports any difference.
+
+use color_eyre::eyre::Result;
+use criterion::{BenchmarkId, Criterion};
+use tattoy::benchmarks::{blend_all, composite_cells, Frame};
+
+/// Terminal sizes, in columns and rows, from a small pane up to a large full-screen terminal.
+const SIZES: [(u16, u16); 3] = [(80, 24), (200, 60), (400, 120)];
+
+/// The number of tattoys layered into each frame.
+const TATTOY_COUNT: usize = 3;
+
+/// The opacity of the cells being composited.
+const OPACITY: f32 = 0.5;
+
+/// An opaque true colour.
+const fn colour(red: f32, green: f32, blue: f32) -> termwiz::color::ColorAttribute {
+    termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(termwiz::color::SrgbaTuple(
+        red, green, blue, 1.0,
+    ))
+}
+
+/// A cell of text.
+fn text_cell() -> termwiz::cell::Cell {
+    let mut attributes = termwiz::cell::CellAttributes::default();
+    attributes.set_foreground(colour(0.9, 0.9, 0.9));
+    termwiz::cell::Cell::new('a', attributes)
+}
+
+/// A cell with 2 half-block pixels.
+fn pixel_cell() -> termwiz::cell::Cell {
+    let mut attributes = termwiz::cell::CellAttributes::default();
+    attributes.set_foreground(colour(1.0, 0.0, 0.0));
+    attributes.set_background(colour(0.0, 0.0, 1.0));
+    termwiz::cell::Cell::new('▀', attributes)
+}
+
+/// Compositing and blending single cells.
+fn cells(criterion: &mut Criterion) {
+    let text = text_cell();
+    let pixel = pixel_cell();
+    let cases = [
+        ("pixel_over_text", &text, &pixel),
+        ("text_over_pixel", &pixel, &text),
+        ("pixel_over_pixel", &pixel, &pixel),
+    ];
+
+    let mut group = criterion.benchmark_group("cells");
+    for (name, below, above) in cases {
+        group.bench_function(BenchmarkId::new("composite_cells", name), |bencher| {
+            bencher.iter(|| {
+                let mut cell = below.clone();
+                composite_cells(&mut cell, std::hint::black_box(above), OPACITY);
+                cell
+            });
+        });
+        group.bench_function(BenchmarkId::new("blend_all", name), |bencher| {
+            bencher.iter(|| {
+                let mut cell = below.clone();
+                blend_all(&mut cell, std::hint::black_box(above), OPACITY);
+                cell
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Compositing and colour grading whole frames.
+fn frames(criterion: &mut Criterion) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut group = criterion.benchmark_group("frames");
+    for (width, height) in SIZES {
+        let size = format!("{width}x{height}");
+        let mut frame = runtime.block_on(Frame::new(width, height, TATTOY_COUNT))?;
+
+        group.bench_function(BenchmarkId::new("composite", &size), |bencher| {
+            bencher.iter(|| runtime.block_on(frame.composite()));
+        });
+
+        runtime.block_on(frame.composite())?;
+        group.bench_function(BenchmarkId::new("colour_grade", &size), |bencher| {
+            bencher.iter(|| runtime.block_on(frame.colour_grade()));
+        });
+    }
+    group.finish();
+    Ok(())
+}
+
+/// Run all the benchmarks.
+fn main() -> Result<()> {
+    let mut criterion = Criterion::default().configure_from_args();
+    cells(&mut criterion);
+    frames(&mut criterion)?;
+    criterion.final_summary();
+    Ok(())
+}
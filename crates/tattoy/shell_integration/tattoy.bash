@@ -0,0 +1,30 @@
+# Tattoy shell integration for Bash. It reports when commands start and finish (OSC 133) and the
+# current directory (OSC 7), but only inside Tattoy.
+if [ -n "$TATTOY_RUNNING" ]; then
+    __tattoy_command_started=0
+
+    __tattoy_prompt() {
+        local exit_status=$?
+        if [ "$__tattoy_command_started" = 1 ]; then
+            printf '\e]133;D;%s\a' "$exit_status"
+        fi
+        __tattoy_command_started=0
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD"
+        printf '\e]133;A\a'
+    }
+
+    __tattoy_preexec() {
+        # The DEBUG trap also runs for the prompt command and for tab completion.
+        if [ "$__tattoy_command_started" = 1 ] || [ -n "$COMP_LINE" ]; then
+            return
+        fi
+        case "$BASH_COMMAND" in
+        __tattoy_*) return ;;
+        esac
+        __tattoy_command_started=1
+        printf '\e]133;C\a'
+    }
+
+    PROMPT_COMMAND="__tattoy_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+    trap '__tattoy_preexec' DEBUG
+fi
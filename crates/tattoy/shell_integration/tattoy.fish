@@ -0,0 +1,16 @@
+# Tattoy shell integration for Fish. It reports when commands start and finish (OSC 133) and the
+# current directory (OSC 7), but only inside Tattoy.
+if set -q TATTOY_RUNNING
+    function __tattoy_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
+
+    function __tattoy_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+
+    function __tattoy_prompt --on-event fish_prompt
+        printf '\e]7;file://%s%s\a' $hostname $PWD
+        printf '\e]133;A\a'
+    end
+end
@@ -0,0 +1,26 @@
+# Tattoy shell integration for PowerShell. It reports when commands start and finish (OSC 133) and
+# the current directory (OSC 7), but only inside Tattoy.
+if ($env:TATTOY_RUNNING) {
+    $Global:__TattoyOriginalPrompt = $function:prompt
+
+    function Global:prompt {
+        $exitCode = if ($?) { 0 } else { 1 }
+        $esc = [char]27
+        $bel = [char]7
+        $location = $ExecutionContext.SessionState.Path.CurrentLocation
+        $marks = "$esc]133;D;$exitCode$bel"
+        if ($location.Provider.Name -eq "FileSystem") {
+            $path = $location.ProviderPath -replace '\\', '/'
+            $marks += "$esc]7;file://$([System.Net.Dns]::GetHostName())/$($path.TrimStart('/'))$bel"
+        }
+        $marks += "$esc]133;A$bel"
+        $marks + (& $Global:__TattoyOriginalPrompt)
+    }
+
+    if (Get-Module PSReadLine) {
+        Set-PSReadLineKeyHandler -Chord Enter -ScriptBlock {
+            [Microsoft.PowerShell.PSConsoleReadLine]::AcceptLine()
+            [Console]::Write("$([char]27)]133;C$([char]7)")
+        }
+    }
+}
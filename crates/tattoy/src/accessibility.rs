@@ -0,0 +1,47 @@
+//! Accessibility settings that apply across all of Tattoy. Reducing motion freezes shader
+//! animations, disables the particle and trail based tattoys, and makes transitions instant, whilst
+//! keeping all of Tattoy's static colours. Plugins are told about it too, so that they can comply.
+
+/// User-configurable accessibility settings.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Avoid animations and moving effects, eg for users who are sensitive to motion.
+    pub reduce_motion: bool,
+}
+
+/// Disable, or freeze, everything that moves.
+pub(crate) fn reduce_motion(config: &mut crate::config::main::Config) {
+    config.typing_sparks.enabled = false;
+    config.cursor_smear.enabled = false;
+    config.starfield.enabled = false;
+    config.random_walker.enabled = false;
+    config.audio_visualiser.enabled = false;
+    config.screensaver.enabled = false;
+
+    config.minimap.animation_speed = 1.0;
+    config.fade_duration = 0.0;
+    config.visual_bell.style = crate::tattoys::visual_bell::Style::Border;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reducing_motion() {
+        let mut config = crate::config::main::Config::default();
+        config.starfield.enabled = true;
+        config.visual_bell.style = crate::tattoys::visual_bell::Style::Flash;
+        config.text_contrast.enabled = true;
+
+        reduce_motion(&mut config);
+        assert!(!config.starfield.enabled);
+        assert!(!config.typing_sparks.enabled);
+        assert_eq!(
+            config.visual_bell.style,
+            crate::tattoys::visual_bell::Style::Border
+        );
+        assert!(config.text_contrast.enabled);
+    }
+}
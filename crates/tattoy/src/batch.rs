@@ -0,0 +1,384 @@
+//! Batch rendering, with `tattoy run --once`. A command is run in the shadow terminal, without any
+//! of the user's input, and once it exits, the final composited frame is written out as either
+//! ANSI or a PNG. It's useful for making styled screenshots of a command's output in scripts and
+//! CI.
+//!
+//! The PNGs don't have a font, so text is drawn as small blocks of its colour. Tattoys' pixels,
+//! which are drawn with half blocks, are drawn exactly.
+
+use color_eyre::eyre::{Result, WrapErr as _};
+use std::io::Write as _;
+
+/// How long to wait for the renderer to paint the final frame.
+const FINAL_FRAME_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// The width of each cell in a PNG, in pixels.
+const CELL_WIDTH: u32 = 8;
+
+/// The height of each cell in a PNG, in pixels. Each half of the cell is one of Tattoy's pixels.
+const CELL_HEIGHT: u32 = 16;
+
+/// The part of a cell's width, in pixels, either side of the block that represents text.
+const TEXT_MARGIN_X: u32 = 1;
+
+/// The part of a cell's height, in pixels, above and below the block that represents text.
+const TEXT_MARGIN_Y: u32 = 4;
+
+/// The formats that a frame can be written as.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// Text with true colour escape sequences, eg for `cat`ing in another terminal.
+    Ansi,
+    /// A PNG image.
+    Png,
+}
+
+/// Settings for batch rendering.
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct Options {
+    /// The format to write the frame as. Defaults to PNG when the output file ends in `.png`,
+    /// otherwise ANSI.
+    #[arg(long, value_enum)]
+    pub format: Option<Format>,
+    /// Write the frame to a file rather than to STDOUT.
+    #[arg(long, value_name = "Path to output file")]
+    pub output: Option<std::path::PathBuf>,
+    /// The width of the terminal that the command runs in.
+    #[arg(long, default_value_t = 80)]
+    pub width: u16,
+    /// The height of the terminal that the command runs in.
+    #[arg(long, default_value_t = 24)]
+    pub height: u16,
+    /// How long, in milliseconds, to keep rendering after the command exits, so that animated
+    /// tattoys and shaders have time to settle.
+    #[arg(long, default_value_t = 500)]
+    pub wait: u64,
+}
+
+impl Options {
+    /// The format to write the frame as.
+    fn format(&self) -> Format {
+        self.format.unwrap_or_else(|| {
+            let is_png = self
+                .output
+                .as_ref()
+                .and_then(|path| path.extension())
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+            if is_png {
+                Format::Png
+            } else {
+                Format::Ansi
+            }
+        })
+    }
+}
+
+/// The colours and styles of a cell, with all palette colours converted to true colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Style {
+    /// The text colour. The default text colour when not set.
+    fg: Option<(u8, u8, u8)>,
+    /// The background colour. The default background colour when not set.
+    bg: Option<(u8, u8, u8)>,
+    /// Whether the text is bold.
+    is_bold: bool,
+    /// Whether the text is italic.
+    is_italic: bool,
+    /// Whether the text is underlined.
+    is_underlined: bool,
+}
+
+impl Style {
+    /// The style of a cell.
+    fn of(
+        attributes: &termwiz::cell::CellAttributes,
+        palette: &crate::palette::converter::Palette,
+    ) -> Self {
+        let (mut fg, mut bg) = (attributes.foreground(), attributes.background());
+        if attributes.reverse() {
+            core::mem::swap(&mut fg, &mut bg);
+        }
+        Self {
+            fg: true_colour(fg, palette),
+            bg: true_colour(bg, palette),
+            is_bold: attributes.intensity() == termwiz::cell::Intensity::Bold,
+            is_italic: attributes.italic(),
+            is_underlined: attributes.underline() != termwiz::cell::Underline::None,
+        }
+    }
+
+    /// The SGR escape sequence that sets the style, from a reset state.
+    fn sgr(&self) -> String {
+        let mut parameters = vec!["0".to_owned()];
+        if self.is_bold {
+            parameters.push("1".to_owned());
+        }
+        if self.is_italic {
+            parameters.push("3".to_owned());
+        }
+        if self.is_underlined {
+            parameters.push("4".to_owned());
+        }
+        if let Some((red, green, blue)) = self.fg {
+            parameters.push(format!("38;2;{red};{green};{blue}"));
+        }
+        if let Some((red, green, blue)) = self.bg {
+            parameters.push(format!("48;2;{red};{green};{blue}"));
+        }
+        format!("\x1b[{}m", parameters.join(";"))
+    }
+}
+
+/// The true colour of a colour attribute. Default colours are left to whatever displays the frame.
+fn true_colour(
+    attribute: termwiz::color::ColorAttribute,
+    palette: &crate::palette::converter::Palette,
+) -> Option<(u8, u8, u8)> {
+    let colour = match attribute {
+        termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(colour, _)
+        | termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(colour) => colour,
+        termwiz::color::ColorAttribute::PaletteIndex(index) => {
+            palette.true_colour_tuple_from_index(index)
+        }
+        termwiz::color::ColorAttribute::Default => return None,
+    };
+    Some(rgb(colour))
+}
+
+/// Convert a colour to 8 bit RGB.
+fn rgb(colour: termwiz::color::SrgbaTuple) -> (u8, u8, u8) {
+    let (red, green, blue, _) = colour.to_srgb_u8();
+    (red, green, blue)
+}
+
+/// Encode a frame as text with true colour escape sequences.
+fn ansi(
+    surface: &termwiz::surface::Surface,
+    palette: &crate::palette::converter::Palette,
+) -> String {
+    let mut output = String::new();
+    for line in surface.screen_lines() {
+        let mut current: Option<Style> = None;
+        for cell in line.visible_cells() {
+            let style = Style::of(cell.attrs(), palette);
+            if current != Some(style) {
+                output.push_str(&style.sgr());
+                current = Some(style);
+            }
+            output.push_str(cell.str());
+        }
+        output.push_str("\x1b[0m\n");
+    }
+    output
+}
+
+/// Encode a frame as a PNG.
+fn png(
+    surface: &termwiz::surface::Surface,
+    palette: &crate::palette::converter::Palette,
+) -> Result<Vec<u8>> {
+    let default_fg = rgb(palette.default_foreground_colour());
+    let default_bg = rgb(palette.default_background_colour());
+
+    let (width, height) = surface.dimensions();
+    let mut image = image::RgbImage::from_pixel(
+        u32::try_from(width)? * CELL_WIDTH,
+        u32::try_from(height)? * CELL_HEIGHT,
+        image::Rgb([default_bg.0, default_bg.1, default_bg.2]),
+    );
+
+    for (row, line) in (0u32..).zip(surface.screen_lines()) {
+        for cell in line.visible_cells() {
+            let style = Style::of(cell.attrs(), palette);
+            let fg = style.fg.unwrap_or(default_fg);
+            let bg = style.bg.unwrap_or(default_bg);
+            let (top, bottom, text) = match cell.str() {
+                "▀" => (fg, bg, None),
+                "▄" => (bg, fg, None),
+                "█" => (fg, fg, None),
+                "" | " " => (bg, bg, None),
+                _ => (bg, bg, Some(fg)),
+            };
+
+            let left = u32::try_from(cell.cell_index())? * CELL_WIDTH;
+            let top_edge = row * CELL_HEIGHT;
+            for y in 0..CELL_HEIGHT {
+                for x in 0..CELL_WIDTH {
+                    let is_text = (TEXT_MARGIN_X..CELL_WIDTH - TEXT_MARGIN_X).contains(&x)
+                        && (TEXT_MARGIN_Y..CELL_HEIGHT - TEXT_MARGIN_Y).contains(&y);
+                    let colour = match text {
+                        Some(text_colour) if is_text => text_colour,
+                        _ if y < CELL_HEIGHT / 2 => top,
+                        _ => bottom,
+                    };
+                    if let Some(pixel) = image.get_pixel_mut_checked(left + x, top_edge + y) {
+                        *pixel = image::Rgb([colour.0, colour.1, colour.2]);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut bytes = Vec::new();
+    image.write_to(
+        &mut std::io::Cursor::new(&mut bytes),
+        image::ImageFormat::Png,
+    )?;
+    Ok(bytes)
+}
+
+/// Run a command in the shadow terminal, render it with all the enabled tattoys and write out the
+/// final frame.
+pub(crate) async fn run(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    options: &Options,
+    command: Vec<String>,
+    enabled_tattoys: Vec<String>,
+) -> Result<()> {
+    if !crate::palette::parser::Parser::palette_config_exists(state).await {
+        color_eyre::eyre::bail!(
+            "Batch rendering needs the terminal's palette. Start Tattoy normally once to detect \
+             it, or import one with `tattoy palette import`."
+        );
+    }
+    let palette = crate::config::main::Config::load_palette(std::sync::Arc::clone(state)).await?;
+    let format = options.format();
+
+    // The frame is always written with true colours, so it doesn't matter what the current
+    // terminal supports.
+    state.is_true_colour.store(true);
+    crate::config::main::Config::load_config_into_shared_state(state).await?;
+    state.set_tty_size(options.width, options.height).await;
+
+    let protocol_tx = state.protocol_tx.clone();
+    let mut frames = state.streamed_frame.subscribe();
+    let (renderer, surfaces_tx) =
+        crate::renderer::Renderer::start(std::sync::Arc::clone(state), protocol_tx.clone(), false);
+    let tattoys_handle = crate::loader::start_tattoys(
+        enabled_tattoys,
+        surfaces_tx.clone(),
+        std::sync::Arc::clone(state),
+    );
+
+    let scrollback_size = state.config.load().scrollback_size;
+    let shadow_terminal_config = shadow_terminal::shadow_terminal::Config {
+        width: options.width,
+        height: options.height,
+        command: command.into_iter().map(Into::into).collect(),
+        scrollback_size: scrollback_size.try_into()?,
+        ..Default::default()
+    };
+    let proxy_result = crate::terminal_proxy::proxy::Proxy::start(
+        std::sync::Arc::clone(state),
+        surfaces_tx,
+        protocol_tx.clone(),
+        shadow_terminal_config,
+    )
+    .await;
+
+    tokio::time::sleep(std::time::Duration::from_millis(options.wait)).await;
+    frames.mark_unchanged();
+    protocol_tx.send(crate::run::Protocol::Repaint)?;
+    if tokio::time::timeout(FINAL_FRAME_TIMEOUT, frames.changed())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Timed out waiting for the final frame, using the latest one instead");
+    }
+    let frame = std::sync::Arc::clone(&frames.borrow_and_update());
+
+    crate::run::broadcast_protocol_end(&protocol_tx);
+    renderer.await??;
+    proxy_result?;
+    tattoys_handle
+        .join()
+        .map_err(|err| color_eyre::eyre::eyre!("Tattoys handle: {err:?}"))??;
+
+    let bytes = match format {
+        Format::Ansi => ansi(&frame.surface, &palette).into_bytes(),
+        Format::Png => png(&frame.surface, &palette)?,
+    };
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, bytes)
+                .with_context(|| format!("Writing frame to {}", path.display()))?;
+            tracing::info!("Frame written as {format:?} to: {path:?}");
+        }
+        None => std::io::stdout().write_all(&bytes)?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn palette() -> crate::palette::converter::Palette {
+        crate::palette::converter::Palette {
+            map: (0..=255)
+                .map(|index| (index.to_string(), (index, index, index)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn inferring_the_format() {
+        let mut options = Options {
+            format: None,
+            output: None,
+            width: 80,
+            height: 24,
+            wait: 0,
+        };
+        assert_eq!(options.format(), Format::Ansi);
+
+        options.output = Some("screenshot.PNG".into());
+        assert_eq!(options.format(), Format::Png);
+
+        options.format = Some(Format::Ansi);
+        assert_eq!(options.format(), Format::Ansi);
+    }
+
+    #[test]
+    fn encoding_ansi() {
+        let mut surface = termwiz::surface::Surface::new(4, 2);
+        surface.add_change(termwiz::surface::Change::Attribute(
+            termwiz::cell::AttributeChange::Foreground(
+                termwiz::color::ColorAttribute::PaletteIndex(1),
+            ),
+        ));
+        surface.add_change("ab");
+        surface.add_change(termwiz::surface::Change::AllAttributes(
+            termwiz::cell::CellAttributes::default(),
+        ));
+        surface.add_change("cd");
+
+        let output = ansi(&surface, &palette());
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "\x1b[0;38;2;1;1;1mab\x1b[0mcd\x1b[0m");
+        assert_eq!(lines[1], "\x1b[0m    \x1b[0m");
+    }
+
+    #[test]
+    fn encoding_png() {
+        let mut surface = termwiz::surface::Surface::new(2, 1);
+        surface.add_change(termwiz::surface::Change::Attribute(
+            termwiz::cell::AttributeChange::Foreground(
+                termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(
+                    termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0),
+                ),
+            ),
+        ));
+        surface.add_change("▀");
+
+        let bytes = png(&surface, &palette()).unwrap();
+        let image = image::load_from_memory(&bytes).unwrap().into_rgb8();
+        assert_eq!(image.dimensions(), (2 * CELL_WIDTH, CELL_HEIGHT));
+        assert_eq!(image.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+        assert_eq!(
+            image.get_pixel(0, CELL_HEIGHT - 1),
+            image.get_pixel(CELL_WIDTH, 0)
+        );
+    }
+}
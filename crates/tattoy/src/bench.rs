@@ -0,0 +1,408 @@
+//! `tattoy bench`: benchmark the whole rendering pipeline, headlessly. The renderer, the selected
+//! tattoys and a shadow terminal running a synthetic workload are all started, just like a normal
+//! Tattoy, but frames are painted to a null terminal rather than the user's terminal. The report's
+//! format is stable, so reports can be saved and compared across commits.
+//!
+//! The numbers themselves depend on the machine, and on the config, because the tattoys enabled in
+//! it run alongside those chosen with `--use`. `--default-config` ignores the user's config, so
+//! that runs on different machines are more comparable.
+//!
+//! The null terminal is a surface that keeps what a real terminal would be showing. Painting to
+//! it does the same diffing as painting to a real terminal, without the cost of writing to one.
+
+use color_eyre::eyre::Result;
+
+/// The default workload. It prints coloured, scrolling lines as fast as it can, so that the PTY
+/// is always sending updates.
+const WORKLOAD: &str = "i=0; while :; do i=$((i + 1)); \
+     printf '\\033[3%dm%08d \\033[1mTattoy benchmark\\033[0m %s\\n' $((i % 8)) $i \
+     'The quick brown fox jumps over the lazy dog'; done";
+
+/// The longest time to wait for any single frame, including the first one.
+const FRAME_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Settings for benchmarking.
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct Options {
+    /// How many frames to paint.
+    #[arg(long, default_value_t = 300)]
+    pub frames: usize,
+    /// The width of the headless terminal.
+    #[arg(long, default_value_t = 120)]
+    pub width: u16,
+    /// The height of the headless terminal.
+    #[arg(long, default_value_t = 40)]
+    pub height: u16,
+    /// Print the report as JSON, for saving and comparing with other runs.
+    #[arg(long)]
+    pub json: bool,
+    /// Use Tattoy's default config rather than yours, so that runs on different machines can be
+    /// compared. The terminal's palette is still yours.
+    #[arg(long)]
+    pub default_config: bool,
+}
+
+/// The results of a benchmark.
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct Report {
+    /// The version of Tattoy that was benchmarked.
+    pub version: String,
+    /// The width of the headless terminal.
+    pub width: u16,
+    /// The height of the headless terminal.
+    pub height: u16,
+    /// The tattoys that were enabled with `--use`, on top of those enabled in the config.
+    pub tattoys: Vec<String>,
+    /// Whether Tattoy's default config was used, rather than the user's.
+    pub default_config: bool,
+    /// How long the benchmark took, in seconds.
+    pub seconds: f64,
+    /// How many frames the renderer composited.
+    pub composited_frames: u64,
+    /// How many frames were composited per second.
+    pub frames_per_second: f64,
+    /// The average time to composite a frame.
+    pub composite_average_ms: f64,
+    /// How many frames were painted to the null terminal. The renderer can composite frames
+    /// faster than they're painted, in which case only the latest frame is painted.
+    pub painted_frames: usize,
+    /// The median time to paint a frame.
+    pub paint_p50_ms: f64,
+    /// The time that 90% of frames were painted within.
+    pub paint_p90_ms: f64,
+    /// The time that 99% of frames were painted within.
+    pub paint_p99_ms: f64,
+    /// The slowest paint.
+    pub paint_max_ms: f64,
+    /// How many frame updates each tattoy, and the PTY, sent per second.
+    pub updates_per_second: std::collections::BTreeMap<String, f64>,
+}
+
+/// Everything measured during a benchmark.
+struct Measurements {
+    /// How long the benchmark took.
+    elapsed: std::time::Duration,
+    /// How long each frame took to paint to the null terminal.
+    paints: Vec<std::time::Duration>,
+    /// The renderer's statistics when the first frame was painted.
+    start: crate::performance::Snapshot,
+    /// The renderer's statistics when the last frame was painted.
+    end: crate::performance::Snapshot,
+}
+
+/// A duration in milliseconds.
+fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Report {
+    /// Summarise the measurements. Everything the renderer did before the first frame, like
+    /// starting up, isn't counted.
+    fn new(options: &Options, tattoys: Vec<String>, measurements: &Measurements) -> Result<Self> {
+        let Some(paint) = crate::latency::Percentiles::from_samples(&measurements.paints) else {
+            color_eyre::eyre::bail!("No frames were painted");
+        };
+        let (start, end) = (&measurements.start, &measurements.end);
+        let seconds = measurements.elapsed.as_secs_f64().max(f64::EPSILON);
+        let composited_frames = end
+            .composite_histogram
+            .count
+            .saturating_sub(start.composite_histogram.count);
+        let composite_total_ms = end.composite_histogram.sum_ms - start.composite_histogram.sum_ms;
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "There are never enough frames or updates to lose precision"
+        )]
+        let as_float = |count: u64| count as f64;
+        let per_second = |count: u64| as_float(count) / seconds;
+        let updates_per_second = end
+            .updates_total
+            .iter()
+            .map(|(id, total)| {
+                let before = start.updates_total.get(id).copied().unwrap_or_default();
+                (id.clone(), per_second(total.saturating_sub(before)))
+            })
+            .collect();
+
+        Ok(Self {
+            version: std::env!("CARGO_PKG_VERSION").to_owned(),
+            width: options.width,
+            height: options.height,
+            tattoys,
+            default_config: options.default_config,
+            seconds,
+            composited_frames,
+            frames_per_second: per_second(composited_frames),
+            composite_average_ms: composite_total_ms / as_float(composited_frames.max(1)),
+            painted_frames: measurements.paints.len(),
+            paint_p50_ms: millis(paint.p50),
+            paint_p90_ms: millis(paint.p90),
+            paint_p99_ms: millis(paint.p99),
+            paint_max_ms: millis(paint.max),
+            updates_per_second,
+        })
+    }
+
+    /// The report for people to read.
+    fn describe(&self) -> String {
+        let config = if self.default_config {
+            "the default config"
+        } else {
+            "your config"
+        };
+        let tattoys = if self.tattoys.is_empty() {
+            format!("those enabled in {config}")
+        } else {
+            format!("{}, and those enabled in {config}", self.tattoys.join(", "))
+        };
+        let updates = self
+            .updates_per_second
+            .iter()
+            .map(|(id, rate)| format!("{id} {rate:.1}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        [
+            format!(
+                "Tattoy v{} at {}x{}, with tattoys: {tattoys}",
+                self.version, self.width, self.height
+            ),
+            format!(
+                "Frames:             {} in {:.2}s, {:.1} per second",
+                self.composited_frames, self.seconds, self.frames_per_second
+            ),
+            format!(
+                "Composite:          {:.2}ms average",
+                self.composite_average_ms
+            ),
+            format!(
+                "Paint:              p50 {:.2}ms, p90 {:.2}ms, p99 {:.2}ms, max {:.2}ms",
+                self.paint_p50_ms, self.paint_p90_ms, self.paint_p99_ms, self.paint_max_ms
+            ),
+            format!("Updates per second: {updates}"),
+        ]
+        .join("\n")
+    }
+}
+
+/// Paint frames to the null terminal as they're composited, until enough have been painted.
+async fn measure(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    options: &Options,
+    frames: &mut tokio::sync::watch::Receiver<std::sync::Arc<crate::streaming::Frame>>,
+) -> Result<Measurements> {
+    let mut terminal =
+        termwiz::surface::Surface::new(usize::from(options.width), usize::from(options.height));
+    let mut paints = Vec::with_capacity(options.frames);
+    let mut maybe_start = None;
+
+    while paints.len() < options.frames {
+        tokio::time::timeout(FRAME_TIMEOUT, frames.changed())
+            .await
+            .map_err(|_| {
+                color_eyre::eyre::eyre!(
+                    "No frame within {FRAME_TIMEOUT:?}, after painting {}",
+                    paints.len()
+                )
+            })??;
+        let frame = std::sync::Arc::clone(&frames.borrow_and_update());
+
+        // The first frame only marks the start, so that startup isn't counted.
+        if maybe_start.is_none() {
+            let snapshot = state.performance.read().await.snapshot();
+            maybe_start = Some((std::time::Instant::now(), snapshot));
+            continue;
+        }
+
+        let started_at = std::time::Instant::now();
+        let changes = terminal.diff_screens(&frame.surface);
+        terminal.add_changes(changes);
+        paints.push(started_at.elapsed());
+    }
+
+    let Some((started_at, start)) = maybe_start else {
+        color_eyre::eyre::bail!("No frames were painted");
+    };
+    Ok(Measurements {
+        elapsed: started_at.elapsed(),
+        paints,
+        start,
+        end: state.performance.read().await.snapshot(),
+    })
+}
+
+/// Run the benchmark and print the report.
+#[expect(
+    clippy::print_stdout,
+    reason = "The report is for the user to read, or to save for comparing"
+)]
+pub(crate) async fn run(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    options: &Options,
+    command: Vec<String>,
+    enabled_tattoys: Vec<String>,
+) -> Result<()> {
+    if !crate::palette::parser::Parser::palette_config_exists(state).await {
+        color_eyre::eyre::bail!(
+            "Benchmarking needs the terminal's palette. Start Tattoy normally once to detect it, \
+             or import one with `tattoy palette import`."
+        );
+    }
+
+    // Benchmarks should be comparable between machines, whatever the current terminal supports,
+    // and whether or not Tattoy was last toggled off.
+    state.is_true_colour.store(true);
+    state.is_rendering_enabled.store(true);
+    if options.default_config {
+        state
+            .config
+            .store(crate::config::main::Config::parse_default_config()?);
+    } else {
+        crate::config::main::Config::load_config_into_shared_state(state).await?;
+    }
+    state.set_tty_size(options.width, options.height).await;
+
+    let command = if command.is_empty() {
+        vec!["sh".to_owned(), "-c".to_owned(), WORKLOAD.to_owned()]
+    } else {
+        command
+    };
+    if !options.json {
+        println!(
+            "Painting {} frames of `{}`...",
+            options.frames,
+            command.join(" ")
+        );
+    }
+
+    let protocol_tx = state.protocol_tx.clone();
+    let mut frames = state.streamed_frame.subscribe();
+    let (renderer, surfaces_tx) =
+        crate::renderer::Renderer::start(std::sync::Arc::clone(state), protocol_tx.clone(), false);
+    let tattoys_handle = crate::loader::start_tattoys(
+        enabled_tattoys.clone(),
+        surfaces_tx.clone(),
+        std::sync::Arc::clone(state),
+    );
+
+    let scrollback_size = state.config.load().scrollback_size;
+    let shadow_terminal_config = shadow_terminal::shadow_terminal::Config {
+        width: options.width,
+        height: options.height,
+        command: command.into_iter().map(Into::into).collect(),
+        scrollback_size: scrollback_size.try_into()?,
+        ..Default::default()
+    };
+    let proxy_result = crate::terminal_proxy::proxy::Proxy::start(
+        std::sync::Arc::clone(state),
+        surfaces_tx,
+        protocol_tx.clone(),
+        shadow_terminal_config,
+    )
+    .await;
+
+    let result = measure(state, options, &mut frames).await;
+
+    crate::run::broadcast_protocol_end(&protocol_tx);
+    renderer.await??;
+    proxy_result?;
+    tattoys_handle
+        .join()
+        .map_err(|err| color_eyre::eyre::eyre!("Tattoys handle: {err:?}"))??;
+
+    let report = Report::new(options, enabled_tattoys, &result?)?;
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report.describe());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options() -> Options {
+        Options {
+            frames: 3,
+            width: 80,
+            height: 24,
+            json: false,
+            default_config: false,
+        }
+    }
+
+    fn snapshot(frames: u64, total_ms: f64, pty_updates: u64) -> crate::performance::Snapshot {
+        crate::performance::Snapshot {
+            composite_histogram: crate::performance::Histogram {
+                count: frames,
+                sum_ms: total_ms,
+                ..Default::default()
+            },
+            updates_total: [(crate::performance::PTY_ID.to_owned(), pty_updates)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn startup_isnt_counted() {
+        let measurements = Measurements {
+            elapsed: std::time::Duration::from_secs(2),
+            paints: [1, 2, 3].map(std::time::Duration::from_millis).to_vec(),
+            start: snapshot(10, 50.0, 4),
+            end: snapshot(110, 250.0, 204),
+        };
+        let report = Report::new(&options(), vec!["minimap".to_owned()], &measurements).unwrap();
+        assert_eq!(report.composited_frames, 100);
+        assert!((report.frames_per_second - 50.0).abs() < 0.001);
+        assert!((report.composite_average_ms - 2.0).abs() < 0.001);
+        assert_eq!(report.painted_frames, 3);
+        assert!((report.paint_p50_ms - 2.0).abs() < 0.001);
+        assert!((report.paint_max_ms - 3.0).abs() < 0.001);
+        assert_eq!(
+            report.updates_per_second.get(crate::performance::PTY_ID),
+            Some(&100.0)
+        );
+    }
+
+    #[test]
+    fn no_frames_is_an_error() {
+        let measurements = Measurements {
+            elapsed: std::time::Duration::from_secs(1),
+            paints: Vec::new(),
+            start: snapshot(0, 0.0, 0),
+            end: snapshot(0, 0.0, 0),
+        };
+        assert!(Report::new(&options(), Vec::new(), &measurements).is_err());
+    }
+
+    #[test]
+    fn describing_the_report() {
+        let measurements = Measurements {
+            elapsed: std::time::Duration::from_secs(1),
+            paints: vec![std::time::Duration::from_millis(1)],
+            start: snapshot(0, 0.0, 0),
+            end: snapshot(60, 120.0, 30),
+        };
+        let report = Report::new(&options(), Vec::new(), &measurements).unwrap();
+        let description = report.describe();
+        assert!(description.contains("80x24, with tattoys: those enabled in your config"));
+        assert!(description.contains("60 in 1.00s, 60.0 per second"));
+        assert!(description.contains("2.00ms average"));
+        assert!(description.contains("pty 30.0"));
+
+        let default = Report {
+            tattoys: vec!["minimap".to_owned()],
+            default_config: true,
+            ..report
+        };
+        assert!(default
+            .describe()
+            .contains("with tattoys: minimap, and those enabled in the default config"));
+    }
+}
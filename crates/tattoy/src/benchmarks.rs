@@ -0,0 +1,114 @@
+//! Entrypoints for the Criterion benchmarks in `benches/`. Everything else in Tattoy is private to
+//! the crate, so these thin wrappers expose just the hot paths of rendering. They give
+//! performance-oriented refactors, like damage tracking or GPU compositing, a baseline to compare
+//! against.
+
+use color_eyre::eyre::Result;
+use termwiz::surface::{Change as TermwizChange, Position as TermwizPosition};
+
+/// The opacity of the tattoys in a benchmark frame. Anything less than 1.0 means that every cell
+/// has to be blended.
+const TATTOY_OPACITY: f32 = 0.5;
+
+/// Composite one cell onto another, see `Compositor::composite_cells`.
+#[inline]
+pub fn composite_cells(
+    composited_cell: &mut termwiz::cell::Cell,
+    cell_above: &termwiz::cell::Cell,
+    opacity: f32,
+) {
+    crate::compositor::Compositor::composite_cells(
+        composited_cell,
+        cell_above,
+        opacity,
+        crate::blender::DEFAULT_COLOUR,
+    );
+}
+
+/// Blend the colours of one cell onto another, see `Blender::blend_all`.
+#[inline]
+pub fn blend_all(cell: &mut termwiz::cell::Cell, cell_above: &termwiz::cell::Cell, opacity: f32) {
+    let mut blender = crate::blender::Blender::new(cell, None, opacity);
+    blender.blend_all(cell_above);
+}
+
+/// A whole frame of the user's terminal: a PTY full of text with tattoys of pixels layered below
+/// and above it.
+#[non_exhaustive]
+pub struct Frame {
+    /// The renderer that does the compositing.
+    renderer: crate::renderer::Renderer,
+}
+
+impl Frame {
+    /// Make a frame of the given size, in columns and rows, with the given number of
+    /// full-screen tattoys.
+    ///
+    /// # Errors
+    /// If the shared state or renderer couldn't be setup.
+    #[inline]
+    pub async fn new(width: u16, height: u16, tattoy_count: usize) -> Result<Self> {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
+        let state = crate::shared_state::SharedState::init(width, height, protocol_tx).await?;
+        state.is_rendering_enabled.store(true);
+        let mut renderer = crate::renderer::Renderer::new(state, false).await?;
+
+        let columns = usize::from(width);
+        let rows = usize::from(height);
+        let line: String = "Lorem ipsum dolor sit amet. "
+            .chars()
+            .cycle()
+            .take(columns)
+            .collect();
+        for row in 0..rows {
+            renderer.pty.add_change(TermwizChange::CursorPosition {
+                x: TermwizPosition::Absolute(0),
+                y: TermwizPosition::Absolute(row),
+            });
+            renderer.pty.add_change(line.clone());
+        }
+
+        for index in 0..tattoy_count {
+            // Alternate the tattoys between being below and above the PTY.
+            let layer = if index.rem_euclid(2) == 0 { -1 } else { 1 };
+            let mut tattoy = crate::surface::Surface::new(
+                format!("benchmark_{index}"),
+                columns,
+                rows,
+                layer,
+                TATTOY_OPACITY,
+            );
+            for y in 0..rows * 2 {
+                for x in 0..columns {
+                    let colour = if (x + y + index).rem_euclid(2) == 0 {
+                        crate::surface::RED
+                    } else {
+                        crate::surface::WHITE
+                    };
+                    tattoy.add_pixel(x, y, colour)?;
+                }
+            }
+            renderer.tattoys.insert(tattoy.id.clone(), tattoy);
+        }
+
+        Ok(Self { renderer })
+    }
+
+    /// Composite the PTY and all the tattoys into a single frame, just like a real render.
+    ///
+    /// # Errors
+    /// If any of the layers couldn't be composited.
+    #[inline]
+    pub async fn composite(&mut self) -> Result<()> {
+        self.renderer.composite().await
+    }
+
+    /// Apply the colour grading to the already composited frame.
+    ///
+    /// # Errors
+    /// If the colours couldn't be graded.
+    #[inline]
+    pub async fn colour_grade(&mut self) -> Result<()> {
+        self.renderer.colour_grade().await
+    }
+}
@@ -9,9 +9,9 @@ use termwiz::cell::Cell;
 
 /// This is the default colour for when an opaque cell is over a "blank" cell.
 ///
-/// In Tattoy, a blank cell is any cell that has the default terminal colour. Most terminals use a
-/// dark theme, so let's say that, when alpha blending, the default colour is pure black.
-/// TODO: support light theme terminals.
+/// In Tattoy, a blank cell is any cell that has the default terminal colour. Where possible the
+/// actual default background colour of the user's terminal is used instead. But when that isn't
+/// known, most terminals use a dark theme, so let's say that the default colour is pure black.
 pub const DEFAULT_COLOUR: termwiz::color::SrgbaTuple =
     termwiz::color::SrgbaTuple(0.0, 0.0, 0.0, 1.0);
 
@@ -104,7 +104,7 @@ impl<'cell> Blender<'cell> {
     }
 
     /// Blend the cell's colours with the cell above.
-    pub fn blend_all(&mut self, cell_above: &Cell) {
+    pub fn blend_all<C: crate::compositor::CellAbove>(&mut self, cell_above: &C) {
         let character_above = cell_above.str();
         let character_above_is_empty = character_above.is_empty() || character_above == " ";
         if character_above_is_empty {
@@ -165,29 +165,32 @@ impl<'cell> Blender<'cell> {
             return;
         }
 
-        let maybe_maxed_out_lightness =
-            self.find_and_set_min_contrast(fg_original, bg, target_contrast, true);
-        if let Some(lightest) = maybe_maxed_out_lightness {
-            let maybe_maxed_out_darkness =
-                self.find_and_set_min_contrast(fg_original, bg, target_contrast, false);
-            if let Some(darkest) = maybe_maxed_out_darkness {
-                let lightest_contrast = bg.relative_contrast(lightest.into_color());
-                let darkest_contrast = bg.relative_contrast(darkest.into_color());
-                if lightest_contrast >= darkest_contrast {
-                    self.set_colour_from_rgba(lightest);
-                    tracing::trace!(
-                        "Contrast for {} not reached, setting to max contrast +{lightest_contrast}",
-                        self.cell.str()
-                    );
-                } else {
-                    self.set_colour_from_rgba(darkest);
-                    tracing::trace!(
-                        "Contrast for {} not reached, setting to max contrast -{darkest_contrast}",
-                        self.cell.str()
-                    );
-                }
-            }
-        }
+        // First try moving the text colour away from the background. So on dark backgrounds text
+        // is lightened and on light backgrounds it is darkened.
+        let is_lighten_first = !crate::palette::converter::Palette::is_light_colour(bg_raw);
+        let Some(first) =
+            self.find_and_set_min_contrast(fg_original, bg, target_contrast, is_lighten_first)
+        else {
+            return;
+        };
+        let Some(second) =
+            self.find_and_set_min_contrast(fg_original, bg, target_contrast, !is_lighten_first)
+        else {
+            return;
+        };
+
+        let first_contrast = bg.relative_contrast(first.into_color());
+        let second_contrast = bg.relative_contrast(second.into_color());
+        let (best, best_contrast) = if first_contrast >= second_contrast {
+            (first, first_contrast)
+        } else {
+            (second, second_contrast)
+        };
+        self.set_colour_from_rgba(best);
+        tracing::trace!(
+            "Contrast for {} not reached, setting to max contrast {best_contrast}",
+            self.cell.str()
+        );
     }
 
     /// Find the foreground colour that achieves the target contrast.
@@ -255,14 +258,16 @@ mod test {
         let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
             .await
             .unwrap();
-        state.config.write().await.show_tattoy_indicator = false;
+        state
+            .config
+            .modify(|config| config.show_tattoy_indicator = false);
         let renderer = crate::renderer::Renderer {
             width: 1,
             height: 1,
             is_cursor_visible: false,
             ..crate::renderer::Renderer::new(state, false).await.unwrap()
         };
-        *renderer.state.is_rendering_enabled.write().await = true;
+        renderer.state.is_rendering_enabled.store(true);
         renderer
     }
 
@@ -0,0 +1,316 @@
+//! Probing the user's terminal for the features that it supports, so that the renderer and tattoys
+//! can make use of them only when they're available.
+//!
+//! The terminal is sent a batch of queries, ending with a "Primary Device Attributes" query, which
+//! every terminal replies to. Terminals reply in order, so once the device attributes arrive we
+//! know that any unanswered queries are for unsupported features. Probing takes a moment, so the
+//! results are cached in Tattoy's state directory, keyed by the terminal's identity, and only
+//! probed again when Tattoy is run in a different terminal.
+
+use std::io::Write as _;
+
+use color_eyre::eyre::Result;
+use termwiz::terminal::Terminal as _;
+
+/// The name of the cache file in Tattoy's state directory.
+const CACHE_FILENAME: &str = "capabilities.json";
+
+/// The environment variables that together identify the terminal.
+const IDENTITY_ENV_VARS: [&str; 4] = ["TERM", "TERM_PROGRAM", "TERM_PROGRAM_VERSION", "COLORTERM"];
+
+/// Asks the terminal for its name and version.
+const XTVERSION_QUERY: &str = "\x1b[>0q";
+
+/// The start of the reply to the `XTVERSION` query, eg: `ESC P > | kitty(0.31.0) ESC \`.
+const XTVERSION_REPLY_PREFIX: &[u8] = b"\x1bP>|";
+
+/// Asks whether the terminal supports the Kitty graphics protocol, using a tiny 1x1 image that is
+/// never actually displayed.
+const KITTY_GRAPHICS_QUERY: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+
+/// The reply to the Kitty graphics query when the protocol is supported.
+const KITTY_GRAPHICS_REPLY: &[u8] = b"\x1b_Gi=31;OK";
+
+/// The DEC private mode for synchronized output, which stops the terminal from painting half of a
+/// frame.
+const SYNCHRONIZED_OUTPUT_MODE: u16 = 2026;
+
+/// The DEC private mode for reporting when the terminal gains or loses focus.
+const FOCUS_REPORTING_MODE: u16 = 1004;
+
+/// The device attribute that terminals send when they support Sixel graphics.
+const SIXEL_DEVICE_ATTRIBUTE: u16 = 4;
+
+/// Start synchronized output. The terminal waits for the end before painting.
+pub(crate) const BEGIN_SYNCHRONIZED_OUTPUT: &str = "\x1b[?2026h";
+
+/// End synchronized output, so that the terminal paints everything since the beginning.
+pub(crate) const END_SYNCHRONIZED_OUTPUT: &str = "\x1b[?2026l";
+
+/// The features of the user's terminal.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Capabilities {
+    /// The parameters of the terminal's reply to the "Primary Device Attributes" query. Empty if
+    /// the terminal didn't reply.
+    pub device_attributes: Vec<u16>,
+    /// The terminal's name and version, from its reply to the `XTVERSION` query.
+    pub terminal_name: Option<String>,
+    /// Whether the terminal can display true colour.
+    pub is_true_colour: bool,
+    /// Whether the terminal supports the Kitty graphics protocol.
+    pub has_kitty_graphics: bool,
+    /// Whether the terminal supports Sixel graphics.
+    pub has_sixel: bool,
+    /// Whether the terminal supports synchronized output, DEC private mode 2026.
+    pub has_synchronized_output: bool,
+    /// Whether the terminal can report focus changes, DEC private mode 1004.
+    pub has_focus_reporting: bool,
+}
+
+impl Capabilities {
+    /// Parse the terminal's replies to all the probing queries.
+    fn parse(bytes: &[u8], is_true_colour: bool) -> Self {
+        let replies = private_mode_replies(bytes);
+        let device_attributes = replies
+            .iter()
+            .find(|(_, terminator)| terminator == b"c")
+            .map(|(parameters, _)| parameters.clone())
+            .unwrap_or_default();
+        let is_mode_supported = |mode: u16| {
+            replies.iter().any(|(parameters, terminator)| {
+                terminator == b"$y"
+                    && parameters.first() == Some(&mode)
+                    && matches!(parameters.get(1), Some(1..=3))
+            })
+        };
+
+        Self {
+            has_sixel: device_attributes
+                .iter()
+                .skip(1)
+                .any(|attribute| *attribute == SIXEL_DEVICE_ATTRIBUTE),
+            device_attributes,
+            terminal_name: terminal_name(bytes),
+            is_true_colour,
+            has_kitty_graphics: find(bytes, KITTY_GRAPHICS_REPLY).is_some(),
+            has_synchronized_output: is_mode_supported(SYNCHRONIZED_OUTPUT_MODE),
+            has_focus_reporting: is_mode_supported(FOCUS_REPORTING_MODE),
+        }
+    }
+
+    /// Whether the terminal replied to the probe at all. Results are only cached when it did, so
+    /// that a terminal that was slow to reply gets probed again next time.
+    fn is_probed(&self) -> bool {
+        !self.device_attributes.is_empty()
+    }
+}
+
+/// All the queries needed to probe the terminal, ending with the device attributes query.
+fn queries() -> String {
+    [
+        XTVERSION_QUERY,
+        KITTY_GRAPHICS_QUERY,
+        &format!("\x1b[?{SYNCHRONIZED_OUTPUT_MODE}$p"),
+        &format!("\x1b[?{FOCUS_REPORTING_MODE}$p"),
+        crate::palette::osc::DEVICE_ATTRIBUTES_QUERY,
+    ]
+    .concat()
+}
+
+/// Find all the replies that start with `ESC [ ?`, returning their numeric parameters and the
+/// bytes that terminate them. For example, `ESC [ ? 2026 ; 2 $ y` gives `([2026, 2], "$y")`.
+fn private_mode_replies(bytes: &[u8]) -> Vec<(Vec<u16>, Vec<u8>)> {
+    let prefix = crate::palette::osc::DEVICE_ATTRIBUTES_REPLY_PREFIX;
+    let mut replies = Vec::new();
+    let mut remaining = bytes;
+    while let Some(start) = find(remaining, prefix) {
+        let sequence = remaining.get(start + prefix.len()..).unwrap_or_default();
+        let parameters_length = sequence
+            .iter()
+            .position(|byte| !byte.is_ascii_digit() && *byte != b';')
+            .unwrap_or(sequence.len());
+        let Some(final_byte) = sequence
+            .iter()
+            .skip(parameters_length)
+            .position(|byte| (0x40..=0x7e).contains(byte))
+        else {
+            break;
+        };
+        let end = parameters_length + final_byte + 1;
+
+        let parameters =
+            String::from_utf8_lossy(sequence.get(..parameters_length).unwrap_or_default())
+                .split(';')
+                .filter_map(|parameter| parameter.parse().ok())
+                .collect();
+        let terminator = sequence.get(parameters_length..end).unwrap_or_default();
+        replies.push((parameters, terminator.to_vec()));
+        remaining = sequence.get(end..).unwrap_or_default();
+    }
+    replies
+}
+
+/// Get the terminal's name from its reply to the `XTVERSION` query.
+fn terminal_name(bytes: &[u8]) -> Option<String> {
+    let start = find(bytes, XTVERSION_REPLY_PREFIX)? + XTVERSION_REPLY_PREFIX.len();
+    let name = bytes.get(start..)?;
+    let end = name.iter().position(|byte| *byte == b'\x1b')?;
+    let name = String::from_utf8_lossy(name.get(..end)?).trim().to_owned();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Find the position of a byte sequence.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A string that identifies the user's terminal, using the given environment variable getter.
+fn identity<F: Fn(&str) -> Option<String>>(get_env: F) -> String {
+    IDENTITY_ENV_VARS
+        .iter()
+        .map(|name| format!("{name}={}", get_env(name).unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The path to the cache of every terminal's capabilities.
+fn cache_path() -> std::path::PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_default()
+        .join("tattoy")
+        .join(CACHE_FILENAME)
+}
+
+/// Read the cache of every terminal's capabilities. A missing or broken cache is just empty.
+fn read_cache(path: &std::path::Path) -> std::collections::BTreeMap<String, Capabilities> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Add a terminal's capabilities to the cache.
+fn write_cache(path: &std::path::Path, identity: &str, capabilities: &Capabilities) -> Result<()> {
+    let mut cache = read_cache(path);
+    cache.insert(identity.to_owned(), capabilities.clone());
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&cache)?)?;
+    Ok(())
+}
+
+/// The cached capabilities of the terminal that Tattoy is currently running in, if it's been
+/// probed before.
+pub(crate) fn cached() -> Option<Capabilities> {
+    read_cache(&cache_path()).remove(&identity(|name| std::env::var(name).ok()))
+}
+
+/// Send the probing queries to the user's terminal and parse its replies.
+fn probe(state: &crate::shared_state::SharedState) -> Result<Capabilities> {
+    let mut terminal = crate::renderer::Renderer::get_termwiz_terminal()?;
+    terminal.set_raw_mode()?;
+
+    let mut stdout = std::io::stdout();
+    let queries = crate::multiplexer::wrap_query(state, &queries());
+    stdout.write_all(queries.as_bytes())?;
+    stdout.flush()?;
+
+    let bytes = crate::palette::osc::read_replies();
+    terminal.set_cooked_mode()?;
+
+    Ok(Capabilities::parse(&bytes?, state.is_true_colour.get()))
+}
+
+/// Detect the user's terminal's capabilities and save them in the shared state. They're read from
+/// the cache when possible, unless `is_forced` is set.
+pub(crate) fn detect(state: &crate::shared_state::SharedState, is_forced: bool) -> Result<()> {
+    let identity = identity(|name| std::env::var(name).ok());
+    let path = cache_path();
+
+    let maybe_cached = (!is_forced)
+        .then(|| read_cache(&path).remove(&identity))
+        .flatten();
+    let capabilities = if let Some(cached) = maybe_cached {
+        tracing::debug!("Using cached terminal capabilities for: {identity}");
+        cached
+    } else {
+        tracing::info!("Probing the terminal for its capabilities");
+        let probed = probe(state)?;
+        if probed.is_probed() {
+            if let Err(error) = write_cache(&path, &identity, &probed) {
+                tracing::warn!(
+                    "Caching terminal capabilities to {}: {error:?}",
+                    path.display()
+                );
+            }
+        }
+        probed
+    };
+
+    tracing::info!("Terminal capabilities: {capabilities:?}");
+    state.capabilities.store(capabilities);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_replies() {
+        let replies = b"\x1bP>|WezTerm 20240203\x1b\\\x1b_Gi=31;OK\x1b\\\
+                        \x1b[?2026;2$y\x1b[?1004;0$y\x1b[?62;4;22c";
+        let capabilities = Capabilities::parse(replies, true);
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                device_attributes: vec![62, 4, 22],
+                terminal_name: Some("WezTerm 20240203".to_owned()),
+                is_true_colour: true,
+                has_kitty_graphics: true,
+                has_sixel: true,
+                has_synchronized_output: true,
+                has_focus_reporting: false,
+            }
+        );
+        assert!(capabilities.is_probed());
+    }
+
+    #[test]
+    fn parsing_minimal_replies() {
+        let capabilities = Capabilities::parse(b"\x1b[?1;2c", false);
+        assert_eq!(capabilities.device_attributes, vec![1, 2]);
+        assert!(!capabilities.has_sixel);
+        assert!(!capabilities.has_synchronized_output);
+        assert_eq!(capabilities.terminal_name, None);
+
+        assert!(!Capabilities::parse(b"", false).is_probed());
+    }
+
+    #[test]
+    fn identifying_terminals() {
+        let env = |name: &str| (name == "TERM").then(|| "xterm-kitty".to_owned());
+        assert_eq!(
+            identity(env),
+            "TERM=xterm-kitty TERM_PROGRAM= TERM_PROGRAM_VERSION= COLORTERM="
+        );
+    }
+
+    #[test]
+    fn caching() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("state").join(CACHE_FILENAME);
+        let capabilities = Capabilities::parse(b"\x1b[?2026;1$y\x1b[?62c", true);
+        write_cache(&path, "TERM=foot", &capabilities).unwrap();
+        write_cache(&path, "TERM=xterm", &Capabilities::default()).unwrap();
+
+        let cache = read_cache(&path);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("TERM=foot"), Some(&capabilities));
+    }
+}
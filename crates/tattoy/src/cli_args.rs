@@ -27,6 +27,10 @@ pub(crate) struct CliArgs {
     #[arg(long)]
     pub capture_palette: bool,
 
+    /// Ignore the cached capabilities of the terminal and probe it again.
+    #[arg(long)]
+    pub probe_terminal: bool,
+
     /// Provide a screenshot of the terminal's palette for parsing into true colours.
     #[arg(long, value_name = "Path to screenshot file")]
     pub parse_palette: Option<String>,
@@ -36,6 +40,11 @@ pub(crate) struct CliArgs {
     #[arg(long, value_name = "Path to config directory")]
     pub config_dir: Option<std::path::PathBuf>,
 
+    /// Path to the data directory, where bundled shaders and installed plugins are kept. Defaults
+    /// to the OS's standard data directory, eg `$HOME/.local/share/tattoy` on Linux.
+    #[arg(long, value_name = "Path to data directory")]
+    pub data_dir: Option<std::path::PathBuf>,
+
     /// Override the default Tattoy config *file*. The same default config directory is used, so the
     /// palette and shader files are the same.
     #[arg(
@@ -45,6 +54,11 @@ pub(crate) struct CliArgs {
     )]
     pub main_config: std::path::PathBuf,
 
+    /// Start with the named config profile, as defined in the `[profiles.<name>]` section of the
+    /// main config.
+    #[arg(long, value_name = "Name of the config profile")]
+    pub profile: Option<String>,
+
     /// Path to the log file, overrides the setting in config.
     #[arg(long, value_name = "Path to log file")]
     pub log_path: Option<std::path::PathBuf>,
@@ -52,4 +66,243 @@ pub(crate) struct CliArgs {
     /// Verbosity of logs
     #[arg(long, value_name = "Level to log at")]
     pub log_level: Option<crate::config::main::LogLevel>,
+
+    /// Subcommands for managing Tattoy, rather than starting it.
+    #[command(subcommand)]
+    pub subcommand: Option<Subcommand>,
+}
+
+/// Tattoy's subcommands.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum Subcommand {
+    /// Manage the terminal's palette.
+    Palette {
+        /// What to do with the palette.
+        #[command(subcommand)]
+        action: PaletteAction,
+    },
+    /// Raise a notification in a running Tattoy, by default the one that this is run inside of.
+    Notify {
+        /// The title of the notification.
+        title: String,
+        /// The optional body of the notification.
+        body: Option<String>,
+        /// The urgency level of the notification.
+        #[arg(long, value_enum, default_value = "info")]
+        level: crate::tattoys::notifications::message::Level,
+        /// The control socket of the Tattoy to notify. Defaults to `$TATTOY_SOCKET`, which is set
+        /// inside of Tattoy.
+        #[arg(long, value_name = "Path to control socket")]
+        socket: Option<std::path::PathBuf>,
+    },
+    /// Check the environment for anything that might stop Tattoy from working properly, eg
+    /// missing GPU drivers or a broken config file.
+    Doctor {
+        /// Also bundle the report, the log file, the config (with secrets scrubbed) and the
+        /// palette into a single file, for attaching to GitHub issues.
+        #[arg(long)]
+        bundle: bool,
+        /// Instead of checking the environment, time how long keypresses take to reach the
+        /// screen, both with and without Tattoy, and report the percentiles.
+        #[arg(long)]
+        latency: bool,
+    },
+    /// Control a running Tattoy, by default the one that this is run inside of.
+    Ctl {
+        /// What to do to Tattoy.
+        #[command(subcommand)]
+        command: CtlCommand,
+        /// The control socket of the Tattoy to control. Defaults to `$TATTOY_SOCKET`, which is
+        /// set inside of Tattoy.
+        #[arg(long, value_name = "Path to control socket")]
+        socket: Option<std::path::PathBuf>,
+    },
+    /// Run a command inside of Tattoy, rather than the configured command.
+    Run {
+        /// Don't start Tattoy interactively. Instead, wait for the command to exit, then write
+        /// its final output, with all the enabled tattoys and shaders, to an ANSI file or a PNG.
+        /// Eg `tattoy run --once --output screenshot.png -- ls --color`.
+        #[arg(long)]
+        once: bool,
+        /// Settings for `--once`.
+        #[command(flatten)]
+        batch: crate::batch::Options,
+        /// The command and its arguments.
+        #[arg(trailing_var_arg = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Benchmark the whole rendering pipeline headlessly, with a synthetic workload, and report
+    /// the frames per second and how long each stage takes. Eg
+    /// `tattoy --use shaders bench --frames 500`.
+    Bench {
+        /// Settings for the benchmark.
+        #[command(flatten)]
+        options: crate::bench::Options,
+        /// A command to use as the workload instead of the built-in one, which prints coloured
+        /// lines as fast as it can.
+        #[arg(trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+    /// Manage the hooks that make your shell report when commands start and finish, and its
+    /// current directory. Some tattoys, like the git status, depend on them.
+    ShellIntegration {
+        /// What to do with the shell integration.
+        #[command(subcommand)]
+        command: ShellIntegrationCommand,
+    },
+    /// Help with writing plugins.
+    Plugin {
+        /// What to do.
+        #[command(subcommand)]
+        command: PluginCommand,
+    },
+    /// Export the config, palette, themes and shaders into a single archive, for moving your
+    /// setup to another machine. Plugins are listed in the archive, but not included.
+    ExportSetup {
+        /// Where to save the archive. Defaults to a timestamped `.tar.gz` in the current
+        /// directory.
+        #[arg(long, short, value_name = "Path to archive")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Import a setup that was exported with `tattoy export-setup`.
+    ImportSetup {
+        /// The archive to import.
+        #[arg(value_name = "Path to archive")]
+        archive: std::path::PathBuf,
+        /// Overwrite config files that already exist and have been changed.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Browse and install shaders.
+    Shader {
+        /// What to do.
+        #[command(subcommand)]
+        command: ShaderCommand,
+    },
+}
+
+/// Commands for managing shaders.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum ShaderCommand {
+    /// Open the shader browser in a running Tattoy, by default the one that this is run inside
+    /// of. The selected shader is previewed live, and choosing it makes it the current shader.
+    Browse {
+        /// The control socket of the Tattoy. Defaults to `$TATTOY_SOCKET`, which is set inside
+        /// of Tattoy.
+        #[arg(long, value_name = "Path to control socket")]
+        socket: Option<std::path::PathBuf>,
+    },
+    /// Install shaders into the data directory, either a single shader from a URL, or every
+    /// shader in a git repo. Needs `curl` or `git`.
+    Install {
+        /// The URL of the shader file, or of the git repo.
+        url: String,
+    },
+}
+
+/// Commands for plugin authors.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum PluginCommand {
+    /// Create a new plugin project, with an example that inverts the terminal, ready to be
+    /// changed into your own plugin.
+    New {
+        /// The name of the plugin, eg `my-plugin`.
+        name: String,
+        /// The language to write the plugin in.
+        #[arg(long, value_enum, default_value = "rust")]
+        lang: crate::plugin_template::Language,
+        /// The directory to create the project in. Defaults to the plugin's name in the current
+        /// directory.
+        #[arg(long, value_name = "Path to project directory")]
+        path: Option<std::path::PathBuf>,
+    },
+}
+
+/// Commands for managing shell integration.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum ShellIntegrationCommand {
+    /// Append the shell integration script to your shell's config file.
+    Install {
+        /// The shell to install the integration for. Defaults to the shell in `$SHELL`.
+        #[arg(long, value_enum)]
+        shell: Option<crate::shell_integration::Shell>,
+        /// Print the script rather than installing it, eg to add it to your config by hand.
+        #[arg(long)]
+        print: bool,
+    },
+}
+
+/// Commands for controlling a running Tattoy.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum CtlCommand {
+    /// Toggle a tattoy on or off, or all of Tattoy's rendering when no tattoy is given. Changes
+    /// are remembered the next time Tattoy starts, until `tattoy ctl reload --reset`.
+    Toggle {
+        /// The name of the tattoy, eg `minimap`.
+        tattoy: Option<String>,
+    },
+    /// Set the opacity of a tattoy.
+    Opacity {
+        /// The name of the tattoy, eg `minimap`.
+        tattoy: String,
+        /// The new opacity, between 0.0 and 1.0.
+        opacity: f32,
+    },
+    /// Switch to another shader.
+    Shader {
+        /// Path to the shader file.
+        #[arg(value_name = "Path to shader file")]
+        path: std::path::PathBuf,
+    },
+    /// Switch to a config profile, like the `profile_next` keybinding does.
+    Profile {
+        /// The name of the profile. Switches back to the main config when not given.
+        name: Option<String>,
+    },
+    /// Reload the config file.
+    Reload {
+        /// Also forget all the changes that were made with `tattoy ctl`.
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Print the status of Tattoy as JSON.
+    Status,
+    /// Print Tattoy's performance statistics as JSON, eg frame times and how often each tattoy
+    /// updates.
+    Stats,
+    /// Print a single value of Tattoy's current state as plain text, eg for a segment in your
+    /// shell's prompt.
+    Get {
+        /// The value to print.
+        #[arg(value_enum)]
+        key: crate::control::ContextKey,
+    },
+    /// Kill the command running in Tattoy and start another one in its place, eg a rescue shell.
+    /// The scrollback and tattoys are kept.
+    Respawn {
+        /// The command to start. Defaults to the configured `command`, usually your shell.
+        #[arg(trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+}
+
+/// Actions for managing the terminal's palette.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum PaletteAction {
+    /// Import a Base16 or Base24 colour scheme file as the palette, instead of capturing the
+    /// palette from the terminal.
+    Import {
+        /// Path to the scheme's YAML file.
+        #[arg(value_name = "Path to scheme file")]
+        path: std::path::PathBuf,
+    },
+    /// Export the palette as a theme for another terminal emulator.
+    Export {
+        /// The terminal emulator's config format.
+        #[arg(value_enum)]
+        format: crate::palette::export::Format,
+        /// Write the theme to a file rather than to STDOUT.
+        #[arg(long, value_name = "Path to theme file")]
+        output: Option<std::path::PathBuf>,
+    },
 }
@@ -0,0 +1,155 @@
+//! Filters for colour-vision deficiencies. They can either simulate how a colour blind person sees
+//! the terminal, which is useful for checking colour schemes, or "daltonize" the terminal, which
+//! shifts colours so that they're easier to tell apart for colour blind people.
+//!
+//! The simulations use the matrices from "A Physiologically-based Model for Simulation of Color
+//! Vision Deficiency" by Machado, Oliveira and Fernandes (2009), at full severity. Daltonization
+//! follows the approach of Fidaner, Lin and Ozguven (2005), where the colour information that is
+//! lost is redistributed to the channels that can still be seen.
+
+/// A 3x3 matrix for transforming linear RGB colours.
+type Matrix = [[f32; 3]; 3];
+
+/// Simulation of protanopia (no red cones).
+const PROTANOPIA: Matrix = [
+    [0.152_286, 1.052_583, -0.204_868],
+    [0.114_503, 0.786_281, 0.099_216],
+    [-0.003_882, -0.048_116, 1.051_998],
+];
+
+/// Simulation of deuteranopia (no green cones).
+const DEUTERANOPIA: Matrix = [
+    [0.367_322, 0.860_646, -0.227_968],
+    [0.280_085, 0.672_501, 0.047_413],
+    [-0.011_820, 0.042_940, 0.968_881],
+];
+
+/// Simulation of tritanopia (no blue cones).
+const TRITANOPIA: Matrix = [
+    [1.255_528, -0.076_749, -0.178_779],
+    [-0.078_411, 0.930_809, 0.147_602],
+    [0.004_733, 0.691_367, 0.303_900],
+];
+
+/// Redistributes the red-green information that is lost with protanopia and deuteranopia.
+const RED_GREEN_CORRECTION: Matrix = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+/// Redistributes the blue-yellow information that is lost with tritanopia.
+const BLUE_YELLOW_CORRECTION: Matrix = [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]];
+
+/// The available colour-vision filters.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VisionFilter {
+    /// No filter.
+    #[default]
+    None,
+    /// Simulate protanopia.
+    Protanopia,
+    /// Simulate deuteranopia.
+    Deuteranopia,
+    /// Simulate tritanopia.
+    Tritanopia,
+    /// Make colours easier to distinguish for people with protanopia.
+    DaltonizeProtanopia,
+    /// Make colours easier to distinguish for people with deuteranopia.
+    DaltonizeDeuteranopia,
+    /// Make colours easier to distinguish for people with tritanopia.
+    DaltonizeTritanopia,
+}
+
+impl VisionFilter {
+    /// Apply the filter to a colour.
+    pub fn apply(self, colour: termwiz::color::SrgbaTuple) -> termwiz::color::SrgbaTuple {
+        let (simulation, maybe_correction) = match self {
+            Self::None => return colour,
+            Self::Protanopia => (PROTANOPIA, None),
+            Self::Deuteranopia => (DEUTERANOPIA, None),
+            Self::Tritanopia => (TRITANOPIA, None),
+            Self::DaltonizeProtanopia => (PROTANOPIA, Some(RED_GREEN_CORRECTION)),
+            Self::DaltonizeDeuteranopia => (DEUTERANOPIA, Some(RED_GREEN_CORRECTION)),
+            Self::DaltonizeTritanopia => (TRITANOPIA, Some(BLUE_YELLOW_CORRECTION)),
+        };
+
+        let linear = palette::Srgb::new(colour.0, colour.1, colour.2).into_linear();
+        let original = [linear.red, linear.green, linear.blue];
+        let simulated = Self::multiply(&simulation, original);
+
+        let filtered = match maybe_correction {
+            None => simulated,
+            Some(correction) => {
+                let error = [
+                    original[0] - simulated[0],
+                    original[1] - simulated[1],
+                    original[2] - simulated[2],
+                ];
+                let shift = Self::multiply(&correction, error);
+                [
+                    original[0] + shift[0],
+                    original[1] + shift[1],
+                    original[2] + shift[2],
+                ]
+            }
+        };
+
+        let [red, green, blue] = filtered.map(|component| component.clamp(0.0, 1.0));
+        let srgb = palette::Srgb::from_linear(palette::LinSrgb::new(red, green, blue));
+        termwiz::color::SrgbaTuple(srgb.red, srgb.green, srgb.blue, colour.3)
+    }
+
+    /// Multiply a colour by a matrix.
+    fn multiply(matrix: &Matrix, colour: [f32; 3]) -> [f32; 3] {
+        matrix.map(|row| {
+            row[0].mul_add(colour[0], row[1].mul_add(colour[1], row[2] * colour[2]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(left: termwiz::color::SrgbaTuple, right: termwiz::color::SrgbaTuple) {
+        let is_close = (left.0 - right.0).abs() < 0.01
+            && (left.1 - right.1).abs() < 0.01
+            && (left.2 - right.2).abs() < 0.01
+            && (left.3 - right.3).abs() < 0.01;
+        assert!(is_close, "{left:?} != {right:?}");
+    }
+
+    #[test]
+    fn greys_are_unchanged() {
+        let grey = termwiz::color::SrgbaTuple(0.5, 0.5, 0.5, 0.8);
+        for filter in [
+            VisionFilter::None,
+            VisionFilter::Protanopia,
+            VisionFilter::Deuteranopia,
+            VisionFilter::Tritanopia,
+            VisionFilter::DaltonizeProtanopia,
+            VisionFilter::DaltonizeDeuteranopia,
+            VisionFilter::DaltonizeTritanopia,
+        ] {
+            assert_close(filter.apply(grey), grey);
+        }
+    }
+
+    #[test]
+    fn simulating_red_green_deficiencies() {
+        let red = termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+        let green = termwiz::color::SrgbaTuple(0.0, 1.0, 0.0, 1.0);
+        for filter in [VisionFilter::Protanopia, VisionFilter::Deuteranopia] {
+            let simulated_red = filter.apply(red);
+            let simulated_green = filter.apply(green);
+            assert!(simulated_red.0 < 1.0);
+            assert!(simulated_red.1 > 0.0);
+            assert!(simulated_green.0 > 0.0);
+        }
+    }
+
+    #[test]
+    fn daltonizing_adds_information_to_visible_channels() {
+        let red = termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+        let daltonized = VisionFilter::DaltonizeDeuteranopia.apply(red);
+        assert!(daltonized.2 > 0.0);
+    }
+}
@@ -1,6 +1,35 @@
 //! Composite individual cells into the final renderablsee frame.
 use color_eyre::eyre::{ContextCompat as _, Result};
 
+/// The parts of a cell that are needed to composite it onto another cell. It means that cells can
+/// be composited straight from a shared surface's lines, without first being copied out of them.
+pub(crate) trait CellAbove {
+    /// The cell's text.
+    fn str(&self) -> &str;
+    /// The cell's attributes, like its colours.
+    fn attrs(&self) -> &termwiz::cell::CellAttributes;
+}
+
+impl CellAbove for termwiz::cell::Cell {
+    fn str(&self) -> &str {
+        Self::str(self)
+    }
+
+    fn attrs(&self) -> &termwiz::cell::CellAttributes {
+        Self::attrs(self)
+    }
+}
+
+impl CellAbove for termwiz::surface::line::CellRef<'_> {
+    fn str(&self) -> &str {
+        Self::str(self)
+    }
+
+    fn attrs(&self) -> &termwiz::cell::CellAttributes {
+        Self::attrs(self)
+    }
+}
+
 /// Composite cells together, honouring alpha blending, text and pixels.
 #[derive(Default)]
 pub(crate) struct Compositor;
@@ -42,6 +71,7 @@ impl Compositor {
     pub fn composite_fg_colour_only(
         base_cell: &mut termwiz::cell::Cell,
         cell_above: &termwiz::cell::Cell,
+        default_colour: termwiz::color::SrgbaTuple,
     ) {
         if base_cell
             .str()
@@ -52,16 +82,18 @@ impl Compositor {
         }
 
         let mut draft = termwiz::cell::Cell::blank();
-        Self::composite_cells(&mut draft, cell_above, 1.0);
+        Self::composite_cells(&mut draft, cell_above, 1.0, default_colour);
         let colour = draft.attrs().foreground();
         base_cell.attrs_mut().set_foreground(colour);
     }
 
-    /// Composite 2 cells together.
-    pub fn composite_cells(
+    /// Composite 2 cells together. The default colour is the terminal's background colour, used
+    /// for cells that don't have an explicit colour.
+    pub fn composite_cells<C: CellAbove>(
         composited_cell: &mut termwiz::cell::Cell,
-        cell_above: &termwiz::cell::Cell,
+        cell_above: &C,
         opacity: f32,
+        default_colour: termwiz::color::SrgbaTuple,
     ) {
         let character_above = cell_above.str();
         let is_composited_cell_pixel = composited_cell.str() == "▀" || composited_cell.str() == "▄";
@@ -70,14 +102,18 @@ impl Compositor {
         let is_character_above_text = !is_character_above_empty && !is_character_above_pixel;
         let is_pixel_onto_non_pixel = is_character_above_pixel && !is_composited_cell_pixel;
 
+        // The whole grapheme is kept, so that emojis made of many code points, like ZWJ sequences
+        // and those with variation selectors, aren't split.
         if is_character_above_text || is_pixel_onto_non_pixel {
-            *composited_cell = termwiz::cell::Cell::new(
-                character_above.chars().nth(0).unwrap_or(' '),
+            *composited_cell = termwiz::cell::Cell::new_grapheme(
+                character_above,
                 composited_cell.attrs().clone(),
+                None,
             );
         }
 
-        let mut blender = crate::blender::Blender::new(composited_cell, None, opacity);
+        let mut blender =
+            crate::blender::Blender::new(composited_cell, Some(default_colour), opacity);
         blender.blend_all(cell_above);
 
         // The convention we use for pixel graphics is that we always try to render using the upper
@@ -94,8 +130,9 @@ impl Compositor {
         composited_cell: &mut termwiz::cell::Cell,
         target_text_contrast: f32,
         apply_to_readable_text_only: bool,
+        default_colour: termwiz::color::SrgbaTuple,
     ) {
-        let mut blender = crate::blender::Blender::new(composited_cell, None, 1.0);
+        let mut blender = crate::blender::Blender::new(composited_cell, Some(default_colour), 1.0);
         blender.ensure_readable_contrast(target_text_contrast, apply_to_readable_text_only);
     }
 
@@ -105,9 +142,10 @@ impl Compositor {
         indicator_cell: &termwiz::cell::Cell,
         x: usize,
         y: usize,
+        default_colour: termwiz::color::SrgbaTuple,
     ) -> Result<()> {
         let composited_cell = Self::get_cell_mut(cells, x, y)?;
-        Self::composite_cells(composited_cell, indicator_cell, 1.0);
+        Self::composite_cells(composited_cell, indicator_cell, 1.0, default_colour);
 
         Ok(())
     }
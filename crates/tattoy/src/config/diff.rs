@@ -0,0 +1,222 @@
+//! Figure out which parts of the config have actually changed between reloads.
+//!
+//! Broadcasting a whole new config causes every tattoy to reconfigure itself, even when the user
+//! only changed, say, the minimap's width. So the old and new configs are compared section by
+//! section and only the names of the changed sections are sent along with the new config. Tattoys
+//! can then ignore updates that don't concern them.
+
+/// A top-level section of the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Section {
+    /// Settings that aren't in their own table, eg `frame_rate` and `show_tattoy_indicator`.
+    Global,
+    /// The `[keybindings]` table.
+    Keybindings,
+    /// The `[color]` grading table.
+    Color,
+    /// The `[text_contrast]` table.
+    TextContrast,
+    /// The `[[plugins]]` array.
+    Plugins,
+    /// The `[minimap]` table.
+    Minimap,
+    /// The `[shader]` table.
+    Shader,
+    /// The `[bg_command]` table.
+    BgCommand,
+    /// The `[notifications]` table.
+    Notifications,
+    /// The `[weather]` table.
+    Weather,
+    /// The `[visual_bell]` table.
+    VisualBell,
+    /// The `[system_stats]` table.
+    SystemStats,
+    /// The `[clock]` table.
+    Clock,
+    /// The `[pomodoro]` table.
+    Pomodoro,
+    /// The `[git_status]` table.
+    GitStatus,
+    /// The `[audio_visualiser]` table.
+    AudioVisualiser,
+    /// The `[typing_sparks]` table.
+    TypingSparks,
+    /// The `[cursor_smear]` table.
+    CursorSmear,
+    /// The `[crt]` table.
+    Crt,
+    /// The `[starfield]` table.
+    Starfield,
+    /// The `[screensaver]` table.
+    Screensaver,
+    /// The `[command_duration]` table.
+    CommandDuration,
+    /// The `[image_preview]` table.
+    ImagePreview,
+    /// The `[status_bar]` table.
+    StatusBar,
+    /// The `[focus]` table.
+    Focus,
+    /// The `[cursor_highlight]` table.
+    CursorHighlight,
+    /// The `[scrollbar]` table.
+    Scrollbar,
+    /// The `[random_walker]` table.
+    RandomWalker,
+    /// The `[magnifier]` table.
+    Magnifier,
+    /// The `[performance_hud]` table.
+    PerformanceHud,
+    /// The `[log_viewer]` table.
+    LogViewer,
+    /// The `[predictive_echo]` table.
+    PredictiveEcho,
+    /// The `[accessibility]` table.
+    Accessibility,
+    /// The `[power_saving]` table.
+    PowerSaving,
+    /// The `[[hooks]]` tables.
+    Hooks,
+    /// The `[metrics]` table.
+    Metrics,
+    /// The `[streaming]` table.
+    Streaming,
+    /// The `[exclusion_zones]` table.
+    ExclusionZones,
+    /// The `[palette]` overrides table.
+    Palette,
+}
+
+/// Compare two configs and list the sections that differ.
+pub(crate) fn changed_sections(
+    old: &super::main::Config,
+    new: &super::main::Config,
+) -> Vec<Section> {
+    let is_global_changed = old.command != new.command
+        || old.log_level != new.log_level
+        || old.log_path != new.log_path
+        || old.log_format != new.log_format
+        || old.log_max_size != new.log_max_size
+        || old.log_max_files != new.log_max_files
+        || old.log_per_session != new.log_per_session
+        || old.frame_rate != new.frame_rate
+        || old.show_tattoy_indicator != new.show_tattoy_indicator
+        || old.show_startup_logo != new.show_startup_logo
+        || old.scrollback_size != new.scrollback_size
+        || old.max_tattoy_surfaces_size != new.max_tattoy_surfaces_size
+        || old.interpolate_frames != new.interpolate_frames
+        || (old.fade_duration - new.fade_duration).abs() > f32::EPSILON
+        || old.exit_behaviour != new.exit_behaviour
+        || old.multiplexer_compatibility != new.multiplexer_compatibility
+        || old.auto_remote_profile != new.auto_remote_profile
+        || old.theme != new.theme
+        || old.profiles != new.profiles;
+
+    [
+        (Section::Global, is_global_changed),
+        (Section::Keybindings, old.keybindings != new.keybindings),
+        (Section::Color, old.color != new.color),
+        (Section::TextContrast, old.text_contrast != new.text_contrast),
+        (Section::Plugins, old.plugins != new.plugins),
+        (Section::Minimap, old.minimap != new.minimap),
+        (Section::Shader, old.shader != new.shader),
+        (Section::BgCommand, old.bg_command != new.bg_command),
+        (Section::Notifications, old.notifications != new.notifications),
+        (Section::Weather, old.weather != new.weather),
+        (Section::VisualBell, old.visual_bell != new.visual_bell),
+        (Section::SystemStats, old.system_stats != new.system_stats),
+        (Section::Clock, old.clock != new.clock),
+        (Section::Pomodoro, old.pomodoro != new.pomodoro),
+        (Section::GitStatus, old.git_status != new.git_status),
+        (Section::AudioVisualiser, old.audio_visualiser != new.audio_visualiser),
+        (Section::TypingSparks, old.typing_sparks != new.typing_sparks),
+        (Section::CursorSmear, old.cursor_smear != new.cursor_smear),
+        (Section::Crt, old.crt != new.crt),
+        (Section::Starfield, old.starfield != new.starfield),
+        (Section::Screensaver, old.screensaver != new.screensaver),
+        (Section::CommandDuration, old.command_duration != new.command_duration),
+        (Section::ImagePreview, old.image_preview != new.image_preview),
+        (Section::StatusBar, old.status_bar != new.status_bar),
+        (Section::Focus, old.focus != new.focus),
+        (Section::CursorHighlight, old.cursor_highlight != new.cursor_highlight),
+        (Section::Scrollbar, old.scrollbar != new.scrollbar),
+        (Section::RandomWalker, old.random_walker != new.random_walker),
+        (Section::Magnifier, old.magnifier != new.magnifier),
+        (Section::PerformanceHud, old.performance_hud != new.performance_hud),
+        (Section::LogViewer, old.log_viewer != new.log_viewer),
+        (Section::PredictiveEcho, old.predictive_echo != new.predictive_echo),
+        (Section::Accessibility, old.accessibility != new.accessibility),
+        (Section::PowerSaving, old.power_saving != new.power_saving),
+        (Section::Hooks, old.hooks != new.hooks),
+        (Section::Metrics, old.metrics != new.metrics),
+        (Section::Streaming, old.streaming != new.streaming),
+        (
+            Section::ExclusionZones,
+            old.exclusion_zones != new.exclusion_zones,
+        ),
+        (Section::Palette, old.palette != new.palette),
+    ]
+    .into_iter()
+    .filter_map(|(section, is_changed)| is_changed.then_some(section))
+    .collect()
+}
+
+/// Whether the message is a config update that just disabled the given section. Tattoys that can
+/// be toggled at runtime use this to know when to exit.
+pub(crate) fn is_section_disabled(
+    message: &crate::run::Protocol,
+    section: &Section,
+    is_enabled: fn(&super::main::Config) -> bool,
+) -> bool {
+    let crate::run::Protocol::Config { config, changes } = message else {
+        return false;
+    };
+
+    changes.contains(section) && !is_enabled(config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical_configs_have_no_changes() {
+        let config = super::super::main::Config::default();
+        assert!(changed_sections(&config, &config.clone()).is_empty());
+    }
+
+    #[test]
+    fn only_changed_sections_are_listed() {
+        let old = super::super::main::Config::default();
+        let mut new = old.clone();
+        new.minimap.enabled = !old.minimap.enabled;
+        new.frame_rate = 10;
+        assert_eq!(
+            changed_sections(&old, &new),
+            vec![Section::Global, Section::Minimap]
+        );
+    }
+
+    #[test]
+    fn detecting_disabled_sections() {
+        let mut config = super::super::main::Config::default();
+        config.shader.enabled = false;
+        let message = crate::run::Protocol::Config {
+            config,
+            changes: vec![Section::Shader],
+        };
+        let is_shader_enabled = |config: &super::super::main::Config| config.shader.enabled;
+        let is_minimap_enabled = |config: &super::super::main::Config| config.minimap.enabled;
+        assert!(is_section_disabled(
+            &message,
+            &Section::Shader,
+            is_shader_enabled
+        ));
+        assert!(!is_section_disabled(
+            &message,
+            &Section::Minimap,
+            is_minimap_enabled
+        ));
+    }
+}
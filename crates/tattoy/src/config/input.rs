@@ -9,8 +9,49 @@ pub(crate) struct KeybindingConfigRaw {
     pub key: String,
 }
 
+/// Key combinations with `CTRL` that shells and terminal applications commonly rely on, along with
+/// what they're used for.
+const RESERVED_CTRL_KEYS: [(char, &str); 8] = [
+    ('c', "interrupting programs (SIGINT)"),
+    ('d', "ending input (EOF)"),
+    ('z', "suspending programs (SIGTSTP)"),
+    ('\\', "quitting programs (SIGQUIT)"),
+    ('s', "pausing output (XOFF)"),
+    ('q', "resuming output (XON)"),
+    ('l', "clearing the screen"),
+    ('r', "searching shell history"),
+];
+
+/// Key combinations with `ALT` that shells, like Bash and Zsh, commonly rely on for editing the
+/// command line, along with what they're used for. Only lowercase letters are included, as
+/// uppercase ones are distinct keys.
+const RESERVED_ALT_KEYS: [(char, &str); 11] = [
+    ('a', "accepting the command line and keeping it (Zsh)"),
+    ('b', "moving back a word"),
+    ('f', "moving forward a word"),
+    ('d', "deleting the next word"),
+    ('c', "capitalising a word"),
+    ('l', "lowercasing a word"),
+    ('u', "uppercasing a word"),
+    ('n', "searching forward through history"),
+    ('p', "searching back through history"),
+    ('y', "pasting earlier deletions"),
+    ('.', "inserting the previous command's last argument"),
+];
+
+impl core::fmt::Display for KeybindingConfigRaw {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.mods {
+            Some(mods) => write!(formatter, "{mods}+{}", self.key),
+            None => write!(formatter, "{}", self.key),
+        }
+    }
+}
+
 /// All the possible actions a user can trigger in Tattoy
-#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
+#[derive(
+    serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Hash,
+)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum KeybindingAction {
     /// Toggle Tattoy's rendering. Doesn't effect the TTY.
@@ -29,6 +70,62 @@ pub(crate) enum KeybindingAction {
     ShaderPrev,
     /// Cycle to next shader in user's config shader directory.
     ShaderNext,
+    /// Open/close the shader browser.
+    ToggleShaderBrowser,
+    /// Cycle through the named config profiles.
+    ProfileNext,
+    /// Open/close the settings panel.
+    ToggleSettings,
+    /// Open/close the palette editor.
+    TogglePaletteEditor,
+    /// Show/hide the system stats.
+    ToggleSystemStats,
+    /// Start or pause the pomodoro timer.
+    PomodoroStartPause,
+    /// Reset the pomodoro timer back to the start of a work interval.
+    PomodoroReset,
+    /// Bookmark the line at the top of the current scroll, or the cursor's line when not
+    /// scrolling.
+    BookmarkAdd,
+    /// Scroll to the previous bookmark.
+    BookmarkPrevious,
+    /// Scroll to the next bookmark.
+    BookmarkNext,
+    /// Show/hide the list of bookmarks.
+    ToggleBookmarks,
+    /// Open/close the scrollback search prompt.
+    Search,
+    /// Show/hide the magnifier lens.
+    ToggleMagnifier,
+    /// Show/hide Tattoy's own performance stats.
+    TogglePerformanceHud,
+    /// Show/hide the live view of this session's warnings and errors.
+    ToggleLogViewer,
+    /// Dismiss the top notification.
+    DismissNotification,
+    /// Dismiss all the notifications.
+    DismissAllNotifications,
+    /// Do the action of the top notification, if it has one, eg opening the log file.
+    NotificationAction,
+    /// Kill the command running in Tattoy, eg a hung shell, and start the configured `command`
+    /// again. The scrollback and tattoys are kept.
+    RespawnCommand,
+}
+
+impl KeybindingAction {
+    /// Actions that are only triggered whilst scrolling, so they don't stop keys reaching the
+    /// terminal the rest of the time.
+    const fn is_scrolling_only(&self) -> bool {
+        matches!(self, Self::ScrollUp | Self::ScrollDown | Self::ScrollExit)
+    }
+
+    /// The name of the action as it's written in the config.
+    fn name(&self) -> String {
+        toml::Value::try_from(self)
+            .ok()
+            .and_then(|value| value.as_str().map(ToOwned::to_owned))
+            .unwrap_or_else(|| format!("{self:?}"))
+    }
 }
 
 /// All the active user-configured keybindings.
@@ -89,6 +186,73 @@ impl TryFrom<KeybindingConfigRaw> for termwiz::input::KeyEvent {
     }
 }
 
+/// Describe what the key is normally used for, if it's a key that shells or terminal applications
+/// rely on.
+fn reserved_key_usage(key_event: &termwiz::input::KeyEvent) -> Option<&'static str> {
+    use termwiz::input::{KeyCode, Modifiers};
+
+    let is_unmodified =
+        key_event.modifiers == Modifiers::NONE || key_event.modifiers == Modifiers::SHIFT;
+
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "Most keys aren't reserved"
+    )]
+    match key_event.key {
+        KeyCode::Char(_) if is_unmodified => Some("typing"),
+        KeyCode::Enter | KeyCode::Tab | KeyCode::Backspace if is_unmodified => {
+            Some("editing the command line")
+        }
+        KeyCode::Char(character) if key_event.modifiers == Modifiers::CTRL => RESERVED_CTRL_KEYS
+            .iter()
+            .find(|(reserved, _)| reserved.eq_ignore_ascii_case(&character))
+            .map(|(_, usage)| *usage),
+        KeyCode::Char(character) if key_event.modifiers == Modifiers::ALT => RESERVED_ALT_KEYS
+            .iter()
+            .find(|(reserved, _)| *reserved == character)
+            .map(|(_, usage)| *usage),
+        _ => None,
+    }
+}
+
+/// Find keybindings that conflict with each other, or with keys that shells and terminal
+/// applications rely on. Returns a human-readable description of each conflict.
+pub(crate) fn find_conflicts(keybindings: &KeybindingsRaw) -> Vec<String> {
+    let mut parsed = keybindings
+        .iter()
+        .filter_map(|(action, binding)| {
+            let key_event: termwiz::input::KeyEvent = binding.clone().try_into().ok()?;
+            Some((action, binding, key_event))
+        })
+        .collect::<Vec<_>>();
+    parsed.sort_by(|left, right| left.0.cmp(right.0));
+
+    let mut conflicts = Vec::new();
+    for (index, (action, binding, key_event)) in parsed.iter().enumerate() {
+        for (other_action, _, other_key_event) in parsed.iter().skip(index + 1) {
+            if key_event == other_key_event {
+                conflicts.push(format!(
+                    "`{binding}` is bound to both `{}` and `{}`",
+                    action.name(),
+                    other_action.name()
+                ));
+            }
+        }
+
+        if action.is_scrolling_only() {
+            continue;
+        }
+        if let Some(usage) = reserved_key_usage(key_event) {
+            conflicts.push(format!(
+                "`{binding}` for `{}` is normally used for {usage}",
+                action.name()
+            ));
+        }
+    }
+
+    conflicts
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -179,4 +343,58 @@ mod test {
         let actual = run(config);
         assert_eq!(actual, expected);
     }
+
+    fn binding(mods: Option<&str>, key: &str) -> KeybindingConfigRaw {
+        KeybindingConfigRaw {
+            mods: mods.map(ToOwned::to_owned),
+            key: key.to_owned(),
+        }
+    }
+
+    #[test]
+    fn no_conflicts() {
+        let keybindings = KeybindingsRaw::from([
+            (KeybindingAction::ToggleTattoy, binding(Some("ALT"), "t")),
+            (KeybindingAction::ToggleScrolling, binding(Some("ALT"), "s")),
+            (KeybindingAction::ScrollUp, binding(None, "UpArrow")),
+            (KeybindingAction::ScrollExit, binding(None, "Escape")),
+        ]);
+        assert!(find_conflicts(&keybindings).is_empty());
+    }
+
+    #[test]
+    fn duplicate_keybindings() {
+        let keybindings = KeybindingsRaw::from([
+            (KeybindingAction::ToggleTattoy, binding(Some("ALT"), "s")),
+            (KeybindingAction::ToggleScrolling, binding(Some("ALT"), "s")),
+        ]);
+        assert_eq!(
+            find_conflicts(&keybindings),
+            vec!["`ALT+s` is bound to both `toggle_tattoy` and `toggle_scrolling`"]
+        );
+    }
+
+    #[test]
+    fn reserved_keybindings() {
+        let keybindings = KeybindingsRaw::from([
+            (KeybindingAction::ToggleTattoy, binding(Some("CTRL"), "c")),
+            (KeybindingAction::ToggleMinimap, binding(None, "m")),
+            (KeybindingAction::BookmarkAdd, binding(Some("ALT"), "b")),
+            (KeybindingAction::ToggleBookmarks, binding(Some("ALT"), "B")),
+        ]);
+        assert_eq!(
+            find_conflicts(&keybindings),
+            vec![
+                "`CTRL+c` for `toggle_tattoy` is normally used for interrupting programs (SIGINT)",
+                "`m` for `toggle_minimap` is normally used for typing",
+                "`ALT+b` for `bookmark_add` is normally used for moving back a word",
+            ]
+        );
+    }
+
+    #[test]
+    fn default_keybindings_dont_conflict() {
+        let config = crate::config::main::Config::parse_default_config().unwrap();
+        assert_eq!(find_conflicts(&config.keybindings), Vec::<String>::new());
+    }
 }
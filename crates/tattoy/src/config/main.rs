@@ -5,7 +5,7 @@ use color_eyre::eyre::Result;
 
 /// A copy of the default config file. It gets copied to the user's config folder the first time
 /// they start Tattoy.
-static DEFAULT_CONFIG: &str = include_str!("../../default_config.toml");
+pub(crate) static DEFAULT_CONFIG: &str = include_str!("../../default_config.toml");
 
 /// Bundle an example shader with Tattoy.
 static EXAMPLE_SHADER: &str = include_str!("../tattoys/shaders/soft_shadows.glsl");
@@ -14,7 +14,10 @@ static EXAMPLE_SHADER: &str = include_str!("../tattoys/shaders/soft_shadows.glsl
 pub static DEFAULT_SHADER_FILENAME: &str = "soft_shadows.glsl";
 
 /// The name of the directory where shader files are kept.
-const SHADER_DIRECTORY_NAME: &str = "shaders";
+pub const SHADER_DIRECTORY_NAME: &str = "shaders";
+
+/// The name of the directory where plugins are installed.
+const PLUGIN_DIRECTORY_NAME: &str = "plugins";
 
 /// The valid log levels. Based on our `tracing` crate.
 #[derive(serde::Serialize, serde::Deserialize, clap::ValueEnum, Debug, Clone, PartialEq, Eq)]
@@ -34,12 +37,23 @@ pub(crate) enum LogLevel {
     Off,
 }
 
+/// The format of the lines in the log file.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LogFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
 /// Managing user config.
 #[expect(
     clippy::unsafe_derive_deserialize,
     reason = "Are the unsafe methods on the `f32`s?"
 )]
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub(crate) struct Config {
     /// The command to run in the underlying PTY, defaults to the users shell as dedfined in the
@@ -49,6 +63,14 @@ pub(crate) struct Config {
     pub log_level: LogLevel,
     /// The location of the log file.
     pub log_path: std::path::PathBuf,
+    /// The format of the log file.
+    pub log_format: LogFormat,
+    /// The size, in megabytes, at which the log file is rotated. 0 means no limit.
+    pub log_max_size: u64,
+    /// The number of old log files to keep.
+    pub log_max_files: usize,
+    /// Whether each session logs to its own file, named with its start time and process ID.
+    pub log_per_session: bool,
     /// Keybindings
     pub keybindings: super::input::KeybindingsRaw,
     /// Target frame rate
@@ -59,12 +81,29 @@ pub(crate) struct Config {
     pub show_startup_logo: bool,
     /// The size of the scrollback. Lines after this will be removed.
     pub scrollback_size: u32,
+    /// The memory, in megabytes, that the renderer can use to keep the frames of every tattoy.
+    /// When it's exceeded, the previous frames that are kept for cross-fading are forgotten, least
+    /// recently updated first. 0 means no limit.
+    pub max_tattoy_surfaces_size: u64,
+    /// Whether to cross-fade between the frames of tattoys that update slower than the frame rate.
+    /// See `crate::interpolation`.
+    pub interpolate_frames: bool,
+    /// How long, in seconds, tattoys take to fade in and out, eg when they're toggled or when
+    /// the shader changes. 0 makes them appear and disappear instantly.
+    pub fade_duration: f32,
+    /// What to leave in the user's terminal when Tattoy exits
+    pub exit_behaviour: crate::handover::ExitBehaviour,
+    /// Whether to adjust Tattoy's behaviour when it's running inside, or running, tmux or GNU
+    /// Screen. See `crate::multiplexer`.
+    pub multiplexer_compatibility: bool,
     /// Colour grading
     pub color: Color,
     /// Auto adjusting of text contrast
     pub text_contrast: TextContrast,
     /// Plugins config
     pub plugins: Vec<crate::tattoys::plugins::Config>,
+    /// Commands and actions that are run when things happen, eg when a command fails
+    pub hooks: Vec<crate::hooks::Hook>,
     /// The minimap
     pub minimap: crate::tattoys::minimap::Config,
     /// The shaders
@@ -73,6 +112,74 @@ pub(crate) struct Config {
     pub bg_command: crate::tattoys::bg_command::Config,
     /// Notifications
     pub notifications: crate::tattoys::notifications::main::Config,
+    /// Falling snow or rain
+    pub weather: crate::tattoys::weather::Config,
+    /// A visual substitute for the terminal's bell
+    pub visual_bell: crate::tattoys::visual_bell::Config,
+    /// CPU, memory, network and battery stats
+    pub system_stats: crate::tattoys::system_stats::Config,
+    /// An always-on-top clock
+    pub clock: crate::tattoys::clock::Config,
+    /// A pomodoro timer
+    pub pomodoro: crate::tattoys::pomodoro::Config,
+    /// The git status of the shell's working directory
+    pub git_status: crate::tattoys::git_status::Config,
+    /// A spectrum or waveform of the audio that's currently playing
+    pub audio_visualiser: crate::tattoys::audio_visualiser::config::Config,
+    /// Sparks that fly out of the cursor whilst typing
+    pub typing_sparks: crate::tattoys::typing_sparks::Config,
+    /// An animated smear following the cursor's jumps
+    pub cursor_smear: crate::tattoys::cursor_smear::Config,
+    /// A retro CRT monitor effect that doesn't need a GPU
+    pub crt: crate::tattoys::crt::Config,
+    /// A parallax starfield behind the terminal
+    pub starfield: crate::tattoys::starfield::Config,
+    /// Show a tattoy full-screen when the terminal is idle
+    pub screensaver: crate::tattoys::screensaver::Config,
+    /// A badge showing how long the last command took
+    pub command_duration: crate::tattoys::command_duration::Config,
+    /// Previews of images whose paths are printed in the terminal
+    pub image_preview: crate::tattoys::image_preview::Config,
+    /// A tmux-style status bar
+    pub status_bar: crate::tattoys::status_bar::Config,
+    /// Dim everything apart from what you're reading
+    pub focus: crate::tattoys::focus::Config,
+    /// Highlight the cursor's row and column
+    pub cursor_highlight: crate::tattoys::cursor_highlight::Config,
+    /// The scrollbar that's shown whilst scrolling
+    pub scrollbar: crate::tattoys::scrollbar::Config,
+    /// Pixels that randomly walk around the terminal
+    pub random_walker: crate::tattoys::random_walker::Config,
+    /// A lens that enlarges the terminal under the mouse
+    pub magnifier: crate::tattoys::magnifier::Config,
+    /// A display of Tattoy's own performance
+    pub performance_hud: crate::tattoys::performance_hud::Config,
+    /// A live view of this session's warnings and errors
+    pub log_viewer: crate::tattoys::log_viewer::Config,
+    /// Echoing keystrokes before the PTY does
+    pub predictive_echo: crate::predictive_echo::Config,
+    /// Accessibility settings that apply to all of Tattoy
+    pub accessibility: crate::accessibility::Config,
+    /// Reducing effects when running on battery power
+    pub power_saving: crate::power::Config,
+    /// Exporting performance metrics
+    pub metrics: crate::metrics::Config,
+    /// Streaming the terminal to web browsers
+    pub streaming: crate::streaming::Config,
+    /// Areas of the terminal that pixel-based tattoys aren't drawn over
+    pub exclusion_zones: crate::exclusion_zones::Config,
+    /// The version of the config schema, used to migrate old configs.
+    pub config_version: u32,
+    /// Named profiles that override subsets of the config.
+    pub profiles: super::profiles::Profiles,
+    /// Whether to automatically use the reduced-effects "remote" profile when running over SSH or
+    /// in a low-bandwidth terminal.
+    pub auto_remote_profile: bool,
+    /// The name of a theme in the `themes/` config directory.
+    pub theme: Option<String>,
+    /// Overrides for individual colours of the terminal's palette, eg `1 = [255, 0, 0]`. Mostly
+    /// useful for themes.
+    pub palette: crate::palette::converter::PaletteHashMap,
 }
 
 impl Default for Config {
@@ -98,24 +205,67 @@ impl Default for Config {
             command,
             log_level: LogLevel::Off,
             log_path,
+            log_format: LogFormat::default(),
+            log_max_size: 10,
+            log_max_files: 3,
+            log_per_session: false,
             frame_rate: 30,
             keybindings: super::input::KeybindingsRaw::new(),
             show_tattoy_indicator: true,
             show_startup_logo: true,
             scrollback_size: 1000,
+            max_tattoy_surfaces_size: 64,
+            interpolate_frames: false,
+            fade_duration: 0.25,
+            exit_behaviour: crate::handover::ExitBehaviour::default(),
+            multiplexer_compatibility: true,
             color: Color::default(),
             text_contrast: TextContrast::default(),
             plugins: Vec::default(),
+            hooks: Vec::default(),
             minimap: crate::tattoys::minimap::Config::default(),
             shader: crate::tattoys::shaders::main::Config::default(),
             bg_command: crate::tattoys::bg_command::Config::default(),
             notifications: crate::tattoys::notifications::main::Config::default(),
+            weather: crate::tattoys::weather::Config::default(),
+            visual_bell: crate::tattoys::visual_bell::Config::default(),
+            system_stats: crate::tattoys::system_stats::Config::default(),
+            clock: crate::tattoys::clock::Config::default(),
+            pomodoro: crate::tattoys::pomodoro::Config::default(),
+            git_status: crate::tattoys::git_status::Config::default(),
+            audio_visualiser: crate::tattoys::audio_visualiser::config::Config::default(),
+            typing_sparks: crate::tattoys::typing_sparks::Config::default(),
+            cursor_smear: crate::tattoys::cursor_smear::Config::default(),
+            crt: crate::tattoys::crt::Config::default(),
+            starfield: crate::tattoys::starfield::Config::default(),
+            screensaver: crate::tattoys::screensaver::Config::default(),
+            command_duration: crate::tattoys::command_duration::Config::default(),
+            image_preview: crate::tattoys::image_preview::Config::default(),
+            status_bar: crate::tattoys::status_bar::Config::default(),
+            focus: crate::tattoys::focus::Config::default(),
+            cursor_highlight: crate::tattoys::cursor_highlight::Config::default(),
+            scrollbar: crate::tattoys::scrollbar::Config::default(),
+            random_walker: crate::tattoys::random_walker::Config::default(),
+            magnifier: crate::tattoys::magnifier::Config::default(),
+            performance_hud: crate::tattoys::performance_hud::Config::default(),
+            log_viewer: crate::tattoys::log_viewer::Config::default(),
+            predictive_echo: crate::predictive_echo::Config::default(),
+            accessibility: crate::accessibility::Config::default(),
+            power_saving: crate::power::Config::default(),
+            metrics: crate::metrics::Config::default(),
+            streaming: crate::streaming::Config::default(),
+            exclusion_zones: crate::exclusion_zones::Config::default(),
+            config_version: super::migrations::CURRENT_CONFIG_VERSION,
+            profiles: super::profiles::Profiles::new(),
+            auto_remote_profile: true,
+            theme: None,
+            palette: crate::palette::converter::PaletteHashMap::new(),
         }
     }
 }
 
 /// Final colour grading for the whole terminal render.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 pub(crate) struct Color {
     /// Saturation
     pub saturation: f32,
@@ -123,6 +273,9 @@ pub(crate) struct Color {
     pub brightness: f32,
     /// Hue
     pub hue: f32,
+    /// Simulate, or correct for, colour-vision deficiencies.
+    #[serde(default)]
+    pub vision_filter: crate::colour_vision::VisionFilter,
 }
 
 impl Default for Color {
@@ -131,12 +284,13 @@ impl Default for Color {
             saturation: 0.0,
             brightness: 0.0,
             hue: 0.0,
+            vision_filter: crate::colour_vision::VisionFilter::None,
         }
     }
 }
 
 /// Config for auto adjusting text contrast.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 pub(crate) struct TextContrast {
     /// Whether it's enabled
     pub enabled: bool,
@@ -185,11 +339,83 @@ impl Config {
         let shaders_directory = path.join(SHADER_DIRECTORY_NAME);
         std::fs::create_dir_all(shaders_directory)?;
 
+        let themes_directory = path.join(super::themes::THEMES_DIRECTORY_NAME);
+        std::fs::create_dir_all(themes_directory)?;
+
         *state.config_path.write().await = path;
 
         Ok(())
     }
 
+    /// Canonical path to the data directory.
+    pub async fn data_directory(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> std::path::PathBuf {
+        state.data_path.read().await.clone()
+    }
+
+    /// Get the stable location of Tattoy's data directory on the user's system.
+    pub fn default_data_directory() -> Result<std::path::PathBuf> {
+        Ok(dirs::data_dir()
+            .context("Couldn't get standard data directory")?
+            .join("tattoy"))
+    }
+
+    /// Setup the data directory. It holds files that the user doesn't normally edit, like bundled
+    /// shaders and installed plugins, so that they don't clutter the config directory.
+    pub async fn setup_data_directory(
+        maybe_custom_path: Option<std::path::PathBuf>,
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let path = match maybe_custom_path {
+            None => Self::default_data_directory()?,
+            Some(path_string) => std::path::PathBuf::new().join(path_string),
+        };
+
+        let shaders_directory = path.join(SHADER_DIRECTORY_NAME);
+        std::fs::create_dir_all(&shaders_directory)?;
+        std::fs::create_dir_all(path.join(PLUGIN_DIRECTORY_NAME))?;
+
+        let bundled_shader_path = shaders_directory.join(DEFAULT_SHADER_FILENAME);
+        if !bundled_shader_path.exists() {
+            std::fs::write(bundled_shader_path, EXAMPLE_SHADER)?;
+        }
+
+        *state.data_path.write().await = path;
+
+        Ok(())
+    }
+
+    /// Find a file, eg `shaders/soft_shadows.glsl`, that can be in either the config directory or
+    /// the data directory. Files in the config directory take precedence. If the file isn't found
+    /// then the path in the config directory is returned, so that any errors refer to it.
+    pub async fn resolve_path(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        path: &std::path::Path,
+    ) -> std::path::PathBuf {
+        let config_path = Self::directory(state).await.join(path);
+        if config_path.exists() {
+            return config_path;
+        }
+
+        let data_path = Self::data_directory(state).await.join(path);
+        if data_path.exists() {
+            return data_path;
+        }
+
+        config_path
+    }
+
+    /// All the directories where shaders can be found, in order of precedence.
+    pub async fn shader_directories(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Vec<std::path::PathBuf> {
+        vec![
+            Self::directory(state).await.join(SHADER_DIRECTORY_NAME),
+            Self::data_directory(state).await.join(SHADER_DIRECTORY_NAME),
+        ]
+    }
+
     /// Canonical path to the main config file.
     pub async fn main_config_path(
         state: &std::sync::Arc<crate::shared_state::SharedState>,
@@ -208,12 +434,6 @@ impl Config {
         let is_default_config = config_file_name == crate::cli_args::DEFAULT_CONFIG_FILE_NAME;
         if is_default_config && !config_path.exists() {
             std::fs::write(config_path.clone(), DEFAULT_CONFIG)?;
-
-            let shader_path = Self::directory(state)
-                .await
-                .join(SHADER_DIRECTORY_NAME)
-                .join(DEFAULT_SHADER_FILENAME);
-            std::fs::write(shader_path, EXAMPLE_SHADER)?;
         }
 
         tracing::info!("(Re)loading the main Tattoy config from: {config_path:?}");
@@ -221,7 +441,37 @@ impl Config {
         match result {
             Ok(data) => {
                 tracing::trace!("Using config file:\n{data}");
-                let config = toml::from_str::<Self>(&data)?;
+                let migrated = super::migrations::migrate_file(&config_path, &data)?;
+                for deprecation in &migrated.deprecations {
+                    state
+                        .send_notification(
+                            "Deprecated config option",
+                            crate::tattoys::notifications::message::Level::Warn,
+                            Some(deprecation.clone()),
+                            false,
+                        )
+                        .await;
+                }
+                let maybe_profile = state.active_profile.read().await.clone();
+                let directory = Self::directory(state).await;
+                let mut overrides = if state.is_true_colour.get() {
+                    toml::Table::new()
+                } else {
+                    toml::from_str(crate::true_colour::REDUCED_COLOUR_CONFIG)?
+                };
+                super::profiles::merge(&mut overrides, state.config_overrides.read().await.clone());
+                let mut config = Self::parse(
+                    migrated.table()?,
+                    maybe_profile.as_deref(),
+                    &directory,
+                    overrides,
+                )?;
+                if *state.is_power_saving.read().await {
+                    crate::power::reduce(&mut config);
+                }
+                if config.accessibility.reduce_motion {
+                    crate::accessibility::reduce_motion(&mut config);
+                }
                 Self::load_keybindings(state, &config).await?;
                 Ok(config)
             }
@@ -235,8 +485,22 @@ impl Config {
         }
     }
 
+    /// Parse config data, applying the named profile if one is given, then any theme, and finally
+    /// any overrides made whilst Tattoy is running.
+    fn parse(
+        raw: toml::Table,
+        maybe_profile: Option<&str>,
+        directory: &std::path::Path,
+        overrides: toml::Table,
+    ) -> Result<Self> {
+        let profiled = super::profiles::apply(raw, maybe_profile)?;
+        let mut themed = super::themes::apply(profiled, directory)?;
+        super::profiles::merge(&mut themed, overrides);
+        Ok(toml::Value::Table(themed).try_into::<Self>()?)
+    }
+
     /// Parse the shipped default config.
-    fn parse_default_config() -> Result<Self> {
+    pub fn parse_default_config() -> Result<Self> {
         Ok(toml::from_str::<Self>(DEFAULT_CONFIG)?)
     }
 
@@ -244,36 +508,41 @@ impl Config {
     pub async fn load_config_into_shared_state(
         state: &std::sync::Arc<crate::shared_state::SharedState>,
     ) -> Result<Self> {
-        let mut config_state = state.config.write().await;
         let new_config = Self::load(state).await?;
-        *config_state = new_config.clone();
-        drop(config_state);
+        state.config.store(new_config.clone());
 
         Ok(new_config)
     }
 
-    /// Load all user keybindings.
+    /// Load all user keybindings. User keybindings replace the default keybinding for the same
+    /// action. The user is warned about any keybindings that conflict.
     #[expect(clippy::iter_over_hash_type, reason = "The ordering doesn't matter")]
     async fn load_keybindings(
         state: &std::sync::Arc<crate::shared_state::SharedState>,
         user_config: &Self,
     ) -> Result<()> {
-        let mut keybindings = crate::config::input::KeybindingsAsEvents::new();
+        let mut raw_keybindings = Self::parse_default_config()?.keybindings;
+        tracing::trace!("Loading user-defined keybindings...");
+        raw_keybindings.extend(user_config.keybindings.clone());
 
-        let defaults = Self::parse_default_config()?;
-        for (action, binding_config) in defaults.keybindings.clone() {
+        let mut keybindings = crate::config::input::KeybindingsAsEvents::new();
+        for (action, binding_config) in raw_keybindings.clone() {
             let key_event: termwiz::input::KeyEvent = binding_config.try_into()?;
-            keybindings.insert(action.clone(), key_event.clone());
+            tracing::debug!("Keybinding parsed for '{action:?}': {key_event:?}");
+            keybindings.insert(action, key_event);
         }
 
-        tracing::trace!("Loading user-defined keybindings...");
-        for (action, binding_config) in user_config.keybindings.clone() {
-            tracing::trace!("Keybinding found for '{action:?}': {binding_config:?}");
-            let key_event: termwiz::input::KeyEvent = binding_config.try_into()?;
-            keybindings
-                .entry(action.clone())
-                .or_insert_with(|| key_event.clone());
-            tracing::debug!("Keybinding parsed for '{action:?}': {key_event:?}");
+        let conflicts = super::input::find_conflicts(&raw_keybindings);
+        if !conflicts.is_empty() {
+            tracing::warn!("Keybinding conflicts: {conflicts:?}");
+            state
+                .send_notification(
+                    "Keybinding conflicts",
+                    crate::tattoys::notifications::message::Level::Warn,
+                    Some(conflicts.join("\n")),
+                    false,
+                )
+                .await;
         }
 
         *state.keybindings.write().await = keybindings;
@@ -313,6 +582,10 @@ impl Config {
                 &path,
                 notify_debouncer_full::notify::RecursiveMode::NonRecursive,
             )?;
+            debouncer.watch(
+                &path.join(super::themes::THEMES_DIRECTORY_NAME),
+                notify_debouncer_full::notify::RecursiveMode::NonRecursive,
+            )?;
 
             #[expect(
                 clippy::integer_division_remainder_used,
@@ -349,27 +622,53 @@ impl Config {
         let notify_event::ModifyKind::Data(_) = kind else {
             return;
         };
+        let is_runtime_state = event.paths.iter().all(|path| {
+            path.file_name()
+                .is_some_and(|name| name == crate::runtime_state::RUNTIME_STATE_FILE_NAME)
+        });
+        if is_runtime_state {
+            return;
+        }
 
         tracing::debug!(
             "Config file change detected ({:?}), updating shared state.",
             event.paths
         );
 
+        Self::reload_and_broadcast(state, "Config updated").await;
+
+        tracing::trace!("Config file change sent");
+    }
+
+    /// Reload the config from disk and let all the tattoys know about it. Only the sections that
+    /// actually changed are announced, so that unaffected tattoys don't needlessly reconfigure
+    /// themselves.
+    pub async fn reload_and_broadcast(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        success_message: &str,
+    ) {
+        let old_config = state.config.load();
         match Self::load_config_into_shared_state(state).await {
             Ok(config) => {
-                state
-                    .protocol_tx
-                    .send(crate::run::Protocol::Config(config))
-                    .unwrap_or_else(|send_error| {
-                        tracing::error!(
-                            "Couldn't send config update on protocol channel: {send_error:?}"
-                        );
-                        0
-                    });
+                let changes = super::diff::changed_sections(&old_config, &config);
+                if changes.is_empty() {
+                    tracing::debug!("Config reloaded but no sections changed");
+                } else {
+                    tracing::debug!("Config sections changed: {changes:?}");
+                    state
+                        .protocol_tx
+                        .send(crate::run::Protocol::Config { config, changes })
+                        .unwrap_or_else(|send_error| {
+                            tracing::error!(
+                                "Couldn't send config update on protocol channel: {send_error:?}"
+                            );
+                            0
+                        });
+                }
 
                 state
                     .send_notification(
-                        "Config updated",
+                        success_message,
                         crate::tattoys::notifications::message::Level::Info,
                         None,
                         false,
@@ -387,8 +686,29 @@ impl Config {
                     .await;
             }
         }
+    }
 
-        tracing::trace!("Config file change sent");
+    /// Switch to the given profile, or back to the main config when `None`.
+    pub async fn switch_profile(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        maybe_profile: Option<String>,
+    ) {
+        let message = match &maybe_profile {
+            Some(profile) => format!("Profile: {profile}"),
+            None => "Profile: default".to_owned(),
+        };
+        tracing::debug!("Switching config profile to: {maybe_profile:?}");
+        (*state.active_profile.write().await).clone_from(&maybe_profile);
+        Self::reload_and_broadcast(state, &message).await;
+    }
+
+    /// Switch to the next profile, in alphabetical order. After the last profile the main config
+    /// is used again.
+    pub async fn cycle_profile(state: &std::sync::Arc<crate::shared_state::SharedState>) {
+        let profiles = state.config.load().profiles.clone();
+        let current = state.active_profile.read().await.clone();
+        let next = super::profiles::next(&profiles, current.as_deref());
+        Self::switch_profile(state, next).await;
     }
 
     /// Get a temporary file handle.
@@ -415,8 +735,41 @@ impl Config {
 
         tracing::info!("Loading the terminal palette's true colours from config");
         let data = tokio::fs::read_to_string(path).await?;
-        let map = toml::from_str::<crate::palette::converter::PaletteHashMap>(&data)?;
+        let mut map = toml::from_str::<crate::palette::converter::PaletteHashMap>(&data)?;
+        let overrides = state.config.load().palette.clone();
+        if !overrides.is_empty() {
+            tracing::debug!("Overriding palette colours from config: {overrides:?}");
+        }
+        map.extend(overrides);
         let palette = crate::palette::converter::Palette { map };
+
+        Self::set_default_background_colour(&state, &palette).await;
         Ok(palette)
     }
+
+    /// Record the palette's default background colour, which is also how we know whether the user
+    /// has a light or dark theme.
+    async fn set_default_background_colour(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        palette: &crate::palette::converter::Palette,
+    ) {
+        let background = palette.default_background_colour();
+        tracing::debug!(
+            "Terminal background is {}: {background:?}",
+            if palette.is_light_background() { "light" } else { "dark" }
+        );
+        state.default_background_colour.store(background);
+    }
+
+    /// Start using a new palette without restarting Tattoy.
+    pub async fn broadcast_palette(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        Self::set_default_background_colour(state, &palette).await;
+        state
+            .protocol_tx
+            .send(crate::run::Protocol::Palette(palette))?;
+        Ok(())
+    }
 }
@@ -0,0 +1,329 @@
+//! Versioning of the config schema, so that old config files can be automatically upgraded when
+//! config options are renamed or moved.
+//!
+//! Every config file has a `config_version`. A config file without one is considered to be from
+//! before versioning was introduced, ie version 0. When an old config is loaded, each migration
+//! newer than the config's version is run in order. If any keys were actually changed then a
+//! backup of the original file is made and the migrated config is written back to disk.
+
+use color_eyre::eyre::Result;
+
+/// The version of the config schema that this version of Tattoy understands.
+pub const CURRENT_CONFIG_VERSION: u32 = 2;
+
+/// The config key that stores the config's version.
+pub const VERSION_KEY: &str = "config_version";
+
+/// A function that upgrades a config by one version. It returns a description of each change
+/// that it made.
+type MigrationFunction = fn(&mut dyn toml_edit::TableLike) -> Vec<String>;
+
+/// All the migrations, indexed by the version that they upgrade the config _to_.
+const MIGRATIONS: [(u32, MigrationFunction); 2] = [(1, migrate_to_v1), (2, migrate_to_v2)];
+
+/// The result of migrating a config.
+#[derive(Debug)]
+pub(crate) struct Migrated {
+    /// The migrated config. It's a document, rather than just a table, so that the user's comments
+    /// and formatting survive being written back to disk.
+    pub config: toml_edit::DocumentMut,
+    /// The version of the config before it was migrated.
+    pub from_version: u32,
+    /// Human-readable descriptions of the deprecated options that were changed.
+    pub deprecations: Vec<String>,
+}
+
+impl Migrated {
+    /// The migrated config as a plain table, ready to be parsed.
+    pub fn table(&self) -> Result<toml::Table> {
+        Ok(toml::from_str(&self.config.to_string())?)
+    }
+}
+
+/// Version 1 is the first versioned schema. There are no changes apart from the adding of the
+/// version itself.
+const fn migrate_to_v1(_config: &mut dyn toml_edit::TableLike) -> Vec<String> {
+    Vec::new()
+}
+
+/// Version 2 renames the minimap's `max_width` to `width`, because the minimap no longer keeps
+/// the scrollback's aspect ratio, so the width is always used.
+fn migrate_to_v2(config: &mut dyn toml_edit::TableLike) -> Vec<String> {
+    rename_key(config, "minimap.max_width", "minimap.width")
+        .into_iter()
+        .collect()
+}
+
+/// Move a value from one dotted key path to another, eg `"shader.file"` to `"shader.path"`. An
+/// existing value at the new path is never overwritten. Any comments above the old key are kept
+/// above the new one.
+pub(crate) fn rename_key(
+    config: &mut dyn toml_edit::TableLike,
+    from: &str,
+    to: &str,
+) -> Option<String> {
+    let (from_parent, from_key) = split_path(from);
+    let source = table_at_path(config, &from_parent, false)?;
+    let decor = source.key(from_key).map(|key| key.leaf_decor().clone());
+    let value = source.remove(from_key)?;
+
+    let (to_parent, to_key) = split_path(to);
+    let destination = table_at_path(config, &to_parent, true)?;
+    if destination.contains_key(to_key) {
+        return Some(format!(
+            "`{from}` is deprecated and has been removed, `{to}` is already set"
+        ));
+    }
+    destination.insert(to_key, value);
+    if let (Some(old_decor), Some(mut new_key)) = (decor, destination.key_mut(to_key)) {
+        *new_key.leaf_decor_mut() = old_decor;
+    }
+    Some(format!(
+        "`{from}` is deprecated and has been renamed to `{to}`"
+    ))
+}
+
+/// Split a dotted path into its parent path and final key.
+fn split_path(path: &str) -> (Vec<&str>, &str) {
+    let mut parts = path.split('.').collect::<Vec<&str>>();
+    let key = parts.pop().unwrap_or_default();
+    (parts, key)
+}
+
+/// Get the nested table at the given path, optionally creating any missing tables on the way.
+/// Created tables don't get their own header unless they end up with values of their own.
+fn table_at_path<'table>(
+    config: &'table mut dyn toml_edit::TableLike,
+    path: &[&str],
+    should_create: bool,
+) -> Option<&'table mut dyn toml_edit::TableLike> {
+    let mut table = config;
+    for part in path {
+        if should_create && !table.contains_key(part) {
+            let mut new_table = toml_edit::Table::new();
+            new_table.set_implicit(true);
+            table.insert(part, toml_edit::Item::Table(new_table));
+        }
+        table = table.get_mut(part)?.as_table_like_mut()?;
+    }
+    Some(table)
+}
+
+/// Get the version of the given config.
+fn version_of(config: &toml_edit::DocumentMut) -> u32 {
+    config
+        .get(VERSION_KEY)
+        .and_then(toml_edit::Item::as_integer)
+        .and_then(|version| u32::try_from(version).ok())
+        .unwrap_or(0)
+}
+
+/// Run all the needed migrations on a config. Profiles are migrated too, as they're partial
+/// copies of the main config.
+pub(crate) fn migrate(mut config: toml_edit::DocumentMut) -> Migrated {
+    let from_version = version_of(&config);
+    let mut deprecations = Vec::new();
+
+    if from_version > CURRENT_CONFIG_VERSION {
+        tracing::warn!(
+            "Config version {from_version} is newer than the supported version \
+            {CURRENT_CONFIG_VERSION}, you may need to upgrade Tattoy"
+        );
+        return Migrated {
+            config,
+            from_version,
+            deprecations,
+        };
+    }
+
+    for (version, migration) in MIGRATIONS {
+        if version <= from_version {
+            continue;
+        }
+
+        tracing::debug!("Migrating config to version {version}");
+        deprecations.extend(migration(config.as_table_mut()));
+        if let Some(profiles) = config
+            .get_mut(super::profiles::PROFILES_KEY)
+            .and_then(toml_edit::Item::as_table_like_mut)
+        {
+            for (name, profile) in profiles.iter_mut() {
+                if let Some(profile_table) = profile.as_table_like_mut() {
+                    for deprecation in migration(profile_table) {
+                        deprecations.push(format!("Profile '{}': {deprecation}", name.get()));
+                    }
+                }
+            }
+        }
+    }
+
+    stamp_version(&mut config);
+
+    Migrated {
+        config,
+        from_version,
+        deprecations,
+    }
+}
+
+/// Set the config's version to the current version, keeping any comments around it.
+fn stamp_version(config: &mut toml_edit::DocumentMut) {
+    let current = toml_edit::Value::from(i64::from(CURRENT_CONFIG_VERSION));
+    match config
+        .get_mut(VERSION_KEY)
+        .and_then(toml_edit::Item::as_value_mut)
+    {
+        Some(version) => {
+            let decor = version.decor().clone();
+            *version = current;
+            *version.decor_mut() = decor;
+        }
+        None => {
+            config.insert(VERSION_KEY, toml_edit::Item::Value(current));
+        }
+    }
+}
+
+/// Migrate a config file. If any options were changed then the original file is backed up and the
+/// migrated config is written in its place.
+pub(crate) fn migrate_file(path: &std::path::Path, data: &str) -> Result<Migrated> {
+    let document = data.parse::<toml_edit::DocumentMut>()?;
+    let migrated = migrate(document);
+    if migrated.deprecations.is_empty() {
+        return Ok(migrated);
+    }
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.backup", migrated.from_version));
+    tracing::info!("Backing up config to {backup:?} before writing migrated config");
+    std::fs::copy(path, &backup)?;
+    std::fs::write(path, migrated.config.to_string())?;
+
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn document(config: &str) -> toml_edit::DocumentMut {
+        config.parse().unwrap()
+    }
+
+    fn parse(config: &str) -> toml::Table {
+        toml::from_str(config).unwrap()
+    }
+
+    #[test]
+    fn unversioned_config_is_stamped() {
+        let migrated = migrate(document("frame_rate = 30\n"));
+        assert_eq!(migrated.from_version, 0);
+        assert!(migrated.deprecations.is_empty());
+        assert_eq!(
+            migrated.config.to_string(),
+            format!("frame_rate = 30\nconfig_version = {CURRENT_CONFIG_VERSION}\n")
+        );
+    }
+
+    #[test]
+    fn renaming_nested_keys() {
+        let mut config = document(
+            "
+            [shader]
+            file = \"shaders/foo.glsl\"
+            ",
+        );
+        let message = rename_key(config.as_table_mut(), "shader.file", "effects.shader.path");
+        assert_eq!(
+            message.unwrap(),
+            "`shader.file` is deprecated and has been renamed to `effects.shader.path`"
+        );
+        assert_eq!(
+            parse(&config.to_string()),
+            parse(
+                "
+                [shader]
+                [effects.shader]
+                path = \"shaders/foo.glsl\"
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn renaming_missing_key_does_nothing() {
+        let mut config = document("frame_rate = 30\n");
+        assert_eq!(rename_key(config.as_table_mut(), "fps", "frame_rate"), None);
+        assert_eq!(config.to_string(), "frame_rate = 30\n");
+    }
+
+    #[test]
+    fn renaming_never_overwrites() {
+        let mut config = document("fps = 10\nframe_rate = 30");
+        assert!(rename_key(config.as_table_mut(), "fps", "frame_rate").is_some());
+        assert_eq!(parse(&config.to_string()), parse("frame_rate = 30"));
+    }
+
+    #[test]
+    fn renaming_in_profiles() {
+        let migrated = migrate(document(
+            "
+            config_version = 1
+            [profiles.presentation]
+            minimap = { max_width = 5 }
+            ",
+        ));
+        assert_eq!(
+            migrated.deprecations,
+            vec![
+                "Profile 'presentation': `minimap.max_width` is deprecated and has been renamed \
+                 to `minimap.width`"
+            ]
+        );
+        assert_eq!(
+            migrated.table().unwrap(),
+            parse(&format!(
+                "
+                config_version = {CURRENT_CONFIG_VERSION}
+                [profiles.presentation]
+                minimap = {{ width = 5 }}
+                "
+            ))
+        );
+    }
+
+    #[test]
+    fn migrated_files_keep_their_comments() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tattoy.toml");
+        let original = "\
+# My config
+config_version = 1
+
+[minimap]
+# Nice and thin
+max_width = 10 # Columns
+";
+        std::fs::write(&path, original).unwrap();
+
+        let migrated = migrate_file(&path, original).unwrap();
+        assert_eq!(migrated.deprecations.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!(
+                "\
+# My config
+config_version = {CURRENT_CONFIG_VERSION}
+
+[minimap]
+# Nice and thin
+width = 10 # Columns
+"
+            )
+        );
+        assert_eq!(
+            std::fs::read_to_string(directory.path().join("tattoy.toml.v1.backup")).unwrap(),
+            original
+        );
+    }
+}
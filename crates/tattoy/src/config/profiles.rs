@@ -0,0 +1,223 @@
+//! Named config profiles, eg `[profiles.presentation]`, that override a subset of the main config.
+//!
+//! A profile is just a partial copy of the main config. When a profile is active its values are
+//! deeply merged over the top of the main config before the config is parsed. So there's no need
+//! for any of the tattoys to know about profiles, they just receive a new config through the usual
+//! `Protocol::Config` message.
+
+use color_eyre::eyre::Result;
+
+/// The name of the config table that contains all the profiles.
+pub const PROFILES_KEY: &str = "profiles";
+
+/// The name of the profile that is automatically used for remote sessions, eg over SSH.
+pub const REMOTE_PROFILE_NAME: &str = "remote";
+
+/// The built-in reduced-effects profile for remote sessions. It can be overridden by defining a
+/// `[profiles.remote]` section in the main config.
+pub(crate) const DEFAULT_REMOTE_PROFILE: &str = "
+    frame_rate = 10
+    shader = { enabled = false }
+    bg_command = { enabled = false }
+";
+
+/// Environment variables that are set by SSH servers.
+const SSH_ENV_VARS: [&str; 3] = ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"];
+
+/// Values of `TERM` that suggest a basic, low-bandwidth terminal.
+const LOW_BANDWIDTH_TERMS: [&str; 4] = ["linux", "vt100", "vt220", "dumb"];
+
+/// All the user-defined profiles, indexed by name.
+pub(crate) type Profiles = std::collections::BTreeMap<String, toml::Table>;
+
+/// Apply the given profile, if any, to the raw TOML of the main config.
+pub(crate) fn apply(mut config: toml::Table, maybe_profile: Option<&str>) -> Result<toml::Table> {
+    let Some(profile_name) = maybe_profile else {
+        return Ok(config);
+    };
+
+    let maybe_profiles = config.get(PROFILES_KEY).and_then(toml::Value::as_table);
+    let maybe_user_profile = maybe_profiles
+        .and_then(|profiles| profiles.get(profile_name))
+        .and_then(toml::Value::as_table);
+    let profile_table = if let Some(profile) = maybe_user_profile {
+        profile.clone()
+    } else if profile_name == REMOTE_PROFILE_NAME {
+        toml::from_str(DEFAULT_REMOTE_PROFILE)?
+    } else if let Some(profiles) = maybe_profiles {
+        let available = profiles.keys().cloned().collect::<Vec<String>>().join(", ");
+        color_eyre::eyre::bail!("Profile '{profile_name}' not found. Available: {available}");
+    } else {
+        color_eyre::eyre::bail!("Profile '{profile_name}' not found, no profiles are defined");
+    };
+
+    tracing::debug!("Applying config profile: {profile_name}");
+    merge(&mut config, profile_table);
+    Ok(config)
+}
+
+/// Recursively merge the overrides into the base. Tables are merged key by key, all other values,
+/// including arrays, are replaced wholesale.
+pub(crate) fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        if let toml::Value::Table(override_table) = value {
+            if let Some(toml::Value::Table(base_table)) = base.get_mut(&key) {
+                merge(base_table, override_table);
+            } else {
+                base.insert(key, toml::Value::Table(override_table));
+            }
+        } else {
+            base.insert(key, value);
+        }
+    }
+}
+
+/// Get the name of the profile that follows the current one. Cycling includes the "no profile"
+/// state, so that it's always possible to get back to the main config.
+pub(crate) fn next(profiles: &Profiles, maybe_current: Option<&str>) -> Option<String> {
+    match maybe_current {
+        None => profiles.keys().next().cloned(),
+        Some(current) => profiles
+            .keys()
+            .skip_while(|name| name.as_str() != current)
+            .nth(1)
+            .cloned(),
+    }
+}
+
+/// Is Tattoy running in a remote session (eg over SSH) or in a basic low-bandwidth terminal?
+pub(crate) fn is_remote_session() -> bool {
+    is_remote_session_from_env(|name| std::env::var(name).ok())
+}
+
+/// Detect a remote session using the given environment variable getter.
+fn is_remote_session_from_env<F: Fn(&str) -> Option<String>>(get_env: F) -> bool {
+    let is_ssh = SSH_ENV_VARS
+        .iter()
+        .any(|name| get_env(name).is_some_and(|value| !value.is_empty()));
+    let is_low_bandwidth = get_env("TERM")
+        .is_some_and(|term| LOW_BANDWIDTH_TERMS.contains(&term.as_str()));
+    is_ssh || is_low_bandwidth
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        frame_rate = 30
+
+        [minimap]
+        enabled = true
+        max_width = 10
+
+        [profiles.battery]
+        frame_rate = 10
+        minimap = { enabled = false }
+
+        [profiles.presentation]
+        show_tattoy_indicator = false
+    "#;
+
+    fn parse() -> toml::Table {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    #[test]
+    fn no_profile_leaves_config_untouched() {
+        let config = apply(parse(), None).unwrap();
+        assert_eq!(config, parse());
+    }
+
+    #[test]
+    fn profile_overrides_nested_values() {
+        let mut config = apply(parse(), Some("battery")).unwrap();
+        config.remove(PROFILES_KEY);
+        let expected: toml::Table = toml::from_str(
+            "
+            frame_rate = 10
+            minimap = { enabled = false, max_width = 10 }
+            ",
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let error = apply(parse(), Some("nope")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Profile 'nope' not found. Available: battery, presentation"
+        );
+    }
+
+    #[test]
+    fn builtin_remote_profile() {
+        let mut config = apply(parse(), Some(REMOTE_PROFILE_NAME)).unwrap();
+        config.remove(PROFILES_KEY);
+        let expected: toml::Table = toml::from_str(
+            "
+            frame_rate = 10
+            minimap = { enabled = true, max_width = 10 }
+            shader = { enabled = false }
+            bg_command = { enabled = false }
+            ",
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn user_defined_remote_profile_takes_precedence() {
+        let config: toml::Table = toml::from_str(
+            "
+            frame_rate = 30
+            [profiles.remote]
+            frame_rate = 20
+            ",
+        )
+        .unwrap();
+        let profiled = apply(config, Some(REMOTE_PROFILE_NAME)).unwrap();
+        assert_eq!(
+            profiled.get("frame_rate").unwrap().as_integer(),
+            Some(20)
+        );
+    }
+
+    #[test]
+    fn detecting_remote_sessions() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| (*value).to_owned())
+            }
+        };
+        assert!(!is_remote_session_from_env(env(&[(
+            "TERM",
+            "xterm-256color"
+        )])));
+        assert!(is_remote_session_from_env(env(&[(
+            "SSH_CONNECTION",
+            "10.0.0.1 22 10.0.0.2 22"
+        )])));
+        assert!(is_remote_session_from_env(env(&[("TERM", "linux")])));
+    }
+
+    #[test]
+    fn cycling_profiles() {
+        let profiles: Profiles = parse()
+            .get(PROFILES_KEY)
+            .unwrap()
+            .clone()
+            .try_into()
+            .unwrap();
+        assert_eq!(next(&profiles, None), Some("battery".to_owned()));
+        assert_eq!(
+            next(&profiles, Some("battery")),
+            Some("presentation".to_owned())
+        );
+        assert_eq!(next(&profiles, Some("presentation")), None);
+    }
+}
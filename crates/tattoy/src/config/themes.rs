@@ -0,0 +1,111 @@
+//! Themes bundle together everything that defines a "look", eg the shader, colour grading,
+//! minimap settings and palette overrides, into a single shareable TOML file.
+//!
+//! Themes live in the `themes/` directory of the config directory and are selected by name with
+//! `theme = "synthwave"` in the main config. A theme is just a partial copy of the main config, so
+//! any setting can be bundled. The theme's values take precedence over the main config.
+
+use color_eyre::eyre::Result;
+
+/// The name of the directory where theme files are kept.
+pub const THEMES_DIRECTORY_NAME: &str = "themes";
+
+/// The config key used to select a theme.
+const THEME_KEY: &str = "theme";
+
+/// Apply the theme that is named in the config, if any.
+pub(crate) fn apply(
+    config: toml::Table,
+    config_directory: &std::path::Path,
+) -> Result<toml::Table> {
+    let Some(theme_value) = config.get(THEME_KEY) else {
+        return Ok(config);
+    };
+    let Some(theme_name) = theme_value.as_str() else {
+        color_eyre::eyre::bail!("`{THEME_KEY}` must be a string, found: {theme_value}");
+    };
+
+    let theme_path = path(config_directory, theme_name);
+    if !theme_path.exists() {
+        color_eyre::eyre::bail!(
+            "Theme '{theme_name}' not found at: {}",
+            theme_path.display()
+        );
+    }
+
+    tracing::debug!("Applying theme '{theme_name}' from: {theme_path:?}");
+    let data = std::fs::read_to_string(&theme_path)?;
+    let theme = toml::from_str::<toml::Table>(&data)?;
+    Ok(merge(config, theme))
+}
+
+/// Merge the theme over the top of the config. All the changes happen on a single copy of the
+/// config so that the theme is applied atomically.
+fn merge(mut config: toml::Table, mut theme: toml::Table) -> toml::Table {
+    // Themes can't select other themes.
+    theme.remove(THEME_KEY);
+    super::profiles::merge(&mut config, theme);
+    config
+}
+
+/// The path to a named theme file.
+pub(crate) fn path(config_directory: &std::path::Path, name: &str) -> std::path::PathBuf {
+    config_directory
+        .join(THEMES_DIRECTORY_NAME)
+        .join(format!("{name}.toml"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn theme_overrides_config() {
+        let config: toml::Table = toml::from_str(
+            r#"
+            theme = "synthwave"
+            [shader]
+            enabled = false
+            path = "shaders/soft_shadows.glsl"
+            [color]
+            hue = 0.0
+            "#,
+        )
+        .unwrap();
+        let theme: toml::Table = toml::from_str(
+            r#"
+            theme = "recursive"
+            [shader]
+            enabled = true
+            [color]
+            hue = 0.5
+            [palette]
+            1 = [255, 0, 128]
+            "#,
+        )
+        .unwrap();
+        let expected: toml::Table = toml::from_str(
+            r#"
+            theme = "synthwave"
+            [shader]
+            enabled = true
+            path = "shaders/soft_shadows.glsl"
+            [color]
+            hue = 0.5
+            [palette]
+            1 = [255, 0, 128]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(merge(config, theme), expected);
+    }
+
+    #[test]
+    fn missing_theme_is_an_error() {
+        let config: toml::Table = toml::from_str(r#"theme = "nope""#).unwrap();
+        let directory = std::path::PathBuf::from("/non-existent");
+        let error = apply(config, &directory).unwrap_err();
+        assert!(error.to_string().starts_with("Theme 'nope' not found"));
+    }
+}
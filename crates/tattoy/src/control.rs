@@ -0,0 +1,712 @@
+//! A control socket, so that scripts inside or outside of a Tattoy session can talk to a running
+//! Tattoy, eg with `tattoy ctl` or `tattoy notify`. It's a Unix socket, or a named pipe on
+//! Windows. Each request is a single line of JSON, and gets a single line of JSON in response.
+
+use std::sync::Arc;
+
+use color_eyre::eyre::{ContextCompat as _, Result, WrapErr as _};
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+
+/// The environment variable that tells programs running inside Tattoy where its control socket is.
+pub(crate) const SOCKET_ENV_VAR: &str = "TATTOY_SOCKET";
+
+/// The environment variable with the config profile that Tattoy started with, if any.
+const PROFILE_ENV_VAR: &str = "TATTOY_PROFILE";
+
+/// The environment variable with the name of the shader that Tattoy started with, if any.
+const SHADER_ENV_VAR: &str = "TATTOY_SHADER";
+
+/// The requests that can be sent to the control socket.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Request {
+    /// Raise a notification.
+    Notify {
+        /// The title of the notification.
+        title: String,
+        /// The optional body of the notification.
+        body: Option<String>,
+        /// The urgency level of the notification.
+        level: crate::tattoys::notifications::message::Level,
+    },
+    /// Toggle one of the tattoys, or all of Tattoy's rendering when no tattoy is given.
+    Toggle {
+        /// The name of the tattoy, eg `minimap`.
+        tattoy: Option<String>,
+    },
+    /// Set the opacity of one of the tattoys.
+    SetOpacity {
+        /// The name of the tattoy, eg `minimap`.
+        tattoy: String,
+        /// The new opacity, between 0.0 and 1.0.
+        opacity: f32,
+    },
+    /// Switch to another shader.
+    SwitchShader {
+        /// The path to the shader file.
+        path: std::path::PathBuf,
+    },
+    /// Switch to a config profile, just like the `profile_next` keybinding does.
+    SwitchProfile {
+        /// The name of the profile, or the main config when not given.
+        name: Option<String>,
+    },
+    /// Reload the config file.
+    ReloadConfig {
+        /// Also forget all the config changes that were made with the control socket.
+        reset: bool,
+    },
+    /// Get the status of Tattoy.
+    Status,
+    /// Get Tattoy's performance statistics.
+    Stats,
+    /// Get a single value of Tattoy's current state, eg for a prompt segment.
+    Get {
+        /// The value to get.
+        key: ContextKey,
+    },
+    /// Replace the command running in Tattoy.
+    Respawn {
+        /// The command to start, or the configured `command` when not given.
+        command: Option<String>,
+    },
+    /// Open the shader browser, or close it if it's already open.
+    BrowseShaders,
+}
+
+/// The values of Tattoy's state that can be queried on their own. They're meant for prompt tools,
+/// like starship, which need to be fast and only want plain text.
+#[derive(
+    serde::Serialize, serde::Deserialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ContextKey {
+    /// Whether the user is scrolling through the scrollback, `true` or `false`.
+    Scrolling,
+    /// The name of the current shader, or nothing when shaders are disabled.
+    Shader,
+    /// The active config profile, or nothing when there isn't one.
+    Profile,
+    /// The number of frames painted in the last second.
+    Fps,
+    /// Whether Tattoy is rendering anything apart from the terminal, `true` or `false`.
+    Rendering,
+}
+
+/// The responses to requests.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Response {
+    /// The request succeeded.
+    Ok,
+    /// The status of Tattoy.
+    Status(Status),
+    /// Tattoy's performance statistics.
+    Stats(crate::performance::Snapshot),
+    /// A single value of Tattoy's state.
+    Value {
+        /// The value as plain text. Empty when there's no value, eg when no profile is active.
+        value: String,
+    },
+    /// The request failed.
+    Error {
+        /// What went wrong.
+        message: String,
+    },
+}
+
+/// A summary of the state of a running Tattoy.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Status {
+    /// The version of Tattoy.
+    pub version: String,
+    /// Whether Tattoy is rendering anything apart from the terminal.
+    pub is_rendering_enabled: bool,
+    /// The active config profile.
+    pub profile: Option<String>,
+    /// The path to the main config file.
+    pub config_path: std::path::PathBuf,
+    /// Whether each of the toggleable tattoys is enabled.
+    pub tattoys: std::collections::BTreeMap<String, bool>,
+    /// The URL of the web viewer, whilst the terminal is being streamed.
+    #[serde(default)]
+    pub streaming_url: Option<String>,
+}
+
+/// The path of the control socket for this Tattoy process. It's in the user's private runtime
+/// directory, `$XDG_RUNTIME_DIR`, when there is one, because anyone that can connect to the socket
+/// can run commands as the user.
+#[cfg(unix)]
+fn socket_path() -> std::path::PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("tattoy-{}.sock", std::process::id()))
+}
+
+/// The path of the control socket's named pipe for this Tattoy process.
+#[cfg(windows)]
+fn socket_path() -> std::path::PathBuf {
+    format!(r"\\.\pipe\tattoy-{}", std::process::id()).into()
+}
+
+/// Listens for connections to the control socket.
+#[cfg(unix)]
+struct Listener {
+    /// The path of the socket.
+    path: std::path::PathBuf,
+    /// The socket itself.
+    listener: tokio::net::UnixListener,
+}
+
+#[cfg(unix)]
+impl Listener {
+    /// Start listening on the socket at the given path. Only the user can connect to it.
+    fn bind(path: std::path::PathBuf) -> Result<Self> {
+        use std::os::unix::fs::MetadataExt as _;
+        use std::os::unix::fs::PermissionsExt as _;
+
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            // SAFETY: `getuid()` can't fail and has no side effects.
+            let uid = unsafe { libc::getuid() };
+            if metadata.uid() != uid {
+                color_eyre::eyre::bail!(
+                    "Refusing to replace a control socket that belongs to someone else"
+                );
+            }
+            // A socket left behind by a previous process that had the same PID.
+            std::fs::remove_file(&path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Self { path, listener })
+    }
+
+    /// Wait for the next connection.
+    async fn accept(&mut self) -> std::io::Result<tokio::net::UnixStream> {
+        Ok(self.listener.accept().await?.0)
+    }
+
+    /// Stop listening.
+    fn close(self) -> Result<()> {
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Listens for connections to the control socket's named pipe.
+#[cfg(windows)]
+struct Listener {
+    /// The path of the named pipe.
+    path: std::path::PathBuf,
+    /// The instance of the named pipe that is waiting for the next connection.
+    server: tokio::net::windows::named_pipe::NamedPipeServer,
+}
+
+#[cfg(windows)]
+impl Listener {
+    /// Start listening on the named pipe at the given path.
+    fn bind(path: std::path::PathBuf) -> Result<Self> {
+        let server = tokio::net::windows::named_pipe::ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&path)?;
+        Ok(Self { path, server })
+    }
+
+    /// Wait for the next connection. Each connection gets its own instance of the named pipe, so
+    /// a new instance is made to wait for the connection after it.
+    async fn accept(
+        &mut self,
+    ) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+        self.server.connect().await?;
+        let next = tokio::net::windows::named_pipe::ServerOptions::new().create(&self.path)?;
+        Ok(core::mem::replace(&mut self.server, next))
+    }
+
+    /// Stop listening. Named pipes are removed once all their instances are closed.
+    #[expect(
+        clippy::unnecessary_wraps,
+        reason = "It matches the Unix version of the listener"
+    )]
+    fn close(self) -> Result<()> {
+        drop(self);
+        Ok(())
+    }
+}
+
+/// Connect to the control socket at the given path.
+#[cfg(unix)]
+async fn connect(path: &std::path::Path) -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+/// Connect to the control socket's named pipe at the given path.
+#[cfg(windows)]
+#[expect(
+    clippy::unused_async,
+    reason = "It matches the Unix version of connecting"
+)]
+async fn connect(
+    path: &std::path::Path,
+) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(path)
+}
+
+/// Start listening on the control socket. The socket's path is exported to the environment, so
+/// that the user's shell, and everything that it runs, can find it.
+pub(crate) fn start(
+    state: Arc<crate::shared_state::SharedState>,
+) -> Result<tokio::task::JoinHandle<Result<()>>> {
+    let path = socket_path();
+    let mut listener = Listener::bind(path.clone())
+        .wrap_err_with(|| format!("Couldn't bind control socket: {}", path.display()))?;
+    std::env::set_var(SOCKET_ENV_VAR, &path);
+    export_context(&state);
+    tracing::debug!("Listening on control socket: {}", path.display());
+
+    Ok(tokio::spawn(async move {
+        let mut protocol = state.protocol_tx.subscribe();
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = listener.accept() => match result {
+                    Ok(stream) => {
+                        tokio::spawn(handle_connection(Arc::clone(&state), stream));
+                    }
+                    Err(error) => tracing::error!("Accepting control socket connection: {error:?}"),
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        listener.close()
+    }))
+}
+
+/// Export some of Tattoy's state to the environment, so that the user's shell, and prompt tools
+/// like starship, can show it without having to query the control socket. These are the values
+/// from when Tattoy started, use `tattoy ctl get` for the current values.
+fn export_context(state: &crate::shared_state::SharedState) {
+    // Nothing else is running yet, so the profile is never locked.
+    let maybe_profile = state
+        .active_profile
+        .try_read()
+        .ok()
+        .and_then(|profile| profile.clone());
+    match maybe_profile {
+        Some(profile) => std::env::set_var(PROFILE_ENV_VAR, profile),
+        None => std::env::remove_var(PROFILE_ENV_VAR),
+    }
+    match shader_name(&state.config.load()) {
+        Some(shader) => std::env::set_var(SHADER_ENV_VAR, shader),
+        None => std::env::remove_var(SHADER_ENV_VAR),
+    }
+}
+
+/// The name of the current shader, eg `soft_shadows` for `shaders/soft_shadows.glsl`. There's no
+/// name when shaders are disabled.
+fn shader_name(config: &crate::config::main::Config) -> Option<String> {
+    if !config.shader.enabled {
+        return None;
+    }
+    config
+        .shader
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+}
+
+/// Handle all the requests from a single connection.
+async fn handle_connection<S>(state: Arc<crate::shared_state::SharedState>, stream: S)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(error) => {
+                tracing::error!("Reading control socket: {error:?}");
+                break;
+            }
+        };
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle_request(&state, request).await,
+            Err(error) => Response::Error {
+                message: format!("Bad request: {error}"),
+            },
+        };
+        if let Response::Error { message } = &response {
+            tracing::warn!("Control socket request failed: {message}");
+        }
+
+        let result = match serde_json::to_string(&response) {
+            Ok(json) => writer.write_all(format!("{json}\n").as_bytes()).await,
+            Err(error) => {
+                tracing::error!("Serialising control socket response: {error:?}");
+                break;
+            }
+        };
+        if let Err(error) = result {
+            tracing::error!("Writing control socket response: {error:?}");
+            break;
+        }
+    }
+}
+
+/// Do what a request asks.
+async fn handle_request(
+    state: &Arc<crate::shared_state::SharedState>,
+    request: Request,
+) -> Response {
+    tracing::debug!("Control socket request: {request:?}");
+    let result = match request {
+        Request::Notify { title, body, level } => {
+            state.send_notification(&title, level, body, false).await;
+            Ok(())
+        }
+        Request::Toggle { tattoy: None } => toggle_rendering(state).await,
+        Request::Toggle {
+            tattoy: Some(tattoy),
+        } => toggle_tattoy(state, &tattoy).await,
+        Request::SetOpacity { tattoy, opacity } => {
+            let opacity = toml::Value::Float(f64::from(opacity.clamp(0.0, 1.0)));
+            override_tattoy_config(state, &tattoy, "opacity", opacity).await
+        }
+        Request::SwitchShader { path } => {
+            let path = toml::Value::String(path.display().to_string());
+            override_tattoy_config(state, "shaders", "path", path).await
+        }
+        Request::SwitchProfile { name } => switch_profile(state, name).await,
+        Request::ReloadConfig { reset } => {
+            if reset {
+                state.config_overrides.write().await.clear();
+                crate::runtime_state::save(state).await;
+            }
+            crate::config::main::Config::reload_and_broadcast(state, "Config reloaded").await;
+            Ok(())
+        }
+        Request::Respawn { command } => state
+            .protocol_tx
+            .send(crate::run::Protocol::RespawnCommand(command))
+            .map(|_| ())
+            .map_err(Into::into),
+        Request::BrowseShaders => state
+            .protocol_tx
+            .send(crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::ToggleShaderBrowser,
+            ))
+            .map(|_| ())
+            .map_err(Into::into),
+        Request::Status => return Response::Status(status(state).await),
+        Request::Stats => return Response::Stats(state.performance.read().await.snapshot()),
+        Request::Get { key } => {
+            return Response::Value {
+                value: context_value(state, key).await,
+            }
+        }
+    };
+
+    match result {
+        Ok(()) => Response::Ok,
+        Err(error) => Response::Error {
+            message: error.to_string(),
+        },
+    }
+}
+
+/// Toggle all of Tattoy's rendering, just like the `toggle_tattoy` keybinding.
+pub(crate) async fn toggle_rendering(state: &Arc<crate::shared_state::SharedState>) -> Result<()> {
+    let existing = state.is_rendering_enabled.get();
+    tracing::debug!("Toggling Tattoy renderer to: {}", !existing);
+    state.is_rendering_enabled.store(!existing);
+    crate::runtime_state::save(state).await;
+    state.protocol_tx.send(crate::run::Protocol::Repaint)?;
+    Ok(())
+}
+
+/// Enable or disable one of the toggleable tattoys.
+async fn toggle_tattoy(state: &Arc<crate::shared_state::SharedState>, tattoy: &str) -> Result<()> {
+    let is_enabled = crate::loader::is_enabled_in_config(tattoy, &*state.config.load());
+    override_tattoy_config(state, tattoy, "enabled", toml::Value::Boolean(!is_enabled)).await
+}
+
+/// Switch to a config profile, or back to the main config.
+async fn switch_profile(
+    state: &Arc<crate::shared_state::SharedState>,
+    maybe_name: Option<String>,
+) -> Result<()> {
+    if let Some(name) = &maybe_name {
+        let profiles = state.config.load().profiles.clone();
+        let is_builtin = name == crate::config::profiles::REMOTE_PROFILE_NAME;
+        if !is_builtin && !profiles.contains_key(name) {
+            let available = profiles.keys().cloned().collect::<Vec<String>>().join(", ");
+            color_eyre::eyre::bail!("Unknown profile '{name}'. Available: {available}");
+        }
+    }
+
+    crate::config::main::Config::switch_profile(state, maybe_name).await;
+    Ok(())
+}
+
+/// Change a single setting of one of the toggleable tattoys. It's remembered between sessions,
+/// until the config is reset.
+async fn override_tattoy_config(
+    state: &Arc<crate::shared_state::SharedState>,
+    tattoy: &str,
+    key: &str,
+    value: toml::Value,
+) -> Result<()> {
+    if !crate::loader::toggleable_tattoys().any(|name| name == tattoy) {
+        let available = crate::loader::toggleable_tattoys()
+            .collect::<Vec<&str>>()
+            .join(", ");
+        color_eyre::eyre::bail!("Unknown tattoy '{tattoy}'. Available: {available}");
+    }
+
+    // A tattoy's config table has the same name as its surface.
+    let table = crate::loader::surface_id(tattoy);
+    crate::runtime_state::override_setting(state, table, key, value).await;
+    crate::config::main::Config::reload_and_broadcast(state, &format!("Changed {tattoy} {key}"))
+        .await;
+    Ok(())
+}
+
+/// A summary of the state of Tattoy.
+async fn status(state: &Arc<crate::shared_state::SharedState>) -> Status {
+    let config = state.config.load();
+    Status {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        is_rendering_enabled: state.is_rendering_enabled.get(),
+        profile: state.active_profile.read().await.clone(),
+        config_path: crate::config::main::Config::main_config_path(state).await,
+        tattoys: crate::loader::toggleable_tattoys()
+            .map(|name| {
+                (
+                    name.to_owned(),
+                    crate::loader::is_enabled_in_config(name, &config),
+                )
+            })
+            .collect(),
+        streaming_url: state.streaming_url.read().await.clone(),
+    }
+}
+
+/// The current value of some of Tattoy's state, as plain text.
+async fn context_value(state: &Arc<crate::shared_state::SharedState>, key: ContextKey) -> String {
+    match key {
+        ContextKey::Scrolling => state.is_scrolling.read().await.to_string(),
+        ContextKey::Shader => shader_name(&state.config.load()).unwrap_or_default(),
+        ContextKey::Profile => state
+            .active_profile
+            .read()
+            .await
+            .clone()
+            .unwrap_or_default(),
+        ContextKey::Fps => format!("{:.0}", state.performance.read().await.frames_per_second()),
+        ContextKey::Rendering => state.is_rendering_enabled.get().to_string(),
+    }
+}
+
+/// Send a request to a running Tattoy. The socket defaults to the one of the Tattoy that this is
+/// being run inside of.
+pub(crate) async fn send(
+    request: &Request,
+    maybe_socket: Option<std::path::PathBuf>,
+) -> Result<Response> {
+    let path = match maybe_socket {
+        Some(path) => path,
+        None => std::env::var_os(SOCKET_ENV_VAR)
+            .map(std::path::PathBuf::from)
+            .with_context(|| {
+                format!(
+                    "Not running inside Tattoy, use `--socket` or `${SOCKET_ENV_VAR}` to choose \
+                     a running Tattoy"
+                )
+            })?,
+    };
+
+    let stream = connect(&path)
+        .await
+        .wrap_err_with(|| format!("Couldn't connect to control socket: {}", path.display()))?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let json = serde_json::to_string(request)?;
+    writer.write_all(format!("{json}\n").as_bytes()).await?;
+
+    let mut response = String::new();
+    tokio::io::BufReader::new(reader)
+        .read_line(&mut response)
+        .await?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+/// Send a request from the CLI, printing the status, statistics or value when they're asked for.
+#[expect(
+    clippy::print_stdout,
+    reason = "Printing to STDOUT means the status can be piped to other tools"
+)]
+pub(crate) async fn run_cli_request(
+    request: &Request,
+    maybe_socket: Option<std::path::PathBuf>,
+) -> Result<()> {
+    match send(request, maybe_socket).await? {
+        Response::Ok => Ok(()),
+        Response::Status(status) => {
+            println!("{}", serde_json::to_string_pretty(&status)?);
+            Ok(())
+        }
+        Response::Stats(stats) => {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            Ok(())
+        }
+        Response::Value { value } => {
+            println!("{value}");
+            Ok(())
+        }
+        Response::Error { message } => color_eyre::eyre::bail!(message),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_format() {
+        let request = Request::Notify {
+            title: "Build finished".to_owned(),
+            body: None,
+            level: crate::tattoys::notifications::message::Level::Info,
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"notify","title":"Build finished","body":null,"level":"info"}"#
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let toggle: Request =
+            serde_json::from_str(r#"{"type":"toggle","tattoy":"minimap"}"#).unwrap();
+        assert_eq!(
+            toggle,
+            Request::Toggle {
+                tattoy: Some("minimap".to_owned())
+            }
+        );
+
+        let respawn: Request =
+            serde_json::from_str(r#"{"type":"respawn","command":"htop"}"#).unwrap();
+        assert_eq!(
+            respawn,
+            Request::Respawn {
+                command: Some("htop".to_owned())
+            }
+        );
+    }
+
+    #[test]
+    fn response_format() {
+        let error = Response::Error {
+            message: "Nope".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"type":"error","message":"Nope"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Response::Ok).unwrap(),
+            r#"{"type":"ok"}"#
+        );
+    }
+
+    #[test]
+    fn get_format() {
+        let request: Request = serde_json::from_str(r#"{"type":"get","key":"fps"}"#).unwrap();
+        assert_eq!(
+            request,
+            Request::Get {
+                key: ContextKey::Fps
+            }
+        );
+        let value = Response::Value {
+            value: "60".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"type":"value","value":"60"}"#
+        );
+    }
+
+    #[test]
+    fn naming_shaders() {
+        let mut config = crate::config::main::Config::default();
+        config.shader.enabled = true;
+        config.shader.path = "shaders/soft_shadows.glsl".into();
+        assert_eq!(shader_name(&config), Some("soft_shadows".to_owned()));
+        config.shader.enabled = false;
+        assert_eq!(shader_name(&config), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn only_the_user_can_use_the_socket() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tattoy.sock");
+        std::fs::write(&path, "stale").unwrap();
+
+        let listener = Listener::bind(path.clone()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        listener.close().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn switching_profiles() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        state.config.modify(|config| {
+            config
+                .profiles
+                .insert("presentation".to_owned(), toml::Table::new());
+        });
+
+        let request: Request =
+            serde_json::from_str(r#"{"type":"switch_profile","name":"presentation"}"#).unwrap();
+        assert_eq!(handle_request(&state, request).await, Response::Ok);
+        assert_eq!(
+            state.active_profile.read().await.as_deref(),
+            Some("presentation")
+        );
+
+        let unknown = Request::SwitchProfile {
+            name: Some("nope".to_owned()),
+        };
+        assert!(matches!(
+            handle_request(&state, unknown).await,
+            Response::Error { .. }
+        ));
+        assert_eq!(
+            state.active_profile.read().await.as_deref(),
+            Some("presentation")
+        );
+
+        let default = Request::SwitchProfile { name: None };
+        assert_eq!(handle_request(&state, default).await, Response::Ok);
+        assert_eq!(*state.active_profile.read().await, None);
+    }
+}
@@ -0,0 +1,277 @@
+//! Bundles of everything that helps to debug Tattoy, for attaching to GitHub issues. A bundle is
+//! made with `tattoy doctor --bundle`, and whenever a running Tattoy exits with an error. It's a
+//! `.tar.gz` of the doctor's report, the log file, the config and the palette.
+//!
+//! Anything in the config that looks like a secret is scrubbed. Protocol messages are only
+//! recorded by their kind, so nothing that was typed into, or shown in, the terminal is bundled.
+
+use color_eyre::eyre::{Result, WrapErr as _};
+use std::io::{Read as _, Seek as _};
+
+/// The number of the most recent protocol messages that are kept.
+const MAX_PROTOCOL_MESSAGES: usize = 50;
+
+/// The most of the end of the log file that's bundled, in bytes. Trace logs can be huge.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Parts of config keys that suggest that their values are secret.
+const SECRET_KEYS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "passphrase",
+    "api_key",
+    "apikey",
+    "credential",
+    "auth",
+];
+
+/// What scrubbed values are replaced with.
+const SCRUBBED: &str = "\"<scrubbed>\"";
+
+/// The most recent protocol messages, oldest first.
+pub(crate) type ProtocolMessages = tokio::sync::RwLock<std::collections::VecDeque<String>>;
+
+/// Remember a protocol message, in case Tattoy crashes.
+pub(crate) async fn record(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    message: &crate::run::Protocol,
+) {
+    let line = format!(
+        "{} {}",
+        chrono::Local::now().format("%H:%M:%S%.3f"),
+        describe(message)
+    );
+    let mut messages = state.recent_protocol_messages.write().await;
+    if messages.len() >= MAX_PROTOCOL_MESSAGES {
+        messages.pop_front();
+    }
+    messages.push_back(line);
+}
+
+/// A description of a protocol message that doesn't include anything from the terminal itself.
+fn describe(message: &crate::run::Protocol) -> String {
+    match message {
+        crate::run::Protocol::Output(_) => "Output".to_owned(),
+        crate::run::Protocol::End => "End".to_owned(),
+        crate::run::Protocol::Resize { width, height } => format!("Resize {width}x{height}"),
+        crate::run::Protocol::Input(_) => "Input".to_owned(),
+        crate::run::Protocol::CursorVisibility(is_visible) => {
+            format!("CursorVisibility({is_visible})")
+        }
+        crate::run::Protocol::Config { changes, .. } => format!("Config {changes:?}"),
+        crate::run::Protocol::KeybindEvent(action) => format!("KeybindEvent({action:?})"),
+        crate::run::Protocol::Notification(_) => "Notification".to_owned(),
+        crate::run::Protocol::Repaint => "Repaint".to_owned(),
+        crate::run::Protocol::Palette(_) => "Palette".to_owned(),
+        crate::run::Protocol::QueryTerminal(_) => "QueryTerminal".to_owned(),
+        crate::run::Protocol::Bell => "Bell".to_owned(),
+        crate::run::Protocol::WorkingDirectory(_) => "WorkingDirectory".to_owned(),
+        crate::run::Protocol::CommandStarted => "CommandStarted".to_owned(),
+        crate::run::Protocol::CommandFinished(exit_code) => {
+            format!("CommandFinished({exit_code:?})")
+        }
+        crate::run::Protocol::CursorMoved(_) => "CursorMoved".to_owned(),
+        crate::run::Protocol::Screensaver(is_active) => format!("Screensaver({is_active})"),
+        crate::run::Protocol::ReservedRows(rows) => format!("ReservedRows({rows:?})"),
+        crate::run::Protocol::Bookmarks(bookmarks) => format!("Bookmarks({})", bookmarks.len()),
+        crate::run::Protocol::ScrollTo(lines) => format!("ScrollTo({lines})"),
+        crate::run::Protocol::RespawnCommand(_) => "RespawnCommand".to_owned(),
+        crate::run::Protocol::CursorColour(_) => "CursorColour".to_owned(),
+        crate::run::Protocol::PreviewShader(path) => format!("PreviewShader({path:?})"),
+    }
+}
+
+/// Replace the values of any config settings that might be secret.
+fn scrub(config: &str) -> String {
+    config
+        .lines()
+        .map(|line| {
+            let Some((key, _)) = line.split_once('=') else {
+                return line.to_owned();
+            };
+            let name = key.trim().to_lowercase();
+            if name.starts_with('#') || !SECRET_KEYS.iter().any(|secret| name.contains(secret)) {
+                return line.to_owned();
+            }
+            format!("{key}= {SCRUBBED}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The end of a file, so that huge log files don't make huge bundles.
+fn tail(path: &std::path::Path, max_size: u64) -> Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    file.seek(std::io::SeekFrom::Start(size.saturating_sub(max_size)))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Write files into a new `.tar.gz`.
+pub(crate) fn write<N: AsRef<std::path::Path>>(
+    path: &std::path::Path,
+    files: &[(N, Vec<u8>)],
+) -> Result<()> {
+    let file = std::fs::File::create(path)?;
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    let modified = u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default();
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(u64::try_from(contents.len())?);
+        header.set_mode(0o644);
+        header.set_mtime(modified);
+        header.set_cksum();
+        archive.append_data(&mut header, name, contents.as_slice())?;
+    }
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Read a file to bundle, explaining why it's missing if it can't be read.
+fn read_or_explain(path: &std::path::Path) -> Vec<u8> {
+    std::fs::read(path)
+        .unwrap_or_else(|error| format!("Couldn't read {}: {error}\n", path.display()).into_bytes())
+}
+
+/// Read a config file to bundle, with any secrets scrubbed.
+fn read_and_scrub(path: &std::path::Path) -> Vec<u8> {
+    scrub(&String::from_utf8_lossy(&read_or_explain(path))).into_bytes()
+}
+
+/// The report at the top of the bundle, with everything that isn't a file.
+async fn report(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    diagnosis: &str,
+) -> String {
+    let mut lines = vec![
+        diagnosis.to_owned(),
+        String::new(),
+        format!("OS: {} {}", std::env::consts::OS, std::env::consts::ARCH),
+        format!("Shader: {}", state.config.load().shader.path.display()),
+        String::new(),
+        "Recent warnings and errors:".to_owned(),
+    ];
+    if let Ok(entries) = state.recent_logs.lock() {
+        lines.extend(entries.iter().map(crate::tattoys::log_viewer::Entry::line));
+    }
+    lines.push(String::new());
+    lines.push("Recent protocol messages:".to_owned());
+    lines.extend(state.recent_protocol_messages.read().await.iter().cloned());
+    format!("{}\n", lines.join("\n"))
+}
+
+/// Bundle everything that helps to debug Tattoy, next to the log file. `diagnosis` is the report
+/// from `tattoy doctor`. Returns the path of the bundle.
+pub(crate) async fn create(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    diagnosis: &str,
+) -> Result<std::path::PathBuf> {
+    let config = state.config.load();
+    let mut log_path = state.log_file.read().await.clone();
+    if log_path.as_os_str().is_empty() {
+        log_path.clone_from(&config.log_path);
+    }
+    let config_path = crate::config::main::Config::main_config_path(state).await;
+    let palette_path = crate::palette::parser::Parser::palette_config_path(state).await;
+    let runtime_state_path = crate::runtime_state::path(state).await;
+
+    let log = tail(&log_path, MAX_LOG_SIZE).unwrap_or_else(|error| {
+        format!("Couldn't read {}: {error}\n", log_path.display()).into_bytes()
+    });
+    let files = [
+        ("report.txt", report(state, diagnosis).await.into_bytes()),
+        ("tattoy.log", log),
+        ("tattoy.toml", read_and_scrub(&config_path)),
+        (
+            crate::runtime_state::RUNTIME_STATE_FILE_NAME,
+            read_and_scrub(&runtime_state_path),
+        ),
+        ("palette.toml", read_or_explain(&palette_path)),
+    ];
+
+    let directory = log_path
+        .parent()
+        .map_or_else(std::env::temp_dir, std::path::Path::to_path_buf);
+    std::fs::create_dir_all(&directory)?;
+    let name = format!(
+        "tattoy-report-{}.tar.gz",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let path = directory.join(name);
+    write(&path, &files).with_context(|| format!("Writing bundle to {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scrubbing_secrets() {
+        let config = "command = \"bash\"\n\
+                      # auth = \"left alone\"\n\
+                      [llm]\n\
+                      api_key = \"abc123\"\n\
+                      GitHub_Token=\"xyz\"";
+        assert_eq!(
+            scrub(config),
+            "command = \"bash\"\n\
+             # auth = \"left alone\"\n\
+             [llm]\n\
+             api_key = \"<scrubbed>\"\n\
+             GitHub_Token= \"<scrubbed>\""
+        );
+    }
+
+    #[test]
+    fn describing_protocol_messages_without_their_contents() {
+        let message = crate::run::Protocol::QueryTerminal("secret".to_owned());
+        assert_eq!(describe(&message), "QueryTerminal");
+        let message = crate::run::Protocol::Resize {
+            width: 80,
+            height: 24,
+        };
+        assert_eq!(describe(&message), "Resize 80x24");
+    }
+
+    #[test]
+    fn bundling_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("bundle.tar.gz");
+        write(
+            &path,
+            &[("report.txt", b"Tattoy".to_vec()), ("empty", Vec::new())],
+        )
+        .unwrap();
+
+        let decoder = flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap());
+        let mut archive = tar::Archive::new(decoder);
+        let mut names = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            names.push((entry.path().unwrap().display().to_string(), contents));
+        }
+        assert_eq!(
+            names,
+            vec![
+                ("report.txt".to_owned(), "Tattoy".to_owned()),
+                ("empty".to_owned(), String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn reading_the_end_of_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tattoy.log");
+        std::fs::write(&path, "first\nsecond\n").unwrap();
+        assert_eq!(tail(&path, 7).unwrap(), b"second\n");
+        assert_eq!(tail(&path, 100).unwrap(), b"first\nsecond\n");
+    }
+}
@@ -0,0 +1,57 @@
+//! Forward the PTY's cursor style to the user's terminal, so that applications that restyle the
+//! cursor look the same in Tattoy. Applications set the cursor's colour with the OSC 12 code, and
+//! its shape, and whether it blinks, with the DECSCUSR code. Termwiz only sends cursor shapes to
+//! terminals whose terminfo has the `Ss` capability, and doesn't know about cursor colours at all,
+//! so Tattoy sends both codes itself.
+
+/// The OSC code that resets the cursor's colour to the terminal's default.
+const RESET_CURSOR_COLOUR: &str = "\x1b]112\x1b\\";
+
+/// The ANSI code that sets the cursor's colour, or resets it to the terminal's default.
+pub(crate) fn colour_code(maybe_colour: Option<termwiz::color::SrgbaTuple>) -> String {
+    maybe_colour.map_or_else(
+        || RESET_CURSOR_COLOUR.to_owned(),
+        |colour| format!("\x1b]12;{}\x1b\\", colour.to_rgb_string()),
+    )
+}
+
+/// The DECSCUSR code that sets the cursor's shape, and whether it blinks.
+pub(crate) const fn shape_code(shape: termwiz::surface::CursorShape) -> &'static str {
+    match shape {
+        termwiz::surface::CursorShape::Default => "\x1b[0 q",
+        termwiz::surface::CursorShape::BlinkingBlock => "\x1b[1 q",
+        termwiz::surface::CursorShape::SteadyBlock => "\x1b[2 q",
+        termwiz::surface::CursorShape::BlinkingUnderline => "\x1b[3 q",
+        termwiz::surface::CursorShape::SteadyUnderline => "\x1b[4 q",
+        termwiz::surface::CursorShape::BlinkingBar => "\x1b[5 q",
+        termwiz::surface::CursorShape::SteadyBar => "\x1b[6 q",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cursor_colour_codes() {
+        let red = termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(colour_code(Some(red)), "\x1b]12;#ff0000\x1b\\");
+        assert_eq!(colour_code(None), "\x1b]112\x1b\\");
+    }
+
+    #[test]
+    fn cursor_shape_codes() {
+        assert_eq!(
+            shape_code(termwiz::surface::CursorShape::BlinkingBar),
+            "\x1b[5 q"
+        );
+        assert_eq!(
+            shape_code(termwiz::surface::CursorShape::SteadyUnderline),
+            "\x1b[4 q"
+        );
+        assert_eq!(
+            shape_code(termwiz::surface::CursorShape::Default),
+            "\x1b[0 q"
+        );
+    }
+}
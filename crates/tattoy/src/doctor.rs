@@ -0,0 +1,369 @@
+//! `tattoy doctor`: check the environment that Tattoy runs in and report anything that might stop
+//! it from working properly. Most problems, like shaders not rendering, are caused by something
+//! outside of Tattoy, such as a missing GPU driver.
+
+use color_eyre::eyre::Result;
+
+/// The result of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// Everything is fine.
+    Pass,
+    /// Tattoy still works, but maybe not as expected.
+    Warn,
+    /// Something that Tattoy needs isn't working.
+    Fail,
+}
+
+impl Outcome {
+    /// The symbol shown at the start of a check in the report.
+    const fn symbol(self) -> &'static str {
+        match self {
+            Self::Pass => "✓",
+            Self::Warn => "!",
+            Self::Fail => "✗",
+        }
+    }
+}
+
+/// A single diagnostic check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Check {
+    /// What was checked.
+    name: String,
+    /// Whether the check passed.
+    outcome: Outcome,
+    /// Details to help the user understand, and hopefully fix, the outcome.
+    details: String,
+}
+
+impl Check {
+    /// Instantiate
+    fn new<D: Into<String>>(name: &str, outcome: Outcome, details: D) -> Self {
+        Self {
+            name: name.to_owned(),
+            outcome,
+            details: details.into(),
+        }
+    }
+
+    /// A single line of the report.
+    fn describe(&self) -> String {
+        format!("{} {}: {}", self.outcome.symbol(), self.name, self.details)
+    }
+}
+
+/// Whether the value of the `COLORTERM` environment variable means that the terminal supports true
+/// colour.
+fn is_true_colour(colorterm: Option<&str>) -> bool {
+    matches!(colorterm, Some("truecolor" | "24bit"))
+}
+
+/// The summary at the end of the report.
+fn summarise(checks: &[Check]) -> String {
+    let count = |outcome: Outcome| {
+        checks
+            .iter()
+            .filter(|check| check.outcome == outcome)
+            .count()
+    };
+    format!(
+        "{} passed, {} warnings, {} failed",
+        count(Outcome::Pass),
+        count(Outcome::Warn),
+        count(Outcome::Fail)
+    )
+}
+
+/// Run all the checks and print the report. Exits with an error if any of the checks failed.
+/// With `is_bundling`, the report is also bundled with everything else that's useful for bug
+/// reports, see `crate::crash_report`.
+///
+/// This is run before Tattoy's usual setup, so that it sees the environment that Tattoy was
+/// started in, and so that it can report a broken config rather than exiting because of it.
+#[expect(
+    clippy::print_stdout,
+    reason = "The report is for the user to read, or to paste into a bug report"
+)]
+pub(crate) async fn run(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    is_bundling: bool,
+) -> Result<()> {
+    let checks = check_everything(state).await;
+    let report = report(&checks);
+    println!("{report}");
+
+    if is_bundling {
+        let path = crate::crash_report::create(state, &report).await?;
+        println!();
+        println!("Bundled for bug reports at: {}", path.display());
+    }
+
+    if checks.iter().any(|check| check.outcome == Outcome::Fail) {
+        color_eyre::eyre::bail!("Some checks failed");
+    }
+    Ok(())
+}
+
+/// Run all the checks and return the report, eg for crash reports.
+pub(crate) async fn diagnose(state: &std::sync::Arc<crate::shared_state::SharedState>) -> String {
+    report(&check_everything(state).await)
+}
+
+/// Run all the checks.
+async fn check_everything(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Vec<Check> {
+    let mut checks = vec![
+        check_terminal_size(),
+        check_true_colour(),
+        check_capabilities(),
+    ];
+    checks.extend(check_gpu_adapters().await);
+    checks.push(check_palette(state).await);
+    checks.push(check_config(state).await);
+    checks.push(check_shader(state).await);
+    checks.extend(check_plugins(state).await);
+    checks
+}
+
+/// The report of all the checks.
+fn report(checks: &[Check]) -> String {
+    let mut lines = vec![format!("Tattoy {}", env!("CARGO_PKG_VERSION"))];
+    lines.extend(checks.iter().map(Check::describe));
+    lines.push(String::new());
+    lines.push(summarise(checks));
+    lines.join("\n")
+}
+
+/// Tattoy needs to be run in an interactive terminal.
+fn check_terminal_size() -> Check {
+    let name = "Terminal size";
+    match crate::renderer::Renderer::get_users_tty_size() {
+        Ok(size) => Check::new(name, Outcome::Pass, format!("{}x{}", size.cols, size.rows)),
+        Err(error) => Check::new(
+            name,
+            Outcome::Fail,
+            format!("Couldn't get the size of the terminal, is this a TTY? ({error})"),
+        ),
+    }
+}
+
+/// Tattoy assumes that the terminal supports true colour.
+fn check_true_colour() -> Check {
+    let name = "True colour";
+    let colorterm = std::env::var("COLORTERM").ok();
+    if is_true_colour(colorterm.as_deref()) {
+        Check::new(name, Outcome::Pass, "`$COLORTERM` advertises true colour")
+    } else {
+        Check::new(
+            name,
+            Outcome::Warn,
+            "`$COLORTERM` isn't `truecolor` or `24bit`, so the terminal might not support true \
+             colour. Colours may look wrong.",
+        )
+    }
+}
+
+/// Shaders, and some other tattoys, need a GPU adapter.
+async fn check_gpu_adapters() -> Vec<Check> {
+    let name = "GPU adapter";
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    let mut checks: Vec<Check> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .iter()
+        .map(|adapter| {
+            let info = adapter.get_info();
+            let outcome = if info.device_type == wgpu::DeviceType::Cpu {
+                Outcome::Warn
+            } else {
+                Outcome::Pass
+            };
+            Check::new(
+                name,
+                outcome,
+                format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type),
+            )
+        })
+        .collect();
+
+    if checks.is_empty() {
+        checks.push(Check::new(
+            name,
+            Outcome::Fail,
+            "No GPU adapters found, so shaders won't render. Check the GPU's drivers.",
+        ));
+        return checks;
+    }
+
+    // This is the same request that the shader pipeline makes.
+    let maybe_adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        })
+        .await;
+    if maybe_adapter.is_none() {
+        checks.push(Check::new(
+            name,
+            Outcome::Fail,
+            "Adapters were found, but none of them could be used for rendering",
+        ));
+    }
+
+    checks
+}
+
+/// The terminal's capabilities are probed once and then cached.
+fn check_capabilities() -> Check {
+    let name = "Terminal capabilities";
+    match crate::capabilities::cached() {
+        Some(capabilities) => Check::new(
+            name,
+            Outcome::Pass,
+            format!(
+                "{}: synchronized output {}, focus reporting {}, Kitty graphics {}, Sixel {}",
+                capabilities
+                    .terminal_name
+                    .as_deref()
+                    .unwrap_or("Unknown terminal"),
+                yes_no(capabilities.has_synchronized_output),
+                yes_no(capabilities.has_focus_reporting),
+                yes_no(capabilities.has_kitty_graphics),
+                yes_no(capabilities.has_sixel),
+            ),
+        ),
+        None => Check::new(
+            name,
+            Outcome::Warn,
+            "Not probed yet. They will be probed when Tattoy next starts in this terminal.",
+        ),
+    }
+}
+
+/// Describe whether a feature is supported.
+const fn yes_no(is_supported: bool) -> &'static str {
+    if is_supported {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// The palette is needed to convert the terminal's colours to true colour.
+async fn check_palette(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Check {
+    let name = "Palette";
+    let path = crate::palette::parser::Parser::palette_config_path(state).await;
+    if path.exists() {
+        Check::new(name, Outcome::Pass, path.display().to_string())
+    } else {
+        Check::new(
+            name,
+            Outcome::Warn,
+            format!(
+                "Not found at {}. It will be detected when Tattoy next starts, or run \
+                 `tattoy --capture-palette`.",
+                path.display()
+            ),
+        )
+    }
+}
+
+/// The main config file has to be valid.
+async fn check_config(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Check {
+    let name = "Config";
+    let path = crate::config::main::Config::main_config_path(state).await;
+    match crate::config::main::Config::load_config_into_shared_state(state).await {
+        Ok(()) => Check::new(name, Outcome::Pass, path.display().to_string()),
+        Err(error) => Check::new(
+            name,
+            Outcome::Fail,
+            format!("{} is invalid: {error}", path.display()),
+        ),
+    }
+}
+
+/// The configured shader file has to exist.
+async fn check_shader(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Check {
+    let name = "Shader";
+    let config = state.config.load().shader.clone();
+    let path = crate::config::main::Config::resolve_path(state, &config.path).await;
+    let outcome = match (path.exists(), config.enabled) {
+        (true, _) => Outcome::Pass,
+        (false, true) => Outcome::Fail,
+        (false, false) => Outcome::Warn,
+    };
+    let details = if path.exists() {
+        path.display().to_string()
+    } else {
+        format!("Not found: {}", path.display())
+    };
+    Check::new(name, outcome, details)
+}
+
+/// Every plugin needs to be an executable file.
+async fn check_plugins(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Vec<Check> {
+    let plugins = state.config.load().plugins.clone();
+    let mut checks = Vec::new();
+    for plugin in plugins {
+        let name = format!("Plugin '{}'", plugin.name);
+        let path = crate::config::main::Config::resolve_path(state, &plugin.path).await;
+        let check = match std::fs::metadata(&path) {
+            Ok(metadata) if is_executable(&metadata) => {
+                Check::new(&name, Outcome::Pass, path.display().to_string())
+            }
+            Ok(_) => Check::new(
+                &name,
+                Outcome::Fail,
+                format!("Not executable: {}", path.display()),
+            ),
+            Err(error) => Check::new(
+                &name,
+                Outcome::Fail,
+                format!("Couldn't read {}: {error}", path.display()),
+            ),
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+/// Whether a file can be executed.
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt as _;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Whether a file can be executed. Windows doesn't have an executable permission.
+#[cfg(not(unix))]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    metadata.is_file()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detecting_true_colour() {
+        assert!(is_true_colour(Some("truecolor")));
+        assert!(is_true_colour(Some("24bit")));
+        assert!(!is_true_colour(Some("256")));
+        assert!(!is_true_colour(None));
+    }
+
+    #[test]
+    fn reporting() {
+        let checks = [
+            Check::new("Config", Outcome::Pass, "tattoy.toml"),
+            Check::new("Palette", Outcome::Warn, "Not found"),
+            Check::new("Shader", Outcome::Pass, "shader.glsl"),
+        ];
+        assert_eq!(checks[1].describe(), "! Palette: Not found");
+        assert_eq!(summarise(&checks), "2 passed, 1 warnings, 0 failed");
+    }
+}
@@ -0,0 +1,206 @@
+//! Areas of the terminal that pixel-based tattoys and shaders aren't drawn over, so that important
+//! text, like the prompt, always stays clean. Zones are either configured rectangles or the
+//! current prompt's line, which is found from the cells that the shell marks as its prompt with the
+//! OSC 133 code.
+
+/// User-configurable settings for exclusion zones.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Keep the line of the current prompt clear. Needs shell integration.
+    pub prompt: bool,
+    /// Rectangles of the terminal to keep clear.
+    pub zones: Vec<Zone>,
+    /// The names of the tattoys and plugins that aren't drawn in the zones.
+    pub tattoys: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prompt: false,
+            zones: Vec::new(),
+            tattoys: [
+                "shaders",
+                "bg_command",
+                "minimap",
+                "random_walker",
+                "starfield",
+                "audio_visualiser",
+                "crt",
+                "cursor_smear",
+                "typing_sparks",
+                "image_preview",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// A configured rectangle of the terminal. Negative coordinates are counted from the right or
+/// bottom of the terminal, so that zones can be anchored to any corner.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Zone {
+    /// The column of the zone's left edge.
+    pub x: i32,
+    /// The row of the zone's top edge.
+    pub y: i32,
+    /// The number of columns in the zone.
+    pub width: u16,
+    /// The number of rows in the zone.
+    pub height: u16,
+}
+
+/// A rectangle of the composited frame, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Rect {
+    /// The column of the left edge.
+    pub x: usize,
+    /// The row of the top edge.
+    pub y: usize,
+    /// The number of columns.
+    pub width: usize,
+    /// The number of rows.
+    pub height: usize,
+}
+
+impl Rect {
+    /// Whether the rectangle covers the given cell.
+    pub const fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+impl Zone {
+    /// Where the zone is in a frame of the given size.
+    fn resolve(&self, frame_width: usize, frame_height: usize) -> Rect {
+        let anchor = |coord: i32, size: usize| {
+            let offset = usize::try_from(coord.unsigned_abs()).unwrap_or(usize::MAX);
+            if coord < 0 {
+                size.saturating_sub(offset)
+            } else {
+                offset.min(size)
+            }
+        };
+        Rect {
+            x: anchor(self.x, frame_width),
+            y: anchor(self.y, frame_height),
+            width: self.width.into(),
+            height: self.height.into(),
+        }
+    }
+}
+
+/// The row of the PTY that has the current prompt on it, which is the lowest row with any cells
+/// that the shell has marked as part of its prompt.
+fn prompt_row(pty: &termwiz::surface::Surface) -> Option<usize> {
+    pty.screen_lines().iter().rposition(|line| {
+        line.visible_cells()
+            .any(|cell| cell.attrs().semantic_type() == termwiz::cell::SemanticType::Prompt)
+    })
+}
+
+/// All the rectangles of the frame that masked tattoys aren't drawn in. `top` is the number of
+/// rows above the PTY that are reserved for other things.
+pub(crate) fn mask(
+    config: &Config,
+    pty: &termwiz::surface::Surface,
+    frame_size: (usize, usize),
+    top: usize,
+) -> Vec<Rect> {
+    let (width, height) = frame_size;
+    let mut rects: Vec<Rect> = config
+        .zones
+        .iter()
+        .map(|zone| zone.resolve(width, height))
+        .collect();
+    if config.prompt {
+        if let Some(row) = prompt_row(pty) {
+            rects.push(Rect {
+                x: 0,
+                y: row + top,
+                width,
+                height: 1,
+            });
+        }
+    }
+    rects
+}
+
+/// Whether a tattoy's surface should be masked by the exclusion zones.
+pub(crate) fn is_masked(config: &Config, surface_id: &str) -> bool {
+    config
+        .tattoys
+        .iter()
+        .any(|name| crate::loader::surface_id(name) == surface_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolving_zones_from_any_corner() {
+        let zone = Zone {
+            x: 2,
+            y: -1,
+            width: 10,
+            height: 1,
+        };
+        assert_eq!(
+            zone.resolve(80, 24),
+            Rect {
+                x: 2,
+                y: 23,
+                width: 10,
+                height: 1
+            }
+        );
+
+        let zone = Zone {
+            x: -20,
+            y: 0,
+            width: 20,
+            height: 3,
+        };
+        let rect = zone.resolve(80, 24);
+        assert!(rect.contains(60, 0));
+        assert!(rect.contains(79, 2));
+        assert!(!rect.contains(59, 0));
+        assert!(!rect.contains(60, 3));
+    }
+
+    #[test]
+    fn finding_the_prompt_row() {
+        let mut pty = termwiz::surface::Surface::new(10, 4);
+        pty.add_change("output\r\n");
+        let mut prompt = termwiz::cell::CellAttributes::default();
+        prompt.set_semantic_type(termwiz::cell::SemanticType::Prompt);
+        pty.add_change(termwiz::surface::Change::AllAttributes(prompt));
+        pty.add_change("$ ");
+        assert_eq!(prompt_row(&pty), Some(1));
+
+        let config = Config {
+            prompt: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            mask(&config, &pty, (10, 5), 1),
+            vec![Rect {
+                x: 0,
+                y: 2,
+                width: 10,
+                height: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn masking_pixel_tattoys_only() {
+        let config = Config::default();
+        assert!(is_masked(&config, "shader"));
+        assert!(is_masked(&config, "minimap"));
+        assert!(!is_masked(&config, "clock"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Hand the terminal back to the user when Tattoy exits. Everything that happened in Tattoy only
+//! ever existed in the shadow terminal, so by default the user's terminal is just reset. But the
+//! scrollback can optionally be replayed into the user's terminal, so that the session's history
+//! isn't lost.
+
+/// What to leave in the user's terminal when Tattoy exits.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ExitBehaviour {
+    /// Reset the terminal, leaving it empty.
+    #[default]
+    Reset,
+    /// Reset the terminal, then print the shadow terminal's scrollback into it.
+    ReplayScrollback,
+}
+
+/// The SGR parameters for a colour.
+fn colour_parameters(colour: termwiz::color::ColorAttribute, is_foreground: bool) -> String {
+    let (default, extended) = if is_foreground { (39, 38) } else { (49, 48) };
+    match colour {
+        termwiz::color::ColorAttribute::Default => default.to_string(),
+        termwiz::color::ColorAttribute::PaletteIndex(index) => format!("{extended};5;{index}"),
+        termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(rgba, _)
+        | termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(rgba) => {
+            let (red, green, blue, _) = rgba.to_srgb_u8();
+            format!("{extended};2;{red};{green};{blue}")
+        }
+    }
+}
+
+/// The escape sequence that sets all the given attributes, starting from a reset.
+fn sgr(attributes: &termwiz::cell::CellAttributes) -> String {
+    let mut parameters = vec![
+        "0".to_owned(),
+        colour_parameters(attributes.foreground(), true),
+        colour_parameters(attributes.background(), false),
+    ];
+    match attributes.intensity() {
+        termwiz::cell::Intensity::Bold => parameters.push("1".to_owned()),
+        termwiz::cell::Intensity::Half => parameters.push("2".to_owned()),
+        termwiz::cell::Intensity::Normal => (),
+    }
+    for (is_set, parameter) in [
+        (attributes.italic(), "3"),
+        (
+            attributes.underline() != termwiz::cell::Underline::None,
+            "4",
+        ),
+        (attributes.reverse(), "7"),
+        (attributes.strikethrough(), "9"),
+    ] {
+        if is_set {
+            parameters.push(parameter.to_owned());
+        }
+    }
+    format!("\x1b[{}m", parameters.join(";"))
+}
+
+/// Whether a cell shows nothing at all.
+fn is_blank(text: &str, attributes: &termwiz::cell::CellAttributes) -> bool {
+    text.trim().is_empty()
+        && attributes.background() == termwiz::color::ColorAttribute::Default
+        && !attributes.reverse()
+}
+
+/// Render a single line, with its colours and styles, but without any trailing blank cells.
+fn render_line(line: &termwiz::surface::line::Line) -> String {
+    let cells: Vec<termwiz::surface::line::CellRef> = line.visible_cells().collect();
+    let length = cells
+        .iter()
+        .rposition(|cell| !is_blank(cell.str(), cell.attrs()))
+        .map_or(0, |last| last + 1);
+
+    let default_attributes = termwiz::cell::CellAttributes::default();
+    let mut current = &default_attributes;
+    let mut rendered = String::new();
+    for cell in cells.iter().take(length) {
+        if cell.attrs() != current {
+            current = cell.attrs();
+            rendered.push_str(&sgr(current));
+        }
+        rendered.push_str(cell.str());
+    }
+    if current != &default_attributes {
+        rendered.push_str(crate::utils::RESET_COLOUR);
+    }
+    rendered
+}
+
+/// Render the scrollback as text with escape sequences, ready to be printed into the user's
+/// terminal. Blank lines at the start and end of the scrollback are left out.
+pub(crate) fn replay(scrollback: &termwiz::surface::Surface) -> String {
+    let lines: Vec<String> = scrollback
+        .screen_lines()
+        .iter()
+        .map(|line| render_line(line))
+        .collect();
+    let maybe_first = lines.iter().position(|line| !line.is_empty());
+    let maybe_last = lines.iter().rposition(|line| !line.is_empty());
+    let (Some(first), Some(last)) = (maybe_first, maybe_last) else {
+        return String::new();
+    };
+
+    lines
+        .get(first..=last)
+        .unwrap_or_default()
+        .iter()
+        .map(|line| format!("{line}{}", crate::utils::NEWLINE))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::surface::{Change as TermwizChange, Position as TermwizPosition};
+
+    #[test]
+    fn replaying_the_scrollback() {
+        let mut surface = termwiz::surface::Surface::new(10, 5);
+        surface.add_change(TermwizChange::CursorPosition {
+            x: TermwizPosition::Absolute(0),
+            y: TermwizPosition::Absolute(1),
+        });
+        surface.add_change("$ ls");
+        surface.add_change(TermwizChange::CursorPosition {
+            x: TermwizPosition::Absolute(0),
+            y: TermwizPosition::Absolute(3),
+        });
+        surface.add_change(TermwizChange::Attribute(
+            termwiz::cell::AttributeChange::Foreground(
+                termwiz::color::ColorAttribute::PaletteIndex(1),
+            ),
+        ));
+        surface.add_change(TermwizChange::Attribute(
+            termwiz::cell::AttributeChange::Intensity(termwiz::cell::Intensity::Bold),
+        ));
+        surface.add_change("error");
+
+        assert_eq!(
+            replay(&surface),
+            format!(
+                "$ ls{newline}{newline}\x1b[0;38;5;1;49;1merror\x1b[m{newline}",
+                newline = crate::utils::NEWLINE
+            )
+        );
+    }
+
+    #[test]
+    fn replaying_an_empty_scrollback() {
+        let surface = termwiz::surface::Surface::new(10, 5);
+        assert_eq!(replay(&surface), "");
+    }
+}
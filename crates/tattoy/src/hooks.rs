@@ -0,0 +1,337 @@
+//! Hooks run the user's own commands, or trigger Tattoy's actions, when things happen in Tattoy,
+//! eg when a command fails or when the terminal has been idle for a while. They listen to the
+//! same protocol channel as everything else in Tattoy.
+
+use color_eyre::eyre::Result;
+
+/// How often to check whether the terminal has become idle.
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The longest time to wait for `session_end` commands, so that they can't stop Tattoy exiting.
+const SESSION_END_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The things that can trigger a hook.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Event {
+    /// Tattoy started.
+    SessionStart,
+    /// Tattoy is exiting.
+    SessionEnd,
+    /// There's been no input or output for the hook's `minutes`.
+    Idle,
+    /// There was input or output after being idle.
+    Active,
+    /// A command finished with a non-zero exit code. Needs shell integration.
+    CommandFailed,
+    /// The terminal rang its bell.
+    Bell,
+}
+
+impl Event {
+    /// The name of the event, as used in the config.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::SessionStart => "session_start",
+            Self::SessionEnd => "session_end",
+            Self::Idle => "idle",
+            Self::Active => "active",
+            Self::CommandFailed => "command_failed",
+            Self::Bell => "bell",
+        }
+    }
+}
+
+/// User-configurable settings for a single hook.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Hook {
+    /// What triggers the hook.
+    pub event: Event,
+    /// A shell command to run. It's given the `TATTOY_HOOK_EVENT` environment variable, and
+    /// `TATTOY_EXIT_CODE` for failed commands.
+    pub command: Option<String>,
+    /// One of Tattoy's keybinding actions to trigger, eg `toggle_minimap`.
+    pub action: Option<crate::config::input::KeybindingAction>,
+    /// For `idle` hooks, how many minutes without any input or output count as idle.
+    #[serde(default = "default_idle_minutes")]
+    pub minutes: u64,
+}
+
+/// The default number of minutes before an `idle` hook runs.
+const fn default_idle_minutes() -> u64 {
+    10
+}
+
+/// The event, and any exit code, that a protocol message triggers, if any.
+const fn event_for(message: &crate::run::Protocol) -> Option<(Event, Option<i32>)> {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "Only a few messages trigger hooks"
+    )]
+    match message {
+        crate::run::Protocol::Bell => Some((Event::Bell, None)),
+        crate::run::Protocol::CommandFinished(Some(exit_code)) if *exit_code != 0 => {
+            Some((Event::CommandFailed, Some(*exit_code)))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a protocol message means that the terminal is being used.
+const fn is_activity(message: &crate::run::Protocol) -> bool {
+    matches!(
+        message,
+        crate::run::Protocol::Input(_) | crate::run::Protocol::Output(_)
+    )
+}
+
+/// The indexes of the `idle` hooks that are due, but haven't run yet, after being idle for the
+/// given time.
+fn due_idle_hooks(hooks: &[Hook], idle_for: std::time::Duration, has_run: &[usize]) -> Vec<usize> {
+    hooks
+        .iter()
+        .enumerate()
+        .filter(|(index, hook)| {
+            hook.event == Event::Idle
+                && !has_run.contains(index)
+                && idle_for >= std::time::Duration::from_secs(hook.minutes.saturating_mul(60))
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Keeps track of activity, and runs hooks when their events happen.
+struct Dispatcher {
+    /// Shared app state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// When there was last any input or output.
+    last_activity: tokio::time::Instant,
+    /// The indexes of the `idle` hooks that have run since the last activity.
+    idle_hooks_run: Vec<usize>,
+}
+
+impl Dispatcher {
+    /// Run all the hooks for an event.
+    async fn dispatch(&self, event: Event, exit_code: Option<i32>) {
+        let hooks = self.state.config.load().hooks.clone();
+        for hook in hooks.iter().filter(|hook| hook.event == event) {
+            self.run(hook, exit_code).await;
+        }
+    }
+
+    /// Run a single hook.
+    async fn run(&self, hook: &Hook, exit_code: Option<i32>) {
+        tracing::debug!("Running {} hook: {hook:?}", hook.event.name());
+        if let Some(action) = &hook.action {
+            if let Err(error) = self.trigger(action).await {
+                tracing::warn!("Triggering hook action {action:?}: {error:?}");
+            }
+        }
+
+        let Some(command) = hook.command.clone() else {
+            return;
+        };
+        let mut process = tokio::process::Command::new("sh");
+        process
+            .args(["-c", &command])
+            .env("TATTOY_HOOK_EVENT", hook.event.name())
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+        if let Some(code) = exit_code {
+            process.env("TATTOY_EXIT_CODE", code.to_string());
+        }
+
+        let finished = async move {
+            match process.status().await {
+                Ok(status) if !status.success() => {
+                    tracing::warn!("Hook command `{command}` failed: {status}");
+                }
+                Ok(_) => (),
+                Err(error) => tracing::warn!("Couldn't run hook command `{command}`: {error:?}"),
+            }
+        };
+
+        // Tattoy is about to exit, so there's nothing to come back to later.
+        if hook.event == Event::SessionEnd {
+            if tokio::time::timeout(SESSION_END_TIMEOUT, finished)
+                .await
+                .is_err()
+            {
+                tracing::warn!("Gave up waiting for a `session_end` hook command");
+            }
+        } else {
+            tokio::spawn(finished);
+        }
+    }
+
+    /// Trigger one of Tattoy's keybinding actions, as if its keys were pressed.
+    async fn trigger(&self, action: &crate::config::input::KeybindingAction) -> Result<()> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Most actions are handled by whatever listens for their keybinding event"
+        )]
+        match action {
+            crate::config::input::KeybindingAction::ToggleTattoy => {
+                crate::control::toggle_rendering(&self.state).await?;
+            }
+            crate::config::input::KeybindingAction::ProfileNext => {
+                crate::config::main::Config::cycle_profile(&self.state).await;
+            }
+            crate::config::input::KeybindingAction::ToggleScrolling
+            | crate::config::input::KeybindingAction::ScrollUp
+            | crate::config::input::KeybindingAction::ScrollDown
+            | crate::config::input::KeybindingAction::ScrollExit
+            | crate::config::input::KeybindingAction::BookmarkAdd
+            | crate::config::input::KeybindingAction::BookmarkPrevious
+            | crate::config::input::KeybindingAction::BookmarkNext => {
+                color_eyre::eyre::bail!("Scrolling and bookmark actions can't be used in hooks");
+            }
+            _ => {
+                self.state
+                    .protocol_tx
+                    .send(crate::run::Protocol::KeybindEvent(action.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run any `idle` hooks that have become due.
+    async fn check_idle(&mut self) {
+        let hooks = self.state.config.load().hooks.clone();
+        let due = due_idle_hooks(&hooks, self.last_activity.elapsed(), &self.idle_hooks_run);
+        for index in due {
+            if let Some(hook) = hooks.get(index) {
+                self.run(hook, None).await;
+            }
+            self.idle_hooks_run.push(index);
+        }
+    }
+
+    /// Note some activity, running the `active` hooks if the terminal was idle.
+    async fn activity(&mut self) {
+        self.last_activity = tokio::time::Instant::now();
+        if self.idle_hooks_run.is_empty() {
+            return;
+        }
+        self.idle_hooks_run.clear();
+        self.dispatch(Event::Active, None).await;
+    }
+}
+
+/// Listen to Tattoy's protocol and run hooks until Tattoy exits.
+pub(crate) fn start(
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+) -> tokio::task::JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut timer = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut dispatcher = Dispatcher {
+            state,
+            last_activity: tokio::time::Instant::now(),
+            idle_hooks_run: Vec::new(),
+        };
+        dispatcher.dispatch(Event::SessionStart, None).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    dispatcher.check_idle().await;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        dispatcher.dispatch(Event::SessionEnd, None).await;
+                        break;
+                    }
+                    if let crate::run::Protocol::Config { changes, .. } = &message {
+                        if changes.contains(&crate::config::diff::Section::Hooks) {
+                            dispatcher.idle_hooks_run.clear();
+                        }
+                    }
+                    if is_activity(&message) {
+                        dispatcher.activity().await;
+                    }
+                    if let Some((event, exit_code)) = event_for(&message) {
+                        dispatcher.dispatch(event, exit_code).await;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hook(event: Event, minutes: u64) -> Hook {
+        Hook {
+            event,
+            command: None,
+            action: None,
+            minutes,
+        }
+    }
+
+    #[test]
+    fn parsing_hooks() {
+        let config = "event = \"command_failed\"\ncommand = \"notify-send failed\"";
+        let parsed = toml::from_str::<Hook>(config).unwrap();
+        assert_eq!(parsed.event, Event::CommandFailed);
+        assert_eq!(parsed.command.as_deref(), Some("notify-send failed"));
+        assert_eq!(parsed.minutes, 10);
+
+        let config = "event = \"idle\"\naction = \"toggle_minimap\"\nminutes = 2";
+        let parsed = toml::from_str::<Hook>(config).unwrap();
+        assert_eq!(
+            parsed.action,
+            Some(crate::config::input::KeybindingAction::ToggleMinimap)
+        );
+        assert_eq!(parsed.minutes, 2);
+    }
+
+    #[test]
+    fn mapping_protocol_messages_to_events() {
+        assert_eq!(
+            event_for(&crate::run::Protocol::Bell),
+            Some((Event::Bell, None))
+        );
+        assert_eq!(
+            event_for(&crate::run::Protocol::CommandFinished(Some(2))),
+            Some((Event::CommandFailed, Some(2)))
+        );
+        assert_eq!(
+            event_for(&crate::run::Protocol::CommandFinished(Some(0))),
+            None
+        );
+        assert_eq!(
+            event_for(&crate::run::Protocol::CommandFinished(None)),
+            None
+        );
+        assert_eq!(event_for(&crate::run::Protocol::Repaint), None);
+    }
+
+    #[test]
+    fn finding_due_idle_hooks() {
+        let hooks = [
+            hook(Event::Idle, 1),
+            hook(Event::Bell, 0),
+            hook(Event::Idle, 5),
+        ];
+        let minutes = |count: u64| std::time::Duration::from_secs(count * 60);
+        assert_eq!(due_idle_hooks(&hooks, minutes(0), &[]), Vec::<usize>::new());
+        assert_eq!(due_idle_hooks(&hooks, minutes(2), &[]), vec![0]);
+        assert_eq!(due_idle_hooks(&hooks, minutes(6), &[0]), vec![2]);
+        assert_eq!(
+            due_idle_hooks(&hooks, minutes(6), &[0, 2]),
+            Vec::<usize>::new()
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Smooth out tattoys and plugins that update slower than the renderer. Rather than a slow tattoy
+//! jumping from one frame to the next, its previous frame is cross-faded into its latest frame
+//! over the time that it usually takes to send a new frame. It's opt-in with the
+//! `interpolate_frames` config setting, because it means that slow tattoys are always shown a
+//! frame behind.
+
+/// Updates that are further apart than this aren't animations, eg a clock ticking, so there's
+/// nothing to smooth out.
+const MAX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The frame that a tattoy is being cross-faded from.
+pub(crate) struct Previous {
+    /// The tattoy's frame before its latest one.
+    pub surface: crate::surface::Surface,
+    /// The time between the previous frame and the latest one.
+    pub interval: std::time::Duration,
+}
+
+/// Whether updates this far apart are slow enough, compared to the render rate, to be worth
+/// interpolating.
+pub(crate) fn is_worth_interpolating(interval: std::time::Duration, render_rate: u32) -> bool {
+    let render_interval = std::time::Duration::from_secs(1) / render_rate.max(1);
+    interval > render_interval && interval <= MAX_INTERVAL
+}
+
+/// How far through the cross-fade from the previous frame to the latest one we are, from 0.0 to
+/// 1.0.
+pub(crate) fn progress(elapsed: std::time::Duration, interval: std::time::Duration) -> f32 {
+    if interval.is_zero() {
+        return 1.0;
+    }
+    (elapsed.as_secs_f32() / interval.as_secs_f32()).clamp(0.0, 1.0)
+}
+
+/// The opacities at which to composite the previous frame and then the latest frame, so that
+/// together they're a linear cross-fade between the two at the tattoy's own opacity.
+///
+/// Compositing the latest frame over the previous frame hides some of the previous frame, so the
+/// previous frame's opacity has to be boosted to make up for it.
+pub(crate) fn opacities(opacity: f32, progress: f32) -> (f32, f32) {
+    let latest = opacity * progress;
+    let hidden = 1.0 - latest;
+    if hidden <= f32::EPSILON {
+        return (0.0, latest);
+    }
+    let previous = (opacity * (1.0 - progress)) / hidden;
+    (previous.clamp(0.0, 1.0), latest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_interpolating_slow_updates() {
+        let millis = std::time::Duration::from_millis;
+        assert!(!is_worth_interpolating(millis(20), 30));
+        assert!(is_worth_interpolating(millis(200), 30));
+        assert!(!is_worth_interpolating(millis(5000), 30));
+    }
+
+    #[test]
+    fn cross_fading_progress() {
+        let millis = std::time::Duration::from_millis;
+        assert!((progress(millis(50), millis(200)) - 0.25).abs() < f32::EPSILON);
+        assert!((progress(millis(500), millis(200)) - 1.0).abs() < f32::EPSILON);
+        assert!((progress(millis(50), millis(0)) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn cross_fading_opacities() {
+        let (previous, latest) = opacities(1.0, 0.0);
+        assert!((previous - 1.0).abs() < f32::EPSILON);
+        assert!(latest.abs() < f32::EPSILON);
+
+        let (previous, latest) = opacities(1.0, 1.0);
+        assert!(previous.abs() < f32::EPSILON);
+        assert!((latest - 1.0).abs() < f32::EPSILON);
+
+        // Half of each frame is left showing, along with half of what's below the tattoy.
+        let (previous, latest) = opacities(0.5, 0.5);
+        let shown_previous = previous * (1.0 - latest);
+        assert!((shown_previous - 0.25).abs() < 0.0001);
+        assert!((latest - 0.25).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,200 @@
+//! `tattoy doctor --latency`: measure how long it takes for a keypress to reach the screen. Tattoy
+//! is started, with the user's own config, inside a headless terminal that runs `cat`, so that
+//! every keypress is simply echoed back. Keys are then typed one at a time and timed until they
+//! appear in Tattoy's composited output. The same is done without Tattoy, so that the overhead
+//! that Tattoy adds can be told apart from the latency of the PTY itself.
+
+use color_eyre::eyre::Result;
+
+/// How many keypresses to time.
+const SAMPLES: usize = 50;
+
+/// The key that's typed. It should be rare enough not to be drawn by any tattoy.
+const KEY: &str = "@";
+
+/// The key that deletes the typed key again, so that the screen is clear for the next keypress.
+const BACKSPACE: &str = "\x7f";
+
+/// The size of the headless terminal.
+const SIZE: (u16, u16) = (80, 24);
+
+/// The longest time to wait for Tattoy to start showing keypresses.
+const STARTUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The longest time to wait for a single keypress to appear.
+const KEYPRESS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The latencies at the percentiles worth reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Percentiles {
+    /// The median latency.
+    pub p50: std::time::Duration,
+    /// The latency that 90% of keypresses are faster than.
+    pub p90: std::time::Duration,
+    /// The latency that 99% of keypresses are faster than.
+    pub p99: std::time::Duration,
+    /// The slowest keypress.
+    pub max: std::time::Duration,
+}
+
+impl Percentiles {
+    /// Summarise the given latencies. Returns `None` if there aren't any.
+    pub(crate) fn from_samples(samples: &[std::time::Duration]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        Some(Self {
+            p50: percentile(&sorted, 50)?,
+            p90: percentile(&sorted, 90)?,
+            p99: percentile(&sorted, 99)?,
+            max: *sorted.last()?,
+        })
+    }
+
+    /// How much slower each percentile is than the given baseline's.
+    fn overhead(&self, baseline: &Self) -> Self {
+        Self {
+            p50: self.p50.saturating_sub(baseline.p50),
+            p90: self.p90.saturating_sub(baseline.p90),
+            p99: self.p99.saturating_sub(baseline.p99),
+            max: self.max.saturating_sub(baseline.max),
+        }
+    }
+
+    /// A single line of the report.
+    fn describe(&self) -> String {
+        let millis =
+            |duration: std::time::Duration| format!("{:.1}ms", duration.as_secs_f64() * 1000.0);
+        format!(
+            "p50 {}, p90 {}, p99 {}, max {}",
+            millis(self.p50),
+            millis(self.p90),
+            millis(self.p99),
+            millis(self.max)
+        )
+    }
+}
+
+/// The nearest-rank percentile of some already sorted latencies.
+fn percentile(sorted: &[std::time::Duration], percent: usize) -> Option<std::time::Duration> {
+    let rank = (percent * sorted.len()).div_ceil(100);
+    sorted.get(rank.saturating_sub(1)).copied()
+}
+
+/// Measure the latency with and without Tattoy and print the report.
+#[expect(
+    clippy::print_stdout,
+    reason = "The report is for the user to read, or to paste into a bug report"
+)]
+pub(crate) async fn run() -> Result<()> {
+    println!("Timing {SAMPLES} keypresses, this takes a few seconds...");
+
+    let tattoy_command = format!(
+        "TATTOY_NEST=allow exec '{}' --disable-indicator --command cat",
+        std::env::current_exe()?.display()
+    );
+    let baseline = measure("exec cat").await?;
+    let tattoy = measure(&tattoy_command).await?;
+
+    let (Some(baseline), Some(tattoy)) = (
+        Percentiles::from_samples(&baseline),
+        Percentiles::from_samples(&tattoy),
+    ) else {
+        color_eyre::eyre::bail!("No keypresses were timed");
+    };
+    println!("Without Tattoy: {}", baseline.describe());
+    println!("With Tattoy:    {}", tattoy.describe());
+    println!("Overhead:       {}", tattoy.overhead(&baseline).describe());
+    Ok(())
+}
+
+/// Time keypresses in a headless terminal running the given shell command. Something is printed
+/// first, because the headless terminal waits for some output before it's ready.
+async fn measure(command: &str) -> Result<Vec<std::time::Duration>> {
+    let config = shadow_terminal::shadow_terminal::Config {
+        width: SIZE.0,
+        height: SIZE.1,
+        command: vec![
+            "sh".into(),
+            "-c".into(),
+            format!("printf ready; {command}").into(),
+        ],
+        ..Default::default()
+    };
+    let mut terminal =
+        shadow_terminal::steppable_terminal::SteppableTerminal::start(config).await?;
+
+    // The first keypress also waits for everything to start up, so it isn't counted.
+    let result = async {
+        type_key(&mut terminal, STARTUP_TIMEOUT).await?;
+        let mut samples = Vec::with_capacity(SAMPLES);
+        for _ in 0..SAMPLES {
+            samples.push(type_key(&mut terminal, KEYPRESS_TIMEOUT).await?);
+        }
+        Ok::<_, color_eyre::eyre::Report>(samples)
+    }
+    .await;
+
+    terminal.kill()?;
+    result
+}
+
+/// Type the key, time how long it takes to appear on the screen, then delete it again.
+async fn type_key(
+    terminal: &mut shadow_terminal::steppable_terminal::SteppableTerminal,
+    timeout: std::time::Duration,
+) -> Result<std::time::Duration> {
+    wait_for_key(terminal, false, timeout).await?;
+    let started = std::time::Instant::now();
+    terminal.send_input(shadow_terminal::steppable_terminal::Input::Characters(
+        KEY.to_owned(),
+    ))?;
+    wait_for_key(terminal, true, timeout).await?;
+    let latency = started.elapsed();
+
+    terminal.send_input(shadow_terminal::steppable_terminal::Input::Characters(
+        BACKSPACE.to_owned(),
+    ))?;
+    Ok(latency)
+}
+
+/// Wait until the key is, or isn't, on the screen.
+async fn wait_for_key(
+    terminal: &mut shadow_terminal::steppable_terminal::SteppableTerminal,
+    is_shown: bool,
+    timeout: std::time::Duration,
+) -> Result<()> {
+    let started = std::time::Instant::now();
+    loop {
+        terminal.render_all_output().await?;
+        if terminal.screen_as_string()?.contains(KEY) == is_shown {
+            return Ok(());
+        }
+        if started.elapsed() > timeout {
+            let state = if is_shown { "appear" } else { "be deleted" };
+            color_eyre::eyre::bail!("Typed key didn't {state} within {timeout:?}");
+        }
+        tokio::time::sleep(std::time::Duration::from_micros(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn calculating_percentiles() {
+        let samples: Vec<std::time::Duration> = (1..=100)
+            .rev()
+            .map(std::time::Duration::from_millis)
+            .collect();
+        let percentiles = Percentiles::from_samples(&samples).unwrap();
+        assert_eq!(percentiles.p50, std::time::Duration::from_millis(50));
+        assert_eq!(percentiles.p90, std::time::Duration::from_millis(90));
+        assert_eq!(percentiles.p99, std::time::Duration::from_millis(99));
+        assert_eq!(percentiles.max, std::time::Duration::from_millis(100));
+
+        let single = Percentiles::from_samples(&[std::time::Duration::from_millis(3)]).unwrap();
+        assert_eq!(single.p50, single.max);
+        assert_eq!(Percentiles::from_samples(&[]), None);
+    }
+}
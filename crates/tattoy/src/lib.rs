@@ -0,0 +1,198 @@
+//! Eye-candy for your terminal.
+//!
+//! Tattoy is an application rather than a library. The library only exists so that the
+//! benchmarks in `benches/` can reach the hot paths of rendering, see `benchmarks.rs`. The actual
+//! entrypoint is `main.rs`.
+
+// TODO: Consider using `mod.rs`. As pointed out by @Justus_Fluegel, the disadvantage of
+// this approach is that when moving files/modules, you _also_ have to move these module
+// definitions.
+
+pub mod cli_args;
+/// All the user-configurable settings.
+pub mod config {
+    pub mod diff;
+    pub mod input;
+    pub mod main;
+    pub mod migrations;
+    pub mod profiles;
+    pub mod themes;
+}
+pub mod accessibility;
+pub mod batch;
+pub mod bench;
+#[doc(hidden)]
+pub mod benchmarks;
+pub mod blender;
+pub mod capabilities;
+pub mod colour_vision;
+pub mod compositor;
+pub mod control;
+pub mod crash_report;
+pub mod cursor_style;
+pub mod doctor;
+pub mod exclusion_zones;
+pub mod handover;
+pub mod hooks;
+pub mod interpolation;
+pub mod latency;
+pub mod loader;
+pub mod log_file;
+pub mod metrics;
+pub mod multiplexer;
+pub mod performance;
+pub mod plugin_template;
+pub mod power;
+pub mod predictive_echo;
+pub mod raw_input;
+/// The palette code is for helping convert a terminal's palette to true colour.
+pub mod palette {
+    pub mod base16;
+    pub mod converter;
+    pub mod export;
+    pub mod osc;
+    pub mod parser;
+    pub mod state_machine;
+    pub mod theme_watcher;
+}
+pub mod renderer;
+pub mod run;
+pub mod runtime_state;
+pub mod setup_archive;
+pub mod shared_state;
+pub mod shell_integration;
+pub mod splash;
+pub mod streaming;
+pub mod surface;
+/// A layer between Tattoy and the Shadow Terminal
+pub mod terminal_proxy {
+    pub mod input_handler;
+    pub mod proxy;
+}
+pub mod true_colour;
+pub mod utils;
+
+/// This is where all the various tattoys are kept
+pub mod tattoys {
+    /// Visualising the audio that's currently playing
+    pub mod audio_visualiser {
+        pub mod config;
+        #[cfg(feature = "audio_visualiser")]
+        pub mod main;
+    }
+
+    pub mod bg_command;
+    pub mod bookmarks;
+    pub mod clock;
+    pub mod command_duration;
+    pub mod crt;
+    pub mod cursor_highlight;
+    pub mod cursor_smear;
+    pub mod focus;
+    pub mod git_status;
+    pub mod hit_test;
+    pub mod image_preview;
+    pub mod log_viewer;
+    pub mod magnifier;
+    pub mod minimap;
+    pub mod starfield;
+    pub mod status_bar;
+    pub mod startup_logo;
+    pub mod system_stats;
+
+    /// Notifications in the terminal UI
+    pub mod notifications {
+        pub mod main;
+        pub mod message;
+    }
+
+    pub mod palette_editor;
+    pub mod performance_hud;
+    pub mod plugins;
+    pub mod pomodoro;
+    pub mod random_walker;
+    pub mod screensaver;
+    pub mod scrollbar;
+    pub mod search;
+    pub mod settings_panel;
+
+    /// Shadertoy-like shaders
+    pub mod shaders {
+        pub mod browser;
+        pub mod gpu;
+        pub mod ichannel;
+        pub mod install;
+        pub mod main;
+    }
+
+    pub mod tattoyer;
+    pub mod typing_sparks;
+    pub mod visual_bell;
+    pub mod weather;
+}
+
+use color_eyre::eyre::Result;
+
+/// Run Tattoy until the user exits it. Errors from running Tattoy are shown to the user once
+/// their terminal has been reset, rather than being returned.
+///
+/// # Errors
+/// If the error reporter couldn't be installed or the shared state couldn't be initialised.
+#[expect(clippy::non_ascii_literal, reason = "It's just for debugging")]
+#[expect(
+    clippy::print_stdout,
+    clippy::print_stderr,
+    reason = "It's our central place for communicating with the user on CLI"
+)]
+#[inline]
+pub async fn start() -> Result<()> {
+    color_eyre::install()?;
+    run::check_for_tattoy_in_tattoy();
+    let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
+    let state_arc = shared_state::SharedState::init_with_users_tty_size(protocol_tx).await?;
+    let result = run::run(&std::sync::Arc::clone(&state_arc)).await;
+    println!("{}", utils::RESET_SCREEN);
+    if state_arc.config.load().exit_behaviour == handover::ExitBehaviour::ReplayScrollback {
+        let scrollback = state_arc.shadow_tty_scrollback.read().await;
+        print!("{}", handover::replay(&scrollback.surface));
+    }
+
+    let logpath = state_arc.log_file.read().await.clone();
+    let is_logging = *state_arc.is_logging.read().await;
+    tracing::debug!("Tattoy is exiting 🙇");
+
+    match result {
+        Ok(()) => {
+            if is_logging {
+                println!("Logs saved to {}", logpath.display());
+            }
+        }
+        Err(error) => {
+            tracing::error!("{error:?}");
+            eprintln!("Error: {error}");
+            if is_logging {
+                eprintln!("See {} for more details", logpath.display());
+            }
+
+            // Only errors whilst Tattoy was actually running are worth reporting, not eg a
+            // mistyped CLI argument.
+            let was_running = state_arc
+                .initialised_systems
+                .read()
+                .await
+                .contains(&"renderer".to_owned());
+            if was_running {
+                let diagnosis = doctor::diagnose(&state_arc).await;
+                match crash_report::create(&state_arc, &diagnosis).await {
+                    Ok(path) => eprintln!(
+                        "A crash report, to attach to a GitHub issue, was saved to {}",
+                        path.display()
+                    ),
+                    Err(report_error) => tracing::error!("Creating crash report: {report_error:?}"),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -4,8 +4,47 @@ use std::sync::Arc;
 
 use color_eyre::eyre::Result;
 
+use crate::config::diff::Section;
 use crate::run::FrameUpdate;
 
+/// All the running tattoys.
+type TattoyFutures = tokio::task::JoinSet<Result<()>>;
+
+/// The task IDs of the running tattoys that can be toggled at runtime, along with their names.
+type ToggleableTasks = std::collections::HashMap<tokio::task::Id, String>;
+
+/// The task IDs of the running plugins, along with their names.
+type PluginTasks = std::collections::HashMap<tokio::task::Id, String>;
+
+/// The tattoys that can be started and stopped by changing the config whilst Tattoy is running,
+/// along with the config section that controls each of them.
+const TOGGLEABLE_TATTOYS: [(&str, Section); 24] = [
+    ("minimap", Section::Minimap),
+    ("shaders", Section::Shader),
+    ("bg_command", Section::BgCommand),
+    ("weather", Section::Weather),
+    ("visual_bell", Section::VisualBell),
+    ("system_stats", Section::SystemStats),
+    ("clock", Section::Clock),
+    ("pomodoro", Section::Pomodoro),
+    ("git_status", Section::GitStatus),
+    ("audio_visualiser", Section::AudioVisualiser),
+    ("typing_sparks", Section::TypingSparks),
+    ("cursor_smear", Section::CursorSmear),
+    ("crt", Section::Crt),
+    ("starfield", Section::Starfield),
+    ("screensaver", Section::Screensaver),
+    ("command_duration", Section::CommandDuration),
+    ("image_preview", Section::ImagePreview),
+    ("status_bar", Section::StatusBar),
+    ("focus", Section::Focus),
+    ("cursor_highlight", Section::CursorHighlight),
+    ("random_walker", Section::RandomWalker),
+    ("magnifier", Section::Magnifier),
+    ("performance_hud", Section::PerformanceHud),
+    ("log_viewer", Section::LogViewer),
+];
+
 /// Start the main loader thread
 #[expect(clippy::too_many_lines, reason = "It's mostly repetitive")]
 pub(crate) fn start_tattoys(
@@ -19,10 +58,17 @@ pub(crate) fn start_tattoys(
             crate::run::wait_for_system(&state, "renderer").await;
 
             let palette = crate::config::main::Config::load_palette(Arc::clone(&state)).await?;
-            let mut tattoy_futures = tokio::task::JoinSet::new();
+            state
+                .initialised_systems
+                .write()
+                .await
+                .push("palette".to_owned());
+            let mut tattoy_futures = TattoyFutures::new();
+            let mut toggleable_tasks = ToggleableTasks::new();
+            let mut plugin_tasks = PluginTasks::new();
 
             if enabled_tattoys.contains(&"startup_logo".to_owned())
-                || state.config.read().await.show_startup_logo
+                || state.config.load().show_startup_logo
             {
                 tracing::info!("Starting 'startup_logo' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::startup_logo::StartupLogo::start(
@@ -33,7 +79,7 @@ pub(crate) fn start_tattoys(
             }
 
             if enabled_tattoys.contains(&"notifications".to_owned())
-                || state.config.read().await.notifications.enabled
+                || state.config.load().notifications.enabled
             {
                 tracing::info!("Starting 'notifications' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::notifications::main::Notifications::start(
@@ -44,85 +90,145 @@ pub(crate) fn start_tattoys(
                 crate::run::wait_for_system(&state, "notifications").await;
             }
 
+            if !state.is_true_colour.get() {
+                crate::true_colour::notify(&state).await;
+            }
+
             tracing::info!("Starting 'scrollbar' tattoy...");
             tattoy_futures.spawn(crate::tattoys::scrollbar::Scrollbar::start(
                 output.clone(),
                 Arc::clone(&state),
+                palette.clone(),
             ));
 
-            if enabled_tattoys.contains(&"random_walker".to_owned()) {
-                tracing::info!("Starting 'random_walker' tattoy...");
-                tattoy_futures.spawn(crate::tattoys::random_walker::RandomWalker::start(
-                    output.clone(),
-                    Arc::clone(&state),
-                ));
-            }
+            tracing::info!("Starting 'settings_panel' tattoy...");
+            tattoy_futures.spawn(crate::tattoys::settings_panel::SettingsPanel::start(
+                output.clone(),
+                Arc::clone(&state),
+            ));
 
-            if enabled_tattoys.contains(&"minimap".to_owned())
-                || state.config.read().await.minimap.enabled
-            {
-                tracing::info!("Starting 'minimap' tattoy...");
-                tattoy_futures.spawn(crate::tattoys::minimap::Minimap::start(
-                    output.clone(),
-                    Arc::clone(&state),
-                ));
-            }
+            tracing::info!("Starting 'bookmarks' tattoy...");
+            tattoy_futures.spawn(crate::tattoys::bookmarks::Bookmarks::start(
+                output.clone(),
+                Arc::clone(&state),
+                palette.clone(),
+            ));
 
-            if enabled_tattoys.contains(&"shaders".to_owned())
-                || state.config.read().await.shader.enabled
-            {
-                tracing::info!("Starting 'shaders' tattoy...");
-                tattoy_futures.spawn(crate::tattoys::shaders::main::Shaders::start(
-                    output.clone(),
-                    Arc::clone(&state),
-                ));
-            }
+            tracing::info!("Starting 'search' tattoy...");
+            tattoy_futures.spawn(crate::tattoys::search::Search::start(
+                output.clone(),
+                Arc::clone(&state),
+                palette.clone(),
+            ));
 
-            if enabled_tattoys.contains(&"bg_command".to_owned())
-                || state.config.read().await.bg_command.enabled
-            {
-                tracing::info!("Starting 'bg_command' tattoy...");
-                tattoy_futures.spawn(crate::tattoys::bg_command::BGCommand::start(
-                    output.clone(),
-                    Arc::clone(&state),
-                    palette.clone(),
-                ));
+            tracing::info!("Starting 'palette_editor' tattoy...");
+            tattoy_futures.spawn(crate::tattoys::palette_editor::PaletteEditor::start(
+                output.clone(),
+                Arc::clone(&state),
+                palette.clone(),
+            ));
+
+            tracing::info!("Starting 'shader_browser' tattoy...");
+            tattoy_futures.spawn(crate::tattoys::shaders::browser::ShaderBrowser::start(
+                output.clone(),
+                Arc::clone(&state),
+            ));
+
+            for (name, _) in TOGGLEABLE_TATTOYS {
+                if enabled_tattoys.contains(&name.to_owned())
+                    || is_enabled_in_config(name, &*state.config.load())
+                {
+                    tracing::info!("Starting '{name}' tattoy...");
+                    spawn_toggleable(
+                        name,
+                        &mut tattoy_futures,
+                        &mut toggleable_tasks,
+                        &output,
+                        &state,
+                        &palette,
+                    );
+                }
             }
 
-            for plugin_config in &state.config.read().await.plugins {
+            for plugin_config in &state.config.load().plugins {
                 if let Some(is_enabled) = plugin_config.enabled {
                     if !is_enabled {
                         continue;
                     }
                 }
 
-                tattoy_futures.spawn(crate::tattoys::plugins::Plugin::start(
+                let handle = tattoy_futures.spawn(crate::tattoys::plugins::Plugin::start(
                     plugin_config.clone(),
                     palette.clone(),
                     Arc::clone(&state),
                     output.clone(),
                 ));
+                plugin_tasks.insert(handle.id(), plugin_config.name.clone());
             }
+            state
+                .initialised_systems
+                .write()
+                .await
+                .push("tattoys".to_owned());
+
+            let mut protocol = state.protocol_tx.subscribe();
 
-            while let Some(completes) = tattoy_futures.join_next().await {
-                match completes {
-                    Ok(result) => match result {
-                        Ok(()) => tracing::debug!("A tattoy succesfully exited"),
-                        Err(error) => {
-                            let title = "Unhandled tattoy error";
-                            let message = format!("{title}: {error:?}");
-                            tracing::warn!(message);
-                            state
-                                .send_notification(
-                                    title,
-                                    crate::tattoys::notifications::message::Level::Error,
-                                    Some(error.root_cause().to_string()),
-                                    true,
-                                )
-                                .await;
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    maybe_completed = tattoy_futures.join_next_with_id() => {
+                        let Some(completed) = maybe_completed else {
+                            break;
+                        };
+                        let maybe_surface_id = handle_completed_tattoy(
+                            completed,
+                            &mut toggleable_tasks,
+                            &mut plugin_tasks,
+                            &state,
+                        )
+                        .await;
+                        if let Some(surface_id) = maybe_surface_id {
+                            forget_surface(&output, surface_id);
+                        }
+                    }
+                    Ok(message) = protocol.recv() => {
+                        if let crate::run::Protocol::Config { config, changes } = &message {
+                            for (name, section) in TOGGLEABLE_TATTOYS {
+                                let is_running = toggleable_tasks
+                                    .values()
+                                    .any(|running_name| running_name == name);
+                                let is_newly_enabled = changes.contains(&section)
+                                    && !is_running
+                                    && is_enabled_in_config(name, config);
+                                if is_newly_enabled {
+                                    tracing::info!(
+                                        "Starting '{name}' tattoy after config update..."
+                                    );
+                                    spawn_toggleable(
+                                        name,
+                                        &mut tattoy_futures,
+                                        &mut toggleable_tasks,
+                                        &output,
+                                        &state,
+                                        &palette,
+                                    );
+                                }
+                            }
+                        }
+                        if matches!(message, crate::run::Protocol::Screensaver(true)) {
+                            spawn_screensaver_tattoy(
+                                &mut tattoy_futures,
+                                &mut toggleable_tasks,
+                                &output,
+                                &state,
+                                &palette,
+                            )
+                            .await;
                         }
-                    },
-                    Err(error) => tracing::error!("Tattoy task join error: {error:?}"),
+                    }
                 }
             }
 
@@ -130,3 +236,288 @@ pub(crate) fn start_tattoys(
         })
     })
 }
+
+/// The names of all the tattoys that can be toggled at runtime.
+pub(crate) fn toggleable_tattoys() -> impl Iterator<Item = &'static str> {
+    TOGGLEABLE_TATTOYS.iter().map(|(name, _)| *name)
+}
+
+/// Whether one of the toggleable tattoys is enabled in the given config.
+pub(crate) fn is_enabled_in_config(name: &str, config: &crate::config::main::Config) -> bool {
+    match name {
+        "minimap" => config.minimap.enabled,
+        "shaders" => config.shader.enabled,
+        "bg_command" => config.bg_command.enabled,
+        "weather" => config.weather.enabled,
+        "visual_bell" => config.visual_bell.enabled,
+        "system_stats" => config.system_stats.enabled,
+        "clock" => config.clock.enabled,
+        "pomodoro" => config.pomodoro.enabled,
+        "git_status" => config.git_status.enabled,
+        "audio_visualiser" => config.audio_visualiser.enabled,
+        "typing_sparks" => config.typing_sparks.enabled,
+        "cursor_smear" => config.cursor_smear.enabled,
+        "crt" => config.crt.enabled,
+        "starfield" => config.starfield.enabled,
+        "screensaver" => config.screensaver.enabled,
+        "command_duration" => config.command_duration.enabled,
+        "image_preview" => config.image_preview.enabled,
+        "status_bar" => config.status_bar.enabled,
+        "focus" => config.focus.enabled,
+        "cursor_highlight" => config.cursor_highlight.enabled,
+        "random_walker" => config.random_walker.enabled,
+        "magnifier" => config.magnifier.enabled,
+        "performance_hud" => config.performance_hud.enabled,
+        "log_viewer" => config.log_viewer.enabled,
+        _ => false,
+    }
+}
+
+/// Spawn one of the tattoys that can be toggled at runtime, keeping track of its task so we know
+/// whether it's still running.
+fn spawn_toggleable(
+    name: &str,
+    tattoy_futures: &mut TattoyFutures,
+    toggleable_tasks: &mut ToggleableTasks,
+    output: &tokio::sync::mpsc::Sender<FrameUpdate>,
+    state: &Arc<crate::shared_state::SharedState>,
+    palette: &crate::palette::converter::Palette,
+) {
+    let handle = match name {
+        "minimap" => tattoy_futures.spawn(crate::tattoys::minimap::Minimap::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "shaders" => tattoy_futures.spawn(crate::tattoys::shaders::main::Shaders::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "bg_command" => tattoy_futures.spawn(crate::tattoys::bg_command::BGCommand::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        "weather" => tattoy_futures.spawn(crate::tattoys::weather::Weather::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "visual_bell" => tattoy_futures.spawn(crate::tattoys::visual_bell::VisualBell::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "system_stats" => tattoy_futures.spawn(
+            crate::tattoys::system_stats::SystemStats::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "clock" => tattoy_futures.spawn(crate::tattoys::clock::Clock::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        "pomodoro" => tattoy_futures.spawn(crate::tattoys::pomodoro::Pomodoro::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        "git_status" => tattoy_futures.spawn(crate::tattoys::git_status::GitStatus::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        #[cfg(feature = "audio_visualiser")]
+        "audio_visualiser" => tattoy_futures.spawn(
+            crate::tattoys::audio_visualiser::main::AudioVisualiser::start(
+                output.clone(),
+                Arc::clone(state),
+            ),
+        ),
+        #[cfg(not(feature = "audio_visualiser"))]
+        "audio_visualiser" => {
+            tracing::error!(
+                "The audio visualiser isn't available, Tattoy needs to be built with the \
+                `audio_visualiser` feature"
+            );
+            return;
+        }
+        "typing_sparks" => tattoy_futures.spawn(
+            crate::tattoys::typing_sparks::TypingSparks::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "cursor_smear" => tattoy_futures.spawn(
+            crate::tattoys::cursor_smear::CursorSmear::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "crt" => tattoy_futures.spawn(crate::tattoys::crt::Crt::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "starfield" => tattoy_futures.spawn(crate::tattoys::starfield::Starfield::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "screensaver" => tattoy_futures.spawn(
+            crate::tattoys::screensaver::Screensaver::start(output.clone(), Arc::clone(state)),
+        ),
+        "command_duration" => tattoy_futures.spawn(
+            crate::tattoys::command_duration::CommandDuration::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "image_preview" => tattoy_futures.spawn(
+            crate::tattoys::image_preview::ImagePreview::start(output.clone(), Arc::clone(state)),
+        ),
+        "status_bar" => tattoy_futures.spawn(crate::tattoys::status_bar::StatusBar::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        "focus" => tattoy_futures.spawn(crate::tattoys::focus::Focus::start(
+            output.clone(),
+            Arc::clone(state),
+        )),
+        "cursor_highlight" => tattoy_futures.spawn(
+            crate::tattoys::cursor_highlight::CursorHighlight::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "random_walker" => tattoy_futures.spawn(
+            crate::tattoys::random_walker::RandomWalker::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "magnifier" => tattoy_futures.spawn(crate::tattoys::magnifier::Magnifier::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        "performance_hud" => tattoy_futures.spawn(
+            crate::tattoys::performance_hud::PerformanceHud::start(
+                output.clone(),
+                Arc::clone(state),
+                palette.clone(),
+            ),
+        ),
+        "log_viewer" => tattoy_futures.spawn(crate::tattoys::log_viewer::LogViewer::start(
+            output.clone(),
+            Arc::clone(state),
+            palette.clone(),
+        )),
+        _ => {
+            tracing::error!("Unknown toggleable tattoy: {name}");
+            return;
+        }
+    };
+    toggleable_tasks.insert(handle.id(), name.to_owned());
+}
+
+/// Start the tattoy that the screensaver shows, unless it's already running. It's stopped again
+/// when the terminal wakes up, see `Tattoyer::is_disabled_by()`.
+async fn spawn_screensaver_tattoy(
+    tattoy_futures: &mut TattoyFutures,
+    toggleable_tasks: &mut ToggleableTasks,
+    output: &tokio::sync::mpsc::Sender<FrameUpdate>,
+    state: &Arc<crate::shared_state::SharedState>,
+    palette: &crate::palette::converter::Palette,
+) {
+    let name = state.config.load().screensaver.tattoy.clone();
+    if !TOGGLEABLE_TATTOYS
+        .iter()
+        .any(|(toggleable, _)| *toggleable == name)
+    {
+        tracing::warn!("Unknown screensaver tattoy: {name}");
+        return;
+    }
+
+    let is_running = toggleable_tasks
+        .values()
+        .any(|running_name| *running_name == name);
+    if is_running {
+        return;
+    }
+
+    tracing::info!("Starting '{name}' tattoy for the screensaver...");
+    spawn_toggleable(
+        &name,
+        tattoy_futures,
+        toggleable_tasks,
+        output,
+        state,
+        palette,
+    );
+    *state.screensaver_tattoy.write().await = Some(surface_id(&name).to_owned());
+}
+
+/// The ID that a toggleable tattoy gives to the surfaces that it renders. They're the same as the
+/// tattoy's name, apart from the shaders.
+pub(crate) fn surface_id(name: &str) -> &str {
+    match name {
+        "shaders" => "shader",
+        _ => name,
+    }
+}
+
+/// Tell the renderer to forget the last frame of a tattoy that isn't running anymore. Tattoys
+/// normally do this themselves when they're disabled, but not when they exit with an error. It
+/// doesn't wait for room in the channel, because the renderer may have already stopped.
+fn forget_surface(output: &tokio::sync::mpsc::Sender<FrameUpdate>, surface_id: String) {
+    let blank = crate::surface::Surface::new(surface_id, 0, 0, 0, 0.0);
+    let result = output.try_send(FrameUpdate::TattoySurface(blank));
+    if let Err(error) = result {
+        tracing::debug!("Couldn't tell the renderer to forget a tattoy's surface: {error:?}");
+    }
+}
+
+/// Handle a tattoy's task finishing, whether successfully or not. Returns the ID of the surface
+/// that the tattoy rendered to, if it's known.
+async fn handle_completed_tattoy(
+    completed: Result<(tokio::task::Id, Result<()>), tokio::task::JoinError>,
+    toggleable_tasks: &mut ToggleableTasks,
+    plugin_tasks: &mut PluginTasks,
+    state: &Arc<crate::shared_state::SharedState>,
+) -> Option<String> {
+    let id = match &completed {
+        Ok((id, _)) => *id,
+        Err(error) => error.id(),
+    };
+    let maybe_surface_id = toggleable_tasks
+        .remove(&id)
+        .map(|name| surface_id(&name).to_owned())
+        .or_else(|| plugin_tasks.remove(&id));
+
+    match completed {
+        Ok((_, Ok(()))) => tracing::debug!("A tattoy succesfully exited"),
+        Ok((_, Err(error))) => {
+            let title = "Unhandled tattoy error";
+            let message = format!("{title}: {error:?}");
+            tracing::warn!(message);
+            state
+                .send_notification(
+                    title,
+                    crate::tattoys::notifications::message::Level::Error,
+                    Some(error.root_cause().to_string()),
+                    true,
+                )
+                .await;
+        }
+        Err(error) => {
+            tracing::error!("Tattoy task join error: {error:?}");
+        }
+    }
+
+    maybe_surface_id
+}
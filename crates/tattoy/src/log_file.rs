@@ -0,0 +1,319 @@
+//! The file that Tattoy logs to. Trace logging in a long-lived session can write a lot, so the file
+//! is rotated once it reaches a maximum size and only a few of the old files are kept. Each session
+//! can also log to its own file. Logs can be written as plain text or as JSON, one object per line.
+
+/// The number of bytes in a megabyte.
+const BYTES_PER_MEGABYTE: u64 = 1024 * 1024;
+
+/// A log file that's rotated when it gets too big. The current logs are always in the file at
+/// `path`, the previous logs are in `path.1`, the ones before that in `path.2`, and so on.
+pub(crate) struct RotatingFile {
+    /// The path of the current log file.
+    path: std::path::PathBuf,
+    /// The current log file.
+    file: std::fs::File,
+    /// The number of bytes written to the current log file.
+    size: u64,
+    /// The size, in bytes, at which the file is rotated. 0 means that it's never rotated.
+    max_size: u64,
+    /// The number of rotated files to keep.
+    max_files: usize,
+}
+
+impl RotatingFile {
+    /// Create a new, empty, log file.
+    pub(crate) fn create(
+        path: std::path::PathBuf,
+        max_size_megabytes: u64,
+        max_files: usize,
+    ) -> std::io::Result<Self> {
+        let file = std::fs::File::create(&path)?;
+        Ok(Self {
+            path,
+            file,
+            size: 0,
+            max_size: max_size_megabytes.saturating_mul(BYTES_PER_MEGABYTE),
+            max_files,
+        })
+    }
+
+    /// The path of a rotated file, eg `tattoy.log.2`.
+    fn rotated_path(&self, index: usize) -> std::path::PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    /// Shift all the rotated files along by one, forgetting the oldest, and start a new file.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        std::io::Write::flush(&mut self.file)?;
+
+        let oldest = self.rotated_path(self.max_files.max(1));
+        if oldest.exists() {
+            std::fs::remove_file(oldest)?;
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = std::fs::File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl std::io::Write for RotatingFile {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let length = u64::try_from(buffer.len()).unwrap_or(u64::MAX);
+        if self.max_size > 0 && self.size > 0 && self.size.saturating_add(length) > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buffer)?;
+        self.size = self
+            .size
+            .saturating_add(u64::try_from(written).unwrap_or(u64::MAX));
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The path of a log file just for this session, named with the time that Tattoy started and its
+/// process ID, eg `tattoy-20250102-150405-1234.log`.
+pub(crate) fn session_path(
+    path: &std::path::Path,
+    started: chrono::DateTime<chrono::Local>,
+    process_id: u32,
+) -> std::path::PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "tattoy".into(), |stem| stem.to_string_lossy());
+    let mut name = format!("{stem}-{}-{process_id}", started.format("%Y%m%d-%H%M%S"));
+    if let Some(extension) = path.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Delete the oldest session log files, and their rotated files, so that only the given number of
+/// sessions are kept, including the current one.
+pub(crate) fn remove_old_sessions(path: &std::path::Path, keep: usize) -> std::io::Result<()> {
+    let Some(directory) = path.parent() else {
+        return Ok(());
+    };
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "tattoy".into(), |stem| stem.to_string_lossy());
+    let prefix = format!("{stem}-");
+    let suffix = path.extension().map_or_else(String::new, |extension| {
+        format!(".{}", extension.to_string_lossy())
+    });
+
+    // The timestamps in the names mean that sorting by name sorts by age.
+    let mut sessions: Vec<std::path::PathBuf> = std::fs::read_dir(directory)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|session| {
+            session
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(&suffix))
+        })
+        .collect();
+    sessions.sort();
+    let excess = sessions.len().saturating_sub(keep.max(1));
+    for session in sessions.iter().take(excess) {
+        std::fs::remove_file(session)?;
+        for index in 1.. {
+            let mut rotated = session.clone().into_os_string();
+            rotated.push(format!(".{index}"));
+            if std::fs::remove_file(rotated).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the fields of a log event, including its message.
+#[derive(Default)]
+pub(crate) struct Fields(pub serde_json::Map<String, serde_json::Value>);
+
+impl Fields {
+    /// Collect all the fields of an event.
+    pub(crate) fn from_event(event: &tracing::Event<'_>) -> Self {
+        let mut fields = Self::default();
+        event.record(&mut fields);
+        fields
+    }
+
+    /// The event's message, followed by any other fields as `name=value`.
+    pub(crate) fn summary(&self) -> String {
+        let mut parts: Vec<String> = self
+            .0
+            .get("message")
+            .and_then(serde_json::Value::as_str)
+            .map(ToOwned::to_owned)
+            .into_iter()
+            .collect();
+        for (name, value) in self.0.iter().filter(|(name, _)| *name != "message") {
+            match value.as_str() {
+                Some(text) => parts.push(format!("{name}={text}")),
+                None => parts.push(format!("{name}={value}")),
+            }
+        }
+        parts.join(" ")
+    }
+}
+
+impl tracing::field::Visit for Fields {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn core::fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{value:?}").into());
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+}
+
+/// Formats each log event as a single line of JSON, so that logs can be read by other tools.
+pub(crate) struct JsonFormat;
+
+impl<S, N> tracing_subscriber::fmt::FormatEvent<S, N> for JsonFormat
+where
+    S: tracing::Subscriber + for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
+    N: for<'writer> tracing_subscriber::fmt::FormatFields<'writer> + 'static,
+{
+    fn format_event(
+        &self,
+        context: &tracing_subscriber::fmt::FmtContext<'_, S, N>,
+        mut writer: tracing_subscriber::fmt::format::Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> core::fmt::Result {
+        use core::fmt::Write as _;
+
+        let metadata = event.metadata();
+        let spans: Vec<String> = context
+            .event_scope()
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| span.name().to_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let line = serde_json::json!({
+            "timestamp": chrono::Local::now().to_rfc3339(),
+            "level": metadata.level().to_string(),
+            "target": metadata.target(),
+            "spans": spans,
+            "fields": Fields::from_event(event).0,
+        });
+        writeln!(writer, "{line}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write as _;
+
+    #[test]
+    fn rotating_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tattoy.log");
+        let mut file = RotatingFile::create(path.clone(), 1, 2).unwrap();
+        file.max_size = 10;
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: &std::path::Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "fourth\n");
+        assert_eq!(read(&file.rotated_path(1)), "third\n");
+        assert_eq!(read(&file.rotated_path(2)), "second\n");
+        assert!(!file.rotated_path(3).exists());
+    }
+
+    #[test]
+    fn naming_session_files() {
+        let started = chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2025, 1, 2, 15, 4, 5)
+            .single()
+            .unwrap();
+        let path = std::path::Path::new("/logs/tattoy.log");
+        assert_eq!(
+            session_path(path, started, 1234),
+            std::path::Path::new("/logs/tattoy-20250102-150405-1234.log")
+        );
+    }
+
+    #[test]
+    fn removing_old_sessions() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tattoy.log");
+        for name in [
+            "tattoy-20250101-000000-1.log",
+            "tattoy-20250101-000000-1.log.1",
+            "tattoy-20250102-000000-2.log",
+            "tattoy-20250103-000000-3.log",
+            "other.log",
+        ] {
+            std::fs::write(directory.path().join(name), "").unwrap();
+        }
+
+        remove_old_sessions(&path, 2).unwrap();
+        let mut remaining: Vec<String> = std::fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            [
+                "other.log",
+                "tattoy-20250102-000000-2.log",
+                "tattoy-20250103-000000-3.log"
+            ]
+        );
+    }
+
+    #[test]
+    fn summarising_fields() {
+        let mut fields = Fields::default();
+        fields
+            .0
+            .insert("message".to_owned(), "Couldn't load".into());
+        fields.0.insert("path".to_owned(), "/tmp/x".into());
+        fields.0.insert("retries".to_owned(), 3.into());
+        assert_eq!(fields.summary(), "Couldn't load path=/tmp/x retries=3");
+    }
+}
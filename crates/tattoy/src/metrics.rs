@@ -0,0 +1,320 @@
+//! Exporting Tattoy's performance statistics, so that performance problems in long-running
+//! sessions can be diagnosed after the fact. A snapshot of the statistics, including frame time
+//! histograms and per-surface update counters, is periodically appended to a local metrics file.
+//! The same statistics can also be scraped by Prometheus.
+
+use color_eyre::eyre::{Result, WrapErr as _};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// User-configurable settings for exporting metrics.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to export metrics.
+    pub enabled: bool,
+    /// The file that metrics are appended to, one JSON object per line. Defaults to
+    /// `metrics.jsonl` next to the log file.
+    pub path: Option<std::path::PathBuf>,
+    /// How often metrics are written, in seconds.
+    pub interval: u64,
+    /// The address to serve Prometheus metrics on, eg `127.0.0.1:9464`. Prometheus metrics
+    /// aren't served when this isn't set.
+    pub prometheus_address: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            interval: 60,
+            prometheus_address: None,
+        }
+    }
+}
+
+/// The name of the default metrics file.
+const METRICS_FILENAME: &str = "metrics.jsonl";
+
+/// The largest HTTP request that is read from Prometheus. Anything more is ignored.
+const MAX_REQUEST_SIZE: usize = 4096;
+
+/// A single line of the metrics file.
+#[derive(serde::Serialize)]
+struct Record<'snapshot> {
+    /// When the snapshot was taken.
+    timestamp: String,
+    /// The ID of Tattoy's process, to tell sessions apart.
+    process_id: u32,
+    /// The performance statistics.
+    #[serde(flatten)]
+    snapshot: &'snapshot crate::performance::Snapshot,
+}
+
+/// The path of the metrics file.
+fn path(config: &crate::config::main::Config) -> std::path::PathBuf {
+    config.metrics.path.clone().unwrap_or_else(|| {
+        config
+            .log_path
+            .parent()
+            .map_or_else(std::path::PathBuf::new, std::path::Path::to_path_buf)
+            .join(METRICS_FILENAME)
+    })
+}
+
+/// Append a snapshot of the statistics to the metrics file.
+async fn append(
+    path: &std::path::Path,
+    snapshot: &crate::performance::Snapshot,
+    timestamp: chrono::DateTime<chrono::Local>,
+) -> Result<()> {
+    let record = Record {
+        timestamp: timestamp.to_rfc3339(),
+        process_id: std::process::id(),
+        snapshot,
+    };
+    let line = format!("{}\n", serde_json::to_string(&record)?);
+
+    if let Some(directory) = path.parent() {
+        tokio::fs::create_dir_all(directory).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Add a histogram's lines, in Prometheus' text format. Prometheus' buckets are cumulative.
+fn prometheus_histogram(
+    lines: &mut Vec<String>,
+    name: &str,
+    help: &str,
+    histogram: &crate::performance::Histogram,
+) {
+    lines.push(format!("# HELP {name} {help}"));
+    lines.push(format!("# TYPE {name} histogram"));
+    let mut cumulative: u64 = 0;
+    for (bound, count) in crate::performance::HISTOGRAM_BOUNDS_MS
+        .iter()
+        .zip(histogram.buckets)
+    {
+        cumulative = cumulative.saturating_add(count);
+        lines.push(format!("{name}_bucket{{le=\"{bound}\"}} {cumulative}"));
+    }
+    lines.push(format!("{name}_bucket{{le=\"+Inf\"}} {}", histogram.count));
+    lines.push(format!("{name}_sum {}", histogram.sum_ms));
+    lines.push(format!("{name}_count {}", histogram.count));
+}
+
+/// Add the lines of a metric with a single value, in Prometheus' text format.
+fn prometheus_value(lines: &mut Vec<String>, name: &str, kind: &str, help: &str, value: &str) {
+    lines.push(format!("# HELP {name} {help}"));
+    lines.push(format!("# TYPE {name} {kind}"));
+    lines.push(format!("{name} {value}"));
+}
+
+/// The statistics in Prometheus' text format.
+fn prometheus(snapshot: &crate::performance::Snapshot) -> String {
+    let mut lines = Vec::new();
+    prometheus_histogram(
+        &mut lines,
+        "tattoy_frame_time_milliseconds",
+        "How long frames took to composite and paint.",
+        &snapshot.frame_histogram,
+    );
+    prometheus_histogram(
+        &mut lines,
+        "tattoy_composite_time_milliseconds",
+        "How long frames took just to composite.",
+        &snapshot.composite_histogram,
+    );
+    prometheus_value(
+        &mut lines,
+        "tattoy_frames_per_second",
+        "gauge",
+        "The number of frames painted in the last second.",
+        &snapshot.frames_per_second.to_string(),
+    );
+    prometheus_value(
+        &mut lines,
+        "tattoy_backlog",
+        "gauge",
+        "The number of frame updates waiting in the renderer's backlog.",
+        &snapshot.backlog.to_string(),
+    );
+    prometheus_value(
+        &mut lines,
+        "tattoy_skipped_frames_total",
+        "counter",
+        "The number of frame updates that weren't painted because the renderer was behind.",
+        &snapshot.skipped_frames.to_string(),
+    );
+    prometheus_value(
+        &mut lines,
+        "tattoy_stored_surfaces_bytes",
+        "gauge",
+        "Roughly how much memory is used to keep the latest frame of every tattoy.",
+        &snapshot.stored_surfaces_size.to_string(),
+    );
+
+    let name = "tattoy_updates_total";
+    lines.push(format!(
+        "# HELP {name} The number of frame updates sent by each tattoy and the PTY."
+    ));
+    lines.push(format!("# TYPE {name} counter"));
+    for (surface, count) in &snapshot.updates_total {
+        lines.push(format!("{name}{{surface=\"{surface}\"}} {count}"));
+    }
+    format!("{}\n", lines.join("\n"))
+}
+
+/// Respond to a single request from Prometheus. Every request gets the metrics, whatever its path.
+async fn respond(
+    state: &crate::shared_state::SharedState,
+    mut stream: tokio::net::TcpStream,
+) -> Result<()> {
+    let mut request = vec![0; MAX_REQUEST_SIZE];
+    let _size = stream.read(&mut request).await?;
+
+    let body = prometheus(&state.performance.read().await.snapshot());
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n\
+         {body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Accept a connection from Prometheus, or wait forever if Prometheus metrics aren't enabled.
+async fn accept(
+    maybe_listener: Option<&tokio::net::TcpListener>,
+) -> std::io::Result<tokio::net::TcpStream> {
+    match maybe_listener {
+        Some(listener) => Ok(listener.accept().await?.0),
+        None => std::future::pending().await,
+    }
+}
+
+/// Start exporting metrics, if they're enabled. Changes to `interval` and `prometheus_address`
+/// only take effect after a restart.
+pub(crate) async fn start(
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+) -> Result<tokio::task::JoinHandle<Result<()>>> {
+    let config = state.config.load().metrics.clone();
+    let maybe_listener = match config.prometheus_address.as_ref() {
+        Some(address) if config.enabled => {
+            let listener = tokio::net::TcpListener::bind(address)
+                .await
+                .wrap_err_with(|| format!("Couldn't serve Prometheus metrics on {address}"))?;
+            tracing::debug!("Serving Prometheus metrics on {address}");
+            Some(listener)
+        }
+        _ => None,
+    };
+
+    Ok(tokio::spawn(async move {
+        let mut protocol = state.protocol_tx.subscribe();
+        let interval = std::time::Duration::from_secs(config.interval.max(1));
+        let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    let config = state.config.load();
+                    if !config.metrics.enabled {
+                        continue;
+                    }
+                    let snapshot = state.performance.read().await.snapshot();
+                    let path = path(&config);
+                    if let Err(error) = append(&path, &snapshot, chrono::Local::now()).await {
+                        tracing::warn!("Writing metrics to {}: {error:?}", path.display());
+                    }
+                },
+                result = accept(maybe_listener.as_ref()) => match result {
+                    Ok(stream) => {
+                        if let Err(error) = respond(&state, stream).await {
+                            tracing::warn!("Responding to Prometheus: {error:?}");
+                        }
+                    }
+                    Err(error) => tracing::error!("Accepting Prometheus connection: {error:?}"),
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot() -> crate::performance::Snapshot {
+        let mut snapshot = crate::performance::Snapshot {
+            frames_per_second: 30.0,
+            skipped_frames: 2,
+            stored_surfaces_size: 4096,
+            ..Default::default()
+        };
+        snapshot.frame_histogram.buckets = [1, 0, 2, 0, 0, 1, 0, 0, 0, 1];
+        snapshot.frame_histogram.count = 5;
+        snapshot.frame_histogram.sum_ms = 1027.0;
+        snapshot.updates_total.insert("shader".to_owned(), 42);
+        snapshot
+    }
+
+    #[test]
+    fn prometheus_format() {
+        let output = prometheus(&snapshot());
+        assert!(output.contains("# TYPE tattoy_frame_time_milliseconds histogram\n"));
+        assert!(output.contains("tattoy_frame_time_milliseconds_bucket{le=\"1\"} 1\n"));
+        assert!(output.contains("tattoy_frame_time_milliseconds_bucket{le=\"4\"} 3\n"));
+        assert!(output.contains("tattoy_frame_time_milliseconds_bucket{le=\"250\"} 4\n"));
+        assert!(output.contains("tattoy_frame_time_milliseconds_bucket{le=\"+Inf\"} 5\n"));
+        assert!(output.contains("tattoy_frame_time_milliseconds_sum 1027\n"));
+        assert!(output.contains("tattoy_frames_per_second 30\n"));
+        assert!(output.contains("tattoy_skipped_frames_total 2\n"));
+        assert!(output.contains("tattoy_stored_surfaces_bytes 4096\n"));
+        assert!(output.contains("tattoy_updates_total{surface=\"shader\"} 42\n"));
+    }
+
+    #[tokio::test]
+    async fn appending_to_the_metrics_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("metrics").join(METRICS_FILENAME);
+        append(&path, &snapshot(), chrono::Local::now())
+            .await
+            .unwrap();
+        append(&path, &snapshot(), chrono::Local::now())
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["skipped_frames"], 2);
+        assert_eq!(lines[0]["updates_total"]["shader"], 42);
+        assert_eq!(lines[1]["process_id"], std::process::id());
+    }
+}
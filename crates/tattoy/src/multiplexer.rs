@@ -0,0 +1,216 @@
+//! Compatibility with terminal multiplexers, namely tmux and GNU Screen. Tattoy and multiplexers
+//! both manage their own screen, scrollback and mouse, so when they're layered they get in each
+//! other's way.
+//!
+//! When a multiplexer is the command running inside Tattoy, Tattoy's own scrolling is disabled and
+//! mouse events are always forwarded, so that only the multiplexer handles them. When Tattoy is
+//! itself running inside a multiplexer, the OSC sequences that Tattoy sends to the user's terminal,
+//! whether they're its own queries or codes forwarded from the PTY, like the cursor colour, are
+//! wrapped in the multiplexer's "passthrough" sequence. Otherwise the multiplexer would swallow
+//! them, or reply to them itself.
+//!
+//! Only the OSC codes that the shadow terminal understands can be forwarded, and currently that's
+//! just the cursor colour. Any others, eg hyperlinks or clipboard writes, are consumed by its
+//! terminal emulator, so they never reach Tattoy. This limitation is documented with the
+//! `multiplexer_compatibility` config option.
+
+/// The start of tmux's passthrough sequence. tmux only honours it with `allow-passthrough on`.
+const TMUX_PASSTHROUGH_START: &str = "\x1bPtmux;";
+
+/// The start of GNU Screen's passthrough sequence.
+const SCREEN_PASSTHROUGH_START: &str = "\x1bP";
+
+/// The "String Terminator" that ends passthrough and OSC sequences.
+const STRING_TERMINATOR: &str = "\x1b\\";
+
+/// The BEL character, an alternative terminator for OSC sequences.
+const BEL: &str = "\x07";
+
+/// The terminal multiplexers that Tattoy knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Multiplexer {
+    /// tmux
+    Tmux,
+    /// GNU Screen
+    Screen,
+}
+
+/// The multiplexers either side of Tattoy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Detected {
+    /// The multiplexer that Tattoy is running inside of.
+    pub outer: Option<Multiplexer>,
+    /// The multiplexer that Tattoy is running as its command.
+    pub inner: Option<Multiplexer>,
+}
+
+impl Multiplexer {
+    /// Detect the multiplexer that Tattoy is running inside of, using the given environment
+    /// variable getter.
+    fn outer_from_env<F: Fn(&str) -> Option<String>>(get_env: F) -> Option<Self> {
+        let is_set = |name: &str| get_env(name).is_some_and(|value| !value.is_empty());
+        if is_set("TMUX") {
+            Some(Self::Tmux)
+        } else if is_set("STY") {
+            Some(Self::Screen)
+        } else {
+            None
+        }
+    }
+
+    /// Detect whether the command that Tattoy runs is a multiplexer, eg `tmux attach`.
+    fn from_command(command: &[std::ffi::OsString]) -> Option<Self> {
+        let program = std::path::Path::new(command.first()?)
+            .file_stem()?
+            .to_str()?;
+        match program {
+            "tmux" => Some(Self::Tmux),
+            "screen" => Some(Self::Screen),
+            _ => None,
+        }
+    }
+
+    /// Wrap escape sequences so that the multiplexer passes them through to the user's terminal.
+    ///
+    /// tmux can pass through many sequences at once, as long as their escape characters are
+    /// doubled. Screen ends its passthrough at the first String Terminator, so each sequence is
+    /// wrapped on its own, with its String Terminator swapped for BEL.
+    pub(crate) fn passthrough(self, sequences: &str) -> String {
+        match self {
+            Self::Tmux => format!(
+                "{TMUX_PASSTHROUGH_START}{}{STRING_TERMINATOR}",
+                sequences.replace('\x1b', "\x1b\x1b")
+            ),
+            Self::Screen => split_sequences(sequences)
+                .into_iter()
+                .map(|sequence| {
+                    let sequence = sequence
+                        .strip_suffix(STRING_TERMINATOR)
+                        .map_or_else(|| sequence.to_owned(), |osc| format!("{osc}{BEL}"));
+                    format!("{SCREEN_PASSTHROUGH_START}{sequence}{STRING_TERMINATOR}")
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Split a string of escape sequences into the individual sequences. A new sequence starts at
+/// every escape character, apart from the ones that start a String Terminator.
+fn split_sequences(sequences: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut start = 0;
+    for (index, _) in sequences.match_indices('\x1b') {
+        let is_terminator = sequences
+            .get(index..)
+            .is_some_and(|rest| rest.starts_with(STRING_TERMINATOR));
+        if index > start && !is_terminator {
+            split.extend(sequences.get(start..index));
+            start = index;
+        }
+    }
+    if start < sequences.len() {
+        split.extend(sequences.get(start..));
+    }
+    split
+}
+
+/// Detect the multiplexers either side of Tattoy and save them in the shared state. Nothing is
+/// detected if multiplexer compatibility is disabled in the config.
+pub(crate) fn detect(state: &crate::shared_state::SharedState, command: &[std::ffi::OsString]) {
+    if !state.config.load().multiplexer_compatibility {
+        return;
+    }
+
+    let detected = Detected {
+        outer: Multiplexer::outer_from_env(|name| std::env::var(name).ok()),
+        inner: Multiplexer::from_command(command),
+    };
+    if detected != Detected::default() {
+        tracing::info!("Multiplexer compatibility enabled for: {detected:?}");
+    }
+    state.multiplexers.store(detected);
+}
+
+/// Wrap a query, or any other OSC code, for the user's terminal in the passthrough sequence of the
+/// multiplexer that Tattoy is running inside of, if there is one.
+pub(crate) fn wrap_query(state: &crate::shared_state::SharedState, query: &str) -> String {
+    match state.multiplexers.get().outer {
+        Some(multiplexer) => multiplexer.passthrough(query),
+        None => query.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detecting_outer_multiplexers() {
+        let env = |name: &str, value: &str| {
+            let (name, value) = (name.to_owned(), value.to_owned());
+            move |requested: &str| (requested == name).then(|| value.clone())
+        };
+        assert_eq!(
+            Multiplexer::outer_from_env(env("TMUX", "/tmp/tmux-1000/default,1234,0")),
+            Some(Multiplexer::Tmux)
+        );
+        assert_eq!(
+            Multiplexer::outer_from_env(env("STY", "1234.pts-0.host")),
+            Some(Multiplexer::Screen)
+        );
+        assert_eq!(Multiplexer::outer_from_env(env("TMUX", "")), None);
+        assert_eq!(Multiplexer::outer_from_env(env("TERM", "xterm")), None);
+    }
+
+    #[test]
+    fn detecting_inner_multiplexers() {
+        let command = |parts: &[&str]| -> Vec<std::ffi::OsString> {
+            parts.iter().map(std::ffi::OsString::from).collect()
+        };
+        assert_eq!(
+            Multiplexer::from_command(&command(&["/usr/bin/tmux", "attach"])),
+            Some(Multiplexer::Tmux)
+        );
+        assert_eq!(
+            Multiplexer::from_command(&command(&["screen", "-R"])),
+            Some(Multiplexer::Screen)
+        );
+        assert_eq!(Multiplexer::from_command(&command(&["zsh"])), None);
+        assert_eq!(Multiplexer::from_command(&command(&[])), None);
+    }
+
+    #[test]
+    fn tmux_passthrough() {
+        assert_eq!(
+            Multiplexer::Tmux.passthrough("\x1b]11;?\x1b\\\x1b[c"),
+            "\x1bPtmux;\x1b\x1b]11;?\x1b\x1b\\\x1b\x1b[c\x1b\\"
+        );
+    }
+
+    #[tokio::test]
+    async fn wrapping_forwarded_codes() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        let code = crate::cursor_style::colour_code(None);
+        assert_eq!(wrap_query(&state, &code), code);
+
+        state.multiplexers.store(Detected {
+            outer: Some(Multiplexer::Tmux),
+            inner: None,
+        });
+        assert_eq!(
+            wrap_query(&state, &code),
+            "\x1bPtmux;\x1b\x1b]112\x1b\x1b\\\x1b\\"
+        );
+    }
+
+    #[test]
+    fn screen_passthrough() {
+        assert_eq!(
+            Multiplexer::Screen.passthrough("\x1b]10;?\x1b\\\x1b]11;?\x07\x1b[c"),
+            "\x1bP\x1b]10;?\x07\x1b\\\x1bP\x1b]11;?\x07\x1b\\\x1bP\x1b[c\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Import Base16 and Base24 colour schemes as the terminal palette.
+//!
+//! See: <https://github.com/tinted-theming/home>
+//!
+//! Scheme files are YAML, but the format is so simple that they can be parsed line by line. Both
+//! the original flat format, eg `base00: "181818"`, and the newer format, where the colours are
+//! nested under a `palette:` key and have a leading `#`, are supported.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+
+/// How the 16 ANSI colours are assigned from a Base16 scheme. This is the same mapping as used by
+/// `base16-shell`.
+const BASE16_ANSI_MAPPING: [&str; 16] = [
+    "00", "08", "0B", "0A", "0D", "0E", "0C", "05", "03", "08", "0B", "0A", "0D", "0E", "0C", "07",
+];
+
+/// Base24 schemes have dedicated colours for the bright ANSI colours.
+const BASE24_ANSI_MAPPING: [&str; 16] = [
+    "00", "08", "0B", "0A", "0D", "0E", "0C", "05", "03", "12", "14", "13", "16", "17", "15", "07",
+];
+
+/// The scheme colour used for the default foreground.
+const FOREGROUND_BASE: &str = "05";
+
+/// The scheme colour used for the default background.
+const BACKGROUND_BASE: &str = "00";
+
+/// The colours of a scheme, keyed by their uppercase hex suffix, eg `0A` for `base0A`.
+type Scheme = std::collections::HashMap<String, super::converter::PaletteColour>;
+
+/// Parse the contents of a Base16 or Base24 scheme file.
+pub(crate) fn parse_scheme(contents: &str) -> Scheme {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once(':')?;
+            let suffix = key.trim().strip_prefix("base")?.to_uppercase();
+            if suffix.len() != 2 {
+                return None;
+            }
+            Some((suffix, parse_hex_colour(value)?))
+        })
+        .collect()
+}
+
+/// Parse a scheme colour value, eg `"#ff8800"`, ignoring any quotes and trailing comments.
+fn parse_hex_colour(value: &str) -> Option<super::converter::PaletteColour> {
+    let trimmed = value.trim();
+    let unquoted = if let Some(quoted) = trimmed.strip_prefix(['"', '\'']) {
+        quoted.split(['"', '\'']).next()?
+    } else {
+        trimmed.split_whitespace().next()?
+    };
+    let hex = unquoted.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let component = |range: std::ops::Range<usize>| -> Option<u8> {
+        u8::from_str_radix(hex.get(range)?, 16).ok()
+    };
+    Some((component(0..2)?, component(2..4)?, component(4..6)?))
+}
+
+/// Convert a parsed scheme into a complete palette. Any of the 256 colours that aren't defined by
+/// the scheme are filled in with the standard xterm values.
+pub(crate) fn scheme_to_palette(scheme: &Scheme) -> Result<super::converter::PaletteHashMap> {
+    let is_base24 = BASE24_ANSI_MAPPING
+        .iter()
+        .any(|base| base.starts_with('1') && scheme.contains_key(*base));
+    let mapping = if is_base24 {
+        BASE24_ANSI_MAPPING
+    } else {
+        BASE16_ANSI_MAPPING
+    };
+
+    let get = |base: &str| -> Result<super::converter::PaletteColour> {
+        scheme
+            .get(base)
+            .copied()
+            .with_context(|| format!("Scheme doesn't define the colour `base{base}`"))
+    };
+
+    let mut map = super::converter::PaletteHashMap::new();
+    for (index, base) in mapping.iter().enumerate() {
+        map.insert(index.to_string(), get(base)?);
+    }
+    for index in 16..=u8::MAX {
+        map.insert(
+            index.to_string(),
+            super::converter::Palette::xterm_colour(index),
+        );
+    }
+    map.insert(
+        super::converter::FOREGROUND_KEY.to_owned(),
+        get(FOREGROUND_BASE)?,
+    );
+    map.insert(
+        super::converter::BACKGROUND_KEY.to_owned(),
+        get(BACKGROUND_BASE)?,
+    );
+
+    Ok(map)
+}
+
+/// Import a scheme file and save it as the palette config.
+#[expect(
+    clippy::print_stdout,
+    reason = "We're printing the imported palette for the user to see"
+)]
+pub(crate) async fn import(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    scheme_path: &std::path::Path,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(scheme_path)?;
+    let map = scheme_to_palette(&parse_scheme(&contents))?;
+    let palette = super::converter::Palette { map };
+
+    super::parser::Parser::print_generic_palette(|palette_index| -> Result<()> {
+        let colour = |index: u8| -> Result<super::converter::PaletteColour> {
+            palette
+                .map
+                .get(&index.to_string())
+                .copied()
+                .context("Palette colour not found")
+        };
+        super::parser::Parser::print_2_true_colours_in_1(
+            colour(palette_index)?,
+            colour(palette_index + super::parser::PALETTE_ROW_SIZE)?,
+        );
+        Ok(())
+    })?;
+
+    let path = super::parser::Parser::palette_config_path(state).await;
+    std::fs::write(&path, toml::to_string(&palette.map)?)?;
+    println!("Palette imported from {scheme_path:?} and saved to: {path:?}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BASE16_SCHEME: &str = r##"
+scheme: "Default Dark"
+author: "Chris Kempson (http://chriskempson.com)"
+base00: "181818" # Default Background
+base01: "282828"
+base02: "383838"
+base03: "585858"
+base04: "b8b8b8"
+base05: "d8d8d8"
+base06: "e8e8e8"
+base07: "f8f8f8"
+base08: "ab4642"
+base09: "dc9656"
+base0A: "f7ca88"
+base0B: "a1b56c"
+base0C: "86c1b9"
+base0D: "7cafc2"
+base0E: "ba8baa"
+base0F: "a16946"
+"##;
+
+    #[test]
+    fn importing_base16() {
+        let map = scheme_to_palette(&parse_scheme(BASE16_SCHEME)).unwrap();
+        assert_eq!(map.get("0"), Some(&(24, 24, 24)));
+        assert_eq!(map.get("1"), Some(&(171, 70, 66)));
+        assert_eq!(map.get("9"), Some(&(171, 70, 66)));
+        assert_eq!(map.get("11"), Some(&(247, 202, 136)));
+        assert_eq!(map.get("15"), Some(&(248, 248, 248)));
+        assert_eq!(map.get("196"), Some(&(255, 0, 0)));
+        assert_eq!(map.get("foreground"), Some(&(216, 216, 216)));
+        assert_eq!(map.get("background"), Some(&(24, 24, 24)));
+    }
+
+    #[test]
+    fn importing_nested_base24() {
+        let colours = (0..24u8)
+            .map(|index| format!("  base{index:02X}: '#{index:02x}{index:02x}{index:02x}'\n"))
+            .collect::<String>();
+        let scheme = format!("system: \"base24\"\npalette:\n{colours}");
+
+        let map = scheme_to_palette(&parse_scheme(&scheme)).unwrap();
+        assert_eq!(map.get("0"), Some(&(0, 0, 0)));
+        assert_eq!(map.get("1"), Some(&(8, 8, 8)));
+        assert_eq!(map.get("9"), Some(&(18, 18, 18)));
+        assert_eq!(map.get("14"), Some(&(21, 21, 21)));
+    }
+
+    #[test]
+    fn incomplete_schemes_fail() {
+        assert!(scheme_to_palette(&parse_scheme("base00: \"181818\"")).is_err());
+    }
+}
@@ -1,19 +1,50 @@
 //! Convert palette indexes to true colour values.
 
 use color_eyre::{eyre::ContextCompat as _, Result};
+use palette::color_difference::Wcag21RelativeContrast as _;
 
 /// This might be a big assumption, but I think the convention is that text uses this colour from
 /// the palette when no other index or true colour is specified.
 const DEFAULT_TEXT_PALETTE_INDEX: u8 = 15;
 
+/// The palette key for the terminal's default foreground colour, when it could be detected.
+pub const FOREGROUND_KEY: &str = "foreground";
+
+/// The palette key for the terminal's default background colour, when it could be detected.
+pub const BACKGROUND_KEY: &str = "background";
+
+/// The relative luminance at which black and white text have the same contrast. Any background
+/// lighter than this is better suited to dark text, and so is considered part of a light theme.
+const LIGHT_LUMINANCE_THRESHOLD: f32 = 0.179;
+
+/// The standard xterm values for the 16 ANSI colours.
+const XTERM_ANSI_COLOURS: [PaletteColour; 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
 /// A single palette colour.
-type PaletteColour = (u8, u8, u8);
+pub(crate) type PaletteColour = (u8, u8, u8);
 
 /// A hash of palette indexes to true colour values.
 pub type PaletteHashMap = std::collections::HashMap<String, PaletteColour>;
 
 /// Convenience type for the palette hash.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub(crate) struct Palette {
     /// The palette hash.
     pub map: PaletteHashMap,
@@ -27,29 +58,71 @@ impl Palette {
     }
 
     /// Convert a palette index to a Termwiz-compatible true colour.
+    ///
+    /// Palettes don't always define all 256 colours, for example hand-written palettes often only
+    /// have the 16 ANSI colours. In which case the rest of the 256 colours, namely the 6x6x6 colour
+    /// cube and the greyscale ramp, are given their standard xterm values. Most terminals don't
+    /// change these anyway.
     pub fn true_colour_tuple_from_index(&self, index: u8) -> termwiz::color::SrgbaTuple {
-        #[expect(
-            clippy::expect_used,
-            reason = "Unreachable because a palette should only have 256 colours"
-        )]
         let true_colour = self
             .map
             .get(&index.to_string())
-            .expect("Palette contains less than 256 colours");
+            .copied()
+            .unwrap_or_else(|| Self::xterm_colour(index));
         termwiz::color::RgbColor::new_8bpc(true_colour.0, true_colour.1, true_colour.2).into()
     }
 
-    /// Terminal emulator convention is that the default background colour is the first colour in
-    /// the terminal's palette.
+    /// The standard xterm value for a palette index. Used for any colours that couldn't be
+    /// detected from the user's terminal.
+    pub fn xterm_colour(index: u8) -> PaletteColour {
+        if let Some(colour) = XTERM_ANSI_COLOURS.get(usize::from(index)) {
+            return *colour;
+        }
+
+        if index >= 232 {
+            let grey = 8 + (index - 232) * 10;
+            return (grey, grey, grey);
+        }
+
+        let cube_index = index - 16;
+        let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+        (
+            level(cube_index.div_euclid(36)),
+            level(cube_index.div_euclid(6).rem_euclid(6)),
+            level(cube_index.rem_euclid(6)),
+        )
+    }
+
+    /// Convert a detected default colour to a true colour tuple.
+    fn detected_colour(&self, key: &str) -> Option<termwiz::color::SrgbaTuple> {
+        let colour = self.map.get(key)?;
+        Some(termwiz::color::RgbColor::new_8bpc(colour.0, colour.1, colour.2).into())
+    }
+
+    /// The default background colour, as reported by the terminal. Otherwise terminal emulator
+    /// convention is that the default background colour is the first colour in the terminal's
+    /// palette.
     pub fn default_background_colour(&self) -> termwiz::color::SrgbaTuple {
-        self.true_colour_tuple_from_index(0)
+        self.detected_colour(BACKGROUND_KEY)
+            .unwrap_or_else(|| self.true_colour_tuple_from_index(0))
     }
 
-    /// This perhaps naively assumes that the default foreground colour is always found at palette
-    /// index 15. This could well be a bad idea, in which case we should add the default foreground
-    /// (and background) colour to the palatte swatch for parsing.
+    /// The default foreground colour, as reported by the terminal. Otherwise this perhaps naively
+    /// assumes that the default foreground colour is always found at palette index 15.
     pub fn default_foreground_colour(&self) -> termwiz::color::SrgbaTuple {
-        self.true_colour_tuple_from_index(15)
+        self.detected_colour(FOREGROUND_KEY)
+            .unwrap_or_else(|| self.true_colour_tuple_from_index(15))
+    }
+
+    /// Whether a colour is light enough that dark text on it is more readable than light text.
+    pub fn is_light_colour(colour: termwiz::color::SrgbaTuple) -> bool {
+        let rgb = palette::rgb::Rgb::new(colour.0, colour.1, colour.2);
+        rgb.relative_luminance().luma > LIGHT_LUMINANCE_THRESHOLD
+    }
+
+    /// Whether the user's terminal has a light theme.
+    pub fn is_light_background(&self) -> bool {
+        Self::is_light_colour(self.default_background_colour())
     }
 
     /// Print all the true colour versions of the terminal's palette as found in the screenshot.
@@ -117,6 +190,27 @@ impl Palette {
         attributes.set_background(colour_attribute);
     }
 
+    /// Update cells that have already been converted to true colour, for when the palette itself
+    /// has changed. Converted cells keep their original palette index as a fallback, so we know
+    /// which palette colour they should now be.
+    pub fn recolour_surface(&self, surface: &mut termwiz::surface::Surface) {
+        for line in surface.screen_cells() {
+            for cell in line {
+                let attributes = cell.attrs_mut();
+                if let termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(_, index) =
+                    attributes.foreground()
+                {
+                    attributes.set_foreground(self.true_colour_attribute_from_index(index));
+                }
+                if let termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(_, index) =
+                    attributes.background()
+                {
+                    attributes.set_background(self.true_colour_attribute_from_index(index));
+                }
+            }
+        }
+    }
+
     /// Convert TTY cell palette indexes into their true colour values.
     pub fn convert_cells_to_true_colour(&self, output: &mut shadow_terminal::output::Output) {
         match output {
@@ -161,3 +255,68 @@ impl Palette {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn xterm_colours() {
+        assert_eq!(Palette::xterm_colour(1), (205, 0, 0));
+        assert_eq!(Palette::xterm_colour(16), (0, 0, 0));
+        assert_eq!(Palette::xterm_colour(196), (255, 0, 0));
+        assert_eq!(Palette::xterm_colour(39), (0, 175, 255));
+        assert_eq!(Palette::xterm_colour(232), (8, 8, 8));
+        assert_eq!(Palette::xterm_colour(255), (238, 238, 238));
+    }
+
+    #[test]
+    fn converting_colours_missing_from_the_palette() {
+        let palette = Palette {
+            map: PaletteHashMap::from([
+                ("1".to_owned(), (200, 10, 10)),
+                ("196".to_owned(), (250, 1, 2)),
+            ]),
+        };
+        let true_colour = |index: u8| palette.true_colour_tuple_from_index(index).to_srgb_u8();
+
+        assert_eq!(true_colour(1), (200, 10, 10, 255));
+        assert_eq!(true_colour(196), (250, 1, 2, 255));
+        assert_eq!(true_colour(2), (0, 205, 0, 255));
+        assert_eq!(true_colour(21), (0, 0, 255, 255));
+        assert_eq!(true_colour(244), (128, 128, 128, 255));
+
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_foreground(termwiz::color::ColorAttribute::PaletteIndex(21));
+        attributes.set_background(termwiz::color::ColorAttribute::PaletteIndex(244));
+        palette.cell_attributes_to_true_colour(&mut attributes);
+        assert!(matches!(
+            attributes.foreground(),
+            termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(_, 21)
+        ));
+        assert!(matches!(
+            attributes.background(),
+            termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(_, 244)
+        ));
+    }
+
+    #[test]
+    fn light_and_dark_backgrounds() {
+        let colour = |red: u8, green: u8, blue: u8| -> termwiz::color::SrgbaTuple {
+            termwiz::color::RgbColor::new_8bpc(red, green, blue).into()
+        };
+        assert!(Palette::is_light_colour(colour(255, 255, 255)));
+        assert!(Palette::is_light_colour(colour(253, 246, 227)));
+        assert!(!Palette::is_light_colour(colour(0, 0, 0)));
+        assert!(!Palette::is_light_colour(colour(0, 43, 54)));
+
+        let mut palette = Palette {
+            map: PaletteHashMap::from([("0".to_owned(), (14, 13, 21))]),
+        };
+        assert!(!palette.is_light_background());
+        palette
+            .map
+            .insert(BACKGROUND_KEY.to_owned(), (250, 250, 250));
+        assert!(palette.is_light_background());
+    }
+}
@@ -0,0 +1,230 @@
+//! Export the terminal's true colour palette as theme snippets for other terminal emulators. This
+//! means that Tattoy's palette detection can also be used as a general purpose way of extracting
+//! themes.
+
+use color_eyre::eyre::Result;
+
+/// The conventional names of the 8 ANSI colours, as used by most terminal config formats.
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// The number of normal, non-bright, ANSI colours.
+const ANSI_COUNT: u8 = 8;
+
+/// The terminal config formats that the palette can be exported to.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// A `[colors]` section for `alacritty.toml`.
+    Alacritty,
+    /// Colour settings for `kitty.conf`.
+    Kitty,
+    /// A WezTerm colour scheme file, for the `colors/` directory of WezTerm's config.
+    Wezterm,
+    /// A Ghostty theme file.
+    Ghostty,
+}
+
+/// The colours that get exported, in hex, eg `#ff8800`.
+struct Colours {
+    /// The default foreground colour.
+    foreground: String,
+    /// The default background colour.
+    background: String,
+    /// The 16 ANSI colours.
+    ansi: Vec<String>,
+}
+
+impl Colours {
+    /// Extract the exportable colours from the palette.
+    fn new(palette: &super::converter::Palette) -> Self {
+        Self {
+            foreground: Self::hex(palette.default_foreground_colour()),
+            background: Self::hex(palette.default_background_colour()),
+            ansi: (0..ANSI_COUNT * 2)
+                .map(|index| Self::hex(palette.true_colour_tuple_from_index(index)))
+                .collect(),
+        }
+    }
+
+    /// Format a colour as hex.
+    fn hex(colour: termwiz::color::SrgbaTuple) -> String {
+        let (red, green, blue, _) = colour.to_srgb_u8();
+        format!("#{red:02x}{green:02x}{blue:02x}")
+    }
+
+    /// The normal ANSI colours, along with their names.
+    fn normal(&self) -> impl Iterator<Item = (&str, &String)> {
+        ANSI_NAMES.into_iter().zip(self.ansi.iter())
+    }
+
+    /// The bright ANSI colours, along with their names.
+    fn bright(&self) -> impl Iterator<Item = (&str, &String)> {
+        ANSI_NAMES
+            .into_iter()
+            .zip(self.ansi.iter().skip(ANSI_COUNT.into()))
+    }
+
+    /// A TOML array of colours.
+    fn toml_array<'colour, I: Iterator<Item = &'colour String>>(colours: I) -> String {
+        let quoted = colours
+            .map(|colour| format!("\"{colour}\""))
+            .collect::<Vec<String>>();
+        format!("[{}]", quoted.join(", "))
+    }
+}
+
+/// Render the palette in the given terminal's config format.
+pub(crate) fn render(palette: &super::converter::Palette, format: Format) -> String {
+    let colours = Colours::new(palette);
+    let lines: Vec<String> = match format {
+        Format::Alacritty => [
+            "[colors.primary]".to_owned(),
+            format!("foreground = \"{}\"", colours.foreground),
+            format!("background = \"{}\"", colours.background),
+            String::new(),
+            "[colors.normal]".to_owned(),
+        ]
+        .into_iter()
+        .chain(
+            colours
+                .normal()
+                .map(|(name, colour)| format!("{name} = \"{colour}\"")),
+        )
+        .chain([String::new(), "[colors.bright]".to_owned()])
+        .chain(
+            colours
+                .bright()
+                .map(|(name, colour)| format!("{name} = \"{colour}\"")),
+        )
+        .collect(),
+        Format::Kitty => [
+            format!("foreground {}", colours.foreground),
+            format!("background {}", colours.background),
+        ]
+        .into_iter()
+        .chain(
+            colours
+                .ansi
+                .iter()
+                .enumerate()
+                .map(|(index, colour)| format!("color{index} {colour}")),
+        )
+        .collect(),
+        Format::Wezterm => vec![
+            "[colors]".to_owned(),
+            format!("foreground = \"{}\"", colours.foreground),
+            format!("background = \"{}\"", colours.background),
+            format!(
+                "ansi = {}",
+                Colours::toml_array(colours.normal().map(|(_, colour)| colour))
+            ),
+            format!(
+                "brights = {}",
+                Colours::toml_array(colours.bright().map(|(_, colour)| colour))
+            ),
+            String::new(),
+            "[metadata]".to_owned(),
+            "name = \"Tattoy\"".to_owned(),
+        ],
+        Format::Ghostty => [
+            format!("foreground = {}", colours.foreground),
+            format!("background = {}", colours.background),
+        ]
+        .into_iter()
+        .chain(
+            colours
+                .ansi
+                .iter()
+                .enumerate()
+                .map(|(index, colour)| format!("palette = {index}={colour}")),
+        )
+        .collect(),
+    };
+
+    format!("{}\n", lines.join("\n"))
+}
+
+/// Export the current palette, either to STDOUT or to a file.
+#[expect(
+    clippy::print_stdout,
+    reason = "Printing to STDOUT means the theme can be piped wherever the user wants"
+)]
+pub(crate) async fn export(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    format: Format,
+    maybe_output: Option<&std::path::Path>,
+) -> Result<()> {
+    let palette = crate::config::main::Config::load_palette(std::sync::Arc::clone(state)).await?;
+    let theme = render(&palette, format);
+
+    match maybe_output {
+        Some(path) => {
+            std::fs::write(path, theme)?;
+            tracing::info!("Palette exported as {format:?} theme to: {path:?}");
+        }
+        None => print!("{theme}"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn palette() -> super::super::converter::Palette {
+        let mut map = (0..=u8::MAX)
+            .map(|index| {
+                (
+                    index.to_string(),
+                    super::super::converter::Palette::xterm_colour(index),
+                )
+            })
+            .collect::<super::super::converter::PaletteHashMap>();
+        map.insert(
+            super::super::converter::FOREGROUND_KEY.to_owned(),
+            (1, 2, 3),
+        );
+        map.insert(
+            super::super::converter::BACKGROUND_KEY.to_owned(),
+            (250, 250, 250),
+        );
+        super::super::converter::Palette { map }
+    }
+
+    #[test]
+    fn exporting_alacritty() {
+        let theme = render(&palette(), Format::Alacritty);
+        assert!(theme.starts_with("[colors.primary]\nforeground = \"#010203\"\n"));
+        assert!(theme.contains("background = \"#fafafa\""));
+        assert!(theme.contains("[colors.normal]\nblack = \"#000000\"\nred = \"#cd0000\""));
+        assert!(theme.contains("[colors.bright]\nblack = \"#7f7f7f\"\nred = \"#ff0000\""));
+    }
+
+    #[test]
+    fn exporting_kitty_and_ghostty() {
+        let kitty = render(&palette(), Format::Kitty);
+        assert!(kitty.contains("background #fafafa\ncolor0 #000000\n"));
+        assert!(kitty.ends_with("color15 #ffffff\n"));
+
+        let ghostty = render(&palette(), Format::Ghostty);
+        assert!(ghostty.contains("foreground = #010203\n"));
+        assert!(ghostty.ends_with("palette = 15=#ffffff\n"));
+    }
+
+    #[test]
+    fn exporting_wezterm() {
+        let theme = render(&palette(), Format::Wezterm);
+        let parsed = theme.parse::<toml::Table>().unwrap();
+        let colors = parsed.get("colors").unwrap();
+        assert_eq!(
+            colors.get("ansi").unwrap().as_array().unwrap().len(),
+            usize::from(ANSI_COUNT)
+        );
+        assert_eq!(
+            colors.get("brights").unwrap().get(1).unwrap().as_str(),
+            Some("#ff0000")
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Query the host terminal for its palette using OSC sequences.
+//!
+//! Most modern terminals reply to `OSC 4 ; <index> ; ?` with the true colour value of that palette
+//! index, and likewise to `OSC 10 ; ?` and `OSC 11 ; ?` with the default foreground and background
+//! colours. This is much more convenient than the screenshot parser, which is only used as a
+//! fallback for terminals that don't reply.
+//!
+//! The queries are followed by a "Primary Device Attributes" query, which every terminal replies
+//! to. Because terminals reply in order, once we see that reply we know that there are no more
+//! palette replies to come.
+
+use std::io::Write as _;
+
+use color_eyre::eyre::Result;
+use termwiz::terminal::Terminal as _;
+
+/// How long to wait for the terminal to start replying. Terminals that support palette queries
+/// reply almost instantly, so there's no need to wait long.
+const FIRST_REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How long to wait for further replies once the terminal has started replying.
+const NEXT_REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// The number of colours in a terminal palette.
+const PALETTE_SIZE: u16 = 256;
+
+/// The "Primary Device Attributes" query. Its reply marks the end of the palette replies.
+pub(crate) const DEVICE_ATTRIBUTES_QUERY: &str = "\x1b[c";
+
+/// The start of the reply to the "Primary Device Attributes" query, eg: `ESC [ ? 6 2 ; 2 2 c`.
+pub(crate) const DEVICE_ATTRIBUTES_REPLY_PREFIX: &[u8] = b"\x1b[?";
+
+/// The start of an OSC sequence.
+pub(crate) const OSC_START: &[u8] = b"\x1b]";
+
+/// A parsed reply to one of our colour queries.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Reply {
+    /// The colour of a palette index, from `OSC 4`.
+    PaletteColour(u8, super::converter::PaletteColour),
+    /// The default foreground colour, from `OSC 10`.
+    Foreground(super::converter::PaletteColour),
+    /// The default background colour, from `OSC 11`.
+    Background(super::converter::PaletteColour),
+}
+
+/// All the queries needed to get the complete palette and the default colours.
+pub(crate) fn palette_queries() -> String {
+    let palette_queries = (0..PALETTE_SIZE)
+        .map(|index| format!("\x1b]4;{index};?\x1b\\"))
+        .collect::<String>();
+    palette_queries + &background_queries() + DEVICE_ATTRIBUTES_QUERY
+}
+
+/// Just the queries for the default foreground and background colours.
+pub(crate) fn background_queries() -> String {
+    "\x1b]10;?\x1b\\\x1b]11;?\x1b\\".to_owned()
+}
+
+/// Parse the payload of an OSC reply, eg: `4;1;rgb:cdcd/0000/0000`. The leading `ESC ]` and the
+/// terminator are optional.
+pub(crate) fn parse_reply(raw: &[u8]) -> Option<Reply> {
+    let text = String::from_utf8_lossy(raw);
+    let payload = text
+        .trim_start_matches("\x1b]")
+        .trim_end_matches('\x07')
+        .trim_end_matches("\x1b\\");
+    let mut parts = payload.split(';');
+
+    match parts.next()? {
+        "4" => {
+            let index = parts.next()?.parse::<u8>().ok()?;
+            Some(Reply::PaletteColour(index, parse_colour(parts.next()?)?))
+        }
+        "10" => Some(Reply::Foreground(parse_colour(parts.next()?)?)),
+        "11" => Some(Reply::Background(parse_colour(parts.next()?)?)),
+        _ => None,
+    }
+}
+
+/// Parse an X11 colour specification, eg: `rgb:ffff/8080/0000`. Each component can have between 1
+/// and 4 hex digits.
+fn parse_colour(spec: &str) -> Option<super::converter::PaletteColour> {
+    let components = spec.strip_prefix("rgb:")?.split('/').collect::<Vec<&str>>();
+    let [red, green, blue] = components.as_slice() else {
+        return None;
+    };
+    Some((
+        parse_component(red)?,
+        parse_component(green)?,
+        parse_component(blue)?,
+    ))
+}
+
+/// Scale a hex colour component of any width to 8 bits.
+fn parse_component(hex: &str) -> Option<u8> {
+    if hex.is_empty() || hex.len() > 4 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let bits = u32::try_from(hex.len()).ok()? * 4;
+    let max = (1u32 << bits) - 1;
+    u8::try_from((value * 255 + max.div_euclid(2)).div_euclid(max)).ok()
+}
+
+/// Extract all the OSC replies from raw terminal input. Each reply is terminated by either `BEL` or
+/// `ESC \`.
+pub(crate) fn extract_replies(bytes: &[u8]) -> Vec<Reply> {
+    let mut replies = Vec::new();
+    let mut remaining = bytes;
+    while let Some(start) = find(remaining, OSC_START) {
+        let Some(sequence) = remaining.get(start + OSC_START.len()..) else {
+            break;
+        };
+        let Some(end) = sequence
+            .iter()
+            .position(|byte| *byte == b'\x07' || *byte == b'\x1b')
+        else {
+            break;
+        };
+        if let Some(reply) = sequence.get(..end).and_then(parse_reply) {
+            replies.push(reply);
+        }
+        remaining = sequence.get(end + 1..).unwrap_or_default();
+    }
+    replies
+}
+
+/// Whether the raw input contains the reply to the "Primary Device Attributes" query.
+fn has_device_attributes_reply(bytes: &[u8]) -> bool {
+    let Some(start) = find(bytes, DEVICE_ATTRIBUTES_REPLY_PREFIX) else {
+        return false;
+    };
+    bytes
+        .get(start + DEVICE_ATTRIBUTES_REPLY_PREFIX.len()..)
+        .is_some_and(|rest| rest.contains(&b'c'))
+}
+
+/// Find the position of a byte sequence.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Read the terminal's replies from STDIN. Stops as soon as the terminal's reply to the device
+/// attributes query arrives, or if the terminal stops replying.
+#[cfg(unix)]
+pub(crate) fn read_replies() -> Result<Vec<u8>> {
+    use std::io::Read as _;
+    use std::os::fd::AsRawFd as _;
+
+    let stdin = std::io::stdin();
+    let mut poll_fds = [filedescriptor::pollfd {
+        fd: stdin.as_raw_fd(),
+        events: filedescriptor::POLLIN,
+        revents: 0,
+    }];
+
+    let mut bytes = Vec::new();
+    let mut timeout = FIRST_REPLY_TIMEOUT;
+    while !has_device_attributes_reply(&bytes) {
+        let ready = filedescriptor::poll(&mut poll_fds, Some(timeout))?;
+        if ready == 0 {
+            tracing::debug!("Timed out waiting for the terminal to reply to queries");
+            break;
+        }
+
+        let mut buffer = [0; 1024];
+        let size = stdin.lock().read(&mut buffer)?;
+        if size == 0 {
+            break;
+        }
+        bytes.extend_from_slice(buffer.get(..size).unwrap_or_default());
+        timeout = NEXT_REPLY_TIMEOUT;
+    }
+
+    Ok(bytes)
+}
+
+/// Non-blocking reads of STDIN aren't currently supported on Windows, so we can't safely query
+/// the terminal.
+#[cfg(not(unix))]
+pub(crate) fn read_replies() -> Result<Vec<u8>> {
+    Ok(Vec::new())
+}
+
+/// Add a reply to the palette that is being built.
+pub(crate) fn apply_reply(map: &mut super::converter::PaletteHashMap, reply: Reply) {
+    match reply {
+        Reply::PaletteColour(index, colour) => {
+            map.insert(index.to_string(), colour);
+        }
+        Reply::Foreground(colour) => {
+            map.insert(super::converter::FOREGROUND_KEY.to_owned(), colour);
+        }
+        Reply::Background(colour) => {
+            map.insert(super::converter::BACKGROUND_KEY.to_owned(), colour);
+        }
+    }
+}
+
+/// Query the terminal for its palette. Returns `None` if the terminal didn't reply with at least
+/// the 16 ANSI colours. Any of the 256 colours that the terminal didn't reply with are filled in
+/// with the standard xterm values.
+fn query_palette(
+    state: &crate::shared_state::SharedState,
+) -> Result<Option<super::converter::PaletteHashMap>> {
+    let mut terminal = crate::renderer::Renderer::get_termwiz_terminal()?;
+    terminal.set_raw_mode()?;
+
+    let mut stdout = std::io::stdout();
+    let queries = crate::multiplexer::wrap_query(state, &palette_queries());
+    stdout.write_all(queries.as_bytes())?;
+    stdout.flush()?;
+
+    let bytes = read_replies();
+    terminal.set_cooked_mode()?;
+
+    let mut map = super::converter::PaletteHashMap::new();
+    for reply in extract_replies(&bytes?) {
+        apply_reply(&mut map, reply);
+    }
+
+    let is_ansi_complete = (0..16u8).all(|index| map.contains_key(&index.to_string()));
+    if !is_ansi_complete {
+        tracing::debug!("Terminal didn't reply to palette queries, got: {map:?}");
+        return Ok(None);
+    }
+
+    for index in 0..=u8::MAX {
+        map.entry(index.to_string())
+            .or_insert_with(|| super::converter::Palette::xterm_colour(index));
+    }
+
+    Ok(Some(map))
+}
+
+/// Try to detect the terminal's palette by querying it, and save it if successful. Returns
+/// whether the palette was detected.
+pub(crate) async fn detect_and_save(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+) -> Result<bool> {
+    tracing::info!("Querying the terminal for its palette");
+    let Some(map) = query_palette(state)? else {
+        return Ok(false);
+    };
+
+    let path = super::parser::Parser::palette_config_path(state).await;
+    std::fs::write(&path, toml::to_string(&map)?)?;
+    tracing::info!("Palette detected from terminal and saved to: {path:?}");
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_palette_replies() {
+        assert_eq!(
+            parse_reply(b"4;1;rgb:cdcd/0000/0000"),
+            Some(Reply::PaletteColour(1, (205, 0, 0)))
+        );
+        assert_eq!(
+            parse_reply(b"\x1b]4;255;rgb:ee/ee/ee\x07"),
+            Some(Reply::PaletteColour(255, (238, 238, 238)))
+        );
+    }
+
+    #[test]
+    fn parsing_default_colour_replies() {
+        assert_eq!(
+            parse_reply(b"11;rgb:1a1a/1b1b/2626\x1b\\"),
+            Some(Reply::Background((26, 27, 38)))
+        );
+        assert_eq!(
+            parse_reply(b"10;rgb:f/f/f"),
+            Some(Reply::Foreground((255, 255, 255)))
+        );
+    }
+
+    #[test]
+    fn extracting_replies_from_raw_input() {
+        let input = b"\x1b]4;0;rgb:0000/0000/0000\x1b\\\x1b]4;1;rgb:ff/00/00\x07\
+            \x1b]11;rgb:ffff/ffff/ffff\x1b\\\x1b[?62;22c";
+        assert_eq!(
+            extract_replies(input),
+            vec![
+                Reply::PaletteColour(0, (0, 0, 0)),
+                Reply::PaletteColour(1, (255, 0, 0)),
+                Reply::Background((255, 255, 255)),
+            ]
+        );
+        assert!(has_device_attributes_reply(input));
+        assert!(!has_device_attributes_reply(b"\x1b]4;0;rgb:00/00/00\x07"));
+    }
+
+    #[test]
+    fn ignoring_unknown_replies() {
+        assert_eq!(parse_reply(b"52;c;aGVsbG8="), None);
+        assert_eq!(parse_reply(b"4;1;?"), None);
+        assert_eq!(parse_reply(b"4;1;rgb:ff/ff"), None);
+    }
+}
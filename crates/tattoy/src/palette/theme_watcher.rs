@@ -0,0 +1,239 @@
+//! Keep the palette up to date when the user's terminal changes its theme whilst Tattoy is
+//! running, for example when the OS switches between light and dark mode.
+//!
+//! Terminals that support "colour palette update notifications" (DEC private mode 2031) send
+//! `CSI ? 997 ; 1 n` (dark) or `CSI ? 997 ; 2 n` (light) whenever their theme changes. When we see
+//! one of these we re-query the terminal for its palette. The replies arrive on STDIN mixed in with
+//! the user's normal input, so they're filtered out here before the input gets parsed.
+
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+
+/// Ask the terminal to notify us whenever its theme changes.
+pub(crate) const ENABLE_THEME_NOTIFICATIONS: &str = "\x1b[?2031h";
+
+/// The start of a theme change notification.
+const THEME_NOTIFICATION_PREFIX: &[u8] = b"\x1b[?997;";
+
+/// The escape byte that starts all the sequences we're interested in.
+const ESCAPE: u8 = 0x1b;
+
+/// The "bell" byte, which can terminate OSC sequences.
+const BELL: u8 = 0x07;
+
+/// The maximum number of bytes of an incomplete sequence to wait for the rest of. Anything longer
+/// can't be one of our replies.
+const MAX_PENDING_BYTES: usize = 256;
+
+/// Things that the watcher noticed in the user's input.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// The terminal's theme has changed, so the palette should be queried.
+    ThemeChanged,
+    /// The terminal has finished replying to our palette queries.
+    Detected(super::converter::PaletteHashMap),
+}
+
+/// How a sequence in the input should be handled.
+enum Sequence {
+    /// The sequence was one of ours and has been removed from the input.
+    Consumed(usize, Option<Event>),
+    /// The sequence might be one of ours but hasn't completely arrived yet.
+    Incomplete,
+    /// The sequence isn't one of ours, so should be passed through to the PTY.
+    Other,
+}
+
+/// Watches the user's input for theme changes and palette replies.
+#[derive(Default)]
+pub(crate) struct ThemeWatcher {
+    /// Are we waiting for the terminal to reply to our palette queries?
+    is_querying: bool,
+    /// The palette replies received so far.
+    replies: super::converter::PaletteHashMap,
+    /// Bytes of a sequence that hasn't completely arrived yet.
+    pending: Vec<u8>,
+}
+
+impl ThemeWatcher {
+    /// Remove any theme notifications and palette replies from the input. Returns the remaining
+    /// input along with anything that was noticed.
+    pub fn filter(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<Event>) {
+        let mut input = core::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let mut passthrough = Vec::new();
+        let mut events = Vec::new();
+        let mut remaining = input.as_slice();
+        while let Some((first, rest)) = remaining.split_first() {
+            if *first != ESCAPE {
+                passthrough.push(*first);
+                remaining = rest;
+                continue;
+            }
+
+            match self.match_sequence(remaining) {
+                Sequence::Consumed(length, maybe_event) => {
+                    events.extend(maybe_event);
+                    remaining = remaining.get(length..).unwrap_or_default();
+                }
+                Sequence::Incomplete => {
+                    self.pending = remaining.to_vec();
+                    break;
+                }
+                Sequence::Other => {
+                    passthrough.push(*first);
+                    remaining = rest;
+                }
+            }
+        }
+
+        (passthrough, events)
+    }
+
+    /// Figure out what kind of sequence starts at the beginning of the bytes.
+    fn match_sequence(&mut self, bytes: &[u8]) -> Sequence {
+        let sequence = if self.is_querying && bytes.starts_with(super::osc::OSC_START) {
+            self.match_osc_reply(bytes)
+        } else if bytes.starts_with(super::osc::DEVICE_ATTRIBUTES_REPLY_PREFIX) {
+            self.match_private_csi(bytes)
+        } else if self.is_querying
+            && (super::osc::OSC_START.starts_with(bytes)
+                || super::osc::DEVICE_ATTRIBUTES_REPLY_PREFIX.starts_with(bytes))
+        {
+            Sequence::Incomplete
+        } else {
+            Sequence::Other
+        };
+
+        if matches!(sequence, Sequence::Incomplete) && bytes.len() > MAX_PENDING_BYTES {
+            return Sequence::Other;
+        }
+        sequence
+    }
+
+    /// Match replies to our OSC palette queries.
+    fn match_osc_reply(&mut self, bytes: &[u8]) -> Sequence {
+        let start = super::osc::OSC_START.len();
+        let Some(end) = bytes
+            .iter()
+            .skip(start)
+            .position(|byte| *byte == BELL || *byte == ESCAPE)
+            .map(|position| position + start)
+        else {
+            return Sequence::Incomplete;
+        };
+
+        let length = match bytes.get(end) {
+            Some(&BELL) => end + 1,
+            _ => match bytes.get(end + 1) {
+                Some(b'\\') => end + 2,
+                Some(_) => return Sequence::Other,
+                None => return Sequence::Incomplete,
+            },
+        };
+
+        let Some(reply) = bytes.get(..end).and_then(super::osc::parse_reply) else {
+            return Sequence::Other;
+        };
+        super::osc::apply_reply(&mut self.replies, reply);
+        Sequence::Consumed(length, None)
+    }
+
+    /// Match theme change notifications and the end of the terminal's replies to our queries.
+    fn match_private_csi(&mut self, bytes: &[u8]) -> Sequence {
+        let start = super::osc::DEVICE_ATTRIBUTES_REPLY_PREFIX.len();
+        let Some(final_byte_position) = bytes
+            .iter()
+            .skip(start)
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map(|position| position + start)
+        else {
+            return Sequence::Incomplete;
+        };
+        let length = final_byte_position + 1;
+
+        match bytes.get(final_byte_position) {
+            Some(b'n') if bytes.starts_with(THEME_NOTIFICATION_PREFIX) => {
+                tracing::debug!("Terminal theme changed");
+                self.is_querying = true;
+                self.replies.clear();
+                Sequence::Consumed(length, Some(Event::ThemeChanged))
+            }
+            Some(b'c') if self.is_querying => {
+                self.is_querying = false;
+                let replies = core::mem::take(&mut self.replies);
+                let maybe_event = (!replies.is_empty()).then_some(Event::Detected(replies));
+                Sequence::Consumed(length, maybe_event)
+            }
+            _ => Sequence::Other,
+        }
+    }
+}
+
+/// Update the saved palette with the newly detected colours and start using it.
+pub(crate) async fn apply_detected_colours(
+    state: &Arc<crate::shared_state::SharedState>,
+    detected: super::converter::PaletteHashMap,
+) -> Result<()> {
+    let path = super::parser::Parser::palette_config_path(state).await;
+    let data = tokio::fs::read_to_string(&path).await?;
+    let mut map = toml::from_str::<super::converter::PaletteHashMap>(&data)?;
+    let previous = map.clone();
+    map.extend(detected);
+    if map == previous {
+        tracing::debug!("Terminal theme changed but the palette is the same");
+        return Ok(());
+    }
+
+    tokio::fs::write(&path, toml::to_string(&map)?).await?;
+    tracing::info!("Terminal theme changed, saved new palette to: {path:?}");
+
+    let palette = crate::config::main::Config::load_palette(Arc::clone(state)).await?;
+    crate::config::main::Config::broadcast_palette(state, palette).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normal_input_passes_through() {
+        let mut watcher = ThemeWatcher::default();
+        let input = b"ls -al\x1b[A\x1b";
+        assert_eq!(watcher.filter(input), (input.to_vec(), vec![]));
+    }
+
+    #[test]
+    fn detecting_a_theme_change() {
+        let mut watcher = ThemeWatcher::default();
+        let (passthrough, events) = watcher.filter(b"a\x1b[?997;2nb");
+        assert_eq!(passthrough, b"ab");
+        assert_eq!(events, vec![Event::ThemeChanged]);
+
+        let (first_passthrough, first_events) =
+            watcher.filter(b"x\x1b]11;rgb:ffff/ffff/ffff\x1b\\\x1b]4;1;rgb:ff");
+        assert_eq!(first_passthrough, b"x");
+        assert!(first_events.is_empty());
+
+        let (second_passthrough, second_events) = watcher.filter(b"/00/00\x07\x1b[?62;22cy");
+        assert_eq!(second_passthrough, b"y");
+        assert_eq!(
+            second_events,
+            vec![Event::Detected(super::super::converter::PaletteHashMap::from(
+                [
+                    ("1".to_owned(), (255, 0, 0)),
+                    ("background".to_owned(), (255, 255, 255)),
+                ]
+            ))]
+        );
+    }
+
+    #[test]
+    fn replies_are_only_filtered_when_querying() {
+        let mut watcher = ThemeWatcher::default();
+        let input = b"\x1b]11;rgb:ffff/ffff/ffff\x07\x1b[?62;22c";
+        assert_eq!(watcher.filter(input), (input.to_vec(), vec![]));
+    }
+}
@@ -0,0 +1,297 @@
+//! Tattoy's own performance statistics. The renderer records how long frames take and how often
+//! each tattoy sends updates, so that performance regressions can be quantified. They can be seen
+//! with `tattoy ctl stats` or with the performance HUD tattoy, and exported with the metrics.
+
+/// The number of the most recent frames whose timings are kept.
+const SAMPLE_COUNT: usize = 120;
+
+/// The period over which rates, like frames per second, are measured.
+const RATE_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The upper bounds, in milliseconds, of the buckets of the frame time histograms. There's also a
+/// final bucket for everything longer than the last bound.
+pub(crate) const HISTOGRAM_BOUNDS_MS: [f64; 9] =
+    [1.0, 2.0, 4.0, 8.0, 16.0, 33.0, 50.0, 100.0, 250.0];
+
+/// The surface ID that PTY updates are recorded under.
+pub(crate) const PTY_ID: &str = "pty";
+
+/// The most recent durations of something.
+#[derive(Debug, Default)]
+struct Samples(std::collections::VecDeque<std::time::Duration>);
+
+impl Samples {
+    /// Add a new duration, forgetting the oldest one if there are too many.
+    fn add(&mut self, duration: std::time::Duration) {
+        if self.0.len() >= SAMPLE_COUNT {
+            self.0.pop_front();
+        }
+        self.0.push_back(duration);
+    }
+
+    /// The average and longest durations, in milliseconds.
+    fn timing(&self) -> Timing {
+        let Some(max) = self.0.iter().max() else {
+            return Timing::default();
+        };
+        let total: std::time::Duration = self.0.iter().sum();
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "There are never enough samples to lose precision"
+        )]
+        let count = self.0.len() as f32;
+        Timing {
+            average_ms: total.as_secs_f32() * crate::renderer::MILLIS_PER_SECOND / count,
+            max_ms: max.as_secs_f32() * crate::renderer::MILLIS_PER_SECOND,
+        }
+    }
+}
+
+/// Counts of every duration since Tattoy started, grouped by how long they were.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, PartialEq)]
+pub(crate) struct Histogram {
+    /// The number of durations in each bucket, see `HISTOGRAM_BOUNDS_MS`. They aren't cumulative.
+    pub buckets: [u64; HISTOGRAM_BOUNDS_MS.len() + 1],
+    /// The total number of durations.
+    pub count: u64,
+    /// The sum of all the durations, in milliseconds.
+    pub sum_ms: f64,
+}
+
+impl Histogram {
+    /// Count a new duration.
+    fn add(&mut self, duration: std::time::Duration) {
+        let millis = duration.as_secs_f64() * f64::from(crate::renderer::MILLIS_PER_SECOND);
+        let index = HISTOGRAM_BOUNDS_MS
+            .iter()
+            .position(|bound| millis <= *bound)
+            .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+        if let Some(bucket) = self.buckets.get_mut(index) {
+            *bucket = bucket.saturating_add(1);
+        }
+        self.count = self.count.saturating_add(1);
+        self.sum_ms += millis;
+    }
+}
+
+/// The times at which something recently happened, for measuring how often it happens.
+#[derive(Debug, Default)]
+struct Rate(std::collections::VecDeque<std::time::Instant>);
+
+impl Rate {
+    /// Record that the thing happened, forgetting times that are too old to affect the rate.
+    fn tick(&mut self, now: std::time::Instant) {
+        while self
+            .0
+            .front()
+            .is_some_and(|oldest| now.duration_since(*oldest) > RATE_WINDOW)
+        {
+            self.0.pop_front();
+        }
+        self.0.push_back(now);
+    }
+
+    /// How many times per second the thing happened.
+    fn per_second(&self, now: std::time::Instant) -> f32 {
+        let count = self
+            .0
+            .iter()
+            .filter(|time| now.duration_since(**time) <= RATE_WINDOW)
+            .count();
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "Rates are never high enough to lose precision"
+        )]
+        let count = count as f32;
+        count / RATE_WINDOW.as_secs_f32()
+    }
+}
+
+/// Tracks the performance of the renderer and tattoys. It lives in the shared state.
+#[derive(Debug, Default)]
+pub(crate) struct Performance {
+    /// How long each frame took to composite and paint to the user's terminal.
+    frame_times: Samples,
+    /// How long each frame took just to composite.
+    composite_times: Samples,
+    /// When frames were painted.
+    frames: Rate,
+    /// The number of frame updates waiting in the renderer's backlog.
+    backlog: usize,
+    /// The largest backlog so far.
+    max_backlog: usize,
+    /// The number of frame updates that weren't painted because the renderer was behind.
+    skipped_frames: usize,
+    /// When each tattoy, and the PTY, sent frame updates.
+    updates: std::collections::HashMap<String, Rate>,
+    /// Every frame time since Tattoy started.
+    frame_histogram: Histogram,
+    /// Every composite time since Tattoy started.
+    composite_histogram: Histogram,
+    /// The number of frame updates that each tattoy, and the PTY, sent since Tattoy started.
+    updates_total: std::collections::BTreeMap<String, u64>,
+    /// Roughly how many bytes the renderer uses to keep the latest frame of every tattoy.
+    stored_surfaces_size: usize,
+}
+
+impl Performance {
+    /// Record a frame update from a tattoy or the PTY, along with the backlog of updates that
+    /// arrived after it.
+    pub(crate) fn record_update(&mut self, surface_id: &str, backlog: usize) {
+        self.backlog = backlog;
+        self.max_backlog = self.max_backlog.max(backlog);
+        if backlog > 0 {
+            self.skipped_frames = self.skipped_frames.saturating_add(1);
+        }
+        self.updates
+            .entry(surface_id.to_owned())
+            .or_default()
+            .tick(std::time::Instant::now());
+        let total = self.updates_total.entry(surface_id.to_owned()).or_default();
+        *total = total.saturating_add(1);
+    }
+
+    /// Record how much memory the renderer uses to keep the latest frame of every tattoy.
+    pub(crate) const fn record_stored_surfaces_size(&mut self, size: usize) {
+        self.stored_surfaces_size = size;
+    }
+
+    /// Record a frame that was painted to the user's terminal.
+    pub(crate) fn record_frame(
+        &mut self,
+        frame_time: std::time::Duration,
+        composite_time: std::time::Duration,
+    ) {
+        self.frame_times.add(frame_time);
+        self.composite_times.add(composite_time);
+        self.frames.tick(std::time::Instant::now());
+        self.frame_histogram.add(frame_time);
+        self.composite_histogram.add(composite_time);
+    }
+
+    /// The number of frames painted in the last second.
+    pub(crate) fn frames_per_second(&self) -> f32 {
+        self.frames.per_second(std::time::Instant::now())
+    }
+
+    /// The current statistics.
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let now = std::time::Instant::now();
+        Snapshot {
+            frames_per_second: self.frames.per_second(now),
+            frame_time: self.frame_times.timing(),
+            composite_time: self.composite_times.timing(),
+            backlog: self.backlog,
+            max_backlog: self.max_backlog,
+            skipped_frames: self.skipped_frames,
+            updates_per_second: self
+                .updates
+                .iter()
+                .map(|(id, rate)| (id.clone(), rate.per_second(now)))
+                .collect(),
+            frame_histogram: self.frame_histogram.clone(),
+            composite_histogram: self.composite_histogram.clone(),
+            updates_total: self.updates_total.clone(),
+            stored_surfaces_size: self.stored_surfaces_size,
+        }
+    }
+}
+
+/// Durations in milliseconds.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct Timing {
+    /// The average duration.
+    pub average_ms: f32,
+    /// The longest duration.
+    pub max_ms: f32,
+}
+
+/// The performance statistics at a single moment.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, PartialEq)]
+pub(crate) struct Snapshot {
+    /// The number of frames painted in the last second.
+    pub frames_per_second: f32,
+    /// How long the most recent frames took to composite and paint.
+    pub frame_time: Timing,
+    /// How long the most recent frames took just to composite.
+    pub composite_time: Timing,
+    /// The number of frame updates currently waiting in the renderer's backlog.
+    pub backlog: usize,
+    /// The largest backlog since Tattoy started.
+    pub max_backlog: usize,
+    /// The number of frame updates that weren't painted because the renderer was behind.
+    pub skipped_frames: usize,
+    /// How many frame updates each tattoy, and the PTY, sent in the last second.
+    pub updates_per_second: std::collections::BTreeMap<String, f32>,
+    /// Every frame time since Tattoy started.
+    pub frame_histogram: Histogram,
+    /// Every composite time since Tattoy started.
+    pub composite_histogram: Histogram,
+    /// How many frame updates each tattoy, and the PTY, sent since Tattoy started.
+    pub updates_total: std::collections::BTreeMap<String, u64>,
+    /// Roughly how many bytes the renderer uses to keep the latest frame of every tattoy.
+    pub stored_surfaces_size: usize,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timing_samples() {
+        let mut samples = Samples::default();
+        assert_eq!(samples.timing(), Timing::default());
+
+        samples.add(std::time::Duration::from_millis(2));
+        samples.add(std::time::Duration::from_millis(4));
+        let timing = samples.timing();
+        assert!((timing.average_ms - 3.0).abs() < 0.001);
+        assert!((timing.max_ms - 4.0).abs() < 0.001);
+
+        for _ in 0..SAMPLE_COUNT {
+            samples.add(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(samples.0.len(), SAMPLE_COUNT);
+        assert!((samples.timing().max_ms - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn measuring_rates() {
+        let start = std::time::Instant::now();
+        let mut rate = Rate::default();
+        for millis in [0, 100, 200, 1500] {
+            rate.tick(start + std::time::Duration::from_millis(millis));
+        }
+        let now = start + std::time::Duration::from_millis(1500);
+        assert!((rate.per_second(now) - 1.0).abs() < f32::EPSILON);
+        assert_eq!(rate.0.len(), 1);
+    }
+
+    #[test]
+    fn tracking_backlogs() {
+        let mut performance = Performance::default();
+        performance.record_update("shader", 3);
+        performance.record_update(PTY_ID, 0);
+        let snapshot = performance.snapshot();
+        assert_eq!(snapshot.backlog, 0);
+        assert_eq!(snapshot.max_backlog, 3);
+        assert_eq!(snapshot.skipped_frames, 1);
+        assert_eq!(snapshot.updates_per_second.len(), 2);
+        assert_eq!(snapshot.updates_total.get("shader"), Some(&1));
+    }
+
+    #[test]
+    fn histograms() {
+        let mut histogram = Histogram::default();
+        for millis in [1, 3, 3, 20, 1000] {
+            histogram.add(std::time::Duration::from_millis(millis));
+        }
+        assert_eq!(histogram.buckets, [1, 0, 2, 0, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(histogram.count, 5);
+        assert!((histogram.sum_ms - 1027.0).abs() < 0.001);
+    }
+}
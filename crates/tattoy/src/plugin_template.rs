@@ -0,0 +1,227 @@
+//! `tattoy plugin new`: scaffold a working plugin project, so that plugin authors can start from
+//! something that already speaks Tattoy's plugin protocol, logs somewhere useful and has an entry
+//! for the config, rather than from a blank file.
+
+use color_eyre::eyre::Result;
+
+/// The placeholder in the templates for the plugin's name.
+const NAME_PLACEHOLDER: &str = "{{name}}";
+
+/// The placeholder in the templates for the plugin's entry in Tattoy's config.
+const CONFIG_ENTRY_PLACEHOLDER: &str = "{{config_entry}}";
+
+/// The languages that plugins can be scaffolded in.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Language {
+    /// A Cargo project that uses the `tattoy-protocol` crate.
+    #[default]
+    Rust,
+    /// A single Python 3 script, without any dependencies.
+    Python,
+}
+
+/// A file of the scaffolded project.
+struct File {
+    /// The path of the file, relative to the project's directory.
+    path: &'static str,
+    /// The contents of the file, with placeholders.
+    template: &'static str,
+    /// Whether the file needs to be executable.
+    is_executable: bool,
+}
+
+impl Language {
+    /// The files of a project in the language.
+    const fn files(self) -> &'static [File] {
+        match self {
+            Self::Rust => &[
+                File {
+                    path: "Cargo.toml",
+                    template: include_str!("../plugin_templates/rust/Cargo.toml.template"),
+                    is_executable: false,
+                },
+                File {
+                    path: "src/main.rs",
+                    template: include_str!("../plugin_templates/rust/main.rs.template"),
+                    is_executable: false,
+                },
+                File {
+                    path: "README.md",
+                    template: include_str!("../plugin_templates/rust/README.md.template"),
+                    is_executable: false,
+                },
+            ],
+            Self::Python => &[
+                File {
+                    path: "plugin.py",
+                    template: include_str!("../plugin_templates/python/plugin.py.template"),
+                    is_executable: true,
+                },
+                File {
+                    path: "README.md",
+                    template: include_str!("../plugin_templates/python/README.md.template"),
+                    is_executable: false,
+                },
+            ],
+        }
+    }
+
+    /// The path of the plugin's executable, once it has been built.
+    fn executable(self, directory: &std::path::Path, name: &str) -> std::path::PathBuf {
+        match self {
+            Self::Rust => directory.join("target").join("release").join(name),
+            Self::Python => directory.join("plugin.py"),
+        }
+    }
+}
+
+/// Plugin names are used for the project's directory, Cargo package and log file, so they're kept
+/// simple.
+fn validate_name(name: &str) -> Result<()> {
+    let is_valid = name
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_".contains(character));
+    if !is_valid {
+        color_eyre::eyre::bail!(
+            "Plugin names must start with a letter, and only contain letters, numbers, '-' and '_'"
+        );
+    }
+
+    Ok(())
+}
+
+/// The plugin's entry for Tattoy's config file.
+fn config_entry(name: &str, executable: &std::path::Path) -> String {
+    let path = toml::Value::String(executable.display().to_string());
+    [
+        "[[plugins]]".to_owned(),
+        format!("name = \"{name}\""),
+        format!("path = {path}"),
+        "enabled = true".to_owned(),
+        "layer = -5".to_owned(),
+        String::new(),
+        "# Settings for the plugin itself, sent to it in the `config` message.".to_owned(),
+        "[plugins.config]".to_owned(),
+        "example = true".to_owned(),
+    ]
+    .join("\n")
+}
+
+/// Fill in a template's placeholders.
+fn render(template: &str, name: &str, config_entry: &str) -> String {
+    template
+        .replace(NAME_PLACEHOLDER, name)
+        .replace(CONFIG_ENTRY_PLACEHOLDER, config_entry)
+}
+
+/// Create a new plugin project, in a directory named after the plugin by default.
+#[expect(
+    clippy::print_stdout,
+    reason = "The next steps are for the user to read"
+)]
+pub(crate) fn create(
+    name: &str,
+    language: Language,
+    maybe_directory: Option<std::path::PathBuf>,
+) -> Result<()> {
+    validate_name(name)?;
+    let directory = maybe_directory.unwrap_or_else(|| name.into());
+    if directory.exists() {
+        color_eyre::eyre::bail!("{} already exists", directory.display());
+    }
+
+    let executable = language.executable(&std::path::absolute(&directory)?, name);
+    let entry = config_entry(name, &executable);
+    for file in language.files() {
+        let path = directory.join(file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, render(file.template, name, &entry))?;
+        if file.is_executable {
+            make_executable(&path)?;
+        }
+    }
+
+    println!("Created the '{name}' plugin in {}", directory.display());
+    if language == Language::Rust {
+        println!("Build it with `cargo build --release` in that directory.");
+    }
+    println!("Then add it to Tattoy's config:\n\n{entry}");
+    Ok(())
+}
+
+/// Let the user run the file.
+#[cfg(unix)]
+fn make_executable(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+/// Windows doesn't have an executable permission.
+#[cfg(not(unix))]
+#[expect(
+    clippy::unnecessary_wraps,
+    reason = "It has the same signature as the Unix version"
+)]
+const fn make_executable(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validating_names() {
+        assert!(validate_name("my-plugin_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("2fast").is_err());
+        assert!(validate_name("../escape").is_err());
+        assert!(validate_name("has space").is_err());
+    }
+
+    #[test]
+    fn config_entry_is_valid_config() {
+        let entry = config_entry("my-plugin", std::path::Path::new("/plugins/my-plugin"));
+        let mut config: toml::Table = toml::from_str(&entry).unwrap();
+        let plugins: Vec<crate::tattoys::plugins::Config> =
+            config.remove("plugins").unwrap().try_into().unwrap();
+        let plugin = plugins.first().unwrap();
+        assert_eq!(plugin.name, "my-plugin");
+        assert_eq!(plugin.path, std::path::PathBuf::from("/plugins/my-plugin"));
+    }
+
+    #[test]
+    fn rendering_every_template() {
+        for language in [Language::Rust, Language::Python] {
+            for file in language.files() {
+                let rendered = render(file.template, "my-plugin", "[[plugins]]");
+                assert!(
+                    !rendered.contains("{{"),
+                    "{} has placeholders left",
+                    file.path
+                );
+                assert!(rendered.contains("my-plugin"), "{} isn't named", file.path);
+            }
+        }
+    }
+
+    #[test]
+    fn creating_a_project() {
+        let temporary = tempfile::tempdir().unwrap();
+        let directory = temporary.path().join("my-plugin");
+        create("my-plugin", Language::Python, Some(directory.clone())).unwrap();
+        let script = std::fs::read_to_string(directory.join("plugin.py")).unwrap();
+        assert!(script.contains("my-plugin.log"));
+        assert!(create("my-plugin", Language::Python, Some(directory)).is_err());
+    }
+}
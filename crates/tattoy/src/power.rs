@@ -0,0 +1,324 @@
+//! Save power when running on battery. The power source is checked periodically, and whilst on
+//! battery the frame rate is lowered and the shader and particle-based tattoys are disabled. Full
+//! effects are restored as soon as the machine is plugged back in.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for saving power.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Reduce Tattoy's effects when running on battery power.
+    pub enabled: bool,
+    /// Only reduce effects once the battery's charge, as a percentage, is at or below this.
+    pub battery_threshold: u8,
+    /// The highest frame rate whilst saving power.
+    pub frame_rate: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            battery_threshold: 100,
+            frame_rate: 10,
+        }
+    }
+}
+
+/// How often the power source is checked, in seconds.
+const CHECK_INTERVAL: u64 = 30;
+
+/// Where Linux describes the machine's power supplies.
+const SYSFS_POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// The machine's current power source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Status {
+    /// Is the machine running on its battery?
+    pub is_on_battery: bool,
+    /// The battery's charge, as a percentage.
+    pub maybe_charge: Option<u8>,
+}
+
+impl Status {
+    /// Whether the given status means that effects should be reduced.
+    pub(crate) fn is_saving(config: &Config, maybe_status: Option<Self>) -> bool {
+        let Some(status) = maybe_status else {
+            return false;
+        };
+        config.enabled
+            && status.is_on_battery
+            && status
+                .maybe_charge
+                .is_none_or(|charge| charge <= config.battery_threshold)
+    }
+
+    /// A short description, for the status bar. It's empty when on AC power.
+    pub(crate) fn describe(maybe_status: Option<Self>, is_saving: bool) -> String {
+        let Some(status) = maybe_status.filter(|status| status.is_on_battery) else {
+            return String::new();
+        };
+        let mut description = match status.maybe_charge {
+            Some(charge) => format!("Battery {charge}%"),
+            None => "Battery".to_owned(),
+        };
+        if is_saving {
+            description.push_str(" (power saving)");
+        }
+        description
+    }
+
+    /// Read the power source from Linux's sysfs. A battery that isn't charging, when no mains
+    /// supply is online, means that the machine is running on battery.
+    fn from_sysfs(directory: &std::path::Path) -> Option<Self> {
+        let read = |supply: &std::path::Path, name: &str| {
+            std::fs::read_to_string(supply.join(name))
+                .ok()
+                .map(|contents| contents.trim().to_owned())
+        };
+
+        let mut is_mains_online = false;
+        let mut maybe_battery: Option<Self> = None;
+        for entry in std::fs::read_dir(directory).ok()?.flatten() {
+            let supply = entry.path();
+            match read(&supply, "type").as_deref() {
+                Some("Mains") => {
+                    is_mains_online |= read(&supply, "online").as_deref() == Some("1");
+                }
+                Some("Battery") => {
+                    let is_discharging = read(&supply, "status").as_deref() == Some("Discharging");
+                    maybe_battery = Some(Self {
+                        is_on_battery: is_discharging,
+                        maybe_charge: read(&supply, "capacity").and_then(|text| text.parse().ok()),
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        maybe_battery.map(|battery| Self {
+            is_on_battery: battery.is_on_battery && !is_mains_online,
+            ..battery
+        })
+    }
+
+    /// Parse the output of macOS's `pmset -g batt`, eg:
+    ///
+    /// ```text
+    /// Now drawing from 'Battery Power'
+    ///  -InternalBattery-0 (id=1234)    85%; discharging; 4:12 remaining present: true
+    /// ```
+    fn from_pmset(output: &str) -> Option<Self> {
+        let maybe_charge = output
+            .split_whitespace()
+            .find_map(|word| word.strip_suffix("%;"))
+            .and_then(|charge| charge.parse().ok());
+        if maybe_charge.is_none() && !output.contains("InternalBattery") {
+            return None;
+        }
+        Some(Self {
+            is_on_battery: output.contains("'Battery Power'"),
+            maybe_charge,
+        })
+    }
+
+    /// Get the machine's current power source. Machines without a battery, or platforms that
+    /// aren't supported, return `None`.
+    async fn read() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            let output = tokio::process::Command::new("pmset")
+                .args(["-g", "batt"])
+                .output()
+                .await
+                .ok()?;
+            Self::from_pmset(&String::from_utf8_lossy(&output.stdout))
+        } else {
+            Self::from_sysfs(std::path::Path::new(SYSFS_POWER_SUPPLY))
+        }
+    }
+}
+
+/// Lower the frame rate and disable the most power-hungry tattoys.
+pub(crate) fn reduce(config: &mut crate::config::main::Config) {
+    let maximum = config.power_saving.frame_rate.max(1);
+    config.frame_rate = config.frame_rate.min(maximum);
+    for frame_rate in [
+        &mut config.minimap.frame_rate,
+        &mut config.bg_command.frame_rate,
+        &mut config.notifications.frame_rate,
+        &mut config.weather.frame_rate,
+        &mut config.visual_bell.frame_rate,
+    ] {
+        *frame_rate = frame_rate.map(|rate| rate.min(maximum));
+    }
+
+    config.shader.enabled = false;
+    config.audio_visualiser.enabled = false;
+    config.typing_sparks.enabled = false;
+    config.cursor_smear.enabled = false;
+    config.starfield.enabled = false;
+    config.random_walker.enabled = false;
+}
+
+/// Periodically check the power source, reducing or restoring effects when it changes.
+pub(crate) fn watch(
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+) -> tokio::task::JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL));
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    check(&state).await;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if let crate::run::Protocol::Config { changes, .. } = &message {
+                        if changes.contains(&crate::config::diff::Section::PowerSaving) {
+                            check(&state).await;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Check the power source and reload the config if effects need to be reduced or restored.
+async fn check(state: &std::sync::Arc<crate::shared_state::SharedState>) {
+    let maybe_status = Status::read().await;
+    *state.power_status.write().await = maybe_status;
+
+    let config = state.config.load().power_saving.clone();
+    let is_saving = Status::is_saving(&config, maybe_status);
+    if is_saving == *state.is_power_saving.read().await {
+        return;
+    }
+
+    tracing::info!("Power saving: {is_saving}");
+    *state.is_power_saving.write().await = is_saving;
+    let message = if is_saving {
+        "On battery power, reducing effects"
+    } else {
+        "Restoring full effects"
+    };
+    crate::config::main::Config::reload_and_broadcast(state, message).await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const fn battery(charge: u8) -> Option<Status> {
+        Some(Status {
+            is_on_battery: true,
+            maybe_charge: Some(charge),
+        })
+    }
+
+    #[test]
+    fn deciding_to_save_power() {
+        let mut config = Config::default();
+        assert!(Status::is_saving(&config, battery(80)));
+        assert!(!Status::is_saving(&config, None));
+        assert!(!Status::is_saving(
+            &config,
+            Some(Status {
+                is_on_battery: false,
+                maybe_charge: Some(80),
+            })
+        ));
+
+        config.battery_threshold = 50;
+        assert!(!Status::is_saving(&config, battery(80)));
+        assert!(Status::is_saving(&config, battery(50)));
+
+        config.enabled = false;
+        assert!(!Status::is_saving(&config, battery(10)));
+    }
+
+    #[test]
+    fn describing_the_power_source() {
+        assert_eq!(
+            Status::describe(battery(42), true),
+            "Battery 42% (power saving)"
+        );
+        assert_eq!(Status::describe(battery(42), false), "Battery 42%");
+        assert_eq!(Status::describe(None, false), "");
+    }
+
+    #[test]
+    fn reading_sysfs() {
+        let directory = tempfile::tempdir().unwrap();
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            let path = directory.path().join(name);
+            std::fs::create_dir(&path).unwrap();
+            for (file, contents) in files {
+                std::fs::write(path.join(file), format!("{contents}\n")).unwrap();
+            }
+        };
+
+        assert_eq!(Status::from_sysfs(directory.path()), None);
+
+        supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("capacity", "67"),
+            ],
+        );
+        assert_eq!(Status::from_sysfs(directory.path()), battery(67));
+
+        supply("AC", &[("type", "Mains"), ("online", "1")]);
+        assert_eq!(
+            Status::from_sysfs(directory.path()),
+            Some(Status {
+                is_on_battery: false,
+                maybe_charge: Some(67),
+            })
+        );
+    }
+
+    #[test]
+    fn parsing_pmset() {
+        let on_battery = "Now drawing from 'Battery Power'\n \
+            -InternalBattery-0 (id=1234)\t85%; discharging; 4:12 remaining present: true";
+        assert_eq!(Status::from_pmset(on_battery), battery(85));
+
+        let on_ac = "Now drawing from 'AC Power'\n \
+            -InternalBattery-0 (id=1234)\t100%; charged; 0:00 remaining present: true";
+        assert_eq!(
+            Status::from_pmset(on_ac),
+            Some(Status {
+                is_on_battery: false,
+                maybe_charge: Some(100),
+            })
+        );
+
+        assert_eq!(Status::from_pmset("Now drawing from 'AC Power'\n"), None);
+    }
+
+    #[test]
+    fn reducing_effects() {
+        let mut config = crate::config::main::Config::default();
+        config.shader.enabled = true;
+        config.minimap.frame_rate = Some(60);
+        reduce(&mut config);
+        assert_eq!(config.frame_rate, 10);
+        assert_eq!(config.minimap.frame_rate, Some(10));
+        assert!(!config.shader.enabled);
+    }
+}
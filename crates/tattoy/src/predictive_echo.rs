@@ -0,0 +1,253 @@
+//! Predictive echo, to hide the latency of the extra terminal layer that Tattoy adds, in the same
+//! way that Mosh does.
+//!
+//! Simple printable keystrokes are drawn straight onto the composited frame, at the cursor,
+//! without waiting for them to round trip through the PTY. Every time the PTY updates, the
+//! predictions are checked against it. Once the PTY shows the predicted character, the prediction
+//! is no longer needed. If the PTY shows something else, or doesn't echo the character at all, eg
+//! at a password prompt, all the predictions are reverted.
+
+/// User-configurable settings for predictive echo.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to predict the echo of keystrokes.
+    pub enabled: bool,
+    /// How long to wait, in milliseconds, for the PTY to confirm a prediction before reverting it.
+    pub timeout: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: 500,
+        }
+    }
+}
+
+/// A single predicted character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Prediction {
+    /// The column of the PTY that the character was typed into.
+    x: usize,
+    /// The row of the PTY that the character was typed into.
+    y: usize,
+    /// The typed character.
+    character: char,
+    /// When the character was typed.
+    at: std::time::Instant,
+}
+
+/// Keeps track of the keystrokes that have been echoed, but not yet confirmed by the PTY.
+#[derive(Debug, Default)]
+pub(crate) struct Predictor {
+    /// The unconfirmed predictions, in the order that they were typed.
+    predictions: Vec<Prediction>,
+}
+
+impl Predictor {
+    /// The character that a key press would simply print, if it's the kind of key press that can
+    /// be safely predicted.
+    fn printable(key: &termwiz::input::KeyEvent) -> Option<char> {
+        let is_unmodified = key.modifiers == termwiz::input::Modifiers::NONE
+            || key.modifiers == termwiz::input::Modifiers::SHIFT;
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only printable characters are predicted"
+        )]
+        match key.key {
+            termwiz::input::KeyCode::Char(character)
+                if is_unmodified && (character.is_ascii_graphic() || character == ' ') =>
+            {
+                Some(character)
+            }
+            _ => None,
+        }
+    }
+
+    /// Predict the echo of a key press. Any key press that can't be predicted, like `Enter` or an
+    /// arrow key, reverts all the predictions, because there's no knowing what it will do. Returns
+    /// whether the predictions changed.
+    pub fn predict(
+        &mut self,
+        key: &termwiz::input::KeyEvent,
+        pty: &termwiz::surface::Surface,
+        now: std::time::Instant,
+    ) -> bool {
+        let Some(character) = Self::printable(key) else {
+            return self.revert();
+        };
+
+        // Predicting line wrapping, or text being inserted into the middle of a line, isn't worth
+        // the risk.
+        let (x, y) = self.cursor().unwrap_or_else(|| pty.cursor_position());
+        if x >= pty.dimensions().0 || !Self::is_blank(Self::cell_text(pty, x, y).as_deref()) {
+            return false;
+        }
+
+        self.predictions.push(Prediction {
+            x,
+            y,
+            character,
+            at: now,
+        });
+        true
+    }
+
+    /// The text of a cell of the PTY.
+    fn cell_text(pty: &termwiz::surface::Surface, x: usize, y: usize) -> Option<String> {
+        pty.screen_lines().get(y).and_then(|line| {
+            line.visible_cells()
+                .find(|cell| cell.cell_index() == x)
+                .map(|cell| cell.str().to_owned())
+        })
+    }
+
+    /// Whether a cell of the PTY is empty.
+    fn is_blank(text: Option<&str>) -> bool {
+        matches!(text, None | Some(" " | ""))
+    }
+
+    /// Check the predictions against a new PTY frame. Confirmed predictions are dropped, and a
+    /// single wrong prediction reverts all of them. Returns whether the predictions changed.
+    pub fn reconcile(&mut self, pty: &termwiz::surface::Surface) -> bool {
+        let mut is_wrong = false;
+        let count = self.predictions.len();
+        self.predictions.retain(|prediction| {
+            let text = Self::cell_text(pty, prediction.x, prediction.y);
+            if text == Some(prediction.character.to_string()) {
+                return false;
+            }
+            if !Self::is_blank(text.as_deref()) {
+                is_wrong = true;
+            }
+            true
+        });
+
+        if is_wrong {
+            tracing::trace!("Reverting mispredicted echo");
+            self.revert();
+            return true;
+        }
+        count != self.predictions.len()
+    }
+
+    /// Revert the predictions if the PTY hasn't confirmed the oldest one in time. Returns whether
+    /// the predictions were reverted.
+    pub fn expire(&mut self, now: std::time::Instant, timeout: std::time::Duration) -> bool {
+        let is_expired = self
+            .predictions
+            .first()
+            .is_some_and(|prediction| now.saturating_duration_since(prediction.at) > timeout);
+        if is_expired {
+            tracing::trace!("Reverting echo predictions that weren't confirmed in time");
+            self.revert();
+        }
+        is_expired
+    }
+
+    /// Forget all the predictions. Returns whether there were any.
+    pub fn revert(&mut self) -> bool {
+        let had_predictions = !self.predictions.is_empty();
+        self.predictions.clear();
+        had_predictions
+    }
+
+    /// Where the PTY's cursor would be if all the predictions are right.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.predictions
+            .last()
+            .map(|prediction| (prediction.x + 1, prediction.y))
+    }
+
+    /// Draw the predictions onto the frame, keeping the styling of the cells that they replace.
+    /// `top` is the number of rows of the frame that are above the PTY.
+    pub fn overlay(&self, frame: &mut termwiz::surface::Surface, top: usize) {
+        let mut cells = frame.screen_cells();
+        for prediction in &self.predictions {
+            let Some(cell) = cells
+                .get_mut(prediction.y + top)
+                .and_then(|line| line.get_mut(prediction.x))
+            else {
+                continue;
+            };
+            *cell = termwiz::cell::Cell::new(prediction.character, cell.attrs().clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(character: char) -> termwiz::input::KeyEvent {
+        termwiz::input::KeyEvent {
+            key: termwiz::input::KeyCode::Char(character),
+            modifiers: termwiz::input::Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn predicting_and_confirming() {
+        let now = std::time::Instant::now();
+        let mut pty = termwiz::surface::Surface::new(10, 3);
+        let mut predictor = Predictor::default();
+
+        assert!(predictor.predict(&key('l'), &pty, now));
+        assert!(predictor.predict(&key('s'), &pty, now));
+        assert_eq!(predictor.cursor(), Some((2, 0)));
+
+        let mut frame = termwiz::surface::Surface::new(10, 4);
+        predictor.overlay(&mut frame, 1);
+        assert!(frame.screen_chars_to_string().starts_with("          \nls"));
+
+        pty.add_change("l");
+        assert!(predictor.reconcile(&pty));
+        assert_eq!(predictor.cursor(), Some((2, 0)));
+        pty.add_change("s");
+        assert!(predictor.reconcile(&pty));
+        assert_eq!(predictor.cursor(), None);
+    }
+
+    #[test]
+    fn reverting_wrong_predictions() {
+        let now = std::time::Instant::now();
+        let mut pty = termwiz::surface::Surface::new(10, 3);
+        let mut predictor = Predictor::default();
+
+        predictor.predict(&key('a'), &pty, now);
+        predictor.predict(&key('b'), &pty, now);
+        pty.add_change("x");
+        assert!(predictor.reconcile(&pty));
+        assert_eq!(predictor.cursor(), None);
+    }
+
+    #[test]
+    fn reverting_unpredictable_keys() {
+        let now = std::time::Instant::now();
+        let pty = termwiz::surface::Surface::new(10, 3);
+        let mut predictor = Predictor::default();
+
+        predictor.predict(&key('a'), &pty, now);
+        let ctrl_c = termwiz::input::KeyEvent {
+            key: termwiz::input::KeyCode::Char('c'),
+            modifiers: termwiz::input::Modifiers::CTRL,
+        };
+        assert!(predictor.predict(&ctrl_c, &pty, now));
+        assert_eq!(predictor.cursor(), None);
+    }
+
+    #[test]
+    fn expiring_unconfirmed_predictions() {
+        let now = std::time::Instant::now();
+        let pty = termwiz::surface::Surface::new(10, 3);
+        let mut predictor = Predictor::default();
+        let timeout = std::time::Duration::from_millis(500);
+
+        predictor.predict(&key('a'), &pty, now);
+        assert!(!predictor.expire(now + std::time::Duration::from_millis(100), timeout));
+        assert!(predictor.expire(now + std::time::Duration::from_secs(1), timeout));
+        assert_eq!(predictor.cursor(), None);
+    }
+}
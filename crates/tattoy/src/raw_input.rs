@@ -18,22 +18,36 @@ pub(crate) struct ParsedInput {
 
 /// Handle input from the user
 pub(crate) struct RawInput {
-    /// The main Tattoy protocol channel.
-    protocol_tx: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+    /// Shared app state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// A handle to the Tokio runtime, so that async work can be started from the input thread.
+    tokio_runtime: tokio::runtime::Handle,
 }
 
 impl RawInput {
     /// Start a thread to listen and parse the end user's STDIN and forward it to the rest of the
     /// application.
     pub fn start(
-        protocol_tx: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
     ) -> std::thread::JoinHandle<std::result::Result<(), color_eyre::eyre::Error>> {
+        let tokio_runtime = tokio::runtime::Handle::current();
+
         // The Tokio docs actually suggest using `std::thread` to listen on STDIN for interactive
         // applications.
         std::thread::spawn(move || -> Result<()> {
-            let protocol_for_shutdown = protocol_tx.clone();
-            let input = Self { protocol_tx };
-            let result = input.consume_stdin();
+            let protocol_for_shutdown = state.protocol_tx.clone();
+            let input = Self {
+                state,
+                tokio_runtime,
+            };
+            let result =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| input.consume_stdin()))
+                    .unwrap_or_else(|payload| {
+                        Err(color_eyre::eyre::eyre!(
+                            "STDIN thread panicked: {}",
+                            crate::run::panic_message(&*payload)
+                        ))
+                    });
             if let Err(error) = result {
                 crate::run::broadcast_protocol_end(&protocol_for_shutdown);
                 return Err(error);
@@ -50,14 +64,20 @@ impl RawInput {
         let stdin = std::io::stdin();
         let mut reader = std::io::BufReader::new(stdin);
         let mut parser = termwiz::input::InputParser::new();
+        let mut theme_watcher = crate::palette::theme_watcher::ThemeWatcher::default();
         let mut accumulated: Vec<u8> = Vec::new();
         let mut is_accumulating = false;
 
         loop {
             let mut buffer: BytesFromSTDIN = [0; 128];
             match reader.read(&mut buffer[..]) {
-                Ok(size) => {
-                    let is_full = size == 128;
+                Ok(raw_size) => {
+                    let size = self.filter_palette_input(&mut theme_watcher, &mut buffer, raw_size);
+                    if size == 0 && raw_size != 0 {
+                        continue;
+                    }
+
+                    let is_full = raw_size == 128;
                     if is_full {
                         is_accumulating = true;
                     }
@@ -92,9 +112,63 @@ impl RawInput {
         }
     }
 
+    /// Remove the terminal's theme change notifications and palette replies from the input, so
+    /// that they don't get sent to the PTY. Returns the size of the remaining input.
+    fn filter_palette_input(
+        &self,
+        theme_watcher: &mut crate::palette::theme_watcher::ThemeWatcher,
+        buffer: &mut BytesFromSTDIN,
+        size: usize,
+    ) -> usize {
+        let Some(bytes) = buffer.get(0..size) else {
+            return size;
+        };
+        let (remaining, events) = theme_watcher.filter(bytes);
+        for event in events {
+            self.handle_palette_event(event);
+        }
+        if remaining.len() == size {
+            return size;
+        }
+
+        *buffer = [0; 128];
+        for (byte, remaining_byte) in buffer.iter_mut().zip(&remaining) {
+            *byte = *remaining_byte;
+        }
+        remaining.len()
+    }
+
+    /// React to changes in the terminal's theme.
+    fn handle_palette_event(&self, event: crate::palette::theme_watcher::Event) {
+        match event {
+            crate::palette::theme_watcher::Event::ThemeChanged => {
+                let query = crate::palette::osc::palette_queries();
+                let result = self
+                    .state
+                    .protocol_tx
+                    .send(crate::run::Protocol::QueryTerminal(query));
+                if let Err(error) = result {
+                    tracing::error!("Couldn't request palette query: {error:?}");
+                }
+            }
+            crate::palette::theme_watcher::Event::Detected(colours) => {
+                let state = std::sync::Arc::clone(&self.state);
+                self.tokio_runtime.spawn(async move {
+                    let result =
+                        crate::palette::theme_watcher::apply_detected_colours(&state, colours)
+                            .await;
+                    if let Err(error) = result {
+                        tracing::error!("Couldn't update palette after theme change: {error:?}");
+                    }
+                });
+            }
+        }
+    }
+
     /// The callback for when the input parser detects known keyboard/mouse events.
     fn parsed_bytes_callback(&self, event: termwiz::input::InputEvent, bytes: Vec<u8>) {
         let result = self
+            .state
             .protocol_tx
             .send(crate::run::Protocol::Input(ParsedInput { bytes, event }));
         if let Err(error) = result {
@@ -1,9 +1,12 @@
 //! Render the output of the PTY and tattoys
 
+use std::io::Write as _;
 use std::str::FromStr as _;
 use std::sync::Arc;
 
 use color_eyre::eyre::{bail, Result};
+use futures_util::FutureExt as _;
+use rayon::iter::{IntoParallelIterator as _, ParallelIterator as _};
 use termwiz::cell::{Cell, CellAttributes};
 
 use termwiz::surface::Surface as TermwizSurface;
@@ -15,12 +18,19 @@ use crate::compositor::Compositor;
 use crate::run::FrameUpdate;
 use crate::shared_state::SharedState;
 
+/// Frames with at least this many cells have their rows composited in parallel. Below this, the
+/// overhead of sharing the work between threads costs more than it saves.
+const PARALLEL_COMPOSITING_MIN_CELLS: usize = 20_000;
+
 /// The number of microseconds in a second.
 pub const ONE_MICROSECOND: u64 = 1_000_000;
 
 /// The number of milliseconds in a second.
 pub const MILLIS_PER_SECOND: f32 = 1_000.0;
 
+/// The number of bytes in a megabyte.
+const BYTES_PER_MEGABYTE: u64 = 1024 * 1024;
+
 /// The minimum rate at which we check that the user's terminal has resized.
 ///
 /// Each time a new frame is rendered a terminal size check is also made, which may lead to checks
@@ -43,6 +53,53 @@ pub const CHECK_FOR_RESIZE_RATE: u64 = 30;
 /// buffer of frames is for extreme conditions. 100 frames should give about 3 seconds of grace.
 const MAX_FRAME_BACKLOG: usize = 100;
 
+/// A movement of the cursor, as seen by the renderer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CursorMovement {
+    /// Where the cursor moved from, in columns and rows.
+    pub from: (usize, usize),
+    /// Where the cursor moved to, in columns and rows.
+    pub to: (usize, usize),
+    /// When the renderer first saw the cursor in its new position. Animations can interpolate
+    /// against this, rather than against their own frame ticks.
+    pub at: tokio::time::Instant,
+}
+
+/// The user's actual terminal. It's put back into cooked mode, with a visible cursor, when it's
+/// dropped. That happens whether Tattoy exits normally or the renderer panics, so the user is never
+/// left with a terminal in raw mode.
+pub(crate) struct UsersTerminal(BufferedTerminal<termwiz::terminal::SystemTerminal>);
+
+impl core::ops::Deref for UsersTerminal {
+    type Target = BufferedTerminal<termwiz::terminal::SystemTerminal>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl core::ops::DerefMut for UsersTerminal {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for UsersTerminal {
+    fn drop(&mut self) {
+        tracing::debug!("Setting user's terminal to cooked mode");
+        let terminal = self.0.terminal();
+        if let Err(error) = terminal.set_cooked_mode() {
+            tracing::error!("Couldn't set user's terminal to cooked mode: {error:?}");
+        }
+        let result = terminal
+            .write_all(crate::utils::SHOW_CURSOR.as_bytes())
+            .and_then(|()| std::io::Write::flush(terminal));
+        if let Err(error) = result {
+            tracing::error!("Couldn't show the cursor in the user's terminal: {error:?}");
+        }
+    }
+}
+
 /// `Render`
 pub(crate) struct Renderer {
     /// Shared app state
@@ -53,16 +110,42 @@ pub(crate) struct Renderer {
     pub height: u16,
     /// Merged tattoy surfaces
     pub tattoys: std::collections::HashMap<String, crate::surface::Surface>,
-    /// A shadow version of the user's conventional terminal
-    pub pty: TermwizSurface,
+    /// When each of the tattoy surfaces was last updated. The least recently updated surfaces are
+    /// the first to be forgotten when they use too much memory.
+    pub tattoys_updated_at: std::collections::HashMap<String, std::time::Instant>,
+    /// The parts of the frame that pixel-based tattoys aren't drawn over. See
+    /// `crate::exclusion_zones`.
+    pub exclusions: Vec<crate::exclusion_zones::Rect>,
+    /// The previous frames of slowly updating tattoys, that are being cross-faded from. See
+    /// `crate::interpolation`.
+    pub interpolations: std::collections::HashMap<String, crate::interpolation::Previous>,
+    /// A shadow version of the user's conventional terminal. It's a snapshot of the shared state's
+    /// screen, so it's never changed here.
+    pub pty: Arc<TermwizSurface>,
     /// A buffered wrapper around the user's actual terminal.
-    pub users_terminal: Option<BufferedTerminal<termwiz::terminal::SystemTerminal>>,
+    pub users_terminal: Option<UsersTerminal>,
     /// The base composited frame onto which all tattoys are rendered.
     pub frame: termwiz::surface::Surface,
+    /// The cells of the frame that the PTY or any tattoys have drawn on. Colour grading and text
+    /// contrast only need to visit these cells.
+    pub frame_dirty_cells: crate::surface::DirtyCells,
     /// A little indicator to show that Tattoy is running.
     pub indicator_cell: Cell,
     /// Is the cursor currently visible?
     pub is_cursor_visible: bool,
+    /// The true colour of the user's terminal's default background.
+    pub default_background_colour: termwiz::color::SrgbaTuple,
+    /// The surface ID of the tattoy being shown by the screensaver, whilst it's running.
+    pub maybe_screensaver_tattoy: Option<String>,
+    /// Rows of the user's terminal that the PTY doesn't use.
+    pub reserved_rows: crate::shared_state::ReservedRows,
+    /// Keystrokes that have been echoed before the PTY has echoed them itself.
+    pub predictor: crate::predictive_echo::Predictor,
+    /// The cursor shape that was last sent to the user's terminal. See `crate::cursor_style`.
+    pub cursor_shape: termwiz::surface::CursorShape,
+    /// Whether the startup splash is being shown, because the PTY hasn't sent its first frame
+    /// yet. See `crate::splash`.
+    pub is_showing_splash: bool,
 }
 
 impl Renderer {
@@ -75,7 +158,10 @@ impl Renderer {
         let users_terminal = if with_user_terminal {
             let mut termwiz_terminal = Self::get_termwiz_terminal()?;
             termwiz_terminal.set_raw_mode()?;
-            Some(BufferedTerminal::new(termwiz_terminal)?)
+            termwiz_terminal.write_all(
+                crate::palette::theme_watcher::ENABLE_THEME_NOTIFICATIONS.as_bytes(),
+            )?;
+            Some(UsersTerminal(BufferedTerminal::new(termwiz_terminal)?))
         } else {
             None
         };
@@ -85,11 +171,21 @@ impl Renderer {
             width: size.width,
             height: size.height,
             users_terminal,
-            pty: TermwizSurface::new(width.into(), height.into()),
+            pty: Arc::new(TermwizSurface::new(width.into(), height.into())),
             tattoys: std::collections::HashMap::default(),
+            tattoys_updated_at: std::collections::HashMap::default(),
+            interpolations: std::collections::HashMap::default(),
+            exclusions: Vec::new(),
             frame: TermwizSurface::new(width.into(), height.into()),
+            frame_dirty_cells: crate::surface::DirtyCells::new(width.into(), height.into()),
             indicator_cell: Self::indicator_cell()?,
             is_cursor_visible: true,
+            default_background_colour: crate::blender::DEFAULT_COLOUR,
+            maybe_screensaver_tattoy: None,
+            reserved_rows: crate::shared_state::ReservedRows::default(),
+            predictor: crate::predictive_echo::Predictor::default(),
+            cursor_shape: termwiz::surface::CursorShape::Default,
+            is_showing_splash: with_user_terminal,
         };
 
         Ok(renderer)
@@ -110,41 +206,56 @@ impl Renderer {
         }
     }
 
-    /// Instantiate and run
+    /// Instantiate and run. Without the user's terminal, frames are only composited, see
+    /// `crate::batch`.
     pub fn start(
         state: Arc<SharedState>,
         protocol_tx: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        with_user_terminal: bool,
     ) -> (
         tokio::task::JoinHandle<Result<()>>,
         tokio::sync::mpsc::Sender<FrameUpdate>,
     ) {
         let (surfaces_tx, surfaces_rx) = tokio::sync::mpsc::channel(MAX_FRAME_BACKLOG);
         let handle = tokio::spawn(async move {
-            // This would be much simpler if async closures where stable, because then we could use
-            // the `?` syntax.
-            match Self::new(Arc::clone(&state), true).await {
-                Ok(mut renderer) => {
-                    let result = renderer.run(surfaces_rx, protocol_tx.clone(), state).await;
-
-                    if let Err(error) = result {
-                        crate::run::broadcast_protocol_end(&protocol_tx);
-                        return Err(error);
-                    }
-                }
-                Err(error) => {
-                    crate::run::broadcast_protocol_end(&protocol_tx);
-                    return Err(error);
-                }
+            // A panic unwinds the renderer, which drops the user's terminal, restoring it.
+            let result = std::panic::AssertUnwindSafe(Self::new_and_run(
+                state,
+                surfaces_rx,
+                protocol_tx.clone(),
+                with_user_terminal,
+            ))
+            .catch_unwind()
+            .await
+            .unwrap_or_else(|payload| {
+                Err(color_eyre::eyre::eyre!(
+                    "Renderer panicked: {}",
+                    crate::run::panic_message(&*payload)
+                ))
+            });
+
+            if result.is_err() {
+                crate::run::broadcast_protocol_end(&protocol_tx);
             }
-
-            Ok(())
+            result
         });
 
         (handle, surfaces_tx)
     }
 
+    /// Instantiate a renderer and run it until Tattoy exits.
+    async fn new_and_run(
+        state: Arc<SharedState>,
+        surfaces_rx: tokio::sync::mpsc::Receiver<FrameUpdate>,
+        protocol_tx: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        with_user_terminal: bool,
+    ) -> Result<()> {
+        let mut renderer = Self::new(Arc::clone(&state), with_user_terminal).await?;
+        renderer.run(surfaces_rx, protocol_tx, state).await
+    }
+
     /// The Termwiz terminal is a wrapper around the user's actual terminal.
-    fn get_termwiz_terminal() -> Result<termwiz::terminal::SystemTerminal> {
+    pub(crate) fn get_termwiz_terminal() -> Result<termwiz::terminal::SystemTerminal> {
         let capabilities = termwiz::caps::Capabilities::new_from_env()?;
         Ok(termwiz::terminal::SystemTerminal::new(capabilities)?)
     }
@@ -229,6 +340,10 @@ impl Renderer {
                 // checks.
                 () = tokio::time::sleep(tokio::time::Duration::from_millis(CHECK_FOR_RESIZE_RATE)) => {
                     self.check_for_user_resize(&protocol_tx).await?;
+                    self.expire_predictions().await?;
+                    if self.is_interpolating() || self.is_showing_splash {
+                        self.paint().await?;
+                    }
                 },
 
                 Ok(message) = protocol_rx.recv() => {
@@ -241,10 +356,8 @@ impl Renderer {
         }
         tracing::debug!("Exited render loop");
 
-        tracing::debug!("Setting user's terminal to cooked mode");
-        if let Some(users_terminal) = self.users_terminal.as_mut() {
-            users_terminal.terminal().set_cooked_mode()?;
-        }
+        // Restores the user's terminal.
+        self.users_terminal = None;
 
         Ok(())
     }
@@ -264,26 +377,119 @@ impl Renderer {
 
     /// Handle messages from the global Tattoy protocol.
     async fn handle_protocol_message(&mut self, message: &crate::run::Protocol) -> Result<()> {
+        crate::crash_report::record(&self.state, message).await;
         match message {
             crate::run::Protocol::Output(_)
             | crate::run::Protocol::End
             | crate::run::Protocol::Resize { .. }
-            | crate::run::Protocol::Input(_)
-            | crate::run::Protocol::Config(_)
+            | crate::run::Protocol::Config { .. }
             | crate::run::Protocol::KeybindEvent(_)
-            | crate::run::Protocol::Notification(_) => (),
+            | crate::run::Protocol::Notification(_)
+            | crate::run::Protocol::Palette(_)
+            | crate::run::Protocol::Bell
+            | crate::run::Protocol::WorkingDirectory(_)
+            | crate::run::Protocol::CommandStarted
+            | crate::run::Protocol::CommandFinished(_)
+            | crate::run::Protocol::CursorMoved(_)
+            | crate::run::Protocol::ReservedRows(_)
+            | crate::run::Protocol::Bookmarks(_)
+            | crate::run::Protocol::ScrollTo(_)
+            | crate::run::Protocol::RespawnCommand(_)
+            | crate::run::Protocol::PreviewShader(_) => (),
+            crate::run::Protocol::Screensaver(is_active) => {
+                self.maybe_screensaver_tattoy = if *is_active {
+                    let name = self.state.config.load().screensaver.tattoy.clone();
+                    Some(crate::loader::surface_id(&name).to_owned())
+                } else {
+                    None
+                };
+            }
             crate::run::Protocol::CursorVisibility(is_visible) => {
                 self.is_cursor_visible = *is_visible;
             }
+            crate::run::Protocol::Input(input) => self.predict_echo(input).await?,
             crate::run::Protocol::Repaint => self.paint().await?,
+            crate::run::Protocol::QueryTerminal(query) => self.query_terminal(query)?,
+            crate::run::Protocol::CursorColour(colour) => self.set_cursor_colour(*colour)?,
+        }
+
+        Ok(())
+    }
+
+    /// Echo a keystroke before the PTY does, to hide Tattoy's latency. See
+    /// `crate::predictive_echo`.
+    async fn predict_echo(&mut self, input: &crate::raw_input::ParsedInput) -> Result<()> {
+        let termwiz::input::InputEvent::Key(key) = &input.event else {
+            return Ok(());
+        };
+
+        let is_predictable = self.state.config.load().predictive_echo.enabled
+            && !self.state.get_is_scrolling().await
+            && !*self.state.is_alternate_screen.read().await
+            && !self.state.is_overlay_capturing_input().await;
+        let is_changed = if is_predictable {
+            self.predictor
+                .predict(key, &self.pty, std::time::Instant::now())
+        } else {
+            self.predictor.revert()
+        };
+
+        if is_changed {
+            self.paint().await?;
+        }
+        Ok(())
+    }
+
+    /// Revert echoed keystrokes that the PTY didn't confirm in time.
+    async fn expire_predictions(&mut self) -> Result<()> {
+        let timeout =
+            std::time::Duration::from_millis(self.state.config.load().predictive_echo.timeout);
+        if self.predictor.expire(std::time::Instant::now(), timeout) {
+            self.paint().await?;
         }
+        Ok(())
+    }
+
+    /// Write a query directly to the user's terminal. It's done here so that it can't get mixed
+    /// up with the output of a frame.
+    fn query_terminal(&mut self, query: &str) -> Result<()> {
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
 
+        tracing::debug!("Sending query to user's terminal");
+        let query = crate::multiplexer::wrap_query(&self.state, query);
+        let terminal = users_terminal.terminal();
+        terminal.write_all(query.as_bytes())?;
+        terminal.flush()?;
+        Ok(())
+    }
+
+    /// Set the colour of the user's cursor to the colour an application set for the PTY's cursor.
+    fn set_cursor_colour(
+        &mut self,
+        maybe_colour: Option<termwiz::color::SrgbaTuple>,
+    ) -> Result<()> {
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
+
+        tracing::debug!("Setting user's cursor colour: {maybe_colour:?}");
+        let code = crate::multiplexer::wrap_query(
+            &self.state,
+            &crate::cursor_style::colour_code(maybe_colour),
+        );
+        let terminal = users_terminal.terminal();
+        terminal.write_all(code.as_bytes())?;
+        terminal.flush()?;
         Ok(())
     }
 
     /// Reset the frame for every render.
     fn reset_frame(&mut self) {
         self.frame = TermwizSurface::new(self.width.into(), self.height.into());
+        self.frame_dirty_cells =
+            crate::surface::DirtyCells::new(self.width.into(), self.height.into());
     }
 
     /// Do a single render to the user's actual terminal. It uses a diffing algorithm to make
@@ -292,11 +498,25 @@ impl Renderer {
         match update {
             FrameUpdate::TattoySurface(surface) => {
                 let surface_id = surface.id.clone();
+                self.state
+                    .performance
+                    .write()
+                    .await
+                    .record_update(&surface_id, backlog);
                 if surface.width == 0 || surface.height == 0 {
-                    self.tattoys.remove(&surface_id);
+                    self.forget_tattoy(&surface_id);
                 } else {
-                    self.tattoys.insert(surface_id.clone(), surface);
+                    let maybe_previous = self.tattoys.insert(surface_id.clone(), surface);
+                    self.remember_previous_frame(&surface_id, maybe_previous);
+                    self.tattoys_updated_at
+                        .insert(surface_id.clone(), std::time::Instant::now());
+                    self.evict_previous_frames();
                 }
+                self.state
+                    .performance
+                    .write()
+                    .await
+                    .record_stored_surfaces_size(self.stored_tattoys_size());
                 // TODO: convert IDs to something more constant.
                 if surface_id != "random_walker"
                     && surface_id != "shader"
@@ -307,7 +527,13 @@ impl Renderer {
             }
             FrameUpdate::PTYSurface => {
                 tracing::trace!("Rendering PTY frame update");
-                self.get_updated_pty_frame().await;
+                self.state
+                    .performance
+                    .write()
+                    .await
+                    .record_update(crate::performance::PTY_ID, backlog);
+                self.is_showing_splash = false;
+                self.get_updated_pty_frame();
             }
         }
 
@@ -323,11 +549,117 @@ impl Renderer {
         Ok(())
     }
 
+    /// Forget a tattoy's surface, so that it's no longer rendered.
+    fn forget_tattoy(&mut self, surface_id: &str) {
+        self.tattoys.remove(surface_id);
+        self.tattoys_updated_at.remove(surface_id);
+        self.interpolations.remove(surface_id);
+    }
+
+    /// Keep a tattoy's previous frame to cross-fade from, if it's updating slowly enough.
+    fn remember_previous_frame(
+        &mut self,
+        surface_id: &str,
+        maybe_previous: Option<crate::surface::Surface>,
+    ) {
+        self.interpolations.remove(surface_id);
+        let config = self.state.config.load();
+        if !config.interpolate_frames {
+            return;
+        }
+        let (Some(previous), Some(updated_at)) =
+            (maybe_previous, self.tattoys_updated_at.get(surface_id))
+        else {
+            return;
+        };
+        let interval = updated_at.elapsed();
+        let is_same_size = self
+            .tattoys
+            .get(surface_id)
+            .is_some_and(|latest| latest.surface.dimensions() == previous.surface.dimensions());
+        let is_slow = crate::interpolation::is_worth_interpolating(interval, config.frame_rate);
+        if is_same_size && is_slow {
+            self.interpolations.insert(
+                surface_id.to_owned(),
+                crate::interpolation::Previous {
+                    surface: previous,
+                    interval,
+                },
+            );
+        }
+    }
+
+    /// How far through cross-fading from its previous frame a tattoy is, if it's being
+    /// cross-faded at all.
+    fn interpolation_progress(&self, surface_id: &str) -> Option<f32> {
+        let previous = self.interpolations.get(surface_id)?;
+        let updated_at = self.tattoys_updated_at.get(surface_id)?;
+        let progress = crate::interpolation::progress(updated_at.elapsed(), previous.interval);
+        (progress < 1.0).then_some(progress)
+    }
+
+    /// Are any tattoys still being cross-faded? If so then frames need painting even when nothing
+    /// new has been sent.
+    fn is_interpolating(&self) -> bool {
+        self.interpolations
+            .keys()
+            .any(|surface_id| self.interpolation_progress(surface_id).is_some())
+    }
+
+    /// Roughly how many bytes all the tattoy surfaces use.
+    fn stored_tattoys_size(&self) -> usize {
+        let previous_frames = self
+            .interpolations
+            .values()
+            .map(|previous| &previous.surface);
+        self.tattoys
+            .values()
+            .chain(previous_frames)
+            .map(crate::surface::Surface::memory_size)
+            .sum()
+    }
+
+    /// Forget the previous frames that are kept for cross-fading, least recently updated first,
+    /// until all the surfaces fit in the configured memory limit. The latest frame of every tattoy
+    /// is always kept, otherwise slowly updating tattoys, like the clock, would disappear until
+    /// their next update. Tattoys that stop running are forgotten by the loader.
+    fn evict_previous_frames(&mut self) {
+        let limit_megabytes = self.state.config.load().max_tattoy_surfaces_size;
+        if limit_megabytes == 0 {
+            return;
+        }
+        let limit = usize::try_from(limit_megabytes.saturating_mul(BYTES_PER_MEGABYTE))
+            .unwrap_or(usize::MAX);
+
+        while self.stored_tattoys_size() > limit {
+            let maybe_stalest = self
+                .interpolations
+                .keys()
+                .min_by_key(|surface_id| self.tattoys_updated_at.get(*surface_id))
+                .cloned();
+            let Some(stalest) = maybe_stalest else {
+                tracing::debug!("The latest tattoy frames alone are over the memory limit");
+                break;
+            };
+            tracing::debug!("Forgetting '{stalest}' previous frame to stay within memory limit");
+            self.interpolations.remove(&stalest);
+        }
+    }
+
     /// Apply the changes to the user's terminal.
     async fn paint(&mut self) -> Result<()> {
+        let started_at = std::time::Instant::now();
         self.composite().await?;
+        let composite_time = started_at.elapsed();
+        self.stream_frame();
 
         let Some(users_terminal) = self.users_terminal.as_mut() else {
+            // Headless frames are only composited, but they're still timed, eg for `tattoy bench`.
+            self.state
+                .performance
+                .write()
+                .await
+                .record_frame(started_at.elapsed(), composite_time);
             return Ok(());
         };
 
@@ -339,16 +671,12 @@ impl Renderer {
         let changes = users_terminal.diff_screens(&self.frame);
         users_terminal.add_changes(changes);
 
-        let (cursor_x, cursor_y) = self.pty.cursor_position();
+        let (cursor_x, cursor_y) = self.displayed_cursor_position();
         users_terminal.add_change(TermwizChange::CursorPosition {
             x: TermwizPosition::Absolute(cursor_x),
             y: TermwizPosition::Absolute(cursor_y),
         });
 
-        if let Some(cursor_shape) = self.pty.cursor_shape() {
-            users_terminal.add_change(TermwizChange::CursorShape(cursor_shape));
-        }
-
         // This avoids flickering at the cost of slower rendering for complex frame updates.
         users_terminal.ignore_high_repaint_cost(true);
 
@@ -358,23 +686,84 @@ impl Renderer {
         ));
 
         // Tattoy can override the PTY's cursor visibility for example when Tattoy is scrolling.
-        if !self.is_cursor_visible {
+        if !self.is_cursor_visible
+            || self.maybe_screensaver_tattoy.is_some()
+            || self.is_showing_splash
+        {
             users_terminal.add_change(TermwizChange::CursorVisibility(
                 termwiz::surface::CursorVisibility::Hidden,
             ));
         }
 
+        // Multiplexers handle synchronized output themselves, if they support it, so it's only used
+        // when Tattoy is talking directly to the user's terminal.
+        let is_synchronized = self.state.capabilities.load().has_synchronized_output
+            && self.state.multiplexers.get().outer.is_none();
+        if is_synchronized {
+            let terminal = users_terminal.terminal();
+            terminal.write_all(crate::capabilities::BEGIN_SYNCHRONIZED_OUTPUT.as_bytes())?;
+        }
+
+        // Only changes to the PTY's cursor shape are sent, so that the user's own cursor style is
+        // left alone until an application changes it.
+        let cursor_shape = self.pty.cursor_shape().unwrap_or_default();
+        if cursor_shape != self.cursor_shape {
+            self.cursor_shape = cursor_shape;
+            let terminal = users_terminal.terminal();
+            terminal.write_all(crate::cursor_style::shape_code(cursor_shape).as_bytes())?;
+        }
+
         // This is where we actually render to the user's real terminal.
         users_terminal.flush()?;
 
+        if is_synchronized {
+            let terminal = users_terminal.terminal();
+            terminal.write_all(crate::capabilities::END_SYNCHRONIZED_OUTPUT.as_bytes())?;
+            terminal.flush()?;
+        }
+
+        self.state
+            .performance
+            .write()
+            .await
+            .record_frame(started_at.elapsed(), composite_time);
+
         Ok(())
     }
 
+    /// Share the composited frame with any web viewers. See `crate::streaming`.
+    fn stream_frame(&self) {
+        if !self.state.streamed_frame.is_subscribed() {
+            return;
+        }
+
+        let is_cursor_visible = self.is_cursor_visible
+            && self.maybe_screensaver_tattoy.is_none()
+            && self.pty.cursor_visibility() == termwiz::surface::CursorVisibility::Visible;
+        self.state.streamed_frame.store(crate::streaming::Frame {
+            surface: self.frame.clone(),
+            cursor: is_cursor_visible.then(|| self.displayed_cursor_position()),
+        });
+    }
+
     // TODO: A failed render shouldn't crash the whole tick.
     /// Composite all the tattoys and the PTY together into a single surface (frame).
     pub async fn composite(&mut self) -> Result<()> {
-        let is_rendering_enabled = *self.state.is_rendering_enabled.read().await;
+        let is_rendering_enabled = self.state.is_rendering_enabled.get();
+        self.default_background_colour = self.state.default_background_colour.get();
+        self.reserved_rows = self.state.reserved_rows.get();
         self.reset_frame();
+        if self.is_showing_splash {
+            let initialised_systems = self.state.initialised_systems.read().await.clone();
+            crate::splash::draw(&mut self.frame, &initialised_systems);
+            return Ok(());
+        }
+        self.exclusions = crate::exclusion_zones::mask(
+            &self.state.config.load().exclusion_zones,
+            &self.pty,
+            self.frame.dimensions(),
+            usize::from(self.reserved_rows.top),
+        );
 
         if is_rendering_enabled {
             self.render_tattoys_below().await?;
@@ -385,23 +774,48 @@ impl Renderer {
         } else {
             self.render_pty().await?;
         }
+        self.predictor
+            .overlay(&mut self.frame, usize::from(self.reserved_rows.top));
 
         if is_rendering_enabled {
             self.render_tattoys_above().await?;
             self.colour_grade().await?;
             self.add_indicator().await?;
             if self.is_cursor_visible {
-                let cursor = self.pty.cursor_position();
+                let cursor = self.displayed_cursor_position();
                 Compositor::clean_cursor_cell(&mut self.frame.screen_cells(), cursor.0, cursor.1);
             }
         }
 
+        if !self.state.is_true_colour.get() {
+            crate::true_colour::quantise_frame(&mut self.frame);
+        }
+
         Ok(())
     }
 
+    /// The position of the PTY's cursor in the user's terminal, which is offset by any rows
+    /// reserved above the PTY.
+    fn cursor_position(
+        pty: &TermwizSurface,
+        reserved_rows: crate::shared_state::ReservedRows,
+    ) -> (usize, usize) {
+        let (x, y) = pty.cursor_position();
+        (x, y + usize::from(reserved_rows.top))
+    }
+
+    /// The position of the cursor in the user's terminal, including any keystrokes that have been
+    /// predictively echoed.
+    fn displayed_cursor_position(&self) -> (usize, usize) {
+        self.predictor.cursor().map_or_else(
+            || Self::cursor_position(&self.pty, self.reserved_rows),
+            |(x, y)| (x, y + usize::from(self.reserved_rows.top)),
+        )
+    }
+
     /// Add the little blue pixel in the top right.
     async fn add_indicator(&mut self) -> Result<()> {
-        if !self.state.config.read().await.show_tattoy_indicator {
+        if !self.state.config.load().show_tattoy_indicator {
             return Ok(());
         }
 
@@ -410,6 +824,7 @@ impl Renderer {
             &self.indicator_cell,
             (self.width - 1).into(),
             0,
+            self.default_background_colour,
         )
     }
 
@@ -430,17 +845,37 @@ impl Renderer {
 
     /// Render a tattoy onto the compositor frame.
     async fn render_tattoys(&mut self, comparator: std::cmp::Ordering) -> Result<()> {
+        // The screensaver's tattoy is lifted above the screensaver's cover.
+        let maybe_lifted = self.maybe_screensaver_tattoy.clone();
+        let layer_of = |tattoy: &crate::surface::Surface| {
+            if maybe_lifted.as_ref() == Some(&tattoy.id) {
+                crate::tattoys::screensaver::TATTOY_LAYER
+            } else {
+                tattoy.layer
+            }
+        };
+
+        let progresses: std::collections::HashMap<String, f32> = self
+            .interpolations
+            .keys()
+            .filter_map(|surface_id| {
+                self.interpolation_progress(surface_id)
+                    .map(|progress| (surface_id.clone(), progress))
+            })
+            .collect();
+
         let mut tattoys: Vec<&mut crate::surface::Surface> = self
             .tattoys
             .values_mut()
-            .filter(|tattoy| tattoy.layer.cmp(&0) == comparator)
+            .filter(|tattoy| layer_of(tattoy).cmp(&0) == comparator)
             .collect();
-        tattoys.sort_by_key(|tattoy| tattoy.layer);
+        tattoys.sort_by_key(|tattoy| layer_of(tattoy));
 
+        let exclusion_zones = self.state.config.load().exclusion_zones.clone();
         let frame_size = self.frame.dimensions();
         let mut frame_cells = self.frame.screen_cells();
         for tattoy in &mut tattoys {
-            if tattoy.id == *"shader" && !self.state.config.read().await.shader.render {
+            if tattoy.id == *"shader" && !self.state.config.load().shader.render {
                 continue;
             }
             let tattoy_frame_size = tattoy.surface.dimensions();
@@ -451,36 +886,119 @@ impl Renderer {
                 );
                 continue;
             }
-            let tattoy_cells = tattoy.surface.screen_cells();
-
-            for (frame_line, tattoy_line) in frame_cells.iter_mut().zip(tattoy_cells) {
-                for (frame_cell, tattoy_cell) in frame_line.iter_mut().zip(tattoy_line) {
-                    Compositor::composite_cells(frame_cell, tattoy_cell, tattoy.opacity);
-                }
+            self.frame_dirty_cells.merge(&tattoy.dirty_cells);
+            let mask = if crate::exclusion_zones::is_masked(&exclusion_zones, &tattoy.id) {
+                self.exclusions.as_slice()
+            } else {
+                &[]
+            };
+            let mut opacity = tattoy.opacity;
+            let maybe_previous = self.interpolations.get_mut(&tattoy.id);
+            if let (Some(previous), Some(progress)) = (maybe_previous, progresses.get(&tattoy.id)) {
+                let (previous_opacity, latest_opacity) =
+                    crate::interpolation::opacities(opacity, *progress);
+                self.frame_dirty_cells.merge(&previous.surface.dirty_cells);
+                Self::composite_surface(
+                    &mut frame_cells,
+                    &mut previous.surface,
+                    previous_opacity,
+                    self.default_background_colour,
+                    frame_size,
+                    mask,
+                )?;
+                opacity = latest_opacity;
             }
+            Self::composite_surface(
+                &mut frame_cells,
+                tattoy,
+                opacity,
+                self.default_background_colour,
+                frame_size,
+                mask,
+            )?;
         }
 
         Ok(())
     }
 
-    /// Render the PTY to the compositor frame.
+    /// Composite a single tattoy surface onto the frame's cells. Cells inside any of the `mask`
+    /// rectangles are left alone.
+    fn composite_surface(
+        frame_cells: &mut [&mut [Cell]],
+        tattoy: &mut crate::surface::Surface,
+        opacity: f32,
+        default_background_colour: termwiz::color::SrgbaTuple,
+        frame_size: (usize, usize),
+        mask: &[crate::exclusion_zones::Rect],
+    ) -> Result<()> {
+        let rows: Vec<_> = frame_cells
+            .iter_mut()
+            .zip(tattoy.surface.screen_cells())
+            .enumerate()
+            .collect();
+        Self::composite_rows(rows, frame_size, |(y, (frame_line, tattoy_line))| {
+            let cells = frame_line.iter_mut().zip(tattoy_line.iter()).enumerate();
+            for (x, (frame_cell, tattoy_cell)) in cells {
+                if mask.iter().any(|rect| rect.contains(x, y)) {
+                    continue;
+                }
+                Compositor::composite_cells(
+                    frame_cell,
+                    tattoy_cell,
+                    opacity,
+                    default_background_colour,
+                );
+            }
+            Ok(())
+        })
+    }
+
+    /// Composite each row of the frame. Rows don't depend on each other, so on big terminals they
+    /// are composited in parallel.
+    fn composite_rows<T, F>(
+        rows: Vec<T>,
+        frame_size: (usize, usize),
+        composite_row: F,
+    ) -> Result<()>
+    where
+        T: Send,
+        F: Fn(T) -> Result<()> + Send + Sync,
+    {
+        if frame_size.0 * frame_size.1 >= PARALLEL_COMPOSITING_MIN_CELLS {
+            rows.into_par_iter().try_for_each(composite_row)
+        } else {
+            rows.into_iter().try_for_each(composite_row)
+        }
+    }
+
+    /// Render the PTY to the compositor frame. The PTY's cells are borrowed straight from its
+    /// shared snapshot.
     async fn render_pty(&mut self) -> Result<()> {
         let frame_size = self.frame.dimensions();
         let mut frame_cells = self.frame.screen_cells();
 
-        let pty_size = self.pty.dimensions();
-        let pty_cells = self.pty.screen_cells();
+        let pty = Arc::clone(&self.pty);
+        let pty_size = pty.dimensions();
 
-        if pty_size != frame_size {
+        let reserved_height = usize::from(self.reserved_rows.total());
+        if pty_size != (frame_size.0, frame_size.1.saturating_sub(reserved_height)) {
             tracing::warn!("Not rendering PTY as its size doesn't match the current frame size");
             return Ok(());
         }
+        let top = usize::from(self.reserved_rows.top);
 
-        let config = self.state.config.read().await;
+        let config = self.state.config.load();
         let text_contrast = config.text_contrast.clone();
         let apply_to_readable_text_only = config.text_contrast.apply_to_readable_text_only;
         let render_shader_colours_to_text = config.shader.render_shader_colours_to_text;
+        let shader_mask = if crate::exclusion_zones::is_masked(&config.exclusion_zones, "shader") {
+            self.exclusions.clone()
+        } else {
+            Vec::new()
+        };
         drop(config);
+        let is_rendering_enabled = self.state.is_rendering_enabled.get();
+        let default_background_colour = self.default_background_colour;
 
         let maybe_shader_cells = if render_shader_colours_to_text {
             Self::get_shader_cells(self.tattoys.get_mut("shader"), frame_size)
@@ -488,30 +1006,87 @@ impl Renderer {
             None
         };
 
-        for (y, (frame_line, pty_line)) in frame_cells.iter_mut().zip(pty_cells).enumerate() {
-            for (x, (frame_cell, pty_cell)) in frame_line.iter_mut().zip(pty_line).enumerate() {
-                Compositor::composite_cells(frame_cell, pty_cell, 1.0);
+        let finish_cell = |frame_cell: &mut Cell, x: usize, y: usize| -> Result<()> {
+            if !is_rendering_enabled {
+                return Ok(());
+            }
 
-                if !*self.state.is_rendering_enabled.read().await {
-                    continue;
-                }
+            let is_masked = shader_mask.iter().any(|rect| rect.contains(x, y + top));
+            if let Some(shader_cells) = maybe_shader_cells.as_ref().filter(|_| !is_masked) {
+                let shader_cell = Compositor::get_cell(shader_cells, x, y + top)?;
+                Compositor::composite_fg_colour_only(
+                    frame_cell,
+                    shader_cell,
+                    default_background_colour,
+                );
+            }
 
-                if let Some(shader_cells) = maybe_shader_cells.as_ref() {
-                    let shader_cell = Compositor::get_cell(shader_cells, x, y)?;
-                    Compositor::composite_fg_colour_only(frame_cell, shader_cell);
-                }
+            if text_contrast.enabled {
+                Compositor::auto_text_contrast(
+                    frame_cell,
+                    text_contrast.target_contrast,
+                    apply_to_readable_text_only,
+                    default_background_colour,
+                );
+            }
+            Ok(())
+        };
 
-                if text_contrast.enabled {
-                    Compositor::auto_text_contrast(
+        let blank_attributes = CellAttributes::default();
+        let rows: Vec<_> = frame_cells
+            .iter_mut()
+            .zip(self.frame_dirty_cells.rows_mut())
+            .skip(top)
+            .zip(pty.screen_lines())
+            .enumerate()
+            .collect();
+        Self::composite_rows(
+            rows,
+            frame_size,
+            |(y, ((frame_line, dirty_row), pty_line))| {
+                for pty_cell in pty_line.visible_cells() {
+                    let x = pty_cell.cell_index();
+                    let Some(frame_cell) = frame_line.get_mut(x) else {
+                        break;
+                    };
+                    Compositor::composite_cells(
                         frame_cell,
-                        text_contrast.target_contrast,
-                        apply_to_readable_text_only,
+                        &pty_cell,
+                        1.0,
+                        default_background_colour,
                     );
-                }
-            }
-        }
+                    let is_blank = pty_cell.str() == " " && pty_cell.attrs() == &blank_attributes;
+                    if !is_blank {
+                        for is_dirty in dirty_row.iter_mut().skip(x).take(pty_cell.width()) {
+                            *is_dirty = true;
+                        }
+                    }
+                    if dirty_row.get(x).copied().unwrap_or_default() {
+                        finish_cell(frame_cell, x, y)?;
+                    }
 
-        Ok(())
+                    // The columns covered by wide characters, like emojis, are blank.
+                    if pty_cell.width() > 1 {
+                        let spacer = Cell::blank_with_attrs(pty_cell.attrs().clone());
+                        for spacer_x in x + 1..x + pty_cell.width() {
+                            let Some(frame_cell) = frame_line.get_mut(spacer_x) else {
+                                break;
+                            };
+                            Compositor::composite_cells(
+                                frame_cell,
+                                &spacer,
+                                1.0,
+                                default_background_colour,
+                            );
+                            if dirty_row.get(spacer_x).copied().unwrap_or_default() {
+                                finish_cell(frame_cell, spacer_x, y)?;
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
     }
 
     /// If there's a shader frame then get it.
@@ -538,38 +1113,60 @@ impl Renderer {
     }
 
     /// Fetch the freshly made PTY frame from the shared state.
-    async fn get_updated_pty_frame(&mut self) {
-        self.pty.resize(self.width.into(), self.height.into());
-        let surface = self.state.shadow_tty_screen.read().await;
-        let (cursor_x, cursor_y) = surface.cursor_position();
-        self.pty = surface.clone();
-        drop(surface);
-
-        self.pty.add_change(TermwizChange::CursorPosition {
-            x: TermwizPosition::Absolute(cursor_x),
-            y: TermwizPosition::Absolute(cursor_y),
-        });
+    fn get_updated_pty_frame(&mut self) {
+        let previous_cursor = Self::cursor_position(&self.pty, self.reserved_rows);
+        self.pty = self.state.shadow_tty_screen.load();
+        self.predictor.reconcile(&self.pty);
+
+        let cursor = Self::cursor_position(&self.pty, self.reserved_rows);
+        if previous_cursor != cursor {
+            let movement = CursorMovement {
+                from: previous_cursor,
+                to: cursor,
+                at: tokio::time::Instant::now(),
+            };
+            let result = self
+                .state
+                .protocol_tx
+                .send(crate::run::Protocol::CursorMoved(movement));
+            if let Err(error) = result {
+                tracing::error!("Couldn't broadcast cursor movement: {error:?}");
+            }
+        }
     }
 
     /// Apply colour changes, like saturation, hue, contrast, etc.
     //
     // TODO: consider including this in the final compositing layer, just for the performance
     // gain of not having to iterate over every cell again.
-    async fn colour_grade(&mut self) -> Result<()> {
-        let config = self.state.config.read().await;
+    pub(crate) async fn colour_grade(&mut self) -> Result<()> {
+        let config = self.state.config.load();
 
         let saturation: f64 = config.color.saturation.into();
         let light: f64 = config.color.brightness.into();
         let hue: f64 = config.color.hue.into();
+        let vision_filter = config.color.vision_filter;
+        // The CPU-only CRT effect approximates the glow of the phosphor by brightening the text.
+        let glow: f64 = if config.crt.enabled {
+            config.crt.glow.into()
+        } else {
+            0.0
+        };
         drop(config);
 
-        for line in &mut self.frame.screen_cells().iter_mut() {
-            for cell in line.iter_mut() {
+        for (y, line) in self.frame.screen_cells().iter_mut().enumerate() {
+            for (x, cell) in line.iter_mut().enumerate() {
+                if !self.frame_dirty_cells.is_dirty(x, y) {
+                    continue;
+                }
+
                 let foreground = cell.attrs().foreground();
                 if let Some(mut gradable) = crate::blender::Blender::extract_colour(foreground) {
                     gradable = gradable.saturate(saturation);
                     gradable = gradable.lighten(light);
                     gradable = gradable.adjust_hue_fixed(hue);
+                    gradable = vision_filter.apply(gradable);
+                    gradable = gradable.lighten(glow);
                     cell.attrs_mut().set_foreground(
                         termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(gradable),
                     );
@@ -580,6 +1177,7 @@ impl Renderer {
                     gradable = gradable.saturate(saturation);
                     gradable = gradable.lighten(light);
                     gradable = gradable.adjust_hue_fixed(hue);
+                    gradable = vision_filter.apply(gradable);
                     cell.attrs_mut().set_background(
                         termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(gradable),
                     );
@@ -590,3 +1188,40 @@ impl Renderer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_evicting_previous_frames() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
+        let state = crate::shared_state::SharedState::init(400, 300, protocol_tx)
+            .await
+            .unwrap();
+        state
+            .config
+            .modify(|config| config.max_tattoy_surfaces_size = 1);
+        let mut renderer = Renderer::new(state, false).await.unwrap();
+
+        for surface_id in ["clock", "shader"] {
+            let surface = crate::surface::Surface::new(surface_id.into(), 400, 300, 1, 1.0);
+            renderer.tattoys.insert(surface_id.into(), surface);
+            renderer
+                .tattoys_updated_at
+                .insert(surface_id.into(), std::time::Instant::now());
+        }
+        renderer.interpolations.insert(
+            "shader".into(),
+            crate::interpolation::Previous {
+                surface: crate::surface::Surface::new("shader".into(), 400, 300, 1, 1.0),
+                interval: std::time::Duration::from_secs(1),
+            },
+        );
+
+        renderer.evict_previous_frames();
+        assert!(renderer.interpolations.is_empty());
+        assert!(renderer.tattoys.contains_key("clock"));
+        assert!(renderer.tattoys.contains_key("shader"));
+    }
+}
@@ -4,6 +4,7 @@ use std::sync::Arc;
 
 use clap::Parser as _;
 use color_eyre::eyre::{ContextCompat as _, Result};
+use futures_util::FutureExt as _;
 use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _, Layer as _};
 
 use crate::cli_args::CliArgs;
@@ -44,14 +45,139 @@ pub(crate) enum Protocol {
     Input(crate::raw_input::ParsedInput),
     /// The visibility of the end user's cursor.
     CursorVisibility(bool),
-    /// Tattoy's configuration.
-    Config(crate::config::main::Config),
+    /// Tattoy's configuration has changed.
+    Config {
+        /// The complete new config.
+        config: crate::config::main::Config,
+        /// The sections of the config that are different from the previous config.
+        changes: Vec<crate::config::diff::Section>,
+    },
     /// A known user-defined keybinding event was triggered.
     KeybindEvent(crate::config::input::KeybindingAction),
     /// User notifications in the the UI.
     Notification(crate::tattoys::notifications::message::Message),
     /// Force a repaint.
     Repaint,
+    /// The terminal's palette has changed, eg whilst it's being edited.
+    Palette(crate::palette::converter::Palette),
+    /// Send a query, like an OSC colour query, directly to the end user's terminal. The replies
+    /// arrive on STDIN.
+    QueryTerminal(String),
+    /// The PTY rang the terminal's bell.
+    Bell,
+    /// The shell reported a new working directory.
+    WorkingDirectory(std::path::PathBuf),
+    /// The shell reported that a command started running.
+    CommandStarted,
+    /// The shell reported that a command finished running, with its exit code if it was reported.
+    CommandFinished(Option<i32>),
+    /// The renderer painted the cursor in a new position.
+    CursorMoved(crate::renderer::CursorMovement),
+    /// The screensaver started (`true`) or the terminal was woken up (`false`).
+    Screensaver(bool),
+    /// Rows of the user's terminal were reserved, or released, so the PTY needs resizing.
+    ReservedRows(crate::shared_state::ReservedRows),
+    /// The scrollback's bookmarks changed, or one of them was jumped to.
+    Bookmarks(Vec<shadow_terminal::output::Bookmark>),
+    /// A tattoy wants to scroll so that the given number of lines of the scrollback are below the
+    /// bottom of the terminal, eg when the minimap is clicked.
+    ScrollTo(usize),
+    /// Replace the command running in the PTY with the given command, or with the configured
+    /// `command` when none is given. The scrollback and tattoys are kept.
+    RespawnCommand(Option<String>),
+    /// An application changed the PTY's cursor colour, or reset it to the default when `None`.
+    CursorColour(Option<termwiz::color::SrgbaTuple>),
+    /// Temporarily show the given shader, without changing the config, eg whilst it's selected in
+    /// the shader browser. `None` goes back to the configured shader.
+    PreviewShader(Option<std::path::PathBuf>),
+}
+
+/// Run one of the CLI subcommands.
+async fn run_subcommand(
+    state: &std::sync::Arc<SharedState>,
+    subcommand: crate::cli_args::Subcommand,
+) -> Result<()> {
+    match subcommand {
+        crate::cli_args::Subcommand::Palette { action } => match action {
+            crate::cli_args::PaletteAction::Import { path } => {
+                crate::palette::base16::import(state, &path).await
+            }
+            crate::cli_args::PaletteAction::Export { format, output } => {
+                crate::palette::export::export(state, format, output.as_deref()).await
+            }
+        },
+        crate::cli_args::Subcommand::Notify {
+            title,
+            body,
+            level,
+            socket,
+        } => {
+            let request = crate::control::Request::Notify { title, body, level };
+            crate::control::run_cli_request(&request, socket).await
+        }
+        crate::cli_args::Subcommand::Doctor { latency: true, .. } => crate::latency::run().await,
+        crate::cli_args::Subcommand::Doctor { bundle, .. } => {
+            crate::doctor::run(state, bundle).await
+        }
+        crate::cli_args::Subcommand::Ctl { command, socket } => {
+            let request = match command {
+                crate::cli_args::CtlCommand::Toggle { tattoy } => {
+                    crate::control::Request::Toggle { tattoy }
+                }
+                crate::cli_args::CtlCommand::Opacity { tattoy, opacity } => {
+                    crate::control::Request::SetOpacity { tattoy, opacity }
+                }
+                crate::cli_args::CtlCommand::Shader { path } => {
+                    crate::control::Request::SwitchShader { path }
+                }
+                crate::cli_args::CtlCommand::Profile { name } => {
+                    crate::control::Request::SwitchProfile { name }
+                }
+                crate::cli_args::CtlCommand::Reload { reset } => {
+                    crate::control::Request::ReloadConfig { reset }
+                }
+                crate::cli_args::CtlCommand::Status => crate::control::Request::Status,
+                crate::cli_args::CtlCommand::Stats => crate::control::Request::Stats,
+                crate::cli_args::CtlCommand::Get { key } => crate::control::Request::Get { key },
+                crate::cli_args::CtlCommand::Respawn { command } => {
+                    let command = (!command.is_empty()).then(|| command.join(" "));
+                    crate::control::Request::Respawn { command }
+                }
+            };
+            crate::control::run_cli_request(&request, socket).await
+        }
+        crate::cli_args::Subcommand::Run { .. } => {
+            color_eyre::eyre::bail!("Unreachable: `tattoy run` starts Tattoy itself")
+        }
+        crate::cli_args::Subcommand::Bench { .. } => {
+            color_eyre::eyre::bail!("Unreachable: `tattoy bench` starts Tattoy itself")
+        }
+        crate::cli_args::Subcommand::ShellIntegration { command } => match command {
+            crate::cli_args::ShellIntegrationCommand::Install { shell, print } => {
+                crate::shell_integration::install(shell, print)
+            }
+        },
+        crate::cli_args::Subcommand::Plugin { command } => match command {
+            crate::cli_args::PluginCommand::New { name, lang, path } => {
+                crate::plugin_template::create(&name, lang, path)
+            }
+        },
+        crate::cli_args::Subcommand::Shader { command } => match command {
+            crate::cli_args::ShaderCommand::Browse { socket } => {
+                crate::control::run_cli_request(&crate::control::Request::BrowseShaders, socket)
+                    .await
+            }
+            crate::cli_args::ShaderCommand::Install { url } => {
+                crate::tattoys::shaders::install::install_from_cli(state, &url).await
+            }
+        },
+        crate::cli_args::Subcommand::ExportSetup { output } => {
+            crate::setup_archive::export(state, output).await
+        }
+        crate::cli_args::Subcommand::ImportSetup { archive, force } => {
+            crate::setup_archive::import(state, &archive, force).await
+        }
+    }
 }
 
 /// Main entrypoint
@@ -67,14 +193,52 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
         std::process::exit(0);
     }
 
+    if let Some(crate::cli_args::Subcommand::Run {
+        once: true,
+        batch,
+        command,
+    }) = cli_args.subcommand.clone()
+    {
+        crate::batch::run(state_arc, &batch, command, cli_args.enabled_tattoys.clone()).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if let Some(crate::cli_args::Subcommand::Bench { options, command }) =
+        cli_args.subcommand.clone()
+    {
+        crate::bench::run(state_arc, &options, command, cli_args.enabled_tattoys.clone()).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if let Some(subcommand) = cli_args
+        .subcommand
+        .clone()
+        .filter(|subcommand| !matches!(subcommand, crate::cli_args::Subcommand::Run { .. }))
+    {
+        run_subcommand(state_arc, subcommand).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
     if let Some(screenshot) = cli_args.parse_palette {
         crate::palette::parser::Parser::run(state_arc, Some(&screenshot)).await?;
         #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
         std::process::exit(0);
     }
 
+    // Must be detected before the palette, so that palette queries can get through to the user's
+    // terminal.
+    let command = get_startup_command(state_arc, cli_args.clone()).await?;
+    crate::multiplexer::detect(state_arc, &command);
+    crate::capabilities::detect(state_arc, cli_args.probe_terminal)?;
+
     if !palette_config_exists {
-        crate::palette::parser::Parser::run(state_arc, None).await?;
+        let is_detected = crate::palette::osc::detect_and_save(state_arc).await?;
+        if !is_detected {
+            crate::palette::parser::Parser::run(state_arc, None).await?;
+        }
     }
 
     let users_tty_size = crate::renderer::Renderer::get_users_tty_size()?;
@@ -85,36 +249,55 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
         )
         .await;
 
-    let (renderer, surfaces_tx) = Renderer::start(Arc::clone(state_arc), protocol_tx.clone());
+    override_on_panic_behaviour();
+    let (renderer, surfaces_tx) = Renderer::start(Arc::clone(state_arc), protocol_tx.clone(), true);
 
     let config_handle = crate::config::main::Config::watch(Arc::clone(state_arc));
-    let input_thread_handle = RawInput::start(protocol_tx.clone());
+    let power_handle = crate::power::watch(Arc::clone(state_arc));
+    let hooks_handle = crate::hooks::start(Arc::clone(state_arc));
+    let metrics_handle = crate::metrics::start(Arc::clone(state_arc)).await?;
+    let streaming_handle = crate::streaming::start(Arc::clone(state_arc)).await?;
+    let input_thread_handle = RawInput::start(Arc::clone(state_arc));
 
-    override_on_panic_behaviour();
     let tattoys_handle = crate::loader::start_tattoys(
         cli_args.enabled_tattoys.clone(),
         surfaces_tx.clone(),
         Arc::clone(state_arc),
     );
 
-    let scrollback_size = state_arc.config.read().await.scrollback_size;
+    // Must be started before the shadow terminal, so that the user's shell inherits the socket's
+    // path.
+    let control_handle = crate::control::start(Arc::clone(state_arc))?;
+
+    let scrollback_size = state_arc.config.load().scrollback_size;
     let shadow_terminal_config = shadow_terminal::shadow_terminal::Config {
         width: users_tty_size.cols.try_into()?,
         height: users_tty_size.rows.try_into()?,
-        command: get_startup_command(state_arc, cli_args).await?,
+        command,
         scrollback_size: scrollback_size.try_into()?,
         ..Default::default()
     };
-    crate::terminal_proxy::proxy::Proxy::start(
+    // Even if the proxy fails, everything else still needs to be shutdown cleanly, especially the
+    // renderer, which restores the user's terminal.
+    let proxy_result = std::panic::AssertUnwindSafe(crate::terminal_proxy::proxy::Proxy::start(
         Arc::clone(state_arc),
         surfaces_tx,
         protocol_tx.clone(),
         shadow_terminal_config,
-    )
-    .await?;
+    ))
+    .catch_unwind()
+    .await
+    .unwrap_or_else(|payload| {
+        Err(color_eyre::eyre::eyre!(
+            "Terminal proxy panicked: {}",
+            panic_message(&*payload)
+        ))
+    });
     tracing::debug!("🏁 left PTY thread, exiting Tattoy...");
     broadcast_protocol_end(&protocol_tx);
 
+    renderer.await??;
+    proxy_result?;
     tattoys_handle
         .join()
         .map_err(|err| color_eyre::eyre::eyre!("Tattoys handle: {err:?}"))??;
@@ -125,8 +308,12 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
             .join()
             .map_err(|err| color_eyre::eyre::eyre!("STDIN handle: {err:?}"))??;
     }
-    renderer.await??;
     config_handle.await??;
+    power_handle.await??;
+    hooks_handle.await??;
+    metrics_handle.await??;
+    streaming_handle.await??;
+    control_handle.await??;
 
     tracing::trace!("Leaving Tattoy's main `run()` function");
     Ok(())
@@ -159,21 +346,14 @@ pub(crate) async fn wait_for_system(state: &Arc<crate::shared_state::SharedState
     tracing::debug!("...{system} system initialised.");
 }
 
-/// The default behaviour prints all panics to the CLI. But we don't want that to happen in
-/// tattoy tasks. However `set_hook` globally changes behaviour, therefore it doesn't allow things
-/// like only changing behaviour for a block. So we want this to be called as late as posssible so
-/// it only affects tattoy tasks. Currently the only main-thread system that we'd want to see
-/// panics for, is the Shadow Terminal. At least a log is made. But it would be good to figure out
-/// a way to notify developers especially, that the Shadow Terminal panicked.
+/// The default behaviour prints all panics to the CLI. But that would corrupt the user's terminal,
+/// which is in raw mode whilst Tattoy is running, and panics in tattoy tasks shouldn't be shown to
+/// the user anyway. So panics are only logged. The renderer, the terminal proxy and the STDIN
+/// thread catch their own panics and return them as errors. They're then printed once the user's
+/// terminal has been restored to cooked mode.
 fn override_on_panic_behaviour() {
     std::panic::set_hook(Box::new(|info| {
-        let message = if let Some(message) = info.payload().downcast_ref::<String>() {
-            message
-        } else if let Some(message) = info.payload().downcast_ref::<&str>() {
-            message
-        } else {
-            "Caught a panic with an unknown type."
-        };
+        let message = panic_message(info.payload());
         let location = match info.location() {
             Some(location) => format!(
                 "{}@{}:{}",
@@ -187,15 +367,32 @@ fn override_on_panic_behaviour() {
     }));
 }
 
+/// Get the message from a panic's payload. Panics made with `panic!` or `.expect()` have either a
+/// `String` or a `&str` payload.
+pub(crate) fn panic_message(payload: &(dyn core::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else {
+        "Caught a panic with an unknown type."
+    }
+}
+
 /// Get the command that Tattoy will use to startup, usually something like `bash`.
 async fn get_startup_command(
     state: &std::sync::Arc<SharedState>,
     cli_args: CliArgs,
 ) -> Result<Vec<std::ffi::OsString>> {
+    if let Some(crate::cli_args::Subcommand::Run { command, .. }) = cli_args.subcommand {
+        tracing::debug!("Starting Tattoy with command: '{command:?}'");
+        return Ok(command.into_iter().map(std::convert::Into::into).collect());
+    }
+
     let maybe_cli_command = cli_args.command;
     let command = match maybe_cli_command {
         Some(cli_command) => cli_command,
-        None => state.config.read().await.command.clone(),
+        None => state.config.load().command.clone(),
     };
 
     let parts = command
@@ -224,16 +421,61 @@ pub(crate) fn broadcast_protocol_end(protocol_tx: &tokio::sync::broadcast::Sende
 async fn setup(state: &std::sync::Arc<SharedState>) -> Result<CliArgs> {
     let cli_args = CliArgs::parse();
 
+    // Notifications and controls are sent to an already running Tattoy, so they don't need any
+    // setup. They can even be sent from outside of a terminal. Shell integration only concerns the
+    // shell's own config, and new plugins are just files.
+    if let Some(
+        subcommand @ (crate::cli_args::Subcommand::Notify { .. }
+        | crate::cli_args::Subcommand::Ctl { .. }
+        | crate::cli_args::Subcommand::ShellIntegration { .. }
+        | crate::cli_args::Subcommand::Plugin { .. }),
+    ) = cli_args.subcommand.clone()
+    {
+        run_subcommand(state, subcommand).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
     let mut main_config_file = state.main_config_file.write().await;
     (*main_config_file).clone_from(&cli_args.main_config);
     drop(main_config_file);
 
+    (*state.active_profile.write().await).clone_from(&cli_args.profile);
+
     let directory_result =
         crate::config::main::Config::setup_directory(cli_args.config_dir.clone(), state).await;
     if let Err(directory_error) = directory_result {
         color_eyre::eyre::bail!("Error setting up config directory: {directory_error:?}");
     }
 
+    let data_directory_result =
+        crate::config::main::Config::setup_data_directory(cli_args.data_dir.clone(), state).await;
+    if let Err(directory_error) = data_directory_result {
+        color_eyre::eyre::bail!("Error setting up data directory: {directory_error:?}");
+    }
+
+    // The doctor checks the environment that Tattoy was started in, so it runs before the rest of
+    // the setup changes it. It also reports a bad config, rather than exiting because of it.
+    // Setups are moved before the config is loaded too, so that a broken config can still be
+    // exported, or replaced by an import.
+    if let Some(
+        subcommand @ (crate::cli_args::Subcommand::Doctor { .. }
+        | crate::cli_args::Subcommand::ExportSetup { .. }
+        | crate::cli_args::Subcommand::ImportSetup { .. }),
+    ) = cli_args.subcommand.clone()
+    {
+        run_subcommand(state, subcommand).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    // Must be detected before `COLORTERM` is overridden below, and before the config is loaded,
+    // because the config is reduced when true colour isn't supported.
+    state
+        .is_true_colour
+        .store(crate::true_colour::is_supported());
+
+    crate::runtime_state::restore(state).await;
     let config_result = crate::config::main::Config::load_config_into_shared_state(state).await;
     if let Err(config_error) = config_result {
         let path = crate::config::main::Config::main_config_path(state).await;
@@ -244,18 +486,22 @@ async fn setup(state: &std::sync::Arc<SharedState>) -> Result<CliArgs> {
     }
 
     setup_logging(cli_args.clone(), state).await?;
+    use_remote_profile_if_needed(&cli_args, state).await?;
 
     if cli_args.disable_indicator {
-        state.config.write().await.show_tattoy_indicator = false;
+        state
+            .config
+            .modify(|config| config.show_tattoy_indicator = false);
     }
 
     // Assuming true colour makes Tattoy simpler.
     // * I think it's safe to assume that the vast majority of people using Tattoy will have a
     //   true color terminal anyway.
+    // * Terminals without true colour still work, because the renderer quantises every frame.
     std::env::set_var("COLORTERM", "truecolor");
 
     tracing::info!("Starting Tattoy");
-    tracing::debug!("Loaded config: {:?}", state.config.read().await);
+    tracing::debug!("Loaded config: {:?}", state.config.load());
 
     let tty_size = crate::renderer::Renderer::get_users_tty_size()?;
     state
@@ -265,16 +511,62 @@ async fn setup(state: &std::sync::Arc<SharedState>) -> Result<CliArgs> {
     Ok(cli_args)
 }
 
-/// Setup logging
+/// Automatically switch to the reduced-effects profile when running in a remote session. Doesn't
+/// override a profile that was explicitly chosen on the CLI.
+async fn use_remote_profile_if_needed(
+    cli_args: &CliArgs,
+    state: &std::sync::Arc<SharedState>,
+) -> Result<()> {
+    if cli_args.profile.is_some() || !state.config.load().auto_remote_profile {
+        return Ok(());
+    }
+    if !crate::config::profiles::is_remote_session() {
+        return Ok(());
+    }
+
+    tracing::info!("Remote session detected, using the reduced-effects profile");
+    *state.active_profile.write().await =
+        Some(crate::config::profiles::REMOTE_PROFILE_NAME.to_owned());
+    crate::config::main::Config::load_config_into_shared_state(state).await?;
+    Ok(())
+}
+
+/// The layer that writes logs to the log file.
+type LogfileLayer =
+    Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + Send + Sync + 'static>;
+
+/// Setup logging. Warnings and errors are always captured for the log viewer, even when logging
+/// to a file is disabled.
 async fn setup_logging(cli_args: CliArgs, state: &std::sync::Arc<SharedState>) -> Result<()> {
+    let maybe_logfile_layer = logfile_layer(cli_args, state).await?;
+    let capture_layer = crate::tattoys::log_viewer::capture_layer(Arc::clone(&state.recent_logs));
+    let tracing_setup = tracing_subscriber::registry()
+        .with(maybe_logfile_layer)
+        .with(capture_layer);
+
+    if std::env::var_os("ENABLE_TOKIO_CONSOLE") == Some("1".into()) {
+        let console_layer = console_subscriber::spawn();
+        tracing_setup.with(console_layer).init();
+    } else {
+        tracing_setup.init();
+    }
+
+    Ok(())
+}
+
+/// Setup logging to a file, if it's enabled.
+async fn logfile_layer(
+    cli_args: CliArgs,
+    state: &std::sync::Arc<SharedState>,
+) -> Result<Option<LogfileLayer>> {
     let are_log_filters_manually_set = std::env::var("TATTOY_LOG").is_ok();
-    let mut path = state.config.read().await.log_path.clone();
+    let mut path = state.config.load().log_path.clone();
 
     if let Some(cli_override_path) = cli_args.log_path {
         path = cli_override_path;
     }
 
-    let mut level = state.config.read().await.log_level.clone();
+    let mut level = state.config.load().log_level.clone();
     if let Some(cli_override_level) = cli_args.log_level {
         level = cli_override_level;
     }
@@ -284,12 +576,27 @@ async fn setup_logging(cli_args: CliArgs, state: &std::sync::Arc<SharedState>) -
         !matches!(level, crate::config::main::LogLevel::Off) || are_log_filters_manually_set;
 
     if !is_loggable {
-        return Ok(());
+        return Ok(None);
     }
 
     let directory = path.parent().context("Couldn't get log path's parent")?;
     std::fs::create_dir_all(directory)?;
-    let file = std::fs::File::create(path)?;
+    let (max_size, max_files, is_per_session, format) = {
+        let config = state.config.load();
+        (
+            config.log_max_size,
+            config.log_max_files,
+            config.log_per_session,
+            config.log_format,
+        )
+    };
+    if is_per_session {
+        path = crate::log_file::session_path(&path, chrono::Local::now(), std::process::id());
+    }
+    let file = crate::log_file::RotatingFile::create(path.clone(), max_size, max_files)?;
+    if is_per_session {
+        crate::log_file::remove_old_sessions(&path, max_files.saturating_add(1))?;
+    }
 
     let filters = if are_log_filters_manually_set {
         if let Ok(user_filters) = std::env::var("TATTOY_LOG") {
@@ -312,24 +619,23 @@ async fn setup_logging(cli_args: CliArgs, state: &std::sync::Arc<SharedState>) -
             .add_directive(format!("tests={level_as_string}").parse()?)
     };
 
-    let logfile_layer = tracing_subscriber::fmt::layer()
-        .with_writer(file)
-        .with_filter(filters);
-
-    let tracing_setup = tracing_subscriber::registry().with(logfile_layer);
-
-    if std::env::var_os("ENABLE_TOKIO_CONSOLE") == Some("1".into()) {
-        let console_layer = console_subscriber::spawn();
-        tracing_setup.with(console_layer).init();
-    } else {
-        tracing_setup.init();
-    }
+    let writer = std::sync::Mutex::new(file);
+    let format_layer = match format {
+        crate::config::main::LogFormat::Text => {
+            tracing_subscriber::fmt::layer().with_writer(writer).boxed()
+        }
+        crate::config::main::LogFormat::Json => tracing_subscriber::fmt::layer()
+            .event_format(crate::log_file::JsonFormat)
+            .with_writer(writer)
+            .boxed(),
+    };
 
     let mut is_logging = state.is_logging.write().await;
     *is_logging = true;
     drop(is_logging);
+    *state.log_file.write().await = path;
 
-    Ok(())
+    Ok(Some(format_layer.with_filter(filters).boxed()))
 }
 
 /// Ensure that Tattoy isn't run inside another Tattoy session, unless explicitly desired.
@@ -338,12 +644,16 @@ async fn setup_logging(cli_args: CliArgs, state: &std::sync::Arc<SharedState>) -
     clippy::exit,
     reason = "This is a valid exit point."
 )]
+#[inline]
 pub fn check_for_tattoy_in_tattoy() {
     let is_running_key = "TATTOY_RUNNING";
     let allow_nested_tattoy = "TATTOY_NEST";
     let is_running = std::env::var(is_running_key).is_ok();
     let is_nesting_allowed = std::env::var(allow_nested_tattoy).unwrap_or_default() == "allow";
-    if is_running && !is_nesting_allowed {
+    // Subcommands, like `tattoy ctl`, don't start another Tattoy, they're meant to be run inside
+    // of one.
+    let is_subcommand = CliArgs::try_parse().is_ok_and(|cli_args| cli_args.subcommand.is_some());
+    if is_running && !is_nesting_allowed && !is_subcommand {
         eprintln!(
             "You're trying to run Tattoy inside Tattoy. Whilst this is possible, \
              it can cause issues. If you're sure this is what you want to do then \
@@ -354,3 +664,23 @@ pub fn check_for_tattoy_in_tattoy() {
 
     std::env::set_var(is_running_key, "1");
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn panic_messages() {
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static");
+
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "formatted 1");
+
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(1)).unwrap_err();
+        assert_eq!(
+            panic_message(&*payload),
+            "Caught a panic with an unknown type."
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Changes that the user makes whilst Tattoy is running, like toggling tattoys or cycling through
+//! shaders, are remembered for the next time Tattoy starts. They're kept in their own file, next to
+//! the main config, and merged over the top of the config, so that editing the config file doesn't
+//! clobber them. `tattoy ctl reload --reset` forgets them.
+
+use color_eyre::eyre::Result;
+
+/// The name of the file that runtime changes are saved to, in the config directory.
+pub(crate) const RUNTIME_STATE_FILE_NAME: &str = "runtime_state.toml";
+
+/// Everything that is remembered between sessions.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+struct RuntimeState {
+    /// Whether all of Tattoy's rendering is enabled, see the `toggle_tattoy` keybinding.
+    is_rendering_enabled: bool,
+    /// Config that was changed whilst Tattoy was running, in the same format as the main config.
+    overrides: toml::Table,
+}
+
+impl Default for RuntimeState {
+    fn default() -> Self {
+        Self {
+            is_rendering_enabled: true,
+            overrides: toml::Table::new(),
+        }
+    }
+}
+
+/// The path to the runtime state file.
+pub(crate) async fn path(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+) -> std::path::PathBuf {
+    crate::config::main::Config::directory(state)
+        .await
+        .join(RUNTIME_STATE_FILE_NAME)
+}
+
+/// Restore the runtime changes from the previous session. Must be called before the config is
+/// first loaded.
+pub(crate) async fn restore(state: &std::sync::Arc<crate::shared_state::SharedState>) {
+    let path = path(state).await;
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let runtime_state = match toml::from_str::<RuntimeState>(&contents) {
+        Ok(runtime_state) => runtime_state,
+        Err(error) => {
+            tracing::warn!(
+                "Ignoring bad runtime state in {}: {error:?}",
+                path.display()
+            );
+            return;
+        }
+    };
+
+    tracing::debug!("Restoring runtime state: {runtime_state:?}");
+    state
+        .is_rendering_enabled
+        .store(runtime_state.is_rendering_enabled);
+    *state.config_overrides.write().await = runtime_state.overrides;
+}
+
+/// Save the current runtime changes, so that they're restored next time. Failing to save isn't
+/// worth interrupting the user for, so errors are only logged.
+pub(crate) async fn save(state: &std::sync::Arc<crate::shared_state::SharedState>) {
+    let runtime_state = RuntimeState {
+        is_rendering_enabled: state.is_rendering_enabled.get(),
+        overrides: state.config_overrides.read().await.clone(),
+    };
+    let path = path(state).await;
+    if let Err(error) = write(&path, &runtime_state) {
+        tracing::warn!("Saving runtime state to {}: {error:?}", path.display());
+    }
+}
+
+/// Write the runtime state file.
+fn write(path: &std::path::Path, runtime_state: &RuntimeState) -> Result<()> {
+    std::fs::write(path, toml::to_string(runtime_state)?)?;
+    Ok(())
+}
+
+/// Override a single setting of the config, until it's reset. `table` is the name of one of the
+/// config's sections, eg `minimap`.
+pub(crate) async fn override_setting(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    table: &str,
+    key: &str,
+    value: toml::Value,
+) {
+    let mut setting = toml::Table::new();
+    setting.insert(key.to_owned(), value);
+    let mut overrides = toml::Table::new();
+    overrides.insert(table.to_owned(), toml::Value::Table(setting));
+
+    crate::config::profiles::merge(&mut *state.config_overrides.write().await, overrides);
+    save(state).await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_tripping() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join(RUNTIME_STATE_FILE_NAME);
+        let overrides = toml::from_str("[minimap]\nenabled = false").unwrap();
+        let runtime_state = RuntimeState {
+            is_rendering_enabled: false,
+            overrides,
+        };
+
+        write(&path, &runtime_state).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            toml::from_str::<RuntimeState>(&contents).unwrap(),
+            runtime_state
+        );
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let runtime_state = toml::from_str::<RuntimeState>("").unwrap();
+        assert_eq!(runtime_state, RuntimeState::default());
+    }
+}
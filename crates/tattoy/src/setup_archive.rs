@@ -0,0 +1,532 @@
+//! `tattoy export-setup` and `tattoy import-setup`: move a whole Tattoy setup between machines as
+//! a single `.tar.gz`. The archive has the main config, the runtime state, the palette, themes
+//! and shaders, along with a manifest of the plugins that the config refers to.
+//!
+//! Plugins themselves aren't archived, because they're executables for a particular machine.
+//! Paths in the config that point inside the config or data directories are made relative when
+//! exporting, so that on import they're resolved relative to the new machine's directories.
+
+use color_eyre::eyre::{Result, WrapErr as _};
+use std::io::Read as _;
+
+/// The name of the manifest in the archive.
+const MANIFEST_FILE_NAME: &str = "setup.toml";
+
+/// The directory in the archive for files from the config directory.
+const CONFIG_PREFIX: &str = "config";
+
+/// The directory in the archive for files from the data directory.
+const DATA_PREFIX: &str = "data";
+
+/// The single files in the config directory that are part of a setup, apart from the main config.
+const CONFIG_FILES: [&str; 2] = [
+    "palette.toml",
+    crate::runtime_state::RUNTIME_STATE_FILE_NAME,
+];
+
+/// The directories in the config directory that are part of a setup.
+const CONFIG_DIRECTORIES: [&str; 2] = [
+    crate::config::main::SHADER_DIRECTORY_NAME,
+    crate::config::themes::THEMES_DIRECTORY_NAME,
+];
+
+/// The manifest, describing what's in the archive.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Manifest {
+    /// The version of Tattoy that made the archive.
+    tattoy_version: String,
+    /// The plugins that the config refers to.
+    #[serde(default)]
+    plugins: Vec<PluginReference>,
+}
+
+/// A plugin that the config refers to, which needs installing separately.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+struct PluginReference {
+    /// The name of the plugin.
+    name: String,
+    /// The path to the plugin's executable, as it is in the config.
+    path: std::path::PathBuf,
+}
+
+/// Where a setup lives on this machine.
+struct Directories {
+    /// The config directory.
+    config: std::path::PathBuf,
+    /// The data directory.
+    data: std::path::PathBuf,
+    /// The main config file, which isn't always `tattoy.toml`.
+    main_config: std::path::PathBuf,
+}
+
+impl Directories {
+    /// The directories that Tattoy is currently using.
+    async fn current(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Self {
+        Self {
+            config: crate::config::main::Config::directory(state).await,
+            data: crate::config::main::Config::data_directory(state).await,
+            main_config: crate::config::main::Config::main_config_path(state).await,
+        }
+    }
+
+    /// Where a file in the archive goes on this machine, or `None` if it isn't a setup file.
+    fn destination(&self, name: &std::path::Path) -> Option<std::path::PathBuf> {
+        if !is_safe(name) {
+            return None;
+        }
+        if let Ok(file) = name.strip_prefix(CONFIG_PREFIX) {
+            if file == std::path::Path::new(crate::cli_args::DEFAULT_CONFIG_FILE_NAME) {
+                return Some(self.main_config.clone());
+            }
+            return Some(self.config.join(file));
+        }
+        if let Ok(file) = name.strip_prefix(DATA_PREFIX) {
+            return Some(self.data.join(file));
+        }
+
+        None
+    }
+}
+
+/// Paths in archives must stay inside the directory that they're unpacked into.
+fn is_safe(name: &std::path::Path) -> bool {
+    name.components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+/// All the files in a directory and its subdirectories, relative to the directory, sorted.
+fn files_in(directory: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut files = Vec::new();
+    if !directory.is_dir() {
+        return Ok(files);
+    }
+
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(directory) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// A relative path with `/` separators, whatever the OS, so that it works on any machine.
+fn slash_separated(path: &std::path::Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// The name of a file in the archive.
+fn archive_name(prefix: &str, path: &std::path::Path) -> String {
+    format!("{prefix}/{}", slash_separated(path))
+}
+
+/// An absolute path inside one of the given directories, made relative to it.
+fn relative_path(path: &str, directories: &[&std::path::Path]) -> Option<String> {
+    let path = std::path::Path::new(path);
+    if !path.is_absolute() {
+        return None;
+    }
+
+    directories
+        .iter()
+        .find_map(|directory| path.strip_prefix(directory).ok())
+        .map(slash_separated)
+}
+
+/// Make the paths in a TOML file relative, if they point inside one of the given directories.
+/// Comments and formatting are preserved.
+fn relativise_paths(toml: &str, directories: &[&std::path::Path]) -> Result<String> {
+    let mut document: toml_edit::DocumentMut = toml.parse()?;
+    for (_, item) in document.iter_mut() {
+        relativise_item(item, directories);
+    }
+
+    Ok(document.to_string())
+}
+
+/// Make the paths in a TOML item relative.
+fn relativise_item(item: &mut toml_edit::Item, directories: &[&std::path::Path]) {
+    match item {
+        toml_edit::Item::None => (),
+        toml_edit::Item::Value(value) => relativise_value(value, directories),
+        toml_edit::Item::Table(table) => {
+            for (_, child) in table.iter_mut() {
+                relativise_item(child, directories);
+            }
+        }
+        toml_edit::Item::ArrayOfTables(tables) => {
+            for table in tables.iter_mut() {
+                for (_, child) in table.iter_mut() {
+                    relativise_item(child, directories);
+                }
+            }
+        }
+    }
+}
+
+/// Make the paths in a TOML value relative.
+fn relativise_value(value: &mut toml_edit::Value, directories: &[&std::path::Path]) {
+    match value {
+        toml_edit::Value::String(string) => {
+            if let Some(relative) = relative_path(string.value(), directories) {
+                let decor = string.decor().clone();
+                *string = toml_edit::Formatted::new(relative);
+                *string.decor_mut() = decor;
+            }
+        }
+        toml_edit::Value::Array(array) => {
+            for child in array.iter_mut() {
+                relativise_value(child, directories);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (_, child) in table.iter_mut() {
+                relativise_value(child, directories);
+            }
+        }
+        toml_edit::Value::Integer(_)
+        | toml_edit::Value::Float(_)
+        | toml_edit::Value::Boolean(_)
+        | toml_edit::Value::Datetime(_) => (),
+    }
+}
+
+/// The plugins that a config refers to.
+fn plugin_references(config: &str) -> Vec<PluginReference> {
+    let Ok(table) = toml::from_str::<toml::Table>(config) else {
+        return Vec::new();
+    };
+
+    table
+        .get("plugins")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|plugin| {
+            Some(PluginReference {
+                name: plugin.get("name")?.as_str()?.to_owned(),
+                path: plugin.get("path")?.as_str()?.into(),
+            })
+        })
+        .collect()
+}
+
+/// Gather all the files of a setup, with their names in the archive.
+fn collect(directories: &Directories) -> Result<Vec<(String, Vec<u8>)>> {
+    let roots = [directories.config.as_path(), directories.data.as_path()];
+    let main_config = std::fs::read_to_string(&directories.main_config).with_context(|| {
+        format!(
+            "Couldn't read the config at {}",
+            directories.main_config.display()
+        )
+    })?;
+    let main_config = relativise_paths(&main_config, &roots)?;
+
+    let manifest = Manifest {
+        tattoy_version: std::env!("CARGO_PKG_VERSION").to_owned(),
+        plugins: plugin_references(&main_config),
+    };
+    let mut files = vec![
+        (
+            MANIFEST_FILE_NAME.to_owned(),
+            toml::to_string(&manifest)?.into_bytes(),
+        ),
+        (
+            archive_name(
+                CONFIG_PREFIX,
+                std::path::Path::new(crate::cli_args::DEFAULT_CONFIG_FILE_NAME),
+            ),
+            main_config.into_bytes(),
+        ),
+    ];
+
+    let config_files = CONFIG_FILES
+        .into_iter()
+        .map(std::path::PathBuf::from)
+        .filter(|file| directories.config.join(file).is_file());
+    let mut config_directory_files = Vec::new();
+    for directory in CONFIG_DIRECTORIES {
+        for file in files_in(&directories.config.join(directory))? {
+            config_directory_files.push(std::path::Path::new(directory).join(file));
+        }
+    }
+    for file in config_files.chain(config_directory_files) {
+        let path = directories.config.join(&file);
+        let contents = if file
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            relativise_paths(&std::fs::read_to_string(path)?, &roots)?.into_bytes()
+        } else {
+            std::fs::read(path)?
+        };
+        files.push((archive_name(CONFIG_PREFIX, &file), contents));
+    }
+
+    let shaders = std::path::Path::new(crate::config::main::SHADER_DIRECTORY_NAME);
+    for file in files_in(&directories.data.join(shaders))? {
+        let path = shaders.join(file);
+        let contents = std::fs::read(directories.data.join(&path))?;
+        files.push((archive_name(DATA_PREFIX, &path), contents));
+    }
+
+    Ok(files)
+}
+
+/// Read all the files in an archive.
+fn read_archive(path: &std::path::Path) -> Result<Vec<(std::path::PathBuf, Vec<u8>)>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Couldn't open the archive at {}", path.display()))?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push((name, contents));
+    }
+
+    Ok(files)
+}
+
+/// Put an archive's files into their places on this machine. Shaders in the data directory are
+/// never overwritten, and nor are config files that have been changed, unless `is_forced`.
+/// Returns the files that were written.
+fn unpack(
+    files: Vec<(std::path::PathBuf, Vec<u8>)>,
+    directories: &Directories,
+    is_forced: bool,
+) -> Result<Vec<std::path::PathBuf>> {
+    let mut writes = Vec::new();
+    let mut conflicts = Vec::new();
+    for (name, contents) in files {
+        if name == std::path::Path::new(MANIFEST_FILE_NAME) {
+            continue;
+        }
+        let Some(destination) = directories.destination(&name) else {
+            tracing::warn!("Ignoring unknown file in setup archive: {}", name.display());
+            continue;
+        };
+
+        if let Ok(existing) = std::fs::read(&destination) {
+            // There's nothing to lose by replacing an identical file, or an untouched default
+            // config.
+            let is_unchanged_default = destination == directories.main_config
+                && existing == crate::config::main::DEFAULT_CONFIG.as_bytes();
+            let is_replaceable = existing == contents || is_unchanged_default;
+            if !is_replaceable && name.starts_with(DATA_PREFIX) {
+                tracing::info!("Keeping existing {}", destination.display());
+                continue;
+            }
+            if !is_replaceable && !is_forced {
+                conflicts.push(destination.display().to_string());
+                continue;
+            }
+        }
+        writes.push((destination, contents));
+    }
+
+    if !conflicts.is_empty() {
+        color_eyre::eyre::bail!(
+            "These files already exist, use `--force` to overwrite them:\n  {}",
+            conflicts.join("\n  ")
+        );
+    }
+
+    let mut written = Vec::new();
+    for (destination, contents) in writes {
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&destination, contents)?;
+        written.push(destination);
+    }
+
+    Ok(written)
+}
+
+/// Export the current setup, by default to a timestamped archive in the current directory.
+#[expect(
+    clippy::print_stdout,
+    reason = "The path of the archive is for the user to read"
+)]
+pub(crate) async fn export(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    maybe_output: Option<std::path::PathBuf>,
+) -> Result<()> {
+    let directories = Directories::current(state).await;
+    let files = collect(&directories)?;
+    let output = maybe_output.unwrap_or_else(|| {
+        format!(
+            "tattoy-setup-{}.tar.gz",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        )
+        .into()
+    });
+    crate::crash_report::write(&output, &files)
+        .with_context(|| format!("Writing setup to {}", output.display()))?;
+
+    println!("Exported {} files to {}", files.len(), output.display());
+    Ok(())
+}
+
+/// Import a setup that was exported with `tattoy export-setup`.
+#[expect(
+    clippy::print_stdout,
+    reason = "What was imported is for the user to read"
+)]
+pub(crate) async fn import(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    archive: &std::path::Path,
+    is_forced: bool,
+) -> Result<()> {
+    let files = read_archive(archive)?;
+    let manifest = files
+        .iter()
+        .find(|(name, _)| name == std::path::Path::new(MANIFEST_FILE_NAME))
+        .map(|(_, contents)| toml::from_str::<Manifest>(&String::from_utf8_lossy(contents)))
+        .transpose()?
+        .ok_or_else(|| {
+            color_eyre::eyre::eyre!("{} isn't a Tattoy setup archive", archive.display())
+        })?;
+    tracing::info!(
+        "Importing setup from Tattoy v{}: {}",
+        manifest.tattoy_version,
+        archive.display()
+    );
+
+    let directories = Directories::current(state).await;
+    let written = unpack(files, &directories, is_forced)?;
+    println!("Imported {} files", written.len());
+
+    for plugin in manifest.plugins {
+        let path = crate::config::main::Config::resolve_path(state, &plugin.path).await;
+        if !path.exists() {
+            println!(
+                "The '{}' plugin needs installing, at: {}",
+                plugin.name,
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn directories(root: &std::path::Path) -> Directories {
+        Directories {
+            config: root.join("config"),
+            data: root.join("data"),
+            main_config: root.join("config").join("tattoy.toml"),
+        }
+    }
+
+    #[test]
+    fn relativising_paths() {
+        let config = std::path::Path::new("/home/me/.config/tattoy");
+        let data = std::path::Path::new("/home/me/.local/share/tattoy");
+        let toml = "\
+            # My shader\n\
+            [shader]\n\
+            path = \"/home/me/.config/tattoy/shaders/waves.glsl\" # Nice\n\
+            [[plugins]]\n\
+            path = \"/home/me/.local/share/tattoy/plugins/smokey\"\n\
+            [[plugins]]\n\
+            path = \"/usr/bin/elsewhere\"\n";
+        let relativised = relativise_paths(toml, &[config, data]).unwrap();
+        assert_eq!(
+            relativised,
+            "\
+            # My shader\n\
+            [shader]\n\
+            path = \"shaders/waves.glsl\" # Nice\n\
+            [[plugins]]\n\
+            path = \"plugins/smokey\"\n\
+            [[plugins]]\n\
+            path = \"/usr/bin/elsewhere\"\n"
+        );
+    }
+
+    #[test]
+    fn finding_plugin_references() {
+        let config = "[[plugins]]\nname = \"smokey\"\npath = \"plugins/smokey\"\n";
+        assert_eq!(
+            plugin_references(config),
+            [PluginReference {
+                name: "smokey".to_owned(),
+                path: "plugins/smokey".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unsafe_archive_paths_are_ignored() {
+        let here = directories(std::path::Path::new("/here"));
+        assert_eq!(
+            here.destination(std::path::Path::new("config/tattoy.toml")),
+            Some("/here/config/tattoy.toml".into())
+        );
+        assert_eq!(
+            here.destination(std::path::Path::new("data/shaders/waves.glsl")),
+            Some("/here/data/shaders/waves.glsl".into())
+        );
+        assert_eq!(
+            here.destination(std::path::Path::new("config/../../etc/passwd")),
+            None
+        );
+        assert_eq!(here.destination(std::path::Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn moving_a_setup() {
+        let from = tempfile::tempdir().unwrap();
+        let old = directories(from.path());
+        std::fs::create_dir_all(old.config.join("shaders")).unwrap();
+        std::fs::create_dir_all(old.data.join("shaders")).unwrap();
+        let shader = old.config.join("shaders").join("waves.glsl");
+        std::fs::write(&shader, "void mainImage() {}").unwrap();
+        std::fs::write(old.data.join("shaders").join("bundled.glsl"), "old").unwrap();
+        let config = format!("[shader]\npath = {:?}\n", shader.display().to_string());
+        std::fs::write(&old.main_config, config).unwrap();
+
+        let archive = from.path().join("setup.tar.gz");
+        crate::crash_report::write(&archive, &collect(&old).unwrap()).unwrap();
+
+        let to = tempfile::tempdir().unwrap();
+        let new = directories(to.path());
+        std::fs::create_dir_all(new.data.join("shaders")).unwrap();
+        std::fs::write(new.data.join("shaders").join("bundled.glsl"), "new").unwrap();
+        unpack(read_archive(&archive).unwrap(), &new, false).unwrap();
+
+        let imported = std::fs::read_to_string(&new.main_config).unwrap();
+        assert_eq!(imported, "[shader]\npath = \"shaders/waves.glsl\"\n");
+        assert!(new.config.join("shaders").join("waves.glsl").exists());
+        let bundled = std::fs::read_to_string(new.data.join("shaders").join("bundled.glsl"));
+        assert_eq!(bundled.unwrap(), "new");
+
+        std::fs::write(&new.main_config, "frame_rate = 60\n").unwrap();
+        assert!(unpack(read_archive(&archive).unwrap(), &new, false).is_err());
+        unpack(read_archive(&archive).unwrap(), &new, true).unwrap();
+        let forced = std::fs::read_to_string(&new.main_config).unwrap();
+        assert_eq!(forced, imported);
+    }
+}
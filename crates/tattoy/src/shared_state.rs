@@ -1,5 +1,6 @@
 //! Here we store all the shared data that the app, particularly tattoys, might use.
-//! Access is mediated with locks to support asynchronicity
+//! Access is mediated with locks to support asynchronicity. Data that's read on every frame, like
+//! the config, is instead kept in `Watched` snapshots, so that reading it never waits on a lock.
 
 use std::sync::Arc;
 
@@ -21,6 +22,92 @@ pub struct TTYSize {
     pub height: u16,
 }
 
+/// Rows at the top and bottom of the user's terminal that the PTY doesn't use, so that something
+/// like a status bar can be shown there instead.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct ReservedRows {
+    /// The number of rows reserved at the top of the terminal.
+    pub top: u16,
+    /// The number of rows reserved at the bottom of the terminal.
+    pub bottom: u16,
+}
+
+impl ReservedRows {
+    /// The total number of reserved rows.
+    pub const fn total(self) -> u16 {
+        self.top.saturating_add(self.bottom)
+    }
+}
+
+/// Shared data that's read far more often than it's written. Readers get a snapshot of the current
+/// value without waiting on any async lock, so snapshots can be read many times per frame and even
+/// held across `.await`s. Writers replace the whole value.
+pub(crate) struct Watched<T>(tokio::sync::watch::Sender<Arc<T>>);
+
+impl<T> Watched<T> {
+    /// Start watching a value.
+    pub fn new(value: T) -> Self {
+        Self(tokio::sync::watch::Sender::new(Arc::new(value)))
+    }
+
+    /// A snapshot of the current value. Later writes don't change it.
+    pub fn load(&self) -> Arc<T> {
+        Arc::clone(&self.0.borrow())
+    }
+
+    /// Replace the value.
+    pub fn store(&self, value: T) {
+        self.0.send_replace(Arc::new(value));
+    }
+
+    /// Replace the value, but only if it's different. Returns whether it was replaced.
+    pub fn store_if_changed(&self, value: T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.0.send_if_modified(|current| {
+            if **current == value {
+                return false;
+            }
+            *current = Arc::new(value);
+            true
+        })
+    }
+
+    /// Change part of the value. Snapshots that are already loaded aren't affected.
+    pub fn modify<M>(&self, modifier: M)
+    where
+        T: Clone,
+        M: FnOnce(&mut T),
+    {
+        self.0
+            .send_modify(|current| modifier(Arc::make_mut(current)));
+    }
+
+    /// Get notified of every new value.
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<Arc<T>> {
+        self.0.subscribe()
+    }
+
+    /// Whether anything is subscribed to new values.
+    pub fn is_subscribed(&self) -> bool {
+        self.0.receiver_count() > 0
+    }
+}
+
+impl<T: Copy> Watched<T> {
+    /// A copy of the current value.
+    pub fn get(&self) -> T {
+        **self.0.borrow()
+    }
+}
+
+impl<T: Default> Default for Watched<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
 /// All the shared data the app uses
 #[non_exhaustive]
 pub(crate) struct SharedState {
@@ -30,17 +117,24 @@ pub(crate) struct SharedState {
     pub initialised_systems: tokio::sync::RwLock<Vec<String>>,
     /// Location of the config directory.
     pub config_path: tokio::sync::RwLock<std::path::PathBuf>,
+    /// Location of the data directory, for bundled shaders and installed plugins.
+    pub data_path: tokio::sync::RwLock<std::path::PathBuf>,
     /// Name of the main config file.
     pub main_config_file: tokio::sync::RwLock<std::path::PathBuf>,
+    /// The name of the currently active config profile, if any.
+    pub active_profile: tokio::sync::RwLock<Option<String>>,
     /// User config
-    pub config: tokio::sync::RwLock<crate::config::main::Config>,
+    pub config: Watched<crate::config::main::Config>,
     /// All the user-configured keybindings.
     pub keybindings: tokio::sync::RwLock<crate::config::input::KeybindingsAsEvents>,
     /// Just the size of the user's terminal. All the tattoys and shadow TTY should follow this
     pub tty_size: tokio::sync::RwLock<TTYSize>,
+    /// Rows of the user's terminal that the PTY doesn't use.
+    pub reserved_rows: Watched<ReservedRows>,
     /// This is a view onto the active screen of the shadow terminal. It's what you would see if
-    /// you had some kind of VNC viewer, let's say.
-    pub shadow_tty_screen: tokio::sync::RwLock<termwiz::surface::Surface>,
+    /// you had some kind of VNC viewer, let's say. The renderer holds onto a snapshot of it, so
+    /// the proxy's changes are copy-on-write.
+    pub shadow_tty_screen: Watched<termwiz::surface::Surface>,
     // TODO: rename to `shadow_primary_screen`
     /// This is the entire scrollback history of the shadow terminal.
     pub shadow_tty_scrollback: tokio::sync::RwLock<shadow_terminal::output::CompleteScrollback>,
@@ -59,8 +153,65 @@ pub(crate) struct SharedState {
     pub pty_sequence: tokio::sync::RwLock<usize>,
     /// Is the application logging?
     pub is_logging: tokio::sync::RwLock<bool>,
+    /// The file that logs are actually being written to. It can differ from the config's
+    /// `log_path` because of the CLI override and per-session log files.
+    pub log_file: tokio::sync::RwLock<std::path::PathBuf>,
+    /// This session's most recent warnings and errors, for the log viewer. They're captured
+    /// whether or not logging to a file is enabled.
+    pub recent_logs: crate::tattoys::log_viewer::Entries,
+    /// Is the settings panel open? When it is, all keyboard input goes to the panel rather than
+    /// the PTY.
+    pub is_settings_panel_open: tokio::sync::RwLock<bool>,
+    /// Is the palette editor open? Like the settings panel, it takes all keyboard input whilst
+    /// it's open.
+    pub is_palette_editor_open: tokio::sync::RwLock<bool>,
+    /// Is the scrollback search prompt open? It takes all keyboard input whilst the search is
+    /// being typed.
+    pub is_search_prompt_open: tokio::sync::RwLock<bool>,
+    /// Is the shader browser open? It takes all keyboard input whilst it's open.
+    pub is_shader_browser_open: tokio::sync::RwLock<bool>,
+    /// Is the screensaver running? When it is, the next input wakes the terminal rather than
+    /// going to the PTY.
+    pub is_screensaver_active: tokio::sync::RwLock<bool>,
+    /// The surface ID of a tattoy that was only started to be shown by the screensaver. It stops
+    /// when the terminal wakes up.
+    pub screensaver_tattoy: tokio::sync::RwLock<Option<String>>,
     /// Is Tattoy rendering anything to the terminal?
-    pub is_rendering_enabled: tokio::sync::RwLock<bool>,
+    pub is_rendering_enabled: Watched<bool>,
+    /// The true colour of the user's terminal's default background, as found in the palette. It
+    /// is also how we know whether the user has a light or dark theme.
+    pub default_background_colour: Watched<termwiz::color::SrgbaTuple>,
+    /// The shell's current working directory. The shell starts in Tattoy's own working directory,
+    /// after that we can only follow it if the shell reports changes with the OSC 7 code.
+    pub working_directory: tokio::sync::RwLock<Option<std::path::PathBuf>>,
+    /// The cursor colour that an application set with the OSC 12 code, if any.
+    pub cursor_colour: tokio::sync::RwLock<Option<termwiz::color::SrgbaTuple>>,
+    /// Config that was changed whilst Tattoy is running, eg with `tattoy ctl`. It's merged over
+    /// the top of the config file, after any profile and theme. They're remembered between
+    /// sessions, see `crate::runtime_state`.
+    pub config_overrides: tokio::sync::RwLock<toml::Table>,
+    /// Statistics about the performance of the renderer and tattoys.
+    pub performance: tokio::sync::RwLock<crate::performance::Performance>,
+    /// Can the user's terminal display true colour? It's assumed that it can, unless its terminfo
+    /// entry says otherwise. If not, Tattoy reduces its effects and approximates its colours, see
+    /// `crate::true_colour`.
+    pub is_true_colour: Watched<bool>,
+    /// The machine's power source, if it has a battery.
+    pub power_status: tokio::sync::RwLock<Option<crate::power::Status>>,
+    /// Are effects currently reduced to save battery power? See `crate::power`.
+    pub is_power_saving: tokio::sync::RwLock<bool>,
+    /// The terminal multiplexers that Tattoy is running inside of, or running. See
+    /// `crate::multiplexer`.
+    pub multiplexers: Watched<crate::multiplexer::Detected>,
+    /// The features supported by the user's terminal. See `crate::capabilities`.
+    pub capabilities: Watched<crate::capabilities::Capabilities>,
+    /// The latest composited frame, but only whilst a web viewer is watching the stream.
+    pub streamed_frame: Watched<crate::streaming::Frame>,
+    /// The URL of the web viewer, with its secret token, whilst the terminal is being streamed.
+    pub streaming_url: tokio::sync::RwLock<Option<String>>,
+    /// The kinds of the most recent protocol messages, for crash reports. See
+    /// `crate::crash_report`.
+    pub recent_protocol_messages: crate::crash_report::ProtocolMessages,
 }
 
 impl SharedState {
@@ -74,20 +225,42 @@ impl SharedState {
             protocol_tx,
             initialised_systems: RwLock::default(),
             config_path: RwLock::default(),
+            data_path: RwLock::default(),
             main_config_file: RwLock::default(),
-            config: RwLock::default(),
+            active_profile: RwLock::default(),
+            config: Watched::default(),
             keybindings: RwLock::default(),
             tty_size: RwLock::new(TTYSize { width, height }),
-            shadow_tty_screen: RwLock::default(),
+            reserved_rows: Watched::default(),
+            shadow_tty_screen: Watched::default(),
             shadow_tty_scrollback: RwLock::default(),
             is_scrolling: RwLock::default(),
             is_alternate_screen: RwLock::default(),
             pty_sequence: RwLock::default(),
             is_logging: RwLock::default(),
-            is_rendering_enabled: RwLock::default(),
+            log_file: RwLock::default(),
+            recent_logs: crate::tattoys::log_viewer::Entries::default(),
+            is_settings_panel_open: RwLock::default(),
+            is_palette_editor_open: RwLock::default(),
+            is_search_prompt_open: RwLock::default(),
+            is_shader_browser_open: RwLock::default(),
+            is_screensaver_active: RwLock::default(),
+            screensaver_tattoy: RwLock::default(),
+            is_rendering_enabled: Watched::new(true),
+            default_background_colour: Watched::new(crate::blender::DEFAULT_COLOUR),
+            working_directory: RwLock::new(std::env::current_dir().ok()),
+            cursor_colour: RwLock::default(),
+            config_overrides: RwLock::default(),
+            performance: RwLock::default(),
+            is_true_colour: Watched::new(true),
+            power_status: RwLock::default(),
+            is_power_saving: RwLock::default(),
+            multiplexers: Watched::default(),
+            capabilities: Watched::default(),
+            streamed_frame: Watched::default(),
+            streaming_url: RwLock::default(),
+            recent_protocol_messages: RwLock::default(),
         };
-        *state.is_rendering_enabled.write().await = true;
-
         state.set_tty_size(width, height).await;
         Ok(Arc::new(state))
     }
@@ -113,20 +286,29 @@ impl SharedState {
         mut maybe_body: Option<String>,
         include_logs_message: bool,
     ) {
+        let mut maybe_action = None;
         if let Some(mut body) = maybe_body.clone() {
             if include_logs_message {
                 use crate::tattoys::notifications::main::Notifications;
-                let logpath = self.config.read().await.log_path.clone();
+                let logpath = self.log_file.read().await.clone();
                 let is_logging = *self.is_logging.read().await;
                 let logs_help_text = Notifications::logs_help_text(is_logging, &logpath);
                 body = format!("{body}\n\n{logs_help_text}");
                 maybe_body = Some(body);
+                if is_logging {
+                    maybe_action = Some(crate::tattoys::notifications::message::Action::OpenFile(
+                        logpath,
+                    ));
+                }
             }
         }
 
         self.protocol_tx
             .send(crate::tattoys::notifications::message::Message::make(
-                title, level, maybe_body,
+                title,
+                level,
+                maybe_body,
+                maybe_action,
             ))
             .unwrap_or_else(|send_error| {
                 tracing::error!("Error sending notification: {send_error:?}");
@@ -146,6 +328,37 @@ impl SharedState {
         *tty_size = TTYSize { width, height };
     }
 
+    /// The size of the PTY, which is the size of the user's terminal without any reserved rows.
+    pub async fn get_pty_size(&self) -> TTYSize {
+        let tty_size = self.get_tty_size().await;
+        let reserved_rows = self.reserved_rows.get();
+        TTYSize {
+            width: tty_size.width,
+            height: tty_size.height.saturating_sub(reserved_rows.total()).max(1),
+        }
+    }
+
+    /// Reserve rows of the user's terminal so that the PTY doesn't use them, and tell the rest of
+    /// the app about it.
+    pub async fn reserve_rows(&self, reserved_rows: ReservedRows) -> Result<()> {
+        if !self.reserved_rows.store_if_changed(reserved_rows) {
+            return Ok(());
+        }
+
+        self.protocol_tx
+            .send(crate::run::Protocol::ReservedRows(reserved_rows))?;
+        Ok(())
+    }
+
+    /// Is an overlay, like the settings panel, open and taking all keyboard input? New overlays
+    /// that take keyboard input need adding here.
+    pub async fn is_overlay_capturing_input(&self) -> bool {
+        *self.is_settings_panel_open.read().await
+            || *self.is_palette_editor_open.read().await
+            || *self.is_search_prompt_open.read().await
+            || *self.is_shader_browser_open.read().await
+    }
+
     /// Get a read lock and return whether the user is currently scrolling.
     pub async fn get_is_scrolling(&self) -> bool {
         let is_scrolling = self.is_scrolling.read().await;
@@ -0,0 +1,186 @@
+//! `tattoy shell-integration`: install the hooks that make the user's shell report when commands
+//! start and finish (OSC 133) and its current directory (OSC 7). Tattoys like the command duration,
+//! git status and focus depend on these reports, but most shells don't send them by default.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+
+/// The first line of every integration script. It's how we know that the integration is already
+/// installed.
+const MARKER: &str = "# Tattoy shell integration";
+
+/// The shells that Tattoy has integration scripts for.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shell {
+    /// Bash, installed in `~/.bashrc`.
+    Bash,
+    /// Zsh, installed in `~/.zshrc`.
+    Zsh,
+    /// Fish, installed in `~/.config/fish/conf.d/tattoy.fish`.
+    Fish,
+    /// PowerShell, installed in its current user profile.
+    Pwsh,
+}
+
+impl Shell {
+    /// Get the shell from the path to its executable, eg `/bin/zsh`.
+    fn from_path(path: &str) -> Option<Self> {
+        let name = std::path::Path::new(path).file_stem()?.to_str()?;
+        match name {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            "pwsh" | "powershell" => Some(Self::Pwsh),
+            _ => None,
+        }
+    }
+
+    /// Detect the user's shell. Windows doesn't set `$SHELL`, so PowerShell is assumed there.
+    fn detect() -> Result<Self> {
+        match std::env::var("SHELL") {
+            Ok(path) => Self::from_path(&path).with_context(|| {
+                format!("Shell integration isn't supported for {path}, choose a shell with --shell")
+            }),
+            Err(_) if cfg!(target_os = "windows") => Ok(Self::Pwsh),
+            Err(_) => {
+                color_eyre::eyre::bail!("Couldn't detect your shell, choose one with --shell")
+            }
+        }
+    }
+
+    /// The integration script.
+    const fn script(self) -> &'static str {
+        match self {
+            Self::Bash => include_str!("../shell_integration/tattoy.bash"),
+            Self::Zsh => include_str!("../shell_integration/tattoy.zsh"),
+            Self::Fish => include_str!("../shell_integration/tattoy.fish"),
+            Self::Pwsh => include_str!("../shell_integration/tattoy.ps1"),
+        }
+    }
+
+    /// The user's config directory, as used by Fish and PowerShell on Unix.
+    fn xdg_config_directory() -> Result<std::path::PathBuf> {
+        match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(directory) => Ok(directory.into()),
+            None => Ok(dirs::home_dir()
+                .context("Couldn't find your home directory")?
+                .join(".config")),
+        }
+    }
+
+    /// The shell config file that the script is installed in.
+    fn config_path(self) -> Result<std::path::PathBuf> {
+        let home = dirs::home_dir().context("Couldn't find your home directory")?;
+        let path = match self {
+            Self::Bash => home.join(".bashrc"),
+            Self::Zsh => std::env::var_os("ZDOTDIR")
+                .map_or(home, std::path::PathBuf::from)
+                .join(".zshrc"),
+            Self::Fish => Self::xdg_config_directory()?
+                .join("fish")
+                .join("conf.d")
+                .join("tattoy.fish"),
+            Self::Pwsh if cfg!(target_os = "windows") => dirs::document_dir()
+                .context("Couldn't find your documents directory")?
+                .join("PowerShell")
+                .join("Microsoft.PowerShell_profile.ps1"),
+            Self::Pwsh => Self::xdg_config_directory()?
+                .join("powershell")
+                .join("Microsoft.PowerShell_profile.ps1"),
+        };
+        Ok(path)
+    }
+}
+
+/// What to append to a shell config file for the script to be installed. Nothing is appended if
+/// the script is already installed.
+fn addition(existing: &str, script: &str) -> Option<String> {
+    if existing.contains(MARKER) {
+        return None;
+    }
+    let separator = match existing {
+        "" => "",
+        text if text.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    Some(format!("{separator}{script}"))
+}
+
+/// Install the shell integration by appending it to the shell's config file, or just print it.
+#[expect(
+    clippy::print_stdout,
+    reason = "The script is printed so that it can be installed manually"
+)]
+pub(crate) fn install(maybe_shell: Option<Shell>, is_print_only: bool) -> Result<()> {
+    let shell = match maybe_shell {
+        Some(shell) => shell,
+        None => Shell::detect()?,
+    };
+    if is_print_only {
+        print!("{}", shell.script());
+        return Ok(());
+    }
+
+    let path = shell.config_path()?;
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error.into()),
+    };
+    let Some(addition) = addition(&existing, shell.script()) else {
+        println!(
+            "Shell integration is already installed in {}",
+            path.display()
+        );
+        return Ok(());
+    };
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    std::io::Write::write_all(&mut file, addition.as_bytes())?;
+    println!(
+        "Installed shell integration in {}. It's used by new shells started inside Tattoy.",
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detecting_shells() {
+        assert_eq!(Shell::from_path("/bin/bash"), Some(Shell::Bash));
+        assert_eq!(Shell::from_path("/usr/local/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_path("fish"), Some(Shell::Fish));
+        assert_eq!(Shell::from_path("pwsh.exe"), Some(Shell::Pwsh));
+        assert_eq!(Shell::from_path("/bin/tcsh"), None);
+    }
+
+    #[test]
+    fn scripts_are_marked() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Pwsh] {
+            assert!(shell.script().starts_with(MARKER));
+        }
+    }
+
+    #[test]
+    fn appending_scripts() {
+        let script = Shell::Bash.script();
+        assert_eq!(addition("", script), Some(script.to_owned()));
+        assert_eq!(
+            addition("alias l=ls\n", script),
+            Some(format!("\n{script}"))
+        );
+        assert_eq!(
+            addition("alias l=ls", script),
+            Some(format!("\n\n{script}"))
+        );
+        assert_eq!(addition(&format!("alias l=ls\n{script}"), script), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! The splash that's shown whilst Tattoy starts up. Loading the palette, compiling shaders and
+//! starting plugins can take a second or two, so rather than showing a blank, or half-finished,
+//! frame, the renderer shows this until the first frame from the PTY arrives.
+
+use std::str::FromStr as _;
+
+use termwiz::surface::{Change as TermwizChange, Position as TermwizPosition};
+
+/// The systems that report themselves in `SharedState::initialised_systems` during startup, in the
+/// order that they start, along with what's shown whilst waiting for each of them.
+const STAGES: [(&str, &str); 3] = [
+    ("renderer", "Starting the renderer"),
+    ("palette", "Loading the palette"),
+    ("tattoys", "Starting tattoys and plugins"),
+];
+
+/// What's shown once everything else has started, whilst waiting for the PTY's first frame.
+const WAITING_FOR_PTY: &str = "Starting the shell";
+
+/// The width of the progress bar, in columns.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// How many of the startup stages have finished, and a description of the current one.
+fn progress(initialised_systems: &[String]) -> (usize, &'static str) {
+    let finished = STAGES
+        .iter()
+        .filter(|(system, _)| initialised_systems.iter().any(|name| name == system))
+        .count();
+    let current = STAGES
+        .iter()
+        .find(|(system, _)| !initialised_systems.iter().any(|name| name == system))
+        .map_or(WAITING_FOR_PTY, |(_, description)| description);
+
+    (finished, current)
+}
+
+/// The progress bar. Waiting for the PTY is the final stage, so the bar is never quite full.
+fn progress_bar(finished: usize, width: usize) -> (String, String) {
+    let filled = (width * finished).div_euclid(STAGES.len() + 1);
+    ("━".repeat(filled), "─".repeat(width.saturating_sub(filled)))
+}
+
+/// Draw the splash in the middle of the frame.
+pub(crate) fn draw(frame: &mut termwiz::surface::Surface, initialised_systems: &[String]) {
+    let (width, height) = frame.dimensions();
+    let (finished, current) = progress(initialised_systems);
+    let title = format!("tattoy v{}", std::env!("CARGO_PKG_VERSION"));
+    let (filled, empty) = progress_bar(finished, PROGRESS_BAR_WIDTH.min(width));
+    let blue = termwiz::color::SrgbaTuple::from_str(crate::utils::TATTOY_BLUE)
+        .map_or(termwiz::color::ColorAttribute::Default, |colour| {
+            termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(colour)
+        });
+
+    let top = height.saturating_sub(3).div_euclid(2);
+    let centred = |text: &str| {
+        width
+            .saturating_sub(crate::utils::display_width(text))
+            .div_euclid(2)
+    };
+    let bar_left = width
+        .saturating_sub(PROGRESS_BAR_WIDTH.min(width))
+        .div_euclid(2);
+
+    frame.add_changes(vec![
+        TermwizChange::CursorPosition {
+            x: TermwizPosition::Absolute(centred(&title)),
+            y: TermwizPosition::Absolute(top),
+        },
+        TermwizChange::Attribute(termwiz::cell::AttributeChange::Intensity(
+            termwiz::cell::Intensity::Bold,
+        )),
+        crate::utils::truncate_to_width(&title, width).into(),
+        TermwizChange::AllAttributes(termwiz::cell::CellAttributes::default()),
+        TermwizChange::CursorPosition {
+            x: TermwizPosition::Absolute(bar_left),
+            y: TermwizPosition::Absolute(top + 1),
+        },
+        TermwizChange::Attribute(termwiz::cell::AttributeChange::Foreground(blue)),
+        filled.into(),
+        TermwizChange::AllAttributes(termwiz::cell::CellAttributes::default()),
+        empty.into(),
+        TermwizChange::CursorPosition {
+            x: TermwizPosition::Absolute(centred(current)),
+            y: TermwizPosition::Absolute(top + 2),
+        },
+        crate::utils::truncate_to_width(current, width).into(),
+    ]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn startup_progress() {
+        assert_eq!(progress(&[]), (0, "Starting the renderer"));
+        let renderer = ["renderer".to_owned()];
+        assert_eq!(progress(&renderer), (1, "Loading the palette"));
+        let everything = [
+            "renderer".to_owned(),
+            "notifications".to_owned(),
+            "palette".to_owned(),
+            "tattoys".to_owned(),
+        ];
+        assert_eq!(progress(&everything), (3, "Starting the shell"));
+    }
+
+    #[test]
+    fn progress_bar_is_never_full() {
+        assert_eq!(progress_bar(0, 8), (String::new(), "─".repeat(8)));
+        assert_eq!(progress_bar(2, 8), ("━".repeat(4), "─".repeat(4)));
+        assert_eq!(progress_bar(3, 8), ("━".repeat(6), "─".repeat(2)));
+    }
+
+    #[test]
+    fn drawing_the_splash() {
+        let mut frame = termwiz::surface::Surface::new(40, 9);
+        draw(&mut frame, &["renderer".to_owned()]);
+        let text = frame.screen_chars_to_string();
+        let mut lines = text.lines();
+        assert!(lines.nth(3).unwrap().contains("tattoy v"));
+        assert!(lines.next().unwrap().contains("━━━━━━━─"));
+        assert!(lines.next().unwrap().contains("Loading the palette"));
+    }
+}
@@ -0,0 +1,490 @@
+//! Streaming the composited terminal to web browsers, for read-only session sharing and remote
+//! demos of Tattoy's effects. A small bundled web viewer is served over HTTP, and it then connects
+//! back to the same address with a websocket. Every frame that Tattoy paints is sent to the viewer
+//! as JSON: rows of text runs that share the same colours and styles.
+//!
+//! Frames are only copied out of the renderer whilst a viewer is connected. Anything sent by the
+//! viewer is ignored, so viewers can never type into the terminal.
+//!
+//! Browsers let any web page open a websocket to any address, so the websocket needs a random
+//! token that's only known from the viewer's URL. Otherwise any web page that the user visits
+//! could watch their terminal.
+
+use color_eyre::eyre::{Result, WrapErr as _};
+use futures_util::{SinkExt as _, StreamExt as _};
+use rand::Rng as _;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// User-configurable settings for streaming to browsers.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to stream the terminal.
+    pub enabled: bool,
+    /// The address to serve the web viewer on. Anyone who can connect to it can see everything in
+    /// the terminal.
+    pub address: String,
+    /// The most frames that are sent to each viewer per second.
+    pub max_frame_rate: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:7681".to_owned(),
+            max_frame_rate: 15,
+        }
+    }
+}
+
+/// The bundled web viewer.
+const VIEWER_HTML: &str = include_str!("../web_viewer/index.html");
+
+/// The most of an HTTP request that's looked at to decide whether it's a websocket connection.
+const MAX_REQUEST_SIZE: usize = 4096;
+
+/// How many times to wait for the rest of a request's headers to arrive.
+const MAX_HEADER_WAITS: usize = 100;
+
+/// How long to wait each time for the rest of a request's headers to arrive.
+const HEADER_WAIT: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// The end of an HTTP request's headers.
+const END_OF_HEADERS: &[u8] = b"\r\n\r\n";
+
+/// The name of the query parameter that has the websocket's token.
+const TOKEN_PARAMETER: &str = "token";
+
+/// A composited frame, as painted to the user's terminal.
+#[derive(Default)]
+pub(crate) struct Frame {
+    /// The cells of the frame.
+    pub surface: termwiz::surface::Surface,
+    /// The position of the cursor, if it's visible.
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// The colours and styles of a cell.
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+struct Style {
+    /// The text colour, eg `#ff8800`. The default text colour when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fg: Option<String>,
+    /// The background colour. The default background colour when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bg: Option<String>,
+    /// Whether the text is bold.
+    #[serde(skip_serializing_if = "core::ops::Not::not")]
+    bold: bool,
+    /// Whether the text is italic.
+    #[serde(skip_serializing_if = "core::ops::Not::not")]
+    italic: bool,
+    /// Whether the text is underlined.
+    #[serde(skip_serializing_if = "core::ops::Not::not")]
+    underline: bool,
+}
+
+/// A run of neighbouring cells in a row that all have the same style.
+#[derive(serde::Serialize, Debug)]
+struct Run {
+    /// The text of the cells.
+    text: String,
+    /// The style of all the cells.
+    #[serde(flatten)]
+    style: Style,
+}
+
+/// A frame as it's sent to the web viewer.
+#[derive(serde::Serialize, Debug)]
+struct Message {
+    /// The width of the frame in cells.
+    width: usize,
+    /// The height of the frame in cells.
+    height: usize,
+    /// The default text colour.
+    foreground: String,
+    /// The default background colour.
+    background: String,
+    /// The position of the cursor, if it's visible.
+    cursor: Option<(usize, usize)>,
+    /// Each row of the frame.
+    rows: Vec<Vec<Run>>,
+}
+
+/// Format a colour as hex.
+fn hex(colour: termwiz::color::SrgbaTuple) -> String {
+    let (red, green, blue, _) = colour.to_srgb_u8();
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+/// The true colour of a colour attribute. Default colours are left to the web viewer.
+fn colour(
+    attribute: termwiz::color::ColorAttribute,
+    palette: &crate::palette::converter::Palette,
+) -> Option<String> {
+    match attribute {
+        termwiz::color::ColorAttribute::TrueColorWithPaletteFallback(colour, _)
+        | termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(colour) => Some(hex(colour)),
+        termwiz::color::ColorAttribute::PaletteIndex(index) => {
+            Some(hex(palette.true_colour_tuple_from_index(index)))
+        }
+        termwiz::color::ColorAttribute::Default => None,
+    }
+}
+
+/// Convert a frame to the message that's sent to the web viewer.
+fn message(frame: &Frame, palette: &crate::palette::converter::Palette) -> Message {
+    let (width, height) = frame.surface.dimensions();
+    let rows = frame
+        .surface
+        .screen_lines()
+        .iter()
+        .map(|line| {
+            let mut runs: Vec<Run> = Vec::new();
+            for cell in line.visible_cells() {
+                let attributes = cell.attrs();
+                let (mut fg, mut bg) = (attributes.foreground(), attributes.background());
+                if attributes.reverse() {
+                    core::mem::swap(&mut fg, &mut bg);
+                }
+                let style = Style {
+                    fg: colour(fg, palette),
+                    bg: colour(bg, palette),
+                    bold: attributes.intensity() == termwiz::cell::Intensity::Bold,
+                    italic: attributes.italic(),
+                    underline: attributes.underline() != termwiz::cell::Underline::None,
+                };
+                match runs.last_mut() {
+                    Some(last) if last.style == style => last.text.push_str(cell.str()),
+                    _ => runs.push(Run {
+                        text: cell.str().to_owned(),
+                        style,
+                    }),
+                }
+            }
+            runs
+        })
+        .collect();
+
+    Message {
+        width,
+        height,
+        foreground: hex(palette.default_foreground_colour()),
+        background: hex(palette.default_background_colour()),
+        cursor: frame.cursor,
+        rows,
+    }
+}
+
+/// A new random token for the websocket.
+fn new_token() -> String {
+    format!("{:032x}", rand::thread_rng().gen::<u128>())
+}
+
+/// The URL of the web viewer, with the websocket's token.
+fn viewer_url(address: &str, token: &str) -> String {
+    format!("http://{address}/?{TOKEN_PARAMETER}={token}")
+}
+
+/// Whether a request's query has the websocket's token.
+fn has_token(maybe_query: Option<&str>, token: &str) -> bool {
+    maybe_query.is_some_and(|query| {
+        query.split('&').any(|parameter| {
+            parameter
+                .split_once('=')
+                .is_some_and(|(name, value)| name == TOKEN_PARAMETER && value == token)
+        })
+    })
+}
+
+/// Accept a websocket, but only if it has the token.
+async fn handshake(
+    stream: tokio::net::TcpStream,
+    token: &str,
+) -> Result<tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>> {
+    use tokio_tungstenite::tungstenite::handshake::server;
+
+    let authorise = |request: &server::Request, response: server::Response| {
+        if has_token(request.uri().query(), token) {
+            return Ok(response);
+        }
+        let mut rejection = server::ErrorResponse::new(Some("Missing or wrong token".to_owned()));
+        *rejection.status_mut() = tokio_tungstenite::tungstenite::http::StatusCode::FORBIDDEN;
+        Err(rejection)
+    };
+
+    Ok(tokio_tungstenite::accept_hdr_async(stream, authorise).await?)
+}
+
+/// Whether the start of an HTTP request is asking to upgrade to a websocket.
+fn is_websocket_request(request: &[u8]) -> bool {
+    String::from_utf8_lossy(request)
+        .to_lowercase()
+        .lines()
+        .any(|line| line.starts_with("upgrade:") && line.contains("websocket"))
+}
+
+/// Look at a request's headers, without consuming them, so that the websocket handshake can still
+/// read them.
+async fn peek_headers(stream: &tokio::net::TcpStream) -> Result<Vec<u8>> {
+    let mut buffer = vec![0; MAX_REQUEST_SIZE];
+    let mut size = 0;
+    for _ in 0..MAX_HEADER_WAITS {
+        size = stream.peek(&mut buffer).await?;
+        let headers = buffer.get(..size).unwrap_or_default();
+        let is_complete = headers
+            .windows(END_OF_HEADERS.len())
+            .any(|window| window == END_OF_HEADERS);
+        if size == 0 || size == MAX_REQUEST_SIZE || is_complete {
+            break;
+        }
+        tokio::time::sleep(HEADER_WAIT).await;
+    }
+    buffer.truncate(size);
+    Ok(buffer)
+}
+
+/// Serve the web viewer itself.
+async fn serve_viewer(mut stream: tokio::net::TcpStream) -> Result<()> {
+    let mut request = vec![0; MAX_REQUEST_SIZE];
+    let _size = stream.read(&mut request).await?;
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/html; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n\
+         {VIEWER_HTML}",
+        VIEWER_HTML.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Stream frames to a single web viewer until it disconnects or Tattoy exits.
+async fn stream_frames(
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    mut websocket: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
+    mut palette: crate::palette::converter::Palette,
+) -> Result<()> {
+    let mut frames = state.streamed_frame.subscribe();
+    let mut protocol = state.protocol_tx.subscribe();
+    let max_frame_rate = state.config.load().streaming.max_frame_rate.max(1);
+    let frame_interval = std::time::Duration::from_secs(1)
+        .checked_div(max_frame_rate)
+        .unwrap_or_default();
+
+    // Frames aren't kept whilst nobody is watching, so there isn't one to send yet.
+    state.protocol_tx.send(crate::run::Protocol::Repaint)?;
+
+    #[expect(
+        clippy::integer_division_remainder_used,
+        reason = "This is caused by the `tokio::select!`"
+    )]
+    loop {
+        tokio::select! {
+            Ok(()) = frames.changed() => {
+                let frame = std::sync::Arc::clone(&frames.borrow_and_update());
+                let json = serde_json::to_string(&message(&frame, &palette))?;
+                websocket
+                    .send(tokio_tungstenite::tungstenite::Message::text(json))
+                    .await?;
+                tokio::time::sleep(frame_interval).await;
+            },
+            incoming = websocket.next() => match incoming {
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Close(_)) | Err(_)) | None => {
+                    break;
+                }
+                // The viewer is read-only.
+                Some(Ok(_)) => (),
+            },
+            Ok(message) = protocol.recv() => {
+                #[expect(clippy::wildcard_enum_match_arm, reason = "It's our internal protocol")]
+                match message {
+                    crate::run::Protocol::End => {
+                        websocket.close(None).await?;
+                        break;
+                    }
+                    crate::run::Protocol::Palette(new_palette) => palette = new_palette,
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Accept a connection from a web viewer, or wait forever if streaming isn't enabled.
+async fn accept(
+    maybe_listener: Option<&tokio::net::TcpListener>,
+) -> std::io::Result<tokio::net::TcpStream> {
+    match maybe_listener {
+        Some(listener) => Ok(listener.accept().await?.0),
+        None => std::future::pending().await,
+    }
+}
+
+/// Handle a single connection, either for the web viewer or for its websocket.
+async fn handle_connection(
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    stream: tokio::net::TcpStream,
+    token: &str,
+) -> Result<()> {
+    let headers = peek_headers(&stream).await?;
+    if !is_websocket_request(&headers) {
+        return serve_viewer(stream).await;
+    }
+
+    let peer = stream.peer_addr().ok();
+    let websocket = handshake(stream, token)
+        .await
+        .wrap_err_with(|| format!("Rejected web viewer: {peer:?}"))?;
+    tracing::info!("Web viewer connected: {peer:?}");
+    let palette = crate::config::main::Config::load_palette(std::sync::Arc::clone(&state)).await?;
+    stream_frames(state, websocket, palette).await
+}
+
+/// Start streaming, if it's enabled. Changes to the config only take effect after a restart.
+pub(crate) async fn start(
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+) -> Result<tokio::task::JoinHandle<Result<()>>> {
+    let config = state.config.load().streaming.clone();
+    let token = std::sync::Arc::new(new_token());
+    let maybe_listener = if config.enabled {
+        let listener = tokio::net::TcpListener::bind(&config.address)
+            .await
+            .wrap_err_with(|| format!("Couldn't stream the terminal on {}", config.address))?;
+        let url = viewer_url(&config.address, &token);
+        tracing::info!("Streaming the terminal on {url}");
+        *state.streaming_url.write().await = Some(url);
+        Some(listener)
+    } else {
+        None
+    };
+
+    Ok(tokio::spawn(async move {
+        let mut protocol = state.protocol_tx.subscribe();
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = accept(maybe_listener.as_ref()) => match result {
+                    Ok(stream) => {
+                        let connection_state = std::sync::Arc::clone(&state);
+                        let connection_token = std::sync::Arc::clone(&token);
+                        tokio::spawn(async move {
+                            let result =
+                                handle_connection(connection_state, stream, &connection_token)
+                                    .await;
+                            if let Err(error) = result {
+                                tracing::warn!("Streaming to web viewer: {error:?}");
+                            }
+                        });
+                    }
+                    Err(error) => tracing::error!("Accepting web viewer connection: {error:?}"),
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn palette() -> crate::palette::converter::Palette {
+        crate::palette::converter::Palette {
+            map: (0..=255)
+                .map(|index| (index.to_string(), (index, index, index)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn merging_cells_into_runs() {
+        let mut surface = termwiz::surface::Surface::new(6, 1);
+        surface.add_change(termwiz::surface::Change::Attribute(
+            termwiz::cell::AttributeChange::Foreground(
+                termwiz::color::ColorAttribute::PaletteIndex(1),
+            ),
+        ));
+        surface.add_change("ab");
+        surface.add_change(termwiz::surface::Change::AllAttributes(
+            termwiz::cell::CellAttributes::default(),
+        ));
+        surface.add_change("cd");
+        let frame = Frame {
+            surface,
+            cursor: Some((4, 0)),
+        };
+
+        let message = message(&frame, &palette());
+        let runs = message.rows.first().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].text, "ab");
+        assert_eq!(runs[0].style.fg.as_deref(), Some("#010101"));
+        assert_eq!(runs[1].text, "cd  ");
+        assert_eq!(runs[1].style.fg, None);
+        assert_eq!(message.cursor, Some((4, 0)));
+    }
+
+    #[test]
+    fn detecting_websocket_requests() {
+        assert!(is_websocket_request(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n"
+        ));
+        assert!(!is_websocket_request(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"
+        ));
+    }
+
+    #[test]
+    fn checking_tokens() {
+        assert!(has_token(Some("token=abc"), "abc"));
+        assert!(has_token(Some("other=1&token=abc"), "abc"));
+        assert!(!has_token(Some("token=abd"), "abc"));
+        assert!(!has_token(Some("token="), "abc"));
+        assert!(!has_token(None, "abc"));
+        assert_ne!(new_token(), new_token());
+        assert_eq!(
+            viewer_url("127.0.0.1:7681", "abc"),
+            "http://127.0.0.1:7681/?token=abc"
+        );
+    }
+
+    async fn try_handshake(path: &str) -> bool {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handshake(stream, "secret").await.is_ok()
+        });
+
+        let client = tokio::net::TcpStream::connect(address).await.unwrap();
+        let url = format!("ws://{address}{path}");
+        let is_client_accepted = tokio_tungstenite::client_async(url, client).await.is_ok();
+        let is_server_accepted = server.await.unwrap();
+        assert_eq!(is_client_accepted, is_server_accepted);
+        is_server_accepted
+    }
+
+    #[tokio::test]
+    async fn rejecting_websockets_without_the_token() {
+        assert!(try_handshake("/?token=secret").await);
+        assert!(!try_handshake("/").await);
+        assert!(!try_handshake("/?token=guess").await);
+    }
+}
@@ -18,6 +18,112 @@ pub const BLACK: Colour = (0.0, 0.0, 0.0, 1.0);
 /// A default pure red.
 pub const RED: Colour = (1.0, 0.0, 0.0, 1.0);
 
+/// Convert a true colour, like those from the palette, to a surface colour.
+pub const fn colour_from_srgba(colour: termwiz::color::SrgbaTuple) -> Colour {
+    (colour.0, colour.1, colour.2, colour.3)
+}
+
+/// The corners of the terminal that small overlays, like the system stats, can be shown in.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Corner {
+    /// The top left of the terminal.
+    TopLeft,
+    /// The top right of the terminal.
+    TopRight,
+    /// The bottom left of the terminal.
+    BottomLeft,
+    /// The bottom right of the terminal.
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    /// The top-left position of a panel of the given size, in columns and rows, placed in this
+    /// corner of the terminal.
+    pub const fn origin(self, terminal: (usize, usize), panel: (usize, usize)) -> (usize, usize) {
+        let right = terminal.0.saturating_sub(panel.0);
+        let bottom = terminal.1.saturating_sub(panel.1);
+        match self {
+            Self::TopLeft => (0, 0),
+            // Leave space for the little Tattoy indicator.
+            Self::TopRight => (right.saturating_sub(1), 0),
+            Self::BottomLeft => (0, bottom),
+            Self::BottomRight => (right, bottom),
+        }
+    }
+}
+
+/// Which cells have been drawn on. Cells that nothing has been drawn on can be skipped by the
+/// renderer's whole-frame passes, like colour grading.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DirtyCells {
+    /// The number of columns.
+    width: usize,
+    /// One flag per cell, row by row.
+    cells: Vec<bool>,
+}
+
+impl DirtyCells {
+    /// Start with every cell clean.
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            cells: vec![false; width * height],
+        }
+    }
+
+    /// Mark a single cell as drawn on.
+    pub fn mark(&mut self, x: usize, y: usize) {
+        if x >= self.width {
+            return;
+        }
+        if let Some(cell) = self.cells.get_mut(y * self.width + x) {
+            *cell = true;
+        }
+    }
+
+    /// Mark a run of cells, starting at the given cell. If the run goes beyond the surface then
+    /// every cell is marked, just to be safe.
+    fn mark_run(&mut self, x: usize, y: usize, length: usize) {
+        let start = (y * self.width + x).min(self.cells.len());
+        let end = start + length;
+        match self.cells.get_mut(start..end) {
+            Some(cells) => cells.fill(true),
+            None => self.mark_all(),
+        }
+    }
+
+    /// Mark every cell that's marked in the other cells, which must be the same size.
+    pub fn merge(&mut self, other: &Self) {
+        for (cell, other_cell) in self.cells.iter_mut().zip(&other.cells) {
+            *cell |= *other_cell;
+        }
+    }
+
+    /// The flags of each row, so that rows can be marked independently of each other.
+    pub fn rows_mut(&mut self) -> core::slice::ChunksMut<'_, bool> {
+        self.cells.chunks_mut(self.width.max(1))
+    }
+
+    /// Mark every cell as drawn on.
+    pub fn mark_all(&mut self) {
+        self.cells.fill(true);
+    }
+
+    /// Has anything been drawn on the cell?
+    #[must_use]
+    pub fn is_dirty(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && self
+                .cells
+                .get(y * self.width + x)
+                .copied()
+                .unwrap_or_default()
+    }
+}
+
 /// `Surface`
 #[derive(Clone)]
 pub(crate) struct Surface {
@@ -35,6 +141,8 @@ pub(crate) struct Surface {
     pub opacity: f32,
     /// A surface of terminal cells
     pub surface: termwiz::surface::Surface,
+    /// The cells that have been drawn on with `add_pixel()`, `add_text()` or `draw_screen()`.
+    pub dirty_cells: DirtyCells,
 }
 
 impl Surface {
@@ -48,9 +156,20 @@ impl Surface {
             layer,
             opacity,
             surface: termwiz::surface::Surface::new(width, height),
+            dirty_cells: DirtyCells::new(width, height),
         }
     }
 
+    /// Roughly how many bytes of memory the surface's cells use. It doesn't count the text of
+    /// cells with graphemes that are too big to be stored inline.
+    #[must_use]
+    pub fn memory_size(&self) -> usize {
+        let cell_size = core::mem::size_of::<termwiz::cell::Cell>() + core::mem::size_of::<bool>();
+        self.width
+            .saturating_mul(self.height)
+            .saturating_mul(cell_size)
+    }
+
     /// Add a pixel ("▀", "▄") to a tattoy surface.
     ///
     /// The rule is that we default to rendering any pair of colours using the upper half block.
@@ -67,6 +186,7 @@ impl Surface {
             y: TermwizPosition::Absolute(row),
         });
 
+        self.dirty_cells.mark(col, row);
         let cell = self.get_cell_at(col, row)?;
         let is_empty_upper = cell.str() != "▀";
         let is_upper_half = y.rem_euclid(2) == 0;
@@ -119,12 +239,13 @@ impl Surface {
         Ok(())
     }
 
-    /// Overlay text at a given coord with the given colours.
+    /// Overlay text at a given coord with the given colours. Text that doesn't fit on the line is
+    /// cut off, rather than being wrapped onto the next line.
     pub fn add_text(
         &mut self,
         x: usize,
         y: usize,
-        text: String,
+        mut text: String,
         maybe_background_colour: Option<Colour>,
         maybe_foreground_colour: Option<Colour>,
     ) {
@@ -144,9 +265,26 @@ impl Surface {
             bg_colour,
             fg_colour,
         ]);
+        let fitted = crate::utils::truncate_to_width(&text, self.width.saturating_sub(x));
+        self.dirty_cells
+            .mark_run(x, y, crate::utils::display_width(fitted));
+        text.truncate(fitted.len());
         self.surface.add_change(text);
     }
 
+    /// Draw another screen onto the surface, with its top left corner at the given cell. Whatever
+    /// doesn't fit on the surface is cut off.
+    pub fn draw_screen(&mut self, screen: &termwiz::surface::Surface, x: usize, y: usize) {
+        self.surface.draw_from_screen(screen, x, y);
+
+        let (screen_width, screen_height) = screen.dimensions();
+        let width = screen_width.min(self.width.saturating_sub(x));
+        let height = screen_height.min(self.height.saturating_sub(y));
+        for row in y..y + height {
+            self.dirty_cells.mark_run(x, row, width);
+        }
+    }
+
     /// Make a Termwiz colour attribute
     #[must_use]
     pub const fn make_colour_attribute(colour: Colour) -> termwiz::color::ColorAttribute {
@@ -337,4 +475,66 @@ mod test {
         assert_eq!(first_cell.attrs().foreground(), fg);
         assert_eq!(first_cell.attrs().background(), bg);
     }
+
+    #[test]
+    fn tracking_dirty_cells() {
+        let mut surface = Surface::new("test".into(), 3, 3, -1, 1.0);
+        assert!(!surface.dirty_cells.is_dirty(0, 0));
+
+        surface.add_pixel(1, 3, WHITE).unwrap();
+        assert!(surface.dirty_cells.is_dirty(1, 1));
+        assert!(!surface.dirty_cells.is_dirty(0, 1));
+
+        surface.add_text(1, 0, "hi".into(), None, None);
+        assert!(surface.dirty_cells.is_dirty(1, 0));
+        assert!(surface.dirty_cells.is_dirty(2, 0));
+        assert!(!surface.dirty_cells.is_dirty(0, 0));
+        assert!(!surface.dirty_cells.is_dirty(0, 2));
+
+        surface.add_text(2, 2, "overflow".into(), None, None);
+        assert!(surface.dirty_cells.is_dirty(2, 2));
+        assert!(!surface.dirty_cells.is_dirty(0, 0));
+    }
+
+    #[test]
+    fn drawing_screens_marks_them_dirty() {
+        let mut surface = Surface::new("test".into(), 4, 3, -1, 1.0);
+        let screen = termwiz::surface::Surface::new(3, 2);
+        surface.draw_screen(&screen, 2, 1);
+        assert!(surface.dirty_cells.is_dirty(2, 1));
+        assert!(surface.dirty_cells.is_dirty(3, 2));
+        assert!(!surface.dirty_cells.is_dirty(1, 1));
+        assert!(!surface.dirty_cells.is_dirty(2, 0));
+    }
+
+    #[test]
+    fn cutting_off_text_at_the_end_of_the_line() {
+        let mut surface = Surface::new("test".into(), 4, 2, -1, 1.0);
+        surface.add_text(1, 0, "日本語".into(), None, None);
+        surface.add_text(3, 1, "日".into(), None, None);
+
+        let cells = surface.surface.screen_cells();
+        assert_eq!(cells[0][1].str(), "日");
+        assert_eq!(cells[0][3].str(), " ");
+        assert_eq!(cells[1][0].str(), " ");
+        assert_eq!(cells[1][3].str(), " ");
+    }
+
+    #[test]
+    fn measuring_memory_size() {
+        let blank = Surface::new("test".into(), 0, 0, -1, 1.0);
+        let small = Surface::new("test".into(), 10, 5, -1, 1.0);
+        let big = Surface::new("test".into(), 20, 5, -1, 1.0);
+        assert_eq!(blank.memory_size(), 0);
+        assert!(small.memory_size() > 0);
+        assert_eq!(big.memory_size(), small.memory_size() * 2);
+    }
+
+    #[test]
+    fn corner_positions() {
+        assert_eq!(Corner::TopLeft.origin((100, 40), (30, 4)), (0, 0));
+        assert_eq!(Corner::BottomRight.origin((100, 40), (30, 4)), (70, 36));
+        assert_eq!(Corner::TopRight.origin((100, 40), (30, 4)), (69, 0));
+        assert_eq!(Corner::BottomLeft.origin((10, 2), (30, 4)), (0, 0));
+    }
 }
@@ -0,0 +1,74 @@
+//! The user-configurable settings for the audio visualiser. They live apart from the visualiser
+//! itself so that configs can still be parsed when Tattoy is built without audio support.
+
+/// How to visualise the audio.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Style {
+    /// Bars showing the loudness of each frequency band.
+    #[default]
+    Spectrum,
+    /// The shape of the sound wave itself.
+    Waveform,
+}
+
+/// Where to draw the visualisation.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Placement {
+    /// Along the bottom rows of the terminal.
+    #[default]
+    Bottom,
+    /// Across the whole terminal.
+    Background,
+}
+
+/// User-configurable settings for the audio visualiser.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the audio visualiser.
+    pub enabled: bool,
+    /// Spectrum or waveform.
+    pub style: Style,
+    /// Along the bottom or across the whole terminal.
+    pub placement: Placement,
+    /// How many rows to use when placed along the bottom.
+    pub rows: u16,
+    /// How many frequency bands to show in the spectrum.
+    pub bars: usize,
+    /// Amplify the audio before visualising it.
+    pub sensitivity: f32,
+    /// The colours of the visualisation, from quiet to loud, as RGBA values between 0.0 and 1.0.
+    pub color_ramp: Vec<crate::surface::Colour>,
+    /// Capture from the first audio device whose name contains this text.
+    pub device: Option<String>,
+    /// The transparency of the visualisation.
+    pub opacity: f32,
+    /// The layer of the compositor on which the visualisation is rendered.
+    pub layer: i16,
+    /// Override the global frame rate for the visualisation.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            style: Style::Spectrum,
+            placement: Placement::Bottom,
+            rows: 4,
+            bars: 32,
+            sensitivity: 1.0,
+            color_ramp: vec![
+                (0.2, 0.4, 1.0, 1.0),
+                (0.8, 0.2, 0.9, 1.0),
+                (1.0, 0.3, 0.3, 1.0),
+            ],
+            device: None,
+            opacity: 0.8,
+            layer: -6,
+            frame_rate: None,
+        }
+    }
+}
@@ -0,0 +1,571 @@
+//! Visualise the audio that's currently playing, either as a spectrum of frequency bars or as a
+//! waveform. It can be drawn along the bottom of the terminal or across the whole background.
+//!
+//! Operating systems don't generally let applications listen to what other applications are
+//! playing, so we capture from an input device instead, such as a PulseAudio/PipeWire "monitor"
+//! device. The device always has to be set explicitly in the config, so that we never start
+//! listening to a microphone by accident.
+//!
+//! Capturing audio needs the system's audio libraries, eg ALSA on Linux, so the visualiser is only
+//! built with the `audio_visualiser` cargo feature.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+use cpal::traits::{DeviceTrait as _, HostTrait as _, StreamTrait as _};
+
+use super::config::{Config, Placement, Style};
+
+/// How many of the most recent samples are analysed on each frame.
+const SAMPLE_WINDOW: usize = 2048;
+
+/// The lowest frequency shown in the spectrum, in Hz.
+const MINIMUM_FREQUENCY: f32 = 40.0;
+
+/// The highest frequency shown in the spectrum, in Hz.
+const MAXIMUM_FREQUENCY: f32 = 16_000.0;
+
+/// How many frequencies within each band are measured to find the band's loudness.
+const PROBES_PER_BAND: u8 = 4;
+
+/// The range of loudness shown in the spectrum, in decibels below full volume.
+const DECIBEL_RANGE: f32 = 60.0;
+
+/// How quickly the spectrum's bars fall, as a fraction of their full height per frame.
+const BAR_FALL_SPEED: f32 = 0.05;
+
+/// How often the capture thread checks whether it should stop.
+const CAPTURE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// The most recent samples, mixed down to mono.
+type Samples = std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<f32>>>;
+
+/// Captures audio in a dedicated thread, because audio streams aren't `Send` on all platforms.
+/// The thread stops when this is dropped.
+struct Capture {
+    /// The most recent samples.
+    samples: Samples,
+    /// The sample rate of the captured audio, in Hz.
+    sample_rate: u32,
+    /// Tells the capture thread to stop.
+    is_stopped: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl Capture {
+    /// Start capturing audio.
+    async fn start(maybe_device_name: Option<String>) -> Result<Self> {
+        let device_name = maybe_device_name.context(
+            "The audio visualiser needs an audio device to capture from, set one with \
+            `audio_visualiser.device`",
+        )?;
+        let samples = Samples::default();
+        let is_stopped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+
+        let thread_samples = std::sync::Arc::clone(&samples);
+        let thread_is_stopped = std::sync::Arc::clone(&is_stopped);
+        std::thread::spawn(move || {
+            let (stream, sample_rate) = match Self::open_stream(&device_name, thread_samples) {
+                Ok(opened) => opened,
+                Err(error) => {
+                    if ready_tx.send(Err(error)).is_err() {
+                        tracing::error!("Audio capture was cancelled whilst starting");
+                    }
+                    return;
+                }
+            };
+            if ready_tx.send(Ok(sample_rate)).is_err() {
+                return;
+            }
+
+            while !thread_is_stopped.load(std::sync::atomic::Ordering::Relaxed) {
+                std::thread::sleep(CAPTURE_POLL_INTERVAL);
+            }
+            tracing::debug!("Stopping audio capture");
+            drop(stream);
+        });
+
+        let sample_rate = ready_rx.await??;
+        Ok(Self {
+            samples,
+            sample_rate,
+            is_stopped,
+        })
+    }
+
+    /// Find the device to capture from.
+    fn find_device(name: &str) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+        let device = cpal::default_host()
+            .input_devices()?
+            .find(|device| device.name().is_ok_and(|found| found.contains(name)))
+            .with_context(|| format!("Couldn't find an audio device called '{name}'"))?;
+        let config = device.default_input_config()?;
+        Ok((device, config))
+    }
+
+    /// Open an audio stream that feeds the captured samples into the shared buffer.
+    fn open_stream(name: &str, samples: Samples) -> Result<(cpal::Stream, u32)> {
+        let (device, config) = Self::find_device(name)?;
+        tracing::info!(
+            "Capturing audio from '{}' at {}Hz",
+            device.name().unwrap_or_default(),
+            config.sample_rate().0
+        );
+
+        let sample_rate = config.sample_rate().0;
+        let channels = usize::from(config.channels()).max(1);
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only support the most common sample formats"
+        )]
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => {
+                Self::build_stream::<f32>(&device, &config.into(), channels, samples)?
+            }
+            cpal::SampleFormat::I16 => {
+                Self::build_stream::<i16>(&device, &config.into(), channels, samples)?
+            }
+            cpal::SampleFormat::U16 => {
+                Self::build_stream::<u16>(&device, &config.into(), channels, samples)?
+            }
+            format => {
+                color_eyre::eyre::bail!("Unsupported audio sample format: {format}")
+            }
+        };
+        stream.play()?;
+
+        Ok((stream, sample_rate))
+    }
+
+    /// Build a stream for a specific sample format.
+    fn build_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        channels: usize,
+        samples: Samples,
+    ) -> Result<cpal::Stream>
+    where
+        T: cpal::SizedSample,
+        f32: cpal::FromSample<T>,
+    {
+        let stream = device.build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let Ok(mut buffer) = samples.lock() else {
+                    return;
+                };
+                for frame in data.chunks(channels) {
+                    let sum: f32 = frame
+                        .iter()
+                        .map(|sample| cpal::Sample::to_sample::<f32>(*sample))
+                        .sum();
+                    #[expect(
+                        clippy::as_conversions,
+                        clippy::cast_precision_loss,
+                        reason = "There are only ever a few channels"
+                    )]
+                    let mono = sum / frame.len() as f32;
+                    buffer.push_back(mono);
+                }
+                while buffer.len() > SAMPLE_WINDOW {
+                    buffer.pop_front();
+                }
+            },
+            |error| tracing::error!("Audio capture error: {error:?}"),
+            None,
+        )?;
+        Ok(stream)
+    }
+
+    /// A copy of the most recent samples.
+    fn latest(&self) -> Vec<f32> {
+        self.samples
+            .lock()
+            .map(|samples| samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        self.is_stopped
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// The loudness of a single frequency, using the Goertzel algorithm. It's much cheaper than a
+/// full Fourier transform when we only need a few frequencies.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "The sample window is small"
+)]
+fn frequency_amplitude(samples: &[f32], sample_rate: f32, frequency: f32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let length = samples.len() as f32;
+    let coefficient = 2.0 * (core::f32::consts::TAU * frequency / sample_rate).cos();
+    let (mut previous, mut before_previous) = (0.0_f32, 0.0_f32);
+    for (index, sample) in samples.iter().enumerate() {
+        // A Hann window, to stop the edges of the window leaking into every frequency.
+        let phase = core::f32::consts::TAU * index as f32 / length;
+        let window = (-0.5_f32).mul_add(phase.cos(), 0.5);
+        let current = sample.mul_add(window, coefficient.mul_add(previous, -before_previous));
+        before_previous = previous;
+        previous = current;
+    }
+
+    let power = previous.mul_add(
+        previous,
+        before_previous.mul_add(before_previous, -(coefficient * previous * before_previous)),
+    );
+    // A full volume sine wave has a windowed amplitude of a quarter of the window's length.
+    power.max(0.0).sqrt() * 4.0 / length
+}
+
+/// The loudness of each frequency band, between 0.0 and 1.0. The bands are spaced
+/// logarithmically, because that's how we hear pitch.
+fn spectrum(samples: &[f32], sample_rate: f32, bars: usize, sensitivity: f32) -> Vec<f32> {
+    let maximum = MAXIMUM_FREQUENCY.min(sample_rate / 2.0);
+    let ratio = maximum / MINIMUM_FREQUENCY;
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "There are only ever a few bars"
+    )]
+    let bar_count = bars as f32;
+
+    (0..bars)
+        .map(|bar| {
+            #[expect(
+                clippy::as_conversions,
+                clippy::cast_precision_loss,
+                reason = "There are only ever a few bars"
+            )]
+            let start = bar as f32 / bar_count;
+            let amplitude = (0..PROBES_PER_BAND)
+                .map(|probe| {
+                    let offset = f32::from(probe) / f32::from(PROBES_PER_BAND) / bar_count;
+                    let frequency = MINIMUM_FREQUENCY * ratio.powf(start + offset);
+                    frequency_amplitude(samples, sample_rate, frequency)
+                })
+                .fold(0.0, f32::max);
+            loudness(amplitude * sensitivity)
+        })
+        .collect()
+}
+
+/// Convert an amplitude to a loudness between 0.0 and 1.0, using a decibel scale.
+fn loudness(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return 0.0;
+    }
+    let decibels = 20.0 * amplitude.log10();
+    ((decibels + DECIBEL_RANGE) / DECIBEL_RANGE).clamp(0.0, 1.0)
+}
+
+/// The lowest and highest sample in each column, for drawing a waveform.
+fn waveform(samples: &[f32], columns: usize, sensitivity: f32) -> Vec<(f32, f32)> {
+    if samples.is_empty() || columns == 0 {
+        return vec![(0.0, 0.0); columns];
+    }
+
+    let chunk_size = samples.len().div_ceil(columns).max(1);
+    let mut extents: Vec<(f32, f32)> = samples
+        .chunks(chunk_size)
+        .map(|chunk| {
+            chunk.iter().fold((f32::MAX, f32::MIN), |(low, high), sample| {
+                let amplified = (sample * sensitivity).clamp(-1.0, 1.0);
+                (low.min(amplified), high.max(amplified))
+            })
+        })
+        .collect();
+    extents.resize(columns, (0.0, 0.0));
+    extents
+}
+
+/// Pick a colour from the ramp, where 0.0 is the first colour and 1.0 is the last.
+fn ramp(colours: &[crate::surface::Colour], position: f32) -> crate::surface::Colour {
+    let Some(first) = colours.first() else {
+        return (1.0, 1.0, 1.0, 1.0);
+    };
+
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "There are only ever a few colours"
+    )]
+    let scaled = position.clamp(0.0, 1.0) * colours.len().saturating_sub(1) as f32;
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "The position is clamped to the ramp"
+    )]
+    let index = scaled.floor() as usize;
+    let fraction = scaled - scaled.floor();
+    let from = colours.get(index).unwrap_or(first);
+    let to = colours.get(index + 1).unwrap_or(from);
+    let mix = |start: f32, end: f32| (end - start).mul_add(fraction, start);
+
+    (
+        mix(from.0, to.0),
+        mix(from.1, to.1),
+        mix(from.2, to.2),
+        mix(from.3, to.3),
+    )
+}
+
+/// `AudioVisualiser`
+pub(crate) struct AudioVisualiser {
+    /// The base Tattoy struct
+    tattoy: crate::tattoys::tattoyer::Tattoyer,
+    /// The audio visualiser's config
+    config: Config,
+    /// The audio capture
+    capture: Capture,
+    /// The currently displayed height of each spectrum bar, so that bars fall smoothly.
+    levels: Vec<f32>,
+}
+
+impl AudioVisualiser {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<Self> {
+        let config = state.config.load().audio_visualiser.clone();
+        let capture = Capture::start(config.device.clone()).await?;
+        let tattoy = crate::tattoys::tattoyer::Tattoyer::new(
+            "audio_visualiser".to_owned(),
+            state,
+            config.layer,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Ok(Self {
+            tattoy,
+            config,
+            capture,
+            levels: Vec::new(),
+        })
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut visualiser = Self::new(output, state).await?;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = visualiser.tattoy.sleep_until_next_frame_tick() => {
+                    visualiser.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = visualiser
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::AudioVisualiser,
+                            |config| config.audio_visualiser.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    visualiser.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        if let crate::run::Protocol::Config { config, .. } = &message {
+            let new_config = config.audio_visualiser.clone();
+            if new_config.device != self.config.device {
+                self.capture = Capture::start(new_config.device.clone()).await?;
+            }
+            self.tattoy.opacity = new_config.opacity;
+            self.tattoy.layer = new_config.layer;
+            self.config = new_config;
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)
+    }
+
+    /// The area to draw in, as the top row and the number of rows.
+    fn region(&self) -> (usize, usize) {
+        let height = usize::from(self.tattoy.height);
+        match self.config.placement {
+            Placement::Bottom => {
+                let rows = usize::from(self.config.rows).min(height);
+                (height - rows, rows)
+            }
+            Placement::Background => (0, height),
+        }
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let samples = self.capture.latest();
+        let width = usize::from(self.tattoy.width);
+        let (top, rows) = self.region();
+        let top_pixel = top * 2;
+        let pixel_rows = rows * 2;
+        if width == 0 || pixel_rows == 0 {
+            return self.tattoy.send_output().await;
+        }
+
+        match self.config.style {
+            Style::Spectrum => self.render_spectrum(&samples, width, top_pixel, pixel_rows)?,
+            Style::Waveform => self.render_waveform(&samples, width, top_pixel, pixel_rows)?,
+        }
+
+        self.tattoy.send_output().await
+    }
+
+    /// Draw the frequency bars.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss,
+        reason = "Positions are always within the bounds of the terminal"
+    )]
+    fn render_spectrum(
+        &mut self,
+        samples: &[f32],
+        width: usize,
+        top_pixel: usize,
+        pixel_rows: usize,
+    ) -> Result<()> {
+        let bars = self.config.bars.clamp(1, width);
+        let latest = spectrum(
+            samples,
+            self.capture.sample_rate as f32,
+            bars,
+            self.config.sensitivity,
+        );
+        self.levels.resize(bars, 0.0);
+        for (level, new) in self.levels.iter_mut().zip(latest) {
+            *level = new.max(*level - BAR_FALL_SPEED);
+        }
+
+        for (bar, level) in self.levels.iter().enumerate() {
+            let left = (bar * width).div_euclid(bars);
+            let right = ((bar + 1) * width).div_euclid(bars).max(left + 1);
+            let height = (level * pixel_rows as f32).round() as usize;
+            for y in 0..height.min(pixel_rows) {
+                let colour = ramp(&self.config.color_ramp, y as f32 / pixel_rows as f32);
+                let pixel_y = top_pixel + pixel_rows - 1 - y;
+                for x in left..right.min(width) {
+                    self.tattoy.surface.add_pixel(x, pixel_y, colour)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw the sound wave.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss,
+        reason = "Positions are always within the bounds of the terminal"
+    )]
+    fn render_waveform(
+        &mut self,
+        samples: &[f32],
+        width: usize,
+        top_pixel: usize,
+        pixel_rows: usize,
+    ) -> Result<()> {
+        let half_height = (pixel_rows - 1) as f32 / 2.0;
+        let to_pixel =
+            |value: f32| value.mul_add(-half_height, half_height).round().max(0.0) as usize;
+
+        for (x, (low, high)) in waveform(samples, width, self.config.sensitivity)
+            .into_iter()
+            .enumerate()
+        {
+            let colour = ramp(&self.config.color_ramp, low.abs().max(high.abs()));
+            for y in to_pixel(high)..=to_pixel(low).min(pixel_rows - 1) {
+                self.tattoy.surface.add_pixel(x, top_pixel + y, colour)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "It's just a test"
+    )]
+    fn sine(frequency: f32, sample_rate: f32) -> Vec<f32> {
+        (0..SAMPLE_WINDOW)
+            .map(|index| (core::f32::consts::TAU * frequency * index as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn spectrum_peaks_at_the_played_frequency() {
+        let levels = spectrum(&sine(1000.0, 48_000.0), 48_000.0, 16, 1.0);
+        let loudest = levels
+            .iter()
+            .enumerate()
+            .max_by(|left, right| left.1.total_cmp(right.1))
+            .map(|(index, _)| index);
+        // 1000Hz is 8/16ths of the way between 40Hz and 16kHz on a logarithmic scale.
+        assert_eq!(loudest, Some(8));
+        assert!(levels.get(8).is_some_and(|level| *level > 0.5));
+        assert!(levels.first().is_some_and(|level| *level < 0.5));
+    }
+
+    #[test]
+    fn silence_is_flat() {
+        let silence = vec![0.0; SAMPLE_WINDOW];
+        assert!(spectrum(&silence, 48_000.0, 8, 1.0)
+            .iter()
+            .all(|level| *level <= 0.0));
+        assert_eq!(waveform(&silence, 4, 1.0), vec![(0.0, 0.0); 4]);
+    }
+
+    #[test]
+    fn waveform_extents() {
+        let samples = [0.1, -0.2, 0.5, 0.3];
+        assert_eq!(waveform(&samples, 2, 2.0), vec![(-0.4, 0.2), (0.6, 1.0)]);
+    }
+
+    #[test]
+    fn colour_ramp() {
+        let colours = [(0.0, 0.0, 0.0, 1.0), (1.0, 0.5, 0.0, 1.0)];
+        assert_eq!(ramp(&colours, 0.0), (0.0, 0.0, 0.0, 1.0));
+        assert_eq!(ramp(&colours, 0.5), (0.5, 0.25, 0.0, 1.0));
+        assert_eq!(ramp(&colours, 2.0), (1.0, 0.5, 0.0, 1.0));
+        assert_eq!(ramp(&[], 0.5), (1.0, 1.0, 1.0, 1.0));
+    }
+}
@@ -1,11 +1,55 @@
-//! Run and output a command in the background.
+//! Run and output a command in the background. The command either runs in its own headless
+//! terminal, or, with `watch_interval`, is re-run every so often with only its final output
+//! rendered, like the `watch` command.
 
 use std::sync::Arc;
 
 use color_eyre::eyre::{ContextCompat as _, Result};
 
+/// When to re-run the command after it exits.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Restart {
+    /// Don't re-run the command, the background command tattoy just ends.
+    #[default]
+    Never,
+    /// Re-run the command straight after it exits.
+    OnExit,
+    /// Re-run the command a set number of seconds after it exits, like the `watch` command.
+    Interval,
+}
+
+/// A rectangle of the terminal to render the command in, rather than the whole terminal. The
+/// command's own terminal is run at the size of the region.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Region {
+    /// The corner of the terminal that the region is placed in.
+    pub gravity: crate::surface::Corner,
+    /// The number of columns between the region and the side of the terminal of its corner.
+    pub x: u16,
+    /// The number of rows between the region and the top or bottom of the terminal of its corner.
+    pub y: u16,
+    /// The width of the region in columns.
+    pub width: u16,
+    /// The height of the region in rows.
+    pub height: u16,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self {
+            gravity: crate::surface::Corner::BottomRight,
+            x: 0,
+            y: 0,
+            width: 40,
+            height: 12,
+        }
+    }
+}
+
 /// User-configurable settings for the background command.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the script
@@ -18,6 +62,17 @@ pub(crate) struct Config {
     command: Vec<String>,
     /// Whether the command is expected to exit or not.
     expect_exit: bool,
+    /// When to re-run the command after it exits.
+    restart: Restart,
+    /// The number of seconds to wait before re-running the command when `restart = "interval"`.
+    interval: f32,
+    /// Override the global frame rate for the background command.
+    pub frame_rate: Option<u32>,
+    /// Only render the command in a part of the terminal.
+    pub region: Option<Region>,
+    /// Rather than running the command in its own terminal, re-run it every this many seconds and
+    /// only render its final output. It's much cheaper for short commands, like `git status`.
+    watch_interval: Option<f32>,
 }
 
 impl Default for Config {
@@ -28,10 +83,181 @@ impl Default for Config {
             layer: -8,
             command: vec!["echo".to_owned(), "No command provided".to_owned()],
             expect_exit: false,
+            restart: Restart::Never,
+            interval: 60.0,
+            frame_rate: None,
+            region: None,
+            watch_interval: None,
+        }
+    }
+}
+
+/// The delay before re-running a command with `restart = "on-exit"`. It stops a command that
+/// fails immediately from being restarted in a busy loop.
+const ON_EXIT_RESTART_DELAY: f32 = 1.0;
+
+/// The shortest allowed interval between re-runs of the command.
+const MINIMUM_INTERVAL: f32 = 0.1;
+
+/// How long to wait before re-running the command after it exits, if it should be re-run at all.
+fn restart_delay(restart: Restart, interval: f32) -> Option<std::time::Duration> {
+    match restart {
+        Restart::Never => None,
+        Restart::OnExit => Some(std::time::Duration::from_secs_f32(ON_EXIT_RESTART_DELAY)),
+        Restart::Interval => Some(std::time::Duration::from_secs_f32(
+            interval.max(MINIMUM_INTERVAL),
+        )),
+    }
+}
+
+/// The title of the notification for the command exiting.
+fn describe_exit(exit_code: Option<u32>) -> String {
+    match exit_code {
+        Some(code) => format!("Background command exited with status {code}"),
+        None => "Background command exited".to_owned(),
+    }
+}
+
+/// The number of columns between tab stops in watched output.
+const TAB_WIDTH: usize = 8;
+
+/// Whether a config update switches the command between running in its own terminal and being
+/// watched.
+fn is_mode_switched(message: &crate::run::Protocol, is_watching: bool) -> bool {
+    let crate::run::Protocol::Config { config, .. } = message else {
+        return false;
+    };
+    config.bg_command.watch_interval.is_some() != is_watching
+}
+
+/// Apply an SGR escape code, like a colour change, to the current attributes.
+fn apply_sgr(attributes: &mut termwiz::cell::CellAttributes, sgr: termwiz::escape::csi::Sgr) {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "Fonts and the like can't be shown in a tattoy"
+    )]
+    match sgr {
+        termwiz::escape::csi::Sgr::Reset => *attributes = termwiz::cell::CellAttributes::default(),
+        termwiz::escape::csi::Sgr::Intensity(intensity) => {
+            attributes.set_intensity(intensity);
+        }
+        termwiz::escape::csi::Sgr::Underline(underline) => {
+            attributes.set_underline(underline);
+        }
+        termwiz::escape::csi::Sgr::Italic(is_italic) => {
+            attributes.set_italic(is_italic);
+        }
+        termwiz::escape::csi::Sgr::Inverse(is_inverse) => {
+            attributes.set_reverse(is_inverse);
         }
+        termwiz::escape::csi::Sgr::StrikeThrough(is_struck) => {
+            attributes.set_strikethrough(is_struck);
+        }
+        termwiz::escape::csi::Sgr::Invisible(is_invisible) => {
+            attributes.set_invisible(is_invisible);
+        }
+        termwiz::escape::csi::Sgr::Foreground(colour) => {
+            attributes.set_foreground(colour);
+        }
+        termwiz::escape::csi::Sgr::Background(colour) => {
+            attributes.set_background(colour);
+        }
+        _ => (),
     }
 }
 
+/// Use the given attributes, in true colour, for the text that's rendered next.
+fn set_attributes(
+    surface: &mut termwiz::surface::Surface,
+    attributes: &termwiz::cell::CellAttributes,
+    palette: &crate::palette::converter::Palette,
+) {
+    let mut true_colour = attributes.clone();
+    palette.cell_attributes_to_true_colour(&mut true_colour);
+    surface.add_change(termwiz::surface::Change::AllAttributes(true_colour));
+}
+
+/// Render a command's output, with its colours, onto a surface. Only text and colours are
+/// rendered, there's no cursor movement, so it's only suitable for commands that just print.
+/// Output below the bottom of the surface is dropped, like `watch` does.
+fn render_output(
+    bytes: &[u8],
+    width: u16,
+    height: u16,
+    palette: &crate::palette::converter::Palette,
+) -> termwiz::surface::Surface {
+    let mut surface = termwiz::surface::Surface::new(width.into(), height.into());
+    let mut attributes = termwiz::cell::CellAttributes::default();
+    set_attributes(&mut surface, &attributes, palette);
+
+    let mut is_full = false;
+    let mut parser = termwiz::escape::parser::Parser::new();
+    parser.parse(bytes, |action| {
+        if is_full {
+            return;
+        }
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only text and colours are rendered"
+        )]
+        match action {
+            termwiz::escape::Action::Print(character) => {
+                surface.add_change(character.to_string());
+            }
+            termwiz::escape::Action::PrintString(text) => {
+                surface.add_change(text);
+            }
+            termwiz::escape::Action::Control(termwiz::escape::ControlCode::LineFeed) => {
+                if surface.cursor_position().1 + 1 >= usize::from(height) {
+                    is_full = true;
+                    return;
+                }
+                surface.add_change("\r\n");
+            }
+            termwiz::escape::Action::Control(termwiz::escape::ControlCode::CarriageReturn) => {
+                surface.add_change("\r");
+            }
+            termwiz::escape::Action::Control(termwiz::escape::ControlCode::HorizontalTab) => {
+                let column = surface.cursor_position().0;
+                surface.add_change(" ".repeat(TAB_WIDTH - column % TAB_WIDTH));
+            }
+            termwiz::escape::Action::CSI(termwiz::escape::CSI::Sgr(sgr)) => {
+                apply_sgr(&mut attributes, sgr);
+                set_attributes(&mut surface, &attributes, palette);
+            }
+            _ => (),
+        }
+    });
+
+    surface
+}
+
+/// The position and size of the command's region, as `(x, y, width, height)`, for a terminal of
+/// the given size. Without a region the command fills the whole terminal.
+fn region_rectangle(region: Option<Region>, terminal: (u16, u16)) -> (u16, u16, u16, u16) {
+    let Some(region) = region else {
+        return (0, 0, terminal.0, terminal.1);
+    };
+
+    let width = region.width.clamp(1, terminal.0.max(1));
+    let height = region.height.clamp(1, terminal.1.max(1));
+    let right = terminal.0.saturating_sub(width);
+    let bottom = terminal.1.saturating_sub(height);
+    let (x, y) = match region.gravity {
+        crate::surface::Corner::TopLeft => (region.x.min(right), region.y.min(bottom)),
+        crate::surface::Corner::TopRight => (right.saturating_sub(region.x), region.y.min(bottom)),
+        crate::surface::Corner::BottomLeft => {
+            (region.x.min(right), bottom.saturating_sub(region.y))
+        }
+        crate::surface::Corner::BottomRight => (
+            right.saturating_sub(region.x),
+            bottom.saturating_sub(region.y),
+        ),
+    };
+
+    (x, y, width, height)
+}
+
 /// `BGCommand`
 pub struct BGCommand {
     /// The base Tattoy struct
@@ -42,6 +268,16 @@ pub struct BGCommand {
     palette: crate::palette::converter::Palette,
     /// The command to run
     command: Vec<String>,
+    /// The part of the terminal that the command is rendered in.
+    region: Option<Region>,
+    /// The command's screen, at the size of its region.
+    screen: termwiz::surface::Surface,
+    /// The exit code of the command, once it's exited.
+    exit_code: Option<u32>,
+    /// Whether the command's exit has already been handled.
+    is_exited: bool,
+    /// When to re-run the command, if it's exited and is configured to be re-run.
+    restart_at: Option<tokio::time::Instant>,
 }
 
 impl BGCommand {
@@ -54,18 +290,41 @@ impl BGCommand {
         let tattoy = super::tattoyer::Tattoyer::new(
             "bg_command".to_owned(),
             Arc::clone(state),
-            state.config.read().await.bg_command.layer,
-            state.config.read().await.bg_command.opacity,
+            state.config.load().bg_command.layer,
+            state.config.load().bg_command.opacity,
             output_channel,
         )
         .await;
 
-        let command = state.config.read().await.bg_command.command.clone();
+        let command = state.config.load().bg_command.command.clone();
+        let region = state.config.load().bg_command.region;
+        let (_, _, width, height) = region_rectangle(region, (tattoy.width, tattoy.height));
+        let shadow_terminal = Self::start_terminal(&command, width, height);
+
+        Self {
+            tattoy,
+            shadow_terminal,
+            palette,
+            command,
+            region,
+            screen: termwiz::surface::Surface::new(width.into(), height.into()),
+            exit_code: None,
+            is_exited: false,
+            restart_at: None,
+        }
+    }
+
+    /// Start the command in a headless terminal.
+    fn start_terminal(
+        command: &[String],
+        width: u16,
+        height: u16,
+    ) -> shadow_terminal::active_terminal::ActiveTerminal {
         let _span = tracing::span!(tracing::Level::TRACE, "BGCommand").entered();
         let shadow_terminal = shadow_terminal::active_terminal::ActiveTerminal::start(
             shadow_terminal::shadow_terminal::Config {
-                width: tattoy.width,
-                height: tattoy.height,
+                width,
+                height,
                 command: command.iter().map(std::convert::Into::into).collect(),
                 scrollback_size: 100,
                 scrollback_step: 1,
@@ -73,11 +332,45 @@ impl BGCommand {
         );
 
         tracing::debug!("Started BG Command for: `{}`", command.join(" "));
-        Self {
-            tattoy,
-            shadow_terminal,
-            palette,
-            command,
+        shadow_terminal
+    }
+
+    /// Replace the running command with a new one, without restarting the whole tattoy.
+    fn restart_terminal(&mut self, command: Vec<String>) -> Result<()> {
+        tracing::info!("Restarting background command");
+        if !self.shadow_terminal.task_handle.is_finished() {
+            self.shadow_terminal.kill()?;
+        }
+        let (_, _, width, height) = self.rectangle((self.tattoy.width, self.tattoy.height));
+        self.shadow_terminal = Self::start_terminal(&command, width, height);
+        self.command = command;
+        self.exit_code = None;
+        self.is_exited = false;
+        self.restart_at = None;
+        Ok(())
+    }
+
+    /// The position and size of the command's region in a terminal of the given size.
+    fn rectangle(&self, terminal: (u16, u16)) -> (u16, u16, u16, u16) {
+        region_rectangle(self.region, terminal)
+    }
+
+    /// Resize the command's terminal to fit its region in a terminal of the given size.
+    fn resize_terminal(&mut self, terminal: (u16, u16)) -> Result<()> {
+        let (_, _, width, height) = self.rectangle(terminal);
+        self.screen.resize(width.into(), height.into());
+        if !self.shadow_terminal.task_handle.is_finished() {
+            self.shadow_terminal.resize(width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Schedule the command to be re-run, according to its restart policy.
+    fn schedule_restart(&mut self, config: &Config) {
+        self.restart_at = restart_delay(config.restart, config.interval)
+            .map(|delay| tokio::time::Instant::now() + delay);
+        if let Some(restart_at) = self.restart_at {
+            tracing::debug!("Background command will restart at: {restart_at:?}");
         }
     }
 
@@ -87,19 +380,66 @@ impl BGCommand {
         state: std::sync::Arc<crate::shared_state::SharedState>,
         palette: crate::palette::converter::Palette,
     ) -> Result<()> {
+        loop {
+            let is_watching = state.config.load().bg_command.watch_interval.is_some();
+            let is_mode_switched = if is_watching {
+                Watcher::run(output.clone(), &state, palette.clone()).await?
+            } else {
+                Self::run(output.clone(), &state, palette.clone()).await?
+            };
+            if !is_mode_switched {
+                break;
+            }
+            tracing::info!("Switching how the background command is run");
+        }
+
+        Ok(())
+    }
+
+    /// Run the command in its own terminal. Returns whether the command should now be watched
+    /// instead.
+    async fn run(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<bool> {
         let mut protocol = state.protocol_tx.subscribe();
-        let mut commander = Self::new(output, &state, palette).await;
+        let mut commander = Self::new(output, state, palette).await;
 
         #[expect(
             clippy::integer_division_remainder_used,
             reason = "This is caused by the `tokio::select!`"
         )]
         loop {
+            let restart_at = commander
+                .restart_at
+                .unwrap_or_else(tokio::time::Instant::now);
             tokio::select! {
+                () = tokio::time::sleep_until(restart_at), if commander.restart_at.is_some() => {
+                    commander.restart_terminal(commander.command.clone())?;
+                }
                 Some(pty_output) = commander.shadow_terminal.surface_output_rx.recv() => {
                     commander.handle_bg_command_output(pty_output).await?;
                 }
                 Ok(message) = protocol.recv() => {
+                    let is_disabled = commander
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::BgCommand,
+                            |config| config.bg_command.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        commander.shadow_terminal.kill()?;
+                        break;
+                    }
+                    if is_mode_switched(&message, false) {
+                        if !commander.shadow_terminal.task_handle.is_finished() {
+                            commander.shadow_terminal.kill()?;
+                        }
+                        return Ok(true);
+                    }
                     commander.handle_protocol_message(&message)?;
                     if matches!(message, crate::run::Protocol::End) {
                         commander.dump_last_known_output();
@@ -108,15 +448,15 @@ impl BGCommand {
                     commander.tattoy.handle_common_protocol_messages(message)?;
                 }
                 () = commander.tattoy.sleep_until_next_frame_tick() => {
-                    let is_exited = commander.check_for_exit_and_notify(&state).await?;
-                    if is_exited {
+                    let is_finished = commander.check_for_exit_and_notify(state).await?;
+                    if is_finished {
                         break;
                     }
                 }
             }
         }
 
-        Ok(())
+        Ok(false)
     }
 
     /// Handle output from the headless terminal where the background command was spawned.
@@ -125,8 +465,13 @@ impl BGCommand {
         mut output: shadow_terminal::output::Output,
     ) -> Result<()> {
         self.palette.convert_cells_to_true_colour(&mut output);
-        self.tattoy.opacity = self.tattoy.state.config.read().await.bg_command.opacity;
-        self.tattoy.layer = self.tattoy.state.config.read().await.bg_command.layer;
+        self.tattoy.opacity = self.tattoy.state.config.load().bg_command.opacity;
+        self.tattoy.layer = self.tattoy.state.config.load().bg_command.layer;
+
+        if let shadow_terminal::output::Output::Exited(code) = output {
+            self.exit_code = Some(code);
+            return Ok(());
+        }
 
         #[expect(
             clippy::collapsible_match,
@@ -137,38 +482,58 @@ impl BGCommand {
         match output {
             shadow_terminal::output::Output::Diff(surface_diff) => match surface_diff {
                 shadow_terminal::output::SurfaceDiff::Screen(screen_diff) => {
-                    self.tattoy.surface.surface.add_changes(screen_diff.changes);
+                    self.screen.add_changes(screen_diff.changes);
                 }
                 _ => (),
             },
             shadow_terminal::output::Output::Complete(complete_surface) => match complete_surface {
                 shadow_terminal::output::CompleteSurface::Screen(complete_screen) => {
-                    self.tattoy.initialise_surface();
-                    self.tattoy.surface.surface = complete_screen.surface;
+                    self.screen = complete_screen.surface;
                 }
                 _ => (),
             },
             _ => (),
         }
 
+        self.tattoy.initialise_surface();
+        let (x, y, _, _) = self.rectangle((self.tattoy.width, self.tattoy.height));
+        self.tattoy
+            .surface
+            .draw_screen(&self.screen, x.into(), y.into());
         self.tattoy.send_output().await?;
 
         Ok(())
     }
 
     /// Custom behaviour for protocol messages.
-    fn handle_protocol_message(&self, message: &crate::run::Protocol) -> Result<()> {
+    fn handle_protocol_message(&mut self, message: &crate::run::Protocol) -> Result<()> {
         #[expect(
             clippy::wildcard_enum_match_arm,
             reason = "We're ready to add handlers for other messages"
         )]
         match message {
             crate::run::Protocol::Resize { width, height } => {
-                self.shadow_terminal.resize(*width, *height)?;
+                self.resize_terminal((*width, *height))?;
             }
             crate::run::Protocol::End => {
                 self.shadow_terminal.kill()?;
             }
+            crate::run::Protocol::Config { config, changes } => {
+                let is_command_changed = changes.contains(&crate::config::diff::Section::BgCommand)
+                    && config.bg_command.command != self.command;
+                if config.bg_command.region != self.region {
+                    self.region = config.bg_command.region;
+                    self.resize_terminal((self.tattoy.width, self.tattoy.height))?;
+                }
+                if is_command_changed {
+                    self.restart_terminal(config.bg_command.command.clone())?;
+                } else if self.is_exited {
+                    self.schedule_restart(&config.bg_command);
+                }
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
             _ => (),
         }
 
@@ -176,11 +541,22 @@ impl BGCommand {
     }
 
     /// Check if the Shadow Terminal has exited and if so, notify the user of the last known output.
+    /// Returns whether the tattoy should end, which it doesn't when the command is going to be
+    /// re-run.
     async fn check_for_exit_and_notify(
         &mut self,
         state: &std::sync::Arc<crate::shared_state::SharedState>,
     ) -> Result<bool> {
-        if !self.shadow_terminal.task_handle.is_finished() {
+        if self.is_exited || !self.shadow_terminal.task_handle.is_finished() {
+            return Ok(false);
+        }
+        self.is_exited = true;
+
+        let config = state.config.load().bg_command.clone();
+        self.schedule_restart(&config);
+        let is_restarting = self.restart_at.is_some();
+        let is_failure = self.exit_code.is_some_and(|code| code != 0);
+        if is_restarting && self.exit_code == Some(0) {
             return Ok(false);
         }
 
@@ -189,9 +565,9 @@ impl BGCommand {
         last_known_output.truncate(max_output.into());
 
         let is_empty_output = last_known_output.trim().is_empty();
-        let is_unexpected_exit = !state.config.read().await.bg_command.expect_exit;
+        let is_unexpected_exit = !config.expect_exit || is_failure;
         if !is_unexpected_exit && !is_empty_output {
-            return Ok(true);
+            return Ok(!is_restarting);
         }
 
         if is_empty_output {
@@ -208,24 +584,25 @@ impl BGCommand {
 
         state
             .send_notification(
-                "Background command exited",
+                &describe_exit(self.exit_code),
                 crate::tattoys::notifications::message::Level::Error,
                 Some(last_known_output),
                 true,
             )
             .await;
 
-        Ok(true)
+        Ok(!is_restarting)
     }
 
     /// Get the last known output of the command, log and return it.
     fn dump_last_known_output(&mut self) -> std::string::String {
         let mut output = String::new();
-        for cell_line in self.tattoy.surface.surface.screen_cells() {
+        let width = self.screen.dimensions().0;
+        for cell_line in self.screen.screen_cells() {
             let mut line = String::new();
             for (x, cell) in cell_line.iter().enumerate() {
                 line.push_str(cell.str());
-                if x == usize::from(self.tattoy.width) - 4 && !line.contains('\n') {
+                if x == width.saturating_sub(4) && !line.contains('\n') {
                     line.push('…');
                     break;
                 }
@@ -245,3 +622,235 @@ impl BGCommand {
         output.trim().into()
     }
 }
+
+/// Re-runs a short command every `watch_interval` seconds and renders its final output, like the
+/// `watch` command. There's no terminal or long-running process to keep alive in between.
+struct Watcher {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The user's terminal's colour palette in true colour values.
+    palette: crate::palette::converter::Palette,
+    /// The command's most recent output, at the size of its region.
+    screen: termwiz::surface::Surface,
+}
+
+impl Watcher {
+    /// Watch the command. Returns whether the command should now run in its own terminal instead.
+    async fn run(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<bool> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "bg_command".to_owned(),
+            Arc::clone(state),
+            state.config.load().bg_command.layer,
+            state.config.load().bg_command.opacity,
+            output,
+        )
+        .await;
+        let mut watcher = Self {
+            tattoy,
+            palette,
+            screen: termwiz::surface::Surface::new(0, 0),
+        };
+        let mut next_run = tokio::time::Instant::now();
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = tokio::time::sleep_until(next_run) => {
+                    let config = state.config.load().bg_command.clone();
+                    let interval = std::time::Duration::from_secs_f32(
+                        config.watch_interval.unwrap_or_default().max(MINIMUM_INTERVAL),
+                    );
+                    next_run = tokio::time::Instant::now() + interval;
+                    watcher.refresh(&config, interval).await?;
+                }
+                Ok(message) = protocol.recv() => {
+                    let is_disabled = watcher
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::BgCommand,
+                            |config| config.bg_command.enabled,
+                        )
+                        .await?;
+                    if is_disabled || matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if is_mode_switched(&message, true) {
+                        return Ok(true);
+                    }
+                    if let crate::run::Protocol::Palette(palette) = &message {
+                        watcher.palette = palette.clone();
+                    }
+                    let is_moved = matches!(
+                        message,
+                        crate::run::Protocol::Resize { .. } | crate::run::Protocol::Config { .. }
+                    );
+                    watcher.tattoy.handle_common_protocol_messages(message)?;
+                    if is_moved {
+                        let config = state.config.load().bg_command.clone();
+                        watcher.render(&config).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Re-run the command and render its output. Commands that take longer than the interval are
+    /// stopped, so that they can't pile up.
+    async fn refresh(&mut self, config: &Config, timeout: std::time::Duration) -> Result<()> {
+        let (_, _, width, height) =
+            region_rectangle(config.region, (self.tattoy.width, self.tattoy.height));
+        let Some((program, arguments)) = config.command.split_first() else {
+            return Ok(());
+        };
+
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(arguments)
+            .env("COLUMNS", width.to_string())
+            .env("LINES", height.to_string())
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+        let bytes = match tokio::time::timeout(timeout, command.output()).await {
+            Ok(Ok(mut output)) => {
+                output.stdout.append(&mut output.stderr);
+                output.stdout
+            }
+            Ok(Err(error)) => format!("Couldn't run `{program}`: {error}").into_bytes(),
+            Err(_) => {
+                tracing::warn!("Background command took longer than `watch_interval`, stopped it");
+                return Ok(());
+            }
+        };
+
+        self.screen = render_output(&bytes, width, height, &self.palette);
+        self.render(config).await
+    }
+
+    /// Render the command's most recent output in its region.
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.layer = config.layer;
+        self.tattoy.initialise_surface();
+        let (x, y, _, _) = region_rectangle(config.region, (self.tattoy.width, self.tattoy.height));
+        self.tattoy
+            .surface
+            .draw_screen(&self.screen, x.into(), y.into());
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delaying_restarts() {
+        assert_eq!(restart_delay(Restart::Never, 5.0), None);
+        assert_eq!(
+            restart_delay(Restart::OnExit, 5.0),
+            Some(std::time::Duration::from_secs(1))
+        );
+        assert_eq!(
+            restart_delay(Restart::Interval, 5.0),
+            Some(std::time::Duration::from_secs(5))
+        );
+        assert_eq!(
+            restart_delay(Restart::Interval, 0.0),
+            Some(std::time::Duration::from_secs_f32(MINIMUM_INTERVAL))
+        );
+    }
+
+    #[test]
+    fn placing_regions() {
+        assert_eq!(region_rectangle(None, (80, 24)), (0, 0, 80, 24));
+
+        let region = Region {
+            gravity: crate::surface::Corner::BottomRight,
+            x: 2,
+            y: 1,
+            width: 30,
+            height: 10,
+        };
+        assert_eq!(region_rectangle(Some(region), (80, 24)), (48, 13, 30, 10));
+
+        let top_left = Region {
+            gravity: crate::surface::Corner::TopLeft,
+            ..region
+        };
+        assert_eq!(region_rectangle(Some(top_left), (80, 24)), (2, 1, 30, 10));
+        assert_eq!(region_rectangle(Some(top_left), (20, 5)), (0, 0, 20, 5));
+    }
+
+    fn palette() -> crate::palette::converter::Palette {
+        crate::palette::converter::Palette {
+            map: (0..=255)
+                .map(|index| (index.to_string(), (index, index, index)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn rendering_watched_output() {
+        let output = b"one\ttwo\n\x1b[31mred\x1b[0m\nthree\nfour";
+        let mut surface = render_output(output, 12, 3, &palette());
+        let screen = surface.screen_chars_to_string();
+        assert_eq!(screen, "one     two \nred         \nthree       \n");
+
+        let cells = surface.screen_cells();
+        let red = cells[1][0].attrs().foreground();
+        assert_eq!(red, palette().true_colour_attribute_from_index(1));
+        // Palette index 15 is the default text colour.
+        let default = cells[2][0].attrs().foreground();
+        assert_eq!(default, palette().true_colour_attribute_from_index(15));
+    }
+
+    #[tokio::test]
+    async fn colour_grading_drawn_output() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
+        let state = crate::shared_state::SharedState::init(3, 1, protocol_tx)
+            .await
+            .unwrap();
+        state.config.modify(|config| {
+            config.show_tattoy_indicator = false;
+            config.color.brightness = 0.5;
+        });
+        state.is_rendering_enabled.store(true);
+        let mut renderer = crate::renderer::Renderer {
+            is_cursor_visible: false,
+            ..crate::renderer::Renderer::new(state, false).await.unwrap()
+        };
+
+        let screen = render_output(b"\x1b[31mred", 3, 1, &palette());
+        let mut tattoy = crate::surface::Surface::new("bg_command".into(), 3, 1, 1, 1.0);
+        tattoy.draw_screen(&screen, 0, 0);
+        renderer.tattoys.insert(tattoy.id.clone(), tattoy);
+        renderer.composite().await.unwrap();
+
+        let cell = &renderer.frame.screen_cells()[0][0];
+        assert_eq!(cell.str(), "r");
+        assert_ne!(
+            cell.attrs().foreground(),
+            palette().true_colour_attribute_from_index(1)
+        );
+    }
+
+    #[test]
+    fn describing_exits() {
+        assert_eq!(
+            describe_exit(Some(1)),
+            "Background command exited with status 1"
+        );
+        assert_eq!(describe_exit(None), "Background command exited");
+    }
+}
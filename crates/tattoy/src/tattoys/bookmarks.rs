@@ -0,0 +1,172 @@
+//! A list of the scrollback's bookmarks. The bookmarks themselves are kept by the shadow terminal,
+//! so that they stay on the same lines as the scrollback grows, or the terminal is resized. This
+//! overlay just shows them, it's toggled with the `toggle_bookmarks` keybinding.
+
+use color_eyre::eyre::Result;
+
+/// The compositing layer of the list.
+const LAYER: i16 = 190;
+
+/// The width of the list in columns.
+const PANEL_WIDTH: usize = 40;
+
+/// The text shown when there aren't any bookmarks.
+const EMPTY_MESSAGE: &str = "No bookmarks yet";
+
+/// Describe a bookmark in a single row of the list, eg `2 ↑120 cargo build`.
+fn describe(index: usize, lines_up: usize, text: &str, width: usize) -> String {
+    let line = format!(" {index} ↑{lines_up} {}", text.trim());
+    let mut row: String = line.chars().take(width).collect();
+    let length = row.chars().count();
+    row.push_str(&" ".repeat(width.saturating_sub(length)));
+    row
+}
+
+/// The only row of the list when there aren't any bookmarks.
+fn describe_empty(width: usize) -> String {
+    format!(" {EMPTY_MESSAGE:<width$}")
+        .chars()
+        .take(width)
+        .collect()
+}
+
+/// `Bookmarks`
+pub(crate) struct Bookmarks {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The most recently reported bookmarks, from the top of the scrollback down. Their distance
+    /// from the bottom of the scrollback is as of whenever a bookmark was last added or jumped to.
+    bookmarks: Vec<shadow_terminal::output::Bookmark>,
+    /// Whether the list is currently shown.
+    is_visible: bool,
+    /// The palette, used for the colours of the list.
+    palette: crate::palette::converter::Palette,
+}
+
+impl Bookmarks {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "bookmarks".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            bookmarks: Vec::new(),
+            is_visible: false,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut bookmarks = Self::new(output, state, palette).await;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            bookmarks.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::ToggleBookmarks,
+            ) => {
+                self.is_visible = !self.is_visible;
+                tracing::debug!("Bookmarks visible: {}", self.is_visible);
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Bookmarks(bookmarks) => {
+                self.bookmarks.clone_from(bookmarks);
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        if !self.is_visible {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        let panel_width = PANEL_WIDTH.min(width);
+        let rows: Vec<String> = if self.bookmarks.is_empty() {
+            vec![describe_empty(panel_width)]
+        } else {
+            self.bookmarks
+                .iter()
+                .enumerate()
+                .map(|(index, bookmark)| {
+                    describe(index + 1, bookmark.lines_up, &bookmark.text, panel_width)
+                })
+                .collect()
+        };
+
+        let background =
+            crate::surface::colour_from_srgba(self.palette.default_background_colour());
+        let foreground =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+        let (left, top) =
+            crate::surface::Corner::TopRight.origin((width, height), (panel_width, rows.len()));
+        for (y, row) in (top..height).zip(rows) {
+            self.tattoy
+                .surface
+                .add_text(left, y, row, Some(background), Some(foreground));
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn describing_bookmarks() {
+        assert_eq!(describe(1, 12, "ls -al  ", 14), " 1 ↑12 ls -al ");
+        assert_eq!(describe(2, 0, "cargo build --release", 12), " 2 ↑0 cargo ");
+        assert_eq!(describe_empty(8), " No book");
+    }
+}
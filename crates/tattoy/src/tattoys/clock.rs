@@ -0,0 +1,358 @@
+//! A small always-on-top clock. Either digital, formatted with `strftime` syntax, or an analogue
+//! clock face drawn with half-block pixels.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the clock.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the clock.
+    pub enabled: bool,
+    /// The format of the digital clock, using `strftime` syntax, eg `%H:%M`.
+    pub format: String,
+    /// Which corner of the terminal to show the clock in.
+    pub position: crate::surface::Corner,
+    /// The transparency of the clock.
+    pub opacity: f32,
+    /// Show an analogue clock face instead of the digital clock.
+    pub analogue: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: "%H:%M".to_owned(),
+            position: crate::surface::Corner::TopRight,
+            opacity: 0.8,
+            analogue: false,
+        }
+    }
+}
+
+/// The compositing layer of the clock.
+const LAYER: i16 = 150;
+
+/// The width and height of the analogue clock face in pixels. Pixels are roughly square, so the
+/// face is this many columns wide and half as many rows tall.
+const FACE_SIZE: usize = 14;
+
+/// The length of the hour hand, relative to the radius of the face.
+const HOUR_HAND_LENGTH: f32 = 0.5;
+
+/// The length of the minute hand, relative to the radius of the face.
+const MINUTE_HAND_LENGTH: f32 = 0.85;
+
+/// The palette index of the colour of the hour marks on the analogue clock face.
+const HOUR_MARK_PALETTE_INDEX: u8 = 8;
+
+/// How far apart to plot the points along a clock hand, in pixels.
+const HAND_STEP: f32 = 0.5;
+
+/// A pixel position.
+type Pixel = (usize, usize);
+
+/// `Clock`
+pub(crate) struct Clock {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The clock's config
+    config: Config,
+    /// The palette, used to style the clock.
+    palette: crate::palette::converter::Palette,
+}
+
+impl Clock {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().clock.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "clock".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut clock = Self::new(output, state, palette).await;
+        let mut tick = tokio::time::interval(std::time::Duration::from_secs(1));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    clock.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = clock
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::Clock,
+                            |config| config.clock.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    clock.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.clock.clone();
+                self.tattoy.opacity = self.config.opacity;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Format the time for the digital clock. An invalid format is shown to the user rather than
+    /// panicking.
+    pub(crate) fn format_time<Tz>(time: &chrono::DateTime<Tz>, format: &str) -> String
+    where
+        Tz: chrono::TimeZone,
+        Tz::Offset: core::fmt::Display,
+    {
+        use core::fmt::Write as _;
+
+        let mut formatted = String::new();
+        if write!(formatted, "{}", time.format(format)).is_err() {
+            tracing::warn!("Invalid clock format: {format}");
+            return "Invalid clock format".to_owned();
+        }
+        formatted
+    }
+
+    /// The angles of the hour and minute hands, in radians clockwise from 12 o'clock.
+    fn hand_angles(hour: u32, minute: u32) -> (f32, f32) {
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "Hours and minutes are small numbers"
+        )]
+        let (hours, minutes) = (hour.rem_euclid(12) as f32, minute as f32);
+        let hour_angle = (hours + minutes / 60.0) / 12.0 * core::f32::consts::TAU;
+        let minute_angle = minutes / 60.0 * core::f32::consts::TAU;
+        (hour_angle, minute_angle)
+    }
+
+    /// The pixel at the given angle and distance from the centre of the clock face.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Points are always within the clock face"
+    )]
+    fn point(angle: f32, distance: f32) -> Pixel {
+        let centre = Self::face_radius();
+        let x = distance.mul_add(angle.sin(), centre);
+        let y = distance.mul_add(-angle.cos(), centre);
+        (x.round().max(0.0) as usize, y.round().max(0.0) as usize)
+    }
+
+    /// The radius of the clock face in pixels.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "The clock face is small"
+    )]
+    fn face_radius() -> f32 {
+        (FACE_SIZE - 1) as f32 / 2.0
+    }
+
+    /// All the pixels of a clock hand.
+    fn hand(angle: f32, length: f32) -> Vec<Pixel> {
+        let mut pixels = Vec::new();
+        let mut distance = 0.0;
+        while distance <= length {
+            let pixel = Self::point(angle, distance);
+            if !pixels.contains(&pixel) {
+                pixels.push(pixel);
+            }
+            distance += HAND_STEP;
+        }
+        pixels
+    }
+
+    /// The 12 hour marks around the edge of the clock face.
+    fn hour_marks() -> Vec<Pixel> {
+        (0..12u8)
+            .map(|hour| {
+                let angle = f32::from(hour) / 12.0 * core::f32::consts::TAU;
+                Self::point(angle, Self::face_radius())
+            })
+            .collect()
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let now = chrono::Local::now();
+        let terminal = (
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+        );
+
+        if self.config.analogue {
+            self.render_analogue(&now, terminal)?;
+        } else {
+            self.render_digital(&now, terminal);
+        }
+
+        self.tattoy.send_output().await
+    }
+
+    /// Render the digital clock.
+    fn render_digital(
+        &mut self,
+        now: &chrono::DateTime<chrono::Local>,
+        terminal: (usize, usize),
+    ) {
+        let text = format!(" {} ", Self::format_time(now, &self.config.format));
+        let panel = (text.chars().count(), 1);
+        if terminal.0 < panel.0 || terminal.1 < panel.1 {
+            tracing::debug!("Terminal too small for the clock");
+            return;
+        }
+
+        let (x, y) = self.config.position.origin(terminal, panel);
+        self.tattoy.surface.add_text(
+            x,
+            y,
+            text,
+            Some(crate::surface::colour_from_srgba(
+                self.palette.default_background_colour(),
+            )),
+            Some(crate::surface::colour_from_srgba(
+                self.palette.default_foreground_colour(),
+            )),
+        );
+    }
+
+    /// Render the analogue clock face.
+    fn render_analogue(
+        &mut self,
+        now: &chrono::DateTime<chrono::Local>,
+        terminal: (usize, usize),
+    ) -> Result<()> {
+        use chrono::Timelike as _;
+
+        let panel = (FACE_SIZE, FACE_SIZE.div_euclid(2));
+        if terminal.0 < panel.0 || terminal.1 < panel.1 {
+            tracing::debug!("Terminal too small for the analogue clock");
+            return Ok(());
+        }
+
+        let (left, top) = self.config.position.origin(terminal, panel);
+        let hand_colour =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+        let mark_colour = crate::surface::colour_from_srgba(
+            self.palette
+                .true_colour_tuple_from_index(HOUR_MARK_PALETTE_INDEX),
+        );
+        let (hour_angle, minute_angle) = Self::hand_angles(now.hour(), now.minute());
+        let radius = Self::face_radius();
+
+        let marks = Self::hour_marks()
+            .into_iter()
+            .map(|pixel| (pixel, mark_colour));
+        let hands = Self::hand(hour_angle, radius * HOUR_HAND_LENGTH)
+            .into_iter()
+            .chain(Self::hand(minute_angle, radius * MINUTE_HAND_LENGTH))
+            .map(|pixel| (pixel, hand_colour));
+        for ((x, y), colour) in marks.chain(hands) {
+            self.tattoy
+                .surface
+                .add_pixel(left + x, top * 2 + y, colour)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formatting_the_time() {
+        let time = chrono::DateTime::parse_from_rfc3339("2025-03-01T09:05:00+00:00").unwrap();
+        assert_eq!(Clock::format_time(&time, "%H:%M"), "09:05");
+        assert_eq!(Clock::format_time(&time, "%a %d %b"), "Sat 01 Mar");
+        assert_eq!(Clock::format_time(&time, "%Q"), "Invalid clock format");
+    }
+
+    #[test]
+    fn hands_at_3_oclock() {
+        let (hour_angle, minute_angle) = Clock::hand_angles(15, 0);
+        assert!((hour_angle - core::f32::consts::FRAC_PI_2).abs() < f32::EPSILON);
+        assert!(minute_angle.abs() < f32::EPSILON);
+
+        let minute_hand = Clock::hand(minute_angle, 3.0);
+        assert!(minute_hand.iter().all(|(x, _)| *x == 7));
+        let hour_hand = Clock::hand(hour_angle, 3.0);
+        assert!(hour_hand.iter().all(|(_, y)| *y == 7));
+    }
+
+    #[test]
+    fn hour_marks_fit_on_the_face() {
+        let marks = Clock::hour_marks();
+        assert_eq!(marks.len(), 12);
+        assert!(marks
+            .iter()
+            .all(|(x, y)| *x < FACE_SIZE && *y < FACE_SIZE));
+    }
+}
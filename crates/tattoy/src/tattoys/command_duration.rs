@@ -0,0 +1,243 @@
+//! Show how long the last command took to run, as a badge at the end of the prompt's line, eg
+//! `⏱ 12.4s`. Quick commands don't get a badge. Long-running commands can also send a
+//! notification, which is handy for when you've switched away from the terminal.
+//!
+//! The shell has to report when commands start and finish, which it only does with shell
+//! integration (OSC 133) enabled.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the command duration badge.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the command duration badge.
+    pub enabled: bool,
+    /// Commands that finish quicker than this don't get a badge, in seconds.
+    pub threshold: f32,
+    /// Send a notification when a command takes longer than this, in seconds.
+    pub notify_after: Option<f32>,
+    /// The transparency of the badge.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 2.0,
+            notify_after: None,
+            opacity: 0.9,
+        }
+    }
+}
+
+/// The compositing layer of the badge.
+const LAYER: i16 = 140;
+
+/// The palette index of the badge's text colour.
+const TEXT_PALETTE_INDEX: u8 = 3;
+
+/// Format a duration in the shortest readable way, eg `12.4s`, `2m 05s` or `1h 02m`.
+fn format_duration(duration: std::time::Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        return format!("{:.1}s", duration.as_secs_f32());
+    }
+
+    let minutes = seconds.div_euclid(60);
+    if minutes < 60 {
+        return format!("{minutes}m {:02}s", seconds.rem_euclid(60));
+    }
+
+    format!("{}h {:02}m", minutes.div_euclid(60), minutes.rem_euclid(60))
+}
+
+/// `CommandDuration`
+pub(crate) struct CommandDuration {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The command duration's config
+    config: Config,
+    /// When the currently running command started.
+    maybe_started_at: Option<tokio::time::Instant>,
+    /// How long the last command took, if it was long enough to show.
+    maybe_last_duration: Option<std::time::Duration>,
+    /// The palette, used to style the badge.
+    palette: crate::palette::converter::Palette,
+}
+
+impl CommandDuration {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().command_duration.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "command_duration".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            maybe_started_at: None,
+            maybe_last_duration: None,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut command_duration = Self::new(output, state, palette).await;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            let is_disabled = command_duration
+                .tattoy
+                .is_disabled_by(
+                    &message,
+                    &crate::config::diff::Section::CommandDuration,
+                    |config| config.command_duration.enabled,
+                )
+                .await?;
+            if is_disabled {
+                break;
+            }
+            command_duration.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        // The prompt may move, eg when the screen is cleared.
+        let mut is_rerender_needed = self.maybe_last_duration.is_some()
+            && (super::tattoyer::Tattoyer::is_screen_output_changed(&message)
+                || matches!(message, crate::run::Protocol::Resize { .. }));
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::CommandStarted => {
+                self.maybe_started_at = Some(tokio::time::Instant::now());
+                if self.maybe_last_duration.take().is_some() {
+                    self.tattoy.send_blank_output().await?;
+                }
+            }
+            crate::run::Protocol::CommandFinished(_) => {
+                if let Some(started_at) = self.maybe_started_at.take() {
+                    self.command_finished(started_at.elapsed()).await;
+                    is_rerender_needed = true;
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.command_duration.clone();
+                self.tattoy.opacity = self.config.opacity;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Record how long the command took and notify the user if it took a long time.
+    async fn command_finished(&mut self, duration: std::time::Duration) {
+        let seconds = duration.as_secs_f32();
+        self.maybe_last_duration = (seconds >= self.config.threshold).then_some(duration);
+
+        let Some(notify_after) = self.config.notify_after else {
+            return;
+        };
+        if seconds < notify_after {
+            return;
+        }
+        self.tattoy
+            .state
+            .send_notification(
+                "Command finished",
+                crate::tattoys::notifications::message::Level::Info,
+                Some(format!("Took {}", format_duration(duration))),
+                false,
+            )
+            .await;
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let Some(duration) = self.maybe_last_duration else {
+            return self.tattoy.send_blank_output().await;
+        };
+        if self.tattoy.is_alternate_screen() || self.tattoy.is_scrolling() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let text = format!(" ⏱ {} ", format_duration(duration));
+        let width = usize::from(self.tattoy.width);
+        let length = text.chars().count();
+        let (_, cursor_y) = self.tattoy.screen.surface.cursor_position();
+        if width < length || cursor_y >= usize::from(self.tattoy.height) {
+            return self.tattoy.send_output().await;
+        }
+
+        self.tattoy.surface.add_text(
+            width - length,
+            cursor_y,
+            text,
+            Some(crate::surface::colour_from_srgba(
+                self.palette.default_background_colour(),
+            )),
+            Some(crate::surface::colour_from_srgba(
+                self.palette
+                    .true_colour_tuple_from_index(TEXT_PALETTE_INDEX),
+            )),
+        );
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formatting_durations() {
+        assert_eq!(
+            format_duration(std::time::Duration::from_millis(12_400)),
+            "12.4s"
+        );
+        assert_eq!(format_duration(std::time::Duration::from_secs(125)), "2m 05s");
+        assert_eq!(
+            format_duration(std::time::Duration::from_secs(3_720)),
+            "1h 02m"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! A retro CRT monitor look that doesn't need a GPU. It's a much cheaper approximation of the CRT
+//! shaders: every other row is darkened to suggest scanlines, the edges of the screen are darkened
+//! with a vignette and everything gets a faint phosphor tint. The glow of the phosphor is
+//! approximated by the renderer brightening all the text during its final colour grading.
+//!
+//! Because the overlay is made of translucent empty cells, it darkens and tints the text beneath it
+//! as well as the background, just like the glass of a real monitor.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the CRT effect.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the CRT effect.
+    pub enabled: bool,
+    /// How much darker the scanline rows are, between 0.0 and 1.0.
+    pub scanline_strength: f32,
+    /// How much darker the corners of the screen are, between 0.0 and 1.0.
+    pub vignette_strength: f32,
+    /// How much brighter text is made, to approximate the glow of the phosphor.
+    pub glow: f32,
+    /// The colour of the phosphor as RGBA values between 0.0 and 1.0. The alpha is how strongly
+    /// the colour tints the terminal.
+    pub tint: crate::surface::Colour,
+    /// The transparency of the whole effect.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scanline_strength: 0.25,
+            vignette_strength: 0.4,
+            glow: 0.1,
+            tint: (0.2, 1.0, 0.4, 0.08),
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The compositing layer of the CRT effect. It's above all the other eye-candy so that it affects
+/// them too, but below UI like the settings panel.
+const LAYER: i16 = 240;
+
+/// How much the vignette darkens a cell, based on how far it is from the centre of the screen.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "Terminals are never big enough to lose precision"
+)]
+fn vignette(cell: (usize, usize), size: (usize, usize), strength: f32) -> f32 {
+    if size.0 == 0 || size.1 == 0 {
+        return 0.0;
+    }
+
+    // The distance from the centre in each axis, between 0.0 and 1.0.
+    let axis_distance = |position: usize, length: usize| {
+        ((position as f32 + 0.5) / length as f32)
+            .mul_add(2.0, -1.0)
+            .abs()
+    };
+    let x = axis_distance(cell.0, size.0);
+    let y = axis_distance(cell.1, size.1);
+    let distance = x.hypot(y) / std::f32::consts::SQRT_2;
+
+    (strength * distance.powi(2)).clamp(0.0, 1.0)
+}
+
+/// How much the scanlines darken a row.
+fn scanline(row: usize, strength: f32) -> f32 {
+    if row.rem_euclid(2) == 1 {
+        strength.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// The single colour that, when blended over a cell, has the same effect as first tinting the cell
+/// with the phosphor colour and then darkening it.
+///
+/// Tinting and darkening are both linear blends, so `(base * (1 - tint) + phosphor * tint) *
+/// light` can be rearranged into a single blend of the form `base * (1 - alpha) + colour * alpha`.
+fn cell_colour(tint: crate::surface::Colour, darkness: f32) -> Option<crate::surface::Colour> {
+    let tint_amount = tint.3.clamp(0.0, 1.0);
+    let light = 1.0 - darkness.clamp(0.0, 1.0);
+    let alpha = (1.0 - tint_amount).mul_add(-light, 1.0);
+    if alpha <= 0.0 {
+        return None;
+    }
+
+    let scale = tint_amount * light / alpha;
+    Some((tint.0 * scale, tint.1 * scale, tint.2 * scale, alpha))
+}
+
+/// `Crt`
+pub(crate) struct Crt {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The CRT's config
+    config: Config,
+}
+
+impl Crt {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().crt.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "crt".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self { tattoy, config }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut crt = Self::new(output, state).await;
+        crt.render().await?;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            let is_disabled = crt
+                .tattoy
+                .is_disabled_by(&message, &crate::config::diff::Section::Crt, |config| {
+                    config.crt.enabled
+                })
+                .await?;
+            if is_disabled {
+                break;
+            }
+            crt.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app. The effect is static, so it only needs
+    /// re-rendering when the terminal's size or the config changes.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(message, crate::run::Protocol::Resize { .. });
+
+        if let crate::run::Protocol::Config { config, .. } = &message {
+            if config.crt != self.config {
+                self.config = config.crt.clone();
+                self.tattoy.opacity = self.config.opacity;
+                is_rerender_needed = true;
+            }
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let size = (
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+        );
+        for y in 0..size.1 {
+            let scanline_light = 1.0 - scanline(y, self.config.scanline_strength);
+            for x in 0..size.0 {
+                let vignette_light = 1.0 - vignette((x, y), size, self.config.vignette_strength);
+                let darkness = 1.0 - scanline_light * vignette_light;
+                let Some(colour) = cell_colour(self.config.tint, darkness) else {
+                    continue;
+                };
+                self.tattoy
+                    .surface
+                    .add_text(x, y, " ".to_owned(), Some(colour), None);
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vignette_darkens_the_edges() {
+        let size = (80, 24);
+        let centre = vignette((40, 12), size, 0.5);
+        let edge = vignette((0, 12), size, 0.5);
+        let corner = vignette((0, 0), size, 0.5);
+        assert!(centre < 0.01);
+        assert!(centre < edge);
+        assert!(edge < corner);
+        assert!(corner <= 0.5);
+    }
+
+    #[test]
+    fn scanlines_are_on_every_other_row() {
+        assert!(scanline(0, 0.3).abs() < f32::EPSILON);
+        assert!((scanline(1, 0.3) - 0.3).abs() < f32::EPSILON);
+        assert!(scanline(2, 0.3).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn cell_colour_combines_tint_and_darkness() {
+        assert_eq!(cell_colour((1.0, 1.0, 1.0, 0.0), 0.0), None);
+        assert_eq!(
+            cell_colour((1.0, 1.0, 1.0, 0.0), 0.5),
+            Some((0.0, 0.0, 0.0, 0.5))
+        );
+        assert_eq!(
+            cell_colour((0.0, 1.0, 0.0, 0.5), 0.0),
+            Some((0.0, 1.0, 0.0, 0.5))
+        );
+
+        // Blending over white should match tinting white and then darkening it.
+        let blend_over_white = |colour: crate::surface::Colour| {
+            (
+                colour.0.mul_add(colour.3, 1.0 - colour.3),
+                colour.1.mul_add(colour.3, 1.0 - colour.3),
+            )
+        };
+        let blended = cell_colour((0.0, 1.0, 0.0, 0.5), 0.5).map(blend_over_white);
+        assert!(blended.is_some_and(|(red, green)| {
+            (red - 0.25).abs() < 0.001 && (green - 0.5).abs() < 0.001
+        }));
+    }
+}
@@ -0,0 +1,211 @@
+//! A subtle highlight of the row that the cursor is on, and optionally its column too, making a
+//! crosshair. It makes the cursor easier to find on busy screens.
+//!
+//! Full-screen apps, like editors, that use the alternate screen often have their own cursor line
+//! highlighting, so the highlight is hidden whilst they're running.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the cursor highlight.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the cursor highlight.
+    pub enabled: bool,
+    /// Also highlight the cursor's column.
+    pub column: bool,
+    /// The colour of the highlight, as RGBA values between 0.0 and 1.0. Defaults to the
+    /// cursor's colour, if an application set one, otherwise the terminal's foreground colour.
+    pub color: Option<crate::surface::Colour>,
+    /// The transparency of the highlight.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            column: false,
+            color: None,
+            opacity: 0.08,
+        }
+    }
+}
+
+/// The compositing layer of the highlight. It's just above the PTY.
+const LAYER: i16 = 2;
+
+/// All the cells that are highlighted for the given cursor position.
+fn highlighted_cells(
+    cursor: (usize, usize),
+    size: (usize, usize),
+    is_column_highlighted: bool,
+) -> Vec<(usize, usize)> {
+    if cursor.0 >= size.0 || cursor.1 >= size.1 {
+        return Vec::new();
+    }
+
+    let mut cells: Vec<(usize, usize)> = (0..size.0).map(|x| (x, cursor.1)).collect();
+    if is_column_highlighted {
+        cells.extend(
+            (0..size.1)
+                .filter(|y| *y != cursor.1)
+                .map(|y| (cursor.0, y)),
+        );
+    }
+    cells
+}
+
+/// `CursorHighlight`
+pub(crate) struct CursorHighlight {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The cursor highlight's config
+    config: Config,
+    /// The position of the cursor in the user's terminal.
+    cursor: (usize, usize),
+    /// The palette, used for the default colour of the highlight.
+    palette: crate::palette::converter::Palette,
+}
+
+impl CursorHighlight {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().cursor_highlight.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "cursor_highlight".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            cursor: (0, 0),
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut cursor_highlight = Self::new(output, state, palette).await;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            let is_disabled = cursor_highlight
+                .tattoy
+                .is_disabled_by(
+                    &message,
+                    &crate::config::diff::Section::CursorHighlight,
+                    |config| config.cursor_highlight.enabled,
+                )
+                .await?;
+            if is_disabled {
+                break;
+            }
+            cursor_highlight.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            // The renderer reports the cursor's position in the user's terminal, so it already
+            // accounts for any reserved rows.
+            crate::run::Protocol::CursorMoved(movement) => {
+                self.cursor = movement.to;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.cursor_highlight.clone();
+                self.tattoy.opacity = self.config.opacity;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::CursorColour(_) => is_rerender_needed = true,
+            _ => (),
+        }
+
+        // Entering or leaving the alternate screen, or scrolling, changes whether the highlight
+        // is shown.
+        let was_hidden = self.is_hidden();
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed || was_hidden != self.is_hidden() {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the highlight shouldn't be shown.
+    fn is_hidden(&self) -> bool {
+        self.tattoy.is_alternate_screen() || self.tattoy.is_scrolling()
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        if self.is_hidden() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let colour = self.config.color.unwrap_or_else(|| {
+            self.tattoy
+                .cursor_colour_or(self.palette.default_foreground_colour())
+        });
+        let size = (
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+        );
+        for (x, y) in highlighted_cells(self.cursor, size, self.config.column) {
+            self.tattoy
+                .surface
+                .add_text(x, y, " ".to_owned(), Some(colour), None);
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn highlighting_the_cursor_row_and_column() {
+        assert_eq!(
+            highlighted_cells((1, 1), (3, 3), false),
+            vec![(0, 1), (1, 1), (2, 1)]
+        );
+        assert_eq!(
+            highlighted_cells((1, 1), (3, 3), true),
+            vec![(0, 1), (1, 1), (2, 1), (1, 0), (1, 2)]
+        );
+        assert!(highlighted_cells((5, 1), (3, 3), true).is_empty());
+    }
+}
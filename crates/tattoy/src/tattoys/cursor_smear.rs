@@ -0,0 +1,332 @@
+//! Animate the cursor's jumps around the terminal with a smear, like the Neovide editor does. The
+//! front of the smear races ahead to the cursor's new position whilst the back catches up.
+
+use color_eyre::eyre::Result;
+
+/// The easing curves for the animation.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Easing {
+    /// A constant speed.
+    Linear,
+    /// Fast at first, then slowing down.
+    #[default]
+    EaseOut,
+    /// Slow at first, fast in the middle, then slowing down.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map linear progress between 0.0 and 1.0 onto the curve.
+    fn apply(self, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => progress,
+            Self::EaseOut => 1.0 - (1.0 - progress).powi(3),
+            Self::EaseInOut => {
+                if progress < 0.5 {
+                    4.0 * progress.powi(3)
+                } else {
+                    1.0 - (-2.0_f32).mul_add(progress, 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// User-configurable settings for the cursor smear.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the cursor smear.
+    pub enabled: bool,
+    /// How long the animation lasts, in seconds.
+    pub duration: f32,
+    /// The easing curve of the animation.
+    pub easing: Easing,
+    /// The shortest jump, in cells, that gets animated. Stops every typed character from smearing.
+    pub minimum_distance: usize,
+    /// The colour of the smear, as RGBA values between 0.0 and 1.0. Defaults to the cursor's
+    /// colour, if an application set one, otherwise the terminal's foreground colour.
+    pub color: Option<crate::surface::Colour>,
+    /// The transparency of the smear.
+    pub opacity: f32,
+    /// Override the global frame rate for the animation.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration: 0.15,
+            easing: Easing::EaseOut,
+            minimum_distance: 2,
+            color: None,
+            opacity: 0.6,
+            frame_rate: None,
+        }
+    }
+}
+
+/// The compositing layer of the smear. It's drawn over everything except UI like the settings
+/// panel.
+const LAYER: i16 = 260;
+
+/// How much fainter the back of the smear is than the front.
+const TAIL_FADE: f32 = 0.7;
+
+/// A pixel position and how strongly it's drawn, between 0.0 and 1.0.
+type SmearPixel = ((usize, usize), f32);
+
+/// The centre of a cell in pixels. There are 2 pixels per terminal row.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "Terminals are never big enough to lose precision"
+)]
+fn cell_centre(cell: (usize, usize)) -> (f32, f32) {
+    (cell.0 as f32 + 0.5, (cell.1 * 2) as f32 + 1.0)
+}
+
+/// Linear interpolation between 2 points.
+fn lerp(from: (f32, f32), to: (f32, f32), amount: f32) -> (f32, f32) {
+    (
+        (to.0 - from.0).mul_add(amount, from.0),
+        (to.1 - from.1).mul_add(amount, from.1),
+    )
+}
+
+/// All the pixels of the smear at the given point in the animation. The front of the smear moves
+/// along the easing curve, whilst the back follows more slowly so that both arrive at the end
+/// together.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    reason = "Smears are always within the bounds of the terminal"
+)]
+fn smear(
+    from: (usize, usize),
+    to: (usize, usize),
+    progress: f32,
+    easing: Easing,
+) -> Vec<SmearPixel> {
+    let start = cell_centre(from);
+    let end = cell_centre(to);
+    let head = lerp(start, end, easing.apply(progress));
+    let tail = lerp(start, end, easing.apply(progress * progress));
+
+    let length = (head.0 - tail.0).abs().max((head.1 - tail.1).abs());
+    let steps = (length.ceil() as usize * 2).max(1);
+    let mut pixels: std::collections::HashMap<(usize, usize), f32> =
+        std::collections::HashMap::new();
+    for step in 0..=steps {
+        let along = step as f32 / steps as f32;
+        let (x, y) = lerp(tail, head, along);
+        let strength = TAIL_FADE.mul_add(along, 1.0 - TAIL_FADE);
+        // A cursor is one column wide and two pixels tall.
+        let column = x.floor().max(0.0) as usize;
+        let top = (y - 1.0).round().max(0.0) as usize;
+        for pixel in [(column, top), (column, top + 1)] {
+            let existing = pixels.entry(pixel).or_insert(0.0);
+            *existing = existing.max(strength);
+        }
+    }
+
+    let mut pixels: Vec<SmearPixel> = pixels.into_iter().collect();
+    pixels.sort_by_key(|(position, _)| *position);
+    pixels
+}
+
+/// `CursorSmear`
+pub(crate) struct CursorSmear {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The cursor smear's config
+    config: Config,
+    /// The movement currently being animated.
+    maybe_animating: Option<crate::renderer::CursorMovement>,
+    /// The palette, used for the default colour of the smear.
+    palette: crate::palette::converter::Palette,
+}
+
+impl CursorSmear {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().cursor_smear.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "cursor_smear".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            maybe_animating: None,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut cursor_smear = Self::new(output, state, palette).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = cursor_smear.tattoy.sleep_until_next_frame_tick(),
+                    if cursor_smear.maybe_animating.is_some() => {
+                    cursor_smear.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = cursor_smear
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::CursorSmear,
+                            |config| config.cursor_smear.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    cursor_smear.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::CursorMoved(movement) => {
+                if self.is_animatable(movement) {
+                    // A new movement replaces any animation that's still running.
+                    self.maybe_animating = Some(*movement);
+                    self.render().await?;
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.cursor_smear.clone();
+                self.tattoy.opacity = self.config.opacity;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)
+    }
+
+    /// Whether the movement is worth animating. The renderer times the movement itself, so the
+    /// animation stays in step with what's actually on screen regardless of when our own frames
+    /// tick.
+    fn is_animatable(&self, movement: &crate::renderer::CursorMovement) -> bool {
+        let distance = movement
+            .from
+            .0
+            .abs_diff(movement.to.0)
+            .max(movement.from.1.abs_diff(movement.to.1));
+        distance >= self.config.minimum_distance && !self.tattoy.is_scrolling()
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let Some(movement) = self.maybe_animating else {
+            return Ok(());
+        };
+
+        let duration = self.config.duration.max(f32::EPSILON);
+        let progress = movement.at.elapsed().as_secs_f32() / duration;
+        if progress >= 1.0 {
+            self.maybe_animating = None;
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let colour = self.config.color.unwrap_or_else(|| {
+            self.tattoy
+                .cursor_colour_or(self.palette.default_foreground_colour())
+        });
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height) * 2;
+        for ((x, y), strength) in smear(movement.from, movement.to, progress, self.config.easing) {
+            if x >= width || y >= height {
+                continue;
+            }
+            self.tattoy
+                .surface
+                .add_pixel(x, y, (colour.0, colour.1, colour.2, colour.3 * strength))?;
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn easing_curves_start_and_end_in_place() {
+        for easing in [Easing::Linear, Easing::EaseOut, Easing::EaseInOut] {
+            assert!(easing.apply(0.0).abs() < f32::EPSILON);
+            assert!((easing.apply(1.0) - 1.0).abs() < f32::EPSILON);
+            assert!(easing.apply(0.25) < easing.apply(0.75));
+        }
+        assert!(Easing::EaseOut.apply(0.5) > Easing::Linear.apply(0.5));
+    }
+
+    #[test]
+    fn smear_starts_and_ends_on_the_cursor() {
+        let start = smear((2, 1), (10, 1), 0.0, Easing::Linear);
+        assert!(start
+            .iter()
+            .all(|((x, y), _)| *x == 2 && (*y == 2 || *y == 3)));
+
+        let end = smear((2, 1), (10, 1), 1.0, Easing::Linear);
+        assert!(end
+            .iter()
+            .all(|((x, y), _)| *x == 10 && (*y == 2 || *y == 3)));
+    }
+
+    #[test]
+    fn smear_stretches_between_the_positions() {
+        let middle = smear((0, 0), (10, 0), 0.5, Easing::Linear);
+        let columns: std::collections::HashSet<usize> =
+            middle.iter().map(|((x, _), _)| *x).collect();
+        // The front is half way and the back a quarter of the way.
+        assert_eq!(columns, (3..=5).collect());
+        let front = middle.iter().find(|((x, _), _)| *x == 5);
+        let back = middle.iter().find(|((x, _), _)| *x == 3);
+        assert!(front.zip(back).is_some_and(|(front, back)| front.1 > back.1));
+    }
+}
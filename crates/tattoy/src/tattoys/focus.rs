@@ -0,0 +1,242 @@
+//! A reading mode that dims everything apart from the part of the terminal that you're focussing
+//! on. That's either the output of the most recent command, or some rows around the cursor.
+//!
+//! Finding the most recent command's output depends on the shell reporting when commands start and
+//! finish, which it only does with shell integration (OSC 133) enabled.
+
+use color_eyre::eyre::Result;
+
+/// What to keep undimmed.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Mode {
+    /// The most recent command and its output.
+    #[default]
+    LastCommand,
+    /// Some rows around the cursor.
+    Cursor,
+}
+
+/// User-configurable settings for the focus mode.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the focus mode.
+    pub enabled: bool,
+    /// What to keep undimmed.
+    pub mode: Mode,
+    /// In `cursor` mode, how many rows above and below the cursor are kept undimmed.
+    pub rows: usize,
+    /// How much the rest of the terminal is dimmed, between 0.0 and 1.0.
+    pub dim: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: Mode::LastCommand,
+            rows: 5,
+            dim: 0.6,
+        }
+    }
+}
+
+/// The compositing layer of the dimming. It's just above the PTY, so that widgets aren't dimmed.
+const LAYER: i16 = 10;
+
+/// The rows of a view onto the terminal that are outside the focussed range. Both the view and
+/// the focus are in lines from the top of the scrollback.
+fn dimmed_rows(
+    view_top: usize,
+    height: usize,
+    focus: &core::ops::Range<usize>,
+) -> impl Iterator<Item = usize> + '_ {
+    (0..height).filter(move |row| !focus.contains(&(view_top + row)))
+}
+
+/// `Focus`
+pub(crate) struct Focus {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The focus mode's config
+    config: Config,
+    /// The line where the most recent command finished, and where the one before it finished.
+    /// Lines are counted from the top of the scrollback.
+    ///
+    /// Once the scrollback is full, its oldest lines are dropped, so these marks slowly drift
+    /// down from where they should be.
+    marks: (Option<usize>, Option<usize>),
+    /// Whether a command is currently running.
+    is_command_running: bool,
+}
+
+impl Focus {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().focus.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "focus".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            marks: (None, None),
+            is_command_running: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut focus = Self::new(output, state).await;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            let is_disabled = focus
+                .tattoy
+                .is_disabled_by(&message, &crate::config::diff::Section::Focus, |config| {
+                    config.focus.enabled
+                })
+                .await?;
+            if is_disabled {
+                break;
+            }
+            focus.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        // Any output may move the focus, including scrolling.
+        let mut is_rerender_needed = matches!(
+            message,
+            crate::run::Protocol::Output(_)
+                | crate::run::Protocol::Resize { .. }
+                | crate::run::Protocol::ReservedRows(_)
+                | crate::run::Protocol::CursorMoved(_)
+        );
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::CommandStarted => {
+                self.is_command_running = true;
+            }
+            crate::run::Protocol::CommandFinished(_) => {
+                self.is_command_running = false;
+                self.marks = (Some(self.cursor_line()), self.marks.0);
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.focus.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// The number of lines in the scrollback above the screen.
+    fn lines_above_screen(&self) -> usize {
+        let scrollback_height = self.tattoy.scrollback.surface.dimensions().1;
+        scrollback_height.saturating_sub(self.tattoy.screen.surface.dimensions().1)
+    }
+
+    /// The line of the cursor, counted from the top of the scrollback.
+    fn cursor_line(&self) -> usize {
+        let (_, cursor_y) = self.tattoy.screen.surface.cursor_position();
+        self.lines_above_screen() + cursor_y
+    }
+
+    /// The lines that are kept undimmed, counted from the top of the scrollback.
+    fn focus(&self) -> Option<core::ops::Range<usize>> {
+        match self.config.mode {
+            Mode::Cursor => {
+                let cursor = self.cursor_line();
+                Some(cursor.saturating_sub(self.config.rows)..cursor + self.config.rows + 1)
+            }
+            Mode::LastCommand => {
+                if self.tattoy.is_alternate_screen() {
+                    return None;
+                }
+                // The output of a running command is focussed as it arrives.
+                if self.is_command_running {
+                    return Some(self.marks.0.unwrap_or(0)..self.cursor_line() + 1);
+                }
+                let last = self.marks.0?;
+                Some(self.marks.1.unwrap_or(0)..last)
+            }
+        }
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let Some(focus) = self.focus() else {
+            return self.tattoy.send_blank_output().await;
+        };
+
+        self.tattoy.initialise_surface();
+        let background = self.tattoy.state.default_background_colour.get();
+        let colour = (
+            background.0,
+            background.1,
+            background.2,
+            self.config.dim.clamp(0.0, 1.0),
+        );
+
+        // The PTY is offset by any rows that are reserved above it, eg by the status bar.
+        let top = usize::from(self.tattoy.state.reserved_rows.get().top);
+        let height = self.tattoy.screen.surface.dimensions().1;
+        let view_top = self
+            .lines_above_screen()
+            .saturating_sub(self.tattoy.scrollback.position);
+        for row in dimmed_rows(view_top, height, &focus) {
+            for x in 0..usize::from(self.tattoy.width) {
+                self.tattoy
+                    .surface
+                    .add_text(x, top + row, " ".to_owned(), Some(colour), None);
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dimming_rows_outside_the_focus() {
+        let rows: Vec<usize> = dimmed_rows(100, 6, &(102..104)).collect();
+        assert_eq!(rows, vec![0, 1, 4, 5]);
+
+        let rows: Vec<usize> = dimmed_rows(100, 3, &(0..50)).collect();
+        assert_eq!(rows, vec![0, 1, 2]);
+    }
+}
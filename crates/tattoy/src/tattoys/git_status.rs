@@ -0,0 +1,351 @@
+//! A small widget showing the git branch, dirty state and ahead/behind counts of the shell's
+//! current working directory.
+//!
+//! Rather than continually polling `git`, the status is refreshed whenever the shell reports that
+//! a command has finished or that its working directory has changed. Shells only report these
+//! with shell integration enabled, so there is also a slow fallback refresh.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the git status.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the git status.
+    pub enabled: bool,
+    /// Which corner of the terminal to show the git status in.
+    pub position: crate::surface::Corner,
+    /// The transparency of the git status.
+    pub opacity: f32,
+    /// How often to refresh the status regardless of shell events, in seconds.
+    pub refresh_interval: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: crate::surface::Corner::TopLeft,
+            opacity: 0.9,
+            refresh_interval: 30.0,
+        }
+    }
+}
+
+/// The compositing layer of the git status.
+const LAYER: i16 = 150;
+
+/// The palette index of the colour used when the working tree is clean.
+const CLEAN_PALETTE_INDEX: u8 = 2;
+
+/// The palette index of the colour used when the working tree has changes.
+const DIRTY_PALETTE_INDEX: u8 = 3;
+
+/// The shortest allowed fallback refresh interval, so that a misconfiguration can't hammer `git`.
+const MINIMUM_REFRESH_INTERVAL: f32 = 1.0;
+
+/// The state of a git repository.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Status {
+    /// The current branch, or the short commit hash when the `HEAD` is detached.
+    branch: String,
+    /// Whether there are any uncommitted changes or untracked files.
+    is_dirty: bool,
+    /// How many commits the branch is ahead of its upstream.
+    ahead: usize,
+    /// How many commits the branch is behind its upstream.
+    behind: usize,
+}
+
+impl Status {
+    /// Parse the output of `git status --porcelain=v2 --branch`.
+    fn parse(output: &str) -> Self {
+        let mut status = Self::default();
+        let mut maybe_oid = None;
+
+        for line in output.lines() {
+            let Some(header) = line.strip_prefix("# ") else {
+                status.is_dirty = true;
+                continue;
+            };
+            if let Some(head) = header.strip_prefix("branch.head ") {
+                head.clone_into(&mut status.branch);
+            } else if let Some(oid) = header.strip_prefix("branch.oid ") {
+                maybe_oid = Some(oid.chars().take(7).collect::<String>());
+            } else if let Some(counts) = header.strip_prefix("branch.ab ") {
+                for count in counts.split_whitespace() {
+                    if let Some(ahead) = count.strip_prefix('+') {
+                        status.ahead = ahead.parse().unwrap_or(0);
+                    }
+                    if let Some(behind) = count.strip_prefix('-') {
+                        status.behind = behind.parse().unwrap_or(0);
+                    }
+                }
+            }
+        }
+
+        if status.branch == "(detached)" {
+            if let Some(oid) = maybe_oid {
+                status.branch = oid;
+            }
+        }
+
+        status
+    }
+
+    /// The text of the widget, eg ` ⎇ main* ↑1 ↓2 `.
+    pub(crate) fn summary(&self) -> String {
+        let dirty = if self.is_dirty { "*" } else { "" };
+        let ahead = if self.ahead > 0 {
+            format!(" ↑{}", self.ahead)
+        } else {
+            String::new()
+        };
+        let behind = if self.behind > 0 {
+            format!(" ↓{}", self.behind)
+        } else {
+            String::new()
+        };
+        format!(" ⎇ {}{dirty}{ahead}{behind} ", self.branch)
+    }
+}
+
+/// `GitStatus`
+pub(crate) struct GitStatus {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The git status's config
+    config: Config,
+    /// The status of the repository in the working directory, if there is one.
+    maybe_status: Option<Status>,
+    /// The palette, used to style the widget.
+    palette: crate::palette::converter::Palette,
+}
+
+impl GitStatus {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().git_status.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "git_status".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            maybe_status: None,
+            palette,
+        }
+    }
+
+    /// The fallback refresh timer.
+    fn refresh_timer(config: &Config) -> tokio::time::Interval {
+        let seconds = config.refresh_interval.max(MINIMUM_REFRESH_INTERVAL);
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs_f32(seconds));
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        timer
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut git_status = Self::new(output, state, palette).await;
+        let mut refresh = Self::refresh_timer(&git_status.config);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = refresh.tick() => {
+                    git_status.refresh().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = git_status
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::GitStatus,
+                            |config| config.git_status.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    if let crate::run::Protocol::Config { config, .. } = &message {
+                        if config.git_status != git_status.config {
+                            refresh = Self::refresh_timer(&config.git_status);
+                        }
+                    }
+                    git_status.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::CommandFinished(_)
+            | crate::run::Protocol::WorkingDirectory(_) => {
+                self.refresh().await?;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.git_status.clone();
+                self.tattoy.opacity = self.config.opacity;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Ask `git` for the status of the working directory.
+    pub(crate) async fn query_git(directory: &std::path::Path) -> Option<Status> {
+        let result = tokio::process::Command::new("git")
+            .args(["status", "--porcelain=v2", "--branch"])
+            .current_dir(directory)
+            .kill_on_drop(true)
+            .output()
+            .await;
+
+        match result {
+            Ok(output) if output.status.success() => {
+                Some(Status::parse(&String::from_utf8_lossy(&output.stdout)))
+            }
+            Ok(_) => None,
+            Err(error) => {
+                tracing::debug!("Couldn't run `git status`: {error:?}");
+                None
+            }
+        }
+    }
+
+    /// Refresh the git status, only re-rendering if it changed.
+    async fn refresh(&mut self) -> Result<()> {
+        let maybe_directory = self.tattoy.state.working_directory.read().await.clone();
+        let maybe_status = match maybe_directory {
+            Some(directory) => Self::query_git(&directory).await,
+            None => None,
+        };
+
+        if maybe_status != self.maybe_status {
+            tracing::trace!("Git status changed: {maybe_status:?}");
+            self.maybe_status = maybe_status;
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let Some(status) = &self.maybe_status else {
+            return self.tattoy.send_blank_output().await;
+        };
+
+        self.tattoy.initialise_surface();
+        let text = status.summary();
+        let terminal = (
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+        );
+        let panel = (text.chars().count(), 1);
+        if terminal.0 < panel.0 || terminal.1 < panel.1 {
+            tracing::debug!("Terminal too small for the git status");
+            return self.tattoy.send_output().await;
+        }
+
+        let palette_index = if status.is_dirty {
+            DIRTY_PALETTE_INDEX
+        } else {
+            CLEAN_PALETTE_INDEX
+        };
+        let (x, y) = self.config.position.origin(terminal, panel);
+        self.tattoy.surface.add_text(
+            x,
+            y,
+            text,
+            Some(crate::surface::colour_from_srgba(
+                self.palette.default_background_colour(),
+            )),
+            Some(crate::surface::colour_from_srgba(
+                self.palette.true_colour_tuple_from_index(palette_index),
+            )),
+        );
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_a_dirty_branch() {
+        let output = "\
+# branch.oid 1234567890abcdef
+# branch.head main
+# branch.upstream origin/main
+# branch.ab +2 -1
+1 .M N... 100644 100644 100644 abc abc src/main.rs
+? new_file.rs
+";
+        let status = Status::parse(output);
+        assert_eq!(
+            status,
+            Status {
+                branch: "main".to_owned(),
+                is_dirty: true,
+                ahead: 2,
+                behind: 1,
+            }
+        );
+        assert_eq!(status.summary(), " ⎇ main* ↑2 ↓1 ");
+    }
+
+    #[test]
+    fn parsing_a_clean_detached_head() {
+        let output = "# branch.oid 1234567890abcdef\n# branch.head (detached)\n";
+        let status = Status::parse(output);
+        assert_eq!(status.branch, "1234567");
+        assert!(!status.is_dirty);
+        assert_eq!(status.summary(), " ⎇ 1234567 ");
+    }
+}
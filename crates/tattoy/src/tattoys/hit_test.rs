@@ -0,0 +1,116 @@
+//! Work out which part of a tattoy the mouse is over. Overlays, like the minimap, notifications
+//! and pickers, only want to react to the mouse when it's over something that they've actually
+//! drawn, not when it's over the transparent parts of their layer.
+
+/// Where a mouse event happened, relative to a tattoy's surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Hit {
+    /// The column and row of the cell under the mouse, starting at 0.
+    pub position: (usize, usize),
+    /// Whether the tattoy has drawn anything in the cell under the mouse.
+    pub is_on_content: bool,
+    /// The buttons that were pressed during the event.
+    pub buttons: termwiz::input::MouseButtons,
+}
+
+impl Hit {
+    /// Whether the left mouse button was pressed.
+    pub const fn is_left_click(&self) -> bool {
+        self.buttons.contains(termwiz::input::MouseButtons::LEFT)
+    }
+}
+
+/// The column and row of a mouse event, starting at 0. Terminals report mouse coordinates starting
+/// at 1.
+pub(crate) fn mouse_position(mouse: &termwiz::input::MouseEvent) -> (usize, usize) {
+    (
+        usize::from(mouse.x.saturating_sub(1)),
+        usize::from(mouse.y.saturating_sub(1)),
+    )
+}
+
+/// Test a mouse event against a tattoy's surface. There's no hit when the mouse is outside of the
+/// surface, for example when the tattoy isn't showing anything.
+pub(crate) fn hit(
+    surface: &crate::surface::Surface,
+    mouse: &termwiz::input::MouseEvent,
+) -> Option<Hit> {
+    let (column, row) = mouse_position(mouse);
+    if column >= surface.width || row >= surface.height {
+        return None;
+    }
+
+    Some(Hit {
+        position: (column, row),
+        is_on_content: is_cell_drawn(&surface.surface, column, row),
+        buttons: mouse.mouse_buttons,
+    })
+}
+
+/// Whether anything has been drawn in a cell. Empty cells without a background colour are
+/// transparent, so the layers underneath show through them.
+fn is_cell_drawn(surface: &termwiz::surface::Surface, column: usize, row: usize) -> bool {
+    surface.screen_lines().get(row).is_some_and(|line| {
+        line.visible_cells()
+            .find(|cell| (cell.cell_index()..cell.cell_index() + cell.width()).contains(&column))
+            .is_some_and(|cell| {
+                let is_empty = matches!(cell.str(), "" | " ");
+                let has_background =
+                    cell.attrs().background() != termwiz::color::ColorAttribute::Default;
+                !is_empty || has_background
+            })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mouse(x: u16, y: u16) -> termwiz::input::MouseEvent {
+        termwiz::input::MouseEvent {
+            x,
+            y,
+            mouse_buttons: termwiz::input::MouseButtons::LEFT,
+            modifiers: termwiz::input::Modifiers::NONE,
+        }
+    }
+
+    fn surface() -> crate::surface::Surface {
+        let mut surface = crate::surface::Surface::new("test".into(), 10, 5, 1, 1.0);
+        surface.add_text(2, 1, "hi".into(), None, None);
+        surface.add_pixel(5, 6, crate::surface::RED).unwrap();
+        surface
+    }
+
+    #[test]
+    fn mouse_positions_start_at_zero() {
+        assert_eq!(mouse_position(&mouse(1, 1)), (0, 0));
+        assert_eq!(mouse_position(&mouse(3, 2)), (2, 1));
+    }
+
+    #[test]
+    fn hitting_text() {
+        let result = hit(&surface(), &mouse(4, 2)).unwrap();
+        assert_eq!(result.position, (3, 1));
+        assert!(result.is_on_content);
+        assert!(result.is_left_click());
+    }
+
+    #[test]
+    fn hitting_pixels() {
+        assert!(hit(&surface(), &mouse(6, 4)).unwrap().is_on_content);
+    }
+
+    #[test]
+    fn missing_blank_cells() {
+        let result = hit(&surface(), &mouse(1, 1)).unwrap();
+        assert!(!result.is_on_content);
+    }
+
+    #[test]
+    fn missing_the_surface() {
+        assert_eq!(hit(&surface(), &mouse(11, 1)), None);
+        let blank = crate::surface::Surface::new("test".into(), 0, 0, 1, 1.0);
+        assert_eq!(hit(&blank, &mouse(1, 1)), None);
+    }
+}
@@ -0,0 +1,421 @@
+//! Show small previews of images whose paths are printed in the terminal, eg by `ls` or `find`.
+//! The previews are drawn with half-block pixels just to the right of the path.
+//!
+//! Decoded images are kept in a small cache, so that redrawing the same screen doesn't decode the
+//! same files again. Paths are resolved relative to the shell's working directory, which can only
+//! be followed when the shell reports it with OSC 7.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for image previews.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable image previews.
+    pub enabled: bool,
+    /// The maximum width of a preview, in columns.
+    pub max_width: u32,
+    /// The maximum height of a preview, in rows.
+    pub max_height: u32,
+    /// The most previews shown at once. The most recently printed paths get previews first.
+    pub max_previews: usize,
+    /// Files bigger than this aren't previewed, in megabytes.
+    pub max_file_size: f32,
+    /// How many decoded previews to keep in memory.
+    pub cache_size: usize,
+    /// The transparency of the previews.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_width: 24,
+            max_height: 8,
+            max_previews: 3,
+            max_file_size: 10.0,
+            cache_size: 32,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The compositing layer of the previews.
+const LAYER: i16 = 60;
+
+/// The file extensions of the images that can be previewed.
+const EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+/// Characters that commonly surround paths in output, eg quotes and brackets.
+const PATH_DELIMITERS: [char; 12] = ['"', '\'', '`', ',', ':', ';', '(', ')', '[', ']', '<', '>'];
+
+/// How many columns to leave between a path and its preview.
+const GAP: usize = 2;
+
+/// How often the screen is checked for new paths whilst its output is changing.
+const SCAN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// An image path printed on the screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mention {
+    /// The path, exactly as it was printed.
+    path: String,
+    /// The row that the path is on.
+    row: usize,
+    /// The column just after the end of the path.
+    end: usize,
+}
+
+/// Find all the image paths in a line of text, returning each path and the column just after it.
+fn find_image_paths(line: &str) -> Vec<(String, usize)> {
+    let mut paths = Vec::new();
+    let mut word = String::new();
+    for (column, character) in line.chars().chain(core::iter::once(' ')).enumerate() {
+        if !character.is_whitespace() {
+            word.push(character);
+            continue;
+        }
+        if word.is_empty() {
+            continue;
+        }
+
+        let trailing = word.len() - word.trim_end_matches(PATH_DELIMITERS).len();
+        let path = word.trim_matches(PATH_DELIMITERS);
+        let is_image = std::path::Path::new(path)
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+        if is_image {
+            paths.push((path.to_owned(), column - trailing));
+        }
+        word.clear();
+    }
+    paths
+}
+
+/// A small least-recently-used cache of decoded previews. Files that couldn't be decoded are
+/// cached too, so that they aren't continually retried.
+#[derive(Default)]
+struct Cache {
+    /// The cached previews, the most recently used last.
+    entries: std::collections::VecDeque<(std::path::PathBuf, Option<image::RgbaImage>)>,
+}
+
+impl Cache {
+    /// Get a cached preview, marking it as recently used. The outer `Option` is whether the path
+    /// is cached at all.
+    fn get(&mut self, path: &std::path::Path) -> Option<Option<&image::RgbaImage>> {
+        let position = self.entries.iter().position(|(cached, _)| cached == path)?;
+        let entry = self.entries.remove(position)?;
+        self.entries.push_back(entry);
+        self.entries.back().map(|(_, maybe_image)| maybe_image.as_ref())
+    }
+
+    /// Add a preview, forgetting the least recently used previews if the cache is full.
+    fn insert(
+        &mut self,
+        path: std::path::PathBuf,
+        maybe_image: Option<image::RgbaImage>,
+        capacity: usize,
+    ) {
+        self.entries.retain(|(cached, _)| *cached != path);
+        self.entries.push_back((path, maybe_image));
+        while self.entries.len() > capacity.max(1) {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// `ImagePreview`
+pub(crate) struct ImagePreview {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The image preview's config
+    config: Config,
+    /// The decoded previews.
+    cache: Cache,
+    /// The image paths currently on screen that have previews.
+    mentions: Vec<Mention>,
+    /// Whether the screen has changed since it was last checked for paths.
+    is_screen_dirty: bool,
+}
+
+impl ImagePreview {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().image_preview.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "image_preview".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            cache: Cache::default(),
+            mentions: Vec::new(),
+            is_screen_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut image_preview = Self::new(output, state).await;
+        let mut scan = tokio::time::interval(SCAN_INTERVAL);
+        scan.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = scan.tick(), if image_preview.is_screen_dirty => {
+                    image_preview.is_screen_dirty = false;
+                    image_preview.scan().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = image_preview
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::ImagePreview,
+                            |config| config.image_preview.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    image_preview.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        if super::tattoyer::Tattoyer::is_screen_output_changed(&message) {
+            self.is_screen_dirty = true;
+        }
+
+        if let crate::run::Protocol::Config { config, .. } = &message {
+            if config.image_preview != self.config {
+                self.config = config.image_preview.clone();
+                self.tattoy.opacity = self.config.opacity;
+                // Previews may now need to be a different size, so everything is redone.
+                self.cache = Cache::default();
+                self.mentions.clear();
+                self.is_screen_dirty = true;
+            }
+        }
+
+        let was_scrolling = self.tattoy.is_scrolling();
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if was_scrolling != self.tattoy.is_scrolling() {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// All the image paths currently on the screen.
+    fn find_mentions(&mut self) -> Vec<Mention> {
+        self.tattoy
+            .screen
+            .surface
+            .screen_cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                let text: String = line
+                    .iter()
+                    .map(|cell| cell.str().chars().next().unwrap_or(' '))
+                    .collect();
+                find_image_paths(&text)
+                    .into_iter()
+                    .map(move |(path, end)| Mention { path, row, end })
+            })
+            .collect()
+    }
+
+    /// Check the screen for image paths and make sure they have previews.
+    async fn scan(&mut self) -> Result<()> {
+        if self.tattoy.is_alternate_screen() {
+            self.mentions.clear();
+            return self.render().await;
+        }
+
+        let maybe_directory = self.tattoy.state.working_directory.read().await.clone();
+        let mut mentions = Vec::new();
+        // The most recently printed paths are at the bottom of the screen.
+        for mention in self.find_mentions().into_iter().rev() {
+            if mentions.len() >= self.config.max_previews {
+                break;
+            }
+            let path = match &maybe_directory {
+                Some(directory) => directory.join(&mention.path),
+                None => std::path::PathBuf::from(&mention.path),
+            };
+            if self.preview(&path).await.is_some() {
+                mentions.push(mention);
+            }
+        }
+
+        if mentions != self.mentions {
+            self.mentions = mentions;
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the preview for an image file, decoding it if it isn't cached.
+    async fn preview(&mut self, path: &std::path::Path) -> Option<&image::RgbaImage> {
+        if self.cache.get(path).is_none() {
+            let maybe_image = self.decode(path).await;
+            self.cache
+                .insert(path.to_path_buf(), maybe_image, self.config.cache_size);
+        }
+        self.cache.get(path).flatten()
+    }
+
+    /// Decode and shrink an image file, as long as it isn't too big.
+    async fn decode(&self, path: &std::path::Path) -> Option<image::RgbaImage> {
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "The file size limit is always a small positive number"
+        )]
+        let maximum_bytes = (self.config.max_file_size.max(0.0) * 1_000_000.0) as u64;
+        if !metadata.is_file() || metadata.len() > maximum_bytes {
+            return None;
+        }
+
+        // There are 2 pixels per row.
+        let size = (self.config.max_width, self.config.max_height * 2);
+        let path = path.to_path_buf();
+        let result = tokio::task::spawn_blocking(move || {
+            image::ImageReader::open(&path)?
+                .with_guessed_format()?
+                .decode()
+                .map_err(std::io::Error::other)
+                .map(|image| image.thumbnail(size.0, size.1).to_rgba8())
+        })
+        .await;
+
+        match result {
+            Ok(Ok(image)) => Some(image),
+            Ok(Err(error)) => {
+                tracing::debug!("Couldn't decode image for preview: {error:?}");
+                None
+            }
+            Err(error) => {
+                tracing::error!("Image decoding task failed: {error:?}");
+                None
+            }
+        }
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        if self.mentions.is_empty() || self.tattoy.is_scrolling() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height) * 2;
+        let maybe_directory = self.tattoy.state.working_directory.read().await.clone();
+        let mentions = self.mentions.clone();
+        for mention in mentions {
+            let path = match &maybe_directory {
+                Some(directory) => directory.join(&mention.path),
+                None => std::path::PathBuf::from(&mention.path),
+            };
+            let Some(Some(image)) = self.cache.get(&path) else {
+                continue;
+            };
+
+            let origin = (mention.end + GAP, mention.row * 2);
+            for (x, y, pixel) in image.enumerate_pixels() {
+                let [red, green, blue, alpha] = pixel.0;
+                if alpha == 0 {
+                    continue;
+                }
+                let position = (origin.0 + usize::try_from(x)?, origin.1 + usize::try_from(y)?);
+                if position.0 >= width || position.1 >= height {
+                    continue;
+                }
+                let colour = (
+                    f32::from(red) / 255.0,
+                    f32::from(green) / 255.0,
+                    f32::from(blue) / 255.0,
+                    f32::from(alpha) / 255.0,
+                );
+                self.tattoy
+                    .surface
+                    .add_pixel(position.0, position.1, colour)?;
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finding_image_paths() {
+        let paths = find_image_paths("cat.png  notes.txt 'holiday.JPG' (dir/dog.webp),");
+        assert_eq!(
+            paths,
+            vec![
+                ("cat.png".to_owned(), 7),
+                ("holiday.JPG".to_owned(), 31),
+                ("dir/dog.webp".to_owned(), 46),
+            ]
+        );
+        assert!(find_image_paths("png jpeg .gitignore").is_empty());
+    }
+
+    #[test]
+    fn cache_forgets_least_recently_used() {
+        let mut cache = Cache::default();
+        let image = || Some(image::RgbaImage::new(1, 1));
+        cache.insert("a.png".into(), image(), 2);
+        cache.insert("b.png".into(), None, 2);
+        assert!(cache.get(std::path::Path::new("a.png")).is_some());
+        cache.insert("c.png".into(), image(), 2);
+
+        assert!(cache.get(std::path::Path::new("b.png")).is_none());
+        assert!(matches!(
+            cache.get(std::path::Path::new("a.png")),
+            Some(Some(_))
+        ));
+        assert!(matches!(
+            cache.get(std::path::Path::new("c.png")),
+            Some(Some(_))
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+//! A live view of the current session's warnings and errors, so that users can see what went wrong
+//! without leaving the terminal to open the log file. The warnings and errors are captured by a
+//! `tracing` layer, even when logging to a file is disabled.
+
+use color_eyre::eyre::Result;
+use tracing_subscriber::Layer as _;
+
+/// User-configurable settings for the log viewer.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the log viewer. When enabled it can be shown and hidden with the
+    /// `toggle_log_viewer` keybinding.
+    pub enabled: bool,
+    /// Whether to show the log viewer as soon as Tattoy starts.
+    pub show_on_start: bool,
+    /// The maximum number of log lines to show.
+    pub lines: usize,
+    /// The transparency of the log viewer.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            show_on_start: false,
+            lines: 10,
+            opacity: 0.9,
+        }
+    }
+}
+
+/// The compositing layer of the log viewer.
+const LAYER: i16 = 150;
+
+/// How often the log viewer is refreshed, in seconds.
+const REFRESH_INTERVAL: f32 = 0.5;
+
+/// The number of warnings and errors that are kept.
+const MAX_ENTRIES: usize = 100;
+
+/// The palette index of the colour of errors.
+const ERROR_PALETTE_INDEX: u8 = 1;
+
+/// The palette index of the colour of warnings.
+const WARNING_PALETTE_INDEX: u8 = 3;
+
+/// A warning or error that was logged during this session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    /// The local time that it was logged, eg `15:04:05`.
+    pub time: String,
+    /// Whether it's a warning or an error.
+    pub level: tracing::Level,
+    /// The message and any other fields.
+    pub message: String,
+}
+
+impl Entry {
+    /// The entry as a single line of text.
+    pub(crate) fn line(&self) -> String {
+        let message = self.message.replace('\n', " ");
+        format!("{} {:5} {message}", self.time, self.level.as_str())
+    }
+}
+
+/// The most recent warnings and errors, shared between the capturing layer and the log viewer.
+pub(crate) type Entries = std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<Entry>>>;
+
+/// A `tracing` layer that keeps the most recent warnings and errors.
+struct Capture(Entries);
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Capture {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let entry = Entry {
+            time: chrono::Local::now().format("%H:%M:%S").to_string(),
+            level: *event.metadata().level(),
+            message: crate::log_file::Fields::from_event(event).summary(),
+        };
+
+        // Logging from inside a `tracing` layer would recurse, so a poisoned lock is just ignored.
+        let Ok(mut entries) = self.0.lock() else {
+            return;
+        };
+        entries.push_back(entry);
+        while entries.len() > MAX_ENTRIES {
+            entries.pop_front();
+        }
+    }
+}
+
+/// The layer that captures Tattoy's warnings and errors into the given entries.
+pub(crate) fn capture_layer<S>(entries: Entries) -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
+{
+    let targets = tracing_subscriber::filter::Targets::new()
+        .with_target("tattoy", tracing::Level::WARN)
+        .with_target("shadow_terminal", tracing::Level::WARN);
+    Capture(entries).with_filter(targets)
+}
+
+/// `LogViewer`
+pub(crate) struct LogViewer {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The log viewer's config
+    config: Config,
+    /// Is the log viewer currently visible?
+    is_visible: bool,
+    /// The palette, used to style the log viewer.
+    palette: crate::palette::converter::Palette,
+}
+
+impl LogViewer {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().log_viewer.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "log_viewer".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            is_visible: config.show_on_start,
+            config,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut viewer = Self::new(output, state, palette).await;
+        let mut refresh =
+            tokio::time::interval(std::time::Duration::from_secs_f32(REFRESH_INTERVAL));
+        refresh.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = refresh.tick(), if viewer.is_visible => {
+                    viewer.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = viewer
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::LogViewer,
+                            |config| config.log_viewer.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    viewer.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::ToggleLogViewer,
+            ) => {
+                self.is_visible = !self.is_visible;
+                tracing::debug!("Log viewer visible: {}", self.is_visible);
+                if !self.is_visible {
+                    self.tattoy.send_blank_output().await?;
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.log_viewer.clone();
+                self.tattoy.opacity = self.config.opacity;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_resize && self.is_visible {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// The most recent entries, oldest first.
+    fn latest_entries(&self) -> Vec<Entry> {
+        let Ok(entries) = self.tattoy.state.recent_logs.lock() else {
+            return Vec::new();
+        };
+        let skip = entries.len().saturating_sub(self.config.lines);
+        entries.iter().skip(skip).cloned().collect()
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let entries = self.latest_entries();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        let panel_height = entries.len().max(1).saturating_add(1);
+        if height < panel_height {
+            tracing::debug!("Terminal too small for the log viewer");
+            return self.tattoy.send_output().await;
+        }
+
+        let background =
+            crate::surface::colour_from_srgba(self.palette.default_background_colour());
+        let foreground =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+        let top = height - panel_height;
+        let fit =
+            |text: String| -> String { format!(" {text:width$}").chars().take(width).collect() };
+
+        self.tattoy.surface.add_text(
+            0,
+            top,
+            fit("Warnings and errors".to_owned()),
+            Some(background),
+            Some(foreground),
+        );
+        if entries.is_empty() {
+            self.tattoy.surface.add_text(
+                0,
+                top + 1,
+                fit("Nothing yet".to_owned()),
+                Some(background),
+                Some(foreground),
+            );
+        }
+        for (y, entry) in (top + 1..).zip(entries) {
+            let index = if entry.level == tracing::Level::ERROR {
+                ERROR_PALETTE_INDEX
+            } else {
+                WARNING_PALETTE_INDEX
+            };
+            let colour =
+                crate::surface::colour_from_srgba(self.palette.true_colour_tuple_from_index(index));
+            self.tattoy
+                .surface
+                .add_text(0, y, fit(entry.line()), Some(background), Some(colour));
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    #[test]
+    fn capturing_warnings_and_errors() {
+        let entries = Entries::default();
+        let subscriber =
+            tracing_subscriber::registry().with(capture_layer(std::sync::Arc::clone(&entries)));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Ignored");
+            tracing::warn!(path = "/tmp/x", "Couldn't load");
+        });
+
+        let captured = entries.lock().unwrap().clone();
+        assert_eq!(captured.len(), 1);
+        let entry = captured.front().unwrap();
+        assert_eq!(entry.level, tracing::Level::WARN);
+        assert_eq!(entry.message, "Couldn't load path=/tmp/x");
+    }
+
+    #[test]
+    fn only_keeping_the_latest_entries() {
+        let entries = Entries::default();
+        let subscriber =
+            tracing_subscriber::registry().with(capture_layer(std::sync::Arc::clone(&entries)));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!("First");
+            for _ in 0..MAX_ENTRIES {
+                tracing::error!("Again");
+            }
+        });
+
+        let captured = entries.lock().unwrap().clone();
+        assert_eq!(captured.len(), MAX_ENTRIES);
+        assert!(captured.iter().all(|entry| entry.message == "Again"));
+    }
+
+    #[test]
+    fn entry_lines() {
+        let entry = Entry {
+            time: "15:04:05".to_owned(),
+            level: tracing::Level::WARN,
+            message: "Couldn't load\nthe theme".to_owned(),
+        };
+        assert_eq!(entry.line(), "15:04:05 WARN  Couldn't load the theme");
+    }
+}
@@ -0,0 +1,429 @@
+//! A lens that follows the mouse and shows an enlarged copy of the terminal underneath it. Useful
+//! for demos and for anyone with low vision. Every cell under the lens is shown twice as big: the
+//! half-block pixels of graphics are each drawn as 2x2 pixels, and text is spaced out over 2x2
+//! cells.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the magnifier.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the magnifier. When enabled it can be shown and hidden with the
+    /// `toggle_magnifier` keybinding.
+    pub enabled: bool,
+    /// The width of the lens in columns, including its border.
+    pub width: u16,
+    /// The height of the lens in rows, including its border.
+    pub height: u16,
+    /// The transparency of the lens.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            width: 42,
+            height: 14,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The compositing layer of the lens. It's above all the other widgets, apart from notifications.
+const LAYER: i16 = 160;
+
+/// How much the lens enlarges the terminal.
+const ZOOM: usize = 2;
+
+/// The columns and rows taken up by the border on each side of the lens.
+const BORDER: usize = 1;
+
+/// What a single cell looks like under the lens.
+#[derive(Debug, Clone, PartialEq)]
+enum Sample {
+    /// A cell of graphics, made up of a top and bottom half-block pixel.
+    Pixels {
+        /// The colour of the top pixel.
+        top: crate::surface::Colour,
+        /// The colour of the bottom pixel.
+        bottom: crate::surface::Colour,
+    },
+    /// A cell of text.
+    Text {
+        /// The cell's character.
+        character: String,
+        /// The colour of the character.
+        foreground: crate::surface::Colour,
+        /// The colour behind the character.
+        background: crate::surface::Colour,
+    },
+}
+
+/// Work out what a cell looks like, based on its character and colours.
+fn sample(
+    character: &str,
+    foreground: crate::surface::Colour,
+    background: crate::surface::Colour,
+) -> Sample {
+    match character {
+        "▀" => Sample::Pixels {
+            top: foreground,
+            bottom: background,
+        },
+        "▄" => Sample::Pixels {
+            top: background,
+            bottom: foreground,
+        },
+        "█" => Sample::Pixels {
+            top: foreground,
+            bottom: foreground,
+        },
+        blank if blank.trim().is_empty() => Sample::Pixels {
+            top: background,
+            bottom: background,
+        },
+        text => Sample::Text {
+            character: text.to_owned(),
+            foreground,
+            background,
+        },
+    }
+}
+
+/// The top-left of a lens of the given size, centred on the mouse but kept inside the terminal.
+fn lens_origin(
+    mouse: (usize, usize),
+    lens: (usize, usize),
+    terminal: (usize, usize),
+) -> (usize, usize) {
+    (
+        mouse
+            .0
+            .saturating_sub(lens.0.div_euclid(2))
+            .min(terminal.0.saturating_sub(lens.0)),
+        mouse
+            .1
+            .saturating_sub(lens.1.div_euclid(2))
+            .min(terminal.1.saturating_sub(lens.1)),
+    )
+}
+
+/// The top-left of the cells that are enlarged, so that the cell under the mouse is in the middle
+/// of the lens's content.
+const fn source_origin(mouse: (usize, usize), content: (usize, usize)) -> (usize, usize) {
+    (
+        mouse.0.saturating_sub(content.0.div_euclid(ZOOM * 2)),
+        mouse.1.saturating_sub(content.1.div_euclid(ZOOM * 2)),
+    )
+}
+
+/// `Magnifier`
+pub(crate) struct Magnifier {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The magnifier's config
+    config: Config,
+    /// Whether the lens is currently shown.
+    is_visible: bool,
+    /// The last known position of the mouse, in columns and rows of the user's terminal.
+    mouse: Option<(usize, usize)>,
+    /// The palette, used for default colours and the border.
+    palette: crate::palette::converter::Palette,
+}
+
+impl Magnifier {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().magnifier.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "magnifier".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            is_visible: false,
+            mouse: None,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut magnifier = Self::new(output, state, palette).await;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            let is_disabled = magnifier
+                .tattoy
+                .is_disabled_by(
+                    &message,
+                    &crate::config::diff::Section::Magnifier,
+                    |config| config.magnifier.enabled,
+                )
+                .await?;
+            if is_disabled {
+                break;
+            }
+            magnifier.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(
+            message,
+            crate::run::Protocol::Output(_) | crate::run::Protocol::Resize { .. }
+        );
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::ToggleMagnifier,
+            ) => {
+                self.is_visible = !self.is_visible;
+                tracing::debug!("Magnifier visible: {}", self.is_visible);
+                if !self.is_visible {
+                    self.tattoy.send_blank_output().await?;
+                }
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Input(input) => {
+                if let termwiz::input::InputEvent::Mouse(mouse) = &input.event {
+                    let position = crate::tattoys::hit_test::mouse_position(mouse);
+                    is_rerender_needed = self.mouse != Some(position);
+                    self.mouse = Some(position);
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.magnifier.clone();
+                self.tattoy.opacity = self.config.opacity;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if self.is_visible && is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sample the cell of the PTY's screen at the given column and row. Cells outside of the
+    /// screen look like the default background.
+    fn sample_cell(
+        cells: &[&mut [termwiz::cell::Cell]],
+        column: usize,
+        row: usize,
+        palette: &crate::palette::converter::Palette,
+    ) -> Sample {
+        let default_foreground =
+            crate::surface::colour_from_srgba(palette.default_foreground_colour());
+        let default_background =
+            crate::surface::colour_from_srgba(palette.default_background_colour());
+        let Some(cell) = cells.get(row).and_then(|line| line.get(column)) else {
+            return sample(" ", default_foreground, default_background);
+        };
+
+        let foreground = crate::blender::Blender::extract_colour(cell.attrs().foreground())
+            .map_or(default_foreground, crate::surface::colour_from_srgba);
+        let background = crate::blender::Blender::extract_colour(cell.attrs().background())
+            .map_or(default_background, crate::surface::colour_from_srgba);
+        sample(cell.str(), foreground, background)
+    }
+
+    /// Draw the border around the lens.
+    fn render_border(&mut self, origin: (usize, usize), size: (usize, usize)) {
+        let foreground =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+        let background =
+            crate::surface::colour_from_srgba(self.palette.default_background_colour());
+        let inner = "─".repeat(size.0.saturating_sub(BORDER * 2));
+        let bottom = origin.1 + size.1.saturating_sub(1);
+        let right = origin.0 + size.0.saturating_sub(1);
+
+        self.tattoy.surface.add_text(
+            origin.0,
+            origin.1,
+            format!("┌{inner}┐"),
+            Some(background),
+            Some(foreground),
+        );
+        for row in (origin.1 + BORDER)..bottom {
+            for column in [origin.0, right] {
+                self.tattoy.surface.add_text(
+                    column,
+                    row,
+                    "│".to_owned(),
+                    Some(background),
+                    Some(foreground),
+                );
+            }
+        }
+        self.tattoy.surface.add_text(
+            origin.0,
+            bottom,
+            format!("└{inner}┘"),
+            Some(background),
+            Some(foreground),
+        );
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let terminal = (
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+        );
+        let lens = (
+            usize::from(self.config.width).min(terminal.0),
+            usize::from(self.config.height).min(terminal.1),
+        );
+        // Each enlarged cell takes up a whole number of columns and rows.
+        let content = (
+            lens.0.saturating_sub(BORDER * 2).div_euclid(ZOOM) * ZOOM,
+            lens.1.saturating_sub(BORDER * 2).div_euclid(ZOOM) * ZOOM,
+        );
+        if content.0 == 0 || content.1 == 0 {
+            return self.tattoy.send_output().await;
+        }
+
+        let mouse = self
+            .mouse
+            .unwrap_or((terminal.0.div_euclid(2), terminal.1.div_euclid(2)));
+        let origin = lens_origin(mouse, lens, terminal);
+        self.render_border(origin, lens);
+
+        // The PTY is offset by any rows that are reserved above it, eg by the status bar.
+        let top = usize::from(self.tattoy.state.reserved_rows.get().top);
+        let source = source_origin((mouse.0, mouse.1.saturating_sub(top)), content);
+        let palette = self.palette.clone();
+        let cells = self.tattoy.screen.surface.screen_cells();
+        let samples = (0..content.1.div_euclid(ZOOM))
+            .flat_map(|row| (0..content.0.div_euclid(ZOOM)).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let cell = Self::sample_cell(&cells, source.0 + column, source.1 + row, &palette);
+                (column, row, cell)
+            })
+            .collect::<Vec<(usize, usize, Sample)>>();
+
+        for (column, row, cell) in samples {
+            let x = origin.0 + BORDER + column * ZOOM;
+            let y = origin.1 + BORDER + row * ZOOM;
+            let blank = " ".repeat(ZOOM);
+            match cell {
+                Sample::Pixels { top, bottom } => {
+                    self.tattoy
+                        .surface
+                        .add_text(x, y, blank.clone(), Some(top), None);
+                    self.tattoy
+                        .surface
+                        .add_text(x, y + 1, blank, Some(bottom), None);
+                }
+                Sample::Text {
+                    character,
+                    foreground,
+                    background,
+                } => {
+                    self.tattoy.surface.add_text(
+                        x,
+                        y,
+                        format!("{character} "),
+                        Some(background),
+                        Some(foreground),
+                    );
+                    self.tattoy
+                        .surface
+                        .add_text(x, y + 1, blank, Some(background), None);
+                }
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RED: crate::surface::Colour = (1.0, 0.0, 0.0, 1.0);
+    const BLUE: crate::surface::Colour = (0.0, 0.0, 1.0, 1.0);
+
+    #[test]
+    fn sampling_cells() {
+        assert_eq!(
+            sample("▀", RED, BLUE),
+            Sample::Pixels {
+                top: RED,
+                bottom: BLUE
+            }
+        );
+        assert_eq!(
+            sample("▄", RED, BLUE),
+            Sample::Pixels {
+                top: BLUE,
+                bottom: RED
+            }
+        );
+        assert_eq!(
+            sample(" ", RED, BLUE),
+            Sample::Pixels {
+                top: BLUE,
+                bottom: BLUE
+            }
+        );
+        assert_eq!(
+            sample("a", RED, BLUE),
+            Sample::Text {
+                character: "a".to_owned(),
+                foreground: RED,
+                background: BLUE
+            }
+        );
+    }
+
+    #[test]
+    fn placing_the_lens() {
+        assert_eq!(lens_origin((40, 12), (20, 10), (80, 24)), (30, 7));
+        assert_eq!(lens_origin((2, 1), (20, 10), (80, 24)), (0, 0));
+        assert_eq!(lens_origin((79, 23), (20, 10), (80, 24)), (60, 14));
+    }
+
+    #[test]
+    fn finding_the_source() {
+        assert_eq!(source_origin((40, 12), (40, 12)), (30, 9));
+        assert_eq!(source_origin((3, 1), (40, 12)), (0, 0));
+    }
+}
@@ -6,25 +6,90 @@ use color_eyre::eyre::{ContextCompat as _, Result};
 
 use super::tattoyer::Tattoyer;
 
+/// Which side of the terminal the minimap is on.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Side {
+    /// The left side of the terminal.
+    Left,
+    /// The right side of the terminal.
+    #[default]
+    Right,
+}
+
+/// What shows and hides the minimap.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Trigger {
+    /// The minimap is shown as soon as Tattoy starts.
+    Always,
+    /// The minimap is shown when the mouse reaches the edge of the terminal, and hidden when the
+    /// mouse moves away from it.
+    #[default]
+    Hover,
+    /// The minimap is only shown and hidden with the `toggle_minimap` keybinding.
+    Keybinding,
+}
+
 /// User-configurable settings for the minimap
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the minimap
     pub enabled: bool,
-    /// The max width of the minimap (in units of terminal columns). The image resizer may choose a
-    /// slimmer minimap in order to maintain the original aspect ratio.
-    max_width: u16,
+    /// Which side of the terminal the minimap is on.
+    position: Side,
+    /// The width of the minimap (in units of terminal columns). The whole scrollback is squashed
+    /// to fit the height of the terminal, so the minimap doesn't keep the scrollback's aspect
+    /// ratio.
+    width: u16,
+    /// What shows and hides the minimap. The `toggle_minimap` keybinding always works.
+    trigger: Trigger,
+    /// The transparency of the minimap.
+    opacity: f32,
     /// The speed of the minimap show/hide animation.
-    animation_speed: f32,
+    pub animation_speed: f32,
+    /// Override the global frame rate for the minimap.
+    pub frame_rate: Option<u32>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             enabled: true,
-            max_width: 15,
+            position: Side::Right,
+            width: 15,
+            trigger: Trigger::Hover,
+            opacity: 0.95,
             animation_speed: 0.15,
+            frame_rate: None,
+        }
+    }
+}
+
+/// The columns of the terminal that the minimap covers when it's completely shown.
+fn minimap_columns(side: Side, minimap_width: u32, tty_width: u32) -> core::ops::Range<u32> {
+    match side {
+        Side::Left => 0..minimap_width.min(tty_width),
+        Side::Right => tty_width.saturating_sub(minimap_width)..tty_width,
+    }
+}
+
+/// The column of the terminal for a column of the minimap, whilst the minimap is slid
+/// `x_offset` columns towards its edge of the terminal. Columns that have slid off the terminal
+/// don't have a column.
+fn surface_column(
+    side: Side,
+    x_minimap: u32,
+    x_offset: u32,
+    minimap_width: u32,
+    tty_width: u32,
+) -> Option<u32> {
+    match side {
+        Side::Left => x_minimap.checked_sub(x_offset),
+        Side::Right => {
+            let column = tty_width.saturating_sub(minimap_width) + x_minimap + x_offset;
+            (column < tty_width).then_some(column)
         }
     }
 }
@@ -42,6 +107,50 @@ enum AnimationStep {
     Hiding(f32),
 }
 
+/// How much the part of the minimap that's currently visible in the terminal is lightened.
+const VIEWPORT_HIGHLIGHT: f32 = 0.25;
+
+/// The rows of the scrollback minimap's image that are currently visible in the terminal.
+fn viewport_pixels(
+    scrollback_height: usize,
+    screen_height: usize,
+    scroll_position: usize,
+    minimap_height: usize,
+) -> core::ops::Range<usize> {
+    if scrollback_height == 0 {
+        return 0..0;
+    }
+
+    let view_top = scrollback_height
+        .saturating_sub(screen_height)
+        .saturating_sub(scroll_position);
+    let view_bottom = (view_top + screen_height).min(scrollback_height);
+    let start = (view_top * minimap_height).div_euclid(scrollback_height);
+    let end = (view_bottom * minimap_height).div_euclid(scrollback_height);
+    start..end.max(start + 1).min(minimap_height)
+}
+
+/// The scroll position that centres the terminal on the scrollback line that's under a row of
+/// the minimap. The minimap's image has 2 pixels per row, and when the scrollback is short, the
+/// image doesn't reach all the way to the top of the terminal.
+fn scroll_position_for_row(
+    row: usize,
+    empty_height: usize,
+    minimap_height: usize,
+    scrollback_height: usize,
+    screen_height: usize,
+) -> Option<usize> {
+    if minimap_height == 0 {
+        return None;
+    }
+
+    let y_image = (row * 2).saturating_sub(empty_height).min(minimap_height);
+    let line = (y_image * scrollback_height).div_euclid(minimap_height);
+    let view_top = line.saturating_sub(screen_height.div_euclid(2));
+    let bottom_screen_top = scrollback_height.saturating_sub(screen_height);
+    Some(bottom_screen_top.saturating_sub(view_top))
+}
+
 /// `Minimap`
 pub struct Minimap {
     /// The base Tattoy struct
@@ -52,6 +161,8 @@ pub struct Minimap {
     screen: image::ImageBuffer<image::Rgba<f32>, Vec<f32>>,
     /// Shared app state
     state: Arc<crate::shared_state::SharedState>,
+    /// The minimap's config
+    config: Config,
     /// If the PTY output has changed.
     output_changed: bool,
     /// The current state of any UI transitions; fading, sliding, etc.
@@ -64,6 +175,7 @@ impl Minimap {
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: Arc<crate::shared_state::SharedState>,
     ) -> Self {
+        let config = state.config.load().minimap.clone();
         let tattoy = Tattoyer::new(
             "minimap".to_owned(),
             Arc::clone(&state),
@@ -72,14 +184,19 @@ impl Minimap {
             output_channel,
         )
         .await;
-        Self {
+        let mut minimap = Self {
             tattoy,
             scrollback: image::ImageBuffer::default(),
             screen: image::ImageBuffer::default(),
             state,
+            config,
             output_changed: true,
             animation_step: AnimationStep::Hidden,
+        };
+        if minimap.config.trigger == Trigger::Always {
+            minimap.show();
         }
+        minimap
     }
 
     /// Our main entrypoint.
@@ -103,6 +220,19 @@ impl Minimap {
                     if matches!(result, Ok(crate::run::Protocol::End)) {
                         break;
                     }
+                    if let Ok(message) = &result {
+                        let is_disabled = minimap
+                            .tattoy
+                            .is_disabled_by(
+                                message,
+                                &crate::config::diff::Section::Minimap,
+                                |config| config.minimap.enabled,
+                            )
+                            .await?;
+                        if is_disabled {
+                            break;
+                        }
+                    }
                     minimap.handle_protocol_message(result).await?;
                 }
             }
@@ -118,15 +248,31 @@ impl Minimap {
     ) -> Result<()> {
         match result {
             Ok(message) => {
-                self.check_if_mouse_is_over_right_columns(&message);
+                self.check_if_mouse_is_over_edge_columns(&message);
+                self.check_for_click(&message)?;
                 self.check_for_keybind(&message);
+                let is_layout_changed = self.check_for_config(&message);
 
                 let maybe_pty_changed = Tattoyer::is_pty_changed(&message);
                 self.tattoy.handle_common_protocol_messages(message)?;
 
+                if is_layout_changed {
+                    self.rebuild(shadow_terminal::output::SurfaceKind::Scrollback)
+                        .await?;
+                    if self.tattoy.is_alternate_screen() {
+                        self.rebuild(shadow_terminal::output::SurfaceKind::Screen)
+                            .await?;
+                    }
+                }
+
                 if let Some(changed_pty_surface) = maybe_pty_changed {
                     self.rebuild(changed_pty_surface).await?;
                 }
+
+                // Scrolling moves the viewport indicator.
+                if self.tattoy.scrollback.position != self.tattoy.last_scroll_position {
+                    self.output_changed = true;
+                }
             }
             Err(error) => tracing::error!("Receiving protocol message: {error:?}"),
         }
@@ -139,11 +285,15 @@ impl Minimap {
         self.output_changed || !self.is_hidden()
     }
 
-    /// Check if the scrollback output has changed such that we need to trigger a re-render.
-    fn check_if_mouse_is_over_right_columns(&mut self, message: &crate::run::Protocol) {
+    /// Show the minimap when the mouse reaches the edge of the terminal, and hide it when the mouse
+    /// moves away.
+    fn check_if_mouse_is_over_edge_columns(&mut self, message: &crate::run::Protocol) {
         let crate::run::Protocol::Input(input) = message else {
             return;
         };
+        if self.config.trigger != Trigger::Hover {
+            return;
+        }
 
         #[expect(
             clippy::single_match,
@@ -152,13 +302,25 @@ impl Minimap {
         )]
         match &input.event {
             termwiz::input::InputEvent::Mouse(mouse) => {
-                if self.is_hidden() && mouse.x > self.tattoy.width - 2 {
+                let (column, _) = crate::tattoys::hit_test::mouse_position(mouse);
+                let Ok(column) = u32::try_from(column) else {
+                    return;
+                };
+                let tty_width = u32::from(self.tattoy.width);
+                let is_at_edge = match self.config.position {
+                    Side::Left => column < 2,
+                    Side::Right => column >= tty_width.saturating_sub(2),
+                };
+                if self.is_hidden() && is_at_edge {
                     self.show();
                 }
 
-                let is_mouse_outside_minimap = u32::from(mouse.x) - 1
-                    < u32::from(self.tattoy.width) - self.scrollback.dimensions().0;
-                if self.is_shown() && is_mouse_outside_minimap {
+                let columns = minimap_columns(
+                    self.config.position,
+                    self.scrollback.dimensions().0,
+                    tty_width,
+                );
+                if self.is_shown() && !columns.contains(&column) {
                     self.hide();
                 }
             }
@@ -166,6 +328,53 @@ impl Minimap {
         }
     }
 
+    /// Clicking, or dragging, on the minimap scrolls to that part of the scrollback.
+    fn check_for_click(&self, message: &crate::run::Protocol) -> Result<()> {
+        let Some(hit) = self.tattoy.hit_test(message) else {
+            return Ok(());
+        };
+        // Full-screen apps don't have a scrollback to navigate.
+        if !self.is_shown() || self.tattoy.is_alternate_screen() {
+            return Ok(());
+        }
+        if !hit.is_left_click() || !hit.is_on_content {
+            return Ok(());
+        }
+
+        let tty_height_in_pixels = u32::from(self.tattoy.height) * 2;
+        let minimap_height = self.scrollback.dimensions().1;
+        let maybe_position = scroll_position_for_row(
+            hit.position.1,
+            usize::try_from(tty_height_in_pixels.saturating_sub(minimap_height))?,
+            usize::try_from(minimap_height)?,
+            self.tattoy.scrollback.surface.dimensions().1,
+            self.tattoy.screen.surface.dimensions().1,
+        );
+        if let Some(position) = maybe_position {
+            self.state
+                .protocol_tx
+                .send(crate::run::Protocol::ScrollTo(position))?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep up to date with the user's config. Returns whether the minimap needs rebuilding.
+    fn check_for_config(&mut self, message: &crate::run::Protocol) -> bool {
+        let crate::run::Protocol::Config { config, .. } = message else {
+            return false;
+        };
+
+        let is_layout_changed = self.config.width != config.minimap.width
+            || self.config.position != config.minimap.position;
+        self.config = config.minimap.clone();
+        if self.config.trigger == Trigger::Always {
+            self.show();
+        }
+        self.output_changed = true;
+        is_layout_changed
+    }
+
     /// Toggle the minimap bases on the user config keybinding event.
     fn check_for_keybind(&mut self, message: &crate::run::Protocol) {
         if let crate::run::Protocol::KeybindEvent(event) = &message {
@@ -220,7 +429,7 @@ impl Minimap {
 
     /// Tick the render
     async fn render(&mut self) -> Result<()> {
-        let Some(transition_state) = self.get_transition_state().await else {
+        let Some(transition_state) = self.get_transition_state() else {
             return Ok(());
         };
 
@@ -243,11 +452,25 @@ impl Minimap {
 
         let tty_height_in_pixels = u32::from(self.tattoy.height) * 2;
         let empty_height = tty_height_in_pixels - minimap_height;
+        let viewport = viewport_pixels(
+            self.tattoy.scrollback.surface.dimensions().1,
+            self.tattoy.screen.surface.dimensions().1,
+            self.tattoy.scrollback.position,
+            usize::try_from(minimap_height)?,
+        );
 
         for y in 0..tty_height_in_pixels {
-            for x_minimap in 0..(minimap_width - x_offset) {
-                let x_surface: usize =
-                    (u32::from(self.tattoy.width) - minimap_width + x_minimap).try_into()?;
+            for x_minimap in 0..minimap_width {
+                let Some(column) = surface_column(
+                    self.config.position,
+                    x_minimap,
+                    x_offset,
+                    minimap_width,
+                    u32::from(self.tattoy.width),
+                ) else {
+                    continue;
+                };
+                let x_surface = usize::try_from(column)?;
 
                 let screen_minimap_height = self.screen.dimensions().1;
                 let screen_minimap_offset = tty_height_in_pixels - screen_minimap_height;
@@ -257,7 +480,7 @@ impl Minimap {
                 if y < empty_height {
                     if y.rem_euclid(2) == 0 {
                         self.tattoy.surface.add_text(
-                            x_surface + usize::try_from(x_offset)?,
+                            x_surface,
                             y.div_euclid(2).try_into()?,
                             " ".to_owned(),
                             Some((0.2, 0.2, 0.2, 0.8)),
@@ -271,10 +494,17 @@ impl Minimap {
                     let mut pixel =
                         if y < screen_minimap_offset || !self.tattoy.is_alternate_screen() {
                             let y_image = y - empty_height;
-                            self.scrollback
+                            let mut scrollback_pixel = self
+                                .scrollback
                                 .get_pixel_checked(x_minimap, y_image)
                                 .context(format!("Couldn't get pixel: {x_minimap}x{y_image}"))?
-                                .0
+                                .0;
+                            if viewport.contains(&usize::try_from(y_image)?) {
+                                for channel in scrollback_pixel.iter_mut().take(3) {
+                                    *channel += (1.0 - *channel) * VIEWPORT_HIGHLIGHT;
+                                }
+                            }
+                            scrollback_pixel
 
                         // Draw the screen minimap.
                         } else {
@@ -285,14 +515,11 @@ impl Minimap {
                                 .0
                         };
 
-                    // TODO: make configurable
-                    pixel[3] = 0.95;
+                    pixel[3] = self.config.opacity;
 
-                    self.tattoy.surface.add_pixel(
-                        x_surface + usize::try_from(x_offset)?,
-                        y.try_into()?,
-                        pixel.into(),
-                    )?;
+                    self.tattoy
+                        .surface
+                        .add_pixel(x_surface, y.try_into()?, pixel.into())?;
                 }
             }
         }
@@ -305,8 +532,8 @@ impl Minimap {
 
     /// Get the transition state of the minimap animation. Therefore whether it's hidden, animating in,
     /// animating out, or just plain showing.
-    async fn get_transition_state(&mut self) -> Option<f32> {
-        let animation_speed = self.state.config.read().await.minimap.animation_speed;
+    fn get_transition_state(&mut self) -> Option<f32> {
+        let animation_speed = self.config.animation_speed;
 
         let animation_state = match self.animation_step {
             AnimationStep::Hidden => {
@@ -348,13 +575,10 @@ impl Minimap {
     async fn build_minimap(&mut self, kind: shadow_terminal::output::SurfaceKind) -> Result<()> {
         let image = self.tattoy.convert_pty_to_pixel_image(&kind)?;
 
-        let max_width = self.state.config.read().await.minimap.max_width;
+        // Short scrollbacks aren't stretched, long ones are squashed to fit the terminal.
+        let height = image.height().min(u32::from(self.tattoy.height) * 2);
         let minimap = image
-            .resize(
-                max_width.into(),
-                (self.tattoy.height * 2).into(),
-                image::imageops::Lanczos3,
-            )
+            .resize_exact(self.config.width.into(), height, image::imageops::Lanczos3)
             .to_rgba32f();
 
         match kind {
@@ -368,3 +592,44 @@ impl Minimap {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn placing_the_minimap_on_either_side() {
+        assert_eq!(minimap_columns(Side::Right, 10, 80), 70..80);
+        assert_eq!(minimap_columns(Side::Left, 10, 80), 0..10);
+
+        // Half slid out.
+        assert_eq!(surface_column(Side::Right, 0, 5, 10, 80), Some(75));
+        assert_eq!(surface_column(Side::Right, 5, 5, 10, 80), None);
+        assert_eq!(surface_column(Side::Left, 9, 5, 10, 80), Some(4));
+        assert_eq!(surface_column(Side::Left, 4, 5, 10, 80), None);
+    }
+
+    #[test]
+    fn viewport_indicator() {
+        // A 100 line scrollback, shown on a 10 row terminal, with a full height minimap.
+        assert_eq!(viewport_pixels(100, 10, 0, 20), 18..20);
+        assert_eq!(viewport_pixels(100, 10, 45, 20), 9..11);
+        assert_eq!(viewport_pixels(100, 10, 90, 20), 0..2);
+
+        // Only part of the screen is visible in a tiny minimap, but it's always at least a pixel.
+        assert_eq!(viewport_pixels(1000, 10, 500, 5), 2..3);
+        assert_eq!(viewport_pixels(0, 10, 0, 0), 0..0);
+    }
+
+    #[test]
+    fn clicking_the_minimap_scrolls_to_the_line_under_the_mouse() {
+        // A 100 line scrollback, shown on a 10 row terminal, with a full height minimap.
+        assert_eq!(scroll_position_for_row(0, 0, 20, 100, 10), Some(90));
+        assert_eq!(scroll_position_for_row(5, 0, 20, 100, 10), Some(45));
+        assert_eq!(scroll_position_for_row(9, 0, 20, 100, 10), Some(5));
+
+        // A short scrollback leaves the top of the minimap empty.
+        assert_eq!(scroll_position_for_row(2, 10, 10, 20, 10), Some(10));
+        assert_eq!(scroll_position_for_row(0, 0, 0, 20, 10), None);
+    }
+}
@@ -3,8 +3,20 @@
 use color_eyre::eyre::Result;
 use palette::Darken as _;
 
+/// The direction that notifications stack in, starting from the most urgent notification.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Stacking {
+    /// The most urgent notification is at the top.
+    #[default]
+    Down,
+    /// The most urgent notification is at the bottom.
+    Up,
+}
+
 /// User-configurable settings for the background command.
-#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the display of notifications
     pub enabled: bool,
@@ -14,6 +26,51 @@ pub(crate) struct Config {
     pub level: super::message::Level,
     /// The amount of time to display a notification
     pub duration: f32,
+    /// Override the global frame rate for the notification animations.
+    pub frame_rate: Option<u32>,
+    /// The corner of the terminal that the notifications are shown in.
+    pub corner: crate::surface::Corner,
+    /// The maximum width of a notification in columns. When not set notifications are as wide as
+    /// their longest line, up to the width of the terminal.
+    pub max_width: Option<usize>,
+    /// Whether lines that are too wide wrap onto the next line, otherwise they're truncated.
+    pub wrap: bool,
+    /// The direction that the notifications stack in.
+    pub stacking: Stacking,
+    /// The number of columns and rows between the notifications and the edges of the terminal.
+    pub margin: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            opacity: 0.9,
+            level: super::message::Level::Info,
+            duration: 5.0,
+            frame_rate: None,
+            corner: crate::surface::Corner::TopRight,
+            max_width: None,
+            wrap: true,
+            stacking: Stacking::Down,
+            margin: 0,
+        }
+    }
+}
+
+/// The number of columns around the text of a notification line.
+const PADDING: usize = 2;
+
+/// A single line of a notification, ready to be rendered.
+struct Line<'message> {
+    /// The notification that the line is part of.
+    message: &'message super::message::Message,
+    /// The text of the line.
+    text: String,
+    /// The width of the whole notification, not including padding.
+    width: usize,
+    /// Whether the line is part of the body, rather than the title.
+    is_body: bool,
 }
 
 /// `Notifications`
@@ -24,6 +81,65 @@ pub(crate) struct Notifications {
     messages: Vec<super::message::Message>,
     /// Text colour taken from the palette
     text_colour: termwiz::color::SrgbaTuple,
+    /// The keybinding for doing a notification's action, shown in the notification.
+    action_key: Option<String>,
+}
+
+/// The notifications that are shown for the given level, most urgent first.
+fn visible_messages<'messages>(
+    messages: &'messages [super::message::Message],
+    level: &super::message::Level,
+) -> Vec<&'messages super::message::Message> {
+    let mut visible = messages
+        .iter()
+        .filter(|message| &message.level <= level)
+        .collect::<Vec<&super::message::Message>>();
+    visible.sort_by(|left, right| left.level.cmp(&right.level));
+    visible
+}
+
+/// Fit a line of text into the given width, either by wrapping it onto more lines or by
+/// truncating it.
+fn fit_line(text: &str, width: usize, is_wrapping: bool) -> Vec<String> {
+    if width == 0 || crate::utils::display_width(text) <= width {
+        return vec![text.to_owned()];
+    }
+
+    if !is_wrapping {
+        let truncated = crate::utils::truncate_to_width(text, width.saturating_sub(1));
+        return vec![format!("{truncated}…")];
+    }
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split(' ') {
+        // Words that are longer than the whole width are split.
+        for chunk in crate::utils::split_to_width(word, width) {
+            let current_width = crate::utils::display_width(&current);
+            if current.is_empty() {
+                chunk.clone_into(&mut current);
+            } else if current_width + 1 + crate::utils::display_width(chunk) <= width {
+                current.push(' ');
+                current.push_str(chunk);
+            } else {
+                lines.push(core::mem::replace(&mut current, chunk.to_owned()));
+            }
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// The top-left position of a panel of the given size, placed in a corner of the terminal with a
+/// margin around it.
+const fn place(
+    corner: crate::surface::Corner,
+    margin: usize,
+    terminal: (usize, usize),
+    panel: (usize, usize),
+) -> (usize, usize) {
+    let (x, y) = corner.origin(terminal, (panel.0 + margin * 2, panel.1 + margin * 2));
+    (x + margin, y + margin)
 }
 
 impl Notifications {
@@ -35,7 +151,8 @@ impl Notifications {
     ) -> Result<Self> {
         crate::config::main::Config::load_palette(std::sync::Arc::clone(&state)).await?;
         let text_colour = palette.default_foreground_colour();
-        let opacity = state.config.read().await.notifications.opacity;
+        let opacity = state.config.load().notifications.opacity;
+        let action_key = Self::find_action_key(&*state.config.load());
         let tattoy = crate::tattoys::tattoyer::Tattoyer::new(
             "notifications".to_owned(),
             state,
@@ -49,9 +166,26 @@ impl Notifications {
             tattoy,
             messages: Vec::new(),
             text_colour,
+            action_key,
         })
     }
 
+    /// The keybinding for doing a notification's action, either the user's or the default.
+    fn find_action_key(config: &crate::config::main::Config) -> Option<String> {
+        let action = crate::config::input::KeybindingAction::NotificationAction;
+        if let Some(binding) = config.keybindings.get(&action) {
+            return Some(binding.to_string());
+        }
+
+        match crate::config::main::Config::parse_default_config() {
+            Ok(default) => default.keybindings.get(&action).map(ToString::to_string),
+            Err(error) => {
+                tracing::error!("Couldn't parse default config: {error:?}");
+                None
+            }
+        }
+    }
+
     /// Our main entrypoint.
     pub(crate) async fn start(
         output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
@@ -82,7 +216,7 @@ impl Notifications {
                     if matches!(result, Ok(crate::run::Protocol::End)) {
                         break;
                     }
-                    notifications.handle_protocol_message(result)?;
+                    notifications.handle_protocol_message(result).await?;
                 }
             }
         }
@@ -91,15 +225,28 @@ impl Notifications {
     }
 
     /// Handle messages from the main Tattoy app.
-    fn handle_protocol_message(
+    async fn handle_protocol_message(
         &mut self,
         result: std::result::Result<crate::run::Protocol, tokio::sync::broadcast::error::RecvError>,
     ) -> Result<()> {
         match result {
             Ok(message) => {
-                if let crate::run::Protocol::Notification(notification) = &message {
-                    tracing::debug!("Notification received: {notification:?}");
-                    self.messages.push(notification.clone());
+                #[expect(
+                    clippy::wildcard_enum_match_arm,
+                    reason = "We only react to a few messages"
+                )]
+                match &message {
+                    crate::run::Protocol::Notification(notification) => {
+                        tracing::debug!("Notification received: {notification:?}");
+                        self.messages.push(notification.clone());
+                    }
+                    crate::run::Protocol::Config { config, .. } => {
+                        self.action_key = Self::find_action_key(config);
+                    }
+                    crate::run::Protocol::KeybindEvent(action) => {
+                        self.handle_keybinding(action).await?;
+                    }
+                    _ => (),
                 }
                 self.tattoy.handle_common_protocol_messages(message)?;
             }
@@ -109,6 +256,61 @@ impl Notifications {
         Ok(())
     }
 
+    /// Dismiss notifications, or do their actions.
+    async fn handle_keybinding(
+        &mut self,
+        action: &crate::config::input::KeybindingAction,
+    ) -> Result<()> {
+        let level = self
+            .tattoy
+            .state
+            .config
+            .read()
+            .await
+            .notifications
+            .level
+            .clone();
+        let top = visible_messages(&self.messages, &level)
+            .first()
+            .map(|message| (message.id, message.action.clone()));
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Most keybindings are for other parts of Tattoy"
+        )]
+        match action {
+            crate::config::input::KeybindingAction::DismissNotification => {
+                let Some((id, _)) = top else {
+                    return Ok(());
+                };
+                self.messages.retain(|message| message.id != id);
+            }
+            crate::config::input::KeybindingAction::DismissAllNotifications => {
+                if self.messages.is_empty() {
+                    return Ok(());
+                }
+                self.messages.clear();
+            }
+            crate::config::input::KeybindingAction::NotificationAction => {
+                let Some((id, Some(notification_action))) = top else {
+                    return Ok(());
+                };
+                if let Err(error) = notification_action.run() {
+                    tracing::error!(
+                        "Couldn't {} from notification: {error:?}",
+                        notification_action.describe()
+                    );
+                }
+                self.messages.retain(|message| message.id != id);
+            }
+            _ => return Ok(()),
+        }
+
+        // The render loop stops once there are no messages, so make sure that dismissed
+        // notifications are cleared from the screen.
+        self.render().await
+    }
+
     /// Remove messages that have been around for longer than the duration set in config.
     fn remove_old_messages(&mut self, duration: f32) {
         self.messages.retain(|message| message.age() < duration);
@@ -118,68 +320,100 @@ impl Notifications {
     async fn render(&mut self) -> Result<()> {
         self.tattoy.initialise_surface();
 
-        let config = self.tattoy.state.config.read().await.notifications.clone();
+        let config = self.tattoy.state.config.load().notifications.clone();
         self.tattoy.opacity = config.opacity;
         let level = config.level.clone();
 
         self.remove_old_messages(config.duration);
 
         let all = self.messages.clone();
-        let mut messages = all
-            .iter()
-            .filter(|message| message.level <= level)
-            .collect::<Vec<&super::message::Message>>();
-        messages.sort_by(|left, right| left.level.cmp(&right.level));
-
-        let mut y = 0;
-        for message in &messages {
-            self.add_text(y, message, message.title.as_str(), config.duration, false);
-
-            if let Some(body) = &message.body {
-                for line in body.lines() {
-                    y += 1;
-                    self.add_text(y, message, line, config.duration, true);
-                }
-            }
-            y += 1;
+        let mut blocks = visible_messages(&all, &level)
+            .into_iter()
+            .map(|message| self.message_lines(message, &config))
+            .collect::<Vec<Vec<Line>>>();
+        if config.stacking == Stacking::Up {
+            blocks.reverse();
+        }
+        let lines = blocks.into_iter().flatten().collect::<Vec<Line>>();
+
+        let terminal = (
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+        );
+        for (row, line) in lines.iter().enumerate() {
+            let (x, y) = place(
+                config.corner,
+                config.margin,
+                terminal,
+                (line.width + PADDING, lines.len()),
+            );
+            self.add_line(x, y + row, line, config.duration);
         }
 
         self.tattoy.send_output().await
     }
 
-    /// Add a line of the notification to the Tattoy surface.
-    fn add_text(
-        &mut self,
-        y: usize,
-        message: &super::message::Message,
-        text: &str,
-        duration: f32,
-        is_body: bool,
-    ) {
-        let fade = message.fade_in_out(duration);
+    /// All the lines of a notification, fitted to the configured width.
+    fn message_lines<'message>(
+        &self,
+        message: &'message super::message::Message,
+        config: &Config,
+    ) -> Vec<Line<'message>> {
+        let hint = message.action_hint(self.action_key.as_deref());
+        let available = usize::from(self.tattoy.width).saturating_sub(PADDING + config.margin * 2);
+        let limit = config
+            .max_width
+            .map_or(available, |max_width| max_width.min(available));
+        let width = message.max_width(hint.as_deref()).min(limit);
+
+        let mut texts = fit_line(&message.title, width, config.wrap)
+            .into_iter()
+            .map(|text| (text, false))
+            .collect::<Vec<(String, bool)>>();
+        let body = message.body.iter().flat_map(|body| body.lines());
+        for text in body.chain(hint.as_deref()) {
+            texts.extend(
+                fit_line(text, width, config.wrap)
+                    .into_iter()
+                    .map(|fitted| (fitted, true)),
+            );
+        }
+
+        texts
+            .into_iter()
+            .map(|(text, is_body)| Line {
+                message,
+                text,
+                width,
+                is_body,
+            })
+            .collect()
+    }
+
+    /// Add a line of a notification to the Tattoy surface.
+    fn add_line(&mut self, x: usize, y: usize, line: &Line, duration: f32) {
+        let fade = line.message.fade_in_out(duration);
         let text_colour = (
             self.text_colour.0,
             self.text_colour.1,
             self.text_colour.2,
             fade,
         );
-        let mut background_colour = message.colour();
+        let mut background_colour = line.message.colour();
         background_colour.3 = fade;
-        if is_body {
+        if line.is_body {
             let darkenable: palette::Srgba<f32> = palette::rgb::Rgba::from(background_colour);
             background_colour = darkenable.darken(0.3).into();
         }
 
-        let padding = 2;
-        let tty_width = usize::from(self.tattoy.width);
-        let max_width = message.max_width().clamp(0, tty_width - padding);
-        let x = tty_width - max_width - padding;
-        let right_padding = max_width - text.len().clamp(0, max_width) + 1;
-
+        let right_padding = line
+            .width
+            .saturating_sub(crate::utils::display_width(&line.text))
+            + 1;
         self.tattoy.surface.add_text(
             x,
             y,
-            format!(" {text}{}", " ".repeat(right_padding)),
+            format!(" {}{}", line.text, " ".repeat(right_padding)),
             Some(background_colour),
             Some(text_colour),
         );
@@ -194,3 +428,57 @@ impl Notifications {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tattoys::notifications::message::{Level, Message};
+
+    #[test]
+    fn ordering_visible_messages() {
+        let messages = vec![
+            Message::new("info", Level::Info, None, None),
+            Message::new("error", Level::Error, None, None),
+            Message::new("debug", Level::Debug, None, None),
+            Message::new("warn", Level::Warn, None, None),
+        ];
+        let titles = visible_messages(&messages, &Level::Info)
+            .iter()
+            .map(|message| message.title.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, vec!["error", "warn", "info"]);
+    }
+
+    #[test]
+    fn fitting_lines() {
+        assert_eq!(fit_line("short", 10, true), vec!["short"]);
+        assert_eq!(
+            fit_line("a line that wraps", 8, true),
+            vec!["a line", "that", "wraps"]
+        );
+        assert_eq!(fit_line("abcdefghij", 4, true), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(fit_line("a line that is cut", 8, false), vec!["a line …"]);
+        assert_eq!(fit_line("日本語のテキスト", 7, false), vec!["日本語…"]);
+        assert_eq!(
+            fit_line("日本語 テキスト", 6, true),
+            vec!["日本語", "テキス", "ト"]
+        );
+    }
+
+    #[test]
+    fn placing_notifications() {
+        let terminal = (80, 24);
+        assert_eq!(
+            place(crate::surface::Corner::TopLeft, 1, terminal, (10, 3)),
+            (1, 1)
+        );
+        assert_eq!(
+            place(crate::surface::Corner::BottomRight, 1, terminal, (10, 3)),
+            (69, 20)
+        );
+        assert_eq!(
+            place(crate::surface::Corner::BottomLeft, 0, terminal, (10, 3)),
+            (0, 21)
+        );
+    }
+}
@@ -1,7 +1,18 @@
 //! A single notification message.
 
 /// The urgency level of the notification.
-#[derive(serde::Deserialize, Debug, Clone, Default, Ord, Eq, PartialEq, PartialOrd)]
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    Default,
+    Ord,
+    Eq,
+    PartialEq,
+    PartialOrd,
+)]
 #[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub(crate) enum Level {
@@ -18,8 +29,48 @@ pub(crate) enum Level {
     Trace,
 }
 
+/// The ID of the next notification to be created.
+static NEXT_ID: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+/// The command used to open files with the system's default application.
+#[cfg(target_os = "macos")]
+const FILE_OPENER: &str = "open";
+/// The command used to open files with the system's default application.
+#[cfg(not(target_os = "macos"))]
+const FILE_OPENER: &str = "xdg-open";
+
+/// Something that can be done from a notification, with the `notification_action` keybinding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Open a file with the system's default application, eg the log file.
+    OpenFile(std::path::PathBuf),
+}
+
+impl Action {
+    /// A short description of the action.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::OpenFile(path) => format!("open {}", path.display()),
+        }
+    }
+
+    /// Do the action.
+    pub fn run(&self) -> std::io::Result<()> {
+        match self {
+            Self::OpenFile(path) => {
+                tokio::process::Command::new(FILE_OPENER)
+                    .arg(path)
+                    .spawn()?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Message {
+    /// A unique ID, so that a notification can be dismissed.
+    pub id: usize,
     /// The text of the notification.
     pub title: String,
     /// An optional body for the notification
@@ -28,18 +79,31 @@ pub(crate) struct Message {
     timestamp: tokio::time::Instant,
     /// The leve of the notification.
     pub level: Level,
+    /// Something the user can do from the notification.
+    pub action: Option<Action>,
 }
 
 impl Message {
-    /// Create a new notification
-    pub fn make(text: &str, level: Level, body: Option<String>) -> crate::run::Protocol {
-        let message = Self {
+    /// Instantiate
+    pub fn new(text: &str, level: Level, body: Option<String>, action: Option<Action>) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, core::sync::atomic::Ordering::Relaxed),
             title: text.into(),
             body,
             timestamp: tokio::time::Instant::now(),
             level,
-        };
-        crate::run::Protocol::Notification(message)
+            action,
+        }
+    }
+
+    /// Create a new notification
+    pub fn make(
+        text: &str,
+        level: Level,
+        body: Option<String>,
+        action: Option<Action>,
+    ) -> crate::run::Protocol {
+        crate::run::Protocol::Notification(Self::new(text, level, body, action))
     }
 
     // TODO: Find the colours in the current palette that most closely resemble these.
@@ -73,16 +137,49 @@ impl Message {
         }
     }
 
+    /// The line describing the notification's action, if it has one, eg `ALT+A: open tattoy.log`.
+    pub fn action_hint(&self, key: Option<&str>) -> Option<String> {
+        let action = self.action.as_ref()?;
+        Some(format!("{}: {}", key?, action.describe()))
+    }
+
     /// Calculate the widest part of the message.
-    pub fn max_width(&self) -> usize {
-        let mut width = self.title.len();
+    pub fn max_width(&self, action_hint: Option<&str>) -> usize {
+        let mut width = crate::utils::display_width(&self.title);
         if let Some(body) = &self.body {
             for line in body.lines() {
-                if line.len() > width {
-                    width = line.len();
-                }
+                width = width.max(crate::utils::display_width(line));
             }
         }
+        if let Some(hint) = action_hint {
+            width = width.max(crate::utils::display_width(hint));
+        }
         width
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unique_ids() {
+        let first = Message::new("first", Level::Info, None, None);
+        let second = Message::new("second", Level::Info, None, None);
+        assert_ne!(first.id, second.id);
+    }
+
+    #[test]
+    fn action_hints() {
+        let action = Action::OpenFile("/tmp/tattoy.log".into());
+        let message = Message::new("error", Level::Error, None, Some(action));
+        assert_eq!(
+            message.action_hint(Some("ALT+A")),
+            Some("ALT+A: open /tmp/tattoy.log".to_owned())
+        );
+        assert_eq!(message.action_hint(None), None);
+
+        let no_action = Message::new("error", Level::Error, None, None);
+        assert_eq!(no_action.action_hint(Some("ALT+A")), None);
+    }
+}
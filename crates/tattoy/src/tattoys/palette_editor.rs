@@ -0,0 +1,367 @@
+//! An interactive overlay for tweaking the true colours of the 16 ANSI colours of the terminal's
+//! palette. Changes are previewed live and can then be saved back to `palette.toml`.
+
+use color_eyre::eyre::Result;
+
+/// The compositing layer of the editor. It should be above everything else.
+const LAYER: i16 = 300;
+
+/// The width of the editor in columns.
+const PANEL_WIDTH: usize = 52;
+
+/// Background colour of the editor.
+const BACKGROUND: crate::surface::Colour = (0.05, 0.05, 0.1, 0.95);
+
+/// Background colour of the currently selected palette colour.
+const SELECTED: crate::surface::Colour = (0.0, 0.2, 0.63, 1.0);
+
+/// Help text displayed at the bottom of the editor.
+const HELP: &str = "↑↓ select  ←→ hue  -+ lightness  ⏎ save  esc close";
+
+/// The number of palette colours that can be edited.
+const EDITABLE_COLOURS: u8 = 16;
+
+/// How many degrees to change the hue by with each key press.
+const HUE_STEP: f64 = 5.0;
+
+/// How much to change the lightness by with each key press.
+const LIGHTNESS_STEP: f64 = 0.02;
+
+/// The blank space used to show a sample of each colour.
+const SWATCH: &str = "      ";
+
+/// The column, relative to the left of the editor, where the swatch starts.
+const SWATCH_OFFSET: usize = 21;
+
+/// The conventional names of the 16 ANSI colours.
+const COLOUR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright black",
+    "bright red",
+    "bright green",
+    "bright yellow",
+    "bright blue",
+    "bright magenta",
+    "bright cyan",
+    "bright white",
+];
+
+/// `PaletteEditor`
+pub(crate) struct PaletteEditor {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Is the editor currently visible?
+    is_open: bool,
+    /// The palette index of the currently selected colour.
+    selected: u8,
+    /// The palette with any unsaved changes.
+    palette: crate::palette::converter::Palette,
+    /// The palette as it was last saved, so that unsaved changes can be discarded.
+    saved: crate::palette::converter::Palette,
+}
+
+impl PaletteEditor {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "palette_editor".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            is_open: false,
+            selected: 0,
+            palette: palette.clone(),
+            saved: palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut editor = Self::new(output, state, palette).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = protocol.recv() => {
+                    if matches!(result, Ok(crate::run::Protocol::End)) {
+                        break;
+                    }
+                    editor.handle_protocol_message(result).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(
+        &mut self,
+        result: std::result::Result<crate::run::Protocol, tokio::sync::broadcast::error::RecvError>,
+    ) -> Result<()> {
+        match result {
+            Ok(message) => {
+                let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+
+                #[expect(
+                    clippy::wildcard_enum_match_arm,
+                    reason = "We only react to a few messages"
+                )]
+                match &message {
+                    crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::TogglePaletteEditor,
+                    ) => {
+                        self.toggle().await?;
+                    }
+                    crate::run::Protocol::Input(input) if self.is_open => {
+                        if let termwiz::input::InputEvent::Key(key_event) = &input.event {
+                            self.handle_key(key_event).await?;
+                        }
+                    }
+                    // Whilst the editor is open, palette updates are our own previews.
+                    crate::run::Protocol::Palette(palette) if !self.is_open => {
+                        self.palette = palette.clone();
+                        self.saved = palette.clone();
+                    }
+                    _ => (),
+                }
+
+                self.tattoy.handle_common_protocol_messages(message)?;
+                if is_resize && self.is_open {
+                    self.render().await?;
+                }
+            }
+            Err(error) => tracing::error!("Receiving protocol message: {error:?}"),
+        }
+
+        Ok(())
+    }
+
+    /// Open or close the editor. Closing the editor discards any unsaved changes.
+    async fn toggle(&mut self) -> Result<()> {
+        self.is_open = !self.is_open;
+        *self.tattoy.state.is_palette_editor_open.write().await = self.is_open;
+        tracing::debug!("Palette editor open: {}", self.is_open);
+
+        if self.is_open {
+            return self.render().await;
+        }
+
+        if self.palette.map != self.saved.map {
+            tracing::debug!("Discarding unsaved palette changes");
+            self.palette = self.saved.clone();
+            self.preview().await?;
+        }
+        self.tattoy.send_blank_output().await
+    }
+
+    /// Handle key presses whilst the editor is open.
+    async fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Result<()> {
+        let last = EDITABLE_COLOURS - 1;
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few keys"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::UpArrow => {
+                self.selected = if self.selected == 0 {
+                    last
+                } else {
+                    self.selected - 1
+                };
+            }
+            termwiz::input::KeyCode::DownArrow => {
+                self.selected = if self.selected == last {
+                    0
+                } else {
+                    self.selected + 1
+                };
+            }
+            termwiz::input::KeyCode::LeftArrow => self.nudge(-HUE_STEP, 0.0).await?,
+            termwiz::input::KeyCode::RightArrow => self.nudge(HUE_STEP, 0.0).await?,
+            termwiz::input::KeyCode::Char('-') => self.nudge(0.0, -LIGHTNESS_STEP).await?,
+            termwiz::input::KeyCode::Char('+' | '=') => self.nudge(0.0, LIGHTNESS_STEP).await?,
+            termwiz::input::KeyCode::Enter => self.save().await?,
+            termwiz::input::KeyCode::Escape => {
+                self.toggle().await?;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        }
+
+        self.render().await
+    }
+
+    /// Change the hue and/or lightness of the currently selected colour.
+    async fn nudge(&mut self, hue: f64, lightness: f64) -> Result<()> {
+        let key = self.selected.to_string();
+        let Some(colour) = self.palette.map.get(&key).copied() else {
+            return Ok(());
+        };
+
+        let nudged = Self::nudge_colour(colour, hue, lightness);
+        tracing::trace!("Palette editor changing colour {key} from {colour:?} to {nudged:?}");
+        self.palette.map.insert(key, nudged);
+        self.preview().await
+    }
+
+    /// Change the hue and lightness of a palette colour.
+    fn nudge_colour(
+        colour: crate::palette::converter::PaletteColour,
+        hue: f64,
+        lightness: f64,
+    ) -> crate::palette::converter::PaletteColour {
+        let true_colour: termwiz::color::SrgbaTuple =
+            termwiz::color::RgbColor::new_8bpc(colour.0, colour.1, colour.2).into();
+        let (red, green, blue, _) = true_colour
+            .adjust_hue_fixed(hue)
+            .lighten_fixed(lightness)
+            .to_srgb_u8();
+        (red, green, blue)
+    }
+
+    /// Show the changes everywhere else in Tattoy.
+    async fn preview(&self) -> Result<()> {
+        crate::config::main::Config::broadcast_palette(&self.tattoy.state, self.palette.clone())
+            .await
+    }
+
+    /// Save the edited palette to the palette config file.
+    async fn save(&mut self) -> Result<()> {
+        let path = crate::palette::parser::Parser::palette_config_path(&self.tattoy.state).await;
+        tokio::fs::write(&path, toml::to_string(&self.palette.map)?).await?;
+        self.saved = self.palette.clone();
+        tracing::info!("Palette editor saved palette to: {path:?}");
+
+        self.tattoy
+            .state
+            .send_notification(
+                "Palette saved",
+                crate::tattoys::notifications::message::Level::Info,
+                Some(path.display().to_string()),
+                false,
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Convert a palette colour to a colour that can be drawn on a surface.
+    fn surface_colour(colour: crate::palette::converter::PaletteColour) -> crate::surface::Colour {
+        (
+            f32::from(colour.0) / 255.0,
+            f32::from(colour.1) / 255.0,
+            f32::from(colour.2) / 255.0,
+            1.0,
+        )
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        let panel_height = usize::from(EDITABLE_COLOURS) + 4;
+        if width < PANEL_WIDTH || height < panel_height {
+            tracing::debug!("Terminal too small for the palette editor");
+            return self.tattoy.send_output().await;
+        }
+
+        let left = (width - PANEL_WIDTH).div_euclid(2);
+        let top = (height - panel_height).div_euclid(2);
+
+        let is_unsaved = self.palette.map != self.saved.map;
+        let title = if is_unsaved {
+            " Tattoy palette (unsaved)"
+        } else {
+            " Tattoy palette"
+        };
+        self.add_line(left, top, title, BACKGROUND);
+        self.add_line(left, top + 1, "", BACKGROUND);
+        for index in 0..EDITABLE_COLOURS {
+            let y = top + 2 + usize::from(index);
+            let name = COLOUR_NAMES.get(usize::from(index)).unwrap_or(&"");
+            let colour = self
+                .palette
+                .map
+                .get(&index.to_string())
+                .copied()
+                .unwrap_or_default();
+            let line = format!(
+                "  {index:>2} {name:<16}{SWATCH}  #{:02x}{:02x}{:02x}",
+                colour.0, colour.1, colour.2
+            );
+            let background = if index == self.selected {
+                SELECTED
+            } else {
+                BACKGROUND
+            };
+            self.add_line(left, y, &line, background);
+            self.tattoy.surface.add_text(
+                left + SWATCH_OFFSET,
+                y,
+                SWATCH.to_owned(),
+                Some(Self::surface_colour(colour)),
+                None,
+            );
+        }
+        self.add_line(left, top + panel_height - 2, "", BACKGROUND);
+        self.add_line(left, top + panel_height - 1, &format!(" {HELP}"), BACKGROUND);
+
+        self.tattoy.send_output().await
+    }
+
+    /// Add a full-width line of the editor.
+    fn add_line(&mut self, x: usize, y: usize, text: &str, background: crate::surface::Colour) {
+        let padding = PANEL_WIDTH.saturating_sub(text.chars().count());
+        self.tattoy.surface.add_text(
+            x,
+            y,
+            format!("{text}{}", " ".repeat(padding)),
+            Some(background),
+            Some(crate::surface::WHITE),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nudging_colours() {
+        let red = (255, 0, 0);
+        assert_eq!(PaletteEditor::nudge_colour(red, 0.0, 0.0), red);
+        assert_ne!(PaletteEditor::nudge_colour(red, HUE_STEP, 0.0), red);
+
+        let lighter = PaletteEditor::nudge_colour(red, 0.0, LIGHTNESS_STEP);
+        assert_eq!(lighter.0, 255);
+        assert!(lighter.1 > 0 && lighter.2 > 0);
+    }
+}
@@ -0,0 +1,252 @@
+//! A small heads-up display of Tattoy's own performance: frame rate, frame times, the renderer's
+//! backlog and how often each tattoy is updating. The same stats are available as JSON with
+//! `tattoy ctl stats`.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the performance HUD.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the performance HUD. When enabled it can be shown and hidden with the
+    /// `toggle_performance_hud` keybinding.
+    pub enabled: bool,
+    /// Whether to show the HUD as soon as Tattoy starts.
+    pub show_on_start: bool,
+    /// Which corner of the terminal to show the HUD in.
+    pub position: crate::surface::Corner,
+    /// The transparency of the HUD.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            show_on_start: false,
+            position: crate::surface::Corner::TopLeft,
+            opacity: 0.9,
+        }
+    }
+}
+
+/// The compositing layer of the HUD.
+const LAYER: i16 = 150;
+
+/// How often the HUD is refreshed, in seconds.
+const REFRESH_INTERVAL: f32 = 0.5;
+
+/// The width of the labels, so that the values line up.
+const LABEL_WIDTH: usize = 10;
+
+/// All the lines of the HUD. The busiest tattoys are listed first.
+fn lines(snapshot: &crate::performance::Snapshot) -> Vec<String> {
+    let timing = |timing: crate::performance::Timing| {
+        format!("{:.1}ms (max {:.1})", timing.average_ms, timing.max_ms)
+    };
+    let mut lines = vec![
+        format!("{:LABEL_WIDTH$}{:.0}", "fps", snapshot.frames_per_second),
+        format!("{:LABEL_WIDTH$}{}", "frame", timing(snapshot.frame_time)),
+        format!(
+            "{:LABEL_WIDTH$}{}",
+            "composite",
+            timing(snapshot.composite_time)
+        ),
+        format!(
+            "{:LABEL_WIDTH$}{} (max {})",
+            "backlog", snapshot.backlog, snapshot.max_backlog
+        ),
+        format!("{:LABEL_WIDTH$}{}", "skipped", snapshot.skipped_frames),
+    ];
+
+    let mut updates: Vec<(&String, &f32)> = snapshot.updates_per_second.iter().collect();
+    updates.sort_by(|left, right| right.1.total_cmp(left.1).then(left.0.cmp(right.0)));
+    for (id, rate) in updates {
+        lines.push(format!("{id:LABEL_WIDTH$}{rate:.0}/s"));
+    }
+    lines
+}
+
+/// `PerformanceHud`
+pub(crate) struct PerformanceHud {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The HUD's config
+    config: Config,
+    /// Is the HUD currently visible?
+    is_visible: bool,
+    /// The palette, used to style the HUD.
+    palette: crate::palette::converter::Palette,
+}
+
+impl PerformanceHud {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().performance_hud.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "performance_hud".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            is_visible: config.show_on_start,
+            config,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut hud = Self::new(output, state, palette).await;
+        let mut refresh =
+            tokio::time::interval(std::time::Duration::from_secs_f32(REFRESH_INTERVAL));
+        refresh.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = refresh.tick(), if hud.is_visible => {
+                    hud.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = hud
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::PerformanceHud,
+                            |config| config.performance_hud.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    hud.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::TogglePerformanceHud,
+            ) => {
+                self.is_visible = !self.is_visible;
+                tracing::debug!("Performance HUD visible: {}", self.is_visible);
+                if !self.is_visible {
+                    self.tattoy.send_blank_output().await?;
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.performance_hud.clone();
+                self.tattoy.opacity = self.config.opacity;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_resize && self.is_visible {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let snapshot = self.tattoy.state.performance.read().await.snapshot();
+        let lines = lines(&snapshot);
+        let panel_width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+            .saturating_add(2);
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        if width < panel_width || height < lines.len() {
+            tracing::debug!("Terminal too small for the performance HUD");
+            return self.tattoy.send_output().await;
+        }
+
+        let background =
+            crate::surface::colour_from_srgba(self.palette.default_background_colour());
+        let foreground =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+        let (left, top) = self
+            .config
+            .position
+            .origin((width, height), (panel_width, lines.len()));
+        for (y, line) in (top..).zip(lines) {
+            let text = format!(" {line:panel_width$}");
+            let text = text.chars().take(panel_width).collect::<String>();
+            self.tattoy
+                .surface
+                .add_text(left, y, text, Some(background), Some(foreground));
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hud_lines() {
+        let mut snapshot = crate::performance::Snapshot {
+            frames_per_second: 29.6,
+            backlog: 1,
+            max_backlog: 4,
+            ..Default::default()
+        };
+        snapshot
+            .updates_per_second
+            .insert("minimap".to_owned(), 2.0);
+        snapshot
+            .updates_per_second
+            .insert("shader".to_owned(), 30.0);
+
+        let lines = lines(&snapshot);
+        assert_eq!(lines[0], "fps       30");
+        assert_eq!(lines[1], "frame     0.0ms (max 0.0)");
+        assert_eq!(lines[3], "backlog   1 (max 4)");
+        assert_eq!(lines[5], "shader    30/s");
+        assert_eq!(lines[6], "minimap   2/s");
+    }
+}
@@ -11,18 +11,29 @@ const DEFAULT_LAYER: i16 = -10;
 const DEFAULT_OPACITY: f32 = 1.0;
 
 /// User-configurable settings for the minimap
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 pub struct Config {
     /// The name of the plugin. Can be any string.
-    name: String,
-    /// The path to the plugin executable.
-    path: std::path::PathBuf,
+    pub name: String,
+    /// The path to the plugin executable. Relative paths are looked for in the config directory
+    /// and then in the data directory, eg `plugins/my-plugin`.
+    pub path: std::path::PathBuf,
     /// The layer upon which the plugin is rendered.
     layer: Option<i16>,
     /// The transparency of the plugin output.
     opacity: Option<f32>,
     /// Whether the plugin is enabled.
     pub enabled: Option<bool>,
+    /// Whether the plugin is sent the contents of the PTY whenever it changes. Defaults to `true`.
+    /// Plugins that don't need the PTY can turn this off to save Tattoy from serialising every
+    /// frame.
+    pty_updates: Option<bool>,
+    /// Whether the plugin is sent the scroll position whenever the user scrolls. Defaults to
+    /// `false`, because plugins built against older versions of the protocol don't know about the
+    /// `scroll` message.
+    scroll_updates: Option<bool>,
+    /// Settings for the plugin itself. They're passed straight through to the plugin.
+    config: Option<toml::Table>,
 }
 
 /// Plugins
@@ -37,6 +48,16 @@ pub struct Plugin {
     plugin_stdin: std::io::BufWriter<std::process::ChildStdin>,
     /// Output stream from spawned plugin process.
     parsed_messages_rx: tokio::sync::mpsc::Receiver<tattoy_protocol::PluginOutputMessages>,
+    /// Whether the plugin wants the contents of the PTY.
+    is_subscribed_to_pty: bool,
+    /// The cells of the last PTY update, kept so that its allocation can be reused.
+    pty_cells: Vec<tattoy_protocol::Cell>,
+    /// The JSON of the last message sent to the plugin, kept so that its allocation can be reused.
+    json: Vec<u8>,
+    /// Whether the plugin wants the scroll position.
+    is_subscribed_to_scroll: bool,
+    /// The last scroll position sent to the plugin.
+    scroll_position: usize,
 }
 
 impl Plugin {
@@ -58,12 +79,15 @@ impl Plugin {
         .await;
         let (parsed_messages_tx, parsed_messages_rx) = tokio::sync::mpsc::channel(16);
 
+        let mut resolved_config = config.clone();
+        resolved_config.path =
+            crate::config::main::Config::resolve_path(&state, &config.path).await;
         tracing::debug!(
             "Spawing plugin, '{}', with: {}",
             config.name,
-            config.path.display()
+            resolved_config.path.display()
         );
-        let result = Self::spawn(config.clone(), listener_rx, parsed_messages_tx, state);
+        let result = Self::spawn(resolved_config, listener_rx, parsed_messages_tx, state);
         match result {
             Ok(mut child) => {
                 let stdin = child
@@ -78,6 +102,11 @@ impl Plugin {
                     child,
                     plugin_stdin: stdin_writer,
                     parsed_messages_rx,
+                    is_subscribed_to_pty: config.pty_updates.unwrap_or(true),
+                    pty_cells: Vec::new(),
+                    json: Vec::new(),
+                    is_subscribed_to_scroll: config.scroll_updates.unwrap_or(false),
+                    scroll_position: 0,
                 })
             }
             Err(error) => {
@@ -122,6 +151,12 @@ impl Plugin {
                 color_eyre::eyre::bail!(message);
             }
         };
+        if let Some(plugin_config) = &config.config {
+            plugin.send_config(plugin_config)?;
+        }
+        if state.config.load().accessibility.reduce_motion {
+            plugin.send_accessibility(true)?;
+        }
 
         #[expect(
             clippy::integer_division_remainder_used,
@@ -136,17 +171,23 @@ impl Plugin {
                     }
                 },
                 Ok(message) = tattoy_protocol_receiver.recv() => {
-                    if matches!(message, crate::run::Protocol::End) {
+                    let is_disabled = Self::is_disabled_by(&message, &config.name);
+                    if matches!(message, crate::run::Protocol::End) || is_disabled {
                         plugin.child.kill()?;
-                        let result = listener_tx.send(message);
+                        let result = listener_tx.send(crate::run::Protocol::End);
                         if let Err(error) = result {
                             tracing::error!("Couldn't send End message to listener: {error:?}");
                         }
                         tracing::info!("Sent kill to plugin process and our plugin listener.");
+                        if is_disabled {
+                            tracing::info!("'{}' plugin disabled by config update", config.name);
+                            plugin.tattoy.send_blank_output().await?;
+                        }
                         break;
                     }
                     plugin.handle_protocol_messages(&message)?;
                     plugin.tattoy.handle_common_protocol_messages(message)?;
+                    plugin.send_scroll_position()?;
                 }
             }
         }
@@ -156,6 +197,17 @@ impl Plugin {
         Ok(())
     }
 
+    /// Whether a config update has disabled, or removed, the plugin with the given name.
+    fn is_disabled_by(message: &crate::run::Protocol, name: &str) -> bool {
+        let crate::run::Protocol::Config { config, .. } = message else {
+            return false;
+        };
+        !config
+            .plugins
+            .iter()
+            .any(|plugin| plugin.name == name && plugin.enabled != Some(false))
+    }
+
     /// Handle Tattoy protocol messages.
     fn handle_protocol_messages(&mut self, message: &crate::run::Protocol) -> Result<()> {
         #[expect(
@@ -167,6 +219,14 @@ impl Plugin {
                 self.send_tty_size(*width, *height)?;
             }
             crate::run::Protocol::Output(_) => self.send_pty_output()?,
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
+            crate::run::Protocol::Config { config, changes } => {
+                if changes.contains(&crate::config::diff::Section::Accessibility) {
+                    self.send_accessibility(config.accessibility.reduce_motion)?;
+                }
+            }
 
             _ => (),
         }
@@ -189,9 +249,65 @@ impl Plugin {
         Ok(())
     }
 
+    /// Send the plugin its own settings from the user's config.
+    fn send_config(&mut self, config: &toml::Table) -> Result<()> {
+        let json = serde_json::to_string(&tattoy_protocol::PluginInputMessages::Config(
+            serde_json::to_value(config)?,
+        ))?;
+
+        tracing::trace!("Sending JSON to plugin: {json}");
+        self.plugin_stdin.write_all(json.as_bytes())?;
+        self.plugin_stdin.write_all(b"\n")?;
+        self.plugin_stdin.flush()?;
+
+        Ok(())
+    }
+
+    /// Send the user's accessibility preferences to the plugin.
+    fn send_accessibility(&mut self, reduce_motion: bool) -> Result<()> {
+        let json = serde_json::to_string(&tattoy_protocol::PluginInputMessages::Accessibility {
+            reduce_motion,
+        })?;
+
+        tracing::trace!("Sending JSON to plugin: {json}");
+        self.plugin_stdin.write_all(json.as_bytes())?;
+        self.plugin_stdin.write_all(b"\n")?;
+        self.plugin_stdin.flush()?;
+
+        Ok(())
+    }
+
+    /// Send the scroll position to the plugin, if it's changed.
+    fn send_scroll_position(&mut self) -> Result<()> {
+        if !self.is_subscribed_to_scroll {
+            return Ok(());
+        }
+
+        let position = self.tattoy.scrollback.position;
+        if position == self.scroll_position {
+            return Ok(());
+        }
+        self.scroll_position = position;
+
+        let json =
+            serde_json::to_string(&tattoy_protocol::PluginInputMessages::Scroll { position })?;
+
+        tracing::trace!("Sending JSON to plugin: {json}");
+        self.plugin_stdin.write_all(json.as_bytes())?;
+        self.plugin_stdin.write_all(b"\n")?;
+        self.plugin_stdin.flush()?;
+
+        Ok(())
+    }
+
     /// Send Tattoy's PTY output to the plugin.
     fn send_pty_output(&mut self) -> Result<()> {
-        let mut cells = Vec::<tattoy_protocol::Cell>::new();
+        if !self.is_subscribed_to_pty {
+            return Ok(());
+        }
+
+        let mut cells = core::mem::take(&mut self.pty_cells);
+        cells.clear();
         for (y, line) in self.tattoy.screen.surface.screen_cells().iter().enumerate() {
             for (x, cell) in line.iter().enumerate() {
                 let character = cell.str();
@@ -231,14 +347,24 @@ impl Plugin {
         }
 
         let cursor_position = self.tattoy.screen.surface.cursor_position();
-        let json = serde_json::to_string(&tattoy_protocol::PluginInputMessages::PTYUpdate {
+        let message = tattoy_protocol::PluginInputMessages::PTYUpdate {
             size: (self.tattoy.width, self.tattoy.height),
             cells,
             cursor: (cursor_position.0.try_into()?, cursor_position.1.try_into()?),
-        })?;
-        tracing::trace!("Sending JSON to plugin: {json}");
-        self.plugin_stdin.write_all(json.as_bytes())?;
-        self.plugin_stdin.write_all(b"\n")?;
+        };
+        self.json.clear();
+        let result = serde_json::to_writer(&mut self.json, &message);
+        if let tattoy_protocol::PluginInputMessages::PTYUpdate { cells, .. } = message {
+            self.pty_cells = cells;
+        }
+        result?;
+
+        tracing::trace!(
+            "Sending JSON to plugin: {}",
+            String::from_utf8_lossy(&self.json)
+        );
+        self.json.push(b'\n');
+        self.plugin_stdin.write_all(&self.json)?;
         self.plugin_stdin.flush()?;
 
         Ok(())
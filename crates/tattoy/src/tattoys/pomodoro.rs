@@ -0,0 +1,440 @@
+//! A pomodoro timer. Alternates between intervals of work and short breaks. During a break the
+//! whole terminal is tinted, as a gentle reminder to step away from it.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the pomodoro timer.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the pomodoro timer.
+    pub enabled: bool,
+    /// The length of a work interval, in minutes.
+    pub work_minutes: f32,
+    /// The length of a break, in minutes.
+    pub break_minutes: f32,
+    /// Which corner of the terminal to show the timer in.
+    pub position: crate::surface::Corner,
+    /// The transparency of the timer.
+    pub opacity: f32,
+    /// The colour that the terminal is tinted during breaks, as RGBA values between 0.0 and 1.0.
+    pub break_tint: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            work_minutes: 25.0,
+            break_minutes: 5.0,
+            position: crate::surface::Corner::BottomLeft,
+            opacity: 0.9,
+            break_tint: (0.2, 0.6, 0.3, 0.15),
+        }
+    }
+}
+
+/// The compositing layer of the timer. The break tint covers the whole screen, so should be above
+/// most other tattoys.
+const LAYER: i16 = 160;
+
+/// The width of the progress bar in columns.
+const BAR_WIDTH: usize = 10;
+
+/// The palette index of the progress bar's colour during work.
+const WORK_PALETTE_INDEX: u8 = 1;
+
+/// The palette index of the progress bar's colour during breaks.
+const BREAK_PALETTE_INDEX: u8 = 2;
+
+/// The 2 phases of a pomodoro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Time to focus.
+    Work,
+    /// Time to rest.
+    Break,
+}
+
+/// The state of the timer, independent of any rendering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Timer {
+    /// The current phase.
+    phase: Phase,
+    /// How much of the current phase was left when the timer was last paused.
+    remaining: std::time::Duration,
+    /// When the timer was last started, if it's running.
+    maybe_started_at: Option<std::time::Instant>,
+}
+
+impl Timer {
+    /// A new, paused, work interval.
+    fn new(config: &Config) -> Self {
+        Self {
+            phase: Phase::Work,
+            remaining: Self::phase_length(config, Phase::Work),
+            maybe_started_at: None,
+        }
+    }
+
+    /// The configured length of a phase.
+    fn phase_length(config: &Config, phase: Phase) -> std::time::Duration {
+        let minutes = match phase {
+            Phase::Work => config.work_minutes,
+            Phase::Break => config.break_minutes,
+        };
+        std::time::Duration::from_secs_f32(minutes.max(0.0) * 60.0)
+    }
+
+    /// Is the timer counting down?
+    const fn is_running(&self) -> bool {
+        self.maybe_started_at.is_some()
+    }
+
+    /// How much of the current phase is left.
+    fn remaining(&self, now: std::time::Instant) -> std::time::Duration {
+        match self.maybe_started_at {
+            Some(started_at) => self
+                .remaining
+                .saturating_sub(now.saturating_duration_since(started_at)),
+            None => self.remaining,
+        }
+    }
+
+    /// How much of the current phase has passed, between 0.0 and 1.0.
+    fn progress(&self, config: &Config, now: std::time::Instant) -> f32 {
+        let length = Self::phase_length(config, self.phase).as_secs_f32();
+        if length <= 0.0 {
+            return 1.0;
+        }
+        (1.0 - self.remaining(now).as_secs_f32() / length).clamp(0.0, 1.0)
+    }
+
+    /// Start or pause the timer.
+    fn toggle(&mut self, now: std::time::Instant) {
+        if self.is_running() {
+            self.remaining = self.remaining(now);
+            self.maybe_started_at = None;
+        } else {
+            self.maybe_started_at = Some(now);
+        }
+    }
+
+    /// Check if the current phase has ended. If it has, move on to the next phase and return it.
+    /// Breaks start automatically, but the next work interval waits for the user to start it.
+    fn tick(&mut self, config: &Config, now: std::time::Instant) -> Option<Phase> {
+        if !self.is_running() || !self.remaining(now).is_zero() {
+            return None;
+        }
+
+        let (next, maybe_started_at) = match self.phase {
+            Phase::Work => (Phase::Break, Some(now)),
+            Phase::Break => (Phase::Work, None),
+        };
+        self.phase = next;
+        self.remaining = Self::phase_length(config, next);
+        self.maybe_started_at = maybe_started_at;
+        Some(next)
+    }
+}
+
+/// `Pomodoro`
+pub(crate) struct Pomodoro {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The pomodoro's config
+    config: Config,
+    /// The timer
+    timer: Timer,
+    /// The palette, used to style the timer.
+    palette: crate::palette::converter::Palette,
+}
+
+impl Pomodoro {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().pomodoro.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "pomodoro".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            timer: Timer::new(&config),
+            config,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut pomodoro = Self::new(output, state, palette).await;
+        let mut tick = tokio::time::interval(std::time::Duration::from_secs(1));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = tick.tick(), if pomodoro.timer.is_running() => {
+                    pomodoro.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = pomodoro
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::Pomodoro,
+                            |config| config.pomodoro.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    pomodoro.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::PomodoroStartPause,
+            ) => {
+                self.timer.toggle(std::time::Instant::now());
+                tracing::debug!("Pomodoro running: {}", self.timer.is_running());
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::PomodoroReset,
+            ) => {
+                tracing::debug!("Pomodoro reset");
+                self.timer = Timer::new(&self.config);
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                let is_lengths_changed = (self.config.work_minutes, self.config.break_minutes)
+                    != (config.pomodoro.work_minutes, config.pomodoro.break_minutes);
+                self.config = config.pomodoro.clone();
+                if is_lengths_changed && !self.timer.is_running() {
+                    self.timer = Timer::new(&self.config);
+                }
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Update the timer and notify the user when a phase ends.
+    async fn tick(&mut self) -> Result<()> {
+        if let Some(phase) = self.timer.tick(&self.config, std::time::Instant::now()) {
+            let (title, body) = match phase {
+                Phase::Work => ("Break over", "Start the next pomodoro when you're ready"),
+                Phase::Break => ("Pomodoro finished", "Time for a break"),
+            };
+            tracing::info!("Pomodoro: {title}");
+            self.tattoy
+                .state
+                .send_notification(
+                    title,
+                    crate::tattoys::notifications::message::Level::Info,
+                    Some(body.to_owned()),
+                    false,
+                )
+                .await;
+        }
+
+        self.render().await
+    }
+
+    /// A progress bar, eg `████░░░░░░`.
+    fn bar(progress: f32) -> String {
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss,
+            reason = "The bar is only a few characters wide"
+        )]
+        let filled = (progress.clamp(0.0, 1.0) * BAR_WIDTH as f32).floor() as usize;
+        format!(
+            "{}{}",
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH.saturating_sub(filled))
+        )
+    }
+
+    /// Format a duration as minutes and seconds, eg `24:59`.
+    fn countdown(remaining: std::time::Duration) -> String {
+        let seconds = remaining.as_secs();
+        format!("{:02}:{:02}", seconds.div_euclid(60), seconds.rem_euclid(60))
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let now = std::time::Instant::now();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+
+        if self.timer.phase == Phase::Break && self.timer.is_running() {
+            for y in 0..height {
+                self.tattoy.surface.add_text(
+                    0,
+                    y,
+                    " ".repeat(width),
+                    Some(self.config.break_tint),
+                    None,
+                );
+            }
+        }
+
+        let (label, bar_palette_index) = match self.timer.phase {
+            Phase::Work => ("WORK", WORK_PALETTE_INDEX),
+            Phase::Break => ("BREAK", BREAK_PALETTE_INDEX),
+        };
+        let status = if self.timer.is_running() { "▶" } else { "⏸" };
+        let prefix = format!(" {status} {label:<5} ");
+        let suffix = format!(" {} ", Self::countdown(self.timer.remaining(now)));
+        let panel_width = prefix.chars().count() + BAR_WIDTH + suffix.chars().count();
+        if width < panel_width || height == 0 {
+            tracing::debug!("Terminal too small for the pomodoro timer");
+            return self.tattoy.send_output().await;
+        }
+
+        let (x, y) = self.config.position.origin((width, height), (panel_width, 1));
+        let opacity = self.config.opacity;
+        let with_opacity = |colour: crate::surface::Colour| -> crate::surface::Colour {
+            (colour.0, colour.1, colour.2, colour.3 * opacity)
+        };
+        let background = with_opacity(crate::surface::colour_from_srgba(
+            self.palette.default_background_colour(),
+        ));
+        let foreground = with_opacity(crate::surface::colour_from_srgba(
+            self.palette.default_foreground_colour(),
+        ));
+        let bar_colour = with_opacity(crate::surface::colour_from_srgba(
+            self.palette.true_colour_tuple_from_index(bar_palette_index),
+        ));
+
+        let prefix_width = prefix.chars().count();
+        self.tattoy
+            .surface
+            .add_text(x, y, prefix, Some(background), Some(foreground));
+        self.tattoy.surface.add_text(
+            x + prefix_width,
+            y,
+            Self::bar(self.timer.progress(&self.config, now)),
+            Some(background),
+            Some(bar_colour),
+        );
+        self.tattoy.surface.add_text(
+            x + prefix_width + BAR_WIDTH,
+            y,
+            suffix,
+            Some(background),
+            Some(foreground),
+        );
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            work_minutes: 1.0,
+            break_minutes: 0.5,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn pausing_and_resuming() {
+        let config = config();
+        let start = std::time::Instant::now();
+        let mut timer = Timer::new(&config);
+        assert_eq!(timer.remaining(start).as_secs(), 60);
+
+        timer.toggle(start);
+        let later = start + std::time::Duration::from_secs(20);
+        assert_eq!(timer.remaining(later).as_secs(), 40);
+
+        timer.toggle(later);
+        let much_later = later + std::time::Duration::from_secs(100);
+        assert!(!timer.is_running());
+        assert_eq!(timer.remaining(much_later).as_secs(), 40);
+        assert!((timer.progress(&config, much_later) - (1.0 / 3.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn moving_between_phases() {
+        let config = config();
+        let start = std::time::Instant::now();
+        let mut timer = Timer::new(&config);
+        timer.toggle(start);
+        assert_eq!(timer.tick(&config, start), None);
+
+        let work_end = start + std::time::Duration::from_secs(60);
+        assert_eq!(timer.tick(&config, work_end), Some(Phase::Break));
+        assert!(timer.is_running());
+
+        let break_end = work_end + std::time::Duration::from_secs(30);
+        assert_eq!(timer.tick(&config, break_end), Some(Phase::Work));
+        assert!(!timer.is_running());
+        assert_eq!(timer.remaining(break_end).as_secs(), 60);
+    }
+
+    #[test]
+    fn rendering_helpers() {
+        assert_eq!(Pomodoro::bar(0.5), format!("{}{}", "█".repeat(5), "░".repeat(5)));
+        assert_eq!(
+            Pomodoro::countdown(std::time::Duration::from_secs(25 * 60)),
+            "25:00"
+        );
+        assert_eq!(Pomodoro::countdown(std::time::Duration::from_secs(61)), "01:01");
+    }
+}
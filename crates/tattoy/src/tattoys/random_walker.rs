@@ -1,60 +1,203 @@
-//! Randomly move a pixel over the screen. It randomly but smoothly changes colour
+//! Randomly move pixels over the screen. Each walker can leave a fading trail behind it and, by
+//! default, randomly but smoothly changes colour.
 
 use color_eyre::eyre::Result;
 use rand::Rng as _;
 
-/// `RandomWalker`
-pub struct RandomWalker {
-    /// The base Tattoy struct
-    tattoy: super::tattoyer::Tattoyer,
+/// User-configurable settings for the random walkers.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the random walkers.
+    pub enabled: bool,
+    /// The number of walkers.
+    pub count: usize,
+    /// The colours of the walkers, as RGBA values between 0.0 and 1.0. Walkers take the colours
+    /// in turn. When there aren't any colours, each walker slowly changes to random colours.
+    pub colors: Vec<crate::surface::Colour>,
+    /// Use the colours of the terminal's palette, when no `colors` are given.
+    pub palette_colors: bool,
+    /// The number of steps each walker takes every frame.
+    pub speed: u8,
+    /// The number of previous positions of each walker that are also drawn.
+    pub trail_length: usize,
+    /// Whether the trail fades out towards its end.
+    pub trail_fade: bool,
+    /// Don't walk over cells that contain text.
+    pub avoid_text: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            count: 1,
+            colors: Vec::new(),
+            palette_colors: false,
+            speed: 1,
+            trail_length: 0,
+            trail_fade: true,
+            avoid_text: false,
+        }
+    }
+}
+
+/// Position of a walker, in pixels. There are 2 pixels per terminal row.
+type Position = (i32, i32);
+
+/// The rate at which the colour changes
+const COLOUR_CHANGE_RATE: f32 = 0.3;
+
+/// The palette indexes of the colours used by `palette_colors`. They're the normal ANSI colours,
+/// without black and white.
+const PALETTE_INDEXES: [u8; 6] = [1, 2, 3, 4, 5, 6];
+
+/// The compositing layer of the walkers.
+const LAYER: i16 = -10;
+
+/// Take a step from a position, staying within a screen of the given size in pixels. A step onto
+/// a blocked pixel isn't taken.
+fn step<B: Fn(Position) -> bool>(
+    position: Position,
+    delta: (i32, i32),
+    size: (i32, i32),
+    is_blocked: B,
+) -> Position {
+    let next = (
+        (position.0 + delta.0).clamp(0, (size.0 - 1).max(0)),
+        (position.1 + delta.1).clamp(0, (size.1 - 1).max(0)),
+    );
+    if is_blocked(next) {
+        position
+    } else {
+        next
+    }
+}
+
+/// The transparency of a pixel in a trail, the youngest pixel has an age of 0.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "Trails are never long enough to lose precision"
+)]
+fn trail_alpha(age: usize, length: usize, is_fading: bool) -> f32 {
+    if !is_fading {
+        return 1.0;
+    }
+    1.0 - (age + 1) as f32 / (length + 1) as f32
+}
+
+/// The fixed colour of the walker with the given index, or `None` if it should change to random
+/// colours.
+fn walker_colour(
+    index: usize,
+    colours: &[crate::surface::Colour],
+    palette: Option<&crate::palette::converter::Palette>,
+) -> Option<crate::surface::Colour> {
+    if !colours.is_empty() {
+        return colours.get(index.rem_euclid(colours.len())).copied();
+    }
+
+    let palette_index = PALETTE_INDEXES.get(index.rem_euclid(PALETTE_INDEXES.len()))?;
+    let colour = palette?.true_colour_tuple_from_index(*palette_index);
+    Some((colour.0, colour.1, colour.2, colour.3))
+}
+
+/// A single walker.
+#[derive(Debug, Clone)]
+struct Walker {
     /// Current x,y position
     position: Position,
     /// Current colour
     colour: crate::surface::Colour,
+    /// Whether the colour randomly changes.
+    is_colour_changing: bool,
+    /// Previous positions, youngest first.
+    trail: std::collections::VecDeque<Position>,
 }
 
-/// Position of the random pixel
-type Position = (i32, i32);
+impl Walker {
+    /// Make a walker at a random position on a screen of the given size in pixels.
+    fn new(size: (i32, i32), maybe_colour: Option<crate::surface::Colour>) -> Self {
+        let mut rng = rand::thread_rng();
+        let position = (
+            rng.gen_range(0i32..size.0.max(1)),
+            rng.gen_range(0i32..size.1.max(1)),
+        );
+        let colour = maybe_colour.unwrap_or_else(|| {
+            (
+                rng.gen_range(0.1..1.0),
+                rng.gen_range(0.1..1.0),
+                rng.gen_range(0.1..1.0),
+                1.0,
+            )
+        });
 
-/// The rate at which the colour changes
-const COLOUR_CHANGE_RATE: f32 = 0.3;
+        Self {
+            position,
+            colour,
+            is_colour_changing: maybe_colour.is_none(),
+            trail: std::collections::VecDeque::new(),
+        }
+    }
+
+    /// Randomly change the colour a little.
+    fn change_colour(&mut self) {
+        let mut rng = rand::thread_rng();
+        for channel in [&mut self.colour.0, &mut self.colour.1, &mut self.colour.2] {
+            *channel += rng.gen_range(0.0..COLOUR_CHANGE_RATE) - COLOUR_CHANGE_RATE / 2.0;
+            *channel = channel.clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// `RandomWalker`
+pub struct RandomWalker {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The random walker's config
+    config: Config,
+    /// All the walkers
+    walkers: Vec<Walker>,
+    /// The palette, used when the walkers have palette colours.
+    palette: crate::palette::converter::Palette,
+}
 
 impl RandomWalker {
     /// Instatiate
     async fn new(
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
     ) -> Self {
+        let config = state.config.load().random_walker.clone();
         let tattoy = super::tattoyer::Tattoyer::new(
             "random_walker".to_owned(),
             state,
-            -10,
+            LAYER,
             1.0,
             output_channel,
         )
         .await;
-        let position: Position = (0, 0);
-        let colour: crate::surface::Colour = (
-            rand::thread_rng().gen_range(0.1..1.0),
-            rand::thread_rng().gen_range(0.1..1.0),
-            rand::thread_rng().gen_range(0.1..1.0),
-            1.0,
-        );
 
-        Self {
+        let mut random_walker = Self {
             tattoy,
-            position,
-            colour,
-        }
+            config,
+            walkers: Vec::new(),
+            palette,
+        };
+        random_walker.spawn_walkers();
+        random_walker
     }
 
     /// Our main entrypoint.
     pub(crate) async fn start(
         output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
     ) -> Result<()> {
         let mut protocol = state.protocol_tx.subscribe();
-        let mut random_walker = Self::new(output, state).await;
+        let mut random_walker = Self::new(output, state, palette).await;
 
         #[expect(
             clippy::integer_division_remainder_used,
@@ -69,8 +212,19 @@ impl RandomWalker {
                     if matches!(message, crate::run::Protocol::End) {
                         break;
                     }
+                    let is_disabled = random_walker
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::RandomWalker,
+                            |config| config.random_walker.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    random_walker.tattoy.handle_common_protocol_messages(message.clone())?;
                     random_walker.handle_protocol_message(&message);
-                    random_walker.tattoy.handle_common_protocol_messages(message)?;
                 }
             }
         }
@@ -81,49 +235,149 @@ impl RandomWalker {
     /// Custom behaviour for protocol messages.
     fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
         #[expect(
-            clippy::single_match,
             clippy::wildcard_enum_match_arm,
             reason = "We're ready to add handlers for other messages"
         )]
         match message {
-            crate::run::Protocol::Resize { width, height } => {
-                self.position = (
-                    rand::thread_rng().gen_range(0i32..i32::from(*width)),
-                    rand::thread_rng().gen_range(0i32..i32::from(*height) * 2i32),
-                );
+            crate::run::Protocol::Resize { .. } => self.spawn_walkers(),
+            crate::run::Protocol::Config { config, .. } => {
+                if config.random_walker != self.config {
+                    self.config = config.random_walker.clone();
+                    self.spawn_walkers();
+                }
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                self.spawn_walkers();
             }
             _ => (),
         }
     }
 
-    /// Tick the render
-    async fn render(&mut self) -> Result<()> {
-        let width_i32: i32 = self.tattoy.width.into();
-        let height_i32: i32 = self.tattoy.height.into();
+    /// The size of the screen in pixels.
+    fn size(&self) -> (i32, i32) {
+        (
+            i32::from(self.tattoy.width),
+            i32::from(self.tattoy.height) * 2i32,
+        )
+    }
+
+    /// Replace all the walkers with new ones at random positions.
+    fn spawn_walkers(&mut self) {
+        let size = self.size();
+        let palette = self.config.palette_colors.then_some(&self.palette);
+        self.walkers = (0..self.config.count)
+            .map(|index| Walker::new(size, walker_colour(index, &self.config.colors, palette)))
+            .collect();
+    }
+
+    /// Which cells of the screen contain text.
+    fn find_text(&mut self) -> Vec<Vec<bool>> {
+        self.tattoy
+            .screen
+            .surface
+            .screen_cells()
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|cell| !cell.str().trim().is_empty())
+                    .collect()
+            })
+            .collect()
+    }
 
-        self.position.0 += rand::thread_rng().gen_range(0i32..=2i32) - 1i32;
-        self.position.0 = self.position.0.clamp(1i32, width_i32 - 1i32);
+    /// Move all the walkers.
+    fn walk(&mut self) {
+        let size = self.size();
+        let text = if self.config.avoid_text {
+            self.find_text()
+        } else {
+            Vec::new()
+        };
+        let is_blocked = |position: Position| {
+            let (Ok(column), Ok(pixel_row)) =
+                (usize::try_from(position.0), usize::try_from(position.1))
+            else {
+                return false;
+            };
+            text.get(pixel_row.div_euclid(2))
+                .and_then(|line| line.get(column))
+                .copied()
+                .unwrap_or(false)
+        };
 
-        self.position.1 += rand::thread_rng().gen_range(0i32..=2i32) - 1i32;
-        self.position.1 = self.position.1.clamp(1i32, (height_i32 * 2i32) - 1i32);
+        let mut rng = rand::thread_rng();
+        for walker in &mut self.walkers {
+            for _ in 0..self.config.speed {
+                walker.trail.push_front(walker.position);
+                walker.trail.truncate(self.config.trail_length);
+                let delta = (rng.gen_range(-1i32..=1i32), rng.gen_range(-1i32..=1i32));
+                walker.position = step(walker.position, delta, size, is_blocked);
+            }
+            if walker.is_colour_changing {
+                walker.change_colour();
+            }
+        }
+    }
 
-        self.colour.0 +=
-            rand::thread_rng().gen_range(0.0..COLOUR_CHANGE_RATE) - COLOUR_CHANGE_RATE / 2.0;
-        self.colour.0 = self.colour.0.clamp(0.0, 1.0);
-        self.colour.1 +=
-            rand::thread_rng().gen_range(0.0..COLOUR_CHANGE_RATE) - COLOUR_CHANGE_RATE / 2.0;
-        self.colour.1 = self.colour.1.clamp(0.0, 1.0);
-        self.colour.2 +=
-            rand::thread_rng().gen_range(0.0..COLOUR_CHANGE_RATE) - COLOUR_CHANGE_RATE / 2.0;
-        self.colour.2 = self.colour.2.clamp(0.0, 1.0);
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        if self.tattoy.width == 0 || self.tattoy.height == 0 {
+            return Ok(());
+        }
 
+        self.walk();
         self.tattoy.initialise_surface();
-        let x_usize = usize::try_from(self.position.0)?;
-        let y_usize = usize::try_from(self.position.1)?;
-        self.tattoy
-            .surface
-            .add_pixel(x_usize, y_usize, self.colour)?;
+        for walker in &self.walkers {
+            // Draw the oldest parts of the trail first, so that younger pixels are on top.
+            for (age, position) in walker.trail.iter().enumerate().rev() {
+                let alpha = trail_alpha(age, self.config.trail_length, self.config.trail_fade);
+                let colour = (walker.colour.0, walker.colour.1, walker.colour.2, alpha);
+                self.tattoy.surface.add_pixel(
+                    usize::try_from(position.0)?,
+                    usize::try_from(position.1)?,
+                    colour,
+                )?;
+            }
+        }
+        for walker in &self.walkers {
+            self.tattoy.surface.add_pixel(
+                usize::try_from(walker.position.0)?,
+                usize::try_from(walker.position.1)?,
+                walker.colour,
+            )?;
+        }
 
         self.tattoy.send_output().await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stepping() {
+        assert_eq!(step((5, 5), (1, -1), (10, 10), |_| false), (6, 4));
+        assert_eq!(step((9, 0), (1, -1), (10, 10), |_| false), (9, 0));
+        assert_eq!(
+            step((5, 5), (1, 1), (10, 10), |position| position == (6, 6)),
+            (5, 5)
+        );
+    }
+
+    #[test]
+    fn fading_trails() {
+        assert!((trail_alpha(0, 3, true) - 0.75).abs() < f32::EPSILON);
+        assert!((trail_alpha(2, 3, true) - 0.25).abs() < f32::EPSILON);
+        assert!((trail_alpha(2, 3, false) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn choosing_colours() {
+        let colours = [(1.0, 0.0, 0.0, 1.0), (0.0, 1.0, 0.0, 1.0)];
+        assert_eq!(walker_colour(0, &colours, None), Some(colours[0]));
+        assert_eq!(walker_colour(3, &colours, None), Some(colours[1]));
+        assert_eq!(walker_colour(0, &[], None), None);
+    }
+}
@@ -0,0 +1,254 @@
+//! After a period of inactivity, fade the terminal out and show another tattoy full-screen. Any
+//! input wakes the terminal back up. The input that wakes the terminal is swallowed, so that
+//! nothing gets accidentally typed into whatever happens to be running in the terminal.
+//!
+//! The screensaver itself is only responsible for noticing inactivity and drawing the fading cover
+//! over the terminal. The loader starts the chosen tattoy if it isn't already running, and the
+//! renderer lifts it above the cover.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the screensaver.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the screensaver.
+    pub enabled: bool,
+    /// How long there has to be no input and no terminal output before the screensaver starts, in
+    /// seconds.
+    pub idle_timeout: f32,
+    /// How long it takes for the terminal to fade out, in seconds.
+    pub fade: f32,
+    /// The name of the tattoy to show, eg "starfield" or "shaders".
+    pub tattoy: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_timeout: 300.0,
+            fade: 2.0,
+            tattoy: "starfield".to_owned(),
+        }
+    }
+}
+
+/// The compositing layer of the cover that hides the terminal. It's above everything apart from
+/// the tattoy being shown and UI like the settings panel.
+const LAYER: i16 = 280;
+
+/// The layer that the renderer moves the chosen tattoy to, so that it's shown above the cover.
+pub(crate) const TATTOY_LAYER: i16 = LAYER + 1;
+
+/// The shortest allowed idle timeout, so that a misconfiguration can't make the terminal unusable.
+const MINIMUM_IDLE_TIMEOUT: f32 = 5.0;
+
+/// How opaque the cover is, given how long ago the screensaver started.
+fn fade_progress(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return 1.0;
+    }
+    (elapsed / duration).clamp(0.0, 1.0)
+}
+
+/// `Screensaver`
+pub(crate) struct Screensaver {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The screensaver's config
+    config: Config,
+    /// The last time there was any input or terminal output.
+    last_activity: tokio::time::Instant,
+    /// When the screensaver started, if it's running.
+    maybe_started_at: Option<tokio::time::Instant>,
+    /// How opaque the cover was in the last rendered frame.
+    cover_opacity: f32,
+}
+
+impl Screensaver {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().screensaver.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "screensaver".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            last_activity: tokio::time::Instant::now(),
+            maybe_started_at: None,
+            cover_opacity: 0.0,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut screensaver = Self::new(output, state).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = tokio::time::sleep_until(screensaver.idle_deadline()),
+                    if screensaver.maybe_started_at.is_none() => {
+                    screensaver.activate().await?;
+                },
+                () = screensaver.tattoy.sleep_until_next_frame_tick(),
+                    if screensaver.is_fading() => {
+                    screensaver.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = screensaver
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::Screensaver,
+                            |config| config.screensaver.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        screensaver.deactivate().await?;
+                        break;
+                    }
+                    screensaver.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = false;
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            // Output from the PTY doesn't wake the terminal, but it does mean that the terminal
+            // isn't idle.
+            crate::run::Protocol::Input(_) | crate::run::Protocol::Output(_) => {
+                if self.maybe_started_at.is_none() {
+                    self.last_activity = tokio::time::Instant::now();
+                }
+            }
+            // The input handler wakes the terminal, as it's the only thing that can reliably
+            // swallow the waking input.
+            crate::run::Protocol::Screensaver(false) => {
+                self.maybe_started_at = None;
+                self.cover_opacity = 0.0;
+                self.last_activity = tokio::time::Instant::now();
+                self.tattoy.send_blank_output().await?;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.screensaver.clone();
+            }
+            crate::run::Protocol::Resize { .. } | crate::run::Protocol::Palette(_) => {
+                is_rerender_needed = self.maybe_started_at.is_some();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// When the screensaver should start if there's no more activity.
+    fn idle_deadline(&self) -> tokio::time::Instant {
+        let seconds = self.config.idle_timeout.max(MINIMUM_IDLE_TIMEOUT);
+        std::time::Duration::try_from_secs_f32(seconds)
+            .ok()
+            .and_then(|timeout| self.last_activity.checked_add(timeout))
+            .unwrap_or(self.last_activity)
+    }
+
+    /// Whether the terminal is still fading out.
+    fn is_fading(&self) -> bool {
+        self.maybe_started_at.is_some() && self.cover_opacity < 1.0
+    }
+
+    /// Start the screensaver.
+    async fn activate(&mut self) -> Result<()> {
+        tracing::debug!("Starting screensaver with '{}' tattoy", self.config.tattoy);
+        self.maybe_started_at = Some(tokio::time::Instant::now());
+        *self.tattoy.state.is_screensaver_active.write().await = true;
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::Screensaver(true))?;
+        self.render().await
+    }
+
+    /// Stop the screensaver, for when it's disabled whilst running.
+    async fn deactivate(&self) -> Result<()> {
+        let mut is_active = self.tattoy.state.is_screensaver_active.write().await;
+        if *is_active {
+            *is_active = false;
+            self.tattoy
+                .state
+                .protocol_tx
+                .send(crate::run::Protocol::Screensaver(false))?;
+        }
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let Some(started_at) = self.maybe_started_at else {
+            return Ok(());
+        };
+
+        self.cover_opacity = fade_progress(started_at.elapsed().as_secs_f32(), self.config.fade);
+        let background = self.tattoy.state.default_background_colour.get();
+        let colour = (background.0, background.1, background.2, self.cover_opacity);
+
+        self.tattoy.initialise_surface();
+        for y in 0..usize::from(self.tattoy.height) {
+            for x in 0..usize::from(self.tattoy.width) {
+                self.tattoy
+                    .surface
+                    .add_text(x, y, " ".to_owned(), Some(colour), None);
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fading_in_the_cover() {
+        assert!(fade_progress(0.0, 2.0).abs() < f32::EPSILON);
+        assert!((fade_progress(1.0, 2.0) - 0.5).abs() < f32::EPSILON);
+        assert!((fade_progress(3.0, 2.0) - 1.0).abs() < f32::EPSILON);
+        assert!((fade_progress(0.0, 0.0) - 1.0).abs() < f32::EPSILON);
+    }
+}
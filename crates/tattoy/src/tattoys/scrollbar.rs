@@ -1,11 +1,114 @@
-//! Display a scrollbar when scrolling
+//! Display a scrollbar when scrolling. Dragging its thumb scrolls proportionally, and clicking
+//! above or below the thumb pages up or down.
 
 use color_eyre::eyre::Result;
 
+/// User-configurable settings for the scrollbar.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// The width of the scrollbar, in columns.
+    pub width: u16,
+    /// The character used to draw the thumb. A space just shows the thumb's colour.
+    pub thumb_character: String,
+    /// The character used to draw the track that the thumb moves along. No track is drawn when
+    /// it isn't set.
+    pub track_character: Option<String>,
+    /// The colour of the thumb, as RGBA values between 0.0 and 1.0. Defaults to the terminal's
+    /// foreground colour, half transparent.
+    pub thumb_color: Option<crate::surface::Colour>,
+    /// The colour of the track. Defaults to the terminal's foreground colour, mostly transparent.
+    pub track_color: Option<crate::surface::Colour>,
+    /// The smallest the thumb can be, in rows, so that it's still visible in long scrollbacks.
+    pub min_thumb_size: usize,
+    /// How many seconds after scrolling stops to hide the scrollbar. When it isn't set, the
+    /// scrollbar is shown for as long as the terminal is scrolled.
+    pub auto_hide_delay: Option<f32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            thumb_character: " ".to_owned(),
+            track_character: None,
+            thumb_color: None,
+            track_color: None,
+            min_thumb_size: 1,
+            auto_hide_delay: None,
+        }
+    }
+}
+
+/// The transparency of the default thumb colour.
+const DEFAULT_THUMB_ALPHA: f32 = 0.5;
+
+/// The transparency of the default track colour.
+const DEFAULT_TRACK_ALPHA: f32 = 0.1;
+
+/// Make sure the thumb is at least a minimum number of rows, without going off the bottom of the
+/// terminal.
+fn with_minimum_size(thumb: (usize, usize), minimum: usize, height: usize) -> (usize, usize) {
+    let (start, end) = thumb;
+    let size = end.saturating_sub(start).max(minimum).min(height);
+    let start = start.min(height - size);
+    (start, start + size)
+}
+
+/// The scroll position after dragging the thumb from one row of the terminal to another. The
+/// position is the number of lines of the scrollback below the bottom of the terminal.
+fn dragged_position(
+    start_position: usize,
+    from_row: usize,
+    to_row: usize,
+    scrollback_height: usize,
+    height: usize,
+) -> usize {
+    if height == 0 {
+        return start_position;
+    }
+
+    let lines = |rows: usize| (rows * scrollback_height).div_euclid(height);
+    let position = if to_row >= from_row {
+        start_position.saturating_sub(lines(to_row - from_row))
+    } else {
+        start_position + lines(from_row - to_row)
+    };
+    position.min(scrollback_height.saturating_sub(height))
+}
+
+/// The scroll position after clicking on the scrollbar's trough, either above or below the thumb.
+/// Clicking on the thumb itself doesn't page.
+fn paged_position(
+    position: usize,
+    row: usize,
+    thumb: (usize, usize),
+    scrollback_height: usize,
+    height: usize,
+) -> Option<usize> {
+    let (start, end) = thumb;
+    if row < start {
+        Some((position + height).min(scrollback_height.saturating_sub(height)))
+    } else if row >= end.max(start + 1) {
+        Some(position.saturating_sub(height))
+    } else {
+        None
+    }
+}
+
 /// `Scrollbar`
 pub(crate) struct Scrollbar {
     /// The base Tattoy struct
     tattoy: super::tattoyer::Tattoyer,
+    /// The scrollbar's config
+    config: Config,
+    /// Whilst the thumb is being dragged, the row it was grabbed on and the scroll position at
+    /// the time.
+    drag: Option<(usize, usize)>,
+    /// When to hide the scrollbar, if it's set to hide itself after scrolling stops.
+    hide_at: Option<tokio::time::Instant>,
+    /// The palette, used for the default colours of the scrollbar.
+    palette: crate::palette::converter::Palette,
 }
 
 impl Scrollbar {
@@ -13,27 +116,41 @@ impl Scrollbar {
     async fn new(
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
     ) -> Self {
+        let config = state.config.load().scrollbar.clone();
         let tattoy =
             super::tattoyer::Tattoyer::new("scrollbar".to_owned(), state, 100, 1.0, output_channel)
                 .await;
-        Self { tattoy }
+        Self {
+            tattoy,
+            config,
+            drag: None,
+            hide_at: None,
+            palette,
+        }
     }
 
     /// Our main entrypoint.
     pub(crate) async fn start(
         output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
     ) -> Result<()> {
         let mut protocol = state.protocol_tx.subscribe();
-        let mut scrollbar = Self::new(output, state).await;
+        let mut scrollbar = Self::new(output, state, palette).await;
 
         #[expect(
             clippy::integer_division_remainder_used,
             reason = "This is caused by the `tokio::select!`"
         )]
         loop {
+            let hide_at = scrollbar.hide_at.unwrap_or_else(tokio::time::Instant::now);
             tokio::select! {
+                () = tokio::time::sleep_until(hide_at), if scrollbar.hide_at.is_some() => {
+                    scrollbar.hide_at = None;
+                    scrollbar.tattoy.send_blank_output().await?;
+                }
                 result = protocol.recv() => {
                     if matches!(result, Ok(crate::run::Protocol::End)) {
                         break;
@@ -53,8 +170,33 @@ impl Scrollbar {
     ) -> Result<()> {
         match result {
             Ok(message) => {
+                let mut is_rerender_needed = false;
+
+                #[expect(
+                    clippy::wildcard_enum_match_arm,
+                    reason = "We only react to a few messages"
+                )]
+                match &message {
+                    crate::run::Protocol::Input(input) => {
+                        if let termwiz::input::InputEvent::Mouse(mouse) = &input.event {
+                            self.handle_mouse(mouse)?;
+                        }
+                    }
+                    crate::run::Protocol::Config { config, .. } => {
+                        self.config = config.scrollbar.clone();
+                        is_rerender_needed = self.tattoy.is_scrolling();
+                    }
+                    crate::run::Protocol::Palette(palette) => {
+                        self.palette = palette.clone();
+                        is_rerender_needed = self.tattoy.is_scrolling();
+                    }
+                    _ => (),
+                }
+
                 self.tattoy.handle_common_protocol_messages(message)?;
-                if self.tattoy.last_scroll_position != self.tattoy.scrollback.position {
+                if is_rerender_needed
+                    || self.tattoy.last_scroll_position != self.tattoy.scrollback.position
+                {
                     self.render().await?;
                 }
             }
@@ -64,10 +206,61 @@ impl Scrollbar {
         Ok(())
     }
 
+    /// Dragging the thumb, or clicking the trough, scrolls the shadow terminal.
+    fn handle_mouse(&mut self, mouse: &termwiz::input::MouseEvent) -> Result<()> {
+        if !mouse
+            .mouse_buttons
+            .contains(termwiz::input::MouseButtons::LEFT)
+        {
+            self.drag = None;
+            return Ok(());
+        }
+        if !self.tattoy.is_scrolling() || self.tattoy.is_alternate_screen() {
+            return Ok(());
+        }
+
+        let (column, row) = crate::tattoys::hit_test::mouse_position(mouse);
+        let scrollback_height = self.tattoy.scrollback.surface.dimensions().1;
+        let height = usize::from(self.tattoy.height);
+        let position = self.tattoy.scrollback.position;
+
+        // Once the thumb is grabbed, the mouse can wander away from the scrollbar.
+        if let Some((from_row, start_position)) = self.drag {
+            let new_position =
+                dragged_position(start_position, from_row, row, scrollback_height, height);
+            return self.scroll_to(new_position);
+        }
+
+        let is_over_scrollbar =
+            column >= usize::from(self.tattoy.width.saturating_sub(self.config.width));
+        if !is_over_scrollbar {
+            return Ok(());
+        }
+        match paged_position(position, row, self.thumb(), scrollback_height, height) {
+            Some(new_position) => self.scroll_to(new_position),
+            None => {
+                self.drag = Some((row, position));
+                Ok(())
+            }
+        }
+    }
+
+    /// Ask the shadow terminal to scroll.
+    fn scroll_to(&self, position: usize) -> Result<()> {
+        if position != self.tattoy.scrollback.position {
+            self.tattoy
+                .state
+                .protocol_tx
+                .send(crate::run::Protocol::ScrollTo(position))?;
+        }
+        Ok(())
+    }
+
     /// Tick the render
     async fn render(&mut self) -> Result<()> {
         if self.tattoy.is_scrolling_end() {
             tracing::debug!("Scrolling finished.");
+            self.hide_at = None;
             self.tattoy.send_blank_output().await?;
             return Ok(());
         }
@@ -77,9 +270,7 @@ impl Scrollbar {
             return Ok(());
         }
 
-        // TODO: only render on scroll position change.
-
-        let (start, end) = self.get_start_end();
+        let (start, end) = self.thumb();
         if start > end {
             tracing::error!("Bad scrollbar dimensions: {start:?} {end:?}");
             return Ok(());
@@ -87,19 +278,73 @@ impl Scrollbar {
 
         self.tattoy.initialise_surface();
 
-        for y in start..end {
-            self.tattoy.surface.add_text(
-                (self.tattoy.width - 1).into(),
-                y,
-                " ".into(),
-                Some((1.0, 1.0, 1.0, 0.5)),
-                None,
+        let foreground = self.palette.default_foreground_colour();
+        let thumb_colour = self.config.thumb_color.unwrap_or((
+            foreground.0,
+            foreground.1,
+            foreground.2,
+            DEFAULT_THUMB_ALPHA,
+        ));
+        let track_colour = self.config.track_color.unwrap_or((
+            foreground.0,
+            foreground.1,
+            foreground.2,
+            DEFAULT_TRACK_ALPHA,
+        ));
+        let width = self.config.width.min(self.tattoy.width);
+        let left = usize::from(self.tattoy.width - width);
+        for y in 0..usize::from(self.tattoy.height) {
+            let is_thumb = (start..end).contains(&y);
+            let (character, colour) = if is_thumb {
+                (self.config.thumb_character.clone(), thumb_colour)
+            } else if let Some(track_character) = &self.config.track_character {
+                (track_character.clone(), track_colour)
+            } else {
+                continue;
+            };
+            for x in left..usize::from(self.tattoy.width) {
+                self.add_character(x, y, character.clone(), colour);
+            }
+        }
+
+        if let Some(delay) = self.config.auto_hide_delay {
+            self.hide_at = Some(
+                tokio::time::Instant::now() + std::time::Duration::from_secs_f32(delay.max(0.0)),
             );
         }
 
         self.tattoy.send_output().await
     }
 
+    /// Draw a single character of the scrollbar. A blank character is just shown as its
+    /// background colour.
+    fn add_character(
+        &mut self,
+        x: usize,
+        y: usize,
+        character: String,
+        colour: crate::surface::Colour,
+    ) {
+        if character.trim().is_empty() {
+            self.tattoy
+                .surface
+                .add_text(x, y, character, Some(colour), None);
+        } else {
+            self.tattoy
+                .surface
+                .add_text(x, y, character, None, Some(colour));
+        }
+    }
+
+    /// The first row of the thumb and the row just after it.
+    fn thumb(&self) -> (usize, usize) {
+        with_minimum_size(
+            self.get_start_end(),
+            self.config.min_thumb_size,
+            usize::from(self.tattoy.height),
+        )
+    }
+
     /// Get the start and end y coordinates of the scrollbar
     #[expect(
         clippy::as_conversions,
@@ -128,3 +373,36 @@ impl Scrollbar {
         (scrollbar_start, scrollbar_end)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn minimum_thumb_size() {
+        assert_eq!(with_minimum_size((3, 4), 3, 10), (3, 6));
+        assert_eq!(with_minimum_size((3, 8), 3, 10), (3, 8));
+        assert_eq!(with_minimum_size((9, 9), 3, 10), (7, 10));
+        assert_eq!(with_minimum_size((0, 1), 20, 10), (0, 10));
+    }
+
+    #[test]
+    fn dragging_the_thumb() {
+        // A 100 line scrollback on a 10 row terminal, so each row is 10 lines.
+        assert_eq!(dragged_position(50, 5, 7, 100, 10), 30);
+        assert_eq!(dragged_position(50, 5, 2, 100, 10), 80);
+        assert_eq!(dragged_position(50, 5, 0, 100, 10), 90);
+        assert_eq!(dragged_position(50, 5, 9, 100, 10), 10);
+        assert_eq!(dragged_position(10, 5, 9, 100, 10), 0);
+    }
+
+    #[test]
+    fn clicking_the_trough() {
+        assert_eq!(paged_position(50, 1, (4, 5), 100, 10), Some(60));
+        assert_eq!(paged_position(85, 1, (1, 2), 100, 10), None);
+        assert_eq!(paged_position(85, 0, (1, 2), 100, 10), Some(90));
+        assert_eq!(paged_position(50, 8, (4, 5), 100, 10), Some(40));
+        assert_eq!(paged_position(5, 8, (4, 5), 100, 10), Some(0));
+        assert_eq!(paged_position(50, 4, (4, 4), 100, 10), None);
+    }
+}
@@ -0,0 +1,318 @@
+//! Search the scrollback. The search is typed into a small prompt at the bottom of the terminal,
+//! then every match in the scrollback stays highlighted, along with a count of the matches, until
+//! the search is dismissed. Matches in new output are highlighted as it arrives.
+//!
+//! Searches are case-insensitive, unless the search contains an uppercase letter.
+
+use color_eyre::eyre::Result;
+
+/// The compositing layer of the highlights. It's just above the focus mode's dimming, so that
+/// matches still stand out, but below all the other widgets.
+const LAYER: i16 = 15;
+
+/// The palette index of the colour used to highlight matches.
+const HIGHLIGHT_PALETTE_INDEX: u8 = 3;
+
+/// The transparency of the highlights, so that the matching text can still be read.
+const HIGHLIGHT_ALPHA: f32 = 0.5;
+
+/// The text at the start of the prompt.
+const PROMPT: &str = " search: ";
+
+/// Find all the columns where a search starts in a line of text. Matches don't overlap.
+fn find_matches(line: &str, search: &str) -> Vec<usize> {
+    let is_case_sensitive = search.chars().any(char::is_uppercase);
+    let normalise = |character: char| {
+        if is_case_sensitive {
+            character
+        } else {
+            character.to_lowercase().next().unwrap_or(character)
+        }
+    };
+    let line: Vec<char> = line.chars().map(normalise).collect();
+    let search: Vec<char> = search.chars().map(normalise).collect();
+    if search.is_empty() {
+        return Vec::new();
+    }
+
+    let mut columns = Vec::new();
+    let mut column = 0;
+    while column + search.len() <= line.len() {
+        if line.get(column..column + search.len()) == Some(search.as_slice()) {
+            columns.push(column);
+            column += search.len();
+        } else {
+            column += 1;
+        }
+    }
+    columns
+}
+
+/// The count indicator, eg ` 3 matches `.
+fn describe_count(count: usize) -> String {
+    if count == 1 {
+        " 1 match ".to_owned()
+    } else {
+        format!(" {count} matches ")
+    }
+}
+
+/// A single match, in lines from the top of the scrollback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Match {
+    /// The line that the match is on.
+    line: usize,
+    /// The column that the match starts at.
+    column: usize,
+}
+
+/// `Search`
+pub(crate) struct Search {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Whether the search is currently being typed.
+    is_prompt_open: bool,
+    /// The search. An empty search means there's nothing to highlight.
+    search: String,
+    /// All the matches in the scrollback.
+    matches: Vec<Match>,
+    /// The palette, used for the colours of the highlights and prompt.
+    palette: crate::palette::converter::Palette,
+}
+
+impl Search {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let tattoy =
+            super::tattoyer::Tattoyer::new("search".to_owned(), state, LAYER, 1.0, output_channel)
+                .await;
+
+        Self {
+            tattoy,
+            is_prompt_open: false,
+            search: String::new(),
+            matches: Vec::new(),
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut search = Self::new(output, state, palette).await;
+
+        while let Ok(message) = protocol.recv().await {
+            if matches!(message, crate::run::Protocol::End) {
+                break;
+            }
+            search.handle_protocol_message(message).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let is_output = matches!(message, crate::run::Protocol::Output(_));
+        let mut is_rerender_needed = matches!(
+            message,
+            crate::run::Protocol::Resize { .. } | crate::run::Protocol::ReservedRows(_)
+        );
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(crate::config::input::KeybindingAction::Search) => {
+                self.set_prompt_open(!self.is_prompt_open).await;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Input(input) if self.is_prompt_open => {
+                if let termwiz::input::InputEvent::Key(key_event) = &input.event {
+                    self.handle_key(key_event).await;
+                    is_rerender_needed = true;
+                }
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if self.search.is_empty() && !self.is_prompt_open {
+            if is_rerender_needed {
+                self.tattoy.send_blank_output().await?;
+            }
+            return Ok(());
+        }
+
+        if is_output || is_rerender_needed {
+            self.matches = self.find_all_matches();
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Open or close the prompt, which captures all keyboard input whilst it's open.
+    async fn set_prompt_open(&mut self, is_open: bool) {
+        self.is_prompt_open = is_open;
+        *self.tattoy.state.is_search_prompt_open.write().await = is_open;
+        tracing::debug!("Search prompt open: {is_open}");
+    }
+
+    /// Handle key presses whilst the prompt is open.
+    async fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) {
+        let is_modified = key_event
+            .modifiers
+            .intersects(termwiz::input::Modifiers::CTRL | termwiz::input::Modifiers::ALT);
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few keys"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::Char(character) if !is_modified => {
+                self.search.push(character);
+            }
+            termwiz::input::KeyCode::Backspace => {
+                self.search.pop();
+            }
+            termwiz::input::KeyCode::Enter => self.set_prompt_open(false).await,
+            termwiz::input::KeyCode::Escape => {
+                self.search.clear();
+                self.set_prompt_open(false).await;
+            }
+            _ => (),
+        }
+    }
+
+    /// Every match in the scrollback.
+    fn find_all_matches(&mut self) -> Vec<Match> {
+        if self.search.is_empty() {
+            return Vec::new();
+        }
+
+        self.tattoy
+            .scrollback
+            .surface
+            .screen_cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(line, cells)| {
+                let text: String = cells
+                    .iter()
+                    .map(|cell| cell.str().chars().next().unwrap_or(' '))
+                    .collect();
+                find_matches(&text, &self.search)
+                    .into_iter()
+                    .map(move |column| Match { line, column })
+            })
+            .collect()
+    }
+
+    /// Highlight all the matches that are currently visible.
+    async fn render_highlights(&mut self) {
+        // Full-screen apps don't show the scrollback.
+        if self.tattoy.is_alternate_screen() {
+            return;
+        }
+
+        let highlight = self
+            .palette
+            .true_colour_tuple_from_index(HIGHLIGHT_PALETTE_INDEX);
+        let colour = (highlight.0, highlight.1, highlight.2, HIGHLIGHT_ALPHA);
+
+        // The PTY is offset by any rows that are reserved above it, eg by the status bar.
+        let top = usize::from(self.tattoy.state.reserved_rows.get().top);
+        let height = self.tattoy.screen.surface.dimensions().1;
+        let scrollback_height = self.tattoy.scrollback.surface.dimensions().1;
+        let view_top = scrollback_height
+            .saturating_sub(height)
+            .saturating_sub(self.tattoy.scrollback.position);
+        let width = self.search.chars().count();
+        for found in &self.matches {
+            let Some(row) = found.line.checked_sub(view_top) else {
+                continue;
+            };
+            if row >= height {
+                continue;
+            }
+            self.tattoy.surface.add_text(
+                found.column,
+                top + row,
+                " ".repeat(width),
+                Some(colour),
+                None,
+            );
+        }
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+        self.render_highlights().await;
+
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        let background =
+            crate::surface::colour_from_srgba(self.palette.default_background_colour());
+        let foreground =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+
+        if !self.search.is_empty() {
+            let count = describe_count(self.matches.len());
+            let (x, y) = crate::surface::Corner::TopRight
+                .origin((width, height), (count.chars().count(), 1));
+            self.tattoy
+                .surface
+                .add_text(x, y, count, Some(background), Some(foreground));
+        }
+
+        if self.is_prompt_open && height > 0 {
+            let prompt = format!("{PROMPT}{}█", self.search);
+            let padding = " ".repeat(width.saturating_sub(prompt.chars().count()));
+            self.tattoy.surface.add_text(
+                0,
+                height - 1,
+                format!("{prompt}{padding}"),
+                Some(background),
+                Some(foreground),
+            );
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finding_matches() {
+        assert_eq!(find_matches("error: an Error", "error"), vec![0, 10]);
+        assert_eq!(find_matches("error: an Error", "Error"), vec![10]);
+        assert_eq!(find_matches("aaaa", "aa"), vec![0, 2]);
+        assert!(find_matches("short", "much longer").is_empty());
+        assert!(find_matches("anything", "").is_empty());
+    }
+
+    #[test]
+    fn describing_the_count() {
+        assert_eq!(describe_count(0), " 0 matches ");
+        assert_eq!(describe_count(1), " 1 match ");
+        assert_eq!(describe_count(12), " 12 matches ");
+    }
+}
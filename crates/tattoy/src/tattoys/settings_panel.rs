@@ -0,0 +1,774 @@
+//! An interactive overlay for browsing and editing the most common config options. Changes are
+//! written straight back to the config files, which are then hot-reloaded like any other config
+//! change.
+//!
+//! Changes are written to wherever they take effect. That's the theme file if the theme sets the
+//! option, otherwise the active profile if there is one, otherwise the main config.
+
+use color_eyre::eyre::Result;
+
+/// The compositing layer of the panel. It should be above everything else.
+const LAYER: i16 = 300;
+
+/// The width of the panel in columns.
+const PANEL_WIDTH: usize = 46;
+
+/// Background colour of the panel.
+const BACKGROUND: crate::surface::Colour = (0.05, 0.05, 0.1, 0.95);
+
+/// Background colour of the currently selected setting.
+const SELECTED: crate::surface::Colour = (0.0, 0.2, 0.63, 1.0);
+
+/// Help text displayed at the bottom of the panel.
+const HELP: &str = "↑↓ select  ←→ adjust  ⏎ toggle/edit  esc close";
+
+/// Help text displayed at the bottom of the panel whilst typing in a value.
+const EDITING_HELP: &str = "type a value  ⏎ save  esc cancel";
+
+/// The kind of value that a setting holds, and how it can be adjusted.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    /// A simple on/off switch.
+    Toggle,
+    /// A whole number.
+    Integer {
+        /// How much to change the value with each key press.
+        step: i64,
+        /// The minimum value.
+        min: i64,
+        /// The maximum value.
+        max: i64,
+    },
+    /// A decimal number.
+    Float {
+        /// How much to change the value with each key press.
+        step: f64,
+        /// The minimum value.
+        min: f64,
+        /// The maximum value.
+        max: f64,
+    },
+}
+
+/// A single editable setting.
+#[derive(Debug, Clone, Copy)]
+struct Setting {
+    /// The dotted path to the setting in the config, eg `shader.opacity`.
+    path: &'static str,
+    /// A human-friendly name for the setting.
+    label: &'static str,
+    /// The kind of value the setting holds.
+    kind: Kind,
+}
+
+/// All the settings that can be edited in the panel.
+const SETTINGS: [Setting; 14] = [
+    Setting {
+        path: "frame_rate",
+        label: "Frame rate",
+        kind: Kind::Integer {
+            step: 5,
+            min: 1,
+            max: 120,
+        },
+    },
+    Setting {
+        path: "show_tattoy_indicator",
+        label: "Show indicator",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "accessibility.reduce_motion",
+        label: "Reduce motion",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "notifications.enabled",
+        label: "Notifications",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "text_contrast.enabled",
+        label: "Auto text contrast",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "text_contrast.target_contrast",
+        label: "Target contrast",
+        kind: Kind::Float {
+            step: 0.1,
+            min: 1.0,
+            max: 21.0,
+        },
+    },
+    Setting {
+        path: "minimap.enabled",
+        label: "Minimap",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "shader.enabled",
+        label: "Shader",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "shader.opacity",
+        label: "Shader opacity",
+        kind: Kind::Float {
+            step: 0.05,
+            min: 0.0,
+            max: 1.0,
+        },
+    },
+    Setting {
+        path: "bg_command.enabled",
+        label: "Background command",
+        kind: Kind::Toggle,
+    },
+    Setting {
+        path: "bg_command.opacity",
+        label: "Background command opacity",
+        kind: Kind::Float {
+            step: 0.05,
+            min: 0.0,
+            max: 1.0,
+        },
+    },
+    Setting {
+        path: "color.saturation",
+        label: "Saturation",
+        kind: Kind::Float {
+            step: 0.05,
+            min: -1.0,
+            max: 1.0,
+        },
+    },
+    Setting {
+        path: "color.brightness",
+        label: "Brightness",
+        kind: Kind::Float {
+            step: 0.05,
+            min: -1.0,
+            max: 1.0,
+        },
+    },
+    Setting {
+        path: "color.hue",
+        label: "Hue",
+        kind: Kind::Float {
+            step: 0.05,
+            min: -1.0,
+            max: 1.0,
+        },
+    },
+];
+
+/// The theme that's in use.
+struct Theme {
+    /// The path to the theme file.
+    path: std::path::PathBuf,
+    /// The theme file, parsed in a way that preserves comments and formatting.
+    document: toml_edit::DocumentMut,
+}
+
+/// `SettingsPanel`
+pub(crate) struct SettingsPanel {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Is the panel currently visible?
+    is_open: bool,
+    /// The index of the currently selected setting.
+    selected: usize,
+    /// The user's main config file, parsed in a way that preserves comments and formatting.
+    document: toml_edit::DocumentMut,
+    /// The shipped default config, used for settings that aren't in the user's config file.
+    defaults: toml_edit::DocumentMut,
+    /// The active theme, if any.
+    theme: Option<Theme>,
+    /// The name of the active profile, if any.
+    profile: Option<String>,
+    /// The text typed so far when editing a number.
+    editing: Option<String>,
+}
+
+impl SettingsPanel {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<Self> {
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "settings_panel".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Ok(Self {
+            tattoy,
+            is_open: false,
+            selected: 0,
+            document: toml_edit::DocumentMut::new(),
+            defaults: crate::config::main::DEFAULT_CONFIG.parse()?,
+            theme: None,
+            profile: None,
+            editing: None,
+        })
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut panel = Self::new(output, state).await?;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = protocol.recv() => {
+                    if matches!(result, Ok(crate::run::Protocol::End)) {
+                        break;
+                    }
+                    panel.handle_protocol_message(result).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(
+        &mut self,
+        result: std::result::Result<crate::run::Protocol, tokio::sync::broadcast::error::RecvError>,
+    ) -> Result<()> {
+        match result {
+            Ok(message) => {
+                let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+
+                #[expect(
+                    clippy::wildcard_enum_match_arm,
+                    reason = "We only react to a few messages"
+                )]
+                match &message {
+                    crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleSettings,
+                    ) => {
+                        self.toggle().await?;
+                    }
+                    crate::run::Protocol::Input(input) if self.is_open => {
+                        if let termwiz::input::InputEvent::Key(key_event) = &input.event {
+                            self.handle_key(key_event).await?;
+                        }
+                    }
+                    _ => (),
+                }
+
+                self.tattoy.handle_common_protocol_messages(message)?;
+                if is_resize && self.is_open {
+                    self.render().await?;
+                }
+            }
+            Err(error) => tracing::error!("Receiving protocol message: {error:?}"),
+        }
+
+        Ok(())
+    }
+
+    /// Open or close the panel.
+    async fn toggle(&mut self) -> Result<()> {
+        self.is_open = !self.is_open;
+        self.editing = None;
+        *self.tattoy.state.is_settings_panel_open.write().await = self.is_open;
+        tracing::debug!("Settings panel open: {}", self.is_open);
+
+        if self.is_open {
+            self.load_document().await?;
+            self.render().await
+        } else {
+            self.tattoy.send_blank_output().await
+        }
+    }
+
+    /// Read the user's main config file, and the file of the active theme.
+    async fn load_document(&mut self) -> Result<()> {
+        let path = crate::config::main::Config::main_config_path(&self.tattoy.state).await;
+        let data = tokio::fs::read_to_string(path).await?;
+        self.document = data.parse()?;
+
+        self.profile = self.tattoy.state.active_profile.read().await.clone();
+        if let Some(name) = self.profile.clone() {
+            let profile_parts = Self::profile_parts(&name, "");
+            let is_builtin_remote = name == crate::config::profiles::REMOTE_PROFILE_NAME
+                && Self::get_item(self.document.as_item(), &profile_parts).is_none();
+            if is_builtin_remote {
+                // Edits start from the built-in profile, so that its other settings are kept.
+                let builtin: toml_edit::DocumentMut =
+                    crate::config::profiles::DEFAULT_REMOTE_PROFILE.parse()?;
+                let mut profile = builtin.as_table().clone();
+                profile.fmt();
+                profile.decor_mut().clear();
+                let document = self.document.as_table_mut();
+                Self::set_item(document, &profile_parts, toml_edit::Item::Table(profile));
+            }
+        }
+
+        self.theme = None;
+        let maybe_theme_name = self.tattoy.state.config.load().theme.clone();
+        if let Some(theme_name) = maybe_theme_name {
+            let directory = crate::config::main::Config::directory(&self.tattoy.state).await;
+            let path = crate::config::themes::path(&directory, &theme_name);
+            let data = tokio::fs::read_to_string(&path).await?;
+            self.theme = Some(Theme {
+                path,
+                document: data.parse()?,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Write any changes back to the user's main config file. The config file watcher then takes
+    /// care of applying the changes.
+    async fn save_document(&self) -> Result<()> {
+        let path = crate::config::main::Config::main_config_path(&self.tattoy.state).await;
+        tokio::fs::write(path, self.document.to_string()).await?;
+        Ok(())
+    }
+
+    /// Handle key presses whilst the panel is open.
+    async fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Result<()> {
+        if let Some(text) = self.editing.take() {
+            return self.handle_editing_key(key_event, text).await;
+        }
+
+        let last = SETTINGS.len() - 1;
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few keys"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::UpArrow => {
+                self.selected = if self.selected == 0 {
+                    last
+                } else {
+                    self.selected - 1
+                };
+            }
+            termwiz::input::KeyCode::DownArrow => {
+                self.selected = if self.selected == last {
+                    0
+                } else {
+                    self.selected + 1
+                };
+            }
+            termwiz::input::KeyCode::LeftArrow => self.adjust(-1).await?,
+            termwiz::input::KeyCode::RightArrow => self.adjust(1).await?,
+            termwiz::input::KeyCode::Enter | termwiz::input::KeyCode::Char(' ') => {
+                let Some(setting) = SETTINGS.get(self.selected).copied() else {
+                    return Ok(());
+                };
+                match setting.kind {
+                    Kind::Toggle => self.adjust(1).await?,
+                    Kind::Integer { .. } | Kind::Float { .. } => {
+                        let current = self.display_value(&setting);
+                        let text = if current == "?" {
+                            String::new()
+                        } else {
+                            current
+                        };
+                        self.editing = Some(text);
+                    }
+                }
+            }
+            termwiz::input::KeyCode::Escape => {
+                self.toggle().await?;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        }
+
+        self.render().await
+    }
+
+    /// Handle key presses whilst typing in a number.
+    async fn handle_editing_key(
+        &mut self,
+        key_event: &termwiz::input::KeyEvent,
+        mut text: String,
+    ) -> Result<()> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few keys"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::Char(character)
+                if character.is_ascii_digit() || matches!(character, '.' | '-') =>
+            {
+                text.push(character);
+                self.editing = Some(text);
+            }
+            termwiz::input::KeyCode::Backspace => {
+                text.pop();
+                self.editing = Some(text);
+            }
+            termwiz::input::KeyCode::Enter => {
+                let Some(setting) = SETTINGS.get(self.selected).copied() else {
+                    return Ok(());
+                };
+                match Self::parse_typed_value(setting.kind, &text) {
+                    Some(value) => self.write_value(setting.path, value).await?,
+                    None => self.editing = Some(text),
+                }
+            }
+            termwiz::input::KeyCode::Escape => (),
+            _ => {
+                self.editing = Some(text);
+                return Ok(());
+            }
+        }
+
+        self.render().await
+    }
+
+    /// Parse a typed in value for a setting, keeping it within the setting's limits.
+    fn parse_typed_value(kind: Kind, text: &str) -> Option<toml_edit::Value> {
+        match kind {
+            Kind::Toggle => None,
+            Kind::Integer { min, max, .. } => text
+                .parse::<i64>()
+                .ok()
+                .map(|value| toml_edit::Value::from(value.clamp(min, max))),
+            Kind::Float { min, max, .. } => text
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(|value| toml_edit::Value::from(value.clamp(min, max))),
+        }
+    }
+
+    /// Change the value of the currently selected setting.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Steps are always small whole numbers"
+    )]
+    async fn adjust(&mut self, direction: i64) -> Result<()> {
+        let Some(setting) = SETTINGS.get(self.selected).copied() else {
+            return Ok(());
+        };
+        let current = self.value_of(setting.path);
+
+        let new_value = match setting.kind {
+            Kind::Toggle => {
+                let value = current.and_then(toml_edit::Item::as_bool).unwrap_or(false);
+                toml_edit::Value::from(!value)
+            }
+            Kind::Integer { step, min, max } => {
+                let value = current.and_then(toml_edit::Item::as_integer).unwrap_or(min);
+                toml_edit::Value::from((value + step * direction).clamp(min, max))
+            }
+            Kind::Float { step, min, max } => {
+                let value = current.and_then(Self::as_float).unwrap_or(min);
+                let adjusted = step.mul_add(direction as f64, value).clamp(min, max);
+                // Avoid saving values like `0.30000000000000004`.
+                toml_edit::Value::from((adjusted * 100.0).round() / 100.0)
+            }
+        };
+
+        self.write_value(setting.path, new_value).await
+    }
+
+    /// Save a new value for a setting to wherever it takes effect: the theme if it sets the
+    /// setting, otherwise the active profile, otherwise the main config.
+    async fn write_value(&mut self, path: &str, value: toml_edit::Value) -> Result<()> {
+        tracing::debug!("Settings panel changing {path} to {value}");
+        let parts = path.split('.').collect::<Vec<&str>>();
+
+        if let Some(theme) = &mut self.theme {
+            if Self::get_item(theme.document.as_item(), &parts).is_some() {
+                Self::set_item(theme.document.as_table_mut(), &parts, value.into());
+                tokio::fs::write(&theme.path, theme.document.to_string()).await?;
+                return Ok(());
+            }
+        }
+
+        let target = match &self.profile {
+            Some(name) => Self::profile_parts(name, path),
+            None => parts,
+        };
+        Self::set_item(self.document.as_table_mut(), &target, value.into());
+        self.save_document().await
+    }
+
+    /// The path to a setting within a profile of the main config. An empty setting path gives the
+    /// path to the profile itself.
+    fn profile_parts<'path>(name: &'path str, path: &'path str) -> Vec<&'path str> {
+        let mut parts = vec![crate::config::profiles::PROFILES_KEY, name];
+        parts.extend(path.split('.').filter(|part| !part.is_empty()));
+        parts
+    }
+
+    /// Get the current value of a setting, from the theme, the active profile, the main config or
+    /// the default config, in that order.
+    fn value_of(&self, path: &str) -> Option<&toml_edit::Item> {
+        let parts = path.split('.').collect::<Vec<&str>>();
+        let maybe_themed = self
+            .theme
+            .as_ref()
+            .and_then(|theme| Self::get_item(theme.document.as_item(), &parts));
+        let maybe_profiled = self.profile.as_ref().and_then(|name| {
+            Self::get_item(self.document.as_item(), &Self::profile_parts(name, path))
+        });
+
+        maybe_themed
+            .or(maybe_profiled)
+            .or_else(|| Self::get_item(self.document.as_item(), &parts))
+            .or_else(|| Self::get_item(self.defaults.as_item(), &parts))
+    }
+
+    /// Get the item at the given path.
+    fn get_item<'item>(
+        item: &'item toml_edit::Item,
+        parts: &[&str],
+    ) -> Option<&'item toml_edit::Item> {
+        parts.iter().try_fold(item, |parent, part| parent.get(part))
+    }
+
+    /// Set the item at the given path, creating any missing tables. Existing comments and
+    /// formatting are preserved.
+    fn set_item(root: &mut dyn toml_edit::TableLike, parts: &[&str], item: toml_edit::Item) {
+        let Some((key, parents)) = parts.split_last() else {
+            return;
+        };
+
+        let mut table = root;
+        for part in parents {
+            if table.get(part).is_none() {
+                let mut new_table = toml_edit::Table::new();
+                new_table.set_implicit(true);
+                table.insert(part, toml_edit::Item::Table(new_table));
+            }
+            let Some(child) = table
+                .get_mut(part)
+                .and_then(toml_edit::Item::as_table_like_mut)
+            else {
+                tracing::warn!(
+                    "Couldn't set '{}' because '{part}' isn't a table",
+                    parts.join(".")
+                );
+                return;
+            };
+            table = child;
+        }
+
+        // Replacing the value in place keeps any comments around it.
+        let is_existing_value = table.get(key).is_some_and(toml_edit::Item::is_value);
+        match item {
+            toml_edit::Item::Value(value) if is_existing_value => {
+                if let Some(existing) = table.get_mut(key).and_then(toml_edit::Item::as_value_mut) {
+                    let decor = existing.decor().clone();
+                    *existing = value;
+                    *existing.decor_mut() = decor;
+                }
+            }
+            toml_edit::Item::Value(_)
+            | toml_edit::Item::None
+            | toml_edit::Item::Table(_)
+            | toml_edit::Item::ArrayOfTables(_) => {
+                table.insert(key, item);
+            }
+        }
+    }
+
+    /// Read a number as a float, even if it was written as an integer, eg `opacity = 1`.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Config values are small numbers"
+    )]
+    fn as_float(item: &toml_edit::Item) -> Option<f64> {
+        item.as_float()
+            .or_else(|| item.as_integer().map(|integer| integer as f64))
+    }
+
+    /// Format the value of a setting for display.
+    fn display_value(&self, setting: &Setting) -> String {
+        let current = self.value_of(setting.path);
+        match setting.kind {
+            Kind::Toggle => match current.and_then(toml_edit::Item::as_bool) {
+                Some(true) => "on".to_owned(),
+                Some(false) => "off".to_owned(),
+                None => "?".to_owned(),
+            },
+            Kind::Integer { .. } => current
+                .and_then(toml_edit::Item::as_integer)
+                .map_or_else(|| "?".to_owned(), |value| value.to_string()),
+            Kind::Float { .. } => current
+                .and_then(Self::as_float)
+                .map_or_else(|| "?".to_owned(), |value| format!("{value:.2}")),
+        }
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        let panel_height = SETTINGS.len() + 4;
+        if width < PANEL_WIDTH || height < panel_height {
+            tracing::debug!("Terminal too small for the settings panel");
+            return self.tattoy.send_output().await;
+        }
+
+        let left = (width - PANEL_WIDTH).div_euclid(2);
+        let top = (height - panel_height).div_euclid(2);
+
+        let title = " Tattoy settings";
+        self.add_line(left, top, title, BACKGROUND);
+        self.add_line(left, top + 1, "", BACKGROUND);
+        for (index, setting) in SETTINGS.iter().enumerate() {
+            let value = match &self.editing {
+                Some(text) if index == self.selected => format!("[{text}]"),
+                _ => self.display_value(setting),
+            };
+            let padding = PANEL_WIDTH.saturating_sub(setting.label.len() + value.len() + 4);
+            let line = format!("  {}{}{value}", setting.label, " ".repeat(padding));
+            let colour = if index == self.selected {
+                SELECTED
+            } else {
+                BACKGROUND
+            };
+            self.add_line(left, top + 2 + index, &line, colour);
+        }
+        self.add_line(left, top + panel_height - 2, "", BACKGROUND);
+        let help = if self.editing.is_some() {
+            EDITING_HELP
+        } else {
+            HELP
+        };
+        self.add_line(
+            left,
+            top + panel_height - 1,
+            &format!(" {help}"),
+            BACKGROUND,
+        );
+
+        self.tattoy.send_output().await
+    }
+
+    /// Add a full-width line of the panel.
+    fn add_line(&mut self, x: usize, y: usize, text: &str, background: crate::surface::Colour) {
+        let padding = PANEL_WIDTH.saturating_sub(text.chars().count());
+        self.tattoy.surface.add_text(
+            x,
+            y,
+            format!("{text}{}", " ".repeat(padding)),
+            Some(background),
+            Some(crate::surface::WHITE),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn setting_nested_values_preserves_comments() {
+        let mut document: toml_edit::DocumentMut = "
+            # The frame rate
+            frame_rate = 30
+
+            [shader]
+            # How transparent
+            opacity = 0.75
+        "
+        .parse()
+        .unwrap();
+
+        let table = document.as_table_mut();
+        SettingsPanel::set_item(table, &["shader", "opacity"], toml_edit::value(0.5f64));
+        SettingsPanel::set_item(table, &["minimap", "enabled"], toml_edit::value(true));
+
+        let output = document.to_string();
+        assert!(output.contains("# The frame rate"));
+        assert!(output.contains("# How transparent"));
+        let opacity = SettingsPanel::get_item(document.as_item(), &["shader", "opacity"]);
+        assert_eq!(opacity.and_then(toml_edit::Item::as_float), Some(0.5f64));
+        let minimap = SettingsPanel::get_item(document.as_item(), &["minimap", "enabled"]);
+        assert_eq!(minimap.and_then(toml_edit::Item::as_bool), Some(true));
+    }
+
+    #[test]
+    fn setting_values_in_profiles() {
+        let mut document: toml_edit::DocumentMut = "
+            frame_rate = 30
+
+            [profiles.\"battery.saver\"]
+            frame_rate = 10
+        "
+        .parse()
+        .unwrap();
+
+        let parts = SettingsPanel::profile_parts("battery.saver", "shader.enabled");
+        SettingsPanel::set_item(document.as_table_mut(), &parts, toml_edit::value(false));
+
+        let config: toml::Table = toml::from_str(&document.to_string()).unwrap();
+        let profiled = crate::config::profiles::apply(config, Some("battery.saver")).unwrap();
+        assert_eq!(profiled.get("frame_rate"), Some(&toml::Value::Integer(10)));
+        let shader = profiled
+            .get("shader")
+            .and_then(toml::Value::as_table)
+            .unwrap();
+        assert_eq!(shader.get("enabled"), Some(&toml::Value::Boolean(false)));
+    }
+
+    #[test]
+    fn parsing_typed_values() {
+        let integer = Kind::Integer {
+            step: 5,
+            min: 1,
+            max: 120,
+        };
+        let float = Kind::Float {
+            step: 0.05f64,
+            min: 0.0f64,
+            max: 1.0f64,
+        };
+        let parse = |kind, text| {
+            SettingsPanel::parse_typed_value(kind, text).map(|value| value.to_string())
+        };
+
+        assert_eq!(parse(integer, "60"), Some("60".to_owned()));
+        assert_eq!(parse(integer, "500"), Some("120".to_owned()));
+        assert_eq!(parse(integer, "6.5"), None);
+        assert_eq!(parse(float, "0.35"), Some("0.35".to_owned()));
+        assert_eq!(parse(float, "-2"), Some("0.0".to_owned()));
+        assert_eq!(parse(float, ""), None);
+        assert_eq!(parse(Kind::Toggle, "1"), None);
+    }
+
+    #[test]
+    fn all_settings_have_defaults() {
+        let defaults: toml_edit::DocumentMut =
+            crate::config::main::DEFAULT_CONFIG.parse().unwrap();
+        for setting in SETTINGS {
+            let parts = setting.path.split('.').collect::<Vec<&str>>();
+            assert!(
+                SettingsPanel::get_item(defaults.as_item(), &parts).is_some(),
+                "No default for {}",
+                setting.path
+            );
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! An interactive overlay for browsing the shaders in the shader directories. The selected shader
+//! is previewed live behind the list, choosing it makes it the configured shader, and new shaders
+//! can be installed from a URL or a git repo. It's toggled with the `toggle_shader_browser`
+//! keybinding, or `tattoy shader browse`.
+
+use color_eyre::eyre::Result;
+
+/// The compositing layer of the browser. It's above everything apart from the settings panel.
+const LAYER: i16 = 290;
+
+/// The width of the browser in columns.
+const PANEL_WIDTH: usize = 40;
+
+/// Rows of the browser that aren't the list of shaders: the title, the status and the help, along
+/// with the gaps between them.
+const CHROME_HEIGHT: usize = 5;
+
+/// Background colour of the browser.
+const BACKGROUND: crate::surface::Colour = (0.05, 0.05, 0.1, 0.95);
+
+/// Background colour of the currently selected shader.
+const SELECTED: crate::surface::Colour = (0.0, 0.2, 0.63, 1.0);
+
+/// Help text displayed at the bottom of the browser.
+const HELP: &str = "↑↓ preview  ⏎ choose  i install  esc close";
+
+/// Help text displayed at the bottom of the browser whilst a URL is being typed.
+const INSTALL_HELP: &str = "⏎ install  esc cancel";
+
+/// The text at the start of the URL prompt.
+const INSTALL_PROMPT: &str = "url: ";
+
+/// The rows of a list that can be shown in the given height, scrolled so that the selected row is
+/// always visible.
+fn visible_rows(selected: usize, count: usize, height: usize) -> core::ops::Range<usize> {
+    if count <= height {
+        return 0..count;
+    }
+
+    let top = selected
+        .saturating_sub(height.saturating_sub(1))
+        .min(count - height);
+    top..top + height
+}
+
+/// What the keyboard is being used for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    /// Moving through the list of shaders.
+    Browsing,
+    /// Typing the URL of shaders to install.
+    Installing(String),
+}
+
+/// `ShaderBrowser`
+pub(crate) struct ShaderBrowser {
+    /// The base Tattoy struct
+    tattoy: crate::tattoys::tattoyer::Tattoyer,
+    /// Is the browser currently visible?
+    is_open: bool,
+    /// All the shaders in the shader directories, as of when the browser was opened.
+    shaders: Vec<std::path::PathBuf>,
+    /// The index of the currently selected shader.
+    selected: usize,
+    /// What the keyboard is being used for.
+    mode: Mode,
+    /// A message for the user, like the result of installing shaders.
+    status: String,
+}
+
+impl ShaderBrowser {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let tattoy = crate::tattoys::tattoyer::Tattoyer::new(
+            "shader_browser".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            is_open: false,
+            shaders: Vec::new(),
+            selected: 0,
+            mode: Mode::Browsing,
+            status: String::new(),
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut browser = Self::new(output, state).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = protocol.recv() => {
+                    if matches!(result, Ok(crate::run::Protocol::End)) {
+                        break;
+                    }
+                    browser.handle_protocol_message(result).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(
+        &mut self,
+        result: std::result::Result<crate::run::Protocol, tokio::sync::broadcast::error::RecvError>,
+    ) -> Result<()> {
+        match result {
+            Ok(message) => {
+                let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+
+                #[expect(
+                    clippy::wildcard_enum_match_arm,
+                    reason = "We only react to a few messages"
+                )]
+                match &message {
+                    crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleShaderBrowser,
+                    ) => {
+                        if self.is_open {
+                            self.close(false).await?;
+                        } else {
+                            self.open().await?;
+                        }
+                    }
+                    crate::run::Protocol::Input(input) if self.is_open => {
+                        if let termwiz::input::InputEvent::Key(key_event) = &input.event {
+                            self.handle_key(key_event).await?;
+                        }
+                    }
+                    _ => (),
+                }
+
+                self.tattoy.handle_common_protocol_messages(message)?;
+                if is_resize && self.is_open {
+                    self.render().await?;
+                }
+            }
+            Err(error) => tracing::error!("Receiving protocol message: {error:?}"),
+        }
+
+        Ok(())
+    }
+
+    /// Open the browser, with the configured shader selected.
+    async fn open(&mut self) -> Result<()> {
+        let state = std::sync::Arc::clone(&self.tattoy.state);
+        self.shaders = super::main::all_shaders(&state).await?;
+        let shader_config = state.config.load().shader.clone();
+        let configured =
+            crate::config::main::Config::resolve_path(&state, &shader_config.path).await;
+        self.selected = self
+            .shaders
+            .iter()
+            .position(|path| *path == configured)
+            .unwrap_or(0);
+        self.mode = Mode::Browsing;
+        self.status = if shader_config.enabled {
+            String::new()
+        } else {
+            "Shaders are off, ⏎ turns them on".to_owned()
+        };
+
+        self.set_open(true).await;
+        self.render().await
+    }
+
+    /// Close the browser. Unless a shader was chosen, the configured shader is shown again.
+    async fn close(&mut self, is_chosen: bool) -> Result<()> {
+        if !is_chosen {
+            self.tattoy
+                .state
+                .protocol_tx
+                .send(crate::run::Protocol::PreviewShader(None))?;
+        }
+
+        self.set_open(false).await;
+        self.tattoy.send_blank_output().await
+    }
+
+    /// Open or close the browser, which captures all keyboard input whilst it's open.
+    async fn set_open(&mut self, is_open: bool) {
+        self.is_open = is_open;
+        *self.tattoy.state.is_shader_browser_open.write().await = is_open;
+        tracing::debug!("Shader browser open: {is_open}");
+    }
+
+    /// Handle key presses whilst the browser is open.
+    async fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Result<()> {
+        if let Mode::Installing(url) = &mut self.mode {
+            let is_modified = key_event
+                .modifiers
+                .intersects(termwiz::input::Modifiers::CTRL | termwiz::input::Modifiers::ALT);
+
+            #[expect(
+                clippy::wildcard_enum_match_arm,
+                reason = "We only react to a few keys"
+            )]
+            match key_event.key {
+                termwiz::input::KeyCode::Char(character) if !is_modified => url.push(character),
+                termwiz::input::KeyCode::Backspace => {
+                    url.pop();
+                }
+                termwiz::input::KeyCode::Enter => {
+                    let typed = url.clone();
+                    self.install(&typed).await?;
+                }
+                termwiz::input::KeyCode::Escape => self.mode = Mode::Browsing,
+                _ => return Ok(()),
+            }
+
+            return self.render().await;
+        }
+
+        let last = self.shaders.len().saturating_sub(1);
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few keys"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::UpArrow => {
+                self.selected = if self.selected == 0 {
+                    last
+                } else {
+                    self.selected - 1
+                };
+                self.preview()?;
+            }
+            termwiz::input::KeyCode::DownArrow => {
+                self.selected = if self.selected >= last {
+                    0
+                } else {
+                    self.selected + 1
+                };
+                self.preview()?;
+            }
+            termwiz::input::KeyCode::Enter => {
+                self.choose().await?;
+                return Ok(());
+            }
+            termwiz::input::KeyCode::Char('i') => {
+                self.mode = Mode::Installing(String::new());
+                self.status.clear();
+            }
+            termwiz::input::KeyCode::Escape => {
+                self.close(false).await?;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        }
+
+        self.render().await
+    }
+
+    /// Show the selected shader behind the browser.
+    fn preview(&self) -> Result<()> {
+        if let Some(path) = self.shaders.get(self.selected) {
+            self.tattoy
+                .state
+                .protocol_tx
+                .send(crate::run::Protocol::PreviewShader(Some(path.clone())))?;
+        }
+
+        Ok(())
+    }
+
+    /// Make the selected shader the configured shader, turning shaders on if they're off. Like
+    /// cycling shaders, it's remembered between sessions.
+    async fn choose(&mut self) -> Result<()> {
+        let Some(path) = self.shaders.get(self.selected).cloned() else {
+            return Ok(());
+        };
+
+        tracing::info!("Choosing shader from browser: {path:?}");
+        let state = std::sync::Arc::clone(&self.tattoy.state);
+        let path = toml::Value::String(path.display().to_string());
+        crate::runtime_state::override_setting(&state, "shader", "path", path).await;
+        crate::runtime_state::override_setting(&state, "shader", "enabled", true.into()).await;
+        crate::config::main::Config::reload_and_broadcast(&state, "Changed shader").await;
+        self.close(true).await
+    }
+
+    /// Install shaders from a URL, then select the first of them.
+    async fn install(&mut self, url: &str) -> Result<()> {
+        self.status = "Installing...".to_owned();
+        self.render().await?;
+
+        let state = std::sync::Arc::clone(&self.tattoy.state);
+        match super::install::install(&state, url).await {
+            Ok(installed) => {
+                self.shaders = super::main::all_shaders(&state).await?;
+                if let Some(first) = installed.first() {
+                    self.selected = self
+                        .shaders
+                        .iter()
+                        .position(|path| path == first)
+                        .unwrap_or(self.selected);
+                }
+                self.status = format!("Installed {} shader(s)", installed.len());
+                self.mode = Mode::Browsing;
+                self.preview()?;
+            }
+            Err(error) => {
+                tracing::warn!("Couldn't install shaders from {url}: {error:?}");
+                self.status = error.to_string();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        if width < PANEL_WIDTH || height <= CHROME_HEIGHT {
+            tracing::debug!("Terminal too small for the shader browser");
+            return self.tattoy.send_output().await;
+        }
+
+        let list_height = height - CHROME_HEIGHT;
+        self.add_line(0, " Shaders", BACKGROUND);
+        self.add_line(1, "", BACKGROUND);
+        if self.shaders.is_empty() {
+            self.add_line(2, "  No shaders found", BACKGROUND);
+        }
+        let rows = visible_rows(self.selected, self.shaders.len(), list_height);
+        for (y, index) in (2..).zip(rows) {
+            let name = self
+                .shaders
+                .get(index)
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let colour = if index == self.selected {
+                SELECTED
+            } else {
+                BACKGROUND
+            };
+            self.add_line(y, &format!("  {name}"), colour);
+        }
+        for y in (2 + self.shaders.len().max(1))..(height - 3) {
+            self.add_line(y, "", BACKGROUND);
+        }
+
+        let (status, help) = match &self.mode {
+            Mode::Browsing => (format!(" {}", self.status), HELP),
+            Mode::Installing(url) => {
+                let text = format!("{INSTALL_PROMPT}{url}");
+                // Keep the end of long URLs, where the typing is, visible.
+                let skip = text.chars().count().saturating_sub(PANEL_WIDTH - 2);
+                (
+                    format!(" {}", text.chars().skip(skip).collect::<String>()),
+                    INSTALL_HELP,
+                )
+            }
+        };
+        self.add_line(height - 3, "", BACKGROUND);
+        self.add_line(height - 2, &status, BACKGROUND);
+        self.add_line(height - 1, &format!(" {help}"), BACKGROUND);
+
+        self.tattoy.send_output().await
+    }
+
+    /// Add a full-width line of the browser.
+    fn add_line(&mut self, y: usize, text: &str, background: crate::surface::Colour) {
+        let text = crate::utils::truncate_to_width(text, PANEL_WIDTH);
+        let padding = PANEL_WIDTH.saturating_sub(crate::utils::display_width(text));
+        self.tattoy.surface.add_text(
+            0,
+            y,
+            format!("{text}{}", " ".repeat(padding)),
+            Some(background),
+            Some(crate::surface::WHITE),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scrolling_the_list() {
+        assert_eq!(visible_rows(0, 3, 10), 0..3);
+        assert_eq!(visible_rows(2, 10, 4), 0..4);
+        assert_eq!(visible_rows(3, 10, 4), 0..4);
+        assert_eq!(visible_rows(4, 10, 4), 1..5);
+        assert_eq!(visible_rows(9, 10, 4), 6..10);
+    }
+}
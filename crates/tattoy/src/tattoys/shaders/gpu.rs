@@ -27,8 +27,35 @@ pub struct Variables {
     iTime: f32,
     /// The number of rendered shader frames.
     iFrame: u32,
-    /// Padding.
-    _padding2: [u32; 2],
+    /// `1.0` when the user's terminal has a light background, otherwise `0.0`.
+    iLightBackground: f32,
+    /// How far the terminal is scrolled up, in the same units as the y-axis of `iCursor`.
+    iScroll: f32,
+}
+
+/// The rendered image, with `f32` RGBA pixels.
+type Image = image::ImageBuffer<image::Rgba<f32>, Vec<f32>>;
+
+/// The result of asking for a buffer to be mapped into memory.
+type Mapping = tokio::sync::oneshot::Receiver<std::result::Result<(), wgpu::BufferAsyncError>>;
+
+/// A buffer that a rendered frame is copied into, so that it can be read back from the GPU.
+struct Readback {
+    /// The raw data of the render.
+    buffer: wgpu::Buffer,
+    /// Resolves once the buffer is mapped and ready to be read. `None` when the buffer isn't
+    /// waiting to be read.
+    maybe_mapping: Option<Mapping>,
+}
+
+impl Readback {
+    /// Instantiate
+    const fn new(buffer: wgpu::Buffer) -> Self {
+        Self {
+            buffer,
+            maybe_mapping: None,
+        }
+    }
 }
 
 /// Code for talking to the GPU.
@@ -37,6 +64,8 @@ pub(crate) struct GPU<'gpu> {
     pub shader_path: std::path::PathBuf,
     /// The time at which rendering began.
     started: std::time::Instant,
+    /// Whether `iTime` is frozen, so that animated shaders stay still. See `crate::accessibility`.
+    pub is_time_frozen: bool,
 
     /// The `wgpu` device.
     pub device: wgpu::Device,
@@ -55,8 +84,11 @@ pub(crate) struct GPU<'gpu> {
     output_texture_descriptor: wgpu::TextureDescriptor<'gpu>,
     /// The texture on which the final render is placed.
     output_texture: wgpu::Texture,
-    /// The raw data for the final render.
-    output_buffer: wgpu::Buffer,
+    /// Two buffers for reading back renders. Whilst one frame is being read back from the GPU the
+    /// next frame is rendered into the other buffer, so that the GPU is never waited on.
+    readbacks: [Readback; 2],
+    /// The index of the readback buffer that the next frame is rendered into.
+    next_readback: usize,
 
     /// The texture for the contents of the TTY.
     pub ichannel_texture: wgpu::Texture,
@@ -96,10 +128,11 @@ impl GPU<'_> {
         let output_texture_descriptor =
             Self::output_texture_descriptor(width.into(), height.into());
         let output_texture = device.create_texture(&output_texture_descriptor);
-        let output_buffer = device.create_buffer(&Self::output_buffer_descriptor(
-            width.into(),
-            height.into(),
-        )?);
+        let output_buffer_descriptor = Self::output_buffer_descriptor(width.into(), height.into())?;
+        let readbacks = [
+            Readback::new(device.create_buffer(&output_buffer_descriptor)),
+            Readback::new(device.create_buffer(&output_buffer_descriptor)),
+        ];
 
         let variables_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
@@ -114,6 +147,7 @@ impl GPU<'_> {
         let mut gpu = Self {
             shader_path,
             started: std::time::Instant::now(),
+            is_time_frozen: false,
 
             device,
             queue,
@@ -124,7 +158,8 @@ impl GPU<'_> {
 
             output_texture_descriptor,
             output_texture,
-            output_buffer,
+            readbacks,
+            next_readback: 0,
 
             ichannel_texture,
 
@@ -333,20 +368,26 @@ impl GPU<'_> {
         self.output_texture_descriptor =
             Self::output_texture_descriptor(image_size.0.into(), image_size.1.into());
         self.output_texture = self.device.create_texture(&self.output_texture_descriptor);
-        self.output_buffer = self.device.create_buffer(&Self::output_buffer_descriptor(
-            image_size.0.into(),
-            image_size.1.into(),
-        )?);
+        let output_buffer_descriptor =
+            Self::output_buffer_descriptor(image_size.0.into(), image_size.1.into())?;
+        self.readbacks = [
+            Readback::new(self.device.create_buffer(&output_buffer_descriptor)),
+            Readback::new(self.device.create_buffer(&output_buffer_descriptor)),
+        ];
         Ok(())
     }
 
-    /// Update the shader variables with the current elapsed wall time since the render began.
+    /// Update the shader variables with the current elapsed wall time since the render began. It
+    /// doesn't change whilst time is frozen.
     #[expect(
         clippy::as_conversions,
         clippy::cast_precision_loss,
         reason = "The side effects are not serious. The value is only used on the GPU"
     )]
     fn update_wall_time(&mut self) {
+        if self.is_time_frozen {
+            return;
+        }
         self.variables.iTime =
             (self.started.elapsed().as_millis() as f32) / crate::renderer::MILLIS_PER_SECOND;
     }
@@ -372,8 +413,36 @@ impl GPU<'_> {
         self.variables.iCursor = [col.into(), image_height - y];
     }
 
-    /// Tick the render
-    pub async fn render(&mut self) -> Result<image::ImageBuffer<image::Rgba<f32>, Vec<f32>>> {
+    /// Update the `iScroll` variable for the shaders to consume.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "The side effects are not serious. The value is only used on the GPU"
+    )]
+    pub fn update_scroll_position(&mut self, lines: usize) {
+        self.variables.iScroll = (lines * 2) as f32;
+    }
+
+    /// Update the `iLightBackground` variable for the shaders to consume.
+    pub fn update_light_background(&mut self, is_light: bool) {
+        self.variables.iLightBackground = f32::from(u8::from(is_light));
+    }
+
+    /// Tick the render. Frames are read back from the GPU asynchronously, so this returns the
+    /// previous frame, or nothing if the previous frame isn't ready yet.
+    pub async fn render(&mut self) -> Result<Option<Image>> {
+        let index = self.next_readback;
+        if self
+            .readbacks
+            .get(index)
+            .is_some_and(|readback| readback.maybe_mapping.is_some())
+        {
+            // The GPU is more than a frame behind, so rather than queueing even more work, wait for
+            // it to catch up.
+            self.device.poll(wgpu::Maintain::Wait);
+            return self.read(index, true).await;
+        }
+
         self.update_wall_time();
 
         self.queue.write_buffer(
@@ -422,6 +491,10 @@ impl GPU<'_> {
         let image_size = self.get_image_size();
         let aligned_width = Self::align_dimension(image_size.0.into());
         let aligned_height = Self::align_dimension(image_size.1.into());
+        let readback = self
+            .readbacks
+            .get_mut(index)
+            .context("No shader readback buffer")?;
         encoder.copy_texture_to_buffer(
             wgpu::TexelCopyTextureInfo {
                 aspect: wgpu::TextureAspect::All,
@@ -430,7 +503,7 @@ impl GPU<'_> {
                 origin: wgpu::Origin3d::ZERO,
             },
             wgpu::TexelCopyBufferInfo {
-                buffer: &self.output_buffer,
+                buffer: &readback.buffer,
                 layout: wgpu::TexelCopyBufferLayout {
                     offset: 0,
                     bytes_per_row: Some(Self::u32_size()? * aligned_width),
@@ -445,28 +518,61 @@ impl GPU<'_> {
         );
 
         self.queue.submit(Some(encoder.finish()));
-        let image = self.convert_final_render_to_image().await;
-        self.output_buffer.unmap();
 
-        image
-    }
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        readback
+            .buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |gpu_state_result| {
+                if tx.send(gpu_state_result).is_err() {
+                    tracing::trace!("Shader readback buffer was replaced before being read");
+                }
+            });
+        readback.maybe_mapping = Some(rx);
 
-    /// Convert the raw data from the GPU into a iterable image of f32-based true colour pixels.
-    async fn convert_final_render_to_image(
-        &self,
-    ) -> Result<image::ImageBuffer<image::Rgba<f32>, Vec<f32>>> {
-        let buffer_slice = self.output_buffer.slice(..);
+        // Read back the previous frame, whilst the GPU gets on with this one.
+        self.next_readback = 1 - index;
+        self.device.poll(wgpu::Maintain::Poll);
+        self.read(self.next_readback, false).await
+    }
 
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        buffer_slice.map_async(wgpu::MapMode::Read, move |gpu_state_result| {
-            let result = tx.send(gpu_state_result);
-            if let Err(error) = result {
-                tracing::error!("GPU ready state result: {error:?}");
+    /// Read a frame back from one of the readback buffers, so that the buffer can be reused.
+    /// Nothing is returned if the buffer doesn't have a frame, or if it isn't ready yet and
+    /// `is_waiting` isn't set.
+    async fn read(&mut self, index: usize, is_waiting: bool) -> Result<Option<Image>> {
+        let readback = self
+            .readbacks
+            .get_mut(index)
+            .context("No shader readback buffer")?;
+        let Some(mut mapping) = readback.maybe_mapping.take() else {
+            return Ok(None);
+        };
+        let mapped = if is_waiting {
+            mapping.await?
+        } else {
+            match mapping.try_recv() {
+                Ok(mapped) => mapped,
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
+                    readback.maybe_mapping = Some(mapping);
+                    return Ok(None);
+                }
+                Err(error) => return Err(error.into()),
             }
-        });
-        self.device.poll(wgpu::Maintain::Wait);
-        rx.await??;
+        };
+        mapped?;
+
+        let readback = self
+            .readbacks
+            .get(index)
+            .context("No shader readback buffer")?;
+        let image = self.convert_final_render_to_image(&readback.buffer);
+        readback.buffer.unmap();
+        image.map(Some)
+    }
 
+    /// Convert the raw data from the GPU into a iterable image of f32-based true colour pixels.
+    fn convert_final_render_to_image(&self, buffer: &wgpu::Buffer) -> Result<Image> {
+        let buffer_slice = buffer.slice(..);
         let image_size = self.get_image_size();
         let aligned_width = Self::align_dimension(image_size.0.into());
         let aligned_height = Self::align_dimension(image_size.1.into());
@@ -484,7 +590,7 @@ impl GPU<'_> {
     fn extract_rgba32f_image(
         &self,
         imaged: &image::ImageBuffer<image::Rgba<u8>, wgpu::BufferView<'_>>,
-    ) -> image::ImageBuffer<image::Rgba<f32>, Vec<f32>> {
+    ) -> Image {
         let image_size = self.get_image_size();
         image::Rgba32FImage::from_fn(image_size.0.into(), image_size.1.into(), |x, y| {
             if let Some(pixel) = imaged.get_pixel_checked(x, y) {
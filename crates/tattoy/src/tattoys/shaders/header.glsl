@@ -9,6 +9,10 @@ layout(binding = 0) uniform Variables
     vec2 iCursor;
     float iTime;
     int iFrame;
+    // Tattoy-specific: 1.0 when the terminal has a light background, otherwise 0.0.
+    float iLightBackground;
+    // Tattoy-specific: how far the terminal is scrolled up, in the same units as `iCursor.y`.
+    float iScroll;
 };
 
 layout(binding = 1) uniform texture2D iChannelTexture;
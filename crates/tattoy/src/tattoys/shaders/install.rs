@@ -0,0 +1,289 @@
+//! Install shaders from the internet, either a single shader file from a URL, or every shader in
+//! a git repo. They're installed into the `shaders/` directory of Tattoy's data directory. Tattoy
+//! doesn't have its own HTTP client, so downloads are done with `curl` and `git`, which nearly
+//! everyone already has.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+
+/// The file extensions of shader files.
+const SHADER_EXTENSIONS: [&str; 3] = ["glsl", "frag", "fs"];
+
+/// The function that every Shadertoy shader has to define.
+const ENTRYPOINT: &str = "mainImage";
+
+/// Where to install shaders from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    /// A single shader file.
+    File(String),
+    /// A git repo of shaders.
+    Git(String),
+}
+
+impl Source {
+    /// Work out what kind of source a URL is. GitHub links to a file's page are changed to link
+    /// to the file itself.
+    fn parse(url: &str) -> Result<Self> {
+        let url = url.trim();
+        // It would be passed to `git` or `curl` as an option, eg `--upload-pack`.
+        if url.starts_with('-') {
+            color_eyre::eyre::bail!("Not a URL or a git repo: '{url}'");
+        }
+        if url.starts_with("git@") || url.starts_with("git://") || url.ends_with(".git") {
+            return Ok(Self::Git(url.to_owned()));
+        }
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            color_eyre::eyre::bail!("Not a URL or a git repo: '{url}'");
+        }
+
+        if let Some(path) = url.strip_prefix("https://github.com/") {
+            let parts = path.split('/').collect::<Vec<&str>>();
+            return Ok(match parts.as_slice() {
+                [owner, repo, "blob", rest @ ..] => Self::File(format!(
+                    "https://raw.githubusercontent.com/{owner}/{repo}/{}",
+                    rest.join("/")
+                )),
+                [_owner, _repo] => Self::Git(url.to_owned()),
+                _ => Self::File(url.to_owned()),
+            });
+        }
+
+        Ok(Self::File(url.to_owned()))
+    }
+}
+
+/// The filename to save a downloaded shader as, from the last part of its URL.
+fn filename_from_url(url: &str) -> Result<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty() && !name.contains(':'))
+        .context(format!("Couldn't get a filename from '{url}'"))?;
+
+    if is_shader_file(std::path::Path::new(name)) {
+        Ok(name.to_owned())
+    } else {
+        Ok(format!("{name}.glsl"))
+    }
+}
+
+/// Whether a file looks like a shader, from its extension.
+fn is_shader_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|extension| SHADER_EXTENSIONS.contains(&extension))
+}
+
+/// Check that a shader looks like a Shadertoy shader before it's installed. Whether it actually
+/// compiles is only known once Tattoy runs it.
+fn validate(source: &str) -> Result<()> {
+    if !source.contains(ENTRYPOINT) {
+        color_eyre::eyre::bail!(
+            "Doesn't look like a Shadertoy shader, there's no `{ENTRYPOINT}` function"
+        );
+    }
+
+    Ok(())
+}
+
+/// Install the shaders from the given URL. Returns the paths of the newly installed shaders.
+pub(crate) async fn install(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    url: &str,
+) -> Result<Vec<std::path::PathBuf>> {
+    let directory = crate::config::main::Config::data_directory(state)
+        .await
+        .join(crate::config::main::SHADER_DIRECTORY_NAME);
+    tokio::fs::create_dir_all(&directory).await?;
+
+    let download = tempfile::tempdir()?;
+    match Source::parse(url)? {
+        Source::File(file_url) => {
+            let filename = filename_from_url(&file_url)?;
+            let downloaded = download.path().join(&filename);
+            let output = downloaded.display().to_string();
+            run(
+                "curl",
+                &[
+                    "--fail",
+                    "--location",
+                    "--silent",
+                    "--show-error",
+                    "--output",
+                    &output,
+                    &file_url,
+                ],
+            )
+            .await?;
+            let installed = copy_shader(&downloaded, &directory.join(filename))?;
+            Ok(vec![installed])
+        }
+        Source::Git(repo) => {
+            let clone = download.path().join("repo");
+            let destination = clone.display().to_string();
+            run(
+                "git",
+                &[
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--quiet",
+                    "--",
+                    &repo,
+                    &destination,
+                ],
+            )
+            .await?;
+            copy_shaders_from_directory(&clone, &directory)
+        }
+    }
+}
+
+/// Install shaders from the command line, `tattoy shader install <url>`.
+#[expect(
+    clippy::print_stdout,
+    reason = "The installed shaders are for the user to read"
+)]
+pub(crate) async fn install_from_cli(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    url: &str,
+) -> Result<()> {
+    for shader in install(state, url).await? {
+        println!("Installed {}", shader.display());
+    }
+    println!("Choose a shader with the shader browser, `tattoy shader browse`.");
+
+    Ok(())
+}
+
+/// Run a download command, turning its failure into an error.
+async fn run(program: &str, arguments: &[&str]) -> Result<()> {
+    tracing::info!("Downloading shaders with: {program} {arguments:?}");
+    let output = tokio::process::Command::new(program)
+        .args(arguments)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .map_err(|error| color_eyre::eyre::eyre!("Couldn't run `{program}`: {error}"))?;
+    if !output.status.success() {
+        color_eyre::eyre::bail!(
+            "`{program}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Copy a single downloaded shader into the shader directory, checking it first. Existing shaders
+/// aren't overwritten.
+fn copy_shader(from: &std::path::Path, to: &std::path::Path) -> Result<std::path::PathBuf> {
+    let source = std::fs::read_to_string(from)
+        .map_err(|error| color_eyre::eyre::eyre!("Couldn't read the shader: {error}"))?;
+    validate(&source)?;
+    if to.exists() {
+        color_eyre::eyre::bail!("A shader called '{}' already exists", to.display());
+    }
+    std::fs::write(to, source)?;
+
+    Ok(to.to_path_buf())
+}
+
+/// Copy all the shaders in a cloned repo into the shader directory. Files that don't look like
+/// shaders, or that are already installed, are skipped.
+fn copy_shaders_from_directory(
+    repo: &std::path::Path,
+    directory: &std::path::Path,
+) -> Result<Vec<std::path::PathBuf>> {
+    let mut installed = Vec::new();
+    let mut pending = vec![repo.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.file_name().is_some_and(|name| name == ".git") {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !is_shader_file(&path) {
+                continue;
+            }
+            let Some(filename) = path.file_name() else {
+                continue;
+            };
+            match copy_shader(&path, &directory.join(filename)) {
+                Ok(shader) => installed.push(shader),
+                Err(error) => tracing::warn!("Skipping {}: {error}", path.display()),
+            }
+        }
+    }
+
+    if installed.is_empty() {
+        color_eyre::eyre::bail!("No new shaders found in the repo");
+    }
+    installed.sort();
+
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_sources() {
+        assert_eq!(
+            Source::parse("https://example.com/waves.glsl").unwrap(),
+            Source::File("https://example.com/waves.glsl".to_owned())
+        );
+        assert_eq!(
+            Source::parse("https://github.com/me/shaders/blob/main/fx/waves.glsl").unwrap(),
+            Source::File(
+                "https://raw.githubusercontent.com/me/shaders/main/fx/waves.glsl".to_owned()
+            )
+        );
+        assert_eq!(
+            Source::parse("https://github.com/me/shaders").unwrap(),
+            Source::Git("https://github.com/me/shaders".to_owned())
+        );
+        assert_eq!(
+            Source::parse("git@example.com:me/shaders.git").unwrap(),
+            Source::Git("git@example.com:me/shaders.git".to_owned())
+        );
+        assert!(Source::parse("~/shaders/waves.glsl").is_err());
+        assert!(Source::parse("--upload-pack=touch pwned.git").is_err());
+    }
+
+    #[test]
+    fn filenames_from_urls() {
+        let filename = filename_from_url("https://example.com/fx/waves.glsl?raw=true").unwrap();
+        assert_eq!(filename, "waves.glsl");
+        let filename = filename_from_url("https://example.com/shader/abc123").unwrap();
+        assert_eq!(filename, "abc123.glsl");
+        assert!(filename_from_url("https://").is_err());
+    }
+
+    #[test]
+    fn validating_shaders() {
+        assert!(validate("void mainImage(out vec4 colour, in vec2 coord) {}").is_ok());
+        assert!(validate("<html>404</html>").is_err());
+    }
+
+    #[test]
+    fn installing_shaders_from_a_repo() {
+        let repo = tempfile::tempdir().unwrap();
+        let shaders = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("fx")).unwrap();
+        std::fs::write(repo.path().join("fx/waves.glsl"), "void mainImage() {}").unwrap();
+        std::fs::write(repo.path().join("broken.glsl"), "nope").unwrap();
+        std::fs::write(repo.path().join("README.md"), "void mainImage() {}").unwrap();
+
+        let installed = copy_shaders_from_directory(repo.path(), shaders.path()).unwrap();
+        assert_eq!(installed, vec![shaders.path().join("waves.glsl")]);
+        assert!(copy_shaders_from_directory(repo.path(), shaders.path()).is_err());
+    }
+}
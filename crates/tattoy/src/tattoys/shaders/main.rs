@@ -4,6 +4,7 @@
 use color_eyre::eyre::{ContextCompat as _, Result};
 use futures_util::FutureExt as _;
 
+use crate::config::diff::Section;
 use crate::tattoys::tattoyer::Tattoyer;
 
 /// All the user config for the shader tattoy.
@@ -11,7 +12,7 @@ use crate::tattoys::tattoyer::Tattoyer;
     clippy::struct_excessive_bools,
     reason = "We need the bools for the config"
 )]
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the shaders on and off
@@ -33,6 +34,34 @@ pub(crate) struct Config {
     /// position. This would most likely be used in conjunction with auto contrast enabled,
     /// otherwise the text won't actually be readable.
     pub render_shader_colours_to_text: bool,
+    /// Override the global frame rate for the shader. Useful for running heavy shaders at a lower
+    /// frame rate than everything else.
+    pub frame_rate: Option<u32>,
+}
+
+/// All the shaders in the user's shader directories, ordered by filename. Shaders in the config
+/// directory shadow any shaders with the same filename in the data directory.
+pub(crate) async fn all_shaders(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+) -> Result<Vec<std::path::PathBuf>> {
+    let mut all_shaders =
+        std::collections::BTreeMap::<std::ffi::OsString, std::path::PathBuf>::new();
+    for shader_directory in crate::config::main::Config::shader_directories(state).await {
+        if !shader_directory.exists() {
+            continue;
+        }
+        for entry in std::fs::read_dir(shader_directory)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(filename) = path.file_name() {
+                all_shaders.entry(filename.to_owned()).or_insert(path);
+            }
+        }
+    }
+
+    Ok(all_shaders.into_values().collect())
 }
 
 impl Default for Config {
@@ -45,6 +74,7 @@ impl Default for Config {
             render: true,
             upload_tty_as_pixels: true,
             render_shader_colours_to_text: false,
+            frame_rate: None,
         }
     }
 }
@@ -55,6 +85,11 @@ pub(crate) struct Shaders<'shaders> {
     tattoy: Tattoyer,
     /// All the special GPU handling code.
     gpu: super::gpu::GPU<'shaders>,
+    /// The shader path from the config. Kept so that config updates only reset the shader when
+    /// the path itself changes, not when the user has cycled to another shader.
+    configured_shader_path: std::path::PathBuf,
+    /// The shader to change to once the current shader has faded out.
+    maybe_next_shader_path: Option<std::path::PathBuf>,
 }
 
 impl Shaders<'_> {
@@ -63,20 +98,32 @@ impl Shaders<'_> {
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
     ) -> Result<Self> {
-        let shader_directory = state.config_path.read().await.clone();
-        let shader_path = state.config.read().await.shader.path.clone();
+        let shader_path = state.config.load().shader.path.clone();
         let tty_size = *state.tty_size.read().await;
-        let gpu = super::gpu::GPU::new(
-            shader_directory.join(shader_path),
+        let configured_shader_path =
+            crate::config::main::Config::resolve_path(&state, &shader_path).await;
+        let mut gpu = super::gpu::GPU::new(
+            configured_shader_path.clone(),
             tty_size.width,
             tty_size.height * 2,
         )
         .await?;
-        let layer = state.config.read().await.shader.layer;
-        let opacity = state.config.read().await.shader.opacity;
-        let tattoy =
+        let background = state.default_background_colour.get();
+        gpu.update_light_background(crate::palette::converter::Palette::is_light_colour(
+            background,
+        ));
+        gpu.is_time_frozen = state.config.load().accessibility.reduce_motion;
+        let layer = state.config.load().shader.layer;
+        let opacity = state.config.load().shader.opacity;
+        let mut tattoy =
             Tattoyer::new("shader".to_owned(), state, layer, opacity, output_channel).await;
-        Ok(Self { tattoy, gpu })
+        tattoy.is_animated = true;
+        Ok(Self {
+            tattoy,
+            gpu,
+            configured_shader_path,
+            maybe_next_shader_path: None,
+        })
     }
 
     /// Our main entrypoint.
@@ -104,13 +151,7 @@ impl Shaders<'_> {
         });
 
         if let Err(error) = may_panic.catch_unwind().await {
-            let message = if let Some(message) = error.downcast_ref::<String>() {
-                message
-            } else if let Some(message) = error.downcast_ref::<&str>() {
-                message
-            } else {
-                "Caught a panic with an unknown type."
-            };
+            let message = crate::run::panic_message(&*error);
             tracing::error!("Shader panic: {message:?}");
             state
                 .send_notification(
@@ -147,6 +188,17 @@ impl Shaders<'_> {
                     if matches!(result, Ok(crate::run::Protocol::End)) {
                         break;
                     }
+                    if let Ok(message) = &result {
+                        let is_disabled = shaders
+                            .tattoy
+                            .is_disabled_by(message, &Section::Shader, |config| {
+                                config.shader.enabled
+                            })
+                            .await?;
+                        if is_disabled {
+                            break;
+                        }
+                    }
                     shaders.handle_protocol_message(result).await?;
                 }
             }
@@ -177,8 +229,11 @@ impl Shaders<'_> {
                             self.gpu.update_mouse_position(mouse.x, mouse.y);
                         }
                     }
-                    crate::run::Protocol::Config(_) => {
-                        self.upload_tty_as_pixels().await?;
+                    crate::run::Protocol::Config { config, changes } => {
+                        if changes.contains(&Section::Shader) {
+                            self.reconfigure(config).await?;
+                        }
+                        self.gpu.is_time_frozen = config.accessibility.reduce_motion;
                     }
                     crate::run::Protocol::KeybindEvent(event) => {
                         if matches!(event, crate::config::input::KeybindingAction::ShaderPrev) {
@@ -191,9 +246,27 @@ impl Shaders<'_> {
                     crate::run::Protocol::Repaint => {
                         self.upload_tty_as_pixels().await?;
                     }
+                    crate::run::Protocol::Palette(palette) => {
+                        self.gpu.update_light_background(palette.is_light_background());
+                    }
+                    crate::run::Protocol::PreviewShader(maybe_path) => {
+                        self.preview(maybe_path.clone()).await?;
+                    }
                     crate::run::Protocol::End
                     | crate::run::Protocol::CursorVisibility(_)
-                    | crate::run::Protocol::Notification(_) => (),
+                    | crate::run::Protocol::Notification(_)
+                    | crate::run::Protocol::QueryTerminal(_)
+                    | crate::run::Protocol::Bell
+                    | crate::run::Protocol::WorkingDirectory(_)
+                    | crate::run::Protocol::CommandStarted
+                    | crate::run::Protocol::CommandFinished(_)
+                    | crate::run::Protocol::CursorMoved(_)
+                    | crate::run::Protocol::Screensaver(_)
+                    | crate::run::Protocol::ReservedRows(_)
+                    | crate::run::Protocol::Bookmarks(_)
+                    | crate::run::Protocol::ScrollTo(_)
+                    | crate::run::Protocol::RespawnCommand(_)
+                    | crate::run::Protocol::CursorColour(_) => (),
                 }
 
                 self.tattoy.handle_common_protocol_messages(message)?;
@@ -204,6 +277,26 @@ impl Shaders<'_> {
         Ok(())
     }
 
+    /// Apply changes to the shader's config section. The GPU pipeline is only rebuilt when the
+    /// shader file itself has changed.
+    async fn reconfigure(&mut self, config: &crate::config::main::Config) -> Result<()> {
+        let shader_path =
+            crate::config::main::Config::resolve_path(&self.tattoy.state, &config.shader.path)
+                .await;
+        if shader_path != self.configured_shader_path {
+            tracing::info!("Shader path changed in config, loading: {shader_path:?}");
+            self.configured_shader_path.clone_from(&shader_path);
+            // The shader browser will already be showing a shader that has just been chosen.
+            if shader_path != self.showing_or_next_shader_path() {
+                self.transition_to(shader_path).await?;
+            }
+        }
+
+        self.upload_tty_as_pixels().await?;
+
+        Ok(())
+    }
+
     /// Upload the TTY content as coloured pixels.
     async fn upload_tty_as_pixels(&mut self) -> Result<()> {
         let is_upload_tty_as_pixels = self
@@ -239,65 +332,113 @@ impl Shaders<'_> {
         )
     }
 
-    /// Cycle through the shaders in the user's shader directory.
+    /// Cycle through the shaders in the user's shader directories.
     async fn cycle_shader(&mut self, direction: bool) -> Result<()> {
-        let Some(shader_directory) = self.gpu.shader_path.parent() else {
-            color_eyre::eyre::bail!("Unreachable: current shader doesn't have a parent path.");
-        };
-        let Some(current_filename) = self.gpu.shader_path.file_name() else {
+        // Cycling again whilst the previous shader is still fading out carries on from the
+        // shader that's about to be shown.
+        let current_path = self
+            .maybe_next_shader_path
+            .as_ref()
+            .unwrap_or(&self.gpu.shader_path);
+        let Some(current_filename) = current_path.file_name() else {
             color_eyre::eyre::bail!("Unreachable: couldn't get current shader's filename.");
         };
 
-        let mut all_shaders = std::fs::read_dir(shader_directory)?
-            .map(|result| result.map_err(Into::into))
-            .collect::<Result<Vec<std::fs::DirEntry>>>()?
-            .into_iter()
-            .filter_map(|entry| entry.path().is_file().then(|| entry.file_name()))
-            .collect::<Vec<std::ffi::OsString>>();
-        all_shaders.sort();
-
+        let mut ordered_shaders = all_shaders(&self.tattoy.state).await?;
         if !direction {
-            all_shaders.reverse();
+            ordered_shaders.reverse();
         }
 
-        let Some(new_shader_raw) = all_shaders.first() else {
-            color_eyre::eyre::bail!(
-                "Unreachable: current shader's directory doesn't have a shader in it."
-            );
+        let Some(first_shader) = ordered_shaders.first() else {
+            color_eyre::eyre::bail!("Unreachable: no shaders in the shader directories.");
         };
-        let mut new_shader = new_shader_raw.clone();
+        let mut shader_path = first_shader.clone();
         let mut is_current_shader_found = false;
-        for shader_filename in all_shaders {
+        for path in ordered_shaders {
             if is_current_shader_found {
-                new_shader = shader_filename;
+                shader_path = path;
                 break;
             }
+            let shader_filename = path.file_name();
             tracing::debug!("{:?}=={:?}", shader_filename, current_filename);
-            if shader_filename == current_filename {
+            if shader_filename == Some(current_filename) {
                 is_current_shader_found = true;
             }
         }
 
-        let shader_path = shader_directory.join(new_shader.clone());
-        tracing::info!("Changing shader to: {new_shader:?}");
+        tracing::info!("Changing shader to: {shader_path:?}");
+
+        // Remember the shader for next time. It's already loaded, so there's no need to load it
+        // again when the config is next reloaded.
+        let path = toml::Value::String(shader_path.display().to_string());
+        crate::runtime_state::override_setting(&self.tattoy.state, "shader", "path", path).await;
+        self.configured_shader_path.clone_from(&shader_path);
+        self.transition_to(shader_path).await?;
+        self.upload_tty_as_pixels().await?;
+
+        Ok(())
+    }
+
+    /// Temporarily show a shader without changing the config, eg whilst it's selected in the
+    /// shader browser. `None` goes back to the configured shader.
+    async fn preview(&mut self, maybe_path: Option<std::path::PathBuf>) -> Result<()> {
+        let shader_path = maybe_path.unwrap_or_else(|| self.configured_shader_path.clone());
+        if shader_path == self.showing_or_next_shader_path() {
+            return Ok(());
+        }
+
+        tracing::debug!("Previewing shader: {shader_path:?}");
+        self.transition_to(shader_path).await?;
+        self.upload_tty_as_pixels().await
+    }
+
+    /// The shader that's either being shown, or that's about to be shown.
+    fn showing_or_next_shader_path(&self) -> std::path::PathBuf {
+        self.maybe_next_shader_path
+            .clone()
+            .unwrap_or_else(|| self.gpu.shader_path.clone())
+    }
+
+    /// Fade the current shader out, so that the new shader can be faded in once it has gone.
+    async fn transition_to(&mut self, shader_path: std::path::PathBuf) -> Result<()> {
+        self.maybe_next_shader_path = Some(shader_path);
+        self.tattoy.fade_out(self.tattoy.fade_duration());
+        self.maybe_load_next_shader().await
+    }
+
+    /// Load the next shader if the current shader has finished fading out.
+    async fn maybe_load_next_shader(&mut self) -> Result<()> {
+        if self.tattoy.is_fading() {
+            return Ok(());
+        }
+        let Some(shader_path) = self.maybe_next_shader_path.take() else {
+            return Ok(());
+        };
 
         self.gpu.shader_path = shader_path;
         self.gpu.build_pipeline().await?;
-        self.upload_tty_as_pixels().await?;
+        self.tattoy.fade_in(self.tattoy.fade_duration());
 
         Ok(())
     }
 
     /// Tick the render
     async fn render(&mut self) -> Result<()> {
+        self.maybe_load_next_shader().await?;
+
         let cursor = self.tattoy.screen.surface.cursor_position();
         self.gpu
             .update_cursor_position(cursor.0.try_into()?, cursor.1.try_into()?);
+        self.gpu
+            .update_scroll_position(self.tattoy.scrollback.position);
+
+        let Some(image) = self.gpu.render().await? else {
+            return Ok(());
+        };
 
         self.tattoy.initialise_surface();
-        self.tattoy.opacity = self.tattoy.state.config.read().await.shader.opacity;
-        self.tattoy.layer = self.tattoy.state.config.read().await.shader.layer;
-        let image = self.gpu.render().await?;
+        self.tattoy.opacity = self.tattoy.state.config.load().shader.opacity;
+        self.tattoy.layer = self.tattoy.state.config.load().shader.layer;
 
         let tty_height_in_pixels = u32::from(self.tattoy.height) * 2;
         for y in 0..tty_height_in_pixels {
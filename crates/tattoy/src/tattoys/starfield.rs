@@ -0,0 +1,387 @@
+//! A parallax starfield drifting behind the terminal, with an optional faint nebula. Nearer stars
+//! are brighter and move faster than distant ones. It's a cheap alternative to the background
+//! shaders for machines without a capable GPU.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// User-configurable settings for the starfield.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the starfield.
+    pub enabled: bool,
+    /// The number of stars per terminal cell.
+    pub density: f32,
+    /// How fast the nearest stars move, in columns per frame. Negative values move to the right.
+    pub speed: f32,
+    /// How much the stars twinkle, between 0.0 and 1.0.
+    pub twinkle: f32,
+    /// How visible the nebula is, between 0.0 and 1.0. 0.0 disables it.
+    pub nebula: f32,
+    /// The transparency of the starfield.
+    pub opacity: f32,
+    /// The layer of the compositor on which the starfield is rendered.
+    pub layer: i16,
+    /// Override the global frame rate for the starfield.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            density: 0.05,
+            speed: 0.1,
+            twinkle: 0.3,
+            nebula: 0.2,
+            opacity: 0.8,
+            layer: -9,
+            frame_rate: None,
+        }
+    }
+}
+
+/// The colour of the stars.
+const STAR_COLOUR: crate::surface::Colour = (0.9, 0.92, 1.0, 1.0);
+
+/// The 2 colours that the nebula fades between.
+const NEBULA_COLOURS: [crate::surface::Colour; 2] =
+    [(0.45, 0.2, 0.65, 1.0), (0.1, 0.3, 0.65, 1.0)];
+
+/// The range of depths of the stars. 1.0 is the nearest, and depth scales both the speed and the
+/// brightness of a star.
+const DEPTH: core::ops::Range<f32> = 0.15..1.0;
+
+/// The nebula is further away than any of the stars.
+const NEBULA_DEPTH: f32 = 0.05;
+
+/// The range of speeds that stars twinkle at, in radians per frame.
+const TWINKLE_RATE: core::ops::Range<f32> = 0.05..0.25;
+
+/// A single star.
+#[derive(Debug, Clone, PartialEq)]
+struct Star {
+    /// The horizontal position, in columns.
+    x: f32,
+    /// The vertical position, in pixels. There are 2 pixels per terminal row.
+    y: usize,
+    /// How near the star is, see `DEPTH`.
+    depth: f32,
+    /// Where the star is in its twinkle cycle.
+    phase: f32,
+    /// How fast the star twinkles.
+    twinkle_rate: f32,
+}
+
+/// The state of all the stars, independent of any rendering.
+#[derive(Debug, Default)]
+struct Sky {
+    /// All the stars.
+    stars: Vec<Star>,
+    /// The width of the sky in columns.
+    width: usize,
+    /// The height of the sky in pixels.
+    height: usize,
+    /// How far the nebula has drifted, in columns.
+    nebula_offset: f32,
+}
+
+impl Sky {
+    /// Fill a sky of the given size, in columns and pixels, with randomly placed stars.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss,
+        reason = "Terminals are never big enough to lose precision"
+    )]
+    fn new(width: usize, height: usize, density: f32) -> Self {
+        let cells = width * height.div_euclid(2);
+        let count = (cells as f32 * density.max(0.0)).round() as usize;
+        let mut sky = Self {
+            width,
+            height,
+            ..Default::default()
+        };
+        if width == 0 || height == 0 {
+            return sky;
+        }
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..count {
+            let x = rng.gen_range(0.0..width as f32);
+            sky.stars.push(Self::make_star(&mut rng, x, height));
+        }
+        sky
+    }
+
+    /// Make a star at the given column, with a random row, depth and twinkle.
+    fn make_star(rng: &mut rand::rngs::ThreadRng, x: f32, height: usize) -> Star {
+        Star {
+            x,
+            y: rng.gen_range(0..height),
+            depth: rng.gen_range(DEPTH),
+            phase: rng.gen_range(0.0..std::f32::consts::TAU),
+            twinkle_rate: rng.gen_range(TWINKLE_RATE),
+        }
+    }
+
+    /// Move all the stars by one frame. Stars that leave one side of the sky come back in on the
+    /// other side, on a new random row.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Terminals are never big enough to lose precision"
+    )]
+    fn tick(&mut self, speed: f32) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let width = self.width as f32;
+        let mut rng = rand::thread_rng();
+        for star in &mut self.stars {
+            star.x -= speed * star.depth;
+            star.phase = (star.phase + star.twinkle_rate).rem_euclid(std::f32::consts::TAU);
+            if star.x < 0.0 || star.x >= width {
+                let wrapped = star.x.rem_euclid(width);
+                // Tiny negative positions can wrap to exactly the width.
+                let x = if wrapped < width { wrapped } else { 0.0 };
+                *star = Self::make_star(&mut rng, x, self.height);
+            }
+        }
+        self.nebula_offset = speed.mul_add(NEBULA_DEPTH, self.nebula_offset);
+    }
+
+    /// All the pixels of the stars along with their brightness, between 0.0 and 1.0.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Positions are always within the bounds of the terminal"
+    )]
+    fn pixels(&self, twinkle: f32) -> Vec<((usize, usize), f32)> {
+        let twinkle = twinkle.clamp(0.0, 1.0);
+        self.stars
+            .iter()
+            .map(|star| {
+                let flicker = 0.5f32.mul_add(star.phase.sin(), 0.5);
+                let brightness = star.depth * twinkle.mul_add(-flicker, 1.0);
+                ((star.x as usize, star.y), brightness)
+            })
+            .filter(|((x, y), _)| *x < self.width && *y < self.height)
+            .collect()
+    }
+
+    /// The colour of the nebula in the given cell. The shape of the nebula is a few overlapping
+    /// waves, which is much cheaper than proper noise and good enough for some faint wisps.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Terminals are never big enough to lose precision"
+    )]
+    fn nebula(&self, column: usize, row: usize, strength: f32) -> Option<crate::surface::Colour> {
+        if strength <= 0.0 {
+            return None;
+        }
+
+        let x = column as f32 + self.nebula_offset;
+        // Rows are roughly twice as tall as columns are wide.
+        let y = row as f32 * 2.0;
+        let swirl = x.mul_add(0.07, (y * 0.19).sin() * 1.7).sin();
+        let band = y.mul_add(0.13, -(x * 0.05).cos() * 2.3).sin();
+        let sweep = ((x + y) * 0.031).sin();
+        let density = (swirl * band).mul_add(0.5, sweep * 0.5).clamp(0.0, 1.0).powi(2);
+        if density <= 0.0 {
+            return None;
+        }
+
+        let mix = 0.5f32.mul_add(sweep, 0.5);
+        let [first, second] = NEBULA_COLOURS;
+        Some((
+            (second.0 - first.0).mul_add(mix, first.0),
+            (second.1 - first.1).mul_add(mix, first.1),
+            (second.2 - first.2).mul_add(mix, first.2),
+            density * strength.min(1.0),
+        ))
+    }
+}
+
+/// `Starfield`
+pub(crate) struct Starfield {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The stars
+    sky: Sky,
+    /// The starfield's config
+    config: Config,
+}
+
+impl Starfield {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().starfield.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "starfield".to_owned(),
+            state,
+            config.layer,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            sky: Sky::default(),
+            config,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut starfield = Self::new(output, state).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = starfield.tattoy.sleep_until_next_frame_tick() => {
+                    starfield.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = starfield
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::Starfield,
+                            |config| config.starfield.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    starfield.handle_protocol_message(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        if let crate::run::Protocol::Config { config, .. } = &message {
+            if config.starfield != self.config {
+                // Forces the stars to be recreated on the next frame.
+                self.sky = Sky::default();
+            }
+            self.config = config.starfield.clone();
+            self.tattoy.opacity = self.config.opacity;
+            self.tattoy.layer = self.config.layer;
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height) * 2;
+        if (self.sky.width, self.sky.height) != (width, height) {
+            self.sky = Sky::new(width, height, self.config.density);
+        }
+        self.sky.tick(self.config.speed);
+
+        self.tattoy.initialise_surface();
+        for row in 0..usize::from(self.tattoy.height) {
+            for column in 0..width {
+                if let Some(colour) = self.sky.nebula(column, row, self.config.nebula) {
+                    self.tattoy
+                        .surface
+                        .add_text(column, row, " ".to_owned(), Some(colour), None);
+                }
+            }
+        }
+        for ((x, y), brightness) in self.sky.pixels(self.config.twinkle) {
+            let colour = (STAR_COLOUR.0, STAR_COLOUR.1, STAR_COLOUR.2, brightness);
+            self.tattoy.surface.add_pixel(x, y, colour)?;
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn density_sets_the_number_of_stars() {
+        let sky = Sky::new(10, 20, 0.5);
+        assert_eq!(sky.stars.len(), 50);
+        assert!(Sky::new(0, 0, 0.5).stars.is_empty());
+    }
+
+    #[test]
+    fn nearer_stars_move_faster() {
+        let mut sky = Sky::new(100, 10, 0.0);
+        for depth in [0.2, 1.0] {
+            sky.stars.push(Star {
+                x: 50.0,
+                y: 0,
+                depth,
+                phase: 0.0,
+                twinkle_rate: 0.1,
+            });
+        }
+        sky.tick(1.0);
+        let positions: Vec<f32> = sky.stars.iter().map(|star| star.x).collect();
+        assert!((positions.first().copied().unwrap_or(0.0) - 49.8).abs() < 0.001);
+        assert!((positions.get(1).copied().unwrap_or(0.0) - 49.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn stars_wrap_around_the_sky() {
+        let mut sky = Sky::new(10, 10, 1.0);
+        for _ in 0..100 {
+            sky.tick(0.7);
+        }
+        assert_eq!(sky.pixels(0.0).len(), sky.stars.len());
+        assert!(sky
+            .stars
+            .iter()
+            .all(|star| (0.0..10.0).contains(&star.x) && star.y < 10));
+    }
+
+    #[test]
+    fn twinkling_dims_stars() {
+        let mut sky = Sky::new(10, 10, 0.0);
+        sky.stars.push(Star {
+            x: 1.0,
+            y: 1,
+            depth: 1.0,
+            phase: std::f32::consts::FRAC_PI_2,
+            twinkle_rate: 0.1,
+        });
+        let steady = sky.pixels(0.0);
+        let twinkled = sky.pixels(0.5);
+        assert_eq!(steady.first().map(|pixel| pixel.0), Some((1, 1)));
+        assert!(steady
+            .first()
+            .zip(twinkled.first())
+            .is_some_and(|(steady, twinkled)| twinkled.1 < steady.1));
+    }
+}
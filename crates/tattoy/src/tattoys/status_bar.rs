@@ -0,0 +1,422 @@
+//! A tmux-style status bar along the top or bottom of the terminal. The PTY is shrunk by a row so
+//! that the status bar never covers any of the terminal's content.
+//!
+//! The status bar is made of segments, like the clock or the git branch, that are either aligned
+//! to the left or the right of the bar.
+
+use color_eyre::eyre::Result;
+
+/// Which edge of the terminal the status bar is on.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Position {
+    /// The top of the terminal.
+    Top,
+    /// The bottom of the terminal.
+    #[default]
+    Bottom,
+}
+
+/// The pieces of information that can be shown in the status bar.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Segment {
+    /// The title that the running program has given the terminal.
+    Title,
+    /// The shell's current working directory.
+    Directory,
+    /// The git branch and status of the working directory.
+    Git,
+    /// The current time.
+    Clock,
+    /// The first line of output of the user's custom command.
+    Command,
+    /// The battery's charge, and whether effects are reduced to save power.
+    Power,
+}
+
+/// User-configurable settings for the status bar.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the status bar.
+    pub enabled: bool,
+    /// Whether the status bar is at the top or the bottom of the terminal.
+    pub position: Position,
+    /// The segments on the left of the status bar.
+    pub left: Vec<Segment>,
+    /// The segments on the right of the status bar.
+    pub right: Vec<Segment>,
+    /// The format of the clock, using `strftime` syntax, eg `%H:%M`.
+    pub clock_format: String,
+    /// A command, run with `sh -c`, whose output is shown in the `command` segment.
+    pub command: Option<String>,
+    /// How often the command is run, in seconds.
+    pub command_interval: f32,
+    /// The transparency of the status bar.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: Position::Bottom,
+            left: vec![Segment::Title, Segment::Directory, Segment::Git],
+            right: vec![Segment::Command, Segment::Clock],
+            clock_format: "%H:%M".to_owned(),
+            command: None,
+            command_interval: 5.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The compositing layer of the status bar.
+const LAYER: i16 = 170;
+
+/// The palette index of the status bar's background colour.
+const BACKGROUND_PALETTE_INDEX: u8 = 2;
+
+/// The text between segments.
+const SEPARATOR: &str = " │ ";
+
+/// The shortest allowed command interval, so that a misconfiguration can't hammer the system.
+const MINIMUM_COMMAND_INTERVAL: f32 = 1.0;
+
+/// Lay out the left and right segments across a status bar of the given width. The left segments
+/// are truncated if there isn't room for everything.
+fn compose(left: &[String], right: &[String], width: usize) -> String {
+    let join = |segments: &[String]| {
+        segments
+            .iter()
+            .filter(|segment| !segment.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+    };
+
+    // Leave a space at each end of the status bar.
+    let inner_width = width.saturating_sub(2);
+    let right_joined = join(right);
+    let right_text = crate::utils::truncate_to_width(&right_joined, inner_width);
+    let right_length = crate::utils::display_width(right_text);
+    let gap = usize::from(right_length > 0);
+    let room = inner_width.saturating_sub(right_length + gap);
+
+    let mut left_text = join(left);
+    if crate::utils::display_width(&left_text) > room {
+        left_text = crate::utils::truncate_to_width(&left_text, room.saturating_sub(1)).to_owned();
+        if room > 0 {
+            left_text.push('…');
+        }
+    }
+
+    let left_length = crate::utils::display_width(&left_text);
+    let padding = " ".repeat(inner_width.saturating_sub(left_length + right_length));
+    let line = format!(" {left_text}{padding}{right_text} ");
+    crate::utils::truncate_to_width(&line, width).to_owned()
+}
+
+/// `StatusBar`
+pub(crate) struct StatusBar {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The status bar's config
+    config: Config,
+    /// The git status of the working directory, if it's a repository.
+    maybe_git: Option<super::git_status::Status>,
+    /// The first line of the last output of the user's command.
+    command_output: String,
+    /// The terminal title in the last rendered frame.
+    title: String,
+    /// The palette, used to style the status bar.
+    palette: crate::palette::converter::Palette,
+}
+
+impl StatusBar {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().status_bar.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "status_bar".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            maybe_git: None,
+            command_output: String::new(),
+            title: String::new(),
+            palette,
+        }
+    }
+
+    /// The timer for running the user's command.
+    fn command_timer(config: &Config) -> tokio::time::Interval {
+        let seconds = config.command_interval.max(MINIMUM_COMMAND_INTERVAL);
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs_f32(seconds));
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        timer
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut status_bar = Self::new(output, state, palette).await;
+        status_bar.reserve_row().await?;
+        status_bar.refresh_git().await;
+        let mut tick = tokio::time::interval(std::time::Duration::from_secs(1));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut command = Self::command_timer(&status_bar.config);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    status_bar.render().await?;
+                },
+                _ = command.tick(), if status_bar.config.command.is_some() => {
+                    status_bar.run_command().await;
+                    status_bar.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = status_bar
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::StatusBar,
+                            |config| config.status_bar.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        // Give the row back to the PTY.
+                        status_bar
+                            .tattoy
+                            .state
+                            .reserve_rows(crate::shared_state::ReservedRows::default())
+                            .await?;
+                        break;
+                    }
+                    if let crate::run::Protocol::Config { config, .. } = &message {
+                        if config.status_bar != status_bar.config {
+                            command = Self::command_timer(&config.status_bar);
+                        }
+                    }
+                    status_bar.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let mut is_rerender_needed = matches!(
+            message,
+            crate::run::Protocol::Resize { .. } | crate::run::Protocol::ReservedRows(_)
+        );
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::CommandFinished(_)
+            | crate::run::Protocol::WorkingDirectory(_) => {
+                self.refresh_git().await;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.status_bar.clone();
+                self.tattoy.opacity = self.config.opacity;
+                self.reserve_row().await?;
+                self.refresh_git().await;
+                is_rerender_needed = true;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+                is_rerender_needed = true;
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if self.tattoy.screen.surface.title() != self.title {
+            is_rerender_needed = true;
+        }
+        if is_rerender_needed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reserve the row that the status bar is shown in, so that the PTY doesn't use it.
+    async fn reserve_row(&self) -> Result<()> {
+        let reserved_rows = match self.config.position {
+            Position::Top => crate::shared_state::ReservedRows { top: 1, bottom: 0 },
+            Position::Bottom => crate::shared_state::ReservedRows { top: 0, bottom: 1 },
+        };
+        self.tattoy.state.reserve_rows(reserved_rows).await
+    }
+
+    /// Whether a segment is shown anywhere in the status bar.
+    fn has_segment(&self, segment: Segment) -> bool {
+        self.config.left.contains(&segment) || self.config.right.contains(&segment)
+    }
+
+    /// Refresh the git status of the working directory.
+    async fn refresh_git(&mut self) {
+        if !self.has_segment(Segment::Git) {
+            self.maybe_git = None;
+            return;
+        }
+
+        let maybe_directory = self.tattoy.state.working_directory.read().await.clone();
+        self.maybe_git = match maybe_directory {
+            Some(directory) => super::git_status::GitStatus::query_git(&directory).await,
+            None => None,
+        };
+    }
+
+    /// Run the user's command and keep the first line of its output.
+    async fn run_command(&mut self) {
+        let Some(command) = &self.config.command else {
+            return;
+        };
+
+        let result = tokio::process::Command::new("sh")
+            .args(["-c", command])
+            .kill_on_drop(true)
+            .output()
+            .await;
+
+        self.command_output = match result {
+            Ok(output) => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_owned(),
+            Err(error) => {
+                tracing::debug!("Couldn't run status bar command: {error:?}");
+                String::new()
+            }
+        };
+    }
+
+    /// The text of a single segment, which is empty when there's nothing to show.
+    async fn segment_text(&self, segment: Segment) -> String {
+        match segment {
+            Segment::Title => self.title.clone(),
+            Segment::Directory => {
+                let maybe_directory = self.tattoy.state.working_directory.read().await.clone();
+                maybe_directory.map_or_else(String::new, |directory| {
+                    match dirs::home_dir().and_then(|home| directory.strip_prefix(home).ok()) {
+                        Some(relative) => format!("~/{}", relative.display()),
+                        None => directory.display().to_string(),
+                    }
+                })
+            }
+            Segment::Git => self
+                .maybe_git
+                .as_ref()
+                .map_or_else(String::new, |git| git.summary().trim().to_owned()),
+            Segment::Clock => {
+                super::clock::Clock::format_time(&chrono::Local::now(), &self.config.clock_format)
+            }
+            Segment::Command => self.command_output.clone(),
+            Segment::Power => {
+                let maybe_status = *self.tattoy.state.power_status.read().await;
+                let is_saving = *self.tattoy.state.is_power_saving.read().await;
+                crate::power::Status::describe(maybe_status, is_saving)
+            }
+        }
+    }
+
+    /// The texts of a list of segments.
+    async fn segment_texts(&self, segments: &[Segment]) -> Vec<String> {
+        let mut texts = Vec::new();
+        for segment in segments {
+            texts.push(self.segment_text(*segment).await);
+        }
+        texts
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.screen.surface.title().clone_into(&mut self.title);
+
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        if width == 0 || height == 0 {
+            return self.tattoy.send_output().await;
+        }
+
+        let left = self.segment_texts(&self.config.left).await;
+        let right = self.segment_texts(&self.config.right).await;
+        let y = match self.config.position {
+            Position::Top => 0,
+            Position::Bottom => height - 1,
+        };
+        self.tattoy.surface.add_text(
+            0,
+            y,
+            compose(&left, &right, width),
+            Some(crate::surface::colour_from_srgba(
+                self.palette
+                    .true_colour_tuple_from_index(BACKGROUND_PALETTE_INDEX),
+            )),
+            Some(crate::surface::colour_from_srgba(
+                self.palette.default_background_colour(),
+            )),
+        );
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn composing_segments() {
+        let left = vec!["main".to_owned(), String::new(), "~/code".to_owned()];
+        let right = vec!["12:00".to_owned()];
+        assert_eq!(compose(&left, &right, 24), " main │ ~/code    12:00 ");
+        assert_eq!(compose(&[], &right, 8), "  12:00 ");
+    }
+
+    #[test]
+    fn truncating_the_left_segments() {
+        let left = vec!["abcdefghij".to_owned()];
+        let right = vec!["12:00".to_owned()];
+        assert_eq!(compose(&left, &right, 12), " abc… 12:00 ");
+        assert_eq!(compose(&left, &[], 6), " abc… ");
+        assert_eq!(compose(&left, &right, 1), " ");
+        assert_eq!(compose(&["日本語".to_owned()], &[], 6), " 日…  ");
+    }
+}
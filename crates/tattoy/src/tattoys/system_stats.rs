@@ -0,0 +1,485 @@
+//! A small heads-up display of the system's CPU, memory, network and battery usage.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the system stats.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the system stats. When enabled they can be shown and hidden with the
+    /// `toggle_system_stats` keybinding.
+    pub enabled: bool,
+    /// Whether to show the stats as soon as Tattoy starts.
+    pub show_on_start: bool,
+    /// Which corner of the terminal to show the stats in.
+    pub position: crate::surface::Corner,
+    /// How often to refresh the stats, in seconds.
+    pub refresh_interval: f32,
+    /// The transparency of the stats.
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            show_on_start: false,
+            position: crate::surface::Corner::BottomRight,
+            refresh_interval: 1.0,
+            opacity: 0.9,
+        }
+    }
+}
+
+/// The compositing layer of the stats.
+const LAYER: i16 = 150;
+
+/// The width of the stats panel in columns.
+const PANEL_WIDTH: usize = 30;
+
+/// The width of the usage bars in columns.
+const BAR_WIDTH: usize = 12;
+
+/// The shortest allowed refresh interval, in seconds. Sampling CPU usage any quicker than this
+/// isn't meaningful.
+const MINIMUM_REFRESH_INTERVAL: f32 = 0.25;
+
+/// The palette index of the colour used for low usage.
+const LOW_USAGE_PALETTE_INDEX: u8 = 2;
+
+/// The palette index of the colour used for medium usage.
+const MEDIUM_USAGE_PALETTE_INDEX: u8 = 3;
+
+/// The palette index of the colour used for high usage.
+const HIGH_USAGE_PALETTE_INDEX: u8 = 1;
+
+/// Where the Linux kernel lists power supplies, including batteries.
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+/// A single sample of the system's stats.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Stats {
+    /// CPU usage, between 0.0 and 1.0.
+    cpu: f32,
+    /// Memory in use, in bytes.
+    memory_used: u64,
+    /// Total memory, in bytes.
+    memory_total: u64,
+    /// Network download speed, in bytes per second.
+    received_per_second: f64,
+    /// Network upload speed, in bytes per second.
+    transmitted_per_second: f64,
+    /// The battery's charge, between 0.0 and 1.0, if there is a battery.
+    battery: Option<f32>,
+}
+
+/// A single row of the stats panel.
+struct Row {
+    /// The name of the stat, eg `CPU`.
+    label: &'static str,
+    /// How full the usage bar is, and how severe the usage is, both between 0.0 and 1.0.
+    maybe_bar: Option<(f32, f32)>,
+    /// A short textual summary of the stat.
+    summary: String,
+}
+
+/// Collects stats from the OS.
+struct Sampler {
+    /// CPU and memory usage.
+    system: sysinfo::System,
+    /// Network usage.
+    networks: sysinfo::Networks,
+    /// When the stats were last sampled, used to calculate network speeds.
+    last_sampled_at: std::time::Instant,
+}
+
+impl Sampler {
+    /// Instantiate
+    fn new() -> Self {
+        Self {
+            system: sysinfo::System::new(),
+            networks: sysinfo::Networks::new_with_refreshed_list(),
+            last_sampled_at: std::time::Instant::now(),
+        }
+    }
+
+    /// Get the latest stats.
+    fn sample(&mut self) -> Stats {
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        self.networks.refresh(true);
+
+        let seconds = f64::from(
+            self.last_sampled_at
+                .elapsed()
+                .as_secs_f32()
+                .max(MINIMUM_REFRESH_INTERVAL),
+        );
+        self.last_sampled_at = std::time::Instant::now();
+        let (received, transmitted) =
+            self.networks
+                .list()
+                .values()
+                .fold((0u64, 0u64), |(received, transmitted), network| {
+                    (
+                        received.saturating_add(network.received()),
+                        transmitted.saturating_add(network.transmitted()),
+                    )
+                });
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "Network speeds don't need to be exact"
+        )]
+        let (received_per_second, transmitted_per_second) =
+            (received as f64 / seconds, transmitted as f64 / seconds);
+
+        Stats {
+            cpu: (self.system.global_cpu_usage() / 100.0).clamp(0.0, 1.0),
+            memory_used: self.system.used_memory(),
+            memory_total: self.system.total_memory(),
+            received_per_second,
+            transmitted_per_second,
+            battery: Self::battery(),
+        }
+    }
+
+    /// The average charge of all the system's batteries. Currently only supported on Linux.
+    fn battery() -> Option<f32> {
+        let capacities = std::fs::read_dir(POWER_SUPPLY_PATH)
+            .ok()?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let kind = std::fs::read_to_string(path.join("type")).ok()?;
+                if kind.trim() != "Battery" {
+                    return None;
+                }
+                let capacity = std::fs::read_to_string(path.join("capacity")).ok()?;
+                capacity.trim().parse::<f32>().ok()
+            })
+            .collect::<Vec<f32>>();
+        if capacities.is_empty() {
+            return None;
+        }
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "There are never enough batteries to lose precision"
+        )]
+        let count = capacities.len() as f32;
+        Some((capacities.iter().sum::<f32>() / count / 100.0).clamp(0.0, 1.0))
+    }
+}
+
+/// `SystemStats`
+pub(crate) struct SystemStats {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The system stats' config
+    config: Config,
+    /// Collects the stats.
+    sampler: Sampler,
+    /// The most recent stats.
+    stats: Stats,
+    /// Are the stats currently visible?
+    is_visible: bool,
+    /// The palette, used to style the stats.
+    palette: crate::palette::converter::Palette,
+}
+
+impl SystemStats {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().system_stats.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "system_stats".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            is_visible: config.show_on_start,
+            config,
+            sampler: Sampler::new(),
+            stats: Stats::default(),
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut system_stats = Self::new(output, state, palette).await;
+        let mut refresh = system_stats.refresh_timer();
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = refresh.tick(), if system_stats.is_visible => {
+                    system_stats.stats = system_stats.sampler.sample();
+                    system_stats.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = system_stats
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::SystemStats,
+                            |config| config.system_stats.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    let is_refresh_changed = system_stats.handle_protocol_message(message).await?;
+                    if is_refresh_changed {
+                        refresh = system_stats.refresh_timer();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A timer for refreshing the stats.
+    fn refresh_timer(&self) -> tokio::time::Interval {
+        let seconds = self.config.refresh_interval.max(MINIMUM_REFRESH_INTERVAL);
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs_f32(seconds));
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        timer
+    }
+
+    /// Handle messages from the main Tattoy app. Returns whether the refresh interval changed.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<bool> {
+        let mut is_refresh_changed = false;
+        let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::ToggleSystemStats,
+            ) => {
+                self.is_visible = !self.is_visible;
+                tracing::debug!("System stats visible: {}", self.is_visible);
+                if !self.is_visible {
+                    self.tattoy.send_blank_output().await?;
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                is_refresh_changed =
+                    self.config.refresh_interval != config.system_stats.refresh_interval;
+                self.config = config.system_stats.clone();
+                self.tattoy.opacity = self.config.opacity;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_resize && self.is_visible {
+            self.render().await?;
+        }
+
+        Ok(is_refresh_changed)
+    }
+
+    /// A usage bar, eg `██████░░░░░░`.
+    fn bar(fraction: f32) -> String {
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss,
+            reason = "The bar is only a few characters wide"
+        )]
+        let filled = (fraction.clamp(0.0, 1.0) * BAR_WIDTH as f32).round() as usize;
+        format!(
+            "{}{}",
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH.saturating_sub(filled))
+        )
+    }
+
+    /// Format a number of bytes in a human-readable way, eg `1.2G`.
+    fn human_bytes(bytes: f64) -> String {
+        let units = ["B", "K", "M", "G", "T"];
+        let mut value = bytes;
+        let mut unit = units.first().unwrap_or(&"B");
+        for next_unit in units.iter().skip(1) {
+            if value < 1024.0 {
+                break;
+            }
+            value /= 1024.0;
+            unit = next_unit;
+        }
+
+        if value < 10.0 && *unit != "B" {
+            format!("{value:.1}{unit}")
+        } else {
+            format!("{value:.0}{unit}")
+        }
+    }
+
+    /// Pick a colour from the palette depending on how heavy the usage is.
+    const fn usage_palette_index(fraction: f32) -> u8 {
+        if fraction < 0.5 {
+            LOW_USAGE_PALETTE_INDEX
+        } else if fraction < 0.8 {
+            MEDIUM_USAGE_PALETTE_INDEX
+        } else {
+            HIGH_USAGE_PALETTE_INDEX
+        }
+    }
+
+    /// All the rows of the panel.
+    fn rows(&self) -> Vec<Row> {
+        let stats = &self.stats;
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_precision_loss,
+            reason = "Memory usage doesn't need to be exact"
+        )]
+        let (memory_used, memory_total) = (stats.memory_used as f64, stats.memory_total as f64);
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            reason = "Memory usage doesn't need to be exact"
+        )]
+        let memory_fraction = if memory_total > 0.0 {
+            (memory_used / memory_total) as f32
+        } else {
+            0.0
+        };
+
+        let mut rows = vec![
+            Row {
+                label: "CPU",
+                maybe_bar: Some((stats.cpu, stats.cpu)),
+                summary: format!("{:>3.0}%", stats.cpu * 100.0),
+            },
+            Row {
+                label: "RAM",
+                maybe_bar: Some((memory_fraction, memory_fraction)),
+                summary: Self::human_bytes(memory_used),
+            },
+            Row {
+                label: "NET",
+                maybe_bar: None,
+                summary: format!(
+                    "↓{}/s ↑{}/s",
+                    Self::human_bytes(stats.received_per_second),
+                    Self::human_bytes(stats.transmitted_per_second)
+                ),
+            },
+        ];
+        if let Some(battery) = stats.battery {
+            // A low battery is shown in the same colour as high usage.
+            rows.push(Row {
+                label: "BAT",
+                maybe_bar: Some((battery, 1.0 - battery)),
+                summary: format!("{:>3.0}%", battery * 100.0),
+            });
+        }
+        rows
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.initialise_surface();
+
+        let rows = self.rows();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        if width < PANEL_WIDTH || height < rows.len() {
+            tracing::debug!("Terminal too small for the system stats");
+            return self.tattoy.send_output().await;
+        }
+
+        let background =
+            crate::surface::colour_from_srgba(self.palette.default_background_colour());
+        let foreground =
+            crate::surface::colour_from_srgba(self.palette.default_foreground_colour());
+        let (left, top) = self
+            .config
+            .position
+            .origin((width, height), (PANEL_WIDTH, rows.len()));
+
+        for (y, row) in (top..).zip(rows) {
+            let line = format!(" {} ", row.label);
+            let padding = " ".repeat(PANEL_WIDTH);
+            self.tattoy
+                .surface
+                .add_text(left, y, padding, Some(background), Some(foreground));
+            self.tattoy
+                .surface
+                .add_text(left, y, line.clone(), Some(background), Some(foreground));
+
+            let mut x = left + line.chars().count();
+            if let Some((fill, severity)) = row.maybe_bar {
+                let colour = self
+                    .palette
+                    .true_colour_tuple_from_index(Self::usage_palette_index(severity));
+                self.tattoy.surface.add_text(
+                    x,
+                    y,
+                    Self::bar(fill),
+                    Some(background),
+                    Some(crate::surface::colour_from_srgba(colour)),
+                );
+                x += BAR_WIDTH + 1;
+            }
+            self.tattoy
+                .surface
+                .add_text(x, y, row.summary, Some(background), Some(foreground));
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn usage_bars() {
+        assert_eq!(SystemStats::bar(0.0), "░".repeat(BAR_WIDTH));
+        assert_eq!(SystemStats::bar(0.5), format!("{}{}", "█".repeat(6), "░".repeat(6)));
+        assert_eq!(SystemStats::bar(2.0), "█".repeat(BAR_WIDTH));
+    }
+
+    #[test]
+    fn human_readable_bytes() {
+        assert_eq!(SystemStats::human_bytes(512.0), "512B");
+        assert_eq!(SystemStats::human_bytes(1536.0), "1.5K");
+        assert_eq!(SystemStats::human_bytes(8.0 * 1024.0 * 1024.0 * 1024.0), "8.0G");
+        assert_eq!(SystemStats::human_bytes(300.0 * 1024.0 * 1024.0), "300M");
+    }
+}
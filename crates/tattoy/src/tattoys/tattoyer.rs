@@ -2,6 +2,51 @@
 
 use color_eyre::eyre::{ContextCompat as _, Result};
 
+/// A tween of a layer's opacity, so that layers fade in and out rather than popping in and out
+/// abruptly.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fade {
+    /// The multiplier of the layer's opacity at the start of the fade.
+    pub from: f32,
+    /// The multiplier of the layer's opacity at the end of the fade.
+    pub to: f32,
+    /// When the fade started.
+    pub started_at: tokio::time::Instant,
+    /// How long the fade takes.
+    pub duration: std::time::Duration,
+}
+
+impl Fade {
+    /// Start a fade now.
+    pub(crate) fn new(from: f32, to: f32, duration: std::time::Duration) -> Self {
+        Self {
+            from,
+            to,
+            started_at: tokio::time::Instant::now(),
+            duration,
+        }
+    }
+
+    /// The eased multiplier of the layer's opacity once the given time has passed.
+    pub(crate) fn multiplier_after(&self, elapsed: std::time::Duration) -> f32 {
+        if self.duration.is_zero() {
+            return self.to;
+        }
+        let progress = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        (self.to - self.from).mul_add(crate::utils::smoothstep(0.0, 1.0, progress), self.from)
+    }
+
+    /// The eased multiplier of the layer's opacity right now.
+    pub(crate) fn multiplier(&self) -> f32 {
+        self.multiplier_after(self.started_at.elapsed())
+    }
+
+    /// Whether the fade has reached its end.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.started_at.elapsed() >= self.duration
+    }
+}
+
 /// Shared state and behaviour useful to all tattoys.
 pub(crate) struct Tattoyer {
     /// A unique identifier.
@@ -30,6 +75,15 @@ pub(crate) struct Tattoyer {
     pub last_frame_tick: tokio::time::Instant,
     /// The last known position of an active scroll.
     pub last_scroll_position: usize,
+    /// The cursor colour that an application set in the PTY, if any.
+    pub cursor_colour: Option<termwiz::color::SrgbaTuple>,
+    /// The current fade of the layer's opacity, if it has ever been faded.
+    pub fade: Option<Fade>,
+    /// Whether the tattoy sends a frame on every frame tick, like shaders do. Otherwise fades are
+    /// finished by re-sending the tattoy's latest frame.
+    pub is_animated: bool,
+    /// Whether the tattoy has sent any frame yet. Layers fade in when they first appear.
+    pub has_sent_output: bool,
 }
 
 impl Tattoyer {
@@ -42,6 +96,8 @@ impl Tattoyer {
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
     ) -> Self {
         let tty_size = state.get_tty_size().await;
+        let target_frame_rate = Self::frame_rate_from_config(&id, &*state.config.load());
+        let cursor_colour = *state.cursor_colour.read().await;
         Self {
             id: id.clone(),
             layer,
@@ -53,9 +109,13 @@ impl Tattoyer {
             height: tty_size.height,
             scrollback: shadow_terminal::output::CompleteScrollback::default(),
             screen: shadow_terminal::output::CompleteScreen::default(),
-            target_frame_rate: 30,
+            target_frame_rate,
             last_frame_tick: tokio::time::Instant::now(),
             last_scroll_position: 0,
+            cursor_colour,
+            fade: None,
+            is_animated: false,
+            has_sent_output: false,
         }
     }
 
@@ -95,13 +155,35 @@ impl Tattoyer {
                 self.set_tty_size(width, height);
             }
             crate::run::Protocol::Output(output) => self.handle_pty_output(output)?,
-            crate::run::Protocol::Config(config) => self.target_frame_rate = config.frame_rate,
+            crate::run::Protocol::Config { config, .. } => {
+                self.target_frame_rate = Self::frame_rate_from_config(&self.id, &config);
+            }
+            crate::run::Protocol::CursorColour(colour) => self.cursor_colour = colour,
             _ => (),
         }
 
         Ok(())
     }
 
+    /// The frame rate for the given tattoy. Tattoys can override the global frame rate in their own
+    /// config section, eg `minimap.frame_rate`.
+    fn frame_rate_from_config(id: &str, config: &crate::config::main::Config) -> u32 {
+        let maybe_override = match id {
+            "minimap" => config.minimap.frame_rate,
+            "shader" => config.shader.frame_rate,
+            "bg_command" => config.bg_command.frame_rate,
+            "notifications" => config.notifications.frame_rate,
+            "weather" => config.weather.frame_rate,
+            "visual_bell" => config.visual_bell.frame_rate,
+            "audio_visualiser" => config.audio_visualiser.frame_rate,
+            "typing_sparks" => config.typing_sparks.frame_rate,
+            "cursor_smear" => config.cursor_smear.frame_rate,
+            "starfield" => config.starfield.frame_rate,
+            _ => None,
+        };
+        maybe_override.unwrap_or(config.frame_rate).max(1)
+    }
+
     /// Whether the user is scolling.
     pub const fn is_scrolling(&self) -> bool {
         self.scrollback.position != 0
@@ -112,6 +194,26 @@ impl Tattoyer {
         self.last_scroll_position != 0 && !self.is_scrolling()
     }
 
+    /// The colour of the PTY's cursor: the colour an application set, otherwise the given default.
+    pub fn cursor_colour_or(&self, default: termwiz::color::SrgbaTuple) -> crate::surface::Colour {
+        crate::surface::colour_from_srgba(self.cursor_colour.unwrap_or(default))
+    }
+
+    /// Test a mouse event against the tattoy's latest frame, so that overlays know whether the
+    /// mouse is over something that they've drawn. Messages that aren't mouse events never hit.
+    pub fn hit_test(
+        &self,
+        message: &crate::run::Protocol,
+    ) -> Option<crate::tattoys::hit_test::Hit> {
+        let crate::run::Protocol::Input(input) = message else {
+            return None;
+        };
+        let termwiz::input::InputEvent::Mouse(mouse) = &input.event else {
+            return None;
+        };
+        crate::tattoys::hit_test::hit(&self.surface, mouse)
+    }
+
     /// Is the underlying terminal in the alternate screen.
     pub const fn is_alternate_screen(&self) -> bool {
         matches!(
@@ -160,12 +262,29 @@ impl Tattoyer {
 
     /// Send the final surface to the main renderer.
     pub(crate) async fn send_output(&mut self) -> Result<()> {
+        if !self.has_sent_output {
+            self.has_sent_output = true;
+            self.fade_in(self.fade_duration());
+        }
+
+        self.send_faded_surface().await?;
+        self.last_scroll_position = self.scrollback.position;
+
+        // Tattoys that only render when something changes would otherwise be left half faded.
+        if !self.is_animated {
+            self.finish_fade().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send the surface at the layer's current faded opacity.
+    async fn send_faded_surface(&mut self) -> Result<()> {
+        self.surface.opacity = self.faded_opacity();
         self.output_channel
             .send(crate::run::FrameUpdate::TattoySurface(self.surface.clone()))
             .await?;
 
-        self.last_scroll_position = self.scrollback.position;
-
         Ok(())
     }
 
@@ -174,7 +293,93 @@ impl Tattoyer {
         self.initialise_surface();
         self.surface.width = 0;
         self.surface.height = 0;
-        self.send_output().await
+        self.send_faded_surface().await?;
+        self.last_scroll_position = self.scrollback.position;
+
+        Ok(())
+    }
+
+    /// How long fades take, from the `fade_duration` config setting.
+    pub fn fade_duration(&self) -> std::time::Duration {
+        std::time::Duration::try_from_secs_f32(self.state.config.load().fade_duration)
+            .unwrap_or_default()
+    }
+
+    /// Fade the layer in to its full opacity. A fade that's already happening is continued from
+    /// wherever it has got to.
+    pub fn fade_in(&mut self, duration: std::time::Duration) {
+        self.fade_to(1.0, duration);
+    }
+
+    /// Fade the layer out until it's transparent. A fade that's already happening is continued
+    /// from wherever it has got to.
+    pub fn fade_out(&mut self, duration: std::time::Duration) {
+        self.fade_to(0.0, duration);
+    }
+
+    /// Start fading the layer's opacity multiplier to the given value.
+    fn fade_to(&mut self, to: f32, duration: std::time::Duration) {
+        let from = self.fade.map_or(1.0 - to, |fade| fade.multiplier());
+        self.fade = Some(Fade::new(from, to, duration));
+    }
+
+    /// Whether the layer is in the middle of a fade.
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some_and(|fade| !fade.is_finished())
+    }
+
+    /// The layer's opacity, including any fade.
+    pub fn faded_opacity(&self) -> f32 {
+        self.opacity * self.fade.map_or(1.0, |fade| fade.multiplier())
+    }
+
+    /// Keep re-sending the latest frame until the current fade has finished.
+    pub(crate) async fn finish_fade(&mut self) -> Result<()> {
+        while self.is_fading() && self.surface.width != 0 {
+            self.sleep_until_next_frame_tick().await;
+            self.send_faded_surface().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fade the latest frame out, then remove the layer from the screen.
+    async fn fade_out_and_clear(&mut self) -> Result<()> {
+        self.fade_out(self.fade_duration());
+        self.finish_fade().await?;
+        self.send_blank_output().await
+    }
+
+    /// Check if a config update has just disabled this tattoy, or if the terminal has just woken
+    /// from a screensaver that this tattoy was only started for. If so, then the tattoy's layer is
+    /// removed from the screen and the caller should exit.
+    pub(crate) async fn is_disabled_by(
+        &mut self,
+        message: &crate::run::Protocol,
+        section: &crate::config::diff::Section,
+        is_enabled: fn(&crate::config::main::Config) -> bool,
+    ) -> Result<bool> {
+        if matches!(message, crate::run::Protocol::Screensaver(false)) {
+            let mut maybe_screensaver_tattoy = self.state.screensaver_tattoy.write().await;
+            if maybe_screensaver_tattoy.as_deref() == Some(self.id.as_str()) {
+                *maybe_screensaver_tattoy = None;
+                drop(maybe_screensaver_tattoy);
+                tracing::info!(
+                    "'{}' tattoy was only running for the screensaver, stopping",
+                    self.id
+                );
+                self.fade_out_and_clear().await?;
+                return Ok(true);
+            }
+        }
+
+        if !crate::config::diff::is_section_disabled(message, section, is_enabled) {
+            return Ok(false);
+        }
+
+        tracing::info!("'{}' tattoy disabled by config update, stopping", self.id);
+        self.fade_out_and_clear().await?;
+        Ok(true)
     }
 
     /// Sleep until the next frame render is due.
@@ -325,3 +530,38 @@ impl Tattoyer {
         Ok(image)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn per_tattoy_frame_rates() {
+        let mut config = crate::config::main::Config {
+            frame_rate: 60,
+            ..Default::default()
+        };
+        config.shader.frame_rate = Some(15);
+        assert_eq!(Tattoyer::frame_rate_from_config("shader", &config), 15);
+        assert_eq!(Tattoyer::frame_rate_from_config("minimap", &config), 60);
+        assert_eq!(Tattoyer::frame_rate_from_config("random_walker", &config), 60);
+    }
+
+    #[test]
+    fn fading_opacity() {
+        let millis = std::time::Duration::from_millis;
+        let fade_in = Fade::new(0.0, 1.0, millis(200));
+        assert!(fade_in.multiplier_after(millis(0)).abs() < f32::EPSILON);
+        assert!((fade_in.multiplier_after(millis(100)) - 0.5).abs() < f32::EPSILON);
+        assert!((fade_in.multiplier_after(millis(500)) - 1.0).abs() < f32::EPSILON);
+        assert!(!fade_in.is_finished());
+
+        let fade_out = Fade::new(0.8, 0.0, millis(200));
+        assert!((fade_out.multiplier_after(millis(0)) - 0.8).abs() < f32::EPSILON);
+        assert!(fade_out.multiplier_after(millis(50)) > fade_out.multiplier_after(millis(150)));
+
+        let instant = Fade::new(0.0, 1.0, millis(0));
+        assert!((instant.multiplier() - 1.0).abs() < f32::EPSILON);
+        assert!(instant.is_finished());
+    }
+}
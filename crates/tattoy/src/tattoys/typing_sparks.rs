@@ -0,0 +1,417 @@
+//! Sparks that fly out of the cursor as you type. The faster you type, the more sparks there are.
+//! There's also an optional combo counter of how many keys have been typed without pausing.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// User-configurable settings for the typing sparks.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the typing sparks.
+    pub enabled: bool,
+    /// How many sparks each keystroke makes when typing slowly.
+    pub sparks_per_key: u8,
+    /// The most that fast typing can multiply the number of sparks by.
+    pub max_intensity: f32,
+    /// How long each spark lives for, in seconds.
+    pub lifetime: f32,
+    /// Show a counter of how many keys have been typed without pausing.
+    pub show_combo: bool,
+    /// Which corner of the terminal to show the combo counter in.
+    pub combo_position: crate::surface::Corner,
+    /// The transparency of the sparks.
+    pub opacity: f32,
+    /// Override the global frame rate for the sparks.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sparks_per_key: 4,
+            max_intensity: 3.0,
+            lifetime: 0.5,
+            show_combo: false,
+            combo_position: crate::surface::Corner::TopRight,
+            opacity: 0.9,
+            frame_rate: None,
+        }
+    }
+}
+
+/// The compositing layer of the sparks. Above the terminal's text.
+const LAYER: i16 = 20;
+
+/// How far back to look when measuring typing speed.
+const TYPING_SPEED_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The typing speed, in keys per second, at which sparks reach their maximum intensity.
+const FULL_INTENSITY_TYPING_SPEED: f32 = 8.0;
+
+/// How long a pause in typing breaks a combo.
+const COMBO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The range of initial speeds of sparks, in pixels per second.
+const SPARK_SPEED: core::ops::Range<f32> = 10.0..30.0;
+
+/// How quickly sparks fall, in pixels per second per second.
+const GRAVITY: f32 = 60.0;
+
+/// The palette indexes of the colours that sparks can be.
+const SPARK_PALETTE_INDEXES: [u8; 4] = [1, 3, 9, 11];
+
+/// The palette index of the combo counter's colour.
+const COMBO_PALETTE_INDEX: u8 = 3;
+
+/// A single spark.
+#[derive(Debug, Clone, PartialEq)]
+struct Spark {
+    /// The horizontal position, in pixels.
+    x: f32,
+    /// The vertical position, in pixels. There are 2 pixels per terminal row.
+    y: f32,
+    /// The horizontal velocity, in pixels per second.
+    velocity_x: f32,
+    /// The vertical velocity, in pixels per second.
+    velocity_y: f32,
+    /// How long the spark has been alive, in seconds.
+    age: f32,
+    /// The palette index of the spark's colour.
+    palette_index: u8,
+}
+
+/// The state of the sparks and typing, independent of any rendering.
+#[derive(Debug, Default)]
+struct Sparks {
+    /// All the sparks that are still alive.
+    sparks: Vec<Spark>,
+    /// When recent keys were pressed, for measuring typing speed.
+    keystrokes: std::collections::VecDeque<std::time::Instant>,
+    /// How many keys have been typed without pausing.
+    combo: usize,
+}
+
+impl Sparks {
+    /// Record a keystroke.
+    fn keystroke(&mut self, now: std::time::Instant) {
+        let is_combo_broken = self
+            .keystrokes
+            .back()
+            .is_none_or(|previous| now.saturating_duration_since(*previous) > COMBO_TIMEOUT);
+        self.combo = if is_combo_broken { 1 } else { self.combo + 1 };
+
+        self.keystrokes.push_back(now);
+        while self
+            .keystrokes
+            .front()
+            .is_some_and(|oldest| now.saturating_duration_since(*oldest) > TYPING_SPEED_WINDOW)
+        {
+            self.keystrokes.pop_front();
+        }
+    }
+
+    /// The current typing speed, in keys per second.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "There are only ever a few recent keystrokes"
+    )]
+    fn typing_speed(&self) -> f32 {
+        self.keystrokes.len() as f32 / TYPING_SPEED_WINDOW.as_secs_f32()
+    }
+
+    /// How many sparks a keystroke should make at the current typing speed.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "The number of sparks is always small and positive"
+    )]
+    fn spark_count(&self, sparks_per_key: u8, max_intensity: f32) -> usize {
+        let speed = (self.typing_speed() / FULL_INTENSITY_TYPING_SPEED).min(1.0);
+        let intensity = (max_intensity.max(1.0) - 1.0).mul_add(speed, 1.0);
+        (f32::from(sparks_per_key) * intensity).round() as usize
+    }
+
+    /// Throw out sparks from the given pixel.
+    fn emit(&mut self, x: f32, y: f32, count: usize) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..count {
+            let angle = rng.gen_range(0.0..core::f32::consts::TAU);
+            let speed = rng.gen_range(SPARK_SPEED);
+            let palette_index = SPARK_PALETTE_INDEXES
+                .get(rng.gen_range(0..SPARK_PALETTE_INDEXES.len()))
+                .copied()
+                .unwrap_or(1);
+            self.sparks.push(Spark {
+                x,
+                y,
+                velocity_x: angle.cos() * speed,
+                // Sparks mostly fly upwards.
+                velocity_y: -angle.sin().abs() * speed,
+                age: 0.0,
+                palette_index,
+            });
+        }
+    }
+
+    /// Move all the sparks on by the given number of seconds, removing those that have died.
+    fn tick(&mut self, elapsed: f32, lifetime: f32) {
+        for spark in &mut self.sparks {
+            spark.x = spark.velocity_x.mul_add(elapsed, spark.x);
+            spark.y = spark.velocity_y.mul_add(elapsed, spark.y);
+            spark.velocity_y = GRAVITY.mul_add(elapsed, spark.velocity_y);
+            spark.age += elapsed;
+        }
+        self.sparks.retain(|spark| spark.age < lifetime);
+    }
+
+    /// Whether the combo has timed out.
+    fn is_combo_over(&self, now: std::time::Instant) -> bool {
+        self.keystrokes
+            .back()
+            .is_none_or(|previous| now.saturating_duration_since(*previous) > COMBO_TIMEOUT)
+    }
+}
+
+/// `TypingSparks`
+pub(crate) struct TypingSparks {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The typing sparks' config
+    config: Config,
+    /// The sparks
+    sparks: Sparks,
+    /// When the sparks were last moved.
+    last_tick: std::time::Instant,
+    /// Whether anything was drawn on the last frame, so we know to clear it.
+    is_drawn: bool,
+    /// The palette, used to colour the sparks.
+    palette: crate::palette::converter::Palette,
+}
+
+impl TypingSparks {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let config = state.config.load().typing_sparks.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "typing_sparks".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            sparks: Sparks::default(),
+            last_tick: std::time::Instant::now(),
+            is_drawn: false,
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut typing_sparks = Self::new(output, state, palette).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = typing_sparks.tattoy.sleep_until_next_frame_tick(),
+                    if typing_sparks.is_drawn => {
+                    typing_sparks.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = typing_sparks
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::TypingSparks,
+                            |config| config.typing_sparks.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    typing_sparks.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::Input(input) => {
+                if matches!(input.event, termwiz::input::InputEvent::Key(_)) {
+                    self.keystroke();
+                    self.render().await?;
+                }
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.typing_sparks.clone();
+                self.tattoy.opacity = self.config.opacity;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette.clone();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)
+    }
+
+    /// Throw out sparks from the cursor.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Terminals are never big enough to lose precision"
+    )]
+    fn keystroke(&mut self) {
+        let now = std::time::Instant::now();
+        if self.sparks.sparks.is_empty() {
+            // Don't move sparks by however long it's been since the last keystroke.
+            self.last_tick = now;
+        }
+
+        self.sparks.keystroke(now);
+        let count = self
+            .sparks
+            .spark_count(self.config.sparks_per_key, self.config.max_intensity);
+        let (cursor_x, cursor_y) = self.tattoy.screen.surface.cursor_position();
+        let x = cursor_x as f32 + 0.5;
+        let y = (cursor_y * 2) as f32 + 1.0;
+        self.sparks.emit(x, y, count);
+    }
+
+    /// Tick the render
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Sparks outside the terminal are skipped"
+    )]
+    async fn render(&mut self) -> Result<()> {
+        let now = std::time::Instant::now();
+        let elapsed = now.saturating_duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
+        self.sparks.tick(elapsed, self.config.lifetime);
+
+        let is_combo_shown = self.config.show_combo && !self.sparks.is_combo_over(now);
+        if self.sparks.sparks.is_empty() && !is_combo_shown {
+            self.is_drawn = false;
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        let lifetime = self.config.lifetime.max(f32::EPSILON);
+        for spark in &self.sparks.sparks {
+            if spark.x < 0.0 || spark.y < 0.0 {
+                continue;
+            }
+            let (x, y) = (spark.x as usize, spark.y as usize);
+            if x >= width || y >= height * 2 {
+                continue;
+            }
+            let colour = self.palette.true_colour_tuple_from_index(spark.palette_index);
+            let fade = 1.0 - spark.age / lifetime;
+            let (red, green, blue, alpha) = crate::surface::colour_from_srgba(colour);
+            self.tattoy
+                .surface
+                .add_pixel(x, y, (red, green, blue, alpha * fade))?;
+        }
+
+        if is_combo_shown {
+            let text = format!(" combo x{} ", self.sparks.combo);
+            let panel = (text.chars().count(), 1);
+            if width >= panel.0 && height >= panel.1 {
+                let (x, y) = self.config.combo_position.origin((width, height), panel);
+                self.tattoy.surface.add_text(
+                    x,
+                    y,
+                    text,
+                    None,
+                    Some(crate::surface::colour_from_srgba(
+                        self.palette
+                            .true_colour_tuple_from_index(COMBO_PALETTE_INDEX),
+                    )),
+                );
+            }
+        }
+
+        self.is_drawn = true;
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn faster_typing_makes_more_sparks() {
+        let mut sparks = Sparks::default();
+        let start = std::time::Instant::now();
+        sparks.keystroke(start);
+        assert_eq!(sparks.spark_count(4, 3.0), 5);
+
+        for millis in 1..16 {
+            sparks.keystroke(start + std::time::Duration::from_millis(millis * 100));
+        }
+        assert_eq!(sparks.spark_count(4, 3.0), 12);
+        assert_eq!(sparks.spark_count(4, 0.5), 4);
+    }
+
+    #[test]
+    fn pausing_breaks_the_combo() {
+        let mut sparks = Sparks::default();
+        let start = std::time::Instant::now();
+        sparks.keystroke(start);
+        sparks.keystroke(start + std::time::Duration::from_millis(500));
+        assert_eq!(sparks.combo, 2);
+
+        let later = start + std::time::Duration::from_secs(3);
+        assert!(sparks.is_combo_over(later));
+        sparks.keystroke(later);
+        assert_eq!(sparks.combo, 1);
+    }
+
+    #[test]
+    fn sparks_die_of_old_age() {
+        let mut sparks = Sparks::default();
+        sparks.emit(10.0, 10.0, 3);
+        sparks.tick(0.1, 0.5);
+        assert_eq!(sparks.sparks.len(), 3);
+        assert!(sparks.sparks.iter().all(|spark| spark.y < 10.0 + 1.0));
+        sparks.tick(0.5, 0.5);
+        assert!(sparks.sparks.is_empty());
+    }
+}
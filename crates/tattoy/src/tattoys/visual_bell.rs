@@ -0,0 +1,205 @@
+//! A visual substitute for the terminal's bell. The bell rung by applications running inside
+//! Tattoy never reaches the user's terminal, so instead the screen's border, or the whole screen,
+//! briefly flashes.
+
+use color_eyre::eyre::Result;
+
+/// How the bell is shown.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Style {
+    /// Flash a line around the edge of the terminal.
+    #[default]
+    Border,
+    /// Flash the whole terminal.
+    Flash,
+}
+
+/// User-configurable settings for the visual bell.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the visual bell.
+    pub enabled: bool,
+    /// Whether to flash the border or the whole screen.
+    pub style: Style,
+    /// The colour of the flash, as RGBA values between 0.0 and 1.0.
+    pub color: crate::surface::Colour,
+    /// How long the flash takes to fade away, in seconds.
+    pub duration: f32,
+    /// Override the global frame rate for the fade animation.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            style: Style::Border,
+            color: (1.0, 1.0, 1.0, 0.5),
+            duration: 0.3,
+            frame_rate: None,
+        }
+    }
+}
+
+/// The compositing layer of the bell. It should be above most other tattoys, but below UI like
+/// the settings panel.
+const LAYER: i16 = 250;
+
+/// `VisualBell`
+pub(crate) struct VisualBell {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The visual bell's config
+    config: Config,
+    /// When the bell was last rung, if it's still being shown.
+    rung_at: Option<tokio::time::Instant>,
+}
+
+impl VisualBell {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().visual_bell.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "visual_bell".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+
+        Self {
+            tattoy,
+            config,
+            rung_at: None,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut bell = Self::new(output, state).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = bell.tattoy.sleep_until_next_frame_tick(), if bell.rung_at.is_some() => {
+                    bell.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = bell
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::VisualBell,
+                            |config| config.visual_bell.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    bell.handle_protocol_message(message).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only react to a few messages"
+        )]
+        match &message {
+            crate::run::Protocol::Bell => {
+                tracing::debug!("Showing visual bell");
+                self.rung_at = Some(tokio::time::Instant::now());
+                self.render().await?;
+            }
+            crate::run::Protocol::Config { config, .. } => {
+                self.config = config.visual_bell.clone();
+            }
+            _ => (),
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)
+    }
+
+    /// How strongly the bell should be shown, fading from 1.0 when the bell is rung to 0.0 at the
+    /// end of the configured duration.
+    fn intensity(&self) -> f32 {
+        let Some(rung_at) = self.rung_at else {
+            return 0.0;
+        };
+        Self::fade(rung_at.elapsed().as_secs_f32(), self.config.duration)
+    }
+
+    /// Linearly fade out over the duration.
+    fn fade(elapsed: f32, duration: f32) -> f32 {
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        (1.0 - elapsed / duration).clamp(0.0, 1.0)
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let intensity = self.intensity();
+        if intensity <= 0.0 {
+            self.rung_at = None;
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        let colour = (
+            self.config.color.0,
+            self.config.color.1,
+            self.config.color.2,
+            self.config.color.3 * intensity,
+        );
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        for y in 0..height {
+            for x in 0..width {
+                let is_edge = x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+                if self.config.style == Style::Border && !is_edge {
+                    continue;
+                }
+                self.tattoy
+                    .surface
+                    .add_text(x, y, " ".to_owned(), Some(colour), None);
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fading_out() {
+        assert!((VisualBell::fade(0.0, 0.5) - 1.0).abs() < f32::EPSILON);
+        assert!((VisualBell::fade(0.25, 0.5) - 0.5).abs() < f32::EPSILON);
+        assert!(VisualBell::fade(1.0, 0.5).abs() < f32::EPSILON);
+        assert!(VisualBell::fade(0.0, 0.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,416 @@
+//! Falling snow or rain. Snow settles on the top edges of the text in the terminal, so that lines
+//! of text look like they're being snowed on.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// The kinds of weather.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    /// Slowly drifting snow that settles on text.
+    #[default]
+    Snow,
+    /// Fast falling rain.
+    Rain,
+}
+
+/// User-configurable settings for the weather.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the weather.
+    pub enabled: bool,
+    /// Snow or rain.
+    pub kind: Kind,
+    /// The chance of a new particle appearing in each column on every frame.
+    pub density: f32,
+    /// The horizontal speed of the wind, in columns per frame. Negative values blow to the left.
+    pub wind: f32,
+    /// The transparency of the weather.
+    pub opacity: f32,
+    /// Override the global frame rate for the weather.
+    pub frame_rate: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kind: Kind::Snow,
+            density: 0.02,
+            wind: 0.1,
+            opacity: 0.8,
+            frame_rate: None,
+        }
+    }
+}
+
+/// The compositing layer of the weather. Just above the terminal's text.
+const LAYER: i16 = 5;
+
+/// The colour of snow.
+const SNOW_COLOUR: crate::surface::Colour = (0.9, 0.92, 1.0, 1.0);
+
+/// The colour of rain.
+const RAIN_COLOUR: crate::surface::Colour = (0.45, 0.55, 0.9, 1.0);
+
+/// The range of falling speeds of snow, in pixels per frame.
+const SNOW_SPEED: core::ops::Range<f32> = 0.2..0.6;
+
+/// The range of falling speeds of rain, in pixels per frame.
+const RAIN_SPEED: core::ops::Range<f32> = 1.5..2.5;
+
+/// How much snow randomly sways from side to side, in columns per frame.
+const SNOW_SWAY: f32 = 0.15;
+
+/// Snow is lighter than rain so it's only blown about by a fraction of the wind.
+const SNOW_WIND_FACTOR: f32 = 0.5;
+
+/// A single snowflake or raindrop.
+#[derive(Debug, Clone, PartialEq)]
+struct Particle {
+    /// The horizontal position, in columns.
+    x: f32,
+    /// The vertical position, in pixels. There are 2 pixels per terminal row.
+    y: f32,
+    /// The falling speed, in pixels per frame.
+    speed: f32,
+}
+
+/// The state of all the weather particles, independent of any rendering.
+#[derive(Debug, Default)]
+struct Sky {
+    /// Snow or rain.
+    kind: Kind,
+    /// The particles that are still falling.
+    particles: Vec<Particle>,
+    /// Snow that has settled on top of text, as pixel coordinates.
+    settled: std::collections::HashSet<(usize, usize)>,
+    /// Which cells of the terminal contain text, indexed by row then column.
+    text: Vec<Vec<bool>>,
+    /// The width of the sky in columns.
+    width: usize,
+    /// The height of the sky in pixels.
+    height: usize,
+}
+
+impl Sky {
+    /// Record which cells of the terminal contain text. Any settled snow that is no longer resting
+    /// on text melts away.
+    fn set_text(&mut self, text: Vec<Vec<bool>>) {
+        self.text = text;
+        let settled = core::mem::take(&mut self.settled);
+        self.settled = settled
+            .into_iter()
+            .filter(|(x, y)| self.is_resting_place(*x, *y))
+            .collect();
+    }
+
+    /// Whether the pixel is part of a cell that contains text.
+    fn is_text(&self, x: usize, y: usize) -> bool {
+        self.text
+            .get(y.div_euclid(2))
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Whether snow can settle on the pixel, namely if the pixel is directly above text.
+    fn is_resting_place(&self, x: usize, y: usize) -> bool {
+        !self.is_text(x, y) && self.is_text(x, y + 1)
+    }
+
+    /// Add new particles along the top of the sky.
+    fn spawn(&mut self, density: f32) {
+        let mut rng = rand::thread_rng();
+        for column in 0..self.width {
+            if rng.gen_range(0.0..1.0) >= density {
+                continue;
+            }
+            let speed = match self.kind {
+                Kind::Snow => rng.gen_range(SNOW_SPEED),
+                Kind::Rain => rng.gen_range(RAIN_SPEED),
+            };
+            #[expect(
+                clippy::as_conversions,
+                clippy::cast_precision_loss,
+                reason = "Terminals are never wide enough to lose precision"
+            )]
+            let x = column as f32 + rng.gen_range(0.0..1.0);
+            self.particles.push(Particle { x, y: 0.0, speed });
+        }
+    }
+
+    /// Move all the particles by one frame.
+    fn tick(&mut self, wind: f32) {
+        let mut rng = rand::thread_rng();
+        let particles = core::mem::take(&mut self.particles);
+        for mut particle in particles {
+            let previous_y = particle.y;
+            particle.y += particle.speed;
+            particle.x += match self.kind {
+                Kind::Snow => {
+                    wind.mul_add(SNOW_WIND_FACTOR, rng.gen_range(-SNOW_SWAY..=SNOW_SWAY))
+                }
+                Kind::Rain => wind,
+            };
+
+            if self.is_landed(previous_y, &particle) {
+                continue;
+            }
+            self.particles.push(particle);
+        }
+    }
+
+    /// Check whether the particle hit something whilst falling from its previous position. Snow
+    /// that lands on text settles there.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss,
+        reason = "Positions are always within the bounds of the terminal"
+    )]
+    fn is_landed(&mut self, previous_y: f32, particle: &Particle) -> bool {
+        if particle.x < 0.0 || particle.x >= self.width as f32 {
+            return true;
+        }
+        if particle.y >= self.height as f32 {
+            return true;
+        }
+
+        let x = particle.x as usize;
+        let start = previous_y.max(0.0) as usize;
+        let end = particle.y as usize;
+        for y in start..=end {
+            if !self.is_resting_place(x, y) {
+                continue;
+            }
+            if self.kind == Kind::Snow {
+                self.settled.insert((x, y));
+            }
+            return true;
+        }
+
+        false
+    }
+
+    /// All the pixels that should be drawn. Particles that are falling behind text are hidden.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Positions are always within the bounds of the terminal"
+    )]
+    fn pixels(&self) -> Vec<(usize, usize)> {
+        self.particles
+            .iter()
+            .map(|particle| (particle.x as usize, particle.y as usize))
+            .chain(self.settled.iter().copied())
+            .filter(|(x, y)| !self.is_text(*x, *y))
+            .collect()
+    }
+}
+
+/// `Weather`
+pub(crate) struct Weather {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The particles
+    sky: Sky,
+    /// The weather's config
+    config: Config,
+}
+
+impl Weather {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.load().weather.clone();
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "weather".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+        let sky = Sky {
+            kind: config.kind,
+            ..Default::default()
+        };
+
+        Self {
+            tattoy,
+            sky,
+            config,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut weather = Self::new(output, state).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = weather.tattoy.sleep_until_next_frame_tick() => {
+                    weather.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_disabled = weather
+                        .tattoy
+                        .is_disabled_by(
+                            &message,
+                            &crate::config::diff::Section::Weather,
+                            |config| config.weather.enabled,
+                        )
+                        .await?;
+                    if is_disabled {
+                        break;
+                    }
+                    weather.handle_protocol_message(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    fn handle_protocol_message(&mut self, message: crate::run::Protocol) -> Result<()> {
+        let is_screen_changed =
+            super::tattoyer::Tattoyer::is_screen_output_changed(&message);
+
+        if let crate::run::Protocol::Config { config, .. } = &message {
+            self.config = config.weather.clone();
+            self.tattoy.opacity = self.config.opacity;
+            if self.sky.kind != self.config.kind {
+                self.sky = Sky {
+                    kind: self.config.kind,
+                    ..Default::default()
+                };
+            }
+        }
+
+        self.tattoy.handle_common_protocol_messages(message)?;
+        if is_screen_changed {
+            self.update_text();
+        }
+
+        Ok(())
+    }
+
+    /// Figure out which cells of the terminal contain text, so that snow can settle on them.
+    fn update_text(&mut self) {
+        let text = self
+            .tattoy
+            .screen
+            .surface
+            .screen_cells()
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|cell| !cell.str().trim().is_empty())
+                    .collect()
+            })
+            .collect();
+        self.sky.set_text(text);
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.sky.width = self.tattoy.width.into();
+        self.sky.height = usize::from(self.tattoy.height) * 2;
+        self.sky.spawn(self.config.density);
+        self.sky.tick(self.config.wind);
+
+        let colour = match self.sky.kind {
+            Kind::Snow => SNOW_COLOUR,
+            Kind::Rain => RAIN_COLOUR,
+        };
+
+        self.tattoy.initialise_surface();
+        for (x, y) in self.sky.pixels() {
+            self.tattoy.surface.add_pixel(x, y, colour)?;
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sky(kind: Kind) -> Sky {
+        let mut sky = Sky {
+            kind,
+            width: 3,
+            height: 6,
+            ..Default::default()
+        };
+        sky.set_text(vec![
+            vec![false, false, false],
+            vec![false, true, false],
+            vec![false, false, false],
+        ]);
+        sky.particles.push(Particle {
+            x: 1.5,
+            y: 0.0,
+            speed: 0.5,
+        });
+        sky
+    }
+
+    #[test]
+    fn snow_settles_on_text() {
+        let mut sky = sky(Kind::Snow);
+        for _ in 0..10 {
+            sky.tick(0.0);
+        }
+        assert!(sky.particles.is_empty());
+        assert_eq!(sky.settled, std::collections::HashSet::from([(1, 1)]));
+        assert_eq!(sky.pixels(), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn rain_does_not_settle() {
+        let mut sky = sky(Kind::Rain);
+        for _ in 0..10 {
+            sky.tick(0.0);
+        }
+        assert!(sky.particles.is_empty());
+        assert!(sky.settled.is_empty());
+    }
+
+    #[test]
+    fn settled_snow_melts_when_text_disappears() {
+        let mut sky = sky(Kind::Snow);
+        for _ in 0..10 {
+            sky.tick(0.0);
+        }
+        sky.set_text(vec![vec![false; 3]; 3]);
+        assert!(sky.settled.is_empty());
+    }
+
+    #[test]
+    fn wind_blows_particles_away() {
+        let mut sky = sky(Kind::Rain);
+        sky.tick(5.0);
+        assert!(sky.particles.is_empty());
+    }
+}
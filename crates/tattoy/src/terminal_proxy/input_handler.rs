@@ -5,6 +5,11 @@ use color_eyre::eyre::{ContextCompat as _, Result};
 impl crate::terminal_proxy::proxy::Proxy {
     /// Handle input from the end user.
     pub async fn handle_input(&self, input: &crate::raw_input::ParsedInput) -> Result<()> {
+        if self.wake_from_screensaver().await? {
+            tracing::trace!("Not forwarding input that woke the terminal from the screensaver");
+            return Ok(());
+        }
+
         if self.handle_tattoy_input_event(&input.event).await? {
             tracing::trace!(
                 "Not forwarding input because Tattoy received a known input event: {:?}",
@@ -16,13 +21,42 @@ impl crate::terminal_proxy::proxy::Proxy {
         self.forward_input_to_pty(input).await
     }
 
+    /// Any input whilst the screensaver is running wakes the terminal. Returns whether the
+    /// terminal was woken, in which case the input should go no further.
+    async fn wake_from_screensaver(&self) -> Result<bool> {
+        let mut is_screensaver_active = self.state.is_screensaver_active.write().await;
+        if !*is_screensaver_active {
+            return Ok(false);
+        }
+
+        *is_screensaver_active = false;
+        drop(is_screensaver_active);
+        self.tattoy_protocol
+            .send(crate::run::Protocol::Screensaver(false))?;
+        Ok(true)
+    }
+
     /// Forward raw input bytes to the underlying PTY.
     async fn forward_input_to_pty(&self, input: &crate::raw_input::ParsedInput) -> Result<()> {
         tracing::trace!(
             "Terminal proxy received input bytes: {}",
             String::from_utf8_lossy(&input.bytes)
         );
-        for chunk in input.bytes.chunks(128) {
+
+        let reserved_rows = self.state.reserved_rows.get();
+        let is_mouse = matches!(input.event, termwiz::input::InputEvent::Mouse(_));
+        let bytes = if is_mouse && reserved_rows.total() > 0 {
+            let pty_height = self.state.get_pty_size().await.height;
+            let Some(bytes) = offset_mouse_row(&input.bytes, reserved_rows, pty_height) else {
+                tracing::trace!("Not forwarding mouse event on a reserved row");
+                return Ok(());
+            };
+            bytes
+        } else {
+            input.bytes.clone()
+        };
+
+        for chunk in bytes.chunks(128) {
             let mut buffer: crate::raw_input::BytesFromSTDIN = [0; 128];
             for (i, chunk_byte) in chunk.iter().enumerate() {
                 let buffer_byte = buffer.get_mut(i).context("Couldn't get byte from buffer")?;
@@ -58,7 +92,9 @@ impl crate::terminal_proxy::proxy::Proxy {
             termwiz::input::InputEvent::Paste(_) | termwiz::input::InputEvent::Wake => false,
         };
 
-        Ok(is_input_event || self.state.get_is_scrolling().await)
+        Ok(is_input_event
+            || self.state.is_overlay_capturing_input().await
+            || self.state.get_is_scrolling().await)
     }
 
     /// Handle a key event that we have a keybinding for.
@@ -75,13 +111,18 @@ impl crate::terminal_proxy::proxy::Proxy {
 
         match trigger {
             crate::config::input::KeybindingAction::ToggleTattoy => {
-                let existing = *self.state.is_rendering_enabled.read().await;
+                let existing = self.state.is_rendering_enabled.get();
                 tracing::debug!("Toggling Tattoy renderer to: {}", !existing);
-                *self.state.is_rendering_enabled.write().await = !existing;
+                self.state.is_rendering_enabled.store(!existing);
+                crate::runtime_state::save(&self.state).await;
                 self.tattoy_protocol.send(crate::run::Protocol::Repaint)?;
                 Ok(true)
             }
             crate::config::input::KeybindingAction::ToggleScrolling => {
+                if self.state.multiplexers.get().inner.is_some() {
+                    tracing::debug!("Not scrolling, the multiplexer inside Tattoy scrolls instead");
+                    return Ok(false);
+                }
                 if self.state.get_is_scrolling().await {
                     self.shadow_terminal.scroll_cancel()?;
                 } else {
@@ -124,6 +165,52 @@ impl crate::terminal_proxy::proxy::Proxy {
                     ))?;
                 Ok(true)
             }
+            crate::config::input::KeybindingAction::ToggleShaderBrowser => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleShaderBrowser,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ProfileNext => {
+                crate::config::main::Config::cycle_profile(&self.state).await;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleSettings => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleSettings,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::TogglePaletteEditor => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::TogglePaletteEditor,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleSystemStats => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleSystemStats,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::PomodoroStartPause => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::PomodoroStartPause,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::PomodoroReset => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::PomodoroReset,
+                    ))?;
+                Ok(true)
+            }
             crate::config::input::KeybindingAction::ToggleMinimap => {
                 self.tattoy_protocol
                     .send(crate::run::Protocol::KeybindEvent(
@@ -131,17 +218,97 @@ impl crate::terminal_proxy::proxy::Proxy {
                     ))?;
                 Ok(true)
             }
+            crate::config::input::KeybindingAction::BookmarkAdd => {
+                self.shadow_terminal
+                    .bookmark(shadow_terminal::Bookmark::Add)?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::BookmarkPrevious => {
+                self.shadow_terminal
+                    .bookmark(shadow_terminal::Bookmark::Previous)?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::BookmarkNext => {
+                self.shadow_terminal
+                    .bookmark(shadow_terminal::Bookmark::Next)?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleBookmarks => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleBookmarks,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::Search => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::Search,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleMagnifier => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleMagnifier,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::TogglePerformanceHud => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::TogglePerformanceHud,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleLogViewer => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleLogViewer,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::DismissNotification => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::DismissNotification,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::DismissAllNotifications => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::DismissAllNotifications,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::NotificationAction => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::NotificationAction,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::RespawnCommand => {
+                self.respawn_command(None)?;
+                Ok(true)
+            }
         }
     }
 
     /// Because Tattoy is a wrapper around a headless, in-memory terminal, it can't rely on the
     /// user's actual terminal (Kitty, Alacritty, iTerm, etc) to do scrolling. So Tattoy forwards
     /// scrolling events to the shadow terminal and renders its own scrollbars etc.
+    ///
+    /// Multiplexers like tmux have their own scrollback and mouse handling, so when one is
+    /// running inside Tattoy, all mouse events are left for it.
     async fn handle_mouse_scrolling_input(
         &self,
         event: &termwiz::input::MouseEvent,
     ) -> Result<bool> {
-        if self.state.get_is_alternate_screen().await {
+        if self.state.get_is_alternate_screen().await
+            || self.state.multiplexers.get().inner.is_some()
+        {
             return Ok(false);
         }
 
@@ -159,3 +326,103 @@ impl crate::terminal_proxy::proxy::Proxy {
         Ok(true)
     }
 }
+
+/// Move a mouse event up by the number of rows reserved at the top of the user's terminal, so that
+/// its row is where it is on the PTY's screen. Events on reserved rows have no PTY row at all, so
+/// they give `None`. Bytes that aren't a known mouse encoding are passed through untouched.
+fn offset_mouse_row(
+    bytes: &[u8],
+    reserved_rows: crate::shared_state::ReservedRows,
+    pty_height: u16,
+) -> Option<Vec<u8>> {
+    let top = usize::from(reserved_rows.top);
+    let is_in_pty = |row: usize| row > top && row <= top + usize::from(pty_height);
+
+    // The SGR encoding: `ESC [ < button ; column ; row M`, with a final `m` for releases.
+    if let Some((button, column, row, terminator)) = parse_sgr_mouse(bytes) {
+        if !is_in_pty(row) {
+            return None;
+        }
+        let mut offset = format!("\x1b[<{button};{column};{}", row - top).into_bytes();
+        offset.push(terminator);
+        return Some(offset);
+    }
+
+    // The original X10 encoding: `ESC [ M button column row`, where each value is offset by 32.
+    if let [b'\x1b', b'[', b'M', button, column, row] = *bytes {
+        let pty_row = usize::from(row.saturating_sub(32));
+        if !is_in_pty(pty_row) {
+            return None;
+        }
+        let offset_row = u8::try_from(usize::from(row) - top).ok()?;
+        return Some(vec![b'\x1b', b'[', b'M', button, column, offset_row]);
+    }
+
+    Some(bytes.to_vec())
+}
+
+/// Parse an SGR encoded mouse event into its button, column, row and final byte.
+fn parse_sgr_mouse(bytes: &[u8]) -> Option<(&str, &str, usize, u8)> {
+    let report = bytes.strip_prefix(b"\x1b[<")?;
+    let (terminator, parameters) = report.split_last()?;
+    if *terminator != b'M' && *terminator != b'm' {
+        return None;
+    }
+    let mut parameters = core::str::from_utf8(parameters).ok()?.split(';');
+    let button = parameters.next()?;
+    let column = parameters.next()?;
+    let row = parameters.next()?.parse().ok()?;
+    if parameters.next().is_some() {
+        return None;
+    }
+    Some((button, column, row, *terminator))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STATUS_BAR_AT_TOP: crate::shared_state::ReservedRows =
+        crate::shared_state::ReservedRows { top: 1, bottom: 0 };
+
+    #[test]
+    fn offsetting_sgr_mouse_rows() {
+        assert_eq!(
+            offset_mouse_row(b"\x1b[<0;5;3M", STATUS_BAR_AT_TOP, 9),
+            Some(b"\x1b[<0;5;2M".to_vec())
+        );
+        assert_eq!(
+            offset_mouse_row(b"\x1b[<0;5;3m", STATUS_BAR_AT_TOP, 9),
+            Some(b"\x1b[<0;5;2m".to_vec())
+        );
+        assert_eq!(
+            offset_mouse_row(b"\x1b[<0;5;1M", STATUS_BAR_AT_TOP, 9),
+            None
+        );
+    }
+
+    #[test]
+    fn offsetting_x10_mouse_rows() {
+        assert_eq!(
+            offset_mouse_row(&[0x1b, b'[', b'M', 32, 37, 35], STATUS_BAR_AT_TOP, 9),
+            Some(vec![0x1b, b'[', b'M', 32, 37, 34])
+        );
+        assert_eq!(
+            offset_mouse_row(&[0x1b, b'[', b'M', 32, 37, 33], STATUS_BAR_AT_TOP, 9),
+            None
+        );
+    }
+
+    #[test]
+    fn dropping_mouse_events_on_bottom_reserved_rows() {
+        let status_bar_at_bottom = crate::shared_state::ReservedRows { top: 0, bottom: 1 };
+        assert_eq!(
+            offset_mouse_row(b"\x1b[<0;5;9M", status_bar_at_bottom, 9),
+            Some(b"\x1b[<0;5;9M".to_vec())
+        );
+        assert_eq!(
+            offset_mouse_row(b"\x1b[<0;5;10M", status_bar_at_bottom, 9),
+            None
+        );
+    }
+}
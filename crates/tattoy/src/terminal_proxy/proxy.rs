@@ -49,10 +49,13 @@ impl Proxy {
         config: shadow_terminal::shadow_terminal::Config,
     ) -> Result<()> {
         let shadow_terminal = shadow_terminal::active_terminal::ActiveTerminal::start(config);
+        let mut shadow_events = shadow_terminal.subscribe();
 
         let mut tattoy_protocol_rx = tattoy_protocol.subscribe();
         let mut proxy =
             Self::new(state, shadow_terminal, surfaces_tx, tattoy_protocol.clone()).await?;
+        // Rows may have been reserved before we subscribed to the protocol.
+        proxy.resize_to_pty_size().await?;
         #[expect(
             clippy::integer_division_remainder_used,
             reason = "This is caused by the `tokio::select!`"
@@ -67,11 +70,18 @@ impl Proxy {
                     if let Err(error) = result {
                         tracing::error!("{error:?}");
                     }
+                    // Don't lose the final output of short-lived commands, see `crate::batch`.
+                    while let Ok(output) = proxy.shadow_terminal.surface_output_rx.try_recv() {
+                        proxy.handle_output(output).await?;
+                    }
                     break;
                 }
                 Some(output) = proxy.shadow_terminal.surface_output_rx.recv() => {
                     proxy.handle_output(output).await?;
                 }
+                Ok(event) = shadow_events.recv() => {
+                    proxy.handle_event(event).await;
+                }
             }
         }
 
@@ -81,6 +91,51 @@ impl Proxy {
     /// Handle output from the Shadow Terminal.
     async fn handle_output(&self, mut output: shadow_terminal::output::Output) -> Result<()> {
         tracing::trace!("Received output from Shadow Terminal: {output:?}");
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Surface outputs are handled below"
+        )]
+        let maybe_event = match &output {
+            shadow_terminal::output::Output::Bell => Some(crate::run::Protocol::Bell),
+            shadow_terminal::output::Output::WorkingDirectory(path) => {
+                *self.state.working_directory.write().await = Some(path.clone());
+                Some(crate::run::Protocol::WorkingDirectory(path.clone()))
+            }
+            shadow_terminal::output::Output::CommandStarted => {
+                Some(crate::run::Protocol::CommandStarted)
+            }
+            shadow_terminal::output::Output::CommandFinished(exit_code) => {
+                Some(crate::run::Protocol::CommandFinished(*exit_code))
+            }
+            shadow_terminal::output::Output::CursorColour(colour) => {
+                *self.state.cursor_colour.write().await = *colour;
+                Some(crate::run::Protocol::CursorColour(*colour))
+            }
+            shadow_terminal::output::Output::Bookmarks(bookmarks) => {
+                Some(crate::run::Protocol::Bookmarks(bookmarks.clone()))
+            }
+            shadow_terminal::output::Output::Notification(notification) => {
+                Some(crate::tattoys::notifications::message::Message::make(
+                    &notification.title,
+                    crate::tattoys::notifications::message::Level::Info,
+                    notification.body.clone(),
+                    None,
+                ))
+            }
+            // The main loop already ends when the user's shell exits.
+            shadow_terminal::output::Output::Exited(code) => {
+                tracing::debug!("User's shell exited with status: {code}");
+                return Ok(());
+            }
+            _ => None,
+        };
+        if let Some(event) = maybe_event {
+            if let Err(err) = self.tattoy_protocol.send(event) {
+                tracing::error!("Couldn't notify protocol channel about shell event: {err:?}");
+            }
+            return Ok(());
+        }
+
         self.palette.convert_cells_to_true_colour(&mut output);
 
         match output.clone() {
@@ -93,15 +148,7 @@ impl Proxy {
                     *shadow_tty_scrollback = scrollback;
                 }
                 shadow_terminal::output::CompleteSurface::Screen(screen) => {
-                    let mut shadow_tty_screen = self.state.shadow_tty_screen.write().await;
-                    *shadow_tty_screen = screen.surface;
-                    drop(shadow_tty_screen);
-
-                    let is_alternate_screen =
-                        matches!(screen.mode, shadow_terminal::output::ScreenMode::Alternate);
-                    self.state
-                        .set_is_alternate_screen(is_alternate_screen)
-                        .await;
+                    self.state.shadow_tty_screen.store(screen.surface);
                 }
                 _ => (),
             },
@@ -117,6 +164,17 @@ impl Proxy {
         Ok(())
     }
 
+    /// Handle semantic events from the Shadow Terminal. Everything else is still sent as surface
+    /// output.
+    async fn handle_event(&self, event: shadow_terminal::events::Event) {
+        tracing::trace!("Received event from Shadow Terminal: {event:?}");
+        if let shadow_terminal::events::Event::AlternateScreen(is_alternate_screen) = event {
+            self.state
+                .set_is_alternate_screen(is_alternate_screen)
+                .await;
+        }
+    }
+
     /// Reconstruct full surfaces from diffs.
     async fn reconstruct_surface_from_diff(
         &self,
@@ -128,13 +186,6 @@ impl Proxy {
                 self.reconstruct_scrollback_diff(scrollback_diff).await?;
             }
             shadow_terminal::output::SurfaceDiff::Screen(screen_diff) => {
-                let is_alternate_screen = matches!(
-                    screen_diff.mode,
-                    shadow_terminal::output::ScreenMode::Alternate
-                );
-                self.state
-                    .set_is_alternate_screen(is_alternate_screen)
-                    .await;
                 self.reconstruct_screen_diff(screen_diff).await;
             }
             _ => (),
@@ -184,34 +235,89 @@ impl Proxy {
 
     /// Reconstruct the alternate screen surface from a diff of changes.
     async fn reconstruct_screen_diff(&self, diff: shadow_terminal::output::ScreenDiff) {
-        let mut shadow_tty_screen = self.state.shadow_tty_screen.write().await;
-        let size = self.state.get_tty_size().await;
-
-        if shadow_tty_screen.dimensions() != diff.size {
-            shadow_tty_screen.resize(size.width.into(), size.height.into());
-        }
-        shadow_tty_screen.add_changes(diff.changes);
+        let size = self.state.get_pty_size().await;
+        self.state.shadow_tty_screen.modify(|shadow_tty_screen| {
+            if shadow_tty_screen.dimensions() != diff.size {
+                shadow_tty_screen.resize(size.width.into(), size.height.into());
+            }
+            shadow_tty_screen.add_changes(diff.changes);
+        });
     }
 
     /// Handle protocol messages from Tattoy.
-    async fn handle_tattoy_protocol_message(&self, message: crate::run::Protocol) -> Result<()> {
+    async fn handle_tattoy_protocol_message(
+        &mut self,
+        message: crate::run::Protocol,
+    ) -> Result<()> {
         #[expect(clippy::wildcard_enum_match_arm, reason = "It's our internal protocol")]
         match message {
             crate::run::Protocol::End => {
                 self.shadow_terminal.kill()?;
             }
-            crate::run::Protocol::Resize { width, height } => {
-                self.shadow_terminal.resize(width, height)?;
+            crate::run::Protocol::Resize { .. } | crate::run::Protocol::ReservedRows(_) => {
+                self.resize_to_pty_size().await?;
             }
             crate::run::Protocol::Input(input) => {
                 self.handle_input(&input).await?;
             }
+            crate::run::Protocol::ScrollTo(position) => {
+                self.shadow_terminal.scroll_to(position)?;
+            }
+            crate::run::Protocol::RespawnCommand(command) => {
+                self.respawn_command(command)?;
+            }
+            crate::run::Protocol::Palette(palette) => {
+                self.palette = palette;
+                self.recolour_existing_output().await;
+            }
             _ => (),
         }
 
         Ok(())
     }
 
+    /// Kill the command running in the PTY and start another one in its place. The shadow
+    /// terminal, and so the scrollback, is kept.
+    pub(crate) fn respawn_command(&self, maybe_command: Option<String>) -> Result<()> {
+        let command = maybe_command.unwrap_or_else(|| self.state.config.load().command.clone());
+        let parts: Vec<std::ffi::OsString> = command
+            .split_whitespace()
+            .map(std::convert::Into::into)
+            .collect();
+        if parts.is_empty() {
+            color_eyre::eyre::bail!("Can't respawn an empty command");
+        }
+
+        tracing::info!("Respawning PTY command: {command}");
+        self.shadow_terminal.respawn(parts)?;
+        Ok(())
+    }
+
+    /// Resize the shadow terminal to the user's terminal, minus any reserved rows.
+    async fn resize_to_pty_size(&self) -> Result<()> {
+        let size = self.state.get_pty_size().await;
+        self.shadow_terminal.resize(size.width, size.height)?;
+        Ok(())
+    }
+
+    /// Apply the current palette to the PTY content that we've already received, so that palette
+    /// changes are visible straight away.
+    async fn recolour_existing_output(&self) {
+        self.state
+            .shadow_tty_screen
+            .modify(|screen| self.palette.recolour_surface(screen));
+        self.palette
+            .recolour_surface(&mut self.state.shadow_tty_scrollback.write().await.surface);
+
+        let result = self
+            .surfaces_tx
+            .send(crate::run::FrameUpdate::PTYSurface)
+            .await;
+        if let Err(err) = result {
+            tracing::error!("Couldn't notify frame update channel about recoloured PTY: {err:?}");
+        }
+    }
+
     // TODO:
     // It is a bit odd that we send 3 notifications about new PTY output. I'm sure the
     // receiver of the `Protocol::Output` message could do everything that the receiver of the
@@ -0,0 +1,171 @@
+//! Support for terminals that can't display true colour. Tattoy works in true colour internally,
+//! so on these terminals the effects that depend on subtle colours are disabled, and every frame
+//! is quantised to the standard 256 colour palette before it's painted.
+//!
+//! Plenty of terminals that support true colour don't advertise it, and wrongly degrading a
+//! terminal makes Tattoy look broken. So terminals are only treated this way when their terminfo
+//! entry positively says that they have fewer than 256 colours.
+
+/// Config that is merged over the user's config when their terminal doesn't support true colour.
+/// It disables colour grading and the tattoys that are mostly made of pixels, because they look
+/// like noise when quantised.
+pub(crate) const REDUCED_COLOUR_CONFIG: &str = r#"
+    color = { saturation = 0.0, brightness = 0.0, hue = 0.0, vision_filter = "none" }
+    shader = { enabled = false }
+    bg_command = { enabled = false }
+    minimap = { enabled = false }
+    random_walker = { enabled = false }
+    starfield = { enabled = false }
+    audio_visualiser = { enabled = false }
+    crt = { enabled = false }
+    cursor_smear = { enabled = false }
+    typing_sparks = { enabled = false }
+    image_preview = { enabled = false }
+"#;
+
+/// The intensities of each channel in the 6x6x6 colour cube of the 256 colour palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The palette index of the first colour in the colour cube.
+const CUBE_START: u8 = 16;
+
+/// The palette index of the first colour in the greyscale ramp.
+const GREYSCALE_START: u8 = 232;
+
+/// The number of greys in the greyscale ramp.
+const GREYSCALE_COUNT: u8 = 24;
+
+/// Whether the user's terminal should be treated as supporting true colour. It only isn't when
+/// there's a terminfo entry for the terminal and it reports fewer than 256 colours. This has to be
+/// checked before Tattoy overrides `COLORTERM` for the PTY.
+pub(crate) fn is_supported() -> bool {
+    match termwiz::caps::Capabilities::new_from_env() {
+        Ok(capabilities) => {
+            let is_reported_by_terminfo = capabilities.terminfo_db().is_some();
+            let has_few_colours = matches!(
+                capabilities.color_level(),
+                termwiz::caps::ColorLevel::MonoChrome | termwiz::caps::ColorLevel::Sixteen
+            );
+            !(is_reported_by_terminfo && has_few_colours)
+        }
+        Err(error) => {
+            tracing::warn!("Couldn't detect terminal capabilities, assuming true colour: {error}");
+            true
+        }
+    }
+}
+
+/// Tell the user why some of their tattoys aren't running.
+pub(crate) async fn notify(state: &crate::shared_state::SharedState) {
+    state
+        .send_notification(
+            "True colour isn't supported",
+            crate::tattoys::notifications::message::Level::Warn,
+            Some(
+                "Your terminal's terminfo entry says that it has fewer than 256 colours, so \
+                 colour grading and pixel-based tattoys are disabled and colours are \
+                 approximated. If your terminal does support true colour, start Tattoy with \
+                 `COLORTERM=truecolor`."
+                    .to_owned(),
+            ),
+            false,
+        )
+        .await;
+}
+
+/// The square of the distance between 2 colours.
+fn distance(left: (u8, u8, u8), right: (u8, u8, u8)) -> u32 {
+    let channel = |left: u8, right: u8| u32::from(left.abs_diff(right)).pow(2);
+    channel(left.0, right.0) + channel(left.1, right.1) + channel(left.2, right.2)
+}
+
+/// The colour cube level that is closest to the given channel intensity, as its index and its
+/// intensity.
+fn nearest_cube_level(intensity: u8) -> (u8, u8) {
+    (0u8..)
+        .zip(CUBE_LEVELS)
+        .min_by_key(|(_, level)| level.abs_diff(intensity))
+        .unwrap_or((0, 0))
+}
+
+/// The intensity of the given grey in the greyscale ramp.
+const fn grey_level(index: u8) -> u8 {
+    8 + index * 10
+}
+
+/// The palette index, from the standard 256 colour palette, that is closest to the given true
+/// colour. Both the colour cube and the greyscale ramp are considered. The first 16 colours are
+/// ignored because they're defined by the user's theme.
+pub(crate) fn quantise(colour: termwiz::color::SrgbaTuple) -> u8 {
+    let (red, green, blue, _) = colour.to_srgb_u8();
+    let rgb = (red, green, blue);
+
+    let (red_index, red_level) = nearest_cube_level(red);
+    let (green_index, green_level) = nearest_cube_level(green);
+    let (blue_index, blue_level) = nearest_cube_level(blue);
+    let cube_colour = (red_level, green_level, blue_level);
+    let cube_index = CUBE_START + red_index * 36 + green_index * 6 + blue_index;
+
+    let grey = (0..GREYSCALE_COUNT)
+        .min_by_key(|index| {
+            let level = grey_level(*index);
+            distance(rgb, (level, level, level))
+        })
+        .unwrap_or(0);
+    let grey_colour = (grey_level(grey), grey_level(grey), grey_level(grey));
+
+    if distance(rgb, grey_colour) < distance(rgb, cube_colour) {
+        GREYSCALE_START + grey
+    } else {
+        cube_index
+    }
+}
+
+/// Replace a true colour with its closest colour from the 256 colour palette.
+fn quantise_attribute(
+    colour_attribute: termwiz::color::ColorAttribute,
+) -> termwiz::color::ColorAttribute {
+    match crate::blender::Blender::extract_colour(colour_attribute) {
+        Some(colour) => termwiz::color::ColorAttribute::PaletteIndex(quantise(colour)),
+        None => colour_attribute,
+    }
+}
+
+/// Replace every true colour in the frame with its closest colour from the 256 colour palette.
+pub(crate) fn quantise_frame(frame: &mut termwiz::surface::Surface) {
+    for line in &mut frame.screen_cells().iter_mut() {
+        for cell in line.iter_mut() {
+            let foreground = quantise_attribute(cell.attrs().foreground());
+            let background = quantise_attribute(cell.attrs().background());
+            cell.attrs_mut().set_foreground(foreground);
+            cell.attrs_mut().set_background(background);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rgb(red: u8, green: u8, blue: u8) -> termwiz::color::SrgbaTuple {
+        termwiz::color::RgbColor::new_8bpc(red, green, blue).to_tuple_rgba()
+    }
+
+    #[test]
+    fn quantising_colours() {
+        assert_eq!(quantise(rgb(0, 0, 0)), 16);
+        assert_eq!(quantise(rgb(255, 255, 255)), 231);
+        assert_eq!(quantise(rgb(255, 0, 0)), 196);
+        assert_eq!(quantise(rgb(0, 135, 255)), 33);
+        assert_eq!(quantise(rgb(128, 128, 128)), 244);
+        assert_eq!(quantise(rgb(250, 2, 3)), 196);
+    }
+
+    #[test]
+    fn reduced_colour_config() {
+        let config: crate::config::main::Config = toml::from_str(REDUCED_COLOUR_CONFIG).unwrap();
+        assert!(!config.shader.enabled);
+        assert!(!config.minimap.enabled);
+        assert!(config.color.saturation.abs() < f32::EPSILON);
+    }
+}
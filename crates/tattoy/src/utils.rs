@@ -1,5 +1,7 @@
 //! Generally useful shared code.
 
+use unicode_segmentation::UnicodeSegmentation as _;
+
 /// The official Tattoy blue;
 pub const TATTOY_BLUE: &str = "#0034a1";
 
@@ -20,9 +22,84 @@ pub const CLEAR_SCREEN: &str = "\x1b[2J";
 /// OSC code to reset the terminal screen.
 pub const RESET_SCREEN: &str = "\x1bc";
 
+/// OSC code to show the cursor.
+pub const SHOW_CURSOR: &str = "\x1b[?25h";
+
 /// Smoothly transition between 2 values.
 #[must_use]
+#[inline]
 pub fn smoothstep(edge0: f32, edge1: f32, mut x: f32) -> f32 {
     x = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
     x * x * 2.0f32.mul_add(-x, 3.0)
 }
+
+/// The number of terminal columns that some text takes up. Wide characters, like emojis and CJK
+/// text, take up 2 columns, whilst combining characters don't take up any.
+#[must_use]
+#[inline]
+pub fn display_width(text: &str) -> usize {
+    termwiz::cell::unicode_column_width(text, None)
+}
+
+/// The start of some text that fits into the given number of terminal columns. Grapheme clusters,
+/// like emojis with modifiers, are never split.
+#[must_use]
+#[inline]
+pub fn truncate_to_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used += termwiz::cell::grapheme_column_width(grapheme, None);
+        if used > width {
+            return text.get(..index).unwrap_or_default();
+        }
+    }
+    text
+}
+
+/// Split some text into chunks that each fit into the given number of terminal columns, without
+/// splitting any grapheme clusters.
+#[must_use]
+#[inline]
+pub fn split_to_width(text: &str, width: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut chunk = truncate_to_width(rest, width);
+        if chunk.is_empty() {
+            // A single grapheme that's wider than the width still has to go somewhere.
+            chunk = rest.graphemes(true).next().unwrap_or(rest);
+        }
+        chunks.push(chunk);
+        rest = rest.get(chunk.len()..).unwrap_or_default();
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn measuring_text() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👍🏽"), 2);
+    }
+
+    #[test]
+    fn truncating_text() {
+        assert_eq!(truncate_to_width("abcdef", 3), "abc");
+        assert_eq!(truncate_to_width("日本語", 5), "日本");
+        assert_eq!(truncate_to_width("e\u{301}e\u{301}", 1), "e\u{301}");
+        assert_eq!(truncate_to_width("👍🏽ok", 1), "");
+        assert_eq!(truncate_to_width("short", 10), "short");
+    }
+
+    #[test]
+    fn splitting_text() {
+        assert_eq!(split_to_width("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(split_to_width("日本語", 3), vec!["日", "本", "語"]);
+        assert_eq!(split_to_width("日本", 1), vec!["日", "本"]);
+    }
+}
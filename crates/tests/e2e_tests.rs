@@ -110,6 +110,7 @@ mod e2e {
             PWD='{pwd:?}' \
             PS1='{prompt}' \
             TERM=xterm-256color \
+            COLORTERM=truecolor \
             {rust_log_filters} \
             "
         );
@@ -122,10 +123,12 @@ mod e2e {
             --disable-indicator \
             --command 'bash --norc --noprofile' \
             --config-dir {} \
+            --data-dir {}/data \
             --log-path ./tests.log \
             ",
             minimum_env,
             tattoy_binary_path(),
+            config_dir,
             config_dir
         );
 
@@ -447,6 +450,36 @@ mod e2e {
             .unwrap();
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mouse_rows_under_a_status_bar() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let conf_dir = temp_dir.into_path();
+        let conf_path = conf_dir.join("tattoy.toml");
+        let mut conf_file = std::fs::File::create(conf_path).unwrap();
+        let config = "
+            [status_bar]
+            enabled = true
+            position = \"top\"
+        ";
+        conf_file.write_all(config.as_bytes()).unwrap();
+
+        let mut tattoy = start_tattoy(Some(conf_dir.to_string_lossy().into())).await;
+
+        // Mouse events are only forwarded to applications on the alternate screen.
+        tattoy
+            .send_command("printf '\\033[?1049h'; cat -v")
+            .unwrap();
+        let on_status_bar = format!("{ESCAPE}[<0;7;1M");
+        let on_third_row = format!("{ESCAPE}[<0;5;3M");
+        tattoy.send_input(Input::Event(on_status_bar)).unwrap();
+        tattoy.send_input(Input::Event(on_third_row)).unwrap();
+        tattoy.send_command("end").unwrap();
+
+        // The PTY starts below the status bar, so the third row of the terminal is its second.
+        tattoy.wait_for_string("^[[<0;5;2Mend", None).await.unwrap();
+        assert!(!tattoy.screen_as_string().unwrap().contains(";7;"));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn auto_text_contrast() {
         fn contrast(cell: &termwiz::cell::Cell) -> f32 {
@@ -477,4 +510,96 @@ mod e2e {
         assert_eq!(light.str(), "l");
         assert!(contrast(&light) > 1.9);
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn multi_codepoint_emojis() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let heart = "❤\u{fe0f}";
+        let thumbs_up = "👍\u{1f3fd}";
+
+        // A solid blue background below the PTY, so that every emoji is composited onto it.
+        let temp_dir = tempfile::tempdir().unwrap();
+        let conf_dir = temp_dir.into_path();
+        let conf_path = conf_dir.join("tattoy.toml");
+        let mut conf_file = std::fs::File::create(conf_path).unwrap();
+        let config = r#"
+            [bg_command]
+            enabled = true
+            command = ["bash", "-c", "printf '\\e[48;2;0;0;255m%450s' ''; sleep 1000"]
+            layer = -1
+            opacity = 1.0
+            expect_exit = false
+        "#;
+        conf_file.write_all(config.as_bytes()).unwrap();
+
+        let mut tattoy = start_tattoy(Some(conf_dir.to_string_lossy().into())).await;
+        tattoy
+            .send_command(concat!(
+                "printf '",
+                "\\U1F468\\u200D\\U1F469\\u200D\\U1F467 ",
+                "\\u2764\\uFE0F ",
+                "\\U1F44D\\U1F3FD ",
+                "emo''jis\\n'"
+            ))
+            .unwrap();
+        tattoy.wait_for_string("emojis", None).await.unwrap();
+
+        let is_on_blue = |cell: &termwiz::cell::Cell| {
+            SteppableTerminal::extract_colour(cell.attrs().background())
+                .is_some_and(|colour| colour.2 > 0.9 && colour.0 < 0.1 && colour.1 < 0.1)
+        };
+        for emoji in [family, heart, thumbs_up] {
+            let (x, y) = tattoy.get_coords_of_cell_by_content(emoji).unwrap();
+            let cell = tattoy.get_cell_at(x, y).unwrap().unwrap();
+            assert_eq!(cell.str(), emoji);
+            assert!(is_on_blue(&cell), "'{emoji}' wasn't composited: {cell:?}");
+        }
+    }
+
+    // Latency depends too much on the machine running the tests to assert on, so it's only
+    // recorded here. See the `doctor` for measuring it on a user's machine.
+    #[expect(
+        clippy::print_stdout,
+        reason = "The latencies are useful to see when running the tests"
+    )]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn keypress_latency() {
+        async fn wait_for_key(tattoy: &mut SteppableTerminal, is_shown: bool) {
+            let timeout = tokio::time::Duration::from_secs(5);
+            let started = std::time::Instant::now();
+            loop {
+                tattoy.render_all_output().await.unwrap();
+                let screen = tattoy.screen_as_string().unwrap();
+                if screen.contains('@') == is_shown {
+                    return;
+                }
+                if started.elapsed() > timeout {
+                    let state = if is_shown { "appear" } else { "be deleted" };
+                    panic!("Typed key didn't {state} within {timeout:?}, screen:\n{screen}");
+                }
+                tokio::time::sleep(tokio::time::Duration::from_micros(100)).await;
+            }
+        }
+
+        let mut tattoy = start_tattoy(None).await;
+        let mut latencies = Vec::new();
+        for _ in 0..20 {
+            let started = std::time::Instant::now();
+            tattoy
+                .send_input(Input::Characters("@".to_owned()))
+                .unwrap();
+            wait_for_key(&mut tattoy, true).await;
+            latencies.push(started.elapsed());
+
+            tattoy
+                .send_input(Input::Characters("\x7f".to_owned()))
+                .unwrap();
+            wait_for_key(&mut tattoy, false).await;
+        }
+
+        latencies.sort_unstable();
+        let median = *latencies.get(latencies.len() / 2).unwrap();
+        let max = *latencies.last().unwrap();
+        println!("Keypress latency: median {median:?}, max {max:?}");
+    }
 }
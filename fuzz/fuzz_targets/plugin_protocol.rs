@@ -0,0 +1,19 @@
+//! Parse arbitrary plugin output in the same way as Tattoy's plugin listener. Plugins can send
+//! anything at all, so parsing must never panic. Anything that parses must also survive being
+//! serialised and parsed again.
+
+#![no_main]
+
+libfuzzer_sys::fuzz_target!(|bytes: &[u8]| {
+    let messages = serde_json::Deserializer::from_slice(bytes)
+        .into_iter::<tattoy_protocol::PluginOutputMessages>();
+    for message in messages.flatten() {
+        let json = serde_json::to_vec(&message).unwrap();
+        serde_json::from_slice::<tattoy_protocol::PluginOutputMessages>(&json).unwrap();
+    }
+
+    if let Ok(message) = serde_json::from_slice::<tattoy_protocol::PluginInputMessages>(bytes) {
+        let json = serde_json::to_vec(&message).unwrap();
+        serde_json::from_slice::<tattoy_protocol::PluginInputMessages>(&json).unwrap();
+    }
+});
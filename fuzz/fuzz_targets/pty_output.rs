@@ -0,0 +1,20 @@
+//! Feed arbitrary bytes to the shadow terminal, as if they'd come from the PTY. Applications can
+//! output anything at all, so scanning for special codes and parsing the output must never panic.
+
+#![no_main]
+
+libfuzzer_sys::fuzz_target!(|bytes: &[u8]| {
+    let _codes = shadow_terminal::shadow_terminal::SpecialCodes::scan(bytes);
+
+    let (output_tx, _output_rx) = tokio::sync::mpsc::channel(1);
+    let config = shadow_terminal::shadow_terminal::Config {
+        width: 20,
+        height: 10,
+        scrollback_size: 10,
+        ..Default::default()
+    };
+    let mut shadow_terminal =
+        shadow_terminal::shadow_terminal::ShadowTerminal::new(config, output_tx);
+    shadow_terminal.terminal.advance_bytes(bytes);
+    let _cursor = shadow_terminal.terminal.cursor_pos();
+});